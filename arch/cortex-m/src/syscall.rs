@@ -408,6 +408,39 @@ impl<A: CortexMVariant> kernel::syscall::UserspaceKernelBoundary for SysCall<A>
         ));
     }
 
+    unsafe fn fault_context(
+        &self,
+        accessible_memory_start: *const u8,
+        app_brk: *const u8,
+        state: &CortexMStoredState,
+    ) -> kernel::syscall::FaultContext {
+        // The PC is in the hardware-stacked frame, if the stack pointer is
+        // valid.
+        let invalid_stack_pointer = state.psp < accessible_memory_start as usize
+            || state.psp.saturating_add(SVC_FRAME_SIZE) > app_brk as usize;
+        let pc = if invalid_stack_pointer {
+            None
+        } else {
+            Some(ptr::read((state.psp as *const usize).offset(6)))
+        };
+
+        // The hard fault handler saved the fault status registers.
+        let cfsr = SCB_REGISTERS[1];
+        let mmfar = SCB_REGISTERS[3];
+        let bfar = SCB_REGISTERS[4];
+        let mmfarvalid = (cfsr & 0x80) == 0x80;
+        let bfarvalid = ((cfsr >> 8) & 0x80) == 0x80;
+        let fault_address = if mmfarvalid {
+            Some(mmfar as usize)
+        } else if bfarvalid {
+            Some(bfar as usize)
+        } else {
+            None
+        };
+
+        kernel::syscall::FaultContext { pc, fault_address }
+    }
+
     fn store_context(
         &self,
         state: &CortexMStoredState,
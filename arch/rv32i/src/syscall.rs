@@ -573,6 +573,35 @@ impl kernel::syscall::UserspaceKernelBoundary for SysCall {
         ));
     }
 
+    unsafe fn fault_context(
+        &self,
+        _accessible_memory_start: *const u8,
+        _app_brk: *const u8,
+        state: &Riscv32iStoredState,
+    ) -> kernel::syscall::FaultContext {
+        // For address misaligned, access and page faults, mtval holds the
+        // faulting address.
+        let fault_address = match mcause::Trap::from(state.mcause as usize) {
+            mcause::Trap::Exception(
+                mcause::Exception::InstructionMisaligned
+                | mcause::Exception::InstructionFault
+                | mcause::Exception::LoadMisaligned
+                | mcause::Exception::LoadFault
+                | mcause::Exception::StoreMisaligned
+                | mcause::Exception::StoreFault
+                | mcause::Exception::InstructionPageFault
+                | mcause::Exception::LoadPageFault
+                | mcause::Exception::StorePageFault,
+            ) => Some(state.mtval as usize),
+            _ => None,
+        };
+
+        kernel::syscall::FaultContext {
+            pc: Some(state.pc as usize),
+            fault_address,
+        }
+    }
+
     fn store_context(
         &self,
         state: &Riscv32iStoredState,
@@ -0,0 +1,165 @@
+//! Component for the app loader driver, which installs new apps at runtime.
+//!
+//! The board must load its processes with a `DynamicProcessLoader`, which is
+//! passed to this component. `F` is the flash controller of the app flash.
+//! New apps only run once the board's `ProcessCheckerMachine` approves their
+//! credentials. Only the apps whose `ShortID` is in `allowed_ids` can install
+//! apps.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let checker_machine = static_init!(
+//!     kernel::process::ProcessCheckerMachine,
+//!     kernel::process::ProcessCheckerMachine::new(board_kernel)
+//! );
+//! checker_machine.set_policy(checker, checker);
+//!
+//! let process_loader = static_init!(
+//!     kernel::process::DynamicProcessLoader<nrf52840::chip::NRF52<Nrf52840DefaultPeripherals>>,
+//!     kernel::process::DynamicProcessLoader::new(
+//!         board_kernel,
+//!         chip,
+//!         core::slice::from_raw_parts(
+//!             &_sapps as *const u8,
+//!             &_eapps as *const u8 as usize - &_sapps as *const u8 as usize,
+//!         ),
+//!         &mut APP_MEMORY,
+//!         &mut PROCESSES,
+//!         &FAULT_RESPONSE,
+//!         checker_machine,
+//!         &process_management_capability,
+//!     )
+//! );
+//! process_loader.load_processes().unwrap_or_else(|err| {
+//!     debug!("Error loading processes!");
+//!     debug!("{:?}", err);
+//! });
+//!
+//! static APP_LOADER_IDS: [ShortID; 1] =
+//!     [ShortID::Fixed(unsafe { NonZeroU32::new_unchecked(0x1b2c3d4e) })];
+//! let app_loader = components::app_loader::AppLoaderComponent::new(
+//!     board_kernel,
+//!     capsules::app_loader::DRIVER_NUM,
+//!     &base_peripherals.nvmc,
+//!     process_loader,
+//!     &APP_LOADER_IDS,
+//! )
+//! .finalize(components::app_loader_component_helper!(
+//!     nrf52840::nvmc::Nvmc,
+//!     512
+//! ));
+//! ```
+
+use capsules::app_loader::AppLoader;
+use capsules::nonvolatile_to_pages::NonvolatileToPages;
+use core::mem::MaybeUninit;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil;
+use kernel::hil::nonvolatile_storage::NonvolatileStorage;
+use kernel::process::{DynamicProcessLoading, ShortID};
+use kernel::static_init_half;
+
+#[macro_export]
+macro_rules! app_loader_component_helper {
+    ($F:ty, $buffer_size: literal) => {{
+        static mut BUFFER: [u8; $buffer_size] = [0; $buffer_size];
+        use capsules::app_loader::AppLoader;
+        use capsules::nonvolatile_to_pages::NonvolatileToPages;
+        use core::mem::MaybeUninit;
+        use kernel::hil;
+        static mut page_buffer: MaybeUninit<<$F as hil::flash::Flash>::Page> =
+            MaybeUninit::uninit();
+        static mut nv_to_page: MaybeUninit<NonvolatileToPages<'static, $F>> = MaybeUninit::uninit();
+        static mut app_loader: MaybeUninit<AppLoader<'static>> = MaybeUninit::uninit();
+        (
+            &mut BUFFER,
+            &mut page_buffer,
+            &mut nv_to_page,
+            &mut app_loader,
+        )
+    };};
+}
+
+pub struct AppLoaderComponent<
+    F: 'static + hil::flash::Flash + hil::flash::HasClient<'static, NonvolatileToPages<'static, F>>,
+> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    storage: &'static F,
+    loader: &'static dyn DynamicProcessLoading,
+    allowed_ids: &'static [ShortID],
+}
+
+impl<
+        F: 'static
+            + hil::flash::Flash
+            + hil::flash::HasClient<'static, NonvolatileToPages<'static, F>>,
+    > AppLoaderComponent<F>
+{
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        storage: &'static F,
+        loader: &'static dyn DynamicProcessLoading,
+        allowed_ids: &'static [ShortID],
+    ) -> AppLoaderComponent<F> {
+        AppLoaderComponent {
+            board_kernel,
+            driver_num,
+            storage,
+            loader,
+            allowed_ids,
+        }
+    }
+}
+
+impl<
+        F: 'static
+            + hil::flash::Flash
+            + hil::flash::HasClient<'static, NonvolatileToPages<'static, F>>,
+    > Component for AppLoaderComponent<F>
+{
+    type StaticInput = (
+        &'static mut [u8],
+        &'static mut MaybeUninit<<F as hil::flash::Flash>::Page>,
+        &'static mut MaybeUninit<NonvolatileToPages<'static, F>>,
+        &'static mut MaybeUninit<AppLoader<'static>>,
+    );
+    type Output = &'static AppLoader<'static>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let flash_pagebuffer = static_init_half!(
+            static_buffer.1,
+            <F as hil::flash::Flash>::Page,
+            <F as hil::flash::Flash>::Page::default()
+        );
+
+        let nv_to_page = static_init_half!(
+            static_buffer.2,
+            NonvolatileToPages<'static, F>,
+            NonvolatileToPages::new(self.storage, flash_pagebuffer)
+        );
+        self.storage.set_client(nv_to_page);
+
+        let app_loader = static_init_half!(
+            static_buffer.3,
+            AppLoader<'static>,
+            AppLoader::new(
+                nv_to_page,
+                self.loader,
+                self.board_kernel.create_grant(self.driver_num, &grant_cap),
+                static_buffer.0,
+                self.allowed_ids,
+            )
+        );
+
+        nv_to_page.set_client(app_loader);
+
+        app_loader
+    }
+}
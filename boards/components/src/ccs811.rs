@@ -23,7 +23,7 @@ use capsules::ccs811::Ccs811;
 use capsules::virtual_i2c::{I2CDevice, MuxI2C};
 use core::mem::MaybeUninit;
 use kernel::component::Component;
-use kernel::dynamic_deferred_call::DynamicDeferredCall;
+use kernel::deferred_call::DeferredCallClient;
 use kernel::{static_init, static_init_half};
 
 // Setup static space for the objects.
@@ -40,19 +40,13 @@ macro_rules! ccs811_component_helper {
 pub struct Ccs811Component {
     i2c_mux: &'static MuxI2C<'static>,
     i2c_address: u8,
-    deferred_caller: &'static DynamicDeferredCall,
 }
 
 impl Ccs811Component {
-    pub fn new(
-        i2c: &'static MuxI2C<'static>,
-        i2c_address: u8,
-        deferred_caller: &'static DynamicDeferredCall,
-    ) -> Self {
+    pub fn new(i2c: &'static MuxI2C<'static>, i2c_address: u8) -> Self {
         Ccs811Component {
             i2c_mux: i2c,
             i2c_address,
-            deferred_caller,
         }
     }
 }
@@ -68,13 +62,11 @@ impl Component for Ccs811Component {
         let ccs811 = static_init_half!(
             static_buffer,
             Ccs811<'static>,
-            Ccs811::new(ccs811_i2c, &mut I2C_BUF, self.deferred_caller)
+            Ccs811::new(ccs811_i2c, &mut I2C_BUF)
         );
 
         ccs811_i2c.set_client(ccs811);
-        ccs811.initialize_callback_handle(
-            self.deferred_caller.register(ccs811).unwrap(), // Unwrap fail = no deferred call slot available for CCS811
-        );
+        ccs811.register();
         ccs811.startup();
         ccs811
     }
@@ -10,9 +10,7 @@
 //! Usage
 //! -----
 //! ```rust
-//! let uart_mux = UartMuxComponent::new(&sam4l::usart::USART3,
-//!                                      115200,
-//!                                      deferred_caller).finalize(());
+//! let uart_mux = UartMuxComponent::new(&sam4l::usart::USART3, 115200).finalize(());
 //! let console = ConsoleComponent::new(board_kernel, uart_mux)
 //!    .finalize(console_component_helper!());
 //! ```
@@ -24,7 +22,7 @@ use capsules::virtual_uart::{MuxUart, UartDevice};
 use kernel::capabilities;
 use kernel::component::Component;
 use kernel::create_capability;
-use kernel::dynamic_deferred_call::DynamicDeferredCall;
+use kernel::deferred_call::DeferredCallClient;
 use kernel::hil;
 use kernel::hil::uart;
 use kernel::static_init;
@@ -35,20 +33,11 @@ use capsules::console::DEFAULT_BUF_SIZE;
 pub struct UartMuxComponent {
     uart: &'static dyn uart::Uart<'static>,
     baud_rate: u32,
-    deferred_caller: &'static DynamicDeferredCall,
 }
 
 impl UartMuxComponent {
-    pub fn new(
-        uart: &'static dyn uart::Uart<'static>,
-        baud_rate: u32,
-        deferred_caller: &'static DynamicDeferredCall,
-    ) -> UartMuxComponent {
-        UartMuxComponent {
-            uart,
-            baud_rate,
-            deferred_caller,
-        }
+    pub fn new(uart: &'static dyn uart::Uart<'static>, baud_rate: u32) -> UartMuxComponent {
+        UartMuxComponent { uart, baud_rate }
     }
 }
 
@@ -63,12 +52,9 @@ impl Component for UartMuxComponent {
                 self.uart,
                 &mut capsules::virtual_uart::RX_BUF,
                 self.baud_rate,
-                self.deferred_caller,
             )
         );
-        uart_mux.initialize_callback_handle(
-            self.deferred_caller.register(uart_mux).unwrap(), // Unwrap fail = no deferred call slot available for uart mux
-        );
+        uart_mux.register();
 
         uart_mux.initialize();
         hil::uart::Transmit::set_transmit_client(self.uart, uart_mux);
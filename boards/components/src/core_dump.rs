@@ -0,0 +1,93 @@
+//! Component for taking core dumps of faulted processes and exporting them.
+//!
+//! The returned `CoreDump` is the fault policy to load processes with; it
+//! takes a dump of every faulted process and then applies `fault_policy`.
+//! The size of the buffer limits how much of the memory of a process is
+//! dumped.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let core_dump = components::core_dump::CoreDumpComponent::new(
+//!     &FAULT_RESPONSE,
+//!     capsules::core_dump::DumpOutput::Uart(core_dump_uart),
+//! )
+//! .finalize(components::core_dump_component_helper!(16384));
+//!
+//! kernel::process::load_processes(
+//!     board_kernel,
+//!     chip,
+//!     app_flash,
+//!     &mut APP_MEMORY,
+//!     &mut PROCESSES,
+//!     core_dump,
+//!     &process_management_capability,
+//! );
+//! ```
+
+use capsules::core_dump::{CoreDumpWriter, DumpOutput};
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::process::{CoreDump, ProcessFaultPolicy};
+use kernel::static_init_half;
+
+// Setup static space for the objects. `$N` is the size of the dump buffer in
+// bytes.
+#[macro_export]
+macro_rules! core_dump_component_helper {
+    ($N:expr $(,)?) => {{
+        use capsules::core_dump::CoreDumpWriter;
+        use core::mem::MaybeUninit;
+        use kernel::process::CoreDump;
+        static mut DUMP: [u8; $N] = [0; $N];
+        static mut BUF1: MaybeUninit<CoreDump<'static>> = MaybeUninit::uninit();
+        static mut BUF2: MaybeUninit<CoreDumpWriter<'static>> = MaybeUninit::uninit();
+        (&mut DUMP, &mut BUF1, &mut BUF2)
+    };};
+}
+
+pub struct CoreDumpComponent {
+    fault_policy: &'static dyn ProcessFaultPolicy,
+    output: DumpOutput<'static>,
+}
+
+impl CoreDumpComponent {
+    pub fn new(
+        fault_policy: &'static dyn ProcessFaultPolicy,
+        output: DumpOutput<'static>,
+    ) -> CoreDumpComponent {
+        CoreDumpComponent {
+            fault_policy,
+            output,
+        }
+    }
+}
+
+impl Component for CoreDumpComponent {
+    type StaticInput = (
+        &'static mut [u8],
+        &'static mut MaybeUninit<CoreDump<'static>>,
+        &'static mut MaybeUninit<CoreDumpWriter<'static>>,
+    );
+    type Output = &'static CoreDump<'static>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let core_dump = static_init_half!(
+            static_buffer.1,
+            CoreDump<'static>,
+            CoreDump::new(self.fault_policy, static_buffer.0)
+        );
+
+        let writer = static_init_half!(
+            static_buffer.2,
+            CoreDumpWriter<'static>,
+            CoreDumpWriter::new(core_dump, self.output, &mut capsules::core_dump::BUF)
+        );
+        core_dump.set_client(writer);
+        match self.output {
+            DumpOutput::Uart(uart) => uart.set_transmit_client(writer),
+            DumpOutput::Storage(storage, _) => storage.set_client(writer),
+        }
+        core_dump
+    }
+}
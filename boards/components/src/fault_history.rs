@@ -0,0 +1,134 @@
+//! Component for recording process faults in a persistent log.
+//!
+//! The fault history is stored in a circular `capsules::log::Log` on a
+//! storage volume. The returned `FaultHistory` is the fault policy to load
+//! processes with; it records every fault and then applies `fault_policy`.
+//! The records from before the last reset are read back when the kernel
+//! starts its main loop.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! storage_volume!(FAULT_HISTORY_VOLUME, 4);
+//!
+//! let fault_history = components::fault_history::FaultHistoryComponent::new(
+//!     &FAULT_HISTORY_VOLUME,
+//!     &base_peripherals.nvmc,
+//!     &FAULT_RESPONSE,
+//! )
+//! .finalize(components::fault_history_component_helper!(
+//!     nrf52840::nvmc::Nvmc,
+//!     8
+//! ));
+//!
+//! kernel::process::load_processes(
+//!     board_kernel,
+//!     chip,
+//!     app_flash,
+//!     &mut APP_MEMORY,
+//!     &mut PROCESSES,
+//!     fault_history,
+//!     &process_management_capability,
+//! );
+//!
+//! pconsole.set_fault_history(fault_history);
+//! ```
+
+use capsules::log::Log;
+use core::mem::MaybeUninit;
+use kernel::component::Component;
+use kernel::deferred_call::DeferredCallClient;
+use kernel::hil;
+use kernel::hil::log::{LogRead, LogWrite};
+use kernel::process::{FaultHistory, ProcessFaultPolicy, ProcessFaultRecord};
+use kernel::static_init_half;
+
+#[macro_export]
+macro_rules! fault_history_component_helper {
+    ($F:ty, $records: literal) => {{
+        use capsules::log::Log;
+        use core::mem::MaybeUninit;
+        use kernel::hil;
+        use kernel::process::{FaultHistory, ProcessFaultRecord, FAULT_RECORD_LEN};
+        static mut RECORDS: MaybeUninit<[ProcessFaultRecord; $records]> = MaybeUninit::uninit();
+        static mut BUFFER: [u8; FAULT_RECORD_LEN] = [0; FAULT_RECORD_LEN];
+        static mut PAGEBUFFER: MaybeUninit<<$F as hil::flash::Flash>::Page> = MaybeUninit::uninit();
+        static mut LOG: MaybeUninit<Log<'static, $F>> = MaybeUninit::uninit();
+        static mut FAULT_HISTORY: MaybeUninit<FaultHistory<'static, Log<'static, $F>>> =
+            MaybeUninit::uninit();
+        (
+            RECORDS.write([ProcessFaultRecord::default(); $records]) as &'static mut [_],
+            &mut BUFFER,
+            &mut PAGEBUFFER,
+            &mut LOG,
+            &mut FAULT_HISTORY,
+        )
+    };};
+}
+
+pub struct FaultHistoryComponent<
+    F: 'static + hil::flash::Flash + hil::flash::HasClient<'static, Log<'static, F>>,
+> {
+    volume: &'static [u8],
+    flash: &'static F,
+    fault_policy: &'static dyn ProcessFaultPolicy,
+}
+
+impl<F: 'static + hil::flash::Flash + hil::flash::HasClient<'static, Log<'static, F>>>
+    FaultHistoryComponent<F>
+{
+    pub fn new(
+        volume: &'static [u8],
+        flash: &'static F,
+        fault_policy: &'static dyn ProcessFaultPolicy,
+    ) -> FaultHistoryComponent<F> {
+        FaultHistoryComponent {
+            volume,
+            flash,
+            fault_policy,
+        }
+    }
+}
+
+impl<F: 'static + hil::flash::Flash + hil::flash::HasClient<'static, Log<'static, F>>> Component
+    for FaultHistoryComponent<F>
+{
+    type StaticInput = (
+        &'static mut [ProcessFaultRecord],
+        &'static mut [u8],
+        &'static mut MaybeUninit<<F as hil::flash::Flash>::Page>,
+        &'static mut MaybeUninit<Log<'static, F>>,
+        &'static mut MaybeUninit<FaultHistory<'static, Log<'static, F>>>,
+    );
+    type Output = &'static FaultHistory<'static, Log<'static, F>>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let pagebuffer = static_init_half!(
+            static_buffer.2,
+            <F as hil::flash::Flash>::Page,
+            <F as hil::flash::Flash>::Page::default()
+        );
+
+        let log = static_init_half!(
+            static_buffer.3,
+            Log<'static, F>,
+            Log::new(self.volume, self.flash, pagebuffer, true)
+        );
+        self.flash.set_client(log);
+        log.register();
+
+        let fault_history = static_init_half!(
+            static_buffer.4,
+            FaultHistory<'static, Log<'static, F>>,
+            FaultHistory::new(self.fault_policy, log, static_buffer.0, static_buffer.1)
+        );
+        log.set_read_client(fault_history);
+        log.set_append_client(fault_history);
+
+        // Faults recorded while the old records are read are appended to the
+        // log once reading finishes.
+        let _ = fault_history.load();
+
+        fault_history
+    }
+}
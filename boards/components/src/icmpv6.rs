@@ -0,0 +1,279 @@
+//! Component to initialize the ICMPv6 responder.
+//!
+//! This provides one Component, ICMP6Component. This component answers
+//! pings and runs 6LoWPAN Neighbor Discovery, which configures a global
+//! address from the prefix advertised by a router once
+//! `ICMP6Responder::start()` is called. It also reports received packets
+//! with unrecognized headers to their source.
+//!
+//! Like TCP, ICMPv6 uses its own MAC user, 6LoWPAN state and IPv6 sender and
+//! receiver, separate from the UDP stack.
+//!
+//! Usage
+//! -----
+//! ```rust
+//!    let icmp6_responder = ICMP6Component::new(
+//!        mux_mac,
+//!        DEFAULT_CTX_PREFIX_LEN,
+//!        DEFAULT_CTX_PREFIX,
+//!        DST_MAC_ADDR,
+//!        src_mac_from_serial_num,
+//!        local_ip_ifaces,
+//!        mux_alarm,
+//!    )
+//!    .finalize(components::icmp6_component_helper!(nrf52840::rtc::Rtc));
+//!    icmp6_responder.start();
+//! ```
+
+use capsules;
+use capsules::ieee802154::device::MacDevice;
+use capsules::net::icmpv6::icmpv6_responder::ICMP6Responder;
+use capsules::net::icmpv6::{ICMP6Header, ICMP6Type};
+use capsules::net::ieee802154::MacAddress;
+use capsules::net::ipv6::ip_utils::IPAddr;
+use capsules::net::ipv6::ipv6_recv::IP6Receiver;
+use capsules::net::ipv6::ipv6_send::{IP6SendStruct, IP6Sender};
+use capsules::net::ipv6::{IP6Packet, IPPayload, TransportHeader};
+use capsules::net::network_capabilities::{
+    AddrRange, IpVisibilityCapability, NetworkCapability, PortRange,
+};
+use capsules::net::sixlowpan::{sixlowpan_compression, sixlowpan_state};
+use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use core::mem::MaybeUninit;
+use kernel;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::radio;
+use kernel::hil::time::Alarm;
+use kernel::{static_init, static_init_half};
+
+// The ICMPv6 responder requires several packet buffers:
+//
+//   1. RADIO_BUF: buffer the IP6_Sender uses to pass frames to the radio after fragmentation
+//   2. SIXLOWPAN_RX_BUF: Buffer to hold full IP packets after they are decompressed by 6LoWPAN
+//   3. ICMP_DGRAM: The payload of the IP6_Packet, which holds full IP Packets before they are tx'd.
+//   4. ICMP_TX_BUF: Buffer the responder builds message payloads in before passing them to the
+//      IP6_Sender. Echo Requests with larger payloads are not answered.
+
+static mut RADIO_BUF: [u8; radio::MAX_BUF_SIZE] = [0x00; radio::MAX_BUF_SIZE];
+static mut SIXLOWPAN_RX_BUF: [u8; 1280] = [0x00; 1280];
+
+pub const MAX_PAYLOAD_LEN: usize = 200; //The max payload of a single ICMPv6 message
+static mut ICMP_DGRAM: [u8; MAX_PAYLOAD_LEN] = [0; MAX_PAYLOAD_LEN];
+static mut ICMP_TX_BUF: [u8; MAX_PAYLOAD_LEN] = [0; MAX_PAYLOAD_LEN];
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! icmp6_component_helper {
+    ($A:ty $(,)?) => {{
+        use capsules;
+        use capsules::net::icmpv6::icmpv6_responder::ICMP6Responder;
+        use capsules::net::ipv6::ipv6_send::IP6SendStruct;
+        use capsules::net::sixlowpan::{sixlowpan_compression, sixlowpan_state};
+        use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+        use core::mem::MaybeUninit;
+        static mut BUF0: MaybeUninit<VirtualMuxAlarm<'static, $A>> = MaybeUninit::uninit();
+        static mut BUF1: MaybeUninit<capsules::ieee802154::virtual_mac::MacUser<'static>> =
+            MaybeUninit::uninit();
+        static mut BUF2: MaybeUninit<
+            sixlowpan_state::Sixlowpan<
+                'static,
+                VirtualMuxAlarm<'static, $A>,
+                sixlowpan_compression::Context,
+            >,
+        > = MaybeUninit::uninit();
+        static mut BUF3: MaybeUninit<sixlowpan_state::RxState<'static>> = MaybeUninit::uninit();
+        static mut BUF4: MaybeUninit<IP6SendStruct<'static, VirtualMuxAlarm<'static, $A>>> =
+            MaybeUninit::uninit();
+        static mut BUF5: MaybeUninit<VirtualMuxAlarm<'static, $A>> = MaybeUninit::uninit();
+        static mut BUF6: MaybeUninit<
+            ICMP6Responder<
+                'static,
+                VirtualMuxAlarm<'static, $A>,
+                IP6SendStruct<'static, VirtualMuxAlarm<'static, $A>>,
+            >,
+        > = MaybeUninit::uninit();
+        (
+            &mut BUF0, &mut BUF1, &mut BUF2, &mut BUF3, &mut BUF4, &mut BUF5, &mut BUF6,
+        )
+    };};
+}
+
+pub struct ICMP6Component<A: Alarm<'static> + 'static> {
+    mux_mac: &'static capsules::ieee802154::virtual_mac::MuxMac<'static>,
+    ctx_pfix_len: u8,
+    ctx_pfix: [u8; 16],
+    dst_mac_addr: MacAddress,
+    src_mac_addr: MacAddress,
+    interface_list: &'static [IPAddr],
+    alarm_mux: &'static MuxAlarm<'static, A>,
+}
+
+impl<A: Alarm<'static> + 'static> ICMP6Component<A> {
+    pub fn new(
+        mux_mac: &'static capsules::ieee802154::virtual_mac::MuxMac<'static>,
+        ctx_pfix_len: u8,
+        ctx_pfix: [u8; 16],
+        dst_mac_addr: MacAddress,
+        src_mac_addr: MacAddress,
+        interface_list: &'static [IPAddr],
+        alarm_mux: &'static MuxAlarm<'static, A>,
+    ) -> Self {
+        Self {
+            mux_mac,
+            ctx_pfix_len,
+            ctx_pfix,
+            dst_mac_addr,
+            src_mac_addr,
+            interface_list,
+            alarm_mux,
+        }
+    }
+}
+
+impl<A: Alarm<'static> + 'static> Component for ICMP6Component<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<capsules::ieee802154::virtual_mac::MacUser<'static>>,
+        &'static mut MaybeUninit<
+            sixlowpan_state::Sixlowpan<
+                'static,
+                VirtualMuxAlarm<'static, A>,
+                sixlowpan_compression::Context,
+            >,
+        >,
+        &'static mut MaybeUninit<sixlowpan_state::RxState<'static>>,
+        &'static mut MaybeUninit<IP6SendStruct<'static, VirtualMuxAlarm<'static, A>>>,
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<
+            ICMP6Responder<
+                'static,
+                VirtualMuxAlarm<'static, A>,
+                IP6SendStruct<'static, VirtualMuxAlarm<'static, A>>,
+            >,
+        >,
+    );
+    type Output = &'static ICMP6Responder<
+        'static,
+        VirtualMuxAlarm<'static, A>,
+        IP6SendStruct<'static, VirtualMuxAlarm<'static, A>>,
+    >;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let ipsender_virtual_alarm = static_init_half!(
+            static_buffer.0,
+            VirtualMuxAlarm<'static, A>,
+            VirtualMuxAlarm::new(self.alarm_mux)
+        );
+        ipsender_virtual_alarm.setup();
+
+        let icmp_mac = static_init_half!(
+            static_buffer.1,
+            capsules::ieee802154::virtual_mac::MacUser<'static>,
+            capsules::ieee802154::virtual_mac::MacUser::new(self.mux_mac)
+        );
+        self.mux_mac.add_user(icmp_mac);
+        let create_cap = create_capability!(capabilities::NetworkCapabilityCreationCapability);
+        let ip_vis = static_init!(
+            IpVisibilityCapability,
+            IpVisibilityCapability::new(&create_cap)
+        );
+
+        let sixlowpan = static_init_half!(
+            static_buffer.2,
+            sixlowpan_state::Sixlowpan<
+                'static,
+                VirtualMuxAlarm<'static, A>,
+                sixlowpan_compression::Context,
+            >,
+            sixlowpan_state::Sixlowpan::new(
+                sixlowpan_compression::Context {
+                    prefix: self.ctx_pfix,
+                    prefix_len: self.ctx_pfix_len,
+                    id: 0,
+                    compress: false,
+                },
+                ipsender_virtual_alarm, // OK to reuse bc only used to get time, not set alarms
+            )
+        );
+
+        let sixlowpan_state = sixlowpan as &dyn sixlowpan_state::SixlowpanState;
+        let sixlowpan_tx = sixlowpan_state::TxState::new(sixlowpan_state);
+        let default_rx_state = static_init_half!(
+            static_buffer.3,
+            sixlowpan_state::RxState<'static>,
+            sixlowpan_state::RxState::new(&mut SIXLOWPAN_RX_BUF)
+        );
+        sixlowpan_state.add_rx_state(default_rx_state);
+        icmp_mac.set_receive_client(sixlowpan);
+
+        let tr_hdr = TransportHeader::ICMP(ICMP6Header::new(ICMP6Type::Type129));
+        let ip_pyld: IPPayload = IPPayload {
+            header: tr_hdr,
+            payload: &mut ICMP_DGRAM,
+        };
+        let ip6_dg = static_init!(IP6Packet<'static>, IP6Packet::new(ip_pyld));
+
+        // Until a router is found the packets are sent to the given MAC
+        // address, afterwards to the router.
+        let ip_send = static_init_half!(
+            static_buffer.4,
+            IP6SendStruct<'static, VirtualMuxAlarm<'static, A>>,
+            IP6SendStruct::new(
+                ip6_dg,
+                ipsender_virtual_alarm,
+                &mut RADIO_BUF,
+                sixlowpan_tx,
+                icmp_mac,
+                self.dst_mac_addr,
+                self.src_mac_addr,
+                ip_vis,
+            )
+        );
+        ipsender_virtual_alarm.set_alarm_client(ip_send);
+        icmp_mac.set_transmit_client(ip_send);
+
+        let ip_receive = static_init!(
+            capsules::net::ipv6::ipv6_recv::IP6RecvStruct<'static>,
+            capsules::net::ipv6::ipv6_recv::IP6RecvStruct::new()
+        );
+        sixlowpan_state.set_rx_client(ip_receive);
+
+        // The responder answers any node
+        let net_cap = static_init!(
+            NetworkCapability,
+            NetworkCapability::new(AddrRange::Any, PortRange::Any, PortRange::Any, &create_cap)
+        );
+
+        let nd_virtual_alarm = static_init_half!(
+            static_buffer.5,
+            VirtualMuxAlarm<'static, A>,
+            VirtualMuxAlarm::new(self.alarm_mux)
+        );
+        nd_virtual_alarm.setup();
+
+        let responder = static_init_half!(
+            static_buffer.6,
+            ICMP6Responder<
+                'static,
+                VirtualMuxAlarm<'static, A>,
+                IP6SendStruct<'static, VirtualMuxAlarm<'static, A>>,
+            >,
+            ICMP6Responder::new(
+                ip_send,
+                nd_virtual_alarm,
+                net_cap,
+                self.interface_list,
+                self.src_mac_addr,
+                &mut ICMP_TX_BUF,
+            )
+        );
+        nd_virtual_alarm.set_alarm_client(responder);
+        ip_send.set_client(responder);
+        ip_receive.set_client(responder);
+        ip_receive.set_error_client(responder);
+
+        responder
+    }
+}
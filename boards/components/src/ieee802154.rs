@@ -4,16 +4,23 @@
 //! userspace syscall interface to a full 802.15.4 stack with a
 //! always-on MAC implementation, as well as multiplexed access to that MAC implementation.
 //!
+//! The keys and neighbors used for link-layer security are kept in a
+//! `SecurityTable`. If the board passes a nonvolatile storage and the address
+//! of a region of `security_table::BUF_LEN` bytes in it, the table is saved
+//! there and survives reboots; the storage must not have another client.
+//! The table is returned for other users of link-layer security, such as
+//! Thread.
+//!
 //! Usage
 //! -----
 //! ```rust
-//! let (radio, mux_mac) = components::ieee802154::Ieee802154Component::new(
+//! let (radio, mux_mac, security_table) = components::ieee802154::Ieee802154Component::new(
 //!     board_kernel,
 //!     &nrf52::ieee802154_radio::RADIO,
 //!     &nrf52::aes::AESECB,
 //!     PAN_ID,
 //!     SRC_MAC,
-//!     deferred_caller,
+//!     None,
 //! )
 //! .finalize(components::ieee802154_component_helper!(
 //!     nrf52::ieee802154_radio::Radio,
@@ -24,10 +31,12 @@
 use capsules;
 use capsules::ieee802154::device::MacDevice;
 use capsules::ieee802154::mac::{AwakeMac, Mac};
+use capsules::ieee802154::security_table::{self, SecurityTable};
 use core::mem::MaybeUninit;
 use kernel::capabilities;
 use kernel::component::Component;
-use kernel::dynamic_deferred_call::DynamicDeferredCall;
+use kernel::deferred_call::DeferredCallClient;
+use kernel::hil::nonvolatile_storage::NonvolatileStorage;
 use kernel::hil::radio;
 use kernel::hil::symmetric_encryption::{self, AES128Ctr, AES128, AES128CBC, AES128CCM, AES128ECB};
 use kernel::{create_capability, static_init, static_init_half};
@@ -64,7 +73,7 @@ pub struct Ieee802154Component<
     aes_mux: &'static capsules::virtual_aes_ccm::MuxAES128CCM<'static, A>,
     pan_id: capsules::net::ieee802154::PanID,
     short_addr: u16,
-    deferred_caller: &'static DynamicDeferredCall,
+    storage: Option<(&'static dyn NonvolatileStorage<'static>, usize)>,
 }
 
 impl<
@@ -79,7 +88,7 @@ impl<
         aes_mux: &'static capsules::virtual_aes_ccm::MuxAES128CCM<'static, A>,
         pan_id: capsules::net::ieee802154::PanID,
         short_addr: u16,
-        deferred_caller: &'static DynamicDeferredCall,
+        storage: Option<(&'static dyn NonvolatileStorage<'static>, usize)>,
     ) -> Self {
         Self {
             board_kernel,
@@ -88,7 +97,7 @@ impl<
             aes_mux,
             pan_id,
             short_addr,
-            deferred_caller,
+            storage,
         }
     }
 }
@@ -103,6 +112,9 @@ static mut RADIO_RX_BUF: [u8; radio::MAX_BUF_SIZE] = [0x00; radio::MAX_BUF_SIZE]
 const CRYPT_SIZE: usize = 3 * symmetric_encryption::AES128_BLOCK_SIZE + radio::MAX_BUF_SIZE;
 static mut CRYPT_BUF: [u8; CRYPT_SIZE] = [0x00; CRYPT_SIZE];
 
+// The security table is saved to and read from storage through this buffer
+static mut SECURITY_TABLE_BUF: [u8; security_table::BUF_LEN] = [0x00; security_table::BUF_LEN];
+
 impl<
         R: 'static + kernel::hil::radio::Radio,
         A: 'static + AES128<'static> + AES128Ctr + AES128CBC + AES128ECB,
@@ -122,6 +134,7 @@ impl<
     type Output = (
         &'static capsules::ieee802154::RadioDriver<'static>,
         &'static capsules::ieee802154::virtual_mac::MuxMac<'static>,
+        &'static SecurityTable<'static>,
     );
 
     unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
@@ -171,26 +184,33 @@ impl<
         );
         mux_mac.add_user(userspace_mac);
 
+        let security_table = static_init!(
+            SecurityTable<'static>,
+            SecurityTable::new(self.storage, &mut SECURITY_TABLE_BUF)
+        );
+        if let Some((storage, _)) = self.storage {
+            storage.set_client(security_table);
+        }
+        mac_device.set_key_procedure(security_table);
+        mac_device.set_device_procedure(security_table);
+        let _ = security_table.load();
+
         let radio_driver = static_init!(
             capsules::ieee802154::RadioDriver<'static>,
             capsules::ieee802154::RadioDriver::new(
                 userspace_mac,
+                security_table,
                 self.board_kernel.create_grant(self.driver_num, &grant_cap),
                 &mut RADIO_BUF,
-                self.deferred_caller,
             )
         );
 
-        mac_device.set_key_procedure(radio_driver);
-        mac_device.set_device_procedure(radio_driver);
         userspace_mac.set_transmit_client(radio_driver);
         userspace_mac.set_receive_client(radio_driver);
         userspace_mac.set_pan(self.pan_id);
         userspace_mac.set_address(self.short_addr);
-        radio_driver.initialize_callback_handle(
-            self.deferred_caller.register(radio_driver).unwrap(), // Unwrap fail = no deferred call slot available for ieee802154 driver
-        );
+        radio_driver.register();
 
-        (radio_driver, mux_mac)
+        (radio_driver, mux_mac, security_table)
     }
 }
@@ -21,6 +21,10 @@
 //!        ),
 //!    );
 //!
+//!    // Optionally encrypt values with a device key, using an AES128-CCM
+//!    // and RNG that aren't used by anything else.
+//!    kv_store.enable_encryption(aes_ccm, rng, &DEVICE_KEY).unwrap();
+//!
 //!    let kv_driver_data_buf = static_init!([u8; 32], [0; 32]);
 //!    let kv_driver_dest_buf = static_init!(capsules::tickv::TicKVKeyType, [0; 8]);
 //!
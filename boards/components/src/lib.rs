@@ -6,6 +6,7 @@ pub mod air_quality;
 pub mod alarm;
 pub mod analog_comparator;
 pub mod app_flash_driver;
+pub mod app_loader;
 pub mod bme280;
 pub mod bmp280;
 pub mod bus;
@@ -13,11 +14,13 @@ pub mod button;
 pub mod ccs811;
 pub mod cdc;
 pub mod console;
+pub mod core_dump;
 pub mod crc;
 pub mod ctap;
 pub mod debug_queue;
 pub mod debug_writer;
 pub mod digest;
+pub mod fault_history;
 pub mod flash;
 pub mod ft6x06;
 pub mod fxos8700;
@@ -27,6 +30,7 @@ pub mod hmac;
 pub mod hts221;
 pub mod humidity;
 pub mod i2c;
+pub mod icmpv6;
 pub mod ieee802154;
 pub mod isl29035;
 pub mod kv_system;
@@ -46,7 +50,9 @@ pub mod nrf51822;
 pub mod panic_button;
 pub mod process_console;
 pub mod process_printer;
+pub mod process_watchdog;
 pub mod rng;
+pub mod rpl;
 pub mod sched;
 pub mod screen;
 pub mod segger_rtt;
@@ -56,11 +62,15 @@ pub mod si7021;
 pub mod sound_pressure;
 pub mod spi;
 pub mod st77xx;
+pub mod syscall_trace;
+pub mod tcp_driver;
+pub mod tcp_mux;
 pub mod temperature;
 pub mod temperature_rp2040;
 pub mod temperature_stm;
 pub mod test;
 pub mod text_screen;
+pub mod thread;
 pub mod tickv;
 pub mod touch;
 pub mod udp_driver;
@@ -17,7 +17,6 @@
 //!         disp_pin,
 //!         extcomin_pin,
 //!         alarm_mux,
-//!         dynamic_deferred_caller,
 //!     )
 //!     .finalize(
 //!         components::lpm013m126_component_helper!(
@@ -37,7 +36,7 @@ use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
 use capsules::virtual_spi::VirtualSpiMasterDevice;
 use core::marker::PhantomData;
 use kernel::component::Component;
-use kernel::dynamic_deferred_call::DynamicDeferredCall;
+use kernel::deferred_call::DeferredCallClient;
 use kernel::hil::gpio;
 use kernel::hil::spi::{SpiMaster, SpiMasterDevice};
 use kernel::hil::time::Alarm;
@@ -136,7 +135,6 @@ where
     disp: &'static P,
     extcomin: &'static P,
     alarm_mux: &'static MuxAlarm<'static, A>,
-    deferred_caller: &'static DynamicDeferredCall,
 }
 
 impl<A, P, S> Lpm013m126Component<A, P, S>
@@ -150,14 +148,12 @@ where
         disp: &'static P,
         extcomin: &'static P,
         alarm_mux: &'static MuxAlarm<'static, A>,
-        deferred_caller: &'static DynamicDeferredCall,
     ) -> Self {
         Self {
             spi: PhantomData::default(),
             disp,
             extcomin,
             alarm_mux,
-            deferred_caller,
         }
     }
 }
@@ -194,13 +190,13 @@ where
                 self.extcomin,
                 self.disp,
                 lpm013m126_alarm,
-                self.deferred_caller,
                 buffer,
             )
             .unwrap(),
         );
         spi_device.set_client(lpm013m126);
         lpm013m126_alarm.set_alarm_client(lpm013m126);
+        lpm013m126.register();
         lpm013m126.setup().unwrap();
         lpm013m126
     }
@@ -0,0 +1,115 @@
+//! Component for the process watchdog, which monitors app heartbeats.
+//!
+//! The returned `ProcessWatchdog` wraps the board's hardware watchdog and
+//! should be used as the board's `KernelResources::WatchDog` so that
+//! `ExpiryAction::ResetBoard` can reset the board.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let process_watchdog = components::process_watchdog::ProcessWatchdogComponent::new(
+//!     board_kernel,
+//!     capsules::process_watchdog::DRIVER_NUM,
+//!     mux_alarm,
+//!     &peripherals.wdt,
+//!     capsules::process_watchdog::ExpiryAction::ResetBoard,
+//! )
+//! .finalize(components::process_watchdog_component_helper!(
+//!     msp432::timer::TimerA,
+//!     msp432::wdt::Wdt
+//! ));
+//! ```
+
+use core::mem::MaybeUninit;
+
+use capsules::process_watchdog::{ExpiryAction, ProcessWatchdog};
+use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::time::{self, Alarm};
+use kernel::platform::watchdog::WatchDog;
+use kernel::static_init_half;
+
+#[macro_export]
+macro_rules! process_watchdog_component_helper {
+    ($A:ty, $W:ty $(,)?) => {{
+        use capsules::process_watchdog::ProcessWatchdog;
+        use capsules::virtual_alarm::VirtualMuxAlarm;
+        use components::process_watchdog::Capability;
+        use core::mem::MaybeUninit;
+        static mut BUF1: MaybeUninit<VirtualMuxAlarm<'static, $A>> = MaybeUninit::uninit();
+        static mut BUF2: MaybeUninit<
+            ProcessWatchdog<'static, VirtualMuxAlarm<'static, $A>, $W, Capability>,
+        > = MaybeUninit::uninit();
+        (&mut BUF1, &mut BUF2)
+    };};
+}
+
+pub struct ProcessWatchdogComponent<A: 'static + time::Alarm<'static>, W: 'static + WatchDog> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    hardware_watchdog: &'static W,
+    action: ExpiryAction,
+}
+
+impl<A: 'static + time::Alarm<'static>, W: 'static + WatchDog> ProcessWatchdogComponent<A, W> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        hardware_watchdog: &'static W,
+        action: ExpiryAction,
+    ) -> ProcessWatchdogComponent<A, W> {
+        ProcessWatchdogComponent {
+            board_kernel,
+            driver_num,
+            alarm_mux,
+            hardware_watchdog,
+            action,
+        }
+    }
+}
+
+pub struct Capability;
+unsafe impl capabilities::ProcessManagementCapability for Capability {}
+
+impl<A: 'static + time::Alarm<'static>, W: 'static + WatchDog> Component
+    for ProcessWatchdogComponent<A, W>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<
+            ProcessWatchdog<'static, VirtualMuxAlarm<'static, A>, W, Capability>,
+        >,
+    );
+    type Output = &'static ProcessWatchdog<'static, VirtualMuxAlarm<'static, A>, W, Capability>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+
+        let watchdog_alarm = static_init_half!(
+            static_buffer.0,
+            VirtualMuxAlarm<'static, A>,
+            VirtualMuxAlarm::new(self.alarm_mux)
+        );
+        watchdog_alarm.setup();
+
+        let process_watchdog = static_init_half!(
+            static_buffer.1,
+            ProcessWatchdog<'static, VirtualMuxAlarm<'static, A>, W, Capability>,
+            ProcessWatchdog::new(
+                watchdog_alarm,
+                self.hardware_watchdog,
+                self.board_kernel,
+                Capability,
+                self.action,
+                self.board_kernel.create_grant(self.driver_num, &grant_cap)
+            )
+        );
+
+        watchdog_alarm.set_alarm_client(process_watchdog);
+        process_watchdog
+    }
+}
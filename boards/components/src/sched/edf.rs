@@ -0,0 +1,80 @@
+//! Component for an earliest deadline first scheduler.
+//!
+//! This provides one Component, EDFComponent. `$N` is the number of process
+//! slots of the board.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let scheduler = components::sched::edf::EDFComponent::new(board_kernel, mux_alarm)
+//!     .finalize(components::edf_component_helper!(
+//!         nrf52840::rtc::Rtc,
+//!         NUM_PROCS
+//!     ));
+//! ```
+
+use core::mem::MaybeUninit;
+
+use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use kernel::component::Component;
+use kernel::hil::time;
+use kernel::scheduler::edf::{EDFProcessState, EDFSched};
+use kernel::static_init_half;
+
+#[macro_export]
+macro_rules! edf_component_helper {
+    ($A:ty, $N:expr $(,)?) => {{
+        use capsules::virtual_alarm::VirtualMuxAlarm;
+        use core::mem::MaybeUninit;
+        use kernel::scheduler::edf::{EDFProcessState, EDFSched};
+        static mut BUF1: MaybeUninit<VirtualMuxAlarm<'static, $A>> = MaybeUninit::uninit();
+        static mut BUF2: MaybeUninit<EDFSched<'static, VirtualMuxAlarm<'static, $A>>> =
+            MaybeUninit::uninit();
+        const INIT: EDFProcessState = EDFProcessState::new();
+        static mut BUF3: [EDFProcessState; $N] = [INIT; $N];
+        (&mut BUF1, &mut BUF2, &mut BUF3)
+    };};
+}
+
+pub struct EDFComponent<A: 'static + time::Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+}
+
+impl<A: 'static + time::Alarm<'static>> EDFComponent<A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+    ) -> EDFComponent<A> {
+        EDFComponent {
+            board_kernel,
+            alarm_mux,
+        }
+    }
+}
+
+impl<A: 'static + time::Alarm<'static>> Component for EDFComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<EDFSched<'static, VirtualMuxAlarm<'static, A>>>,
+        &'static mut [EDFProcessState],
+    );
+    type Output = &'static mut EDFSched<'static, VirtualMuxAlarm<'static, A>>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let (alarm_buf, sched_buf, states) = static_buffer;
+        let scheduler_alarm = static_init_half!(
+            alarm_buf,
+            VirtualMuxAlarm<'static, A>,
+            VirtualMuxAlarm::new(self.alarm_mux)
+        );
+        scheduler_alarm.setup();
+
+        let scheduler = static_init_half!(
+            sched_buf,
+            EDFSched<'static, VirtualMuxAlarm<'static, A>>,
+            EDFSched::new(self.board_kernel, scheduler_alarm, states)
+        );
+        scheduler
+    }
+}
@@ -1,4 +1,6 @@
 pub mod cooperative;
+pub mod edf;
 pub mod mlfq;
 pub mod priority;
+pub mod rate_monotonic;
 pub mod round_robin;
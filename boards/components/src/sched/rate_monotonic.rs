@@ -0,0 +1,38 @@
+//! Component for a rate monotonic scheduler.
+//!
+//! This provides one Component, RateMonotonicComponent.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let scheduler =
+//!     components::sched::rate_monotonic::RateMonotonicComponent::new(board_kernel)
+//!         .finalize(());
+//! ```
+
+use kernel::component::Component;
+use kernel::scheduler::rate_monotonic::RateMonotonicSched;
+use kernel::static_init;
+
+pub struct RateMonotonicComponent {
+    board_kernel: &'static kernel::Kernel,
+}
+
+impl RateMonotonicComponent {
+    pub fn new(board_kernel: &'static kernel::Kernel) -> RateMonotonicComponent {
+        RateMonotonicComponent { board_kernel }
+    }
+}
+
+impl Component for RateMonotonicComponent {
+    type StaticInput = ();
+    type Output = &'static mut RateMonotonicSched;
+
+    unsafe fn finalize(self, _static_buffer: Self::StaticInput) -> Self::Output {
+        let scheduler = static_init!(
+            RateMonotonicSched,
+            RateMonotonicSched::new(self.board_kernel)
+        );
+        scheduler
+    }
+}
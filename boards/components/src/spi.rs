@@ -35,7 +35,7 @@ use capsules::virtual_spi;
 use capsules::virtual_spi::{MuxSpiMaster, VirtualSpiMasterDevice};
 use kernel::capabilities;
 use kernel::component::Component;
-use kernel::dynamic_deferred_call::DynamicDeferredCall;
+use kernel::deferred_call::DeferredCallClient;
 use kernel::hil::spi;
 use kernel::hil::spi::{SpiMasterDevice, SpiSlaveDevice};
 use kernel::{create_capability, static_init, static_init_half};
@@ -99,7 +99,6 @@ macro_rules! spi_peripheral_component_helper {
 
 pub struct SpiMuxComponent<S: 'static + spi::SpiMaster> {
     spi: &'static S,
-    deferred_caller: &'static DynamicDeferredCall,
 }
 
 pub struct SpiSyscallComponent<S: 'static + spi::SpiMaster> {
@@ -121,11 +120,8 @@ pub struct SpiComponent<S: 'static + spi::SpiMaster> {
 }
 
 impl<S: 'static + spi::SpiMaster> SpiMuxComponent<S> {
-    pub fn new(spi: &'static S, deferred_caller: &'static DynamicDeferredCall) -> Self {
-        SpiMuxComponent {
-            spi: spi,
-            deferred_caller: deferred_caller,
-        }
+    pub fn new(spi: &'static S) -> Self {
+        SpiMuxComponent { spi: spi }
     }
 }
 
@@ -137,12 +133,10 @@ impl<S: 'static + spi::SpiMaster> Component for SpiMuxComponent<S> {
         let mux_spi = static_init_half!(
             static_buffer,
             MuxSpiMaster<'static, S>,
-            MuxSpiMaster::new(self.spi, self.deferred_caller)
+            MuxSpiMaster::new(self.spi)
         );
 
-        mux_spi.initialize_callback_handle(
-            self.deferred_caller.register(mux_spi).unwrap(), // Unwrap fail = no deferred call slot available for SPI mux
-        );
+        mux_spi.register();
 
         self.spi.set_client(mux_spi);
 
@@ -0,0 +1,114 @@
+//! Component for the syscall trace and the capsule that sends it to a host.
+//!
+//! The kernel only records system calls if it is built with the
+//! `trace_syscalls_buffer` feature.
+//!
+//! Usage
+//! -----
+//! ```rust
+//! let syscall_trace = components::syscall_trace::SyscallTraceComponent::new(
+//!     board_kernel,
+//!     mux_alarm,
+//!     capsules::syscall_trace::TraceOutput::DebugWriter,
+//!     100,
+//! )
+//! .finalize(components::syscall_trace_component_helper!(
+//!     nrf52840::rtc::Rtc,
+//!     64
+//! ));
+//! ```
+
+use core::mem::MaybeUninit;
+
+use capsules::syscall_trace::{SyscallTraceWriter, TraceOutput};
+use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use kernel::component::Component;
+use kernel::hil::time::{self, Alarm};
+use kernel::static_init_half;
+use kernel::syscall_trace::{SyscallRecord, SyscallTrace};
+
+// Setup static space for the objects. `$N` is the number of records the
+// trace holds.
+#[macro_export]
+macro_rules! syscall_trace_component_helper {
+    ($A:ty, $N:expr $(,)?) => {{
+        use capsules::syscall_trace::SyscallTraceWriter;
+        use capsules::virtual_alarm::VirtualMuxAlarm;
+        use core::mem::MaybeUninit;
+        use kernel::syscall_trace::{SyscallRecord, SyscallTrace};
+        static mut RECORDS: [SyscallRecord; $N] = [SyscallRecord::EMPTY; $N];
+        static mut BUF1: MaybeUninit<VirtualMuxAlarm<'static, $A>> = MaybeUninit::uninit();
+        static mut BUF2: MaybeUninit<SyscallTrace> = MaybeUninit::uninit();
+        static mut BUF3: MaybeUninit<SyscallTraceWriter<'static, VirtualMuxAlarm<'static, $A>>> =
+            MaybeUninit::uninit();
+        (&mut RECORDS, &mut BUF1, &mut BUF2, &mut BUF3)
+    };};
+}
+
+pub struct SyscallTraceComponent<A: 'static + time::Alarm<'static>> {
+    board_kernel: &'static kernel::Kernel,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    output: TraceOutput<'static>,
+    interval_ms: u32,
+}
+
+impl<A: 'static + time::Alarm<'static>> SyscallTraceComponent<A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        output: TraceOutput<'static>,
+        interval_ms: u32,
+    ) -> SyscallTraceComponent<A> {
+        SyscallTraceComponent {
+            board_kernel,
+            alarm_mux,
+            output,
+            interval_ms,
+        }
+    }
+}
+
+impl<A: 'static + time::Alarm<'static>> Component for SyscallTraceComponent<A> {
+    type StaticInput = (
+        &'static mut [SyscallRecord],
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<SyscallTrace>,
+        &'static mut MaybeUninit<SyscallTraceWriter<'static, VirtualMuxAlarm<'static, A>>>,
+    );
+    type Output = &'static SyscallTraceWriter<'static, VirtualMuxAlarm<'static, A>>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let trace_alarm = static_init_half!(
+            static_buffer.1,
+            VirtualMuxAlarm<'static, A>,
+            VirtualMuxAlarm::new(self.alarm_mux)
+        );
+        trace_alarm.setup();
+
+        let syscall_trace = static_init_half!(
+            static_buffer.2,
+            SyscallTrace,
+            SyscallTrace::new(static_buffer.0)
+        );
+        syscall_trace.set_clock(trace_alarm);
+        self.board_kernel.set_syscall_trace(syscall_trace);
+
+        let trace_writer = static_init_half!(
+            static_buffer.3,
+            SyscallTraceWriter<'static, VirtualMuxAlarm<'static, A>>,
+            SyscallTraceWriter::new(
+                trace_alarm,
+                syscall_trace,
+                self.output,
+                &mut capsules::syscall_trace::BUF,
+                self.interval_ms,
+            )
+        );
+        trace_alarm.set_alarm_client(trace_writer);
+        if let TraceOutput::Uart(uart) = self.output {
+            uart.set_transmit_client(trace_writer);
+        }
+        trace_writer.start();
+        trace_writer
+    }
+}
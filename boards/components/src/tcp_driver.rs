@@ -0,0 +1,112 @@
+//! Component to initialize the userland TCP driver.
+//!
+//! This provides one Component, TCPDriverComponent. This component initializes a userspace
+//! TCP driver that allows apps to open TCP connections. The driver owns a pool of
+//! `NUM_SOCKETS` sockets, which bounds the number of connections userspace can have open
+//! at once.
+//!
+//! Usage
+//! -----
+//! ```rust
+//!    let tcp_driver = TCPDriverComponent::new(
+//!        board_kernel,
+//!        capsules::net::tcp::DRIVER_NUM,
+//!        tcp_mux,
+//!        local_ip_ifaces,
+//!     )
+//!     .finalize(components::tcp_driver_component_helper!(nrf52840::rtc::Rtc));
+//! ```
+
+use capsules;
+use capsules::net::ipv6::ip_utils::IPAddr;
+use capsules::net::network_capabilities::{AddrRange, NetworkCapability, PortRange};
+use capsules::net::tcp::tcp_mux::MuxTcp;
+use capsules::net::tcp::tcp_socket::TCPSocket;
+use capsules::net::tcp::TCPDriver;
+use capsules::virtual_alarm::VirtualMuxAlarm;
+use core::mem::MaybeUninit;
+use kernel;
+use kernel::capabilities;
+use kernel::capabilities::NetworkCapabilityCreationCapability;
+use kernel::component::Component;
+use kernel::hil::time::Alarm;
+use kernel::{create_capability, static_init, static_init_half};
+
+pub const NUM_SOCKETS: usize = 4;
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! tcp_driver_component_helper {
+    ($A:ty $(,)?) => {{
+        use capsules::net::tcp::tcp_socket::TCPSocket;
+        use core::mem::MaybeUninit;
+        static mut BUF0: MaybeUninit<[TCPSocket<'static>; $crate::tcp_driver::NUM_SOCKETS]> =
+            MaybeUninit::uninit();
+        (&mut BUF0,)
+    };};
+}
+
+pub struct TCPDriverComponent<A: Alarm<'static> + 'static> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    tcp_mux: &'static MuxTcp<'static, VirtualMuxAlarm<'static, A>>,
+    interface_list: &'static [IPAddr],
+}
+
+impl<A: Alarm<'static>> TCPDriverComponent<A> {
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        tcp_mux: &'static MuxTcp<'static, VirtualMuxAlarm<'static, A>>,
+        interface_list: &'static [IPAddr],
+    ) -> Self {
+        Self {
+            board_kernel,
+            driver_num,
+            tcp_mux,
+            interface_list,
+        }
+    }
+}
+
+impl<A: Alarm<'static>> Component for TCPDriverComponent<A> {
+    type StaticInput = (&'static mut MaybeUninit<[TCPSocket<'static>; NUM_SOCKETS]>,);
+    type Output = &'static TCPDriver<'static>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+        let create_cap = create_capability!(NetworkCapabilityCreationCapability);
+        let net_cap = static_init!(
+            NetworkCapability,
+            NetworkCapability::new(AddrRange::Any, PortRange::Any, PortRange::Any, &create_cap)
+        );
+
+        // Socket ids are their index in the pool
+        let sockets = static_init_half!(
+            static_buffer.0,
+            [TCPSocket<'static>; NUM_SOCKETS],
+            [
+                TCPSocket::new(0),
+                TCPSocket::new(1),
+                TCPSocket::new(2),
+                TCPSocket::new(3),
+            ]
+        );
+
+        let tcp_driver = static_init!(
+            TCPDriver<'static>,
+            TCPDriver::new(
+                self.tcp_mux,
+                sockets,
+                self.board_kernel.create_grant(self.driver_num, &grant_cap),
+                self.interface_list,
+                net_cap,
+            )
+        );
+        for socket in sockets.iter() {
+            socket.set_client(tcp_driver);
+            self.tcp_mux.add_socket(socket);
+        }
+        tcp_driver
+    }
+}
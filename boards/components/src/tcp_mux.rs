@@ -0,0 +1,258 @@
+//! Component to initialize the tcp/6lowpan interface.
+//!
+//! This provides one Component, TCPMuxComponent. This component
+//! exposes a MuxTcp that userspace drivers and capsules can open TCP
+//! connections through.
+//!
+//! TCP uses its own MAC user, 6LoWPAN state and IPv6 sender and receiver,
+//! separate from the UDP stack, so both stacks can be used at the same time.
+//!
+//! Usage
+//! -----
+//! ```rust
+//!    let tcp_mux = TCPMuxComponent::new(
+//!        mux_mac,
+//!        DEFAULT_CTX_PREFIX_LEN,
+//!        DEFAULT_CTX_PREFIX,
+//!        DST_MAC_ADDR,
+//!        src_mac_from_serial_num,
+//!        local_ip_ifaces,
+//!        mux_alarm,
+//!    )
+//!    .finalize(components::tcp_mux_component_helper!(nrf52840::rtc::Rtc));
+//! ```
+
+use capsules;
+use capsules::ieee802154::device::MacDevice;
+use capsules::net::ieee802154::MacAddress;
+use capsules::net::ipv6::ip_utils::IPAddr;
+use capsules::net::ipv6::ipv6_recv::IP6Receiver;
+use capsules::net::ipv6::ipv6_send::IP6Sender;
+use capsules::net::ipv6::{IP6Packet, IPPayload, TransportHeader};
+use capsules::net::network_capabilities::{
+    AddrRange, IpVisibilityCapability, NetworkCapability, PortRange,
+};
+use capsules::net::sixlowpan::{sixlowpan_compression, sixlowpan_state};
+use capsules::net::tcp::tcp_mux::MuxTcp;
+use capsules::net::tcp::TCPHeader;
+use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use core::mem::MaybeUninit;
+use kernel;
+use kernel::capabilities;
+use kernel::component::Component;
+use kernel::create_capability;
+use kernel::hil::radio;
+use kernel::hil::time::Alarm;
+use kernel::utilities::leasable_buffer::LeasableMutableBuffer;
+use kernel::{static_init, static_init_half};
+
+// The TCP stack requires several packet buffers:
+//
+//   1. RADIO_BUF: buffer the IP6_Sender uses to pass frames to the radio after fragmentation
+//   2. SIXLOWPAN_RX_BUF: Buffer to hold full IP packets after they are decompressed by 6LoWPAN
+//   3. TCP_DGRAM: The payload of the IP6_Packet, which holds full IP Packets before they are tx'd.
+//   4. TCP_TX_BUF: Buffer the MuxTcp builds segment payloads in before passing them to the
+//      IP6_Sender.
+
+static mut RADIO_BUF: [u8; radio::MAX_BUF_SIZE] = [0x00; radio::MAX_BUF_SIZE];
+static mut SIXLOWPAN_RX_BUF: [u8; 1280] = [0x00; 1280];
+
+pub const MAX_SEGMENT_SIZE: usize = 200; //The max payload carried in a single TCP segment
+static mut TCP_DGRAM: [u8; MAX_SEGMENT_SIZE] = [0; MAX_SEGMENT_SIZE];
+static mut TCP_TX_BUF: [u8; MAX_SEGMENT_SIZE] = [0; MAX_SEGMENT_SIZE];
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! tcp_mux_component_helper {
+    ($A:ty $(,)?) => {{
+        use capsules;
+        use capsules::net::sixlowpan::{sixlowpan_compression, sixlowpan_state};
+        use capsules::net::tcp::tcp_mux::MuxTcp;
+        use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+        use core::mem::MaybeUninit;
+        static mut BUF0: MaybeUninit<VirtualMuxAlarm<'static, $A>> = MaybeUninit::uninit();
+        static mut BUF1: MaybeUninit<capsules::ieee802154::virtual_mac::MacUser<'static>> =
+            MaybeUninit::uninit();
+        static mut BUF2: MaybeUninit<
+            sixlowpan_state::Sixlowpan<
+                'static,
+                VirtualMuxAlarm<'static, $A>,
+                sixlowpan_compression::Context,
+            >,
+        > = MaybeUninit::uninit();
+        static mut BUF3: MaybeUninit<sixlowpan_state::RxState<'static>> = MaybeUninit::uninit();
+        static mut BUF4: MaybeUninit<
+            capsules::net::ipv6::ipv6_send::IP6SendStruct<'static, VirtualMuxAlarm<'static, $A>>,
+        > = MaybeUninit::uninit();
+        static mut BUF5: MaybeUninit<VirtualMuxAlarm<'static, $A>> = MaybeUninit::uninit();
+        static mut BUF6: MaybeUninit<MuxTcp<'static, VirtualMuxAlarm<'static, $A>>> =
+            MaybeUninit::uninit();
+        (
+            &mut BUF0, &mut BUF1, &mut BUF2, &mut BUF3, &mut BUF4, &mut BUF5, &mut BUF6,
+        )
+    };};
+}
+
+pub struct TCPMuxComponent<A: Alarm<'static> + 'static> {
+    mux_mac: &'static capsules::ieee802154::virtual_mac::MuxMac<'static>,
+    ctx_pfix_len: u8,
+    ctx_pfix: [u8; 16],
+    dst_mac_addr: MacAddress,
+    src_mac_addr: MacAddress,
+    interface_list: &'static [IPAddr],
+    alarm_mux: &'static MuxAlarm<'static, A>,
+}
+
+impl<A: Alarm<'static> + 'static> TCPMuxComponent<A> {
+    pub fn new(
+        mux_mac: &'static capsules::ieee802154::virtual_mac::MuxMac<'static>,
+        ctx_pfix_len: u8,
+        ctx_pfix: [u8; 16],
+        dst_mac_addr: MacAddress,
+        src_mac_addr: MacAddress,
+        interface_list: &'static [IPAddr],
+        alarm_mux: &'static MuxAlarm<'static, A>,
+    ) -> Self {
+        Self {
+            mux_mac,
+            ctx_pfix_len,
+            ctx_pfix,
+            dst_mac_addr,
+            src_mac_addr,
+            interface_list,
+            alarm_mux,
+        }
+    }
+}
+
+impl<A: Alarm<'static> + 'static> Component for TCPMuxComponent<A> {
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<capsules::ieee802154::virtual_mac::MacUser<'static>>,
+        &'static mut MaybeUninit<
+            sixlowpan_state::Sixlowpan<
+                'static,
+                VirtualMuxAlarm<'static, A>,
+                sixlowpan_compression::Context,
+            >,
+        >,
+        &'static mut MaybeUninit<sixlowpan_state::RxState<'static>>,
+        &'static mut MaybeUninit<
+            capsules::net::ipv6::ipv6_send::IP6SendStruct<'static, VirtualMuxAlarm<'static, A>>,
+        >,
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<MuxTcp<'static, VirtualMuxAlarm<'static, A>>>,
+    );
+    type Output = &'static MuxTcp<'static, VirtualMuxAlarm<'static, A>>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let ipsender_virtual_alarm = static_init_half!(
+            static_buffer.0,
+            VirtualMuxAlarm<'static, A>,
+            VirtualMuxAlarm::new(self.alarm_mux)
+        );
+        ipsender_virtual_alarm.setup();
+
+        let tcp_mac = static_init_half!(
+            static_buffer.1,
+            capsules::ieee802154::virtual_mac::MacUser<'static>,
+            capsules::ieee802154::virtual_mac::MacUser::new(self.mux_mac)
+        );
+        self.mux_mac.add_user(tcp_mac);
+        let create_cap = create_capability!(capabilities::NetworkCapabilityCreationCapability);
+        let ip_vis = static_init!(
+            IpVisibilityCapability,
+            IpVisibilityCapability::new(&create_cap)
+        );
+
+        let sixlowpan = static_init_half!(
+            static_buffer.2,
+            sixlowpan_state::Sixlowpan<
+                'static,
+                VirtualMuxAlarm<'static, A>,
+                sixlowpan_compression::Context,
+            >,
+            sixlowpan_state::Sixlowpan::new(
+                sixlowpan_compression::Context {
+                    prefix: self.ctx_pfix,
+                    prefix_len: self.ctx_pfix_len,
+                    id: 0,
+                    compress: false,
+                },
+                ipsender_virtual_alarm, // OK to reuse bc only used to get time, not set alarms
+            )
+        );
+
+        let sixlowpan_state = sixlowpan as &dyn sixlowpan_state::SixlowpanState;
+        let sixlowpan_tx = sixlowpan_state::TxState::new(sixlowpan_state);
+        let default_rx_state = static_init_half!(
+            static_buffer.3,
+            sixlowpan_state::RxState<'static>,
+            sixlowpan_state::RxState::new(&mut SIXLOWPAN_RX_BUF)
+        );
+        sixlowpan_state.add_rx_state(default_rx_state);
+        tcp_mac.set_receive_client(sixlowpan);
+
+        let tr_hdr = TransportHeader::TCP(TCPHeader::new());
+        let ip_pyld: IPPayload = IPPayload {
+            header: tr_hdr,
+            payload: &mut TCP_DGRAM,
+        };
+        let ip6_dg = static_init!(IP6Packet<'static>, IP6Packet::new(ip_pyld));
+
+        // As with UDP, the IP sender holds the destination mac address, so all
+        // TCP connections are routed via a single gateway.
+        let ip_send = static_init_half!(
+            static_buffer.4,
+            capsules::net::ipv6::ipv6_send::IP6SendStruct<'static, VirtualMuxAlarm<'static, A>>,
+            capsules::net::ipv6::ipv6_send::IP6SendStruct::new(
+                ip6_dg,
+                ipsender_virtual_alarm,
+                &mut RADIO_BUF,
+                sixlowpan_tx,
+                tcp_mac,
+                self.dst_mac_addr,
+                self.src_mac_addr,
+                ip_vis,
+            )
+        );
+        ipsender_virtual_alarm.set_alarm_client(ip_send);
+        ip_send.set_addr(self.interface_list[0]);
+        tcp_mac.set_transmit_client(ip_send);
+
+        let ip_receive = static_init!(
+            capsules::net::ipv6::ipv6_recv::IP6RecvStruct<'static>,
+            capsules::net::ipv6::ipv6_recv::IP6RecvStruct::new()
+        );
+        sixlowpan_state.set_rx_client(ip_receive);
+
+        // Capability used by the mux to answer segments that match no socket
+        let rst_net_cap = static_init!(
+            NetworkCapability,
+            NetworkCapability::new(AddrRange::Any, PortRange::Any, PortRange::Any, &create_cap)
+        );
+
+        let tcp_virtual_alarm = static_init_half!(
+            static_buffer.5,
+            VirtualMuxAlarm<'static, A>,
+            VirtualMuxAlarm::new(self.alarm_mux)
+        );
+        tcp_virtual_alarm.setup();
+
+        let tcp_mux = static_init_half!(
+            static_buffer.6,
+            MuxTcp<'static, VirtualMuxAlarm<'static, A>>,
+            MuxTcp::new(
+                ip_send,
+                tcp_virtual_alarm,
+                LeasableMutableBuffer::new(&mut TCP_TX_BUF),
+                rst_net_cap,
+            )
+        );
+        tcp_virtual_alarm.set_alarm_client(tcp_mux);
+        ip_send.set_client(tcp_mux);
+        ip_receive.set_client(tcp_mux);
+
+        tcp_mux
+    }
+}
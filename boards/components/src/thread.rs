@@ -0,0 +1,289 @@
+//! Component to initialize Thread networking.
+//!
+//! This provides one Component, ThreadComponent. This component creates the
+//! MLE node that attaches the board to a Thread network as a sleepy end
+//! device, and the userspace driver that controls it.
+//!
+//! The node sends MLE messages over the UDP stack, so it takes the UDP muxes
+//! and port table created by the UDP mux component. The UDP driver component
+//! must be created first, since binding the MLE port checks the ports bound
+//! by userspace. The node polls its parent with its own MAC user, and derives
+//! its keys with a software SHA-256. It takes the frame counters of its
+//! messages from the security table created by the IEEE 802.15.4 component,
+//! which must be kept in nonvolatile storage for the node to attach.
+//!
+//! Usage
+//! -----
+//! ```rust
+//!    let thread_driver = ThreadComponent::new(
+//!        board_kernel,
+//!        capsules::net::thread::DRIVER_NUM,
+//!        mux_mac,
+//!        security_table,
+//!        udp_send_mux,
+//!        udp_recv_mux,
+//!        udp_port_table,
+//!        mux_alarm,
+//!        aes_mux,
+//!        rng,
+//!    )
+//!    .finalize(components::thread_component_helper!(
+//!        nrf52840::rtc::Rtc,
+//!        nrf52840::aes::AesECB<'static>
+//!    ));
+//! ```
+
+use capsules;
+use capsules::ieee802154::device::MacDevice;
+use capsules::ieee802154::security_table::SecurityTable;
+use capsules::ieee802154::virtual_mac::{MacUser, MuxMac};
+use capsules::net::ipv6::ipv6_send::IP6SendStruct;
+use capsules::net::network_capabilities::{
+    AddrRange, NetworkCapability, PortRange, UdpVisibilityCapability,
+};
+use capsules::net::thread::mle::{self, Mle, MleNode, MLE_PORT};
+use capsules::net::thread::ThreadDriver;
+use capsules::net::udp::udp_port_table::UdpPortManager;
+use capsules::net::udp::udp_recv::{MuxUdpReceiver, UDPReceiver};
+use capsules::net::udp::udp_send::{MuxUdpSender, UDPSendStruct, UDPSender};
+use capsules::sha256::Sha256Software;
+use capsules::virtual_aes_ccm::{MuxAES128CCM, VirtualAES128CCM};
+use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
+use core::mem::MaybeUninit;
+use kernel;
+use kernel::capabilities;
+use kernel::capabilities::NetworkCapabilityCreationCapability;
+use kernel::component::Component;
+use kernel::deferred_call::DeferredCallClient;
+use kernel::hil::digest::Digest;
+use kernel::hil::radio;
+use kernel::hil::rng::Rng;
+use kernel::hil::symmetric_encryption::{self, AES128Ctr, AES128, AES128CBC, AES128CCM, AES128ECB};
+use kernel::hil::time::Alarm;
+use kernel::{create_capability, static_init, static_init_half};
+
+// The MLE node requires several buffers:
+//
+//   1. CRYPT_BUF: MLE messages are encoded and secured in this buffer
+//   2. CCM_BUF: buffer of the AES-CCM virtualizer, which must hold 3 blocks more than CRYPT_BUF
+//   3. SEND_BUF: the UDP payload of secured MLE messages
+//   4. HASH_BUF and DIGEST_BUF: the input and output of the key derivation
+//   5. POLL_BUF: the MAC Data Request frames polling the parent
+
+static mut CRYPT_BUF: [u8; mle::CRYPT_BUF_LEN] = [0; mle::CRYPT_BUF_LEN];
+const CCM_BUF_LEN: usize = 3 * symmetric_encryption::AES128_BLOCK_SIZE + mle::CRYPT_BUF_LEN;
+static mut CCM_BUF: [u8; CCM_BUF_LEN] = [0; CCM_BUF_LEN];
+static mut SEND_BUF: [u8; mle::SEND_BUF_LEN] = [0; mle::SEND_BUF_LEN];
+static mut HASH_BUF: [u8; mle::HASH_BUF_LEN] = [0; mle::HASH_BUF_LEN];
+static mut DIGEST_BUF: [u8; 32] = [0; 32];
+static mut POLL_BUF: [u8; radio::MAX_BUF_SIZE] = [0; radio::MAX_BUF_SIZE];
+
+// Setup static space for the objects.
+#[macro_export]
+macro_rules! thread_component_helper {
+    ($A:ty, $E:ty $(,)?) => {{
+        use capsules::net::ipv6::ipv6_send::IP6SendStruct;
+        use capsules::net::thread::mle::MleNode;
+        use capsules::net::udp::udp_send::UDPSendStruct;
+        use capsules::sha256::Sha256Software;
+        use capsules::virtual_aes_ccm::VirtualAES128CCM;
+        use capsules::virtual_alarm::VirtualMuxAlarm;
+        use core::mem::MaybeUninit;
+        static mut BUF0: MaybeUninit<VirtualMuxAlarm<'static, $A>> = MaybeUninit::uninit();
+        static mut BUF1: MaybeUninit<
+            UDPSendStruct<'static, IP6SendStruct<'static, VirtualMuxAlarm<'static, $A>>>,
+        > = MaybeUninit::uninit();
+        static mut BUF2: MaybeUninit<VirtualAES128CCM<'static, $E>> = MaybeUninit::uninit();
+        static mut BUF3: MaybeUninit<
+            MleNode<
+                'static,
+                VirtualMuxAlarm<'static, $A>,
+                VirtualAES128CCM<'static, $E>,
+                Sha256Software<'static>,
+                IP6SendStruct<'static, VirtualMuxAlarm<'static, $A>>,
+            >,
+        > = MaybeUninit::uninit();
+        (&mut BUF0, &mut BUF1, &mut BUF2, &mut BUF3)
+    };};
+}
+
+pub struct ThreadComponent<
+    A: Alarm<'static> + 'static,
+    E: 'static + AES128<'static> + AES128Ctr + AES128CBC + AES128ECB,
+> {
+    board_kernel: &'static kernel::Kernel,
+    driver_num: usize,
+    mux_mac: &'static MuxMac<'static>,
+    security_table: &'static SecurityTable<'static>,
+    udp_send_mux:
+        &'static MuxUdpSender<'static, IP6SendStruct<'static, VirtualMuxAlarm<'static, A>>>,
+    udp_recv_mux: &'static MuxUdpReceiver<'static>,
+    port_table: &'static UdpPortManager,
+    alarm_mux: &'static MuxAlarm<'static, A>,
+    aes_mux: &'static MuxAES128CCM<'static, E>,
+    rng: &'static dyn Rng<'static>,
+}
+
+impl<
+        A: Alarm<'static> + 'static,
+        E: 'static + AES128<'static> + AES128Ctr + AES128CBC + AES128ECB,
+    > ThreadComponent<A, E>
+{
+    pub fn new(
+        board_kernel: &'static kernel::Kernel,
+        driver_num: usize,
+        mux_mac: &'static MuxMac<'static>,
+        security_table: &'static SecurityTable<'static>,
+        udp_send_mux: &'static MuxUdpSender<
+            'static,
+            IP6SendStruct<'static, VirtualMuxAlarm<'static, A>>,
+        >,
+        udp_recv_mux: &'static MuxUdpReceiver<'static>,
+        port_table: &'static UdpPortManager,
+        alarm_mux: &'static MuxAlarm<'static, A>,
+        aes_mux: &'static MuxAES128CCM<'static, E>,
+        rng: &'static dyn Rng<'static>,
+    ) -> Self {
+        Self {
+            board_kernel,
+            driver_num,
+            mux_mac,
+            security_table,
+            udp_send_mux,
+            udp_recv_mux,
+            port_table,
+            alarm_mux,
+            aes_mux,
+            rng,
+        }
+    }
+}
+
+impl<
+        A: Alarm<'static> + 'static,
+        E: 'static + AES128<'static> + AES128Ctr + AES128CBC + AES128ECB,
+    > Component for ThreadComponent<A, E>
+{
+    type StaticInput = (
+        &'static mut MaybeUninit<VirtualMuxAlarm<'static, A>>,
+        &'static mut MaybeUninit<
+            UDPSendStruct<'static, IP6SendStruct<'static, VirtualMuxAlarm<'static, A>>>,
+        >,
+        &'static mut MaybeUninit<VirtualAES128CCM<'static, E>>,
+        &'static mut MaybeUninit<
+            MleNode<
+                'static,
+                VirtualMuxAlarm<'static, A>,
+                VirtualAES128CCM<'static, E>,
+                Sha256Software<'static>,
+                IP6SendStruct<'static, VirtualMuxAlarm<'static, A>>,
+            >,
+        >,
+    );
+    type Output = &'static ThreadDriver<'static>;
+
+    unsafe fn finalize(self, static_buffer: Self::StaticInput) -> Self::Output {
+        let grant_cap = create_capability!(capabilities::MemoryAllocationCapability);
+        let create_cap = create_capability!(NetworkCapabilityCreationCapability);
+
+        let mle_alarm = static_init_half!(
+            static_buffer.0,
+            VirtualMuxAlarm<'static, A>,
+            VirtualMuxAlarm::new(self.alarm_mux)
+        );
+        mle_alarm.setup();
+
+        let mle_mac = static_init!(MacUser<'static>, MacUser::new(self.mux_mac));
+        self.mux_mac.add_user(mle_mac);
+
+        let udp_vis = static_init!(
+            UdpVisibilityCapability,
+            UdpVisibilityCapability::new(&create_cap)
+        );
+        let udp_send = static_init_half!(
+            static_buffer.1,
+            UDPSendStruct<'static, IP6SendStruct<'static, VirtualMuxAlarm<'static, A>>>,
+            UDPSendStruct::new(self.udp_send_mux, udp_vis)
+        );
+        let udp_recv = static_init!(UDPReceiver<'static>, UDPReceiver::new());
+        self.udp_recv_mux.add_client(udp_recv);
+
+        // MLE messages are exchanged with neighbors on the MLE port only
+        let net_cap = static_init!(
+            NetworkCapability,
+            NetworkCapability::new(
+                AddrRange::Any,
+                PortRange::Port(MLE_PORT),
+                PortRange::Port(MLE_PORT),
+                &create_cap
+            )
+        );
+        let socket = self
+            .port_table
+            .create_socket()
+            .expect("Thread: no UDP socket available");
+        let (tx_binding, rx_binding) = self
+            .port_table
+            .bind(socket, MLE_PORT, net_cap)
+            .expect("Thread: MLE port already bound");
+        udp_send.set_binding(tx_binding);
+        // MLE messages are secured by MLE, not by the MAC
+        udp_send.disable_link_security();
+        udp_recv.set_binding(rx_binding);
+
+        let ccm = static_init_half!(
+            static_buffer.2,
+            VirtualAES128CCM<'static, E>,
+            VirtualAES128CCM::new(self.aes_mux, &mut CCM_BUF)
+        );
+        ccm.setup();
+
+        let sha = static_init!(Sha256Software<'static>, Sha256Software::new());
+        sha.register();
+
+        let mle_node = static_init_half!(
+            static_buffer.3,
+            MleNode<
+                'static,
+                VirtualMuxAlarm<'static, A>,
+                VirtualAES128CCM<'static, E>,
+                Sha256Software<'static>,
+                IP6SendStruct<'static, VirtualMuxAlarm<'static, A>>,
+            >,
+            MleNode::new(
+                mle_alarm,
+                ccm,
+                sha,
+                self.udp_send_mux,
+                udp_send,
+                mle_mac,
+                self.security_table,
+                self.rng,
+                net_cap,
+                &mut CRYPT_BUF,
+                &mut SEND_BUF,
+                &mut HASH_BUF,
+                &mut DIGEST_BUF,
+                &mut POLL_BUF,
+            )
+        );
+        mle_alarm.set_alarm_client(mle_node);
+        mle_mac.set_transmit_client(mle_node);
+        udp_send.set_client(mle_node);
+        udp_recv.set_client(mle_node);
+        AES128CCM::set_client(ccm, mle_node);
+        sha.set_client(mle_node);
+        self.rng.set_client(mle_node);
+
+        let thread_driver = static_init!(
+            ThreadDriver<'static>,
+            ThreadDriver::new(
+                mle_node,
+                self.board_kernel.create_grant(self.driver_num, &grant_cap)
+            )
+        );
+        mle_node.set_client(thread_driver);
+
+        thread_driver
+    }
+}
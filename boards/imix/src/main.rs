@@ -20,7 +20,7 @@ use capsules::virtual_spi::VirtualSpiMasterDevice;
 //use capsules::virtual_timer::MuxTimer;
 use kernel::capabilities;
 use kernel::component::Component;
-use kernel::dynamic_deferred_call::{DynamicDeferredCall, DynamicDeferredCallClientState};
+use kernel::deferred_call::DeferredCallClient;
 use kernel::hil::i2c::I2CMaster;
 use kernel::hil::radio;
 #[allow(unused_imports)]
@@ -95,6 +95,11 @@ const FAULT_RESPONSE: kernel::process::PanicFaultPolicy = kernel::process::Panic
 static mut PROCESSES: [Option<&'static dyn kernel::process::Process>; NUM_PROCS] =
     [None; NUM_PROCS];
 
+// Storage for the 802.15.4 keys, neighbors and frame counters
+mod ieee802154_storage {
+    kernel::storage_volume!(SECURITY_VOLUME, 1);
+}
+
 static mut CHIP: Option<&'static sam4l::chip::Sam4l<Sam4lDefaultPeripherals>> = None;
 static mut PROCESS_PRINTER: Option<&'static kernel::process::ProcessPrinterText> = None;
 
@@ -103,6 +108,12 @@ static mut PROCESS_PRINTER: Option<&'static kernel::process::ProcessPrinterText>
 #[link_section = ".stack_buffer"]
 pub static mut STACK_MEMORY: [u8; 0x2000] = [0; 0x2000];
 
+/// Memory that processes can share with each other. The MPU requires each
+/// 1 kB block to be aligned to its size.
+#[repr(align(1024))]
+struct SharedMemoryPool([u8; 0x1000]);
+static mut SHARED_MEMORY_POOL: SharedMemoryPool = SharedMemoryPool([0; 0x1000]);
+
 struct Imix {
     pconsole: &'static capsules::process_console::ProcessConsole<
         'static,
@@ -132,6 +143,8 @@ struct Imix {
         VirtualSpiMasterDevice<'static, sam4l::spi::SpiHw>,
     >,
     ipc: kernel::ipc::IPC<{ NUM_PROCS as u8 }>,
+    message_ipc: kernel::ipc::MessageIPC,
+    shared_memory: &'static kernel::shared_memory::SharedMemory<4>,
     ninedof: &'static capsules::ninedof::NineDof<'static>,
     udp_driver: &'static capsules::net::udp::UDPDriver<'static>,
     crc: &'static capsules::crc::CrcDriver<'static, sam4l::crccu::Crccu<'static>>,
@@ -183,6 +196,8 @@ impl SyscallDriverLookup for Imix {
             capsules::nonvolatile_storage_driver::DRIVER_NUM => f(Some(self.nonvolatile_storage)),
             capsules::rng::DRIVER_NUM => f(Some(self.rng)),
             kernel::ipc::DRIVER_NUM => f(Some(&self.ipc)),
+            kernel::ipc::MESSAGE_DRIVER_NUM => f(Some(&self.message_ipc)),
+            kernel::shared_memory::DRIVER_NUM => f(Some(self.shared_memory)),
             _ => f(None),
         }
     }
@@ -322,7 +337,7 @@ pub unsafe fn main() {
 
     set_pin_primary_functions(peripherals);
 
-    peripherals.setup_dma();
+    peripherals.setup_circular_deps();
     let chip = static_init!(
         sam4l::chip::Sam4l<Sam4lDefaultPeripherals>,
         sam4l::chip::Sam4l::new(pm, peripherals)
@@ -349,14 +364,6 @@ pub unsafe fn main() {
 
     let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&PROCESSES));
 
-    let dynamic_deferred_call_clients =
-        static_init!([DynamicDeferredCallClientState; 5], Default::default());
-    let dynamic_deferred_caller = static_init!(
-        DynamicDeferredCall,
-        DynamicDeferredCall::new(dynamic_deferred_call_clients)
-    );
-    DynamicDeferredCall::set_global_instance(dynamic_deferred_caller);
-
     let process_printer =
         components::process_printer::ProcessPrinterTextComponent::new().finalize(());
     PROCESS_PRINTER = Some(process_printer);
@@ -364,8 +371,7 @@ pub unsafe fn main() {
     // # CONSOLE
     // Create a shared UART channel for the consoles and for kernel debug.
     peripherals.usart3.set_mode(sam4l::usart::UsartMode::Uart);
-    let uart_mux =
-        UartMuxComponent::new(&peripherals.usart3, 115200, dynamic_deferred_caller).finalize(());
+    let uart_mux = UartMuxComponent::new(&peripherals.usart3, 115200).finalize(());
 
     // # TIMER
     let mux_alarm = AlarmMuxComponent::new(&peripherals.ast)
@@ -393,10 +399,8 @@ pub unsafe fn main() {
     .finalize(());
 
     // # I2C and I2C Sensors
-    let mux_i2c = static_init!(
-        MuxI2C<'static>,
-        MuxI2C::new(&peripherals.i2c2, None, dynamic_deferred_caller)
-    );
+    let mux_i2c = static_init!(MuxI2C<'static>, MuxI2C::new(&peripherals.i2c2, None));
+    mux_i2c.register();
     peripherals.i2c2.set_master_client(mux_i2c);
 
     let ambient_light = AmbientLightComponent::new(
@@ -425,7 +429,7 @@ pub unsafe fn main() {
     .finalize(());
 
     // SPI MUX, SPI syscall driver and RF233 radio
-    let mux_spi = components::spi::SpiMuxComponent::new(&peripherals.spi, dynamic_deferred_caller)
+    let mux_spi = components::spi::SpiMuxComponent::new(&peripherals.spi)
         .finalize(components::spi_mux_component_helper!(sam4l::spi::SpiHw));
 
     let spi_syscalls = SpiSyscallComponent::new(
@@ -530,35 +534,10 @@ pub unsafe fn main() {
 
     let aes_mux = static_init!(
         MuxAES128CCM<'static, sam4l::aes::Aes>,
-        MuxAES128CCM::new(&peripherals.aes, dynamic_deferred_caller)
+        MuxAES128CCM::new(&peripherals.aes)
     );
     peripherals.aes.set_client(aes_mux);
-    aes_mux.initialize_callback_handle(
-        dynamic_deferred_caller.register(aes_mux).unwrap(), // Unwrap fail = no deferred call slot available for ccm mux
-    );
-
-    // Can this initialize be pushed earlier, or into component? -pal
-    let _ = rf233.initialize(&mut RF233_BUF, &mut RF233_REG_WRITE, &mut RF233_REG_READ);
-    let (_, mux_mac) = components::ieee802154::Ieee802154Component::new(
-        board_kernel,
-        capsules::ieee802154::DRIVER_NUM,
-        rf233,
-        aes_mux,
-        PAN_ID,
-        serial_num_bottom_16,
-        dynamic_deferred_caller,
-    )
-    .finalize(components::ieee802154_component_helper!(
-        capsules::rf233::RF233<'static, VirtualSpiMasterDevice<'static, sam4l::spi::SpiHw>>,
-        sam4l::aes::Aes<'static>
-    ));
-
-    let usb_driver = UsbComponent::new(
-        board_kernel,
-        capsules::usb::usb_user::DRIVER_NUM,
-        &peripherals.usbc,
-    )
-    .finalize(());
+    aes_mux.register();
 
     // Kernel storage region, allocated with the storage_volume!
     // macro in common/utils.rs
@@ -581,6 +560,32 @@ pub unsafe fn main() {
         sam4l::flashcalw::FLASHCALW
     ));
 
+    // Can this initialize be pushed earlier, or into component? -pal
+    let _ = rf233.initialize(&mut RF233_BUF, &mut RF233_REG_WRITE, &mut RF233_REG_READ);
+    let (_, mux_mac, _) = components::ieee802154::Ieee802154Component::new(
+        board_kernel,
+        capsules::ieee802154::DRIVER_NUM,
+        rf233,
+        aes_mux,
+        PAN_ID,
+        serial_num_bottom_16,
+        Some((
+            nonvolatile_storage,
+            ieee802154_storage::SECURITY_VOLUME.as_ptr() as usize,
+        )),
+    )
+    .finalize(components::ieee802154_component_helper!(
+        capsules::rf233::RF233<'static, VirtualSpiMasterDevice<'static, sam4l::spi::SpiHw>>,
+        sam4l::aes::Aes<'static>
+    ));
+
+    let usb_driver = UsbComponent::new(
+        board_kernel,
+        capsules::usb::usb_user::DRIVER_NUM,
+        &peripherals.usbc,
+    )
+    .finalize(());
+
     let local_ip_ifaces = static_init!(
         [IPAddr; 3],
         [
@@ -622,6 +627,12 @@ pub unsafe fn main() {
     let scheduler = components::sched::round_robin::RoundRobinComponent::new(&PROCESSES)
         .finalize(components::rr_component_helper!(NUM_PROCS));
 
+    let shared_memory = static_init!(
+        kernel::shared_memory::SharedMemory<4>,
+        kernel::shared_memory::SharedMemory::new(board_kernel, &mut SHARED_MEMORY_POOL.0)
+    );
+    board_kernel.set_process_termination_client(shared_memory);
+
     let imix = Imix {
         pconsole,
         console,
@@ -638,6 +649,12 @@ pub unsafe fn main() {
         crc,
         spi: spi_syscalls,
         ipc: kernel::ipc::IPC::new(board_kernel, kernel::ipc::DRIVER_NUM, &grant_cap),
+        message_ipc: kernel::ipc::MessageIPC::new(
+            board_kernel,
+            kernel::ipc::MESSAGE_DRIVER_NUM,
+            &grant_cap,
+        ),
+        shared_memory,
         ninedof,
         udp_driver,
         usb_driver,
@@ -665,17 +682,15 @@ pub unsafe fn main() {
     //
     //test::virtual_uart_rx_test::run_virtual_uart_receive(uart_mux);
     //test::rng_test::run_entropy32(&peripherals.trng);
-    //test::virtual_aes_ccm_test::run(&peripherals.aes, dynamic_deferred_caller);
+    //test::virtual_aes_ccm_test::run(&peripherals.aes);
     //test::aes_test::run_aes128_ctr(&peripherals.aes);
     //test::aes_test::run_aes128_cbc(&peripherals.aes);
     //test::log_test::run(
     //    mux_alarm,
-    //    dynamic_deferred_caller,
     //    &peripherals.flash_controller,
     //);
     //test::linear_log_test::run(
     //    mux_alarm,
-    //    dynamic_deferred_caller,
     //    &peripherals.flash_controller,
     //);
     //test::icmp_lowpan_test::run(mux_mac, mux_alarm);
@@ -702,7 +717,7 @@ pub unsafe fn main() {
     );*/
     //virtual_alarm_timer.set_alarm_client(mux_timer);
 
-    //test::sha256_test::run_sha256(dynamic_deferred_caller);
+    //test::sha256_test::run_sha256();
 
     /*components::test::multi_alarm_test::MultiAlarmTestComponent::new(mux_alarm)
     .finalize(components::multi_alarm_test_component_buf!(sam4l::ast::Ast))
@@ -20,7 +20,7 @@
 //!
 //! To run the test, add the following line to the imix boot sequence:
 //! ```
-//!     test::log_test::run(mux_alarm, dynamic_deferred_caller, &peripherals.flash_controller);
+//!     test::log_test::run(mux_alarm, &peripherals.flash_controller);
 //! ```
 //! and use the `USER` and `RESET` buttons to manually erase the log and reboot the imix,
 //! respectively.
@@ -29,7 +29,7 @@ use capsules::log;
 use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
 use core::cell::Cell;
 use kernel::debug;
-use kernel::dynamic_deferred_call::DynamicDeferredCall;
+use kernel::deferred_call::DeferredCallClient;
 use kernel::hil::flash;
 use kernel::hil::gpio::{self, Interrupt};
 use kernel::hil::log::{LogRead, LogReadClient, LogWrite, LogWriteClient};
@@ -46,7 +46,6 @@ storage_volume!(TEST_LOG, 2);
 
 pub unsafe fn run(
     mux_alarm: &'static MuxAlarm<'static, Ast>,
-    deferred_caller: &'static DynamicDeferredCall,
     flash_controller: &'static sam4l::flashcalw::FLASHCALW,
 ) {
     // Set up flash controller.
@@ -56,20 +55,10 @@ pub unsafe fn run(
     // Create actual log storage abstraction on top of flash.
     let log = static_init!(
         Log,
-        log::Log::new(
-            &TEST_LOG,
-            &flash_controller,
-            pagebuffer,
-            deferred_caller,
-            true
-        )
+        log::Log::new(&TEST_LOG, &flash_controller, pagebuffer, true)
     );
     flash::HasClient::set_client(flash_controller, log);
-    log.initialize_callback_handle(
-        deferred_caller
-            .register(log)
-            .expect("no deferred call slot available for log storage"),
-    );
+    log.register();
 
     let alarm = static_init!(
         VirtualMuxAlarm<'static, Ast>,
@@ -1,10 +1,9 @@
 //! This tests a software SHA256 implementation. To run this test,
 //! add this line to the imix boot sequence:
 //! ```
-//!     test::sha256_test::run_sha256(dynamic_deferred_caller);
+//!     test::sha256_test::run_sha256();
 //! ```
-//! This test takes a dynamic deferred call (for callbacks). It tries to
-//! hash 'hello world' and uses Digest::validate to check that the hash
+//! It tries to hash 'hello world' and uses Digest::validate to check that the hash
 //! is correct.
 //!
 //! The expected output is
@@ -18,11 +17,11 @@
 
 use capsules::sha256::Sha256Software;
 use capsules::test::sha256::TestSha256;
-use kernel::dynamic_deferred_call::DynamicDeferredCall;
+use kernel::deferred_call::DeferredCallClient;
 use kernel::static_init;
 
-pub unsafe fn run_sha256(call: &'static DynamicDeferredCall) {
-    let t = static_init_test_sha256(call);
+pub unsafe fn run_sha256() {
+    let t = static_init_test_sha256();
     t.run();
 }
 
@@ -42,9 +41,9 @@ pub static mut LHASH: [u8; 32] = [
     0x45, 0x94, 0xd5, 0xee, 0x15, 0xcb, 0x8a, 0x1e, 0x28, 0x7c, 0x20, 0x12, 0xc2, 0xce, 0xb5, 0xa9,
 ];
 
-unsafe fn static_init_test_sha256(call: &'static DynamicDeferredCall) -> &'static TestSha256 {
-    let sha = static_init!(Sha256Software<'static>, Sha256Software::new(call));
-    sha.initialize_callback_handle(call.register(sha).unwrap());
+unsafe fn static_init_test_sha256() -> &'static TestSha256 {
+    let sha = static_init!(Sha256Software<'static>, Sha256Software::new());
+    sha.register();
     let bytes = b"hello ";
     for i in 0..12 {
         for j in 0..6 {
@@ -9,7 +9,7 @@
 use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
 use kernel::capabilities;
 use kernel::component::Component;
-use kernel::dynamic_deferred_call::{DynamicDeferredCall, DynamicDeferredCallClientState};
+use kernel::deferred_call::DeferredCallClient;
 use kernel::hil::time::{Alarm, Timer};
 use kernel::platform::chip::InterruptService;
 use kernel::platform::scheduler_timer::VirtualSchedulerTimer;
@@ -47,7 +47,7 @@ struct LiteXArtyInterruptablePeripherals {
     >,
     ethmac0: &'static litex_vexriscv::liteeth::LiteEth<'static, socc::SoCRegisterFmt>,
 }
-impl InterruptService<()> for LiteXArtyInterruptablePeripherals {
+impl InterruptService for LiteXArtyInterruptablePeripherals {
     unsafe fn service_interrupt(&self, interrupt: u32) -> bool {
         match interrupt as usize {
             socc::UART_INTERRUPT => {
@@ -65,10 +65,6 @@ impl InterruptService<()> for LiteXArtyInterruptablePeripherals {
             _ => false,
         }
     }
-
-    unsafe fn service_deferred_call(&self, _task: ()) -> bool {
-        false
-    }
 }
 
 const NUM_PROCS: usize = 4;
@@ -234,14 +230,6 @@ pub unsafe fn main() {
 
     let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&PROCESSES));
 
-    let dynamic_deferred_call_clients =
-        static_init!([DynamicDeferredCallClientState; 2], Default::default());
-    let dynamic_deferred_caller = static_init!(
-        DynamicDeferredCall,
-        DynamicDeferredCall::new(dynamic_deferred_call_clients)
-    );
-    DynamicDeferredCall::set_global_instance(dynamic_deferred_caller);
-
     // ---------- LED CONTROLLER HARDWARE ----------
 
     // Initialize the LEDs, stopping any patterns from the bootloader
@@ -392,22 +380,16 @@ pub unsafe fn main() {
             // hardware. Change with --uart-baudrate during SoC
             // generation. Fixed to 1MBd.
             None,
-            dynamic_deferred_caller,
         )
     );
-    uart0.initialize(
-        dynamic_deferred_caller.register(uart0).unwrap(), // Unwrap fail = dynamic deferred caller out of slots
-    );
+    uart0.initialize();
+    uart0.register();
 
     PANIC_REFERENCES.uart = Some(uart0);
 
     // Create a shared UART channel for the console and for kernel debug.
-    let uart_mux = components::console::UartMuxComponent::new(
-        uart0,
-        socc::UART_BAUDRATE,
-        dynamic_deferred_caller,
-    )
-    .finalize(());
+    let uart_mux =
+        components::console::UartMuxComponent::new(uart0, socc::UART_BAUDRATE).finalize(());
 
     // ---------- ETHERNET ----------
 
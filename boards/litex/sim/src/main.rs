@@ -8,7 +8,7 @@
 use capsules::virtual_alarm::{MuxAlarm, VirtualMuxAlarm};
 use kernel::capabilities;
 use kernel::component::Component;
-use kernel::dynamic_deferred_call::{DynamicDeferredCall, DynamicDeferredCallClientState};
+use kernel::deferred_call::DeferredCallClient;
 use kernel::hil::led::LedHigh;
 use kernel::hil::time::{Alarm, Timer};
 use kernel::platform::chip::InterruptService;
@@ -49,7 +49,7 @@ struct LiteXSimInterruptablePeripherals {
     ethmac0: &'static litex_vexriscv::liteeth::LiteEth<'static, socc::SoCRegisterFmt>,
 }
 
-impl InterruptService<()> for LiteXSimInterruptablePeripherals {
+impl InterruptService for LiteXSimInterruptablePeripherals {
     unsafe fn service_interrupt(&self, interrupt: u32) -> bool {
         match interrupt as usize {
             socc::UART_INTERRUPT => {
@@ -71,10 +71,6 @@ impl InterruptService<()> for LiteXSimInterruptablePeripherals {
             _ => false,
         }
     }
-
-    unsafe fn service_deferred_call(&self, _task: ()) -> bool {
-        false
-    }
 }
 
 const NUM_PROCS: usize = 4;
@@ -235,14 +231,6 @@ pub unsafe fn main() {
 
     let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&PROCESSES));
 
-    let dynamic_deferred_call_clients =
-        static_init!([DynamicDeferredCallClientState; 2], Default::default());
-    let dynamic_deferred_caller = static_init!(
-        DynamicDeferredCall,
-        DynamicDeferredCall::new(dynamic_deferred_call_clients)
-    );
-    DynamicDeferredCall::set_global_instance(dynamic_deferred_caller);
-
     // --------- TIMER & UPTIME CORE; ALARM INITIALIZATION ----------
 
     // Initialize the hardware timer
@@ -370,12 +358,10 @@ pub unsafe fn main() {
                     as *const litex_vexriscv::uart::LiteXUartRegisters<socc::SoCRegisterFmt>,
             ),
             None, // LiteX simulator has no UART phy
-            dynamic_deferred_caller,
         )
     );
-    uart0.initialize(
-        dynamic_deferred_caller.register(uart0).unwrap(), // Unwrap fail = dynamic deferred caller out of slots
-    );
+    uart0.initialize();
+    uart0.register();
 
     PANIC_REFERENCES.uart = Some(uart0);
 
@@ -383,9 +369,7 @@ pub unsafe fn main() {
     //
     // The baudrate is ingnored, as no UART phy is present in the
     // verilated simulation.
-    let uart_mux =
-        components::console::UartMuxComponent::new(uart0, 115200, dynamic_deferred_caller)
-            .finalize(());
+    let uart_mux = components::console::UartMuxComponent::new(uart0, 115200).finalize(());
 
     // ---------- ETHERNET ----------
 
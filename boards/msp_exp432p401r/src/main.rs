@@ -11,8 +11,6 @@
 use components::gpio::GpioComponent;
 use kernel::capabilities;
 use kernel::component::Component;
-use kernel::dynamic_deferred_call::DynamicDeferredCall;
-use kernel::dynamic_deferred_call::DynamicDeferredCallClientState;
 use kernel::hil::gpio::Configure;
 use kernel::platform::{KernelResources, SyscallDriverLookup};
 use kernel::scheduler::round_robin::RoundRobinSched;
@@ -59,7 +57,12 @@ struct MspExp432P401R {
     >,
     ipc: kernel::ipc::IPC<{ NUM_PROCS as u8 }>,
     adc: &'static capsules::adc::AdcDedicated<'static, msp432::adc::Adc<'static>>,
-    wdt: &'static msp432::wdt::Wdt,
+    process_watchdog: &'static capsules::process_watchdog::ProcessWatchdog<
+        'static,
+        capsules::virtual_alarm::VirtualMuxAlarm<'static, msp432::timer::TimerA<'static>>,
+        msp432::wdt::Wdt,
+        components::process_watchdog::Capability,
+    >,
     scheduler: &'static RoundRobinSched<'static>,
     systick: cortexm4::systick::SysTick,
 }
@@ -72,7 +75,12 @@ impl KernelResources<msp432::chip::Msp432<'static, msp432::chip::Msp432DefaultPe
     type ProcessFault = ();
     type Scheduler = RoundRobinSched<'static>;
     type SchedulerTimer = cortexm4::systick::SysTick;
-    type WatchDog = msp432::wdt::Wdt;
+    type WatchDog = capsules::process_watchdog::ProcessWatchdog<
+        'static,
+        capsules::virtual_alarm::VirtualMuxAlarm<'static, msp432::timer::TimerA<'static>>,
+        msp432::wdt::Wdt,
+        components::process_watchdog::Capability,
+    >;
     type ContextSwitchCallback = ();
 
     fn syscall_driver_lookup(&self) -> &Self::SyscallDriverLookup {
@@ -91,7 +99,7 @@ impl KernelResources<msp432::chip::Msp432<'static, msp432::chip::Msp432DefaultPe
         &self.systick
     }
     fn watchdog(&self) -> &Self::WatchDog {
-        &self.wdt
+        self.process_watchdog
     }
     fn context_switch_callback(&self) -> &Self::ContextSwitchCallback {
         &()
@@ -112,6 +120,7 @@ impl SyscallDriverLookup for MspExp432P401R {
             capsules::alarm::DRIVER_NUM => f(Some(self.alarm)),
             kernel::ipc::DRIVER_NUM => f(Some(&self.ipc)),
             capsules::adc::DRIVER_NUM => f(Some(self.adc)),
+            capsules::process_watchdog::DRIVER_NUM => f(Some(self.process_watchdog)),
             _ => f(None),
         }
     }
@@ -324,21 +333,9 @@ pub unsafe fn main() {
     let main_loop_capability = create_capability!(capabilities::MainLoopCapability);
     let process_management_capability =
         create_capability!(capabilities::ProcessManagementCapability);
-    let dynamic_deferred_call_clients =
-        static_init!([DynamicDeferredCallClientState; 1], Default::default());
-    let dynamic_deferred_caller = static_init!(
-        DynamicDeferredCall,
-        DynamicDeferredCall::new(dynamic_deferred_call_clients)
-    );
-    DynamicDeferredCall::set_global_instance(dynamic_deferred_caller);
-
     // Setup UART0
-    let uart_mux = components::console::UartMuxComponent::new(
-        &peripherals.uart0,
-        115200,
-        dynamic_deferred_caller,
-    )
-    .finalize(());
+    let uart_mux =
+        components::console::UartMuxComponent::new(&peripherals.uart0, 115200).finalize(());
 
     // Setup the console.
     let console = components::console::ConsoleComponent::new(
@@ -362,6 +359,20 @@ pub unsafe fn main() {
     )
     .finalize(components::alarm_component_helper!(msp432::timer::TimerA));
 
+    // Setup the process watchdog. A process that misses its heartbeat stops
+    // the hardware watchdog from being tickled, which resets the board.
+    let process_watchdog = components::process_watchdog::ProcessWatchdogComponent::new(
+        board_kernel,
+        capsules::process_watchdog::DRIVER_NUM,
+        mux_alarm,
+        &peripherals.wdt,
+        capsules::process_watchdog::ExpiryAction::ResetBoard,
+    )
+    .finalize(components::process_watchdog_component_helper!(
+        msp432::timer::TimerA,
+        msp432::wdt::Wdt
+    ));
+
     // Setup ADC
 
     setup_adc_pins(&peripherals.gpio);
@@ -440,7 +451,7 @@ pub unsafe fn main() {
         adc: adc,
         scheduler,
         systick: cortexm4::systick::SysTick::new_with_calibration(48_000_000),
-        wdt: &peripherals.wdt,
+        process_watchdog,
     };
 
     debug!("Initialization complete. Entering main loop");
@@ -115,8 +115,8 @@ impl<'a> Component for NrfClockComponent<'a> {
     type StaticInput = ();
     type Output = ();
     unsafe fn finalize(self, _s: Self::StaticInput) -> Self::Output {
-        // Start all of the clocks. Low power operation will require a better
-        // approach than this.
+        // Start all of the clocks. The chip stops the HFXO in its deepest
+        // sleep state, unless a driver's power constraint rules it out.
         self.clock.low_stop();
         self.clock.high_stop();
 
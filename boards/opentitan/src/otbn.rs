@@ -5,12 +5,6 @@
 //! ```rust
 //!     let _mux_otbn = crate::otbn::AccelMuxComponent::new(&peripherals.otbn)
 //!         .finalize(otbn_mux_component_helper!());
-//!
-//!     peripherals.otbn.initialise(
-//!         dynamic_deferred_caller
-//!             .register(&peripherals.otbn)
-//!             .unwrap(), // Unwrap fail = dynamic deferred caller out of slots
-//!     );
 //! ```
 
 use core::mem::MaybeUninit;
@@ -18,8 +18,6 @@ use components::bme280::Bme280Component;
 use components::ccs811::Ccs811Component;
 use kernel::capabilities;
 use kernel::component::Component;
-use kernel::dynamic_deferred_call::DynamicDeferredCall;
-use kernel::dynamic_deferred_call::DynamicDeferredCallClientState;
 use kernel::hil::i2c::I2CMaster;
 use kernel::hil::led::LedHigh;
 use kernel::hil::time::Counter;
@@ -172,14 +170,6 @@ unsafe fn setup() -> (
     let process_mgmt_cap = create_capability!(capabilities::ProcessManagementCapability);
     let memory_allocation_cap = create_capability!(capabilities::MemoryAllocationCapability);
 
-    let dynamic_deferred_call_clients =
-        static_init!([DynamicDeferredCallClientState; 3], Default::default());
-    let dynamic_deferred_caller = static_init!(
-        DynamicDeferredCall,
-        DynamicDeferredCall::new(dynamic_deferred_call_clients)
-    );
-    DynamicDeferredCall::set_global_instance(dynamic_deferred_caller);
-
     let board_kernel = static_init!(kernel::Kernel, kernel::Kernel::new(&PROCESSES));
 
     // Power up components
@@ -203,12 +193,8 @@ unsafe fn setup() -> (
     );
 
     // Create a shared UART channel for the console and for kernel debug.
-    let uart_mux = components::console::UartMuxComponent::new(
-        &peripherals.uart0,
-        115200,
-        dynamic_deferred_caller,
-    )
-    .finalize(());
+    let uart_mux =
+        components::console::UartMuxComponent::new(&peripherals.uart0, 115200).finalize(());
 
     // Setup the console.
     let console = components::console::ConsoleComponent::new(
@@ -274,9 +260,8 @@ unsafe fn setup() -> (
     let _ = &peripherals.iom2.set_master_client(i2c_master);
     let _ = &peripherals.iom2.enable();
 
-    let mux_i2c =
-        components::i2c::I2CMuxComponent::new(&peripherals.iom2, None, dynamic_deferred_caller)
-            .finalize(components::i2c_mux_component_helper!());
+    let mux_i2c = components::i2c::I2CMuxComponent::new(&peripherals.iom2, None)
+        .finalize(components::i2c_mux_component_helper!());
 
     let bme280 =
         Bme280Component::new(mux_i2c, 0x77).finalize(components::bme280_component_helper!());
@@ -294,8 +279,8 @@ unsafe fn setup() -> (
     .finalize(());
     BME280 = Some(bme280);
 
-    let ccs811 = Ccs811Component::new(mux_i2c, 0x5B, dynamic_deferred_caller)
-        .finalize(components::ccs811_component_helper!());
+    let ccs811 =
+        Ccs811Component::new(mux_i2c, 0x5B).finalize(components::ccs811_component_helper!());
     let air_quality = components::air_quality::AirQualityComponent::new(
         board_kernel,
         capsules::temperature::DRIVER_NUM,
@@ -0,0 +1,37 @@
+//! Credential checkers that decide whether processes may run.
+//!
+//! These implement the kernel's `AppCredentialsChecker` policy on top of the
+//! `digest` and `public_key_crypto` HILs. A board passes one of them to the
+//! `ProcessCheckerMachine` it uses with `load_and_check_processes()`.
+
+use core::num::NonZeroU32;
+
+use kernel::process::ShortID;
+use tock_tbf::types::{TbfFooterV2Credentials, TbfFooterV2CredentialsType};
+
+pub mod sha256;
+pub mod signature;
+
+/// Length in bytes of an RSA-3072 public key (and of its signatures).
+pub const RSA3072_KEY_LEN: usize = 3072 / 8;
+/// Length in bytes of an RSA-4096 public key (and of its signatures).
+pub const RSA4096_KEY_LEN: usize = 4096 / 8;
+
+/// Computes a `ShortID` from the first four bytes of the hash or signature in
+/// the credentials. Hashes and signatures are uniformly distributed, so their
+/// leading bytes make a good identifier for a given application binary. RSA
+/// credentials start with the public key, which is the same for every
+/// application signed with it, so the signature that follows it is used.
+fn short_id_from_credentials(credentials: &TbfFooterV2Credentials) -> ShortID {
+    let start = match credentials.format() {
+        TbfFooterV2CredentialsType::Rsa3072Key => RSA3072_KEY_LEN,
+        TbfFooterV2CredentialsType::Rsa4096Key => RSA4096_KEY_LEN,
+        _ => 0,
+    };
+    let id = credentials
+        .data()
+        .get(start..start + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map_or(0, u32::from_be_bytes);
+    NonZeroU32::new(id).map_or(ShortID::LocallyUnique, ShortID::Fixed)
+}
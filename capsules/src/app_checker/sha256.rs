@@ -0,0 +1,161 @@
+//! Credential checker that accepts processes whose SHA-256 credentials
+//! match the hash of their TBF header and binary.
+//!
+//! A SHA-256 credential only shows that the process was not corrupted or
+//! modified after it was built, not who built it. Boards that must only run
+//! trusted applications should use the signature checker instead.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let checker = static_init!(
+//!     capsules::app_checker::sha256::AppCheckerSha256<
+//!         'static,
+//!         capsules::sha256::Sha256Software<'static>,
+//!     >,
+//!     capsules::app_checker::sha256::AppCheckerSha256::new(
+//!         sha,
+//!         &mut capsules::app_checker::sha256::SHA256_CHECKER_BUF
+//!     )
+//! );
+//! digest::Digest::set_client(sha, checker);
+//! checker_machine.set_policy(checker, checker);
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::digest::{ClientData, ClientHash, ClientVerify, DigestDataVerify, Sha256};
+use kernel::process::{
+    AppCredentialsChecker, CheckResult, Compress, CredentialsCheckingClient, ShortID,
+};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::utilities::leasable_buffer::{LeasableBuffer, LeasableMutableBuffer};
+use kernel::ErrorCode;
+use tock_tbf::types::{TbfFooterV2Credentials, TbfFooterV2CredentialsType};
+
+/// Buffer holding the expected hash while it is compared.
+pub static mut SHA256_CHECKER_BUF: [u8; 32] = [0; 32];
+
+pub struct AppCheckerSha256<'a, H: DigestDataVerify<'a, 32> + Sha256> {
+    hasher: &'a H,
+    client: OptionalCell<&'static dyn CredentialsCheckingClient<'static>>,
+    hash: TakeCell<'static, [u8; 32]>,
+    /// The credentials and binary being checked.
+    credentials: OptionalCell<TbfFooterV2Credentials>,
+    binary: Cell<Option<&'static [u8]>>,
+}
+
+impl<'a, H: DigestDataVerify<'a, 32> + Sha256> AppCheckerSha256<'a, H> {
+    pub fn new(hasher: &'a H, buffer: &'static mut [u8; 32]) -> AppCheckerSha256<'a, H> {
+        AppCheckerSha256 {
+            hasher,
+            client: OptionalCell::empty(),
+            hash: TakeCell::new(buffer),
+            credentials: OptionalCell::empty(),
+            binary: Cell::new(None),
+        }
+    }
+
+    /// Reports the result of the current check to the client.
+    fn check_done(&self, result: Result<CheckResult, ErrorCode>) {
+        let credentials = self.credentials.take();
+        let binary = self.binary.take();
+        if let (Some(credentials), Some(binary)) = (credentials, binary) {
+            self.client.map(|client| {
+                client.check_done(result, credentials, binary);
+            });
+        }
+    }
+}
+
+impl<'a, H: DigestDataVerify<'a, 32> + Sha256> AppCredentialsChecker<'static>
+    for AppCheckerSha256<'a, H>
+{
+    fn set_client(&self, client: &'static dyn CredentialsCheckingClient<'static>) {
+        self.client.replace(client);
+    }
+
+    fn require_credentials(&self) -> bool {
+        true
+    }
+
+    fn check_credentials(
+        &self,
+        credentials: TbfFooterV2Credentials,
+        binary: &'static [u8],
+    ) -> Result<(), (ErrorCode, TbfFooterV2Credentials, &'static [u8])> {
+        if credentials.format() != TbfFooterV2CredentialsType::SHA256 {
+            return Err((ErrorCode::NOSUPPORT, credentials, binary));
+        }
+        if self.credentials.is_some() || self.hash.is_none() {
+            return Err((ErrorCode::BUSY, credentials, binary));
+        }
+
+        self.hasher.clear_data();
+        if let Err(e) = self.hasher.set_mode_sha256() {
+            return Err((e, credentials, binary));
+        }
+        if let Err((e, _)) = self.hasher.add_data(LeasableBuffer::new(binary)) {
+            return Err((e, credentials, binary));
+        }
+        self.credentials.set(credentials);
+        self.binary.set(Some(binary));
+        Ok(())
+    }
+}
+
+impl<'a, H: DigestDataVerify<'a, 32> + Sha256> Compress for AppCheckerSha256<'a, H> {
+    fn to_short_id(&self, credentials: &TbfFooterV2Credentials) -> ShortID {
+        super::short_id_from_credentials(credentials)
+    }
+}
+
+impl<'a, H: DigestDataVerify<'a, 32> + Sha256> ClientData<32> for AppCheckerSha256<'a, H> {
+    fn add_data_done(&self, result: Result<(), ErrorCode>, _data: LeasableBuffer<'static, u8>) {
+        if let Err(e) = result {
+            self.check_done(Err(e));
+            return;
+        }
+
+        // Compare the hash of the binary with the one in the credentials.
+        let hash = match self.hash.take() {
+            Some(hash) => hash,
+            None => {
+                self.check_done(Err(ErrorCode::FAIL));
+                return;
+            }
+        };
+        self.credentials.map(|credentials| {
+            hash.copy_from_slice(credentials.data());
+        });
+        if let Err((e, hash)) = self.hasher.verify(hash) {
+            self.hash.replace(hash);
+            self.check_done(Err(e));
+        }
+    }
+
+    fn add_mut_data_done(
+        &self,
+        _result: Result<(), ErrorCode>,
+        _data: LeasableMutableBuffer<'static, u8>,
+    ) {
+    }
+}
+
+impl<'a, H: DigestDataVerify<'a, 32> + Sha256> ClientHash<32> for AppCheckerSha256<'a, H> {
+    fn hash_done(&self, _result: Result<(), ErrorCode>, _digest: &'static mut [u8; 32]) {}
+}
+
+impl<'a, H: DigestDataVerify<'a, 32> + Sha256> ClientVerify<32> for AppCheckerSha256<'a, H> {
+    fn verification_done(&self, result: Result<bool, ErrorCode>, compare: &'static mut [u8; 32]) {
+        self.hash.replace(compare);
+        self.check_done(result.map(|matches| {
+            if matches {
+                CheckResult::Accept
+            } else {
+                CheckResult::Reject
+            }
+        }));
+    }
+}
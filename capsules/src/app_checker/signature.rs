@@ -0,0 +1,252 @@
+//! Credential checker that accepts processes whose credentials contain a
+//! valid signature of the SHA-256 hash of their TBF header and binary.
+//!
+//! The checker supports a single credentials format, for example
+//! `EcdsaNistP256`, whose signatures are `SL` bytes long. The public key the
+//! signatures are verified with is configured in the verifier. Credentials
+//! in any other format are passed on, so a board can require that every
+//! process is signed with its key.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! let checker = static_init!(
+//!     capsules::app_checker::signature::AppCheckerSignature<
+//!         'static,
+//!         EcdsaP256Verifier<'static>,
+//!         capsules::sha256::Sha256Software<'static>,
+//!         64,
+//!     >,
+//!     capsules::app_checker::signature::AppCheckerSignature::new(
+//!         sha,
+//!         verifier,
+//!         tock_tbf::types::TbfFooterV2CredentialsType::EcdsaNistP256,
+//!         &mut HASH_BUF,
+//!         &mut SIGNATURE_BUF,
+//!     )
+//! );
+//! digest::Digest::set_client(sha, checker);
+//! verifier.set_verify_client(checker);
+//! checker_machine.set_policy(checker, checker);
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::digest::{ClientData, ClientHash, ClientVerify, DigestDataHash, Sha256};
+use kernel::hil::public_key_crypto::signature;
+use kernel::process::{
+    AppCredentialsChecker, CheckResult, Compress, CredentialsCheckingClient, ShortID,
+};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::utilities::leasable_buffer::{LeasableBuffer, LeasableMutableBuffer};
+use kernel::ErrorCode;
+use tock_tbf::types::{TbfFooterV2Credentials, TbfFooterV2CredentialsType};
+
+pub struct AppCheckerSignature<
+    'a,
+    S: signature::SignatureVerify<'a, 32, SL>,
+    H: DigestDataHash<'a, 32> + Sha256,
+    const SL: usize,
+> {
+    hasher: &'a H,
+    verifier: &'a S,
+    credentials_type: TbfFooterV2CredentialsType,
+    client: OptionalCell<&'static dyn CredentialsCheckingClient<'static>>,
+    hash: TakeCell<'static, [u8; 32]>,
+    signature: TakeCell<'static, [u8; SL]>,
+    /// The credentials and binary being checked.
+    credentials: OptionalCell<TbfFooterV2Credentials>,
+    binary: Cell<Option<&'static [u8]>>,
+}
+
+impl<
+        'a,
+        S: signature::SignatureVerify<'a, 32, SL>,
+        H: DigestDataHash<'a, 32> + Sha256,
+        const SL: usize,
+    > AppCheckerSignature<'a, S, H, SL>
+{
+    pub fn new(
+        hasher: &'a H,
+        verifier: &'a S,
+        credentials_type: TbfFooterV2CredentialsType,
+        hash_buffer: &'static mut [u8; 32],
+        signature_buffer: &'static mut [u8; SL],
+    ) -> AppCheckerSignature<'a, S, H, SL> {
+        AppCheckerSignature {
+            hasher,
+            verifier,
+            credentials_type,
+            client: OptionalCell::empty(),
+            hash: TakeCell::new(hash_buffer),
+            signature: TakeCell::new(signature_buffer),
+            credentials: OptionalCell::empty(),
+            binary: Cell::new(None),
+        }
+    }
+
+    /// Reports the result of the current check to the client.
+    fn check_done(&self, result: Result<CheckResult, ErrorCode>) {
+        let credentials = self.credentials.take();
+        let binary = self.binary.take();
+        if let (Some(credentials), Some(binary)) = (credentials, binary) {
+            self.client.map(|client| {
+                client.check_done(result, credentials, binary);
+            });
+        }
+    }
+}
+
+impl<
+        'a,
+        S: signature::SignatureVerify<'a, 32, SL>,
+        H: DigestDataHash<'a, 32> + Sha256,
+        const SL: usize,
+    > AppCredentialsChecker<'static> for AppCheckerSignature<'a, S, H, SL>
+{
+    fn set_client(&self, client: &'static dyn CredentialsCheckingClient<'static>) {
+        self.client.replace(client);
+    }
+
+    fn require_credentials(&self) -> bool {
+        true
+    }
+
+    fn check_credentials(
+        &self,
+        credentials: TbfFooterV2Credentials,
+        binary: &'static [u8],
+    ) -> Result<(), (ErrorCode, TbfFooterV2Credentials, &'static [u8])> {
+        if credentials.format() != self.credentials_type || credentials.data().len() != SL {
+            return Err((ErrorCode::NOSUPPORT, credentials, binary));
+        }
+        if self.credentials.is_some() || self.hash.is_none() || self.signature.is_none() {
+            return Err((ErrorCode::BUSY, credentials, binary));
+        }
+
+        self.hasher.clear_data();
+        if let Err(e) = self.hasher.set_mode_sha256() {
+            return Err((e, credentials, binary));
+        }
+        if let Err((e, _)) = self.hasher.add_data(LeasableBuffer::new(binary)) {
+            return Err((e, credentials, binary));
+        }
+        self.credentials.set(credentials);
+        self.binary.set(Some(binary));
+        Ok(())
+    }
+}
+
+impl<
+        'a,
+        S: signature::SignatureVerify<'a, 32, SL>,
+        H: DigestDataHash<'a, 32> + Sha256,
+        const SL: usize,
+    > Compress for AppCheckerSignature<'a, S, H, SL>
+{
+    fn to_short_id(&self, credentials: &TbfFooterV2Credentials) -> ShortID {
+        super::short_id_from_credentials(credentials)
+    }
+}
+
+impl<
+        'a,
+        S: signature::SignatureVerify<'a, 32, SL>,
+        H: DigestDataHash<'a, 32> + Sha256,
+        const SL: usize,
+    > ClientData<32> for AppCheckerSignature<'a, S, H, SL>
+{
+    fn add_data_done(&self, result: Result<(), ErrorCode>, _data: LeasableBuffer<'static, u8>) {
+        if let Err(e) = result {
+            self.check_done(Err(e));
+            return;
+        }
+
+        match self.hash.take() {
+            Some(hash) => {
+                if let Err((e, hash)) = self.hasher.run(hash) {
+                    self.hash.replace(hash);
+                    self.check_done(Err(e));
+                }
+            }
+            None => self.check_done(Err(ErrorCode::FAIL)),
+        }
+    }
+
+    fn add_mut_data_done(
+        &self,
+        _result: Result<(), ErrorCode>,
+        _data: LeasableMutableBuffer<'static, u8>,
+    ) {
+    }
+}
+
+impl<
+        'a,
+        S: signature::SignatureVerify<'a, 32, SL>,
+        H: DigestDataHash<'a, 32> + Sha256,
+        const SL: usize,
+    > ClientHash<32> for AppCheckerSignature<'a, S, H, SL>
+{
+    fn hash_done(&self, result: Result<(), ErrorCode>, digest: &'static mut [u8; 32]) {
+        if let Err(e) = result {
+            self.hash.replace(digest);
+            self.check_done(Err(e));
+            return;
+        }
+
+        let signature = match self.signature.take() {
+            Some(signature) => signature,
+            None => {
+                self.hash.replace(digest);
+                self.check_done(Err(ErrorCode::FAIL));
+                return;
+            }
+        };
+        self.credentials.map(|credentials| {
+            signature.copy_from_slice(credentials.data());
+        });
+        if let Err((e, digest, signature)) = self.verifier.verify(digest, signature) {
+            self.hash.replace(digest);
+            self.signature.replace(signature);
+            self.check_done(Err(e));
+        }
+    }
+}
+
+impl<
+        'a,
+        S: signature::SignatureVerify<'a, 32, SL>,
+        H: DigestDataHash<'a, 32> + Sha256,
+        const SL: usize,
+    > ClientVerify<32> for AppCheckerSignature<'a, S, H, SL>
+{
+    fn verification_done(&self, _result: Result<bool, ErrorCode>, _compare: &'static mut [u8; 32]) {
+    }
+}
+
+impl<
+        'a,
+        S: signature::SignatureVerify<'a, 32, SL>,
+        H: DigestDataHash<'a, 32> + Sha256,
+        const SL: usize,
+    > signature::ClientVerify<32, SL> for AppCheckerSignature<'a, S, H, SL>
+{
+    fn verification_done(
+        &self,
+        result: Result<bool, ErrorCode>,
+        hash: &'static mut [u8; 32],
+        signature: &'static mut [u8; SL],
+    ) {
+        self.hash.replace(hash);
+        self.signature.replace(signature);
+        self.check_done(result.map(|valid| {
+            if valid {
+                CheckResult::Accept
+            } else {
+                CheckResult::Reject
+            }
+        }));
+    }
+}
@@ -0,0 +1,343 @@
+//! Syscall driver that lets a process install new apps at runtime.
+//!
+//! A loader process receives a TBF object, for example over the console or
+//! USB, and passes it to this driver in chunks. The driver reserves app flash
+//! for the object, writes the chunks to it and, once the whole object has
+//! been written, asks the kernel to create a process from it. The process
+//! starts once the kernel approves its credentials. No reboot is needed, and
+//! the new app is found again by the kernel at boot.
+//!
+//! Installing apps is privileged: only processes whose `ShortID` is in the
+//! allow list the board passes to the driver can use it. Other processes,
+//! including every process with a `LocallyUnique` identifier, see the driver
+//! as absent.
+//!
+//! Only one process can load an app at a time. If loading fails or is
+//! abandoned, a padding TBF header is written over the reserved flash, so
+//! the partially written app is skipped when the kernel loads apps at boot.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! # use kernel::static_init;
+//!
+//! pub static mut APP_LOADER_BUFFER: [u8; 512] = [0; 512];
+//! // The identifiers of the apps allowed to install apps.
+//! pub static APP_LOADER_IDS: [ShortID; 1] =
+//!     [ShortID::Fixed(unsafe { NonZeroU32::new_unchecked(0x1b2c3d4e) })];
+//! let app_loader = static_init!(
+//!     capsules::app_loader::AppLoader<'static>,
+//!     capsules::app_loader::AppLoader::new(
+//!         nv_to_page,
+//!         dynamic_process_loader,
+//!         board_kernel.create_grant(capsules::app_loader::DRIVER_NUM, &grant_cap),
+//!         &mut APP_LOADER_BUFFER,
+//!         &APP_LOADER_IDS,
+//!     )
+//! );
+//! nv_to_page.set_client(app_loader);
+//! ```
+
+use core::cell::Cell;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil;
+use kernel::process::{DynamicProcessLoading, ShortID};
+use kernel::processbuffer::ReadableProcessBuffer;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::{ErrorCode, ProcessId};
+
+/// Syscall driver number.
+use crate::driver;
+pub const DRIVER_NUM: usize = driver::NUM::AppLoader as usize;
+
+/// Ids for read-only allow buffers
+mod ro_allow {
+    pub const DATA: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Ids for subscribe upcalls
+mod upcall {
+    pub const WRITE_DONE: usize = 0;
+    /// The number of subscribe upcalls the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+pub struct AppLoader<'a> {
+    storage: &'a dyn hil::nonvolatile_storage::NonvolatileStorage<'static>,
+    loader: &'a dyn DynamicProcessLoading,
+    apps: Grant<
+        (),
+        UpcallCount<{ upcall::COUNT }>,
+        AllowRoCount<{ ro_allow::COUNT }>,
+        AllowRwCount<0>,
+    >,
+    /// The process that is loading an app.
+    current_app: OptionalCell<ProcessId>,
+    /// Address and length of the flash reserved for the new app.
+    flash_address: Cell<usize>,
+    flash_length: Cell<usize>,
+    /// Holds the chunk being written. `None` while a write is in progress.
+    buffer: TakeCell<'static, [u8]>,
+    /// A padding header is being written over the reserved flash.
+    discarding: Cell<bool>,
+    /// The applications that may install apps.
+    allowed_ids: &'a [ShortID],
+}
+
+/// Length of a TBF header without any TLVs.
+const TBF_BASE_HEADER_LEN: usize = 16;
+
+/// Fills `buffer` with a TBF header for `size` bytes of padding.
+fn padding_header(buffer: &mut [u8], size: usize) {
+    // Version 2, no TLVs, not enabled.
+    buffer[0..2].copy_from_slice(&2u16.to_le_bytes());
+    buffer[2..4].copy_from_slice(&(TBF_BASE_HEADER_LEN as u16).to_le_bytes());
+    buffer[4..8].copy_from_slice(&(size as u32).to_le_bytes());
+    buffer[8..12].copy_from_slice(&0u32.to_le_bytes());
+    let checksum =
+        tock_tbf::parse::compute_tbf_header_checksum(&buffer[0..TBF_BASE_HEADER_LEN]).unwrap_or(0);
+    buffer[12..16].copy_from_slice(&checksum.to_le_bytes());
+}
+
+impl<'a> AppLoader<'a> {
+    pub fn new(
+        storage: &'a dyn hil::nonvolatile_storage::NonvolatileStorage<'static>,
+        loader: &'a dyn DynamicProcessLoading,
+        grant: Grant<
+            (),
+            UpcallCount<{ upcall::COUNT }>,
+            AllowRoCount<{ ro_allow::COUNT }>,
+            AllowRwCount<0>,
+        >,
+        buffer: &'static mut [u8],
+        allowed_ids: &'a [ShortID],
+    ) -> AppLoader<'a> {
+        AppLoader {
+            storage,
+            loader,
+            apps: grant,
+            current_app: OptionalCell::empty(),
+            flash_address: Cell::new(0),
+            flash_length: Cell::new(0),
+            buffer: TakeCell::new(buffer),
+            discarding: Cell::new(false),
+            allowed_ids,
+        }
+    }
+
+    /// Checks whether `appid` runs an application that may install apps.
+    fn allowed(&self, appid: ProcessId) -> bool {
+        let id = appid.short_app_id();
+        id != ShortID::LocallyUnique && self.allowed_ids.contains(&id)
+    }
+
+    /// Checks whether `appid` may start loading an app. A load started by a
+    /// process that has since stopped is abandoned.
+    fn can_start(&self, appid: ProcessId) -> Result<(), ErrorCode> {
+        if self.discarding.get() {
+            return Err(ErrorCode::BUSY);
+        }
+        match self.current_app.extract() {
+            None => Ok(()),
+            Some(current) if current == appid => Err(ErrorCode::ALREADY),
+            Some(current) => {
+                if self.buffer.is_some() && self.apps.enter(current, |_, _| {}).is_err() {
+                    self.loader.cancel_reservation();
+                    self.current_app.clear();
+                    Ok(())
+                } else {
+                    Err(ErrorCode::BUSY)
+                }
+            }
+        }
+    }
+
+    /// Checks that `appid` is loading an app and no write is in progress.
+    fn check_owner(&self, appid: ProcessId) -> Result<(), ErrorCode> {
+        if !self.current_app.contains(&appid) {
+            Err(ErrorCode::RESERVE)
+        } else if self.buffer.is_none() {
+            Err(ErrorCode::BUSY)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn setup(&self, size: usize, appid: ProcessId) -> Result<(), ErrorCode> {
+        self.can_start(appid)?;
+        let address = self.loader.reserve_app_flash(size)?;
+        self.flash_address.set(address);
+        self.flash_length.set(size);
+        self.current_app.set(appid);
+        Ok(())
+    }
+
+    fn write(&self, offset: usize, length: usize, appid: ProcessId) -> Result<(), ErrorCode> {
+        self.check_owner(appid)?;
+        // The sums are checked, as a wrapped offset would pass the bounds
+        // check and point anywhere in flash.
+        let out_of_bounds = offset
+            .checked_add(length)
+            .map_or(true, |end| end > self.flash_length.get());
+        if length == 0 || out_of_bounds {
+            return Err(ErrorCode::INVAL);
+        }
+        let address = self
+            .flash_address
+            .get()
+            .checked_add(offset)
+            .ok_or(ErrorCode::INVAL)?;
+
+        self.apps
+            .enter(appid, |_, kernel_data| {
+                kernel_data
+                    .get_readonly_processbuffer(ro_allow::DATA)
+                    .and_then(|data| {
+                        data.enter(|data| {
+                            if length > data.len() {
+                                return Err(ErrorCode::SIZE);
+                            }
+                            self.buffer.take().map_or(Err(ErrorCode::BUSY), |buffer| {
+                                if length > buffer.len() {
+                                    self.buffer.replace(buffer);
+                                    return Err(ErrorCode::SIZE);
+                                }
+                                data[0..length].copy_to_slice(&mut buffer[0..length]);
+                                self.storage.write(buffer, address, length)
+                            })
+                        })
+                    })
+                    .unwrap_or(Err(ErrorCode::RESERVE))
+            })
+            .unwrap_or_else(|err| Err(err.into()))
+    }
+
+    fn load(&self, appid: ProcessId) -> Result<ProcessId, ErrorCode> {
+        self.check_owner(appid)?;
+        let result = self.loader.load_app();
+        match result {
+            Ok(_) => self.current_app.clear(),
+            Err(_) => self.discard(),
+        }
+        result
+    }
+
+    fn abort(&self, appid: ProcessId) -> Result<(), ErrorCode> {
+        self.check_owner(appid)?;
+        self.loader.cancel_reservation();
+        self.discard();
+        Ok(())
+    }
+
+    /// Writes a padding header over the reserved flash, so that the kernel
+    /// skips whatever was written there. The current load ends once the
+    /// header is written.
+    fn discard(&self) {
+        let length = TBF_BASE_HEADER_LEN;
+        let result = self.buffer.take().map_or(Err(ErrorCode::BUSY), |buffer| {
+            if buffer.len() < length {
+                self.buffer.replace(buffer);
+                return Err(ErrorCode::SIZE);
+            }
+            padding_header(&mut buffer[0..length], self.flash_length.get());
+            self.storage.write(buffer, self.flash_address.get(), length)
+        });
+        match result {
+            Ok(()) => self.discarding.set(true),
+            Err(_) => self.current_app.clear(),
+        }
+    }
+}
+
+impl hil::nonvolatile_storage::NonvolatileStorageClient<'static> for AppLoader<'_> {
+    fn read_done(&self, _buffer: &'static mut [u8], _length: usize) {}
+
+    fn write_done(&self, buffer: &'static mut [u8], length: usize) {
+        self.buffer.replace(buffer);
+
+        if self.discarding.take() {
+            self.current_app.clear();
+            return;
+        }
+
+        self.current_app.map(|appid| {
+            let _ = self.apps.enter(*appid, |_, upcalls| {
+                upcalls
+                    .schedule_upcall(upcall::WRITE_DONE, (0, length, 0))
+                    .ok();
+            });
+        });
+    }
+}
+
+impl SyscallDriver for AppLoader<'_> {
+    /// Load new apps.
+    ///
+    /// Processes whose `ShortID` is not allowed to install apps get `NODEVICE`
+    /// for every command.
+    ///
+    /// ### `command_num`
+    ///
+    /// - `0`: Driver check.
+    /// - `1`: Reserve flash for a new app of `arg1` bytes.
+    /// - `2`: Write `arg2` bytes from the allowed buffer at offset `arg1` of
+    ///        the new app.
+    /// - `3`: Load the new app, which starts once its credentials are
+    ///        approved. Returns the identifier of the new process.
+    /// - `4`: Abandon loading the new app.
+    fn command(
+        &self,
+        command_num: usize,
+        arg1: usize,
+        arg2: usize,
+        appid: ProcessId,
+    ) -> CommandReturn {
+        if !self.allowed(appid) {
+            return CommandReturn::failure(ErrorCode::NODEVICE);
+        }
+
+        match command_num {
+            0 => CommandReturn::success(),
+
+            1 => self.setup(arg1, appid).into(),
+
+            2 => self.write(arg1, arg2, appid).into(),
+
+            3 => match self.load(appid) {
+                Ok(processid) => CommandReturn::success_u32(processid.id() as u32),
+                Err(e) => CommandReturn::failure(e),
+            },
+
+            4 => self.abort(appid).into(),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn padding_header_is_valid() {
+        let mut buffer = [0xFF; TBF_BASE_HEADER_LEN];
+        padding_header(&mut buffer, 0x2000);
+
+        // Version 2, 16 byte header, 0x2000 bytes long.
+        assert_eq!(buffer[0..8], [2, 0, 16, 0, 0, 0x20, 0, 0]);
+        // Flags: not enabled.
+        assert_eq!(buffer[8..12], [0, 0, 0, 0]);
+        let checksum = tock_tbf::parse::compute_tbf_header_checksum(&buffer).unwrap();
+        assert_eq!(buffer[12..16], checksum.to_le_bytes());
+    }
+}
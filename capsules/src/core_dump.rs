@@ -0,0 +1,159 @@
+//! Exports the core dumps of faulted processes.
+//!
+//! The kernel takes a core dump with `kernel::process::CoreDump` when a
+//! process faults. This capsule copies each dump, in the format described in
+//! `kernel/src/process_core_dump.rs`, either to a UART, such as the console
+//! or a SEGGER RTT channel, or to a region of nonvolatile storage where it
+//! survives a reset. Once a dump has been exported it is discarded, so that
+//! the next fault can be dumped. The dump is turned into an ELF core file on
+//! the host with `tools/core-dump`.
+//!
+//! A dump stored in nonvolatile storage starts at the given address and
+//! overwrites the previous dump. It can be read from the flash of the board,
+//! for example with `tockloader read`.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! # use kernel::static_init;
+//!
+//! let core_dump = static_init!(
+//!     kernel::process::CoreDump,
+//!     kernel::process::CoreDump::new(&FAULT_RESPONSE, &mut CORE_DUMP_BUF)
+//! );
+//! let core_dump_writer = static_init!(
+//!     capsules::core_dump::CoreDumpWriter<'static>,
+//!     capsules::core_dump::CoreDumpWriter::new(
+//!         core_dump,
+//!         capsules::core_dump::DumpOutput::Uart(core_dump_uart),
+//!         &mut capsules::core_dump::BUF,
+//!     )
+//! );
+//! core_dump.set_client(core_dump_writer);
+//! core_dump_uart.set_transmit_client(core_dump_writer);
+//! ```
+
+use core::cell::Cell;
+
+use kernel::hil::nonvolatile_storage::{NonvolatileStorage, NonvolatileStorageClient};
+use kernel::hil::uart;
+use kernel::process::{CoreDumpClient, ProcessCoreDump};
+use kernel::utilities::cells::TakeCell;
+use kernel::ErrorCode;
+
+/// Default buffer for copying the dump in chunks.
+pub static mut BUF: [u8; 256] = [0; 256];
+
+/// Where core dumps are written.
+#[derive(Copy, Clone)]
+pub enum DumpOutput<'a> {
+    /// A UART, shared with the console or used only for the dumps.
+    Uart(&'a dyn uart::Transmit<'a>),
+    /// Nonvolatile storage, starting at the given address.
+    Storage(&'a dyn NonvolatileStorage<'static>, usize),
+}
+
+pub struct CoreDumpWriter<'a> {
+    core_dump: &'a dyn ProcessCoreDump,
+    output: DumpOutput<'a>,
+    buffer: TakeCell<'static, [u8]>,
+    /// How much of the dump has been written.
+    offset: Cell<usize>,
+}
+
+impl<'a> CoreDumpWriter<'a> {
+    pub fn new(
+        core_dump: &'a dyn ProcessCoreDump,
+        output: DumpOutput<'a>,
+        buffer: &'static mut [u8],
+    ) -> CoreDumpWriter<'a> {
+        CoreDumpWriter {
+            core_dump,
+            output,
+            buffer: TakeCell::new(buffer),
+            offset: Cell::new(0),
+        }
+    }
+
+    /// Write the next chunk of the dump, or discard the dump once all of it
+    /// has been written.
+    fn write_next(&self) {
+        let offset = self.offset.get();
+        if offset >= self.core_dump.dump_len() {
+            self.finish();
+            return;
+        }
+
+        match self.buffer.take() {
+            Some(buffer) => {
+                let len = self.core_dump.read_dump(offset, buffer);
+                let result = match self.output {
+                    DumpOutput::Uart(uart) => {
+                        uart.transmit_buffer(buffer, len).map_err(|(e, buffer)| {
+                            self.buffer.replace(buffer);
+                            e
+                        })
+                    }
+                    DumpOutput::Storage(storage, address) => {
+                        storage.write(buffer, address + offset, len)
+                    }
+                };
+                if result.is_err() {
+                    // Give up on this dump rather than blocking the next one.
+                    self.finish();
+                }
+            }
+            None => {
+                // The storage did not return the buffer after an error, so
+                // dumps cannot be written anymore.
+                self.finish();
+            }
+        }
+    }
+
+    /// Called when a chunk of `length` bytes has been written.
+    fn chunk_done(&self, buffer: &'static mut [u8], length: usize, rcode: Result<(), ErrorCode>) {
+        self.buffer.replace(buffer);
+        match rcode {
+            Ok(()) if length > 0 => {
+                self.offset.set(self.offset.get() + length);
+                self.write_next();
+            }
+            _ => self.finish(),
+        }
+    }
+
+    fn finish(&self) {
+        self.offset.set(0);
+        self.core_dump.clear_dump();
+    }
+}
+
+impl<'a> CoreDumpClient for CoreDumpWriter<'a> {
+    fn core_dump_ready(&self, _length: usize) {
+        // A dump is only taken once the previous one has been discarded, so
+        // no chunk is in flight.
+        self.offset.set(0);
+        self.write_next();
+    }
+}
+
+impl<'a> uart::TransmitClient for CoreDumpWriter<'a> {
+    fn transmitted_buffer(
+        &self,
+        buffer: &'static mut [u8],
+        tx_len: usize,
+        rcode: Result<(), ErrorCode>,
+    ) {
+        self.chunk_done(buffer, tx_len, rcode);
+    }
+}
+
+impl<'a> NonvolatileStorageClient<'static> for CoreDumpWriter<'a> {
+    fn read_done(&self, _buffer: &'static mut [u8], _length: usize) {}
+
+    fn write_done(&self, buffer: &'static mut [u8], length: usize) {
+        self.chunk_done(buffer, length, Ok(()));
+    }
+}
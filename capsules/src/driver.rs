@@ -21,6 +21,9 @@ pub enum NUM {
 
     // Kernel
     Ipc                   = 0x10000,
+    AppLoader             = 0x10001,
+    MessageIpc            = 0x10002,
+    SharedMemory          = 0x10003,
 
     // HW Buses
     Spi                   = 0x20001,
@@ -33,6 +36,8 @@ pub enum NUM {
     BleAdvertising        = 0x30000,
     Ieee802154            = 0x30001,
     Udp                   = 0x30002,
+    Tcp                   = 0x30003,
+    Thread                = 0x30004,
 
     // Cryptography
     Rng                   = 0x40001,
@@ -79,5 +84,6 @@ pub enum NUM {
     Touch                 = 0x90002,
     TextScreen            = 0x90003,
     SevenSegment          = 0x90004,
+    ProcessWatchdog       = 0x90005,
 }
 }
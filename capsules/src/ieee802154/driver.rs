@@ -1,28 +1,25 @@
 //! IEEE 802.15.4 userspace interface for configuration and transmit/receive.
 //!
 //! Implements a userspace interface for sending and receiving IEEE 802.15.4
-//! frames. Also provides a minimal list-based interface for managing keys and
-//! known link neighbors, which is needed for 802.15.4 security.
+//! frames. Also lets userspace provision the keys and known link neighbors
+//! needed for 802.15.4 security, which are kept in a `SecurityTable`.
 
-use crate::ieee802154::{device, framer};
+use crate::ieee802154::device;
+use crate::ieee802154::security_table::{
+    KeyDescriptor, KeyUsage, SecurityTable, MAX_DEVICES, MAX_KEYS,
+};
 use crate::net::ieee802154::{AddressMode, Header, KeyId, MacAddress, PanID, SecurityLevel};
 use crate::net::stream::{decode_bytes, decode_u8, encode_bytes, encode_u8, SResult};
 
-use core::cell::Cell;
 use core::cmp::min;
 
-use kernel::dynamic_deferred_call::{
-    DeferredCallHandle, DynamicDeferredCall, DynamicDeferredCallClient,
-};
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
 use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
 use kernel::processbuffer::{ReadableProcessBuffer, WriteableProcessBuffer};
 use kernel::syscall::{CommandReturn, SyscallDriver};
-use kernel::utilities::cells::{MapCell, OptionalCell, TakeCell};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::{ErrorCode, ProcessId};
 
-const MAX_NEIGHBORS: usize = 4;
-const MAX_KEYS: usize = 4;
-
 /// Ids for read-only allow buffers
 mod ro_allow {
     pub const WRITE: usize = 0;
@@ -41,21 +38,6 @@ mod rw_allow {
 use crate::driver;
 pub const DRIVER_NUM: usize = driver::NUM::Ieee802154 as usize;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
-struct DeviceDescriptor {
-    short_addr: u16,
-    long_addr: [u8; 8],
-}
-
-impl Default for DeviceDescriptor {
-    fn default() -> Self {
-        DeviceDescriptor {
-            short_addr: 0,
-            long_addr: [0; 8],
-        }
-    }
-}
-
 /// The Key ID mode mapping expected by the userland driver
 #[repr(u8)]
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -132,39 +114,29 @@ impl From<&KeyId> for KeyIdModeUserland {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
-struct KeyDescriptor {
-    level: SecurityLevel,
-    key_id: KeyId,
-    key: [u8; 16],
-}
-
-impl Default for KeyDescriptor {
-    fn default() -> Self {
+/// Decodes a key descriptor in the format produced by the userland driver:
+/// the security level, the key ID, the key and optionally the key usage.
+fn decode_key_descriptor(buf: &[u8]) -> SResult<KeyDescriptor> {
+    stream_len_cond!(buf, 27);
+    let level = stream_from_option!(SecurityLevel::from_scf(buf[0]));
+    let (_, key_id) = dec_try!(buf, 1; decode_key_id);
+    let mut key = [0u8; 16];
+    let off = dec_consume!(buf, 11; decode_bytes, &mut key);
+    let (off, usage) = if buf.len() > off {
+        let (off, usage) = dec_try!(buf, off; decode_u8);
+        (off, KeyUsage(usage))
+    } else {
+        (off, KeyUsage::default())
+    };
+    stream_done!(
+        off,
         KeyDescriptor {
-            level: SecurityLevel::None,
-            key_id: KeyId::Implicit,
-            key: [0; 16],
+            level: level,
+            key_id: key_id,
+            key: key,
+            usage: usage,
         }
-    }
-}
-
-impl KeyDescriptor {
-    fn decode(buf: &[u8]) -> SResult<KeyDescriptor> {
-        stream_len_cond!(buf, 27);
-        let level = stream_from_option!(SecurityLevel::from_scf(buf[0]));
-        let (_, key_id) = dec_try!(buf, 1; decode_key_id);
-        let mut key = [0u8; 16];
-        let off = dec_consume!(buf, 11; decode_bytes, &mut key);
-        stream_done!(
-            off,
-            KeyDescriptor {
-                level: level,
-                key_id: key_id,
-                key: key,
-            }
-        );
-    }
+    );
 }
 
 #[derive(Default)]
@@ -176,17 +148,9 @@ pub struct RadioDriver<'a> {
     /// Underlying MAC device, possibly multiplexed
     mac: &'a dyn device::MacDevice<'a>,
 
-    /// List of (short address, long address) pairs representing IEEE 802.15.4
-    /// neighbors.
-    neighbors: MapCell<[DeviceDescriptor; MAX_NEIGHBORS]>,
-    /// Actual number of neighbors in the fixed size array of neighbors.
-    num_neighbors: Cell<usize>,
-
-    /// List of (security level, key_id, key) tuples representing IEEE 802.15.4
-    /// key descriptors.
-    keys: MapCell<[KeyDescriptor; MAX_KEYS]>,
-    /// Actual number of keys in the fixed size array of keys.
-    num_keys: Cell<usize>,
+    /// IEEE 802.15.4 key descriptors and neighbors, shared with the MAC
+    /// device.
+    security: &'a SecurityTable<'a>,
 
     /// Grant of apps that use this radio driver.
     apps: Grant<
@@ -202,10 +166,7 @@ pub struct RadioDriver<'a> {
     kernel_tx: TakeCell<'static, [u8]>,
 
     /// Used to ensure callbacks are delivered during upcalls
-    deferred_caller: &'a DynamicDeferredCall,
-
-    /// Also used for deferred calls
-    handle: OptionalCell<DeferredCallHandle>,
+    deferred_call: DeferredCall,
 
     /// Used to deliver callbacks to the correct app during deferred calls
     saved_appid: OptionalCell<ProcessId>,
@@ -217,6 +178,7 @@ pub struct RadioDriver<'a> {
 impl<'a> RadioDriver<'a> {
     pub fn new(
         mac: &'a dyn device::MacDevice<'a>,
+        security: &'a SecurityTable<'a>,
         grant: Grant<
             App,
             UpcallCount<2>,
@@ -224,135 +186,16 @@ impl<'a> RadioDriver<'a> {
             AllowRwCount<{ rw_allow::COUNT }>,
         >,
         kernel_tx: &'static mut [u8],
-        deferred_caller: &'a DynamicDeferredCall,
     ) -> RadioDriver<'a> {
         RadioDriver {
             mac,
-            neighbors: MapCell::new(Default::default()),
-            num_neighbors: Cell::new(0),
-            keys: MapCell::new(Default::default()),
-            num_keys: Cell::new(0),
+            security,
             apps: grant,
             current_app: OptionalCell::empty(),
             kernel_tx: TakeCell::new(kernel_tx),
-            deferred_caller,
             saved_appid: OptionalCell::empty(),
             saved_result: OptionalCell::empty(),
-            handle: OptionalCell::empty(),
-        }
-    }
-
-    pub fn initialize_callback_handle(&self, handle: DeferredCallHandle) {
-        self.handle.replace(handle);
-    }
-
-    // Neighbor management functions
-
-    /// Add a new neighbor to the end of the list if there is still space
-    /// for one, returning its new index. If the neighbor already exists,
-    /// returns the index of the existing neighbor. Returns `None` if there is
-    /// no remaining space.
-    fn add_neighbor(&self, new_neighbor: DeviceDescriptor) -> Option<usize> {
-        self.neighbors.and_then(|neighbors| {
-            let num_neighbors = self.num_neighbors.get();
-            let position = neighbors[..num_neighbors]
-                .iter()
-                .position(|neighbor| *neighbor == new_neighbor);
-            match position {
-                Some(index) => Some(index),
-                None => {
-                    if num_neighbors == MAX_NEIGHBORS {
-                        None
-                    } else {
-                        neighbors[num_neighbors] = new_neighbor;
-                        self.num_neighbors.set(num_neighbors + 1);
-                        Some(num_neighbors)
-                    }
-                }
-            }
-        })
-    }
-
-    /// Deletes the neighbor at `index` if `index` is valid, returning
-    /// `Ok()`. Otherwise, returns `Err(ErrorCode::INVAL)`.  Ensures
-    /// that the `neighbors` list is compact by shifting forward any elements
-    /// after the index.
-    fn remove_neighbor(&self, index: usize) -> Result<(), ErrorCode> {
-        let num_neighbors = self.num_neighbors.get();
-        if index < num_neighbors {
-            self.neighbors.map(|neighbors| {
-                for i in index..(num_neighbors - 1) {
-                    neighbors[i] = neighbors[i + 1];
-                }
-            });
-            self.num_neighbors.set(num_neighbors - 1);
-            Ok(())
-        } else {
-            Err(ErrorCode::INVAL)
-        }
-    }
-
-    /// Gets the `DeviceDescriptor` corresponding to the neighbor at a
-    /// particular `index`, if the `index` is valid. Otherwise, returns `None`
-    fn get_neighbor(&self, index: usize) -> Option<DeviceDescriptor> {
-        if index < self.num_neighbors.get() {
-            self.neighbors.map(|neighbors| neighbors[index])
-        } else {
-            None
-        }
-    }
-
-    // Key management functions
-
-    /// Add a new key to the end of the list if there is still space
-    /// for one, returning its new index. If the key already exists,
-    /// returns the index of the existing key. Returns `None` if there
-    /// is no remaining space.
-    fn add_key(&self, new_key: KeyDescriptor) -> Option<usize> {
-        self.keys.and_then(|keys| {
-            let num_keys = self.num_keys.get();
-            let position = keys[..num_keys].iter().position(|key| *key == new_key);
-            match position {
-                Some(index) => Some(index),
-                None => {
-                    if num_keys == MAX_KEYS {
-                        None
-                    } else {
-                        keys[num_keys] = new_key;
-                        self.num_keys.set(num_keys + 1);
-                        Some(num_keys)
-                    }
-                }
-            }
-        })
-    }
-
-    /// Deletes the key at `index` if `index` is valid, returning
-    /// `Ok(())`. Otherwise, returns `Err(ErrorCode::INVAL)`.  Ensures
-    /// that the `keys` list is compact by shifting forward any elements
-    /// after the index.
-    fn remove_key(&self, index: usize) -> Result<(), ErrorCode> {
-        let num_keys = self.num_keys.get();
-        if index < num_keys {
-            self.keys.map(|keys| {
-                for i in index..(num_keys - 1) {
-                    keys[i] = keys[i + 1];
-                }
-            });
-            self.num_keys.set(num_keys - 1);
-            Ok(())
-        } else {
-            Err(ErrorCode::INVAL)
-        }
-    }
-
-    /// Gets the `DeviceDescriptor` corresponding to the key at a
-    /// particular `index`, if the `index` is valid. Otherwise, returns `None`
-    fn get_key(&self, index: usize) -> Option<KeyDescriptor> {
-        if index < self.num_keys.get() {
-            self.keys.map(|keys| keys[index])
-        } else {
-            None
+            deferred_call: DeferredCall::new(),
         }
     }
 
@@ -387,7 +230,7 @@ impl<'a> RadioDriver<'a> {
         if result != Ok(()) {
             self.saved_appid.set(appid);
             self.saved_result.set(result);
-            self.handle.map(|handle| self.deferred_caller.set(*handle));
+            self.deferred_call.set();
         }
     }
 
@@ -474,8 +317,8 @@ impl<'a> RadioDriver<'a> {
     }
 }
 
-impl DynamicDeferredCallClient for RadioDriver<'_> {
-    fn call(&self, _handle: DeferredCallHandle) {
+impl DeferredCallClient for RadioDriver<'_> {
+    fn handle_deferred_call(&self) {
         let _ = self
             .apps
             .enter(self.saved_appid.unwrap_or_panic(), |_app, upcalls| {
@@ -494,35 +337,9 @@ impl DynamicDeferredCallClient for RadioDriver<'_> {
                     .ok();
             });
     }
-}
-
-impl framer::DeviceProcedure for RadioDriver<'_> {
-    /// Gets the long address corresponding to the neighbor that matches the given
-    /// MAC address. If no such neighbor exists, returns `None`.
-    fn lookup_addr_long(&self, addr: MacAddress) -> Option<[u8; 8]> {
-        self.neighbors.and_then(|neighbors| {
-            neighbors[..self.num_neighbors.get()]
-                .iter()
-                .find(|neighbor| match addr {
-                    MacAddress::Short(addr) => addr == neighbor.short_addr,
-                    MacAddress::Long(addr) => addr == neighbor.long_addr,
-                })
-                .map(|neighbor| neighbor.long_addr)
-        })
-    }
-}
 
-impl framer::KeyProcedure for RadioDriver<'_> {
-    /// Gets the key corresponding to the key that matches the given security
-    /// level `level` and key ID `key_id`. If no such key matches, returns
-    /// `None`.
-    fn lookup_key(&self, level: SecurityLevel, key_id: KeyId) -> Option<[u8; 16]> {
-        self.keys.and_then(|keys| {
-            keys[..self.num_keys.get()]
-                .iter()
-                .find(|key| key.level == level && key.key_id == key_id)
-                .map(|key| key.key)
-        })
+    fn register(&'static self) {
+        self.deferred_call.register(self);
     }
 }
 
@@ -592,12 +409,25 @@ impl SyscallDriver for RadioDriver<'_> {
     ///                       up to 9 bytes: the key ID.
     /// - `23`: Get the key at an index.
     ///        app_cfg (out): 16 bytes: the key.
-    /// - `24`: Add a new key with the given description.
+    /// - `24`: Add a new key with the given description, or replace the key
+    ///        with the same security level and key ID.
     ///        app_cfg (in): 1 byte: the security level +
     ///                      1 byte: the key ID mode +
     ///                      9 bytes: the key ID (might not use all bytes) +
-    ///                      16 bytes: the key.
+    ///                      16 bytes: the key +
+    ///                      optionally 1 byte: the key usage, with the bit
+    ///                      `1 << frame_type` set for each frame type the
+    ///                      key may secure. By default, data and MAC
+    ///                      command frames.
     /// - `25`: Remove the key at an index.
+    /// - `26`: Transmit a frame to the short address `arg1`.
+    ///        app_cfg (in): 1 byte: the security level +
+    ///                      10 bytes: the key ID mode and key ID.
+    /// - `27`: Get the key usage of the key at an index.
+    ///
+    /// Neighbors and keys are saved to nonvolatile storage if the board
+    /// provides it, and commands 17, 18, 24 and 25 return BUSY until they
+    /// have been read back after a reboot.
     fn command(
         &self,
         command_number: usize,
@@ -682,14 +512,15 @@ impl SyscallDriver for RadioDriver<'_> {
             12 => CommandReturn::failure(ErrorCode::NOSUPPORT),
             13 => {
                 // Guarantee that it is positive by adding 1
-                CommandReturn::success_u32(MAX_NEIGHBORS as u32 + 1)
+                CommandReturn::success_u32(MAX_DEVICES as u32 + 1)
             }
             14 => {
                 // Guarantee that it is positive by adding 1
-                CommandReturn::success_u32(self.num_neighbors.get() as u32 + 1)
+                CommandReturn::success_u32(self.security.num_devices() as u32 + 1)
             }
             15 => self
-                .get_neighbor(arg1)
+                .security
+                .get_device(arg1)
                 .map_or(CommandReturn::failure(ErrorCode::INVAL), |neighbor| {
                     CommandReturn::success_u32(neighbor.short_addr as u32 + 1)
                 }),
@@ -703,7 +534,7 @@ impl SyscallDriver for RadioDriver<'_> {
                                 if cfg.len() != 8 {
                                     return CommandReturn::failure(ErrorCode::SIZE);
                                 }
-                                self.get_neighbor(arg1).map_or(
+                                self.security.get_device(arg1).map_or(
                                     CommandReturn::failure(ErrorCode::INVAL),
                                     |neighbor| {
                                         cfg.copy_from_slice(&neighbor.long_addr);
@@ -725,21 +556,19 @@ impl SyscallDriver for RadioDriver<'_> {
                                 if cfg.len() != 8 {
                                     return CommandReturn::failure(ErrorCode::SIZE);
                                 }
-                                let mut new_neighbor: DeviceDescriptor =
-                                    DeviceDescriptor::default();
-                                new_neighbor.short_addr = arg1 as u16;
-                                cfg.copy_to_slice(&mut new_neighbor.long_addr);
-                                self.add_neighbor(new_neighbor)
-                                    .map_or(CommandReturn::failure(ErrorCode::INVAL), |index| {
-                                        CommandReturn::success_u32(index as u32 + 1)
-                                    })
+                                let mut long_addr = [0u8; 8];
+                                cfg.copy_to_slice(&mut long_addr);
+                                match self.security.add_device(arg1 as u16, long_addr) {
+                                    Ok(index) => CommandReturn::success_u32(index as u32 + 1),
+                                    Err(e) => CommandReturn::failure(e),
+                                }
                             })
                         })
                         .unwrap_or(CommandReturn::failure(ErrorCode::INVAL))
                 })
                 .unwrap_or_else(|err| CommandReturn::failure(err.into())),
 
-            18 => match self.remove_neighbor(arg1) {
+            18 => match self.security.remove_device(arg1) {
                 Ok(_) => CommandReturn::success(),
                 Err(e) => CommandReturn::failure(e),
             },
@@ -749,9 +578,10 @@ impl SyscallDriver for RadioDriver<'_> {
             }
             20 => {
                 // Guarantee that it is positive by adding 1
-                CommandReturn::success_u32(self.num_keys.get() as u32 + 1)
+                CommandReturn::success_u32(self.security.num_keys() as u32 + 1)
             }
             21 => self
+                .security
                 .get_key(arg1)
                 .map_or(CommandReturn::failure(ErrorCode::INVAL), |key| {
                     CommandReturn::success_u32(key.level as u32 + 1)
@@ -769,6 +599,7 @@ impl SyscallDriver for RadioDriver<'_> {
 
                                 let mut tmp_cfg: [u8; 10] = [0; 10];
                                 let res = self
+                                    .security
                                     .get_key(arg1)
                                     .and_then(|key| encode_key_id(&key.key_id, &mut tmp_cfg).done())
                                     .map_or(CommandReturn::failure(ErrorCode::INVAL), |_| {
@@ -792,7 +623,7 @@ impl SyscallDriver for RadioDriver<'_> {
                                 if cfg.len() != 16 {
                                     return CommandReturn::failure(ErrorCode::SIZE);
                                 }
-                                self.get_key(arg1).map_or(
+                                self.security.get_key(arg1).map_or(
                                     CommandReturn::failure(ErrorCode::INVAL),
                                     |key| {
                                         cfg.copy_from_slice(&key.key);
@@ -811,29 +642,30 @@ impl SyscallDriver for RadioDriver<'_> {
                         .get_readwrite_processbuffer(rw_allow::CFG)
                         .and_then(|cfg| {
                             cfg.mut_enter(|cfg| {
-                                if cfg.len() != 27 {
+                                if cfg.len() != 27 && cfg.len() != 28 {
                                     return CommandReturn::failure(ErrorCode::SIZE);
                                 }
 
-                                // The cfg userspace buffer is exactly 27
-                                // bytes long, copy it into a proper slice
-                                // for decoding
-                                let mut tmp_cfg: [u8; 27] = [0; 27];
-                                cfg.copy_to_slice(&mut tmp_cfg);
-
-                                KeyDescriptor::decode(&tmp_cfg)
-                                    .done()
-                                    .and_then(|(_, new_key)| self.add_key(new_key))
-                                    .map_or(CommandReturn::failure(ErrorCode::INVAL), |index| {
-                                        CommandReturn::success_u32(index as u32 + 1)
-                                    })
+                                // The cfg userspace buffer is 27 bytes long,
+                                // or 28 with the key usage, copy it into a
+                                // proper slice for decoding
+                                let mut tmp_cfg: [u8; 28] = [0; 28];
+                                cfg.copy_to_slice(&mut tmp_cfg[..cfg.len()]);
+
+                                match decode_key_descriptor(&tmp_cfg[..cfg.len()]).done() {
+                                    Some((_, new_key)) => match self.security.add_key(new_key) {
+                                        Ok(index) => CommandReturn::success_u32(index as u32 + 1),
+                                        Err(e) => CommandReturn::failure(e),
+                                    },
+                                    None => CommandReturn::failure(ErrorCode::INVAL),
+                                }
                             })
                         })
                         .unwrap_or(CommandReturn::failure(ErrorCode::INVAL))
                 })
                 .unwrap_or_else(|err| CommandReturn::failure(err.into())),
 
-            25 => self.remove_key(arg1).into(),
+            25 => self.security.remove_key(arg1).into(),
             26 => {
                 self.apps
                     .enter(appid, |app, kernel_data| {
@@ -886,6 +718,13 @@ impl SyscallDriver for RadioDriver<'_> {
                         },
                     )
             }
+            27 => self.security.get_key(arg1).map_or(
+                CommandReturn::failure(ErrorCode::INVAL),
+                |key| {
+                    // Guarantee that it is positive by adding 1
+                    CommandReturn::success_u32(key.usage.0 as u32 + 1)
+                },
+            ),
             _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
         }
     }
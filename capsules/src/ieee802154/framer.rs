@@ -56,11 +56,14 @@
 //! ```rust
 //! # use kernel::static_init;
 //!
+//! let security_table = static_init!(
+//!     capsules::ieee802154::security_table::SecurityTable<'static>,
+//!     capsules::ieee802154::security_table::SecurityTable::new(None, &mut SECURITY_TABLE_BUF));
 //! let radio_capsule = static_init!(
 //!     capsules::ieee802154::RadioDriver<'static>,
-//!     capsules::ieee802154::RadioDriver::new(mac_device, board_kernel.create_grant(&grant_cap), &mut RADIO_BUF));
-//! mac_device.set_key_procedure(radio_capsule);
-//! mac_device.set_device_procedure(radio_capsule);
+//!     capsules::ieee802154::RadioDriver::new(mac_device, security_table, board_kernel.create_grant(&grant_cap), &mut RADIO_BUF));
+//! mac_device.set_key_procedure(security_table);
+//! mac_device.set_device_procedure(security_table);
 //! mac_device.set_transmit_client(radio_capsule);
 //! mac_device.set_receive_client(radio_capsule);
 //! ```
@@ -119,6 +122,9 @@ struct FrameInfo {
 
     // Security level, key, and nonce
     security_params: Option<(SecurityLevel, [u8; 16], [u8; 13])>,
+    // For received frames, the extended address of the device that secured
+    // the frame and its frame counter
+    device_frame_counter: Option<([u8; 8], u32)>,
 }
 
 impl Frame {
@@ -189,8 +195,9 @@ impl FrameInfo {
                 unimplemented!()
             }
             FrameType::MACCommand => {
-                // Beginning of MAC command content field
-                unimplemented!()
+                // Beginning of MAC command content field, after the command
+                // identifier
+                self.mac_payload_offset + 1
             }
             _ => {
                 // MAC payload field, which includes payload IEs
@@ -210,7 +217,7 @@ impl FrameInfo {
             // m data is the private payload field
             (
                 private_payload_offset,
-                self.unsecured_length() | private_payload_offset,
+                self.unsecured_length() - private_payload_offset,
             )
         }
     }
@@ -248,8 +255,20 @@ pub const CRYPT_BUF_SIZE: usize = radio::MAX_MTU + 3 * 16;
 /// implicitly with some equivalent logic.
 pub trait KeyProcedure {
     /// Lookup the KeyDescriptor matching the provided security level and key ID
-    /// mode and return the key associated with it.
-    fn lookup_key(&self, level: SecurityLevel, key_id: KeyId) -> Option<[u8; 16]>;
+    /// mode and return the key associated with it, if its key usage policy
+    /// (IEEE 802.15.4-2015, 9.2.4) allows it to secure frames of type
+    /// `frame_type`.
+    fn lookup_key(
+        &self,
+        frame_type: FrameType,
+        level: SecurityLevel,
+        key_id: KeyId,
+    ) -> Option<[u8; 16]>;
+
+    /// Return the frame counter for the next secured frame (macFrameCounter)
+    /// and increment it, or `None` if no frame counter can be used. A frame
+    /// counter must never be used twice with the same key.
+    fn next_frame_counter(&self) -> Option<u32>;
 }
 
 /// IEEE 802.15.4-2015, 9.2.5, DeviceDescriptor lookup procedure.
@@ -263,6 +282,17 @@ pub trait DeviceProcedure {
     /// address is already long, a long address should be returned only if the
     /// given address matches a known DeviceDescriptor.
     fn lookup_addr_long(&self, addr: MacAddress) -> Option<[u8; 8]>;
+
+    /// IEEE 802.15.4-2015, 9.2.6, incoming frame counter check procedure.
+    /// Returns true if `frame_counter` has not been used by the device with
+    /// extended address `addr` yet, that is, the frame is not a replay.
+    fn check_frame_counter(&self, addr: [u8; 8], frame_counter: u32) -> bool;
+
+    /// Record that a frame secured with `frame_counter` was received from the
+    /// device with extended address `addr`, so that frames with lower frame
+    /// counters from this device are no longer accepted. Only called once
+    /// the frame is authenticated.
+    fn update_frame_counter(&self, addr: [u8; 8], frame_counter: u32);
 }
 
 /// This state enum describes the state of the transmission pipeline.
@@ -362,9 +392,21 @@ impl<'a, M: Mac, A: AES128CCM<'a>> Framer<'a, M, A> {
 
     /// Look up the key using the IEEE 802.15.4 KeyDescriptor lookup procedure
     /// implemented elsewhere.
-    fn lookup_key(&self, level: SecurityLevel, key_id: KeyId) -> Option<[u8; 16]> {
+    fn lookup_key(
+        &self,
+        frame_type: FrameType,
+        level: SecurityLevel,
+        key_id: KeyId,
+    ) -> Option<[u8; 16]> {
         self.key_procedure
-            .and_then(|key_procedure| key_procedure.lookup_key(level, key_id))
+            .and_then(|key_procedure| key_procedure.lookup_key(frame_type, level, key_id))
+    }
+
+    /// Get the frame counter of the next secured frame from the key
+    /// procedure.
+    fn next_frame_counter(&self) -> Option<u32> {
+        self.key_procedure
+            .and_then(|key_procedure| key_procedure.next_frame_counter())
     }
 
     /// Look up the extended address of a device using the IEEE 802.15.4
@@ -376,6 +418,103 @@ impl<'a, M: Mac, A: AES128CCM<'a>> Framer<'a, M, A> {
         })
     }
 
+    /// Check the frame counter of a received frame using the IEEE 802.15.4
+    /// incoming frame counter check procedure implemented elsewhere.
+    fn check_frame_counter(&self, addr: [u8; 8], frame_counter: u32) -> bool {
+        self.device_procedure.map_or(false, |device_procedure| {
+            device_procedure.check_frame_counter(addr, frame_counter)
+        })
+    }
+
+    /// Record the frame counter of an authenticated frame using the IEEE
+    /// 802.15.4 DeviceDescriptor procedures implemented elsewhere.
+    fn update_frame_counter(&self, addr: [u8; 8], frame_counter: u32) {
+        self.device_procedure
+            .map(|device_procedure| device_procedure.update_frame_counter(addr, frame_counter));
+    }
+
+    /// Writes the header of a frame of type `frame_type` into `buf`, see
+    /// `MacDevice::prepare_data_frame`.
+    fn prepare_frame(
+        &self,
+        frame_type: FrameType,
+        buf: &'static mut [u8],
+        dst_pan: PanID,
+        dst_addr: MacAddress,
+        src_pan: PanID,
+        src_addr: MacAddress,
+        security_needed: Option<(SecurityLevel, KeyId)>,
+    ) -> Result<Frame, &'static mut [u8]> {
+        // IEEE 802.15.4-2015: 9.2.1, outgoing frame security
+        // Steps a-e of the security procedure are implemented here.
+
+        // TODO: For Thread, in the case of `KeyIdMode::Source4Index`, the source
+        // address should instead be some constant defined in their
+        // specification.
+        let src_addr_long = self.get_address_long();
+        let security_desc = security_needed.and_then(|(level, key_id)| {
+            self.lookup_key(frame_type, level, key_id).and_then(|key| {
+                self.next_frame_counter().map(|frame_counter| {
+                    let nonce = get_ccm_nonce(&src_addr_long, frame_counter, level);
+                    (
+                        Security {
+                            level: level,
+                            asn_in_nonce: false,
+                            frame_counter: Some(frame_counter),
+                            key_id: key_id,
+                        },
+                        key,
+                        nonce,
+                    )
+                })
+            })
+        });
+        if security_needed.is_some() && security_desc.is_none() {
+            // If security was requested, fail when desired key was not found
+            // or no frame counter is available.
+            return Err(buf);
+        }
+
+        // Construct MAC header
+        let security = security_desc.map(|(sec, _, _)| sec);
+        let mic_len = security.map_or(0, |sec| sec.level.mic_len());
+        let header = Header {
+            frame_type: frame_type,
+            /* TODO: determine this by looking at queue, and also set it in
+             * hardware so that ACKs set this flag to the right value. */
+            frame_pending: false,
+            // Unicast data and command frames request acknowledgement
+            ack_requested: true,
+            version: FrameVersion::V2006,
+            seq: Some(self.data_sequence.get()),
+            dst_pan: Some(dst_pan),
+            dst_addr: Some(dst_addr),
+            src_pan: Some(src_pan),
+            src_addr: Some(src_addr),
+            security: security,
+            header_ies: Default::default(),
+            header_ies_len: 0,
+            payload_ies: Default::default(),
+            payload_ies_len: 0,
+        };
+
+        match header.encode(&mut buf[radio::PSDU_OFFSET..], true).done() {
+            Some((data_offset, mac_payload_offset)) => Ok(Frame {
+                buf: buf,
+                info: FrameInfo {
+                    frame_type: frame_type,
+                    mac_payload_offset: mac_payload_offset,
+                    data_offset: data_offset,
+                    data_len: 0,
+                    mic_len: mic_len,
+                    security_params: security_desc.map(|(sec, key, nonce)| (sec.level, key, nonce)),
+                    device_frame_counter: None,
+                },
+            }),
+            None => Err(buf),
+        }
+    }
+
     /// IEEE 802.15.4-2015, 9.2.1, outgoing frame security procedure
     /// Performs the first checks in the security procedure. The rest of the
     /// steps are performed as part of the transmission pipeline.
@@ -426,8 +565,13 @@ impl<'a, M: Mac, A: AES128CCM<'a>> Framer<'a, M, A> {
                     if header.version == FrameVersion::V2003 {
                         None
                     } else {
-                        // Step e: Lookup the key.
-                        let key = match self.lookup_key(security.level, security.key_id) {
+                        // Step e: Lookup the key, which also checks the key
+                        // usage policy.
+                        let key = match self.lookup_key(
+                            header.frame_type,
+                            security.level,
+                            security.key_id,
+                        ) {
                             Some(key) => key,
                             None => {
                                 return None;
@@ -449,11 +593,12 @@ impl<'a, M: Mac, A: AES128CCM<'a>> Framer<'a, M, A> {
                         // Step g, h: Check frame counter
                         let frame_counter = match security.frame_counter {
                             Some(frame_counter) => {
-                                if frame_counter == 0xffffffff {
-                                    // Counter error
+                                if frame_counter == 0xffffffff
+                                    || !self.check_frame_counter(device_addr, frame_counter)
+                                {
+                                    // Counter error, or a replayed frame
                                     return None;
                                 }
-                                // TODO: Check frame counter against source device
                                 frame_counter
                             }
                             // TSCH mode, where ASN is used instead, not supported
@@ -472,6 +617,7 @@ impl<'a, M: Mac, A: AES128CCM<'a>> Framer<'a, M, A> {
                             data_len: data_len,
                             mic_len: mic_len,
                             security_params: Some((security.level, key, nonce)),
+                            device_frame_counter: Some((device_addr, frame_counter)),
                         })
                     }
                 } else {
@@ -588,7 +734,7 @@ impl<'a, M: Mac, A: AES128CCM<'a>> Framer<'a, M, A> {
                                     m_len,
                                     info.mic_len,
                                     level.encryption_needed(),
-                                    true,
+                                    false,
                                 );
                                 match res {
                                     Ok(()) => (RxState::Decrypting(info), None),
@@ -696,72 +842,35 @@ impl<'a, M: Mac, A: AES128CCM<'a>> MacDevice<'a> for Framer<'a, M, A> {
         src_addr: MacAddress,
         security_needed: Option<(SecurityLevel, KeyId)>,
     ) -> Result<Frame, &'static mut [u8]> {
-        // IEEE 802.15.4-2015: 9.2.1, outgoing frame security
-        // Steps a-e of the security procedure are implemented here.
-
-        // TODO: For Thread, in the case of `KeyIdMode::Source4Index`, the source
-        // address should instead be some constant defined in their
-        // specification.
-        let src_addr_long = self.get_address_long();
-        let security_desc = security_needed.and_then(|(level, key_id)| {
-            self.lookup_key(level, key_id).map(|key| {
-                // TODO: lookup frame counter for device
-                let frame_counter = 0;
-                let nonce = get_ccm_nonce(&src_addr_long, frame_counter, level);
-                (
-                    Security {
-                        level: level,
-                        asn_in_nonce: false,
-                        frame_counter: Some(frame_counter),
-                        key_id: key_id,
-                    },
-                    key,
-                    nonce,
-                )
-            })
-        });
-        if security_needed.is_some() && security_desc.is_none() {
-            // If security was requested, fail when desired key was not found.
-            return Err(buf);
-        }
-
-        // Construct MAC header
-        let security = security_desc.map(|(sec, _, _)| sec);
-        let mic_len = security.map_or(0, |sec| sec.level.mic_len());
-        let header = Header {
-            frame_type: FrameType::Data,
-            /* TODO: determine this by looking at queue, and also set it in
-             * hardware so that ACKs set this flag to the right value. */
-            frame_pending: false,
-            // Unicast data frames request acknowledgement
-            ack_requested: true,
-            version: FrameVersion::V2006,
-            seq: Some(self.data_sequence.get()),
-            dst_pan: Some(dst_pan),
-            dst_addr: Some(dst_addr),
-            src_pan: Some(src_pan),
-            src_addr: Some(src_addr),
-            security: security,
-            header_ies: Default::default(),
-            header_ies_len: 0,
-            payload_ies: Default::default(),
-            payload_ies_len: 0,
-        };
+        self.prepare_frame(
+            FrameType::Data,
+            buf,
+            dst_pan,
+            dst_addr,
+            src_pan,
+            src_addr,
+            security_needed,
+        )
+    }
 
-        match header.encode(&mut buf[radio::PSDU_OFFSET..], true).done() {
-            Some((data_offset, mac_payload_offset)) => Ok(Frame {
-                buf: buf,
-                info: FrameInfo {
-                    frame_type: FrameType::Data,
-                    mac_payload_offset: mac_payload_offset,
-                    data_offset: data_offset,
-                    data_len: 0,
-                    mic_len: mic_len,
-                    security_params: security_desc.map(|(sec, key, nonce)| (sec.level, key, nonce)),
-                },
-            }),
-            None => Err(buf),
-        }
+    fn prepare_command_frame(
+        &self,
+        buf: &'static mut [u8],
+        dst_pan: PanID,
+        dst_addr: MacAddress,
+        src_pan: PanID,
+        src_addr: MacAddress,
+        security_needed: Option<(SecurityLevel, KeyId)>,
+    ) -> Result<Frame, &'static mut [u8]> {
+        self.prepare_frame(
+            FrameType::MACCommand,
+            buf,
+            dst_pan,
+            dst_addr,
+            src_pan,
+            src_addr,
+            security_needed,
+        )
     }
 
     fn transmit(&self, frame: Frame) -> Result<(), (ErrorCode, &'static mut [u8])> {
@@ -892,7 +1001,12 @@ impl<'a, M: Mac, A: AES128CCM<'a>> CCMClient for Framer<'a, M, A> {
                 let buf = buf;
                 match state {
                     RxState::Decrypting(info) => {
-                        let next_state = if tag_is_valid {
+                        let next_state = if res == Ok(()) && tag_is_valid {
+                            // The frame is authentic, so its frame counter
+                            // can no longer be used by the sender.
+                            info.device_frame_counter.map(|(addr, frame_counter)| {
+                                self.update_frame_counter(addr, frame_counter)
+                            });
                             RxState::ReadyToYield(info, buf)
                         } else {
                             RxState::ReadyToReturn(buf)
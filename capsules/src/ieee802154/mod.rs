@@ -3,6 +3,7 @@
 pub mod device;
 pub mod framer;
 pub mod mac;
+pub mod security_table;
 pub mod virtual_mac;
 pub mod xmac;
 
@@ -0,0 +1,704 @@
+//! IEEE 802.15.4 key and device tables.
+//!
+//! Keeps the security attributes of the MAC PIB (IEEE 802.15.4-2015, 9.5)
+//! that the `Framer` needs to secure and unsecure frames:
+//!
+//! - the key table. A key is looked up by its security level and key ID, and
+//!   may only secure the frame types in its key usage list.
+//! - the device table, which maps the short address of each neighbor to its
+//!   extended address and records the lowest frame counter still accepted
+//!   from it. Frames with a lower frame counter are replays and are dropped.
+//! - the outgoing frame counter, so that a frame counter is never used twice.
+//!
+//! The tables are provisioned by userspace through the `RadioDriver`.
+//!
+//! If the board provides nonvolatile storage for the tables, they are written
+//! to it whenever they change and read back after a reboot. Writing the
+//! storage for every frame would wear it out, so outgoing frame counters are
+//! instead reserved in blocks of `FRAME_COUNTER_RESERVE`: the stored table
+//! records a bound above all counters used, and counting restarts from that
+//! bound after a reboot. Frames cannot be secured until the tables have been
+//! read, or if the reserved counters run out before the next block is stored.
+//! The frame counters of the neighbors are stored with the tables, and at
+//! least every `INCOMING_SAVE_INTERVAL` received frames, so frames received
+//! since the last write can be replayed after a reboot.
+//!
+//! The storage holds two copies of the tables, each with a CRC, which are
+//! written in turn so that a reset during a write leaves the previous copy
+//! intact. If neither copy is valid the tables start empty.
+//!
+//! Without storage, the tables are lost on reboot and the outgoing frame
+//! counter restarts from 0, so the keys provisioned again must be new keys.
+//!
+//! Other users of keys that survive reboots, such as Thread MLE, take their
+//! frame counters from the outgoing frame counter too. They use different
+//! keys than the MAC, so sharing the counter only makes it skip values.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! # use kernel::static_init;
+//!
+//! let security_table = static_init!(
+//!     capsules::ieee802154::security_table::SecurityTable<'static>,
+//!     capsules::ieee802154::security_table::SecurityTable::new(
+//!         Some((nonvolatile_storage, storage_address)),
+//!         &mut SECURITY_TABLE_BUF,
+//!     )
+//! );
+//! nonvolatile_storage.set_client(security_table);
+//! mac_device.set_key_procedure(security_table);
+//! mac_device.set_device_procedure(security_table);
+//! security_table.load();
+//! ```
+
+use crate::ieee802154::framer::{DeviceProcedure, KeyProcedure};
+use crate::net::ieee802154::{FrameType, KeyId, KeyIdMode, MacAddress, SecurityLevel};
+use crate::net::stream::{
+    decode_bytes, decode_u16, decode_u32, decode_u8, encode_bytes, encode_u16, encode_u32,
+    encode_u8, SResult,
+};
+
+use core::cell::Cell;
+
+use kernel::hil::nonvolatile_storage::{NonvolatileStorage, NonvolatileStorageClient};
+use kernel::utilities::cells::{MapCell, TakeCell};
+use kernel::ErrorCode;
+
+use tickv::crc32::Crc32;
+
+pub const MAX_KEYS: usize = 4;
+pub const MAX_DEVICES: usize = 4;
+
+/// Number of outgoing frame counters reserved each time the tables are
+/// stored.
+pub const FRAME_COUNTER_RESERVE: u32 = 1024;
+
+/// Maximum number of secured frames received between two writes of the
+/// tables.
+pub const INCOMING_SAVE_INTERVAL: u32 = 256;
+
+/// Identifies stored tables: "T154".
+const MAGIC: u32 = 0x54313534;
+
+/// Key ID field, long enough for `KeyId::Source8Index`.
+const KEY_ID_LEN: usize = 9;
+/// Security level, key ID mode, key ID, key and key usage.
+const KEY_LEN: usize = 1 + 1 + KEY_ID_LEN + 16 + 1;
+/// Short address, extended address and frame counter.
+const DEVICE_LEN: usize = 2 + 8 + 4;
+/// Magic, sequence number, reserved frame counter and table sizes.
+const HEADER_LEN: usize = 4 + 4 + 4 + 1 + 1;
+const CRC_LEN: usize = 4;
+
+/// Length of one stored copy of the tables.
+pub const TABLE_LEN: usize = HEADER_LEN + MAX_KEYS * KEY_LEN + MAX_DEVICES * DEVICE_LEN + CRC_LEN;
+
+/// Length of the buffer and of the storage region used by the tables, which
+/// holds two copies.
+pub const BUF_LEN: usize = 2 * TABLE_LEN;
+
+/// The frame types that a key may secure, with the bit `1 << frame_type` set
+/// for each allowed `FrameType` (IEEE 802.15.4-2015, 9.5, KeyUsageDescriptor).
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct KeyUsage(pub u8);
+
+impl KeyUsage {
+    pub fn allows(&self, frame_type: FrameType) -> bool {
+        self.0 & (1 << frame_type as u8) != 0
+    }
+}
+
+impl Default for KeyUsage {
+    /// Data and MAC command frames, the frame types that are secured by the
+    /// `Framer`.
+    fn default() -> Self {
+        KeyUsage(1 << FrameType::Data as u8 | 1 << FrameType::MACCommand as u8)
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct KeyDescriptor {
+    pub level: SecurityLevel,
+    pub key_id: KeyId,
+    pub key: [u8; 16],
+    pub usage: KeyUsage,
+}
+
+impl Default for KeyDescriptor {
+    fn default() -> Self {
+        KeyDescriptor {
+            level: SecurityLevel::None,
+            key_id: KeyId::Implicit,
+            key: [0; 16],
+            usage: KeyUsage::default(),
+        }
+    }
+}
+
+impl KeyDescriptor {
+    /// Encodes the key into exactly `KEY_LEN` bytes.
+    fn encode(&self, buf: &mut [u8]) -> SResult {
+        stream_len_cond!(buf, KEY_LEN);
+        let off = enc_consume!(buf; encode_u8, self.level as u8);
+        let off = enc_consume!(buf, off; encode_u8, KeyIdMode::from(&self.key_id) as u8);
+        for b in buf[off..off + KEY_ID_LEN].iter_mut() {
+            *b = 0;
+        }
+        enc_consume!(buf, off; self.key_id; encode);
+        let off = enc_consume!(buf, off + KEY_ID_LEN; encode_bytes, &self.key);
+        let off = enc_consume!(buf, off; encode_u8, self.usage.0);
+        stream_done!(off);
+    }
+
+    fn decode(buf: &[u8]) -> SResult<KeyDescriptor> {
+        stream_len_cond!(buf, KEY_LEN);
+        let level = stream_from_option!(SecurityLevel::from_scf(buf[0]));
+        let mode = stream_from_option!(KeyIdMode::from_scf(buf[1]));
+        let (_, key_id) = dec_try!(buf, 2; KeyId::decode, mode);
+        let mut key = [0u8; 16];
+        let off = dec_consume!(buf, 2 + KEY_ID_LEN; decode_bytes, &mut key);
+        let (off, usage) = dec_try!(buf, off; decode_u8);
+        stream_done!(
+            off,
+            KeyDescriptor {
+                level: level,
+                key_id: key_id,
+                key: key,
+                usage: KeyUsage(usage),
+            }
+        );
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct DeviceDescriptor {
+    pub short_addr: u16,
+    pub long_addr: [u8; 8],
+    /// The lowest frame counter accepted in the next secured frame from the
+    /// device.
+    pub frame_counter: u32,
+}
+
+impl Default for DeviceDescriptor {
+    fn default() -> Self {
+        DeviceDescriptor {
+            short_addr: 0,
+            long_addr: [0; 8],
+            frame_counter: 0,
+        }
+    }
+}
+
+impl DeviceDescriptor {
+    fn encode(&self, buf: &mut [u8]) -> SResult {
+        let off = enc_consume!(buf; encode_u16, self.short_addr);
+        let off = enc_consume!(buf, off; encode_bytes, &self.long_addr);
+        let off = enc_consume!(buf, off; encode_u32, self.frame_counter);
+        stream_done!(off);
+    }
+
+    fn decode(buf: &[u8]) -> SResult<DeviceDescriptor> {
+        let (off, short_addr) = dec_try!(buf; decode_u16);
+        let mut long_addr = [0u8; 8];
+        let off = dec_consume!(buf, off; decode_bytes, &mut long_addr);
+        let (off, frame_counter) = dec_try!(buf, off; decode_u32);
+        stream_done!(
+            off,
+            DeviceDescriptor {
+                short_addr: short_addr,
+                long_addr: long_addr,
+                frame_counter: frame_counter,
+            }
+        );
+    }
+}
+
+/// One copy of the tables in storage.
+struct StoredTables {
+    /// Incremented for each write, to find the latest copy.
+    sequence: u32,
+    /// Bound above all the outgoing frame counters used.
+    reserved_counter: u32,
+    num_keys: usize,
+    keys: [KeyDescriptor; MAX_KEYS],
+    num_devices: usize,
+    devices: [DeviceDescriptor; MAX_DEVICES],
+}
+
+impl StoredTables {
+    fn encode(&self, buf: &mut [u8]) -> SResult {
+        stream_len_cond!(buf, TABLE_LEN);
+        let mut off = enc_consume!(buf; encode_u32, MAGIC);
+        off = enc_consume!(buf, off; encode_u32, self.sequence);
+        off = enc_consume!(buf, off; encode_u32, self.reserved_counter);
+        off = enc_consume!(buf, off; encode_u8, self.num_keys as u8);
+        off = enc_consume!(buf, off; encode_u8, self.num_devices as u8);
+        for key in self.keys.iter() {
+            off = enc_consume!(buf, off; key; encode);
+        }
+        for device in self.devices.iter() {
+            off = enc_consume!(buf, off; device; encode);
+        }
+        let mut crc = Crc32::new();
+        crc.update(&buf[..off]);
+        off = enc_consume!(buf, off; encode_u32, crc.finalise());
+        stream_done!(off);
+    }
+
+    fn decode(buf: &[u8]) -> SResult<StoredTables> {
+        stream_len_cond!(buf, TABLE_LEN);
+        let mut crc = Crc32::new();
+        crc.update(&buf[..TABLE_LEN - CRC_LEN]);
+        let (_, stored_crc) = dec_try!(buf, TABLE_LEN - CRC_LEN; decode_u32);
+        stream_cond!(crc.finalise() == stored_crc);
+
+        let (off, magic) = dec_try!(buf; decode_u32);
+        stream_cond!(magic == MAGIC);
+        let (off, sequence) = dec_try!(buf, off; decode_u32);
+        let (off, reserved_counter) = dec_try!(buf, off; decode_u32);
+        let (off, num_keys) = dec_try!(buf, off; decode_u8);
+        let (_, num_devices) = dec_try!(buf, off; decode_u8);
+        stream_cond!(num_keys as usize <= MAX_KEYS && num_devices as usize <= MAX_DEVICES);
+
+        let mut keys = [KeyDescriptor::default(); MAX_KEYS];
+        for (i, key) in keys[..num_keys as usize].iter_mut().enumerate() {
+            let (_, decoded) = dec_try!(buf, HEADER_LEN + i * KEY_LEN; KeyDescriptor::decode);
+            *key = decoded;
+        }
+        let devices_off = HEADER_LEN + MAX_KEYS * KEY_LEN;
+        let mut devices = [DeviceDescriptor::default(); MAX_DEVICES];
+        for (i, device) in devices[..num_devices as usize].iter_mut().enumerate() {
+            let (_, decoded) =
+                dec_try!(buf, devices_off + i * DEVICE_LEN; DeviceDescriptor::decode);
+            *device = decoded;
+        }
+        stream_done!(
+            TABLE_LEN,
+            StoredTables {
+                sequence: sequence,
+                reserved_counter: reserved_counter,
+                num_keys: num_keys as usize,
+                keys: keys,
+                num_devices: num_devices as usize,
+                devices: devices,
+            }
+        );
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+enum State {
+    /// The tables have not been read from storage yet.
+    Loading,
+    Idle,
+    /// The tables are being written to storage.
+    Saving,
+}
+
+pub struct SecurityTable<'a> {
+    keys: MapCell<[KeyDescriptor; MAX_KEYS]>,
+    num_keys: Cell<usize>,
+    devices: MapCell<[DeviceDescriptor; MAX_DEVICES]>,
+    num_devices: Cell<usize>,
+
+    /// The frame counter of the next secured frame (macFrameCounter).
+    frame_counter: Cell<u32>,
+    /// Frame counters below this bound are reserved by the stored tables.
+    reserved_counter: Cell<u32>,
+    /// The bound reserved by the tables being written.
+    saving_counter: Cell<u32>,
+    /// Secured frames received since the tables were last written.
+    unsaved_frames: Cell<u32>,
+
+    /// Storage for the tables, and the address of the region used.
+    storage: Option<(&'a dyn NonvolatileStorage<'a>, usize)>,
+    buffer: TakeCell<'a, [u8]>,
+    state: Cell<State>,
+    /// Whether the tables changed while they were written.
+    save_pending: Cell<bool>,
+    /// Sequence number and slot of the latest stored copy.
+    sequence: Cell<u32>,
+    slot: Cell<usize>,
+}
+
+impl<'a> SecurityTable<'a> {
+    /// `buffer` must be at least `BUF_LEN` bytes long, as well as the region
+    /// of the storage starting at the given address.
+    pub fn new(
+        storage: Option<(&'a dyn NonvolatileStorage<'a>, usize)>,
+        buffer: &'a mut [u8],
+    ) -> SecurityTable<'a> {
+        SecurityTable {
+            keys: MapCell::new(Default::default()),
+            num_keys: Cell::new(0),
+            devices: MapCell::new(Default::default()),
+            num_devices: Cell::new(0),
+            frame_counter: Cell::new(0),
+            reserved_counter: Cell::new(0),
+            saving_counter: Cell::new(0),
+            unsaved_frames: Cell::new(0),
+            storage: storage,
+            buffer: TakeCell::new(buffer),
+            state: Cell::new(if storage.is_some() {
+                State::Loading
+            } else {
+                State::Idle
+            }),
+            save_pending: Cell::new(false),
+            sequence: Cell::new(0),
+            // The first write goes to the first slot
+            slot: Cell::new(1),
+        }
+    }
+
+    /// Reads the tables from storage. Until they are read, the tables are
+    /// empty and cannot be provisioned, and no frame can be secured. If the
+    /// storage cannot be read, this remains the case.
+    pub fn load(&self) -> Result<(), ErrorCode> {
+        let (storage, address) = match self.storage {
+            Some(storage) => storage,
+            None => {
+                return Ok(());
+            }
+        };
+        if self.state.get() != State::Loading {
+            return Err(ErrorCode::ALREADY);
+        }
+        self.buffer.take().map_or(Err(ErrorCode::NOMEM), |buffer| {
+            storage.read(buffer, address, BUF_LEN)
+        })
+    }
+
+    /// Writes the tables to storage, reserving the next block of outgoing
+    /// frame counters. If the tables are being written, they are written
+    /// again afterwards.
+    fn save(&self) {
+        self.unsaved_frames.set(0);
+        let (storage, address) = match self.storage {
+            Some(storage) => storage,
+            None => {
+                return;
+            }
+        };
+        match self.state.get() {
+            State::Loading => {}
+            State::Saving => self.save_pending.set(true),
+            State::Idle => {
+                self.buffer.take().map(|buffer| {
+                    // Counters are only used below the reservation of the
+                    // previous write, so the reservation never decreases.
+                    let reserved_counter = self
+                        .frame_counter
+                        .get()
+                        .saturating_add(FRAME_COUNTER_RESERVE);
+                    let tables = StoredTables {
+                        sequence: self.sequence.get().wrapping_add(1),
+                        reserved_counter: reserved_counter,
+                        num_keys: self.num_keys.get(),
+                        keys: self
+                            .keys
+                            .map_or([KeyDescriptor::default(); MAX_KEYS], |k| *k),
+                        num_devices: self.num_devices.get(),
+                        devices: self
+                            .devices
+                            .map_or([DeviceDescriptor::default(); MAX_DEVICES], |d| *d),
+                    };
+                    if tables.encode(buffer).done().is_none() {
+                        self.buffer.replace(buffer);
+                        return;
+                    }
+
+                    // Overwrite the older copy
+                    let slot = 1 - self.slot.get();
+                    if storage
+                        .write(buffer, address + slot * TABLE_LEN, TABLE_LEN)
+                        .is_ok()
+                    {
+                        self.saving_counter.set(reserved_counter);
+                        self.state.set(State::Saving);
+                    }
+                });
+            }
+        }
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.state.get() != State::Loading
+    }
+
+    /// Whether the tables, and the bound of the outgoing frame counter, are
+    /// kept in storage across reboots.
+    pub fn is_persistent(&self) -> bool {
+        self.storage.is_some()
+    }
+
+    /// The frame counter the next secured frame will use.
+    pub fn frame_counter(&self) -> u32 {
+        self.frame_counter.get()
+    }
+
+    pub fn num_keys(&self) -> usize {
+        self.num_keys.get()
+    }
+
+    /// Gets the key at `index`, if `index` is valid.
+    pub fn get_key(&self, index: usize) -> Option<KeyDescriptor> {
+        if index < self.num_keys.get() {
+            self.keys.map(|keys| keys[index])
+        } else {
+            None
+        }
+    }
+
+    /// Adds a key to the end of the table, returning its index. A key with
+    /// the same security level and key ID is replaced instead. Returns NOMEM
+    /// if the table is full and BUSY until the tables are read from storage.
+    pub fn add_key(&self, new_key: KeyDescriptor) -> Result<usize, ErrorCode> {
+        if !self.is_loaded() {
+            return Err(ErrorCode::BUSY);
+        }
+        let result = self.keys.map_or(Err(ErrorCode::FAIL), |keys| {
+            let num_keys = self.num_keys.get();
+            let position = keys[..num_keys]
+                .iter()
+                .position(|key| key.level == new_key.level && key.key_id == new_key.key_id);
+            match position {
+                Some(index) => {
+                    keys[index] = new_key;
+                    Ok(index)
+                }
+                None => {
+                    if num_keys == MAX_KEYS {
+                        Err(ErrorCode::NOMEM)
+                    } else {
+                        keys[num_keys] = new_key;
+                        self.num_keys.set(num_keys + 1);
+                        Ok(num_keys)
+                    }
+                }
+            }
+        });
+        if result.is_ok() {
+            self.save();
+        }
+        result
+    }
+
+    /// Deletes the key at `index`, keeping the table compact. Returns INVAL
+    /// if `index` is not valid.
+    pub fn remove_key(&self, index: usize) -> Result<(), ErrorCode> {
+        let num_keys = self.num_keys.get();
+        if index >= num_keys {
+            return Err(ErrorCode::INVAL);
+        }
+        self.keys.map(|keys| {
+            for i in index..(num_keys - 1) {
+                keys[i] = keys[i + 1];
+            }
+        });
+        self.num_keys.set(num_keys - 1);
+        self.save();
+        Ok(())
+    }
+
+    pub fn num_devices(&self) -> usize {
+        self.num_devices.get()
+    }
+
+    /// Gets the device at `index`, if `index` is valid.
+    pub fn get_device(&self, index: usize) -> Option<DeviceDescriptor> {
+        if index < self.num_devices.get() {
+            self.devices.map(|devices| devices[index])
+        } else {
+            None
+        }
+    }
+
+    /// Adds a device with the given addresses to the end of the table,
+    /// returning its index. If a device with the same extended address
+    /// exists, its short address is updated but its frame counter is kept.
+    /// Returns NOMEM if the table is full and BUSY until the tables are read
+    /// from storage.
+    pub fn add_device(&self, short_addr: u16, long_addr: [u8; 8]) -> Result<usize, ErrorCode> {
+        if !self.is_loaded() {
+            return Err(ErrorCode::BUSY);
+        }
+        let result = self.devices.map_or(Err(ErrorCode::FAIL), |devices| {
+            let num_devices = self.num_devices.get();
+            let position = devices[..num_devices]
+                .iter()
+                .position(|device| device.long_addr == long_addr);
+            match position {
+                Some(index) => {
+                    devices[index].short_addr = short_addr;
+                    Ok(index)
+                }
+                None => {
+                    if num_devices == MAX_DEVICES {
+                        Err(ErrorCode::NOMEM)
+                    } else {
+                        devices[num_devices] = DeviceDescriptor {
+                            short_addr: short_addr,
+                            long_addr: long_addr,
+                            frame_counter: 0,
+                        };
+                        self.num_devices.set(num_devices + 1);
+                        Ok(num_devices)
+                    }
+                }
+            }
+        });
+        if result.is_ok() {
+            self.save();
+        }
+        result
+    }
+
+    /// Deletes the device at `index`, keeping the table compact. Returns
+    /// INVAL if `index` is not valid.
+    pub fn remove_device(&self, index: usize) -> Result<(), ErrorCode> {
+        let num_devices = self.num_devices.get();
+        if index >= num_devices {
+            return Err(ErrorCode::INVAL);
+        }
+        self.devices.map(|devices| {
+            for i in index..(num_devices - 1) {
+                devices[i] = devices[i + 1];
+            }
+        });
+        self.num_devices.set(num_devices - 1);
+        self.save();
+        Ok(())
+    }
+}
+
+impl KeyProcedure for SecurityTable<'_> {
+    /// Gets the key matching the security level `level` and key ID `key_id`,
+    /// if it may secure frames of type `frame_type`.
+    fn lookup_key(
+        &self,
+        frame_type: FrameType,
+        level: SecurityLevel,
+        key_id: KeyId,
+    ) -> Option<[u8; 16]> {
+        self.keys.and_then(|keys| {
+            keys[..self.num_keys.get()]
+                .iter()
+                .find(|key| key.level == level && key.key_id == key_id)
+                .filter(|key| key.usage.allows(frame_type))
+                .map(|key| key.key)
+        })
+    }
+
+    fn next_frame_counter(&self) -> Option<u32> {
+        let frame_counter = self.frame_counter.get();
+        // IEEE 802.15.4-2015, 9.2.1: a frame counter of 0xffffffff is a
+        // counter error
+        if !self.is_loaded() || frame_counter == 0xffffffff {
+            return None;
+        }
+        if self.storage.is_some() {
+            let reserved_counter = self.reserved_counter.get();
+            if reserved_counter - frame_counter <= FRAME_COUNTER_RESERVE / 2
+                && self.state.get() == State::Idle
+            {
+                // Reserve the next block before this one runs out
+                self.save();
+            }
+            if frame_counter >= reserved_counter {
+                return None;
+            }
+        }
+        self.frame_counter.set(frame_counter + 1);
+        Some(frame_counter)
+    }
+}
+
+impl DeviceProcedure for SecurityTable<'_> {
+    /// Gets the extended address of the device that matches the given MAC
+    /// address. If no such device exists, returns `None`.
+    fn lookup_addr_long(&self, addr: MacAddress) -> Option<[u8; 8]> {
+        self.devices.and_then(|devices| {
+            devices[..self.num_devices.get()]
+                .iter()
+                .find(|device| match addr {
+                    MacAddress::Short(addr) => addr == device.short_addr,
+                    MacAddress::Long(addr) => addr == device.long_addr,
+                })
+                .map(|device| device.long_addr)
+        })
+    }
+
+    fn check_frame_counter(&self, addr: [u8; 8], frame_counter: u32) -> bool {
+        self.devices.map_or(false, |devices| {
+            devices[..self.num_devices.get()]
+                .iter()
+                .find(|device| device.long_addr == addr)
+                .map_or(false, |device| frame_counter >= device.frame_counter)
+        })
+    }
+
+    fn update_frame_counter(&self, addr: [u8; 8], frame_counter: u32) {
+        self.devices.map(|devices| {
+            devices[..self.num_devices.get()]
+                .iter_mut()
+                .find(|device| device.long_addr == addr)
+                .map(|device| device.frame_counter = frame_counter.saturating_add(1));
+        });
+        let unsaved_frames = self.unsaved_frames.get() + 1;
+        if unsaved_frames >= INCOMING_SAVE_INTERVAL {
+            self.save();
+        } else {
+            self.unsaved_frames.set(unsaved_frames);
+        }
+    }
+}
+
+impl<'a> NonvolatileStorageClient<'a> for SecurityTable<'a> {
+    fn read_done(&self, buffer: &'a mut [u8], length: usize) {
+        if length != BUF_LEN {
+            // Starting with empty tables could reuse frame counters, so the
+            // tables stay unusable.
+            self.buffer.replace(buffer);
+            return;
+        }
+
+        let latest = buffer[..length]
+            .chunks(TABLE_LEN)
+            .enumerate()
+            .filter_map(|(slot, copy)| StoredTables::decode(copy).done().map(|(_, t)| (slot, t)))
+            .max_by_key(|(_, tables)| tables.sequence);
+        if let Some((slot, tables)) = latest {
+            self.keys.map(|keys| *keys = tables.keys);
+            self.num_keys.set(tables.num_keys);
+            self.devices.map(|devices| *devices = tables.devices);
+            self.num_devices.set(tables.num_devices);
+            // Some of the reserved counters may have been used before the
+            // reboot
+            self.frame_counter.set(tables.reserved_counter);
+            self.reserved_counter.set(tables.reserved_counter);
+            self.sequence.set(tables.sequence);
+            self.slot.set(slot);
+        }
+        self.buffer.replace(buffer);
+        self.state.set(State::Idle);
+
+        // Reserve frame counters
+        self.save();
+    }
+
+    fn write_done(&self, buffer: &'a mut [u8], length: usize) {
+        self.buffer.replace(buffer);
+        self.state.set(State::Idle);
+        if length == TABLE_LEN {
+            self.slot.set(1 - self.slot.get());
+            self.sequence.set(self.sequence.get().wrapping_add(1));
+            self.reserved_counter.set(self.saving_counter.get());
+        }
+        if self.save_pending.take() {
+            self.save();
+        }
+    }
+}
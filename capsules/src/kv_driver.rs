@@ -223,6 +223,11 @@ impl<'a, K: kv_system::KVSystem<'a, K = T>, T: kv_system::KeyType> KVSystemDrive
                                     return e;
                                 }
                             }
+                            UserSpaceOp::NextKey => {
+                                let perms =
+                                    appid.get_storage_permissions().ok_or(ErrorCode::INVAL)?;
+                                self.kv.next_key(app.cursor.get(), perms)?;
+                            }
                         }
                     }
 
@@ -367,6 +372,47 @@ impl<'a, K: kv_system::KVSystem<'a, K = T>, T: kv_system::KeyType> kv_system::St
             })
         });
     }
+
+    fn next_key_complete(
+        &self,
+        result: Result<(), ErrorCode>,
+        key: &T,
+        value_length: usize,
+        next_cursor: usize,
+    ) {
+        self.appid.map(move |id| {
+            self.apps.enter(*id, move |app, upcalls| {
+                if app.op.get() == Some(UserSpaceOp::NextKey) {
+                    let ret = result.and_then(|()| {
+                        upcalls
+                            .get_readwrite_processbuffer(rw_allow::VALUE)
+                            .and_then(|buffer| {
+                                buffer.mut_enter(|data| {
+                                    // Copy as much of the hashed key as fits
+                                    let key = key.as_ref();
+                                    let len = data.len().min(key.len());
+                                    data[..len].copy_from_slice(&key[..len]);
+                                })
+                            })
+                            .map_err(|_| ErrorCode::RESERVE)
+                    });
+
+                    match ret {
+                        Ok(()) => upcalls
+                            .schedule_upcall(upcalls::VALUE, (0, value_length, next_cursor))
+                            .ok(),
+                        Err(e) => upcalls
+                            .schedule_upcall(
+                                upcalls::VALUE,
+                                (kernel::errorcode::into_statuscode(Err(e)), 0, 0),
+                            )
+                            .ok(),
+                    };
+                }
+            })
+        });
+        self.appid.clear();
+    }
 }
 
 impl<'a, K: kv_system::KVSystem<'a, K = T>, T: kv_system::KeyType> SyscallDriver
@@ -375,7 +421,7 @@ impl<'a, K: kv_system::KVSystem<'a, K = T>, T: kv_system::KeyType> SyscallDriver
     fn command(
         &self,
         command_num: usize,
-        _data1: usize,
+        data1: usize,
         _data2: usize,
         appid: ProcessId,
     ) -> CommandReturn {
@@ -407,14 +453,18 @@ impl<'a, K: kv_system::KVSystem<'a, K = T>, T: kv_system::KeyType> SyscallDriver
             // check if present
             0 => CommandReturn::success(),
 
-            // get, set, delete
-            1 | 2 | 3 => {
+            // get, set, delete, next key
+            1 | 2 | 3 | 4 => {
                 if match_or_empty_or_nonexistant {
                     self.appid.set(appid);
                     let _ = self.apps.enter(appid, |app, _| match command_num {
                         1 => app.op.set(Some(UserSpaceOp::Get)),
                         2 => app.op.set(Some(UserSpaceOp::Set)),
                         3 => app.op.set(Some(UserSpaceOp::Delete)),
+                        4 => {
+                            app.op.set(Some(UserSpaceOp::NextKey));
+                            app.cursor.set(data1);
+                        }
                         _ => {}
                     });
                     let ret = self.run();
@@ -442,6 +492,10 @@ impl<'a, K: kv_system::KVSystem<'a, K = T>, T: kv_system::KeyType> SyscallDriver
                                     1 => app.op.set(Some(UserSpaceOp::Get)),
                                     2 => app.op.set(Some(UserSpaceOp::Set)),
                                     3 => app.op.set(Some(UserSpaceOp::Delete)),
+                                    4 => {
+                                        app.op.set(Some(UserSpaceOp::NextKey));
+                                        app.cursor.set(data1);
+                                    }
                                     _ => {}
                                 }
                                 CommandReturn::success()
@@ -466,10 +520,12 @@ enum UserSpaceOp {
     Get,
     Set,
     Delete,
+    NextKey,
 }
 
 #[derive(Default)]
 pub struct App {
     pending_run_app: Option<ProcessId>,
     op: Cell<Option<UserSpaceOp>>,
+    cursor: Cell<usize>,
 }
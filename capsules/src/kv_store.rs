@@ -28,10 +28,32 @@
 //!
 //!    hil::flash
 //! ```
+//!
+//! Values can optionally be encrypted and authenticated with AES128-CCM by
+//! calling `enable_encryption()` with a device key. Each value is then
+//! encrypted with a random nonce. The value header (including the writer ID)
+//! and the hashed key name are authenticated as associated data, so a value
+//! can't be moved to a different key or have its permissions changed without
+//! failing to decrypt. Encrypted values are stored as:
+//!
+//! ```text
+//! | KeyHeader (9) | Nonce (13) | Hashed key (8) | Ciphertext | MIC (16) |
+//! ```
+//!
+//! The buffers passed to `set()` and `get()` must have space for this
+//! overhead, and the AES128-CCM implementation must support values of that
+//! length.
+//!
+//! Once encryption is enabled, values stored without encryption are treated
+//! as if they don't belong to any writer: they can't be read, deleted or
+//! listed. Otherwise anyone with access to the flash could plant plaintext
+//! values that would be accepted in place of the encrypted ones.
 
 use core::cell::Cell;
 use kernel::collections::list::{List, ListLink, ListNode};
 use kernel::hil::kv_system::{self, KVSystem};
+use kernel::hil::rng::{self, Rng};
+use kernel::hil::symmetric_encryption::{CCMClient, AES128CCM, CCM_NONCE_LENGTH};
 use kernel::storage_permissions::StoragePermissions;
 use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::ErrorCode;
@@ -41,11 +63,24 @@ enum Operation {
     Get,
     Set,
     Delete,
+    NextKey,
 }
 
 const HEADER_VERSION: u8 = 0;
+const HEADER_VERSION_ENCRYPTED: u8 = 1;
 const HEADER_LENGTH: usize = 9;
 
+/// Offset of the CCM nonce in an encrypted value
+const NONCE_OFFSET: usize = HEADER_LENGTH;
+/// Offset of the hashed key in an encrypted value. This is only stored so
+/// that it can be authenticated as part of the associated data, it is
+/// replaced with the key being looked up before decrypting.
+const KEY_OFFSET: usize = NONCE_OFFSET + CCM_NONCE_LENGTH;
+const KEY_LENGTH: usize = 8;
+/// Offset of the ciphertext in an encrypted value
+const ENCRYPTED_DATA_OFFSET: usize = KEY_OFFSET + KEY_LENGTH;
+const MIC_LENGTH: usize = 16;
+
 /// This is the header used for KV stores
 struct KeyHeader {
     version: u8,
@@ -91,6 +126,12 @@ pub struct KVStore<'a, K: KVSystem<'a> + KVSystem<'a, K = T>, T: 'static + kv_sy
 
     valid_ids: OptionalCell<StoragePermissions>,
     next_valid_ids: OptionalCell<StoragePermissions>,
+
+    cursor: Cell<usize>,
+
+    aes: OptionalCell<&'a dyn AES128CCM<'a>>,
+    rng: OptionalCell<&'a dyn Rng<'a>>,
+    nonce_length: Cell<usize>,
 }
 
 impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType> ListNode<'a, KVStore<'a, K, T>>
@@ -118,6 +159,10 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType> KVStore<'a, K, T> {
             header_value: TakeCell::new(header_value),
             valid_ids: OptionalCell::empty(),
             next_valid_ids: OptionalCell::empty(),
+            cursor: Cell::new(0),
+            aes: OptionalCell::empty(),
+            rng: OptionalCell::empty(),
+            nonce_length: Cell::new(0),
         }
     }
 
@@ -125,6 +170,27 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType> KVStore<'a, K, T> {
         self.client.set(client);
     }
 
+    /// Encrypt and authenticate values stored from now on with `key`.
+    ///
+    /// `aes` and `rng` are used exclusively by this `KVStore`, this sets
+    /// their clients. Values stored without encryption can no longer be
+    /// accessed, and encrypted values can only be read after this has been
+    /// called with the same `key`.
+    pub fn enable_encryption(
+        &'a self,
+        aes: &'a dyn AES128CCM<'a>,
+        rng: &'a dyn Rng<'a>,
+        key: &[u8],
+    ) -> Result<(), ErrorCode> {
+        aes.set_key(key)?;
+        aes.set_client(self);
+        rng.set_client(self);
+
+        self.aes.set(aes);
+        self.rng.set(rng);
+        Ok(())
+    }
+
     pub fn get(
         &self,
         unhashed_key: &'static mut [u8],
@@ -184,17 +250,27 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType> KVStore<'a, K, T> {
         };
 
         // Create the Tock header and ensure we have space to fit it
+        let encrypt = self.aes.is_some();
         let header = KeyHeader {
-            version: HEADER_VERSION,
+            version: if encrypt {
+                HEADER_VERSION_ENCRYPTED
+            } else {
+                HEADER_VERSION
+            },
             length: length as u32,
             write_id,
         };
-        if length + header.len() > value.len() {
+        let (data_offset, overhead) = if encrypt {
+            (ENCRYPTED_DATA_OFFSET, ENCRYPTED_DATA_OFFSET + MIC_LENGTH)
+        } else {
+            (header.len(), header.len())
+        };
+        if length + overhead > value.len() {
             return Err((unhashed_key, value, Err(ErrorCode::SIZE)));
         }
 
         // Move the value to make space for the header
-        value.copy_within(0..length, header.len());
+        value.copy_within(0..length, data_offset);
         header.copy_to_buf(value);
 
         if self.mux_kv.operation.is_none() {
@@ -276,6 +352,147 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType> KVStore<'a, K, T> {
             }
         }
     }
+
+    /// Find the next key stored after `cursor` that `perms` allows reading.
+    ///
+    /// `cursor` is `0` to start with the first key, otherwise the
+    /// `next_cursor` from the previous `next_key_complete()` callback. Keys
+    /// the caller doesn't have read access to are skipped.
+    pub fn next_key(&self, cursor: usize, perms: StoragePermissions) -> Result<(), ErrorCode> {
+        if self.mux_kv.operation.is_none() {
+            self.mux_kv.operation.set(Operation::NextKey);
+            self.valid_ids.set(perms);
+
+            self.hashed_key
+                .take()
+                .map_or(Err(ErrorCode::NOMEM), |hashed_key| {
+                    if let Err((hashed_key, e)) = self.mux_kv.kv.get_next_key(cursor, hashed_key) {
+                        self.hashed_key.replace(hashed_key);
+                        self.mux_kv.operation.clear();
+                        return e;
+                    }
+
+                    Ok(())
+                })
+        } else {
+            // Another app is already running, queue this app as long as we
+            // don't already have data queued.
+            if self.next_operation.is_none() {
+                self.next_operation.set(Operation::NextKey);
+                self.cursor.set(cursor);
+                self.next_valid_ids.set(perms);
+
+                Ok(())
+            } else {
+                Err(ErrorCode::BUSY)
+            }
+        }
+    }
+
+    /// Report the result of a `set()` operation to the client.
+    fn set_done(&self, result: Result<(), ErrorCode>) {
+        self.mux_kv.operation.clear();
+
+        self.unhashed_key.take().map(|unhashed_key| {
+            self.value.take().map(|value| {
+                self.client.map(move |cb| {
+                    cb.set_complete(result, unhashed_key, value);
+                });
+            });
+        });
+    }
+
+    /// Report the result of a `get()` operation to the client. If
+    /// `read_allowed` is false the value is cleared.
+    fn get_done(
+        &self,
+        result: Result<(), ErrorCode>,
+        read_allowed: bool,
+        ret_buf: &'static mut [u8],
+    ) {
+        self.mux_kv.operation.clear();
+
+        if !read_allowed {
+            // Access denied or the header is invalid, zero the buffer
+            ret_buf.iter_mut().for_each(|m| *m = 0)
+        }
+
+        self.unhashed_key.take().map(|unhashed_key| {
+            self.client.map(move |cb| {
+                if read_allowed {
+                    cb.get_complete(result, unhashed_key, ret_buf);
+                } else {
+                    // The operation failed or the caller doesn't have permission,
+                    // just return an error (and an empty buffer)
+                    cb.get_complete(Err(ErrorCode::FAIL), unhashed_key, ret_buf);
+                }
+            });
+        });
+    }
+
+    /// Whether values with header version `version` are accepted. Only
+    /// encrypted values are accepted once encryption is enabled.
+    fn version_accepted(&self, version: u8) -> bool {
+        if self.aes.is_some() {
+            version == HEADER_VERSION_ENCRYPTED
+        } else {
+            version == HEADER_VERSION
+        }
+    }
+
+    /// Copy the hashed key into the associated data of an encrypted value.
+    fn copy_key_to_buf(hashed_key: &T, buf: &mut [u8]) {
+        let key_buf = &mut buf[KEY_OFFSET..ENCRYPTED_DATA_OFFSET];
+        key_buf.iter_mut().for_each(|d| *d = 0);
+        key_buf
+            .iter_mut()
+            .zip(hashed_key.as_ref().iter())
+            .for_each(|(d, k)| *d = *k);
+    }
+
+    /// Encrypt or decrypt the value in `buf` in place, using the nonce stored
+    /// in `buf`.
+    fn crypt(
+        &self,
+        buf: &'static mut [u8],
+        length: usize,
+        encrypting: bool,
+    ) -> Result<(), (ErrorCode, &'static mut [u8])> {
+        match self.aes.extract() {
+            Some(aes) => {
+                if let Err(e) = aes.set_nonce(&buf[NONCE_OFFSET..KEY_OFFSET]) {
+                    return Err((e, buf));
+                }
+
+                aes.crypt(
+                    buf,
+                    0,
+                    ENCRYPTED_DATA_OFFSET,
+                    length,
+                    MIC_LENGTH,
+                    true,
+                    encrypting,
+                )
+            }
+            None => Err((ErrorCode::NODEVICE, buf)),
+        }
+    }
+
+    /// Report the result of a `next_key()` operation to the client.
+    fn next_key_done(
+        &self,
+        result: Result<(), ErrorCode>,
+        hashed_key: &'static mut T,
+        value_length: usize,
+    ) {
+        let key = *hashed_key;
+        self.hashed_key.replace(hashed_key);
+        self.mux_kv.operation.clear();
+
+        self.client.map(|cb| {
+            cb.next_key_complete(result, &key, value_length, self.cursor.get());
+        });
+    }
 }
 
 impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_system::Client<T>
@@ -313,6 +530,7 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
                             cb.delete_complete(result, unhashed_key);
                         });
                     }
+                    Operation::NextKey => {}
                 });
             } else {
                 match op {
@@ -330,6 +548,17 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
                             }
                         });
                     }
+                    Operation::Set if self.aes.is_some() => {
+                        // Get a random nonce before encrypting the value
+                        self.value
+                            .map(|value| Self::copy_key_to_buf(hashed_key, value));
+                        self.hashed_key.replace(hashed_key);
+                        self.nonce_length.set(0);
+
+                        if let Err(e) = self.rng.map_or(Err(ErrorCode::NODEVICE), |rng| rng.get()) {
+                            self.set_done(Err(e));
+                        }
+                    }
                     Operation::Set => {
                         self.value.take().map(|value| {
                             if let Err((key, value, e)) =
@@ -358,6 +587,9 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
                             }
                         });
                     }
+                    Operation::NextKey => {
+                        self.hashed_key.replace(hashed_key);
+                    }
                 }
             }
         });
@@ -375,7 +607,7 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
         self.value.replace(value);
 
         self.mux_kv.operation.map(|op| match op {
-            Operation::Get | Operation::Delete => {}
+            Operation::Get | Operation::Delete | Operation::NextKey => {}
             Operation::Set => {
                 self.unhashed_key.take().map(|unhashed_key| {
                     self.value.take().map(|value| {
@@ -406,7 +638,7 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
 
                 let header = KeyHeader::new_from_buf(ret_buf);
 
-                if header.version == HEADER_VERSION {
+                if self.version_accepted(header.version) {
                     self.valid_ids.map(|perms| {
                         access_allowed = perms.check_write_permission(header.write_id);
                     });
@@ -438,38 +670,58 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
 
                 if result.is_ok() {
                     let header = KeyHeader::new_from_buf(ret_buf);
+                    let length = header.length as usize;
 
-                    if header.version == HEADER_VERSION {
+                    if self.version_accepted(header.version) {
                         self.valid_ids.map(|perms| {
                             read_allowed = perms.check_read_permission(header.write_id);
                         });
+                    }
 
-                        if read_allowed {
-                            ret_buf.copy_within(
-                                HEADER_LENGTH..(HEADER_LENGTH + header.length as usize),
-                                0,
-                            );
+                    if read_allowed && header.version == HEADER_VERSION_ENCRYPTED {
+                        if ENCRYPTED_DATA_OFFSET + length + MIC_LENGTH > ret_buf.len() {
+                            self.get_done(result, false, ret_buf);
+                            return;
                         }
+
+                        // Only the key that was looked up decrypts the value
+                        self.hashed_key
+                            .map(|hashed_key| Self::copy_key_to_buf(hashed_key, ret_buf));
+
+                        if let Err((_, ret_buf)) = self.crypt(ret_buf, length, false) {
+                            self.get_done(result, false, ret_buf);
+                        }
+                        return;
                     }
-                }
 
-                if !read_allowed {
-                    // Access denied or the header is invalid, zero the buffer
-                    ret_buf.iter_mut().for_each(|m| *m = 0)
+                    if read_allowed {
+                        ret_buf.copy_within(HEADER_LENGTH..(HEADER_LENGTH + length), 0);
+                    }
                 }
 
-                self.unhashed_key.take().map(|unhashed_key| {
-                    self.client.map(move |cb| {
-                        if read_allowed {
-                            cb.get_complete(result, unhashed_key, ret_buf);
-                        } else {
-                            // The operation failed or the caller doesn't have permission,
-                            // just return an error (and an empty buffer)
-                            cb.get_complete(Err(ErrorCode::FAIL), unhashed_key, ret_buf);
-                        }
-                    });
+                self.get_done(result, read_allowed, ret_buf);
+            }
+            Operation::NextKey => {
+                // `ret_buf` only has space for the header, so reading the
+                // value is expected to fail after filling in the header.
+                let header = KeyHeader::new_from_buf(ret_buf);
+                let read_allowed = self.version_accepted(header.version)
+                    && self
+                        .valid_ids
+                        .map_or(false, |perms| perms.check_read_permission(header.write_id));
+
+                self.header_value.replace(ret_buf);
+
+                self.hashed_key.take().map(|hashed_key| {
+                    if read_allowed {
+                        self.next_key_done(Ok(()), hashed_key, header.length as usize);
+                    } else if let Err((hashed_key, e)) =
+                        // Skip keys the caller can't access
+                        self.mux_kv.kv.get_next_key(self.cursor.get(), hashed_key)
+                    {
+                        self.next_key_done(e, hashed_key, 0);
+                    }
                 });
-                self.mux_kv.operation.clear();
             }
         });
 
@@ -480,7 +732,7 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
         self.hashed_key.replace(key);
 
         self.mux_kv.operation.map(|op| match op {
-            Operation::Set | Operation::Get => {}
+            Operation::Set | Operation::Get | Operation::NextKey => {}
             Operation::Delete => {
                 self.unhashed_key.take().map(|unhashed_key| {
                     self.client.map(move |cb| {
@@ -499,6 +751,142 @@ impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType + core::fmt::Debug> kv_sy
         self.mux_kv.perform_cleanup.set(false);
         self.mux_kv.do_next_op();
     }
+
+    fn get_next_key_complete(
+        &self,
+        result: Result<(), ErrorCode>,
+        next_cursor: usize,
+        key: &'static mut T,
+    ) {
+        self.mux_kv.operation.map(|op| match op {
+            Operation::Get | Operation::Set | Operation::Delete => {
+                self.hashed_key.replace(key);
+            }
+            Operation::NextKey => {
+                if result.is_err() {
+                    self.next_key_done(result, key, 0);
+                    return;
+                }
+
+                self.cursor.set(next_cursor);
+
+                // Read the header of the key to check if the caller can
+                // access it
+                match self.header_value.take() {
+                    Some(value) => {
+                        if let Err((key, value, e)) = self.mux_kv.kv.get_value(key, value) {
+                            self.header_value.replace(value);
+                            self.next_key_done(e, key, 0);
+                        }
+                    }
+                    None => self.next_key_done(Err(ErrorCode::NOMEM), key, 0),
+                }
+            }
+        });
+
+        self.mux_kv.do_next_op();
+    }
+}
+
+impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType> rng::Client for KVStore<'a, K, T> {
+    fn randomness_available(
+        &self,
+        randomness: &mut dyn Iterator<Item = u32>,
+        error: Result<(), ErrorCode>,
+    ) -> rng::Continue {
+        if !self.mux_kv.operation.contains(&Operation::Set) {
+            return rng::Continue::Done;
+        }
+
+        if error.is_err() {
+            self.set_done(Err(ErrorCode::FAIL));
+            self.mux_kv.do_next_op();
+            return rng::Continue::Done;
+        }
+
+        // Fill the nonce of the value with random data
+        let mut nonce_length = self.nonce_length.get();
+        self.value.map(|value| {
+            while nonce_length < CCM_NONCE_LENGTH {
+                match randomness.next() {
+                    Some(random) => {
+                        for b in random.to_le_bytes() {
+                            if nonce_length < CCM_NONCE_LENGTH {
+                                value[NONCE_OFFSET + nonce_length] = b;
+                                nonce_length += 1;
+                            }
+                        }
+                    }
+                    None => break,
+                }
+            }
+        });
+        self.nonce_length.set(nonce_length);
+
+        if nonce_length < CCM_NONCE_LENGTH {
+            return rng::Continue::More;
+        }
+
+        if let Some(value) = self.value.take() {
+            let length = KeyHeader::new_from_buf(value).length as usize;
+            if let Err((e, value)) = self.crypt(value, length, true) {
+                self.value.replace(value);
+                self.set_done(Err(e));
+                self.mux_kv.do_next_op();
+            }
+        }
+
+        rng::Continue::Done
+    }
+}
+
+impl<'a, K: KVSystem<'a, K = T>, T: kv_system::KeyType> CCMClient for KVStore<'a, K, T> {
+    fn crypt_done(&self, buf: &'static mut [u8], res: Result<(), ErrorCode>, tag_is_valid: bool) {
+        let length = KeyHeader::new_from_buf(buf).length as usize;
+
+        self.mux_kv.operation.map(|op| match op {
+            Operation::Set => {
+                if res.is_err() {
+                    self.value.replace(buf);
+                    self.set_done(Err(ErrorCode::FAIL));
+                    return;
+                }
+
+                // Don't store any data left over in the buffer
+                buf[(ENCRYPTED_DATA_OFFSET + length + MIC_LENGTH)..]
+                    .iter_mut()
+                    .for_each(|d| *d = 0);
+
+                match self.hashed_key.take() {
+                    Some(hashed_key) => {
+                        if let Err((key, value, e)) = self.mux_kv.kv.append_key(hashed_key, buf) {
+                            self.hashed_key.replace(key);
+                            self.value.replace(value);
+                            self.set_done(e);
+                        }
+                    }
+                    None => {
+                        self.value.replace(buf);
+                        self.set_done(Err(ErrorCode::NOMEM));
+                    }
+                }
+            }
+            Operation::Get => {
+                // `tag_is_valid` is only true if the value is authentic
+                let read_allowed = res.is_ok() && tag_is_valid;
+
+                if read_allowed {
+                    buf.copy_within(ENCRYPTED_DATA_OFFSET..(ENCRYPTED_DATA_OFFSET + length), 0);
+                    buf[length..].iter_mut().for_each(|d| *d = 0);
+                }
+
+                self.get_done(Ok(()), read_allowed, buf);
+            }
+            Operation::Delete | Operation::NextKey => {}
+        });
+
+        self.mux_kv.do_next_op();
+    }
 }
 
 pub struct MuxKVStore<'a, K: KVSystem<'a> + KVSystem<'a, K = T>, T: 'static + kv_system::KeyType> {
@@ -528,9 +916,23 @@ impl<'a, K: KVSystem<'a> + KVSystem<'a, K = T>, T: 'static + kv_system::KeyType>
         let mnode = self.users.iter().find(|node| node.next_operation.is_some());
 
         let ret = mnode.map_or(Err(ErrorCode::NODEVICE), |node| {
-            node.next_operation.map(|op| {
+            node.next_operation.take().map(|op| {
                 self.operation.set(op.clone());
 
+                if op == Operation::NextKey {
+                    node.valid_ids.insert(node.next_valid_ids.take());
+                    node.next_valid_ids.clear();
+
+                    node.hashed_key.take().map(|hashed_key| {
+                        if let Err((hashed_key, e)) =
+                            self.kv.get_next_key(node.cursor.get(), hashed_key)
+                        {
+                            node.next_key_done(e, hashed_key, 0);
+                        }
+                    });
+                    return;
+                }
+
                 node.unhashed_key.take().map(|unhashed_key| {
                     node.hashed_key.take().map(|hashed_key| {
                         match op {
@@ -571,6 +973,7 @@ impl<'a, K: KVSystem<'a> + KVSystem<'a, K = T>, T: 'static + kv_system::KeyType>
                                     });
                                 }
                             }
+                            Operation::NextKey => {}
                         };
                     });
                 });
@@ -14,7 +14,9 @@ pub mod ambient_light;
 pub mod analog_comparator;
 pub mod analog_sensor;
 pub mod apds9960;
+pub mod app_checker;
 pub mod app_flash_driver;
+pub mod app_loader;
 pub mod ble_advertising_driver;
 pub mod bme280;
 pub mod bmp280;
@@ -24,6 +26,7 @@ pub mod buzzer_driver;
 pub mod buzzer_pwm;
 pub mod ccs811;
 pub mod console;
+pub mod core_dump;
 pub mod crc;
 pub mod ctap;
 pub mod dac;
@@ -67,6 +70,7 @@ pub mod nrf51822_serialization;
 pub mod panic_button;
 pub mod pca9544a;
 pub mod process_console;
+pub mod process_watchdog;
 pub mod proximity;
 pub mod public_key_crypto;
 pub mod read_only_state;
@@ -87,6 +91,7 @@ pub mod spi_controller;
 pub mod spi_peripheral;
 pub mod st77xx;
 pub mod symmetric_encryption;
+pub mod syscall_trace;
 pub mod temperature;
 pub mod temperature_rp2040;
 pub mod temperature_stm;
@@ -11,9 +11,7 @@
 use core::cell::Cell;
 use core::cmp;
 use kernel::debug;
-use kernel::dynamic_deferred_call::{
-    DeferredCallHandle, DynamicDeferredCall, DynamicDeferredCallClient,
-};
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
 use kernel::hil::gpio::Pin;
 use kernel::hil::screen::{Screen, ScreenClient, ScreenPixelFormat, ScreenRotation};
 use kernel::hil::spi::{SpiMasterClient, SpiMasterDevice};
@@ -129,22 +127,6 @@ impl CommandHeader {
     }
 }
 
-/// Schedules a deferred call with no arguments.
-/// This is separate from the device to make sure no other state is modified.
-fn schedule_deferred(
-    caller: &DynamicDeferredCall,
-    callback: &OptionalCell<DeferredCallHandle>,
-    name: &str,
-) -> Result<(), ()> {
-    match callback.extract().and_then(|handle| caller.set(handle)) {
-        Some(true) => Ok(()),
-        other => {
-            debug!("LPM013M126 can't call {} (returned {:?})", name, other,);
-            Err(())
-        }
-    }
-}
-
 /// Area of the screen to which data is written
 #[derive(Debug, Copy, Clone)]
 struct WriteFrame {
@@ -189,11 +171,11 @@ pub struct Lpm013m126<'a, A: Alarm<'a>, P: Pin, S: SpiMasterDevice> {
 
     /// This is responsible for sending callbacks
     /// for actions completed in software.
-    deferred_caller: &'a DynamicDeferredCall,
-    ready_callback: OptionalCell<DeferredCallHandle>,
-    command_complete_callback: OptionalCell<DeferredCallHandle>,
-    /// Holds the handle and the pending call parameter.
-    write_complete_callback: OptionalCell<(DeferredCallHandle, Option<Result<(), ErrorCode>>)>,
+    deferred_call: DeferredCall,
+    ready_callback: Cell<bool>,
+    command_complete_callback: Cell<bool>,
+    /// Holds the pending call parameter.
+    write_complete_callback: OptionalCell<Result<(), ErrorCode>>,
 
     /// The HIL requires updates to arbitrary rectangles.
     /// The display supports only updating entire rows,
@@ -218,7 +200,6 @@ where
         extcomin: &'a P,
         disp: &'a P,
         alarm: &'a A,
-        deferred_caller: &'a DynamicDeferredCall,
         frame_buffer: &'static mut [u8],
     ) -> Result<Self, InitError> {
         if frame_buffer.len() < BUFFER_SIZE {
@@ -229,9 +210,9 @@ where
                 alarm,
                 disp,
                 extcomin,
-                deferred_caller,
-                ready_callback: OptionalCell::empty(),
-                command_complete_callback: OptionalCell::empty(),
+                deferred_call: DeferredCall::new(),
+                ready_callback: Cell::new(false),
+                command_complete_callback: Cell::new(false),
                 write_complete_callback: OptionalCell::empty(),
                 frame_buffer: OptionalCell::new(FrameBuffer::new(frame_buffer)),
                 buffer: TakeCell::empty(),
@@ -245,42 +226,8 @@ where
     /// Does not touch the hardware.
     /// Idempotent.
     pub fn setup(&'static self) -> Result<(), ErrorCode> {
-        // Needed this way to avoid exposing accessors to deferred callers.
-        // That would be unnecessary, no external data is needed.
-        // At the same time, self must be static for client registration.
         match self.state.get() {
             State::Uninitialized => {
-                self.ready_callback.insert(
-                    self.ready_callback
-                        .extract()
-                        .or_else(|| self.deferred_caller.register(self)),
-                );
-                if self.ready_callback.is_none() {
-                    return Err(ErrorCode::NOMEM);
-                }
-
-                self.command_complete_callback.insert(
-                    self.command_complete_callback
-                        .extract()
-                        .or_else(|| self.deferred_caller.register(self)),
-                );
-                if self.command_complete_callback.is_none() {
-                    return Err(ErrorCode::NOMEM);
-                }
-
-                self.write_complete_callback.insert(
-                    self.write_complete_callback
-                        .extract()
-                        .or_else(|| self.deferred_caller.register(self).map(|h| (h, None))),
-                );
-                if self.write_complete_callback.is_none() {
-                    return Err(ErrorCode::NOMEM);
-                }
-                // TODO: handles should maybe get unregistered on drop.
-                // Ideally they should unregister RAII style, but I'm not sure how.
-                // Perhaps in new().
-                // The deferred caller doesn't support unregistering anyway.
-
                 self.state.set(State::Off);
                 Ok(())
             }
@@ -353,25 +300,26 @@ where
                 self.disp.clear();
                 self.state.set(State::Off);
 
-                let scheduled =
-                    schedule_deferred(self.deferred_caller, &self.ready_callback, "ready");
-
-                if let Err(()) = scheduled {
-                    self.state.set(State::Bug);
-                    Err(ErrorCode::FAIL)
-                } else {
-                    Ok(())
-                }
+                self.schedule_deferred(&self.ready_callback);
+                Ok(())
             }
         }
     }
 
-    fn call_write_complete(&self, ret: Result<(), ErrorCode>) -> Option<bool> {
-        if let Some((handle, None)) = self.write_complete_callback.extract() {
-            self.write_complete_callback.set((handle, Some(ret)));
-            self.deferred_caller.set(handle)
+    /// Schedules a deferred call with no arguments.
+    fn schedule_deferred(&self, callback: &Cell<bool>) {
+        callback.set(true);
+        self.deferred_call.set();
+    }
+
+    /// Returns `false` if a write_complete call is already pending.
+    fn call_write_complete(&self, ret: Result<(), ErrorCode>) -> bool {
+        if self.write_complete_callback.is_none() {
+            self.write_complete_callback.set(ret);
+            self.deferred_call.set();
+            true
         } else {
-            None
+            false
         }
     }
 
@@ -433,15 +381,7 @@ where
             State::Bug => Err(ErrorCode::FAIL),
         };
 
-        let scheduled = schedule_deferred(
-            self.deferred_caller,
-            &self.command_complete_callback,
-            "command_complete",
-        );
-
-        if let Err(()) = scheduled {
-            new_state = Some(State::Bug);
-        };
+        self.schedule_deferred(&self.command_complete_callback);
 
         if let Some(new_state) = new_state {
             self.state.set(new_state);
@@ -486,16 +426,12 @@ where
 
         match self.state.get() {
             State::Writing(..) => {}
-            _ => match self.call_write_complete(ret) {
-                Some(true) => {}
-                other => {
-                    debug!(
-                        "LPM013M126 can't call write_complete (returned {:?})",
-                        other,
-                    );
+            _ => {
+                if !self.call_write_complete(ret) {
+                    debug!("LPM013M126 can't call write_complete (already pending)");
                     self.state.set(State::Bug);
                 }
-            },
+            }
         };
 
         ret
@@ -528,14 +464,8 @@ where
         // If the device is in the desired state by now,
         // then a callback needs to be sent manually.
         if let Err(ErrorCode::ALREADY) = ret {
-            let scheduled = schedule_deferred(self.deferred_caller, &self.ready_callback, "ready");
-
-            if let Err(()) = scheduled {
-                self.state.set(State::Bug);
-                Err(ErrorCode::FAIL)
-            } else {
-                Ok(())
-            }
+            self.schedule_deferred(&self.ready_callback);
+            Ok(())
         } else {
             ret
         }
@@ -647,32 +577,30 @@ impl<'a, A: Alarm<'a>, P: Pin, S: SpiMasterDevice> SpiMasterClient for Lpm013m12
     }
 }
 
-impl<'a, A: Alarm<'a>, P: Pin, S: SpiMasterDevice> DynamicDeferredCallClient
-    for Lpm013m126<'a, A, P, S>
+impl<'a, A: Alarm<'a>, P: Pin, S: SpiMasterDevice> DeferredCallClient for Lpm013m126<'a, A, P, S>
+where
+    Self: 'static,
 {
-    fn call(&self, handle: DeferredCallHandle) {
-        if Some(handle) == self.command_complete_callback.extract() {
+    fn handle_deferred_call(&self) {
+        if self.command_complete_callback.take() {
             // Thankfully, this is the only command that results in the callback,
             // so there's no danger that this will get attributed
             // to a command that's not finished yet.
             self.client.map(|client| client.command_complete(Ok(())));
-        } else if let Some((handle, Some(arg))) = self.write_complete_callback.extract() {
+        }
+        if let Some(arg) = self.write_complete_callback.take() {
             self.client.map(|client| {
                 self.buffer
                     .take()
                     .map(|buffer| client.write_complete(buffer, arg));
-                self.write_complete_callback.replace((handle, None));
             });
-        } else if Some(handle) == self.ready_callback.extract() {
+        }
+        if self.ready_callback.take() {
             self.client.map(|client| client.screen_is_ready());
-        } else {
-            debug!(
-                "LPM013M126 received deferred call impossible to handle. State: {:?}, command: {:?}, write: {:?}",
-                self.state.get(),
-                self.command_complete_callback.extract(),
-                self.write_complete_callback.extract(),
-            );
-            self.state.set(State::Bug)
         }
     }
+
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+    }
 }
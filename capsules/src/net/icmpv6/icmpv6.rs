@@ -8,6 +8,13 @@ use crate::net::stream::SResult;
 use crate::net::stream::{decode_u16, decode_u32, decode_u8};
 use crate::net::stream::{encode_u16, encode_u32, encode_u8};
 
+/// Parameter Problem codes (RFC 4443).
+pub mod param_problem {
+    pub const ERRONEOUS_HEADER_FIELD: u8 = 0;
+    pub const UNRECOGNIZED_NEXT_HEADER: u8 = 1;
+    pub const UNRECOGNIZED_OPTION: u8 = 2;
+}
+
 /// A struct representing an ICMPv6 header.
 #[derive(Copy, Clone)]
 pub struct ICMP6Header {
@@ -17,20 +24,60 @@ pub struct ICMP6Header {
     pub len: u16, // Not a real ICMP field, here for convenience
 }
 
+/// The type-specific second word of the ICMPv6 header. For the Neighbor
+/// Discovery and RPL messages the remaining fields and the options are part
+/// of the payload.
 #[derive(Copy, Clone)]
 pub enum ICMP6HeaderOptions {
-    Type1 { unused: u32 },
-    Type3 { unused: u32 },
-    Type128 { id: u16, seqno: u16 },
-    Type129 { id: u16, seqno: u16 },
+    Type1 {
+        unused: u32,
+    },
+    Type3 {
+        unused: u32,
+    },
+    Type4 {
+        pointer: u32,
+    },
+    Type128 {
+        id: u16,
+        seqno: u16,
+    },
+    Type129 {
+        id: u16,
+        seqno: u16,
+    },
+    Type133 {
+        unused: u32,
+    },
+    Type134 {
+        hop_limit: u8,
+        flags: u8,
+        router_lifetime: u16,
+    },
+    Type135 {
+        unused: u32,
+    },
+    Type136 {
+        flags: u32,
+    },
+    /// RPL Control Message, whose code gives the message type
+    Type155 {
+        base: u32,
+    },
 }
 
 #[derive(Copy, Clone)]
 pub enum ICMP6Type {
     Type1,   // Destination Unreachable
     Type3,   // Time Exceeded
+    Type4,   // Parameter Problem
     Type128, // Echo Request
     Type129, // Echo Reply
+    Type133, // Router Solicitation
+    Type134, // Router Advertisement
+    Type135, // Neighbor Solicitation
+    Type136, // Neighbor Advertisement
+    Type155, // RPL Control Message
 }
 
 impl ICMP6Header {
@@ -38,8 +85,18 @@ impl ICMP6Header {
         let options = match icmp_type {
             ICMP6Type::Type1 => ICMP6HeaderOptions::Type1 { unused: 0 },
             ICMP6Type::Type3 => ICMP6HeaderOptions::Type3 { unused: 0 },
+            ICMP6Type::Type4 => ICMP6HeaderOptions::Type4 { pointer: 0 },
             ICMP6Type::Type128 => ICMP6HeaderOptions::Type128 { id: 0, seqno: 0 },
             ICMP6Type::Type129 => ICMP6HeaderOptions::Type129 { id: 0, seqno: 0 },
+            ICMP6Type::Type133 => ICMP6HeaderOptions::Type133 { unused: 0 },
+            ICMP6Type::Type134 => ICMP6HeaderOptions::Type134 {
+                hop_limit: 0,
+                flags: 0,
+                router_lifetime: 0,
+            },
+            ICMP6Type::Type135 => ICMP6HeaderOptions::Type135 { unused: 0 },
+            ICMP6Type::Type136 => ICMP6HeaderOptions::Type136 { flags: 0 },
+            ICMP6Type::Type155 => ICMP6HeaderOptions::Type155 { base: 0 },
         };
 
         ICMP6Header {
@@ -51,12 +108,7 @@ impl ICMP6Header {
     }
 
     pub fn set_type(&mut self, icmp_type: ICMP6Type) {
-        match icmp_type {
-            ICMP6Type::Type1 => self.set_options(ICMP6HeaderOptions::Type1 { unused: 0 }),
-            ICMP6Type::Type3 => self.set_options(ICMP6HeaderOptions::Type3 { unused: 0 }),
-            ICMP6Type::Type128 => self.set_options(ICMP6HeaderOptions::Type128 { id: 0, seqno: 0 }),
-            ICMP6Type::Type129 => self.set_options(ICMP6HeaderOptions::Type129 { id: 0, seqno: 0 }),
-        }
+        self.set_options(Self::new(icmp_type).get_options());
     }
 
     pub fn set_code(&mut self, code: u8) {
@@ -79,8 +131,14 @@ impl ICMP6Header {
         match self.options {
             ICMP6HeaderOptions::Type1 { .. } => ICMP6Type::Type1,
             ICMP6HeaderOptions::Type3 { .. } => ICMP6Type::Type3,
+            ICMP6HeaderOptions::Type4 { .. } => ICMP6Type::Type4,
             ICMP6HeaderOptions::Type128 { .. } => ICMP6Type::Type128,
             ICMP6HeaderOptions::Type129 { .. } => ICMP6Type::Type129,
+            ICMP6HeaderOptions::Type133 { .. } => ICMP6Type::Type133,
+            ICMP6HeaderOptions::Type134 { .. } => ICMP6Type::Type134,
+            ICMP6HeaderOptions::Type135 { .. } => ICMP6Type::Type135,
+            ICMP6HeaderOptions::Type136 { .. } => ICMP6Type::Type136,
+            ICMP6HeaderOptions::Type155 { .. } => ICMP6Type::Type155,
         }
     }
 
@@ -88,8 +146,14 @@ impl ICMP6Header {
         match self.get_type() {
             ICMP6Type::Type1 => 1,
             ICMP6Type::Type3 => 3,
+            ICMP6Type::Type4 => 4,
             ICMP6Type::Type128 => 128,
             ICMP6Type::Type129 => 129,
+            ICMP6Type::Type133 => 133,
+            ICMP6Type::Type134 => 134,
+            ICMP6Type::Type135 => 135,
+            ICMP6Type::Type136 => 136,
+            ICMP6Type::Type155 => 155,
         }
     }
 
@@ -132,7 +196,13 @@ impl ICMP6Header {
         off = enc_consume!(buf, off; encode_u16, self.cksum);
 
         match self.options {
-            ICMP6HeaderOptions::Type1 { unused } | ICMP6HeaderOptions::Type3 { unused } => {
+            ICMP6HeaderOptions::Type1 { unused }
+            | ICMP6HeaderOptions::Type3 { unused }
+            | ICMP6HeaderOptions::Type4 { pointer: unused }
+            | ICMP6HeaderOptions::Type133 { unused }
+            | ICMP6HeaderOptions::Type135 { unused }
+            | ICMP6HeaderOptions::Type136 { flags: unused }
+            | ICMP6HeaderOptions::Type155 { base: unused } => {
                 off = enc_consume!(buf, off; encode_u32, unused);
             }
             ICMP6HeaderOptions::Type128 { id, seqno }
@@ -140,6 +210,15 @@ impl ICMP6Header {
                 off = enc_consume!(buf, off; encode_u16, id);
                 off = enc_consume!(buf, off; encode_u16, seqno);
             }
+            ICMP6HeaderOptions::Type134 {
+                hop_limit,
+                flags,
+                router_lifetime,
+            } => {
+                off = enc_consume!(buf, off; encode_u8, hop_limit);
+                off = enc_consume!(buf, off; encode_u8, flags);
+                off = enc_consume!(buf, off; encode_u16, router_lifetime);
+            }
         }
 
         stream_done!(off, off);
@@ -161,8 +240,14 @@ impl ICMP6Header {
         let icmp_type = match type_num {
             1 => ICMP6Type::Type1,
             3 => ICMP6Type::Type3,
+            4 => ICMP6Type::Type4,
             128 => ICMP6Type::Type128,
             129 => ICMP6Type::Type129,
+            133 => ICMP6Type::Type133,
+            134 => ICMP6Type::Type134,
+            135 => ICMP6Type::Type135,
+            136 => ICMP6Type::Type136,
+            155 => ICMP6Type::Type155,
             _ => return SResult::Error(()),
         };
 
@@ -171,34 +256,50 @@ impl ICMP6Header {
         let (off, code) = dec_try!(buf, off; decode_u8);
         icmp_header.set_code(code);
         let (off, cksum) = dec_try!(buf, off; decode_u16);
-        icmp_header.set_cksum(u16::from_be(cksum));
+        icmp_header.set_cksum(cksum);
 
-        match icmp_type {
-            ICMP6Type::Type1 => {
-                let (_off, unused) = dec_try!(buf, off; decode_u32);
-                let unused = u32::from_be(unused);
-                icmp_header.set_options(ICMP6HeaderOptions::Type1 { unused });
+        // The decoders already return the fields in host byte order.
+        let off = match icmp_type {
+            ICMP6Type::Type1
+            | ICMP6Type::Type3
+            | ICMP6Type::Type4
+            | ICMP6Type::Type133
+            | ICMP6Type::Type135
+            | ICMP6Type::Type136
+            | ICMP6Type::Type155 => {
+                let (off, word) = dec_try!(buf, off; decode_u32);
+                icmp_header.set_options(match icmp_type {
+                    ICMP6Type::Type1 => ICMP6HeaderOptions::Type1 { unused: word },
+                    ICMP6Type::Type3 => ICMP6HeaderOptions::Type3 { unused: word },
+                    ICMP6Type::Type4 => ICMP6HeaderOptions::Type4 { pointer: word },
+                    ICMP6Type::Type133 => ICMP6HeaderOptions::Type133 { unused: word },
+                    ICMP6Type::Type135 => ICMP6HeaderOptions::Type135 { unused: word },
+                    ICMP6Type::Type136 => ICMP6HeaderOptions::Type136 { flags: word },
+                    _ => ICMP6HeaderOptions::Type155 { base: word },
+                });
+                off
             }
-            ICMP6Type::Type3 => {
-                let (_off, unused) = dec_try!(buf, off; decode_u32);
-                let unused = u32::from_be(unused);
-                icmp_header.set_options(ICMP6HeaderOptions::Type3 { unused });
+            ICMP6Type::Type128 | ICMP6Type::Type129 => {
+                let (off, id) = dec_try!(buf, off; decode_u16);
+                let (off, seqno) = dec_try!(buf, off; decode_u16);
+                icmp_header.set_options(match icmp_type {
+                    ICMP6Type::Type128 => ICMP6HeaderOptions::Type128 { id, seqno },
+                    _ => ICMP6HeaderOptions::Type129 { id, seqno },
+                });
+                off
             }
-            ICMP6Type::Type128 => {
-                let (_off, id) = dec_try!(buf, off; decode_u16);
-                let id = u16::from_be(id);
-                let (_off, seqno) = dec_try!(buf, off; decode_u16);
-                let seqno = u16::from_be(seqno);
-                icmp_header.set_options(ICMP6HeaderOptions::Type128 { id, seqno });
+            ICMP6Type::Type134 => {
+                let (off, hop_limit) = dec_try!(buf, off; decode_u8);
+                let (off, flags) = dec_try!(buf, off; decode_u8);
+                let (off, router_lifetime) = dec_try!(buf, off; decode_u16);
+                icmp_header.set_options(ICMP6HeaderOptions::Type134 {
+                    hop_limit,
+                    flags,
+                    router_lifetime,
+                });
+                off
             }
-            ICMP6Type::Type129 => {
-                let (_off, id) = dec_try!(buf, off; decode_u16);
-                let id = u16::from_be(id);
-                let (_off, seqno) = dec_try!(buf, off; decode_u16);
-                let seqno = u16::from_be(seqno);
-                icmp_header.set_options(ICMP6HeaderOptions::Type129 { id, seqno });
-            }
-        }
+        };
 
         stream_done!(off, icmp_header);
     }
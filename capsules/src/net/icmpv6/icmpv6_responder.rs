@@ -0,0 +1,552 @@
+//! This file contains the receive path for ICMPv6. The
+//! [ICMP6Responder](struct.ICMP6Responder.html) answers Echo Requests, so
+//! that a node can be pinged, and acts as a host for 6LoWPAN Neighbor
+//! Discovery (RFC 6775):
+//!
+//! - After `start()` it sends Router Solicitations to the all-routers
+//!   address, backing off up to once a minute, until a router advertises a
+//!   prefix.
+//! - From an autonomous 64-bit prefix in the Router Advertisement it forms a
+//!   global address (SLAAC) out of the prefix and the interface identifier
+//!   of the MAC address, and sends all further packets to the router.
+//! - It registers the address with the router by sending a Neighbor
+//!   Solicitation carrying an Address Registration option, and refreshes the
+//!   registration before its lifetime ends. If the router reports the
+//!   address as a duplicate, the address is dropped.
+//! - It answers Neighbor Solicitations for its own addresses.
+//!
+//! As the error client of its `IP6RecvStruct`, the responder also sends
+//! Parameter Problem messages for received packets with a next header or an
+//! option the stack does not recognize.
+//!
+//! The responder uses its own `IP6Sender`, since it sets the source address
+//! of each packet it sends. Once the global address changes, the client is
+//! told, so that it can set the source address of other senders.
+//!
+//! Usage
+//! -----
+//!
+//! The responder is set up by `components::icmpv6::ICMP6Component`:
+//!
+//! ```rust
+//! let icmp6_responder = components::icmpv6::ICMP6Component::new(
+//!     mux_mac,
+//!     DEFAULT_CTX_PREFIX_LEN,
+//!     DEFAULT_CTX_PREFIX,
+//!     DST_MAC_ADDR,
+//!     src_mac_from_serial_num,
+//!     local_ip_ifaces,
+//!     mux_alarm,
+//! )
+//! .finalize(components::icmp6_component_helper!(nrf52840::rtc::Rtc));
+//! icmp6_responder.start();
+//! ```
+
+use crate::net::icmpv6::ndp::{self, aro_status, nd_opt, NDOption, NDOptionIter};
+use crate::net::icmpv6::{param_problem, ICMP6Header, ICMP6HeaderOptions, ICMP6Type};
+use crate::net::ieee802154::MacAddress;
+use crate::net::ipv6::ip_utils::{ip6_nh, IPAddr};
+use crate::net::ipv6::ipv6_recv::{IP6RecvClient, IP6RecvErrorClient};
+use crate::net::ipv6::ipv6_send::{IP6SendClient, IP6Sender};
+use crate::net::ipv6::{IP6Header, TransportHeader};
+use crate::net::network_capabilities::NetworkCapability;
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::hil::time::{self, ConvertTicks};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::utilities::leasable_buffer::LeasableMutableBuffer;
+use kernel::ErrorCode;
+
+/// The link-local all-nodes multicast address, ff02::1.
+pub const ALL_NODES_ADDR: IPAddr =
+    IPAddr([0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01]);
+
+/// The link-local all-routers multicast address, ff02::2.
+pub const ALL_ROUTERS_ADDR: IPAddr =
+    IPAddr([0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x02]);
+
+/// Neighbor Discovery messages must be sent and received with this hop
+/// limit, so that they cannot come from off-link.
+const ND_HOP_LIMIT: u8 = 255;
+
+// Timers of RFC 6775, in seconds.
+const RTR_SOLICITATION_INTERVAL: u32 = 10;
+const MAX_RTR_SOLICITATION_INTERVAL: u32 = 60;
+const RETRANS_TIMER: u32 = 1;
+const MAX_UNICAST_SOLICIT: u8 = 3;
+
+/// The lifetime requested when registering the address, in units of 60
+/// seconds.
+const REGISTRATION_LIFETIME: u16 = 15;
+
+/// The longest single alarm, in seconds. Longer timers are split so that the
+/// alarm does not overflow on chips with narrow counters.
+const MAX_ALARM_INTERVAL: u32 = 60;
+
+/// Receives the changes of the global address configured from the prefix a
+/// router advertises.
+pub trait ICMP6ResponderClient {
+    /// The global address was registered with the router, or was dropped
+    /// (`None`) because the registration failed or the prefix expired.
+    fn global_address_changed(&self, addr: Option<IPAddr>);
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum NDState {
+    /// Neighbor Discovery has not been started, or the router rejected the
+    /// address as a duplicate.
+    Idle,
+    /// Soliciting routers, with the current retransmission interval.
+    Soliciting { interval: u32 },
+    /// Registering the global address with the router.
+    Registering { attempts: u8 },
+    /// The address is registered until it is refreshed.
+    Registered,
+}
+
+pub struct ICMP6Responder<'a, A: time::Alarm<'a>, S: IP6Sender<'a>> {
+    ip_send: &'a S,
+    alarm: &'a A,
+    net_cap: &'static NetworkCapability,
+    /// The link-local addresses of the node. The first one is used as the
+    /// source of Router Solicitations.
+    interface_list: &'static [IPAddr],
+    mac_addr: MacAddress,
+    /// Buffer for the payload of the ICMPv6 messages sent.
+    send_buf: TakeCell<'static, [u8]>,
+    sending: Cell<bool>,
+    /// A Neighbor Discovery message could not be sent because the sender
+    /// was busy, and is sent once it is done.
+    nd_pending: Cell<bool>,
+    state: Cell<NDState>,
+    /// Seconds left until the timer of the current state expires.
+    timer: Cell<u32>,
+    global_addr: OptionalCell<IPAddr>,
+    /// Whether the client was told about the global address.
+    global_addr_announced: Cell<bool>,
+    router_addr: OptionalCell<IPAddr>,
+    client: OptionalCell<&'a dyn ICMP6ResponderClient>,
+}
+
+impl<'a, A: time::Alarm<'a>, S: IP6Sender<'a>> ICMP6Responder<'a, A, S> {
+    pub fn new(
+        ip_send: &'a S,
+        alarm: &'a A,
+        net_cap: &'static NetworkCapability,
+        interface_list: &'static [IPAddr],
+        mac_addr: MacAddress,
+        send_buf: &'static mut [u8],
+    ) -> ICMP6Responder<'a, A, S> {
+        ICMP6Responder {
+            ip_send: ip_send,
+            alarm: alarm,
+            net_cap: net_cap,
+            interface_list: interface_list,
+            mac_addr: mac_addr,
+            send_buf: TakeCell::new(send_buf),
+            sending: Cell::new(false),
+            nd_pending: Cell::new(false),
+            state: Cell::new(NDState::Idle),
+            timer: Cell::new(0),
+            global_addr: OptionalCell::empty(),
+            global_addr_announced: Cell::new(false),
+            router_addr: OptionalCell::empty(),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn ICMP6ResponderClient) {
+        self.client.set(client);
+    }
+
+    /// Start soliciting routers to configure and register a global address.
+    /// Echo Requests and Neighbor Solicitations for the link-local addresses
+    /// are answered without calling this.
+    pub fn start(&self) {
+        if self.state.get() == NDState::Idle {
+            self.start_soliciting(RTR_SOLICITATION_INTERVAL);
+        }
+    }
+
+    /// Returns the global address, if one has been configured.
+    pub fn global_addr(&self) -> Option<IPAddr> {
+        self.global_addr.extract()
+    }
+
+    fn is_local_addr(&self, addr: IPAddr) -> bool {
+        self.interface_list.contains(&addr) || self.global_addr.contains(&addr)
+    }
+
+    /// The EUI-64 that identifies the node in address registrations, taken
+    /// from the interface identifier of its MAC address.
+    fn eui64(&self) -> [u8; 8] {
+        let mut eui64 = [0; 8];
+        eui64.copy_from_slice(&IPAddr::generate_from_mac(self.mac_addr).0[8..]);
+        eui64[0] ^= 0b00000010;
+        eui64
+    }
+
+    fn set_timer(&self, seconds: u32) {
+        self.timer.set(seconds);
+        self.arm_alarm();
+    }
+
+    fn arm_alarm(&self) {
+        let interval = cmp::min(self.timer.get(), MAX_ALARM_INTERVAL);
+        self.alarm
+            .set_alarm(self.alarm.now(), self.alarm.ticks_from_seconds(interval));
+    }
+
+    fn start_soliciting(&self, interval: u32) {
+        self.state.set(NDState::Soliciting { interval: interval });
+        self.send_nd_message();
+        self.set_timer(interval);
+    }
+
+    fn start_registering(&self) {
+        self.state.set(NDState::Registering { attempts: 1 });
+        self.send_nd_message();
+        self.set_timer(RETRANS_TIMER);
+    }
+
+    /// Drop the global address, and tell the client if it knew about it.
+    fn clear_global_addr(&self) {
+        self.global_addr.clear();
+        self.router_addr.clear();
+        if self.global_addr_announced.take() {
+            self.client
+                .map(|client| client.global_address_changed(None));
+        }
+    }
+
+    fn timer_expired(&self) {
+        match self.state.get() {
+            NDState::Idle => {}
+            NDState::Soliciting { interval } => {
+                let interval = cmp::min(interval * 2, MAX_RTR_SOLICITATION_INTERVAL);
+                self.start_soliciting(interval);
+            }
+            NDState::Registering { attempts } => {
+                if attempts >= MAX_UNICAST_SOLICIT {
+                    // The router does not answer, look for another one.
+                    self.clear_global_addr();
+                    self.start_soliciting(RTR_SOLICITATION_INTERVAL);
+                } else {
+                    self.state.set(NDState::Registering {
+                        attempts: attempts + 1,
+                    });
+                    self.send_nd_message();
+                    self.set_timer(RETRANS_TIMER);
+                }
+            }
+            NDState::Registered => self.start_registering(),
+        }
+    }
+
+    /// Send the Neighbor Discovery message of the current state, or send it
+    /// once the sender is free.
+    fn send_nd_message(&self) {
+        let result = match self.state.get() {
+            NDState::Idle | NDState::Registered => Ok(()),
+            NDState::Soliciting { .. } => self.send_router_solicitation(),
+            NDState::Registering { .. } => self.send_registration(),
+        };
+        if result == Err(ErrorCode::BUSY) {
+            self.nd_pending.set(true);
+        }
+    }
+
+    fn send_router_solicitation(&self) -> Result<(), ErrorCode> {
+        let src_addr = self.interface_list.first().map_or(IPAddr::new(), |a| *a);
+        let header = ICMP6Header::new(ICMP6Type::Type133);
+        self.send_icmp(src_addr, ALL_ROUTERS_ADDR, header, |buf| {
+            ndp::encode_ll_addr_option(buf, 0, nd_opt::SOURCE_LL_ADDR, self.mac_addr).done()
+        })
+    }
+
+    fn send_registration(&self) -> Result<(), ErrorCode> {
+        let (global_addr, router_addr) =
+            match (self.global_addr.extract(), self.router_addr.extract()) {
+                (Some(global_addr), Some(router_addr)) => (global_addr, router_addr),
+                _ => return Err(ErrorCode::FAIL),
+            };
+        let header = ICMP6Header::new(ICMP6Type::Type135);
+        let eui64 = self.eui64();
+        self.send_icmp(global_addr, router_addr, header, |buf| {
+            let off = buf.get_mut(..ndp::NS_FIELDS_LEN).map(|target| {
+                target.copy_from_slice(&global_addr.0);
+                ndp::NS_FIELDS_LEN
+            })?;
+            let (off, _) =
+                ndp::encode_ll_addr_option(buf, off, nd_opt::SOURCE_LL_ADDR, self.mac_addr)
+                    .done()?;
+            ndp::encode_aro(buf, off, aro_status::SUCCESS, REGISTRATION_LIFETIME, &eui64).done()
+        })
+    }
+
+    /// Send an ICMPv6 message whose payload is written by `write_payload`,
+    /// which returns the length of the payload and `None` if it does not fit.
+    fn send_icmp<F>(
+        &self,
+        src_addr: IPAddr,
+        dst_addr: IPAddr,
+        header: ICMP6Header,
+        write_payload: F,
+    ) -> Result<(), ErrorCode>
+    where
+        F: FnOnce(&mut [u8]) -> Option<(usize, usize)>,
+    {
+        if self.sending.get() {
+            return Err(ErrorCode::BUSY);
+        }
+        let buf = self.send_buf.take().ok_or(ErrorCode::NOMEM)?;
+        let len = match write_payload(buf) {
+            Some((len, _)) => len,
+            None => {
+                self.send_buf.replace(buf);
+                return Err(ErrorCode::SIZE);
+            }
+        };
+        let mut payload = LeasableMutableBuffer::new(buf);
+        payload.slice(..len);
+
+        // The packet is copied by the sender, so the buffer can be reused
+        // right away.
+        self.sending.set(true);
+        self.ip_send.set_addr(src_addr);
+        let result = self.ip_send.send_to(
+            dst_addr,
+            TransportHeader::ICMP(header),
+            &payload,
+            self.net_cap,
+        );
+        self.send_buf.replace(payload.take());
+        if result.is_err() {
+            self.sending.set(false);
+        }
+        result
+    }
+
+    fn echo_request(&self, ip_header: &IP6Header, id: u16, seqno: u16, body: &[u8]) {
+        let dst_addr = ip_header.get_dst_addr();
+        let src_addr = if dst_addr.is_multicast() {
+            match self.interface_list.first() {
+                Some(addr) => *addr,
+                None => return,
+            }
+        } else if self.is_local_addr(dst_addr) {
+            dst_addr
+        } else {
+            return;
+        };
+        let mut header = ICMP6Header::new(ICMP6Type::Type129);
+        header.set_options(ICMP6HeaderOptions::Type129 { id, seqno });
+        let _ = self.send_icmp(src_addr, ip_header.get_src_addr(), header, |buf| {
+            buf.get_mut(..body.len()).map(|reply| {
+                reply.copy_from_slice(body);
+                (body.len(), body.len())
+            })
+        });
+    }
+
+    fn router_advertisement(&self, ip_header: &IP6Header, router_lifetime: u16, body: &[u8]) {
+        let router_addr = ip_header.get_src_addr();
+        if !router_addr.is_unicast_link_local() || body.len() < ndp::RA_FIELDS_LEN {
+            return;
+        }
+
+        let mut router_mac = None;
+        let mut prefix = None;
+        for option in NDOptionIter::new(&body[ndp::RA_FIELDS_LEN..]) {
+            match option {
+                NDOption::SourceLinkLayerAddr(mac_addr) => router_mac = Some(mac_addr),
+                NDOption::PrefixInfo(info) if info.is_autonomous() && info.prefix_len == 64 => {
+                    prefix = Some(info)
+                }
+                _ => {}
+            }
+        }
+        let prefix = match prefix {
+            Some(prefix) => prefix,
+            None => return,
+        };
+        let mut addr = IPAddr::generate_from_mac(self.mac_addr);
+        addr.set_prefix(&prefix.prefix, prefix.prefix_len);
+
+        if prefix.valid_lifetime == 0 {
+            // The router withdraws the prefix.
+            if self.global_addr.contains(&addr) {
+                self.clear_global_addr();
+                self.start_soliciting(RTR_SOLICITATION_INTERVAL);
+            }
+            return;
+        }
+        match self.state.get() {
+            NDState::Soliciting { .. } if router_lifetime != 0 => {
+                self.global_addr.set(addr);
+                self.router_addr.set(router_addr);
+                router_mac.map(|mac_addr| self.ip_send.set_gateway(mac_addr));
+                self.start_registering();
+            }
+            _ => {}
+        }
+    }
+
+    fn neighbor_solicitation(&self, ip_header: &IP6Header, body: &[u8]) {
+        if body.len() < ndp::NS_FIELDS_LEN {
+            return;
+        }
+        let mut target = IPAddr::new();
+        target.0.copy_from_slice(&body[..ndp::NS_FIELDS_LEN]);
+        if !self.is_local_addr(target) {
+            return;
+        }
+
+        // A solicitation from the unspecified address comes from a node
+        // checking for duplicates, which is answered on all nodes.
+        let src_addr = ip_header.get_src_addr();
+        let (dst_addr, flags) = if src_addr.is_unspecified() {
+            (ALL_NODES_ADDR, ndp::NA_FLAG_OVERRIDE)
+        } else {
+            (src_addr, ndp::NA_FLAG_SOLICITED | ndp::NA_FLAG_OVERRIDE)
+        };
+        let mut header = ICMP6Header::new(ICMP6Type::Type136);
+        header.set_options(ICMP6HeaderOptions::Type136 { flags });
+        let _ = self.send_icmp(target, dst_addr, header, |buf| {
+            let off = buf.get_mut(..ndp::NS_FIELDS_LEN).map(|fields| {
+                fields.copy_from_slice(&target.0);
+                ndp::NS_FIELDS_LEN
+            })?;
+            ndp::encode_ll_addr_option(buf, off, nd_opt::TARGET_LL_ADDR, self.mac_addr).done()
+        });
+    }
+
+    fn neighbor_advertisement(&self, body: &[u8]) {
+        if body.len() < ndp::NS_FIELDS_LEN {
+            return;
+        }
+        let mut target = IPAddr::new();
+        target.0.copy_from_slice(&body[..ndp::NS_FIELDS_LEN]);
+        match self.state.get() {
+            NDState::Registering { .. } if self.global_addr.contains(&target) => {}
+            _ => return,
+        }
+
+        let status =
+            NDOptionIter::new(&body[ndp::NS_FIELDS_LEN..]).find_map(|option| match option {
+                NDOption::AddressRegistration {
+                    status, lifetime, ..
+                } => Some((status, lifetime)),
+                _ => None,
+            });
+        match status {
+            Some((aro_status::SUCCESS, lifetime)) => {
+                self.state.set(NDState::Registered);
+                // Refresh the registration when 80% of its lifetime passed.
+                self.set_timer(lifetime as u32 * 60 * 4 / 5);
+                if !self.global_addr_announced.replace(true) {
+                    self.client
+                        .map(|client| client.global_address_changed(Some(target)));
+                }
+            }
+            Some((aro_status::DUPLICATE, _)) => {
+                self.clear_global_addr();
+                self.state.set(NDState::Idle);
+            }
+            Some(_) => {
+                // The router cannot register the address now, try again
+                // later.
+                self.clear_global_addr();
+                self.start_soliciting(MAX_RTR_SOLICITATION_INTERVAL);
+            }
+            None => {}
+        }
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, S: IP6Sender<'a>> IP6RecvClient for ICMP6Responder<'a, A, S> {
+    fn receive(&self, ip_header: IP6Header, payload: &[u8]) {
+        // Other protocols may share the IP receive path
+        if ip_header.get_next_header() != ip6_nh::ICMP {
+            return;
+        }
+        let len = cmp::min(payload.len(), ip_header.get_payload_len() as usize);
+        let payload = &payload[..len];
+        let (offset, icmp_header) = match ICMP6Header::decode(payload).done() {
+            Some(decoded) => decoded,
+            None => return,
+        };
+        let body = &payload[offset..];
+
+        let nd_valid = ip_header.get_hop_limit() == ND_HOP_LIMIT && icmp_header.get_code() == 0;
+        match icmp_header.get_options() {
+            ICMP6HeaderOptions::Type128 { id, seqno } => {
+                self.echo_request(&ip_header, id, seqno, body)
+            }
+            ICMP6HeaderOptions::Type134 {
+                router_lifetime, ..
+            } if nd_valid => self.router_advertisement(&ip_header, router_lifetime, body),
+            ICMP6HeaderOptions::Type135 { .. } if nd_valid => {
+                self.neighbor_solicitation(&ip_header, body)
+            }
+            ICMP6HeaderOptions::Type136 { .. } if nd_valid => self.neighbor_advertisement(body),
+            _ => {}
+        }
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, S: IP6Sender<'a>> IP6RecvErrorClient for ICMP6Responder<'a, A, S> {
+    fn parameter_problem(&self, header: IP6Header, code: u8, pointer: u32, packet: &[u8]) {
+        // RFC 4443 2.4: no errors are sent to addresses that do not identify
+        // a single node, nor for multicast packets except for options that
+        // ask for it.
+        let src_addr = header.get_src_addr();
+        let dst_addr = header.get_dst_addr();
+        if src_addr.is_unspecified() || src_addr.is_multicast() {
+            return;
+        }
+        if dst_addr.is_multicast() && code != param_problem::UNRECOGNIZED_OPTION {
+            return;
+        }
+        let local_addr = if self.is_local_addr(dst_addr) {
+            dst_addr
+        } else {
+            match self.interface_list.first() {
+                Some(addr) => *addr,
+                None => return,
+            }
+        };
+        let mut icmp_header = ICMP6Header::new(ICMP6Type::Type4);
+        icmp_header.set_code(code);
+        icmp_header.set_options(ICMP6HeaderOptions::Type4 { pointer });
+        // The message carries as much of the discarded packet as fits
+        let _ = self.send_icmp(local_addr, src_addr, icmp_header, |buf| {
+            let len = cmp::min(buf.len(), packet.len());
+            buf[..len].copy_from_slice(&packet[..len]);
+            Some((len, len))
+        });
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, S: IP6Sender<'a>> IP6SendClient for ICMP6Responder<'a, A, S> {
+    fn send_done(&self, _result: Result<(), ErrorCode>) {
+        self.sending.set(false);
+        if self.nd_pending.take() {
+            self.send_nd_message();
+        }
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, S: IP6Sender<'a>> time::AlarmClient for ICMP6Responder<'a, A, S> {
+    fn alarm(&self) {
+        let interval = cmp::min(self.timer.get(), MAX_ALARM_INTERVAL);
+        self.timer.set(self.timer.get() - interval);
+        if self.timer.get() > 0 {
+            self.arm_alarm();
+        } else {
+            self.timer_expired();
+        }
+    }
+}
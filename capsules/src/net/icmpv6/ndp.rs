@@ -0,0 +1,249 @@
+//! This file contains the types and functions for encoding and decoding the
+//! options carried by Neighbor Discovery messages (RFC 4861), including the
+//! 6LoWPAN extensions for address registration (RFC 6775).
+//!
+//! The fixed part of a Neighbor Discovery message is split between the
+//! [ICMP6Header](../struct.ICMP6Header.html), which holds the first word
+//! after the checksum, and the ICMPv6 payload, which holds the remaining
+//! fields followed by the options.
+
+use crate::net::ieee802154::MacAddress;
+use crate::net::stream::SResult;
+use crate::net::stream::{decode_bytes, decode_u16, decode_u32, decode_u8};
+use crate::net::stream::{encode_bytes, encode_u16, encode_u8};
+
+/// Neighbor Discovery option types.
+pub mod nd_opt {
+    pub const SOURCE_LL_ADDR: u8 = 1;
+    pub const TARGET_LL_ADDR: u8 = 2;
+    pub const PREFIX_INFO: u8 = 3;
+    pub const MTU: u8 = 5;
+    pub const ADDR_REGISTRATION: u8 = 33;
+    pub const CONTEXT: u8 = 34;
+    pub const ABRO: u8 = 35;
+}
+
+/// Length of the fields of a Router Advertisement that precede the options
+/// in the ICMPv6 payload (reachable time and retransmission timer).
+pub const RA_FIELDS_LEN: usize = 8;
+
+/// Length of the fields of a Neighbor Solicitation or Advertisement that
+/// precede the options in the ICMPv6 payload (the target address).
+pub const NS_FIELDS_LEN: usize = 16;
+
+/// Neighbor Advertisement flags, in the first word of the ICMPv6 header.
+pub const NA_FLAG_ROUTER: u32 = 1 << 31;
+pub const NA_FLAG_SOLICITED: u32 = 1 << 30;
+pub const NA_FLAG_OVERRIDE: u32 = 1 << 29;
+
+/// Prefix Information option flags.
+pub const PREFIX_FLAG_ON_LINK: u8 = 0x80;
+pub const PREFIX_FLAG_AUTONOMOUS: u8 = 0x40;
+
+/// Address Registration option status values.
+pub mod aro_status {
+    pub const SUCCESS: u8 = 0;
+    pub const DUPLICATE: u8 = 1;
+    pub const CACHE_FULL: u8 = 2;
+}
+
+/// Length in bytes of an Address Registration option.
+pub const ARO_LEN: usize = 16;
+
+/// The contents of a Prefix Information option.
+#[derive(Copy, Clone)]
+pub struct PrefixInfo {
+    pub prefix_len: u8,
+    pub flags: u8,
+    /// Seconds the prefix is valid for, `u32::MAX` for infinity.
+    pub valid_lifetime: u32,
+    /// Seconds addresses from the prefix are preferred for.
+    pub preferred_lifetime: u32,
+    pub prefix: [u8; 16],
+}
+
+impl PrefixInfo {
+    pub fn is_autonomous(&self) -> bool {
+        self.flags & PREFIX_FLAG_AUTONOMOUS != 0
+    }
+}
+
+/// A decoded Neighbor Discovery option.
+#[derive(Copy, Clone)]
+pub enum NDOption {
+    SourceLinkLayerAddr(MacAddress),
+    TargetLinkLayerAddr(MacAddress),
+    PrefixInfo(PrefixInfo),
+    /// Address Registration option. The lifetime is in units of 60 seconds.
+    AddressRegistration {
+        status: u8,
+        lifetime: u16,
+        eui64: [u8; 8],
+    },
+    /// An option that is not interpreted, with its type.
+    Other(u8),
+}
+
+impl NDOption {
+    /// Deserializes one option from the start of `buf`, returning the
+    /// length of the option in bytes.
+    pub fn decode(buf: &[u8]) -> SResult<NDOption> {
+        let (off, opt_type) = dec_try!(buf, 0; decode_u8);
+        let (off, len_units) = dec_try!(buf, off; decode_u8);
+        // Options with a length of zero must be dropped, together with the
+        // rest of the message.
+        stream_cond!(len_units != 0, ());
+        let len = len_units as usize * 8;
+        stream_len_cond!(buf, len);
+
+        let option = match opt_type {
+            nd_opt::SOURCE_LL_ADDR | nd_opt::TARGET_LL_ADDR => {
+                // RFC 4944: one unit holds a short address, two units an
+                // extended address.
+                let mac_addr = match len_units {
+                    1 => {
+                        let (_, short_addr) = dec_try!(buf, off; decode_u16);
+                        MacAddress::Short(short_addr)
+                    }
+                    2 => {
+                        let mut long_addr = [0; 8];
+                        dec_consume!(buf, off; decode_bytes, &mut long_addr);
+                        MacAddress::Long(long_addr)
+                    }
+                    _ => return SResult::Done(len, NDOption::Other(opt_type)),
+                };
+                if opt_type == nd_opt::SOURCE_LL_ADDR {
+                    NDOption::SourceLinkLayerAddr(mac_addr)
+                } else {
+                    NDOption::TargetLinkLayerAddr(mac_addr)
+                }
+            }
+            nd_opt::PREFIX_INFO => {
+                stream_cond!(len_units == 4, ());
+                let (off, prefix_len) = dec_try!(buf, off; decode_u8);
+                let (off, flags) = dec_try!(buf, off; decode_u8);
+                let (off, valid_lifetime) = dec_try!(buf, off; decode_u32);
+                let (off, preferred_lifetime) = dec_try!(buf, off; decode_u32);
+                // Skip the reserved word
+                let off = off + 4;
+                let mut prefix = [0; 16];
+                dec_consume!(buf, off; decode_bytes, &mut prefix);
+                NDOption::PrefixInfo(PrefixInfo {
+                    prefix_len,
+                    flags,
+                    valid_lifetime,
+                    preferred_lifetime,
+                    prefix,
+                })
+            }
+            nd_opt::ADDR_REGISTRATION => {
+                stream_cond!(len_units == 2, ());
+                let (off, status) = dec_try!(buf, off; decode_u8);
+                // Skip the reserved bytes
+                let off = off + 3;
+                let (off, lifetime) = dec_try!(buf, off; decode_u16);
+                let mut eui64 = [0; 8];
+                dec_consume!(buf, off; decode_bytes, &mut eui64);
+                NDOption::AddressRegistration {
+                    status,
+                    lifetime,
+                    eui64,
+                }
+            }
+            _ => NDOption::Other(opt_type),
+        };
+        stream_done!(len, option);
+    }
+}
+
+/// Iterates over the options of a Neighbor Discovery message. The iteration
+/// ends at the first malformed option.
+pub struct NDOptionIter<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> NDOptionIter<'a> {
+    /// `buf` - The options of the message, following its fixed fields
+    pub fn new(buf: &'a [u8]) -> NDOptionIter<'a> {
+        NDOptionIter { buf: buf }
+    }
+}
+
+impl<'a> Iterator for NDOptionIter<'a> {
+    type Item = NDOption;
+
+    fn next(&mut self) -> Option<NDOption> {
+        match NDOption::decode(self.buf).done() {
+            Some((len, option)) => {
+                self.buf = &self.buf[len..];
+                Some(option)
+            }
+            None => None,
+        }
+    }
+}
+
+/// Serializes a Source or Target Link-layer Address option.
+///
+/// # Arguments
+///
+/// `buf` - The buffer to serialize the option into
+/// `offset` - The current offset into the provided buffer
+/// `opt_type` - `nd_opt::SOURCE_LL_ADDR` or `nd_opt::TARGET_LL_ADDR`
+/// `mac_addr` - The link-layer address
+///
+/// # Return Value
+///
+/// This function returns the new offset into the buffer, wrapped in an
+/// SResult
+pub fn encode_ll_addr_option(
+    buf: &mut [u8],
+    offset: usize,
+    opt_type: u8,
+    mac_addr: MacAddress,
+) -> SResult<usize> {
+    let (len_units, padding) = match mac_addr {
+        MacAddress::Short(_) => (1, 4),
+        MacAddress::Long(_) => (2, 6),
+    };
+    let mut off = offset;
+    off = enc_consume!(buf, off; encode_u8, opt_type);
+    off = enc_consume!(buf, off; encode_u8, len_units);
+    off = match mac_addr {
+        MacAddress::Short(short_addr) => enc_consume!(buf, off; encode_u16, short_addr),
+        MacAddress::Long(long_addr) => enc_consume!(buf, off; encode_bytes, &long_addr),
+    };
+    off = enc_consume!(buf, off; encode_bytes, &[0; 6][..padding]);
+    stream_done!(off, off);
+}
+
+/// Serializes an Address Registration option.
+///
+/// # Arguments
+///
+/// `buf` - The buffer to serialize the option into
+/// `offset` - The current offset into the provided buffer
+/// `status` - The registration status, `aro_status::SUCCESS` in requests
+/// `lifetime` - The registration lifetime in units of 60 seconds
+/// `eui64` - The EUI-64 identifying the registering node
+///
+/// # Return Value
+///
+/// This function returns the new offset into the buffer, wrapped in an
+/// SResult
+pub fn encode_aro(
+    buf: &mut [u8],
+    offset: usize,
+    status: u8,
+    lifetime: u16,
+    eui64: &[u8; 8],
+) -> SResult<usize> {
+    let mut off = offset;
+    off = enc_consume!(buf, off; encode_u8, nd_opt::ADDR_REGISTRATION);
+    off = enc_consume!(buf, off; encode_u8, (ARO_LEN / 8) as u8);
+    off = enc_consume!(buf, off; encode_u8, status);
+    off = enc_consume!(buf, off; encode_bytes, &[0; 3]);
+    off = enc_consume!(buf, off; encode_u16, lifetime);
+    off = enc_consume!(buf, off; encode_bytes, eui64);
+    stream_done!(off, off);
+}
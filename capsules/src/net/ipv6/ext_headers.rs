@@ -0,0 +1,973 @@
+//! This file contains the types and functions for encoding and decoding the
+//! IPv6 extension headers (RFC 8200) the stack understands: the Hop-by-Hop
+//! and Destination Options headers, the Routing header carrying an RPL
+//! Source Route (RFC 6554), and the Fragment header.
+//!
+//! Extension headers of outgoing packets are serialized as they are added
+//! to an [ExtensionHeaders](struct.ExtensionHeaders.html) struct, which an
+//! `IP6Packet` carries between its `IP6Header` and its `IPPayload`. This
+//! sidesteps the recursive headers the `IP6Packet` design cannot express:
+//! the only field that depends on the transport header, the Next Header of
+//! the last extension header, is filled in when the packet is encoded.
+//!
+//! Extension headers of received packets are walked one at a time with
+//! [ExtHeader::decode](struct.ExtHeader.html#method.decode), which returns
+//! the type and the offset of the header that follows.
+
+use crate::net::ipv6::ip_utils::{ip6_nh, IPAddr};
+use crate::net::stream::SResult;
+use crate::net::stream::{decode_u16, decode_u32, decode_u8};
+use crate::net::stream::{encode_bytes, encode_u16, encode_u32, encode_u8};
+
+use kernel::ErrorCode;
+
+/// The space reserved for the extension headers of an outgoing packet.
+pub const MAX_EXT_HDRS_LEN: usize = 64;
+
+/// Length in bytes of the Fragment header.
+pub const FRAGMENT_HDR_LEN: usize = 8;
+
+/// Length in bytes of the fixed part of a Routing header, which precedes
+/// the addresses of a Source Route.
+const ROUTING_HDR_LEN: usize = 8;
+
+/// Option types of the Hop-by-Hop and Destination Options headers.
+pub mod ip6_opt {
+    pub const PAD1: u8 = 0;
+    pub const PADN: u8 = 1;
+    /// The RPL Option (RFC 6553), carried in the Hop-by-Hop Options header.
+    pub const RPL: u8 = 0x63;
+}
+
+/// Routing header types.
+pub mod routing_type {
+    /// The RPL Source Route header (RFC 6554).
+    pub const RPL_SOURCE_ROUTE: u8 = 3;
+}
+
+/// What a node must do with a packet carrying an option it does not
+/// recognize, given by the two highest bits of the option type.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum OptionAction {
+    /// Skip the option and continue processing the header.
+    Skip,
+    /// Discard the packet.
+    Discard,
+    /// Discard the packet and send an ICMPv6 Parameter Problem message to
+    /// its source.
+    DiscardAndReport,
+    /// Discard the packet and send an ICMPv6 Parameter Problem message to
+    /// its source, unless its destination was a multicast address.
+    DiscardAndReportUnicast,
+}
+
+impl OptionAction {
+    pub fn from_type(opt_type: u8) -> OptionAction {
+        match opt_type >> 6 {
+            0 => OptionAction::Skip,
+            1 => OptionAction::Discard,
+            2 => OptionAction::DiscardAndReport,
+            _ => OptionAction::DiscardAndReportUnicast,
+        }
+    }
+}
+
+/// The fields common to all extension headers.
+#[derive(Copy, Clone)]
+pub struct ExtHeader {
+    /// The type of the header that follows this one
+    pub next_header: u8,
+    /// The length of this header in bytes
+    pub len: usize,
+}
+
+impl ExtHeader {
+    /// Deserializes the common fields of an extension header, and checks
+    /// that the whole header is in the buffer.
+    ///
+    /// # Arguments
+    ///
+    /// `buf` - The buffer, starting with the extension header
+    /// `hdr_type` - The `ip6_nh` type of the extension header
+    ///
+    /// # Return Value
+    ///
+    /// This function returns the `ExtHeader` wrapped in an SResult, whose
+    /// offset is the length of the header. Header types that are not
+    /// extension headers are an error.
+    pub fn decode(buf: &[u8], hdr_type: u8) -> SResult<ExtHeader> {
+        let (off, next_header) = dec_try!(buf, 0; decode_u8);
+        let len = match hdr_type {
+            ip6_nh::FRAGMENT => FRAGMENT_HDR_LEN,
+            ip6_nh::HOP_OPTS | ip6_nh::ROUTING | ip6_nh::DST_OPTS => {
+                let (_, len_units) = dec_try!(buf, off; decode_u8);
+                (len_units as usize + 1) * 8
+            }
+            _ => stream_err!(),
+        };
+        stream_len_cond!(buf, len);
+        stream_done!(len, ExtHeader { next_header, len });
+    }
+}
+
+/// An option of a Hop-by-Hop or Destination Options header.
+#[derive(Copy, Clone)]
+pub struct IP6Option<'a> {
+    pub opt_type: u8,
+    pub data: &'a [u8],
+}
+
+/// Iterates over the options of a Hop-by-Hop or Destination Options header,
+/// skipping the padding. Each item holds the offset of the option into the
+/// header, so that a Parameter Problem message can point to it. An option
+/// that overruns the header is returned as an error, which ends the
+/// iteration.
+pub struct IP6OptionIter<'a> {
+    hdr: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> IP6OptionIter<'a> {
+    /// `hdr` - The whole options header, as delimited by `ExtHeader::decode`
+    pub fn new(hdr: &'a [u8]) -> IP6OptionIter<'a> {
+        IP6OptionIter {
+            hdr: hdr,
+            offset: 2,
+        }
+    }
+}
+
+impl<'a> Iterator for IP6OptionIter<'a> {
+    type Item = Result<(usize, IP6Option<'a>), ()>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.offset < self.hdr.len() {
+            let opt_offset = self.offset;
+            let opt_type = self.hdr[opt_offset];
+            if opt_type == ip6_opt::PAD1 {
+                self.offset += 1;
+                continue;
+            }
+            let data = self.hdr.get(opt_offset + 1).and_then(|&data_len| {
+                self.hdr
+                    .get(opt_offset + 2..opt_offset + 2 + data_len as usize)
+            });
+            let data = match data {
+                Some(data) => data,
+                None => {
+                    self.offset = self.hdr.len();
+                    return Some(Err(()));
+                }
+            };
+            self.offset = opt_offset + 2 + data.len();
+            if opt_type != ip6_opt::PADN {
+                return Some(Ok((opt_offset, IP6Option { opt_type, data })));
+            }
+        }
+        None
+    }
+}
+
+/// RPL Option flags.
+pub const RPL_OPT_FLAG_DOWN: u8 = 0x80;
+pub const RPL_OPT_FLAG_RANK_ERROR: u8 = 0x40;
+pub const RPL_OPT_FLAG_FORWARDING_ERROR: u8 = 0x20;
+
+/// Length in bytes of the data of the RPL Option.
+const RPL_OPT_DATA_LEN: usize = 4;
+
+/// The RPL Option (RFC 6553), which carries the RPL instance and the rank of
+/// the sender in the Hop-by-Hop Options header of packets routed by RPL.
+#[derive(Copy, Clone, PartialEq)]
+pub struct RplOption {
+    pub flags: u8,
+    pub instance_id: u8,
+    pub sender_rank: u16,
+}
+
+impl RplOption {
+    /// Deserializes the RPL Option from the data of an `IP6Option`.
+    pub fn decode(data: &[u8]) -> SResult<RplOption> {
+        stream_cond!(data.len() == RPL_OPT_DATA_LEN, ());
+        let (off, flags) = dec_try!(data, 0; decode_u8);
+        let (off, instance_id) = dec_try!(data, off; decode_u8);
+        let (off, sender_rank) = dec_try!(data, off; decode_u16);
+        stream_done!(
+            off,
+            RplOption {
+                flags,
+                instance_id,
+                sender_rank,
+            }
+        );
+    }
+
+    /// Serializes the whole option, including its type and length, so that
+    /// it can be passed to `ExtensionHeaders::add_hop_by_hop`.
+    pub fn encode(&self, buf: &mut [u8], offset: usize) -> SResult<usize> {
+        let mut off = offset;
+        off = enc_consume!(buf, off; encode_u8, ip6_opt::RPL);
+        off = enc_consume!(buf, off; encode_u8, RPL_OPT_DATA_LEN as u8);
+        off = enc_consume!(buf, off; encode_u8, self.flags);
+        off = enc_consume!(buf, off; encode_u8, self.instance_id);
+        off = enc_consume!(buf, off; encode_u16, self.sender_rank);
+        stream_done!(off, off);
+    }
+}
+
+/// The fields common to all Routing headers.
+#[derive(Copy, Clone)]
+pub struct RoutingHeader {
+    pub routing_type: u8,
+    /// The number of route segments before the final destination
+    pub segments_left: u8,
+}
+
+impl RoutingHeader {
+    /// `hdr` - The whole Routing header, as delimited by `ExtHeader::decode`
+    pub fn decode(hdr: &[u8]) -> SResult<RoutingHeader> {
+        stream_len_cond!(hdr, 4);
+        let (off, routing_type) = dec_try!(hdr, 2; decode_u8);
+        let (off, segments_left) = dec_try!(hdr, off; decode_u8);
+        stream_done!(
+            off,
+            RoutingHeader {
+                routing_type,
+                segments_left,
+            }
+        );
+    }
+}
+
+/// An RPL Source Route header (RFC 6554). Its addresses are elided down to
+/// the bytes that differ from the IPv6 destination address: the last
+/// address by `cmpr_e` bytes, the others by `cmpr_i` bytes.
+#[derive(Copy, Clone)]
+pub struct SourceRoute<'a> {
+    pub segments_left: u8,
+    cmpr_i: usize,
+    cmpr_e: usize,
+    num_addrs: usize,
+    addrs: &'a [u8],
+}
+
+impl<'a> SourceRoute<'a> {
+    /// `hdr` - The whole Routing header, as delimited by `ExtHeader::decode`
+    pub fn decode(hdr: &'a [u8]) -> SResult<SourceRoute<'a>> {
+        let (off, routing) = dec_try!(hdr, 0; RoutingHeader::decode);
+        stream_cond!(routing.routing_type == routing_type::RPL_SOURCE_ROUTE, ());
+        stream_len_cond!(hdr, ROUTING_HDR_LEN);
+        let (_, cmpr) = dec_try!(hdr, off; decode_u8);
+        let (_, pad) = dec_try!(hdr, off + 1; decode_u8);
+        let cmpr_i = (cmpr >> 4) as usize;
+        let cmpr_e = (cmpr & 0x0f) as usize;
+        let pad = (pad >> 4) as usize;
+
+        // All addresses but the last one have the same length
+        let addrs_len = hdr.len() - ROUTING_HDR_LEN;
+        stream_cond!(addrs_len >= pad + 16 - cmpr_e, ());
+        let first_addrs_len = addrs_len - pad - (16 - cmpr_e);
+        stream_cond!(first_addrs_len % (16 - cmpr_i) == 0, ());
+        let num_addrs = first_addrs_len / (16 - cmpr_i) + 1;
+        stream_cond!(routing.segments_left as usize <= num_addrs, ());
+        stream_done!(
+            hdr.len(),
+            SourceRoute {
+                segments_left: routing.segments_left,
+                cmpr_i,
+                cmpr_e,
+                num_addrs,
+                addrs: &hdr[ROUTING_HDR_LEN..hdr.len() - pad],
+            }
+        );
+    }
+
+    pub fn num_addrs(&self) -> usize {
+        self.num_addrs
+    }
+
+    /// Returns the address of the route at `index`, restoring the elided
+    /// bytes from the IPv6 destination address `dst_addr`. The last address
+    /// is the final destination.
+    pub fn address(&self, index: usize, dst_addr: IPAddr) -> Option<IPAddr> {
+        if index >= self.num_addrs {
+            return None;
+        }
+        let elided = if index + 1 == self.num_addrs {
+            self.cmpr_e
+        } else {
+            self.cmpr_i
+        };
+        let start = index * (16 - self.cmpr_i);
+        let mut addr = dst_addr;
+        addr.0[elided..].copy_from_slice(&self.addrs[start..start + 16 - elided]);
+        Some(addr)
+    }
+}
+
+/// The Fragment header. The stack does not reassemble IPv6 fragments, so
+/// only atomic fragments (RFC 6946) can be received.
+#[derive(Copy, Clone)]
+pub struct FragmentHeader {
+    /// Offset of the fragment in units of 8 bytes
+    pub offset: u16,
+    /// Whether more fragments follow
+    pub more: bool,
+    pub id: u32,
+}
+
+impl FragmentHeader {
+    /// `hdr` - The Fragment header, as delimited by `ExtHeader::decode`
+    pub fn decode(hdr: &[u8]) -> SResult<FragmentHeader> {
+        stream_len_cond!(hdr, FRAGMENT_HDR_LEN);
+        let (off, offset_flags) = dec_try!(hdr, 2; decode_u16);
+        let (off, id) = dec_try!(hdr, off; decode_u32);
+        stream_done!(
+            off,
+            FragmentHeader {
+                offset: offset_flags >> 3,
+                more: offset_flags & 1 != 0,
+                id,
+            }
+        );
+    }
+
+    /// Serializes the header, with `ip6_nh::NO_NEXT` as its next header.
+    pub fn encode(&self, buf: &mut [u8], offset: usize) -> SResult<usize> {
+        let mut off = offset;
+        off = enc_consume!(buf, off; encode_u8, ip6_nh::NO_NEXT);
+        off = enc_consume!(buf, off; encode_u8, 0);
+        off = enc_consume!(buf, off; encode_u16, self.offset << 3 | self.more as u16);
+        off = enc_consume!(buf, off; encode_u32, self.id);
+        stream_done!(off, off);
+    }
+
+    /// A fragment that is the whole packet
+    pub fn is_atomic(&self) -> bool {
+        self.offset == 0 && !self.more
+    }
+}
+
+/// The number of leading bytes two addresses share, at most 15 as that is
+/// the most a Source Route can elide.
+fn elided_len(addr: &IPAddr, dst_addr: &IPAddr) -> usize {
+    addr.0
+        .iter()
+        .zip(dst_addr.0.iter())
+        .take(15)
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Serializes a Hop-by-Hop or Destination Options header holding the
+/// serialized `options`, padded to a multiple of 8 bytes.
+fn encode_options_header(buf: &mut [u8], options: &[u8]) -> SResult<usize> {
+    let len = (2 + options.len() + 7) / 8 * 8;
+    stream_cond!(len / 8 <= 256, ());
+    let mut off = enc_consume!(buf, 0; encode_u8, ip6_nh::NO_NEXT);
+    off = enc_consume!(buf, off; encode_u8, (len / 8 - 1) as u8);
+    off = enc_consume!(buf, off; encode_bytes, options);
+    off = match len - off {
+        0 => off,
+        1 => enc_consume!(buf, off; encode_u8, ip6_opt::PAD1),
+        padding => {
+            off = enc_consume!(buf, off; encode_u8, ip6_opt::PADN);
+            off = enc_consume!(buf, off; encode_u8, (padding - 2) as u8);
+            enc_consume!(buf, off; encode_bytes, &[0; 6][..padding - 2])
+        }
+    };
+    stream_done!(off, off);
+}
+
+/// Serializes an RPL Source Route header.
+///
+/// # Arguments
+///
+/// `buf` - The buffer to serialize the header into
+/// `dst_addr` - The IPv6 destination address, which is the first hop
+/// `route` - The hops that follow, ending with the final destination
+fn encode_source_route(buf: &mut [u8], dst_addr: &IPAddr, route: &[IPAddr]) -> SResult<usize> {
+    let (last, hops) = stream_from_option!(route.split_last());
+    stream_cond!(route.len() <= u8::MAX as usize, ());
+    let cmpr_i = hops
+        .iter()
+        .map(|hop| elided_len(hop, dst_addr))
+        .min()
+        .unwrap_or(0);
+    let cmpr_e = elided_len(last, dst_addr);
+    let addrs_len = hops.len() * (16 - cmpr_i) + 16 - cmpr_e;
+    let pad = (8 - addrs_len % 8) % 8;
+    let len = ROUTING_HDR_LEN + addrs_len + pad;
+    stream_cond!(len / 8 <= 256, ());
+
+    let mut off = enc_consume!(buf, 0; encode_u8, ip6_nh::NO_NEXT);
+    off = enc_consume!(buf, off; encode_u8, (len / 8 - 1) as u8);
+    off = enc_consume!(buf, off; encode_u8, routing_type::RPL_SOURCE_ROUTE);
+    off = enc_consume!(buf, off; encode_u8, route.len() as u8);
+    off = enc_consume!(buf, off; encode_u8, (cmpr_i << 4 | cmpr_e) as u8);
+    off = enc_consume!(buf, off; encode_u8, (pad << 4) as u8);
+    off = enc_consume!(buf, off; encode_u16, 0);
+    for hop in hops {
+        off = enc_consume!(buf, off; encode_bytes, &hop.0[cmpr_i..]);
+    }
+    off = enc_consume!(buf, off; encode_bytes, &last.0[cmpr_e..]);
+    off = enc_consume!(buf, off; encode_bytes, &[0; 8][..pad]);
+    stream_done!(off, off);
+}
+
+/// The extension headers of an outgoing packet, serialized in the order
+/// they are added. RFC 8200 recommends the order Hop-by-Hop Options,
+/// Destination Options, Routing, Fragment, which callers should follow; only
+/// the Hop-by-Hop Options header is required to come first.
+#[derive(Copy, Clone)]
+pub struct ExtensionHeaders {
+    buf: [u8; MAX_EXT_HDRS_LEN],
+    len: usize,
+    first_header: Option<u8>,
+    /// The offset of the last header, whose next header is only known once
+    /// the packet is encoded
+    last_header: usize,
+    final_dst: Option<IPAddr>,
+}
+
+impl Default for ExtensionHeaders {
+    fn default() -> ExtensionHeaders {
+        ExtensionHeaders {
+            buf: [0; MAX_EXT_HDRS_LEN],
+            len: 0,
+            first_header: None,
+            last_header: 0,
+            final_dst: None,
+        }
+    }
+}
+
+impl ExtensionHeaders {
+    pub fn new() -> ExtensionHeaders {
+        ExtensionHeaders::default()
+    }
+
+    pub fn clear(&mut self) {
+        *self = ExtensionHeaders::default();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The length in bytes of the serialized headers
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The `ip6_nh` type of the first header, which the IPv6 header points
+    /// to
+    pub fn first_header(&self) -> Option<u8> {
+        self.first_header
+    }
+
+    /// The final destination of a source-routed packet, which the transport
+    /// checksum covers instead of the IPv6 destination address
+    pub fn final_destination(&self) -> Option<IPAddr> {
+        self.final_dst
+    }
+
+    /// Adds the Hop-by-Hop Options header, which must be the first header.
+    ///
+    /// # Arguments
+    ///
+    /// `options` - The serialized options, e.g. from `RplOption::encode`
+    pub fn add_hop_by_hop(&mut self, options: &[u8]) -> Result<(), ErrorCode> {
+        if !self.is_empty() {
+            return Err(ErrorCode::INVAL);
+        }
+        self.push(ip6_nh::HOP_OPTS, |buf| encode_options_header(buf, options))
+    }
+
+    /// Adds a Destination Options header.
+    ///
+    /// # Arguments
+    ///
+    /// `options` - The serialized options
+    pub fn add_destination_options(&mut self, options: &[u8]) -> Result<(), ErrorCode> {
+        self.push(ip6_nh::DST_OPTS, |buf| encode_options_header(buf, options))
+    }
+
+    /// Adds an RPL Source Route header. The packet must be sent to the first
+    /// hop of the route.
+    ///
+    /// # Arguments
+    ///
+    /// `dst_addr` - The destination address of the packet, the first hop
+    /// `route` - The hops that follow, ending with the final destination
+    pub fn add_source_route(
+        &mut self,
+        dst_addr: IPAddr,
+        route: &[IPAddr],
+    ) -> Result<(), ErrorCode> {
+        self.push(ip6_nh::ROUTING, |buf| {
+            encode_source_route(buf, &dst_addr, route)
+        })?;
+        self.final_dst = route.last().copied();
+        Ok(())
+    }
+
+    /// Adds a Fragment header.
+    pub fn add_fragment(&mut self, fragment: FragmentHeader) -> Result<(), ErrorCode> {
+        self.push(ip6_nh::FRAGMENT, |buf| fragment.encode(buf, 0))
+    }
+
+    /// Serializes a header with `encode` after the current ones, and links
+    /// it to the header before it.
+    fn push<F>(&mut self, hdr_type: u8, encode: F) -> Result<(), ErrorCode>
+    where
+        F: FnOnce(&mut [u8]) -> SResult<usize>,
+    {
+        let start = self.len;
+        let len = match encode(&mut self.buf[start..]).done() {
+            Some((len, _)) => len,
+            None => return Err(ErrorCode::SIZE),
+        };
+        match self.first_header {
+            Some(_) => self.buf[self.last_header] = hdr_type,
+            None => self.first_header = Some(hdr_type),
+        }
+        self.last_header = start;
+        self.len = start + len;
+        Ok(())
+    }
+
+    /// Serializes the headers into a buffer.
+    ///
+    /// # Arguments
+    ///
+    /// `buf` - The buffer to serialize the headers into
+    /// `offset` - The current offset into the provided buffer
+    /// `next_header` - The `ip6_nh` type of the header that follows the
+    /// last extension header
+    ///
+    /// # Return Value
+    ///
+    /// This function returns the new offset into the buffer, wrapped in an
+    /// SResult
+    pub fn encode(&self, buf: &mut [u8], offset: usize, next_header: u8) -> SResult<usize> {
+        let off = enc_consume!(buf, offset; encode_bytes, &self.buf[..self.len]);
+        if !self.is_empty() {
+            buf[offset + self.last_header] = next_header;
+        }
+        stream_done!(off, off);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DST: IPAddr = IPAddr([
+        0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0x02, 0x11, 0x22, 0xff, 0xfe, 0x33, 0x44, 0x55,
+    ]);
+
+    /// An address sharing exactly its first `shared` bytes with `DST`.
+    fn hop(shared: usize, seed: u8) -> IPAddr {
+        let mut addr = DST;
+        for (i, byte) in addr.0.iter_mut().enumerate().skip(shared) {
+            *byte = !DST.0[i] ^ seed;
+        }
+        addr
+    }
+
+    /// Builds an RPL Source Route header by hand, so that any combination
+    /// of compression and padding can be tested. Returns the header length.
+    fn build_source_route(
+        buf: &mut [u8],
+        segments_left: u8,
+        cmpr_i: usize,
+        cmpr_e: usize,
+        pad: usize,
+        route: &[IPAddr],
+    ) -> usize {
+        let (last, hops) = route.split_last().unwrap();
+        buf[0] = ip6_nh::NO_NEXT;
+        buf[2] = routing_type::RPL_SOURCE_ROUTE;
+        buf[3] = segments_left;
+        buf[4] = (cmpr_i << 4 | cmpr_e) as u8;
+        buf[5] = (pad << 4) as u8;
+        buf[6] = 0;
+        buf[7] = 0;
+        let mut off = ROUTING_HDR_LEN;
+        for hop in hops {
+            buf[off..off + 16 - cmpr_i].copy_from_slice(&hop.0[cmpr_i..]);
+            off += 16 - cmpr_i;
+        }
+        buf[off..off + 16 - cmpr_e].copy_from_slice(&last.0[cmpr_e..]);
+        off += 16 - cmpr_e;
+        for byte in &mut buf[off..off + pad] {
+            *byte = 0xff;
+        }
+        off += pad;
+        buf[1] = ((off + 7) / 8 - 1) as u8;
+        off
+    }
+
+    #[test]
+    fn source_route_decode_all_compressions() {
+        let mut buf = [0; 64];
+        for cmpr_i in 0..16 {
+            for cmpr_e in 0..16 {
+                for pad in 0..8 {
+                    for num_hops in 0..3 {
+                        let route = [hop(cmpr_i, 1), hop(cmpr_i, 2), hop(cmpr_e, 3)];
+                        let route = &route[2 - num_hops..];
+                        let len = build_source_route(
+                            &mut buf,
+                            route.len() as u8,
+                            cmpr_i,
+                            cmpr_e,
+                            pad,
+                            route,
+                        );
+
+                        let (off, sr) = SourceRoute::decode(&buf[..len]).done().unwrap();
+                        assert_eq!(off, len);
+                        assert_eq!(sr.segments_left as usize, route.len());
+                        assert_eq!(sr.num_addrs(), route.len());
+                        for (i, addr) in route.iter().enumerate() {
+                            assert_eq!(sr.address(i, DST), Some(*addr));
+                        }
+                        assert_eq!(sr.address(route.len(), DST), None);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn source_route_decode_malformed() {
+        let mut buf = [0; 64];
+        let route = [hop(8, 1), hop(12, 2)];
+
+        // Too short for the fixed part of the header
+        let len = build_source_route(&mut buf, 2, 8, 12, 0, &route);
+        assert!(SourceRoute::decode(&buf[..4]).is_needed());
+        assert!(SourceRoute::decode(&buf[..7]).is_needed());
+
+        // Not an RPL Source Route
+        buf[2] = 0;
+        assert!(SourceRoute::decode(&buf[..len]).is_err());
+        buf[2] = routing_type::RPL_SOURCE_ROUTE;
+
+        // More segments left than addresses
+        buf[3] = 3;
+        assert!(SourceRoute::decode(&buf[..len]).is_err());
+        buf[3] = 2;
+        assert!(SourceRoute::decode(&buf[..len]).is_done());
+
+        // The addresses don't add up to whole compressed addresses
+        assert!(SourceRoute::decode(&buf[..len - 1]).is_err());
+        assert!(SourceRoute::decode(&buf[..len + 1]).is_err());
+
+        // Padding that leaves no room for the last address
+        buf[5] = 0xf0;
+        assert!(SourceRoute::decode(&buf[..len]).is_err());
+        buf[5] = 0;
+
+        // No room for the last address at all
+        let len = build_source_route(&mut buf, 1, 0, 0, 0, &route[1..]);
+        assert!(SourceRoute::decode(&buf[..len - 8]).is_err());
+    }
+
+    #[test]
+    fn source_route_encode_roundtrip() {
+        let routes: [&[IPAddr]; 6] = [
+            &[DST],
+            &[hop(0, 1)],
+            &[hop(15, 1)],
+            &[hop(10, 1), hop(14, 2)],
+            &[hop(14, 1), hop(6, 2), DST],
+            &[hop(3, 1), hop(3, 2), hop(0, 3)],
+        ];
+        for route in routes.iter() {
+            let mut headers = ExtensionHeaders::new();
+            headers.add_source_route(DST, route).unwrap();
+            assert_eq!(headers.first_header(), Some(ip6_nh::ROUTING));
+            assert_eq!(headers.final_destination(), route.last().copied());
+
+            let mut buf = [0; MAX_EXT_HDRS_LEN];
+            let (len, _) = headers.encode(&mut buf, 0, ip6_nh::UDP).done().unwrap();
+            assert_eq!(len, headers.len());
+            assert_eq!(len % 8, 0);
+
+            let (_, hdr) = ExtHeader::decode(&buf[..len], ip6_nh::ROUTING)
+                .done()
+                .unwrap();
+            assert_eq!(hdr.next_header, ip6_nh::UDP);
+            assert_eq!(hdr.len, len);
+            let (_, sr) = SourceRoute::decode(&buf[..len]).done().unwrap();
+            assert_eq!(sr.segments_left as usize, route.len());
+            assert_eq!(sr.num_addrs(), route.len());
+            for (i, addr) in route.iter().enumerate() {
+                assert_eq!(sr.address(i, DST), Some(*addr));
+            }
+        }
+
+        let mut headers = ExtensionHeaders::new();
+        assert_eq!(headers.add_source_route(DST, &[]), Err(ErrorCode::SIZE));
+        assert!(headers.is_empty());
+        assert_eq!(headers.final_destination(), None);
+    }
+
+    #[test]
+    fn ext_header_decode_lengths() {
+        let mut buf = [0; 24];
+        buf[0] = ip6_nh::UDP;
+        buf[1] = 1;
+
+        for &hdr_type in [ip6_nh::HOP_OPTS, ip6_nh::ROUTING, ip6_nh::DST_OPTS].iter() {
+            let (off, hdr) = ExtHeader::decode(&buf, hdr_type).done().unwrap();
+            assert_eq!(off, 16);
+            assert_eq!(hdr.len, 16);
+            assert_eq!(hdr.next_header, ip6_nh::UDP);
+            assert!(ExtHeader::decode(&buf[..15], hdr_type).is_needed());
+            assert!(ExtHeader::decode(&buf[..1], hdr_type).is_needed());
+        }
+
+        let (off, hdr) = ExtHeader::decode(&buf, ip6_nh::FRAGMENT).done().unwrap();
+        assert_eq!(off, FRAGMENT_HDR_LEN);
+        assert_eq!(hdr.len, FRAGMENT_HDR_LEN);
+        assert!(ExtHeader::decode(&buf[..7], ip6_nh::FRAGMENT).is_needed());
+
+        assert!(ExtHeader::decode(&buf, ip6_nh::UDP).is_err());
+        assert!(ExtHeader::decode(&buf, ip6_nh::NO_NEXT).is_err());
+        assert!(ExtHeader::decode(&[], ip6_nh::HOP_OPTS).is_needed());
+    }
+
+    #[test]
+    fn option_iter() {
+        let hdr = [
+            ip6_nh::UDP,
+            1,
+            ip6_opt::PAD1,
+            ip6_opt::RPL,
+            4,
+            RPL_OPT_FLAG_DOWN,
+            7,
+            0x01,
+            0x00,
+            ip6_opt::PADN,
+            1,
+            0,
+            0x1e,
+            0,
+            ip6_opt::PAD1,
+            ip6_opt::PAD1,
+        ];
+        let mut iter = IP6OptionIter::new(&hdr);
+
+        let (offset, opt) = iter.next().unwrap().unwrap();
+        assert_eq!(offset, 3);
+        assert_eq!(opt.opt_type, ip6_opt::RPL);
+        let (_, rpl) = RplOption::decode(opt.data).done().unwrap();
+        assert!(
+            rpl == RplOption {
+                flags: RPL_OPT_FLAG_DOWN,
+                instance_id: 7,
+                sender_rank: 0x100,
+            }
+        );
+
+        let (offset, opt) = iter.next().unwrap().unwrap();
+        assert_eq!(offset, 12);
+        assert_eq!(opt.opt_type, 0x1e);
+        assert_eq!(opt.data.len(), 0);
+
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn option_iter_overrun() {
+        // The data of the option runs past the header
+        let hdr = [ip6_nh::UDP, 0, ip6_opt::PAD1, 0x1e, 4, 0, 0, ip6_opt::PAD1];
+        let mut iter = IP6OptionIter::new(&hdr);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+
+        // So does the padding
+        let hdr = [ip6_nh::UDP, 0, ip6_opt::PADN, 6, 0, 0, 0, 0];
+        let mut iter = IP6OptionIter::new(&hdr);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+
+        // The option has no room for its length
+        let hdr = [ip6_nh::UDP, 0, ip6_opt::PADN, 3, 0, 0, 0, 0x1e];
+        let mut iter = IP6OptionIter::new(&hdr);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+
+        // Options before the overrun one are still returned
+        let hdr = [ip6_nh::UDP, 0, 0x1e, 0, 0x1f, 4, 0, 0];
+        let mut iter = IP6OptionIter::new(&hdr);
+        assert_eq!(iter.next().unwrap().unwrap().0, 2);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn options_header_padding() {
+        let mut options = [0; 16];
+        for data_len in 0..14 {
+            options[0] = 0x1e;
+            options[1] = data_len as u8;
+            let options = &options[..2 + data_len];
+
+            let mut headers = ExtensionHeaders::new();
+            headers.add_destination_options(options).unwrap();
+            let mut buf = [0; MAX_EXT_HDRS_LEN];
+            let (len, _) = headers.encode(&mut buf, 0, ip6_nh::TCP).done().unwrap();
+            assert_eq!(len, (2 + options.len() + 7) / 8 * 8);
+
+            let (_, hdr) = ExtHeader::decode(&buf, ip6_nh::DST_OPTS).done().unwrap();
+            assert_eq!(hdr.len, len);
+            assert_eq!(hdr.next_header, ip6_nh::TCP);
+            let mut iter = IP6OptionIter::new(&buf[..len]);
+            let (offset, opt) = iter.next().unwrap().unwrap();
+            assert_eq!(offset, 2);
+            assert_eq!(opt.opt_type, 0x1e);
+            assert_eq!(opt.data.len(), data_len);
+            assert!(iter.next().is_none());
+        }
+    }
+
+    #[test]
+    fn extension_headers_chaining() {
+        let rpl = RplOption {
+            flags: RPL_OPT_FLAG_RANK_ERROR,
+            instance_id: 1,
+            sender_rank: 256,
+        };
+        let mut rpl_buf = [0; 6];
+        rpl.encode(&mut rpl_buf, 0).done().unwrap();
+        let fragment = FragmentHeader {
+            offset: 0,
+            more: false,
+            id: 0xdeadbeef,
+        };
+        let final_dst = hop(8, 1);
+
+        let mut headers = ExtensionHeaders::new();
+        assert_eq!(headers.first_header(), None);
+        headers.add_hop_by_hop(&rpl_buf).unwrap();
+        assert_eq!(headers.add_hop_by_hop(&rpl_buf), Err(ErrorCode::INVAL));
+        headers.add_destination_options(&[]).unwrap();
+        headers.add_source_route(DST, &[final_dst]).unwrap();
+        headers.add_fragment(fragment).unwrap();
+        assert_eq!(headers.first_header(), Some(ip6_nh::HOP_OPTS));
+        assert_eq!(headers.final_destination(), Some(final_dst));
+
+        // Headers that don't fit leave the others untouched
+        let len = headers.len();
+        let big = [0x1e; MAX_EXT_HDRS_LEN];
+        assert_eq!(headers.add_destination_options(&big), Err(ErrorCode::SIZE));
+        assert_eq!(headers.len(), len);
+
+        let mut buf = [0; 4 + MAX_EXT_HDRS_LEN];
+        let (off, _) = headers.encode(&mut buf, 4, ip6_nh::ICMP).done().unwrap();
+        assert_eq!(off, 4 + len);
+        assert!(headers
+            .encode(&mut buf[..3 + len], 4, ip6_nh::ICMP)
+            .done()
+            .is_none());
+
+        let expected = [
+            ip6_nh::HOP_OPTS,
+            ip6_nh::DST_OPTS,
+            ip6_nh::ROUTING,
+            ip6_nh::FRAGMENT,
+            ip6_nh::ICMP,
+        ];
+        let mut off = 4;
+        for types in expected.windows(2) {
+            let (hdr_len, hdr) = ExtHeader::decode(&buf[off..4 + len], types[0])
+                .done()
+                .unwrap();
+            assert_eq!(hdr.next_header, types[1]);
+            let hdr_buf = &buf[off..off + hdr_len];
+            match types[0] {
+                ip6_nh::HOP_OPTS => {
+                    let mut iter = IP6OptionIter::new(hdr_buf);
+                    let (_, opt) = iter.next().unwrap().unwrap();
+                    let (_, decoded) = RplOption::decode(opt.data).done().unwrap();
+                    assert!(decoded == rpl);
+                    assert!(iter.next().is_none());
+                }
+                ip6_nh::DST_OPTS => assert!(IP6OptionIter::new(hdr_buf).next().is_none()),
+                ip6_nh::ROUTING => {
+                    let (_, sr) = SourceRoute::decode(hdr_buf).done().unwrap();
+                    assert_eq!(sr.address(0, DST), Some(final_dst));
+                }
+                _ => {
+                    let (_, frag) = FragmentHeader::decode(hdr_buf).done().unwrap();
+                    assert_eq!(frag.id, fragment.id);
+                    assert!(frag.is_atomic());
+                }
+            }
+            off += hdr_len;
+        }
+        assert_eq!(off, 4 + len);
+
+        headers.clear();
+        assert!(headers.is_empty());
+        assert_eq!(headers.first_header(), None);
+        assert_eq!(headers.final_destination(), None);
+        let (off, _) = headers.encode(&mut buf, 4, ip6_nh::ICMP).done().unwrap();
+        assert_eq!(off, 4);
+    }
+
+    #[test]
+    fn fragment_roundtrip() {
+        let mut buf = [0; FRAGMENT_HDR_LEN];
+        for &(offset, more) in [(0, false), (0, true), (1, false), (0x1fff, true)].iter() {
+            let fragment = FragmentHeader {
+                offset,
+                more,
+                id: 0x01020304,
+            };
+            let (len, _) = fragment.encode(&mut buf, 0).done().unwrap();
+            assert_eq!(len, FRAGMENT_HDR_LEN);
+            assert_eq!(buf[0], ip6_nh::NO_NEXT);
+            let (_, decoded) = FragmentHeader::decode(&buf).done().unwrap();
+            assert_eq!(decoded.offset, offset);
+            assert_eq!(decoded.more, more);
+            assert_eq!(decoded.id, 0x01020304);
+            assert_eq!(decoded.is_atomic(), offset == 0 && !more);
+        }
+        assert!(FragmentHeader::decode(&buf[..7]).is_needed());
+    }
+
+    #[test]
+    fn rpl_option_lengths() {
+        assert!(RplOption::decode(&[0; 3]).is_err());
+        assert!(RplOption::decode(&[0; 5]).is_err());
+        assert!(RplOption::decode(&[0; 4]).is_done());
+        let rpl = RplOption {
+            flags: 0,
+            instance_id: 0,
+            sender_rank: 0,
+        };
+        assert!(rpl.encode(&mut [0; 5], 0).is_needed());
+    }
+
+    #[test]
+    fn option_action() {
+        assert_eq!(OptionAction::from_type(0x1e), OptionAction::Skip);
+        assert_eq!(OptionAction::from_type(ip6_opt::RPL), OptionAction::Discard);
+        assert_eq!(
+            OptionAction::from_type(0x80),
+            OptionAction::DiscardAndReport
+        );
+        assert_eq!(
+            OptionAction::from_type(0xc2),
+            OptionAction::DiscardAndReportUnicast
+        );
+    }
+}
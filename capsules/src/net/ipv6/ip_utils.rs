@@ -197,7 +197,13 @@ pub fn compute_icmp_checksum(
 
     // add options
     match icmp_header.get_options() {
-        ICMP6HeaderOptions::Type1 { unused } | ICMP6HeaderOptions::Type3 { unused } => {
+        ICMP6HeaderOptions::Type1 { unused }
+        | ICMP6HeaderOptions::Type3 { unused }
+        | ICMP6HeaderOptions::Type4 { pointer: unused }
+        | ICMP6HeaderOptions::Type133 { unused }
+        | ICMP6HeaderOptions::Type135 { unused }
+        | ICMP6HeaderOptions::Type136 { flags: unused }
+        | ICMP6HeaderOptions::Type155 { base: unused } => {
             sum += unused >> 16; // upper 16 bits
             sum += unused & 0xffff; // lower 16 bits
         }
@@ -205,6 +211,14 @@ pub fn compute_icmp_checksum(
             sum += id as u32;
             sum += seqno as u32;
         }
+        ICMP6HeaderOptions::Type134 {
+            hop_limit,
+            flags,
+            router_lifetime,
+        } => {
+            sum += ((hop_limit as u32) << 8) + flags as u32;
+            sum += router_lifetime as u32;
+        }
     }
 
     // add icmp payload
@@ -215,7 +229,7 @@ pub fn compute_icmp_checksum(
     while sum > 0xffff {
         let sum_upper = sum >> 16;
         let sum_lower = sum & 0xffff;
-        sum += sum_upper + sum_lower;
+        sum = sum_upper + sum_lower;
     }
 
     sum = !sum;
@@ -241,7 +255,7 @@ pub fn compute_ipv6_ph_sum(ip6_header: &IP6Header) -> u32 {
         i += 2;
     }
 
-    sum += ip6_header.payload_len as u32;
+    sum += ip6_header.get_payload_len() as u32;
     sum += ip6_header.next_header as u32;
 
     sum
@@ -253,10 +267,41 @@ pub fn compute_sum(buf: &[u8], len: u16) -> u32 {
     let mut i: usize = 0;
     while i < (len as usize) {
         let msb = (buf[i] as u32) << 8;
-        let lsb = buf[i + 1] as u32;
+        // An odd trailing byte is padded with zero
+        let lsb = if i + 1 < len as usize {
+            buf[i + 1] as u32
+        } else {
+            0
+        };
         sum += msb + lsb;
         i += 2;
     }
 
     sum
 }
+
+/// Computes the TCP checksum over the IPv6 pseudo-header, the serialized
+/// TCP header (including any options) and the segment payload. When
+/// `tcp_header` contains a valid checksum, the result is 0.
+pub fn compute_tcp_checksum(ip6_header: &IP6Header, tcp_header: &[u8], payload: &[u8]) -> u16 {
+    let mut sum: u32 = 0;
+
+    // add ipv6 pseudo-header
+    sum += compute_ipv6_ph_sum(ip6_header);
+
+    // add the tcp header and payload
+    sum += compute_sum(tcp_header, tcp_header.len() as u16);
+    sum += compute_sum(payload, payload.len() as u16);
+
+    // carry overflow
+    while sum > 0xffff {
+        let sum_upper = sum >> 16;
+        let sum_lower = sum & 0xffff;
+        sum = sum_upper + sum_lower;
+    }
+
+    sum = !sum;
+    sum = sum & 0xffff;
+
+    sum as u16
+}
@@ -9,17 +9,18 @@
 //! file, and a rough outline is given below:
 //!
 //! ```txt
-//!            ----------------------------------------------
-//!            |                 IP6Packet                  |
-//!            |--------------------------------------------|
-//!            |                 |         IPPayload        |
-//!            |    IP6Header    |--------------------------|
-//!            |                 |TransportHeader | Payload |
-//!            ----------------------------------------------
+//!            -----------------------------------------------------------------
+//!            |                          IP6Packet                            |
+//!            |---------------------------------------------------------------|
+//!            |                 |                  |         IPPayload        |
+//!            |    IP6Header    | ExtensionHeaders |--------------------------|
+//!            |                 |                  |TransportHeader | Payload |
+//!            -----------------------------------------------------------------
 //! ```
 //!
 //! The [IP6Packet](struct.IP6Packet.html) struct contains an
-//! [IP6Header](struct.IP6Header.html) struct and an
+//! [IP6Header](struct.IP6Header.html) struct, the (usually empty)
+//! [ExtensionHeaders](../ext_headers/struct.ExtensionHeaders.html) and an
 //! [IPPayload](struct.IPPayload.html) struct, with the `IPPayload` struct
 //! also containing a [TransportHeader](enum.TransportHeader.html) enum and
 //! a `Payload` buffer. Note that transport-level headers are contained inside
@@ -57,8 +58,10 @@
 //
 // One of the primary problems with the current encapsulation design is that
 // it is impossible to encode recursive headers - any subsequent headers (IPv6
-// or transport) must be serialized and carried in the raw payload. This may
-// be avoided with references and allocation, but since we do not have
+// or transport) must be serialized and carried in the raw payload. IPv6
+// extension headers work around this by being serialized as they are added
+// to the `ExtensionHeaders` of the packet. A general solution may be found
+// with references and allocation, but since we do not have
 // a memory allocator we could not allocate all possible headers at compile
 // time. Additionally, we couldn't just allocate headers "as-needed" on the
 // stack, as the network send interface is asynchronous, so anything allocated
@@ -67,11 +70,14 @@
 // (as required by 6LoWPAN) difficult.
 
 use crate::net::icmpv6::ICMP6Header;
-use crate::net::ipv6::ip_utils::{compute_icmp_checksum, compute_udp_checksum, ip6_nh, IPAddr};
+use crate::net::ipv6::ext_headers::{ExtensionHeaders, MAX_EXT_HDRS_LEN};
+use crate::net::ipv6::ip_utils::{
+    compute_icmp_checksum, compute_tcp_checksum, compute_udp_checksum, ip6_nh, IPAddr,
+};
 use crate::net::stream::SResult;
 use crate::net::stream::{decode_bytes, decode_u16, decode_u8};
 use crate::net::stream::{encode_bytes, encode_u16, encode_u8};
-use crate::net::tcp::TCPHeader;
+use crate::net::tcp::{TCPHeader, TCP_HDR_LEN};
 use crate::net::udp::UDPHeader;
 
 use kernel::utilities::leasable_buffer::LeasableMutableBuffer;
@@ -79,6 +85,13 @@ use kernel::ErrorCode;
 
 pub const UDP_HDR_LEN: usize = 8;
 pub const ICMP_HDR_LEN: usize = 8;
+/// The largest TCP header the stack will serialize (header plus 40 bytes of
+/// options)
+const MAX_TCP_HDR_LEN: usize = 60;
+/// The longest headers an `IP6Packet` serializes before its transport
+/// payload: the IPv6 header, the extension headers and the largest transport
+/// header.
+pub const MAX_HDRS_LEN: usize = 40 + MAX_EXT_HDRS_LEN + MAX_TCP_HDR_LEN;
 
 /// This is the struct definition for an IPv6 header. It contains (in order)
 /// the same fields as a normal IPv6 header.
@@ -265,16 +278,24 @@ impl IP6Header {
     pub fn check_transport_checksum(&self, buf: &[u8]) -> Result<(), ErrorCode> {
         match self.next_header {
             ip6_nh::UDP => {
+                if buf.len() < UDP_HDR_LEN {
+                    return Err(ErrorCode::FAIL);
+                }
                 let mut udp_header: [u8; UDP_HDR_LEN] = [0; UDP_HDR_LEN];
                 udp_header.copy_from_slice(&buf[..UDP_HDR_LEN]);
                 let checksum = match UDPHeader::decode(&udp_header).done() {
-                    Some((_offset, hdr)) => u16::from_be(compute_udp_checksum(
-                        &self,
-                        &hdr,
-                        buf.len() as u16,
-                        &buf[UDP_HDR_LEN..],
-                    )),
-                    None => 0xffff, //Will be dropped, as ones comp -0 checksum is invalid
+                    // The length field must not overrun the packet
+                    Some((_offset, hdr))
+                        if (UDP_HDR_LEN..=buf.len()).contains(&(hdr.get_len() as usize)) =>
+                    {
+                        u16::from_be(compute_udp_checksum(
+                            &self,
+                            &hdr,
+                            hdr.get_len(),
+                            &buf[UDP_HDR_LEN..],
+                        ))
+                    }
+                    _ => 0xffff, //Will be dropped, as ones comp -0 checksum is invalid
                 };
                 if checksum != 0 {
                     return Err(ErrorCode::FAIL); //Incorrect cksum
@@ -282,13 +303,29 @@ impl IP6Header {
                 Ok(())
             }
             ip6_nh::ICMP => {
-                // Untested (10/5/18)
+                if buf.len() < ICMP_HDR_LEN {
+                    return Err(ErrorCode::FAIL);
+                }
                 let mut icmp_header: [u8; ICMP_HDR_LEN] = [0; ICMP_HDR_LEN];
                 icmp_header.copy_from_slice(&buf[..ICMP_HDR_LEN]);
                 let checksum = match ICMP6Header::decode(&icmp_header).done() {
                     Some((_offset, mut hdr)) => {
+                        // The checksum is computed without the checksum
+                        // field, so it must match the received one.
                         hdr.set_len(buf.len() as u16);
-                        u16::from_be(compute_icmp_checksum(&self, &hdr, &buf[ICMP_HDR_LEN..]))
+                        compute_icmp_checksum(&self, &hdr, &buf[ICMP_HDR_LEN..]) ^ hdr.get_cksum()
+                    }
+                    None => 0xffff, //Will be dropped, as ones comp -0 checksum is invalid
+                };
+                if checksum != 0 {
+                    return Err(ErrorCode::FAIL); //Incorrect cksum
+                }
+                Ok(())
+            }
+            ip6_nh::TCP => {
+                let checksum = match TCPHeader::decode(buf).done() {
+                    Some((offset, _hdr)) => {
+                        compute_tcp_checksum(&self, &buf[..offset], &buf[offset..])
                     }
                     None => 0xffff, //Will be dropped, as ones comp -0 checksum is invalid
                 };
@@ -304,7 +341,8 @@ impl IP6Header {
 
 /// This defines the currently supported `TransportHeader` types. The contents
 /// of each header is encapsulated by the enum type. Note that this definition
-/// of `TransportHeader`s means that recursive headers are not supported.
+/// of `TransportHeader`s means that recursive headers are not supported;
+/// IPv6 extension headers are carried by the `IP6Packet` instead.
 /// As of now, there is no support for sending raw IP packets without a transport header.
 /// Currently we accept the overhead of copying these structs in/out of an OptionalCell
 /// in `udp_send.rs`.
@@ -370,7 +408,12 @@ impl<'a> IPPayload<'a> {
                 self.header = transport_header;
                 (ip6_nh::ICMP, length)
             }
-            _ => (ip6_nh::NO_NEXT, payload.len() as u16),
+            TransportHeader::TCP(mut tcp_header) => {
+                let length = (payload.len() + tcp_header.get_hdr_size()) as u16;
+                tcp_header.set_len(length);
+                self.header = TransportHeader::TCP(tcp_header);
+                (ip6_nh::TCP, length)
+            }
         }
     }
 
@@ -387,17 +430,25 @@ impl<'a> IPPayload<'a> {
     /// wrapped in an SResult
     pub fn encode(&self, buf: &mut [u8], offset: usize) -> SResult<usize> {
         let (offset, _) = match self.header {
-            TransportHeader::UDP(udp_header) => udp_header.encode(buf, offset).done().unwrap(),
-            TransportHeader::ICMP(icmp_header) => icmp_header.encode(buf, offset).done().unwrap(),
-            _ => {
-                unimplemented!();
-            }
+            TransportHeader::UDP(udp_header) => enc_try!(udp_header.encode(buf, offset)),
+            TransportHeader::ICMP(icmp_header) => enc_try!(icmp_header.encode(buf, offset)),
+            TransportHeader::TCP(tcp_header) => enc_try!(tcp_header.encode(buf, offset)),
         };
         let payload_length = self.get_payload_length();
+        stream_cond!(payload_length <= self.payload.len(), ());
         let offset = enc_consume!(buf, offset; encode_bytes, &self.payload[..payload_length]);
         stream_done!(offset, offset)
     }
 
+    /// The `ip6_nh` type of the transport header
+    fn get_next_header(&self) -> u8 {
+        match self.header {
+            TransportHeader::UDP(_) => ip6_nh::UDP,
+            TransportHeader::ICMP(_) => ip6_nh::ICMP,
+            TransportHeader::TCP(_) => ip6_nh::TCP,
+        }
+    }
+
     fn get_payload_length(&self) -> usize {
         match self.header {
             TransportHeader::UDP(udp_header) => {
@@ -406,17 +457,18 @@ impl<'a> IPPayload<'a> {
             TransportHeader::ICMP(icmp_header) => {
                 icmp_header.get_len() as usize - icmp_header.get_hdr_size()
             }
-            _ => {
-                unimplemented!();
+            TransportHeader::TCP(tcp_header) => {
+                tcp_header.get_len() as usize - tcp_header.get_hdr_size()
             }
         }
     }
 }
 
-/// This struct defines the `IP6Packet` format, and contains an `IP6Header`
-/// and an `IPPayload`.
+/// This struct defines the `IP6Packet` format, and contains an `IP6Header`,
+/// the extension headers and an `IPPayload`.
 pub struct IP6Packet<'a> {
     pub header: IP6Header,
+    pub ext_headers: ExtensionHeaders,
     pub payload: IPPayload<'a>,
 }
 
@@ -435,6 +487,7 @@ impl<'a> IP6Packet<'a> {
     pub fn new(payload: IPPayload<'a>) -> IP6Packet<'a> {
         IP6Packet {
             header: IP6Header::default(),
+            ext_headers: ExtensionHeaders::new(),
             payload: payload,
         }
     }
@@ -455,9 +508,27 @@ impl<'a> IP6Packet<'a> {
         let transport_hdr_size = match self.payload.header {
             TransportHeader::UDP(udp_hdr) => udp_hdr.get_hdr_size(),
             TransportHeader::ICMP(icmp_header) => icmp_header.get_hdr_size(),
-            _ => unimplemented!(),
+            TransportHeader::TCP(tcp_header) => tcp_header.get_hdr_size(),
         };
-        40 + transport_hdr_size
+        40 + self.ext_headers.len() + transport_hdr_size
+    }
+
+    /// Returns the header the transport checksum is computed over. It
+    /// differs from the `IP6Header` of the packet when there are extension
+    /// headers, as the pseudo-header holds the upper-layer protocol and
+    /// length, and the final destination of a source-routed packet.
+    fn get_pseudo_header(&self) -> IP6Header {
+        let mut header = self.header;
+        header.set_next_header(self.payload.get_next_header());
+        header.set_payload_len(
+            self.header
+                .get_payload_len()
+                .saturating_sub(self.ext_headers.len() as u16),
+        );
+        if let Some(dst_addr) = self.ext_headers.final_destination() {
+            header.dst_addr = dst_addr;
+        }
+        header
     }
 
     pub fn set_transport_checksum(&mut self) {
@@ -467,10 +538,11 @@ impl<'a> IP6Packet<'a> {
         // psuedoheader cksum and calls the appropriate transport packet function
         // using this pseudoheader cksum to set the transport packet cksum
 
+        let pseudo_header = self.get_pseudo_header();
         match self.payload.header {
             TransportHeader::UDP(ref mut udp_header) => {
                 let cksum = compute_udp_checksum(
-                    &self.header,
+                    &pseudo_header,
                     &udp_header,
                     udp_header.get_len(),
                     self.payload.payload,
@@ -478,11 +550,25 @@ impl<'a> IP6Packet<'a> {
                 udp_header.set_cksum(cksum);
             }
             TransportHeader::ICMP(ref mut icmp_header) => {
-                let cksum = compute_icmp_checksum(&self.header, &icmp_header, self.payload.payload);
+                let cksum =
+                    compute_icmp_checksum(&pseudo_header, &icmp_header, self.payload.payload);
                 icmp_header.set_cksum(cksum);
             }
-            _ => {
-                unimplemented!();
+            TransportHeader::TCP(ref mut tcp_header) => {
+                let mut hdr_buf = [0 as u8; MAX_TCP_HDR_LEN];
+                let hdr_size = tcp_header.get_hdr_size();
+                if hdr_size < TCP_HDR_LEN || hdr_size > MAX_TCP_HDR_LEN {
+                    return;
+                }
+                tcp_header.set_cksum(0);
+                let _ = tcp_header.encode(&mut hdr_buf, 0);
+                let payload_len = tcp_header.get_len() as usize - hdr_size;
+                let cksum = compute_tcp_checksum(
+                    &pseudo_header,
+                    &hdr_buf[..hdr_size],
+                    &self.payload.payload[..payload_len],
+                );
+                tcp_header.set_cksum(cksum);
             }
         }
     }
@@ -492,9 +578,11 @@ impl<'a> IP6Packet<'a> {
     /// method to set the transport header and transport payload, which then
     /// returns the `ip6_nh` value for the `TransportHeader` and the length of
     /// the serialized `IPPayload` region. This function then sets the
-    /// `IP6Header` next header field correctly. **Without using this function,
+    /// `IP6Header` next header field correctly, pointing to the first extension
+    /// header if there is one. **Without using this function,
     /// the `IP6Header.next_header` field may not agree with the actual
-    /// next header (`IP6Header.payload.header`)**
+    /// next header (`IP6Header.payload.header`)**, so the extension headers
+    /// must be set before it is called.
     ///
     /// # Arguments
     ///
@@ -507,8 +595,10 @@ impl<'a> IP6Packet<'a> {
         payload: &LeasableMutableBuffer<'static, u8>,
     ) {
         let (next_header, payload_len) = self.payload.set_payload(transport_header, payload);
-        self.header.set_next_header(next_header);
-        self.header.set_payload_len(payload_len);
+        self.header
+            .set_next_header(self.ext_headers.first_header().unwrap_or(next_header));
+        self.header
+            .set_payload_len(payload_len + self.ext_headers.len() as u16);
     }
 
     // TODO: Do we need a decode equivalent? I don't think so, but we might
@@ -516,8 +606,10 @@ impl<'a> IP6Packet<'a> {
     pub fn encode(&self, buf: &mut [u8]) -> SResult<usize> {
         let ip6_header = self.header;
 
-        // TODO: Handle unwrap safely
-        let (off, _) = ip6_header.encode(buf).done().unwrap();
+        let (off, _) = enc_try!(ip6_header.encode(buf));
+        let (off, _) = enc_try!(self
+            .ext_headers
+            .encode(buf, off, self.payload.get_next_header()));
         self.payload.encode(buf, off)
     }
 }
@@ -1,3 +1,8 @@
+use crate::net::icmpv6::param_problem;
+use crate::net::ipv6::ext_headers::{
+    ip6_opt, routing_type, ExtHeader, FragmentHeader, IP6OptionIter, OptionAction, RoutingHeader,
+};
+use crate::net::ipv6::ip_utils::ip6_nh;
 use crate::net::ipv6::IP6Header;
 use crate::net::sixlowpan::sixlowpan_state::SixlowpanRxClient;
 
@@ -28,10 +33,26 @@ use kernel::ErrorCode;
   packets up to userland.
 */
 
+/// Receives the packets for the transport protocols. Extension headers have
+/// already been processed, so the payload starts with the transport header,
+/// and `header` holds the transport protocol as next header and the length
+/// of the transport header and payload, as the transport checksum expects.
 pub trait IP6RecvClient {
     fn receive(&self, header: IP6Header, payload: &[u8]);
 }
 
+/// Receives the packets the receiver discards because it does not
+/// recognize one of their headers, so that their source can be told with an
+/// ICMPv6 Parameter Problem message. As every receiver sees every packet,
+/// only one receiver should have an error client.
+pub trait IP6RecvErrorClient {
+    /// `header` - The IPv6 header of the discarded packet
+    /// `code` - The Parameter Problem code (`param_problem::*`)
+    /// `pointer` - The offset of the unrecognized field into the packet
+    /// `packet` - The discarded packet, starting with its IPv6 header
+    fn parameter_problem(&self, header: IP6Header, code: u8, pointer: u32, packet: &[u8]);
+}
+
 /// Currently only one implementation of this trait should exist,
 /// as we do not multiplex received packets based on the address.
 /// The receiver receives IP packets destined for any local address.
@@ -39,22 +60,137 @@ pub trait IP6RecvClient {
 /// that are not among the local addresses of this device.
 pub trait IP6Receiver<'a> {
     fn set_client(&self, client: &'a dyn IP6RecvClient);
+    fn set_error_client(&self, client: &'a dyn IP6RecvErrorClient);
+}
+
+/// What to do with a received packet once its extension headers have been
+/// processed.
+enum Disposition {
+    /// Pass the transport header of type `next_header` at `offset` to the
+    /// client.
+    Deliver {
+        next_header: u8,
+        offset: usize,
+    },
+    Discard,
+    /// Discard the packet and report a Parameter Problem for the field at
+    /// `pointer`.
+    Report {
+        code: u8,
+        pointer: usize,
+    },
 }
 
 pub struct IP6RecvStruct<'a> {
     client: OptionalCell<&'a dyn IP6RecvClient>,
+    error_client: OptionalCell<&'a dyn IP6RecvErrorClient>,
 }
 
 impl<'a> IP6Receiver<'a> for IP6RecvStruct<'a> {
     fn set_client(&self, client: &'a dyn IP6RecvClient) {
         self.client.set(client);
     }
+
+    fn set_error_client(&self, client: &'a dyn IP6RecvErrorClient) {
+        self.error_client.set(client);
+    }
 }
 
 impl<'a> IP6RecvStruct<'a> {
     pub fn new() -> IP6RecvStruct<'a> {
         IP6RecvStruct {
             client: OptionalCell::empty(),
+            error_client: OptionalCell::empty(),
+        }
+    }
+
+    /// Walks the extension headers of `packet` up to the transport header.
+    /// Hop-by-Hop and Destination options other than the RPL option are not
+    /// recognized. As this node does not forward packets, packets whose
+    /// Routing header has segments left are discarded, and as it does not
+    /// reassemble IPv6 fragments, so are fragments other than atomic ones.
+    fn process_ext_headers(ip6_header: &IP6Header, packet: &[u8]) -> Disposition {
+        let mut next_header = ip6_header.get_next_header();
+        let mut offset = 40;
+        // The offset of the field holding `next_header`
+        let mut next_header_field = 6;
+        loop {
+            match next_header {
+                ip6_nh::UDP | ip6_nh::TCP | ip6_nh::ICMP => {
+                    return Disposition::Deliver {
+                        next_header,
+                        offset,
+                    }
+                }
+                ip6_nh::NO_NEXT => return Disposition::Discard,
+                // The Hop-by-Hop Options header must follow the IPv6 header
+                ip6_nh::HOP_OPTS if offset == 40 => {}
+                ip6_nh::DST_OPTS | ip6_nh::ROUTING | ip6_nh::FRAGMENT => {}
+                _ => {
+                    return Disposition::Report {
+                        code: param_problem::UNRECOGNIZED_NEXT_HEADER,
+                        pointer: next_header_field,
+                    }
+                }
+            }
+            let ext_header = match ExtHeader::decode(&packet[offset..], next_header).done() {
+                Some((_, ext_header)) => ext_header,
+                None => return Disposition::Discard,
+            };
+            let hdr = &packet[offset..offset + ext_header.len];
+
+            match next_header {
+                ip6_nh::HOP_OPTS | ip6_nh::DST_OPTS => {
+                    for option in IP6OptionIter::new(hdr) {
+                        let (opt_offset, option) = match option {
+                            Ok(option) => option,
+                            Err(()) => return Disposition::Discard,
+                        };
+                        if next_header == ip6_nh::HOP_OPTS && option.opt_type == ip6_opt::RPL {
+                            continue;
+                        }
+                        let report = Disposition::Report {
+                            code: param_problem::UNRECOGNIZED_OPTION,
+                            pointer: offset + opt_offset,
+                        };
+                        match OptionAction::from_type(option.opt_type) {
+                            OptionAction::Skip => {}
+                            OptionAction::Discard => return Disposition::Discard,
+                            OptionAction::DiscardAndReport => return report,
+                            OptionAction::DiscardAndReportUnicast => {
+                                if ip6_header.get_dst_addr().is_multicast() {
+                                    return Disposition::Discard;
+                                }
+                                return report;
+                            }
+                        }
+                    }
+                }
+                ip6_nh::ROUTING => {
+                    let routing = match RoutingHeader::decode(hdr).done() {
+                        Some((_, routing)) => routing,
+                        None => return Disposition::Discard,
+                    };
+                    if routing.segments_left != 0 {
+                        if routing.routing_type == routing_type::RPL_SOURCE_ROUTE {
+                            return Disposition::Discard;
+                        }
+                        // Point to the Routing Type field
+                        return Disposition::Report {
+                            code: param_problem::ERRONEOUS_HEADER_FIELD,
+                            pointer: offset + 2,
+                        };
+                    }
+                }
+                _ => match FragmentHeader::decode(hdr).done() {
+                    Some((_, fragment)) if fragment.is_atomic() => {}
+                    _ => return Disposition::Discard,
+                },
+            }
+
+            next_header_field = offset;
+            offset += ext_header.len;
+            next_header = ext_header.next_header;
         }
     }
 }
@@ -65,18 +201,41 @@ impl<'a> SixlowpanRxClient for IP6RecvStruct<'a> {
         if len > buf.len() || result != Ok(()) {
             return;
         }
-        match IP6Header::decode(buf).done() {
-            Some((offset, ip6_header)) => {
-                let checksum_result = ip6_header.check_transport_checksum(&buf[offset..len]);
+        let packet = &buf[..len];
+        match IP6Header::decode(packet).done() {
+            Some((_, mut ip6_header)) => {
+                let offset = match Self::process_ext_headers(&ip6_header, packet) {
+                    Disposition::Deliver {
+                        next_header,
+                        offset,
+                    } => {
+                        // Hand the transport protocol and length to the
+                        // client, as the extension headers are consumed.
+                        let ext_headers_len = (offset - 40) as u16;
+                        ip6_header.set_next_header(next_header);
+                        ip6_header.set_payload_len(
+                            ip6_header.get_payload_len().saturating_sub(ext_headers_len),
+                        );
+                        offset
+                    }
+                    Disposition::Discard => return,
+                    Disposition::Report { code, pointer } => {
+                        self.error_client.map(|client| {
+                            client.parameter_problem(ip6_header, code, pointer as u32, packet)
+                        });
+                        return;
+                    }
+                };
+                let checksum_result = ip6_header.check_transport_checksum(&packet[offset..]);
                 if checksum_result == Err(ErrorCode::FAIL) {
                     debug!("cksum fail!: {:?}", checksum_result);
                     return; //Dropped.
                 }
-                // Note: Protocols for which checksum verification is not implemented (TCP, etc.)
+                // Note: Protocols for which checksum verification is not implemented
                 // are automatically assumed as fine, rather than dropped
 
                 self.client
-                    .map(|client| client.receive(ip6_header, &buf[offset..len]));
+                    .map(|client| client.receive(ip6_header, &packet[offset..]));
             }
             None => {
                 debug!("failed to decode ipv6 header");
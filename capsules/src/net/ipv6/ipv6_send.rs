@@ -17,7 +17,8 @@
 // interface.
 
 use crate::ieee802154::device::{MacDevice, TxClient};
-use crate::net::ieee802154::MacAddress;
+use crate::net::ieee802154::{KeyId, MacAddress, SecurityLevel};
+use crate::net::ipv6::ext_headers::ExtensionHeaders;
 use crate::net::ipv6::ip_utils::IPAddr;
 use crate::net::ipv6::{IP6Header, IP6Packet, TransportHeader};
 use crate::net::network_capabilities::{IpVisibilityCapability, NetworkCapability};
@@ -39,6 +40,15 @@ pub trait IP6SendClient {
     fn send_done(&self, result: Result<(), ErrorCode>);
 }
 
+/// This trait is implemented by routing protocols that know the neighbor
+/// packets to a destination must be sent to, so that packets can reach
+/// destinations beyond the link.
+pub trait NextHopResolver {
+    /// Returns the MAC address of the next hop towards the unicast address
+    /// `dst`, or `None` if the packet is to be sent to the gateway.
+    fn next_hop(&self, dst: IPAddr) -> Option<MacAddress>;
+}
+
 /// This trait provides a basic IPv6 sending interface. It exposes basic
 /// configuration information for the IPv6 layer (setting the source address,
 /// setting the gateway MAC address), as well as a way to send an IPv6
@@ -61,12 +71,30 @@ pub trait IP6Sender<'a> {
     fn set_addr(&self, src_addr: IPAddr);
 
     /// This method sets the gateway/next hop MAC address for this `IP6Sender`
-    /// instance.
+    /// instance. Packets to multicast addresses are sent to the broadcast
+    /// address instead.
     ///
     /// # Arguments
     /// `gateway` - MAC address to send the constructed packet to
     fn set_gateway(&self, gateway: MacAddress);
 
+    /// This method sets the resolver that is asked for the next hop MAC
+    /// address of each unicast packet. The gateway is used for the packets
+    /// the resolver has no next hop for.
+    ///
+    /// # Arguments
+    /// `resolver` - The `NextHopResolver`, typically a routing protocol
+    fn set_next_hop_resolver(&self, resolver: &'a dyn NextHopResolver);
+
+    /// This method sets the link-layer security of the frames that
+    /// subsequent packets are sent in. The key must be in the key table of
+    /// the MAC device.
+    ///
+    /// # Arguments
+    /// `security` - The security level and key ID, or `None` to send
+    /// unsecured frames
+    fn set_link_security(&self, security: Option<(SecurityLevel, KeyId)>);
+
     /// This method sets the `IP6Header` for the `IP6Sender` instance
     ///
     /// # Arguments
@@ -74,6 +102,14 @@ pub trait IP6Sender<'a> {
     /// `IP6Sender` instance will use
     fn set_header(&mut self, ip6_header: IP6Header);
 
+    /// This method sets the extension headers that subsequent packets sent
+    /// via this `IP6Sender` instance carry. An RPL Source Route must agree
+    /// with the destination the packets are sent to.
+    ///
+    /// # Arguments
+    /// `ext_headers` - The extension headers, which are copied
+    fn set_ext_headers(&self, ext_headers: &ExtensionHeaders);
+
     /// This method sends the provided transport header and payload to the
     /// given destination IP address
     ///
@@ -100,10 +136,11 @@ pub struct IP6SendStruct<'a, A: time::Alarm<'a>> {
     // (imix)
     src_addr: Cell<IPAddr>,
     gateway: Cell<MacAddress>,
+    next_hop_resolver: OptionalCell<&'a dyn NextHopResolver>,
+    link_security: Cell<Option<(SecurityLevel, KeyId)>>,
     tx_buf: TakeCell<'static, [u8]>,
     sixlowpan: TxState<'a>,
     radio: &'a dyn MacDevice<'a>,
-    dst_mac_addr: MacAddress,
     src_mac_addr: MacAddress,
     client: OptionalCell<&'a dyn IP6SendClient>,
     ip_vis: &'static IpVisibilityCapability,
@@ -122,11 +159,24 @@ impl<'a, A: time::Alarm<'a>> IP6Sender<'a> for IP6SendStruct<'a, A> {
         self.gateway.set(gateway);
     }
 
+    fn set_next_hop_resolver(&self, resolver: &'a dyn NextHopResolver) {
+        self.next_hop_resolver.set(resolver);
+    }
+
+    fn set_link_security(&self, security: Option<(SecurityLevel, KeyId)>) {
+        self.link_security.set(security);
+    }
+
     fn set_header(&mut self, ip6_header: IP6Header) {
         self.ip6_packet
             .map(|ip6_packet| ip6_packet.header = ip6_header);
     }
 
+    fn set_ext_headers(&self, ext_headers: &ExtensionHeaders) {
+        self.ip6_packet
+            .map(|ip6_packet| ip6_packet.ext_headers = *ext_headers);
+    }
+
     fn send_to(
         &self,
         dst: IPAddr,
@@ -137,11 +187,18 @@ impl<'a, A: time::Alarm<'a>> IP6Sender<'a> for IP6SendStruct<'a, A> {
         if !net_cap.remote_addr_valid(dst, self.ip_vis) {
             return Err(ErrorCode::FAIL);
         }
+        let dst_mac_addr = if dst.is_multicast() {
+            MacAddress::Short(0xffff)
+        } else {
+            self.next_hop_resolver
+                .and_then(|resolver| resolver.next_hop(dst))
+                .unwrap_or(self.gateway.get())
+        };
         let _ = self.sixlowpan.init(
             self.src_mac_addr,
-            self.dst_mac_addr,
+            dst_mac_addr,
             self.radio.get_pan(),
-            None,
+            self.link_security.get(),
         );
         self.init_packet(dst, transport_header, payload);
         let ret = self.send_next_fragment();
@@ -165,10 +222,11 @@ impl<'a, A: time::Alarm<'a>> IP6SendStruct<'a, A> {
             alarm: alarm,
             src_addr: Cell::new(IPAddr::new()),
             gateway: Cell::new(dst_mac_addr),
+            next_hop_resolver: OptionalCell::empty(),
+            link_security: Cell::new(None),
             tx_buf: TakeCell::new(tx_buf),
             sixlowpan: sixlowpan,
             radio: radio,
-            dst_mac_addr: dst_mac_addr,
             src_mac_addr: src_mac_addr,
             client: OptionalCell::empty(),
             ip_vis: ip_vis,
@@ -1,3 +1,4 @@
+pub mod ext_headers;
 pub mod ip_utils;
 pub mod ipv6_recv;
 pub mod ipv6_send;
@@ -10,4 +11,5 @@ pub use ipv6::IP6Packet;
 pub use ipv6::IPPayload;
 pub use ipv6::TransportHeader;
 pub use ipv6::ICMP_HDR_LEN;
+pub use ipv6::MAX_HDRS_LEN;
 pub use ipv6::UDP_HDR_LEN;
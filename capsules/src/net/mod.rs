@@ -9,6 +9,7 @@ pub mod icmpv6;
 pub mod ieee802154;
 pub mod ipv6;
 pub mod network_capabilities;
+pub mod rpl;
 pub mod tcp;
 pub mod thread;
 pub mod udp;
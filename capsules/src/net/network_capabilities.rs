@@ -130,6 +130,21 @@ impl NetworkCapability {
         }
     }
 
+    /// Creates a capability for unit tests, which cannot hold a
+    /// `NetworkCapabilityCreationCapability`.
+    #[cfg(test)]
+    pub(crate) const fn new_for_test(
+        remote_addrs: AddrRange,
+        remote_ports: PortRange,
+        local_ports: PortRange,
+    ) -> NetworkCapability {
+        NetworkCapability {
+            remote_addrs,
+            remote_ports,
+            local_ports,
+        }
+    }
+
     pub fn get_range(&self, _ip_cap: &'static IpVisibilityCapability) -> AddrRange {
         self.remote_addrs
     }
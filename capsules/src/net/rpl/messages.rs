@@ -0,0 +1,565 @@
+//! This file contains the types and functions for encoding and decoding RPL
+//! control messages (RFC 6550), which are carried by ICMPv6 messages of type
+//! 155 whose code gives the message type.
+//!
+//! As for Neighbor Discovery, the first word of the message base is held by
+//! the [ICMP6Header](../../icmpv6/struct.ICMP6Header.html), and the rest of
+//! the base and the options are the ICMPv6 payload. The functions below
+//! work on the whole message, from the first byte after the ICMPv6
+//! checksum, which the RPL node splits when it sends a message.
+
+use crate::net::icmpv6::ndp::PrefixInfo;
+use crate::net::ipv6::ip_utils::IPAddr;
+use crate::net::stream::SResult;
+use crate::net::stream::{decode_bytes, decode_u16, decode_u32, decode_u8};
+use crate::net::stream::{encode_bytes, encode_u8};
+
+/// RPL control message codes.
+pub mod rpl_code {
+    pub const DIS: u8 = 0x00;
+    pub const DIO: u8 = 0x01;
+    pub const DAO: u8 = 0x02;
+    pub const DAO_ACK: u8 = 0x03;
+}
+
+/// RPL control message option types.
+pub mod rpl_opt {
+    pub const PAD1: u8 = 0;
+    pub const PADN: u8 = 1;
+    pub const DODAG_CONFIG: u8 = 4;
+    pub const TARGET: u8 = 5;
+    pub const TRANSIT_INFO: u8 = 6;
+    pub const PREFIX_INFO: u8 = 8;
+}
+
+/// The rank of a node that is not part of a DODAG.
+pub const INFINITE_RANK: u16 = 0xffff;
+
+/// The Mode of Operation of DODAGs without downward routes in the nodes,
+/// where the root source-routes packets down the DODAG.
+pub const MOP_NON_STORING: u8 = 1;
+
+/// Length in bytes of the base of a DIO.
+pub const DIO_BASE_LEN: usize = 24;
+
+/// DAO flag asking the root to acknowledge the DAO.
+const DAO_FLAG_ACK: u8 = 0x80;
+/// DAO and DAO-ACK flag telling that the DODAGID is present.
+const DAO_FLAG_DODAG_ID: u8 = 0x40;
+const DAO_ACK_FLAG_DODAG_ID: u8 = 0x80;
+
+const DIO_FLAG_GROUNDED: u8 = 0x80;
+
+/// The DAO-ACK status values below this one accept the DAO.
+pub const DAO_ACK_REJECT: u8 = 128;
+
+/// The base of a DODAG Information Object.
+#[derive(Copy, Clone)]
+pub struct DioBase {
+    pub instance_id: u8,
+    pub version: u8,
+    pub rank: u16,
+    pub grounded: bool,
+    /// The Mode of Operation of the DODAG
+    pub mop: u8,
+    pub preference: u8,
+    /// The Destination Advertisement Trigger Sequence Number, which the
+    /// parent increments to ask for new DAOs
+    pub dtsn: u8,
+    pub dodag_id: IPAddr,
+}
+
+impl DioBase {
+    pub fn decode(buf: &[u8]) -> SResult<DioBase> {
+        stream_len_cond!(buf, DIO_BASE_LEN);
+        let (off, instance_id) = dec_try!(buf, 0; decode_u8);
+        let (off, version) = dec_try!(buf, off; decode_u8);
+        let (off, rank) = dec_try!(buf, off; decode_u16);
+        let (off, g_mop_prf) = dec_try!(buf, off; decode_u8);
+        let (off, dtsn) = dec_try!(buf, off; decode_u8);
+        // Skip the flags and the reserved byte
+        let off = off + 2;
+        let mut dodag_id = IPAddr::new();
+        let off = dec_consume!(buf, off; decode_bytes, &mut dodag_id.0);
+        stream_done!(
+            off,
+            DioBase {
+                instance_id,
+                version,
+                rank,
+                grounded: g_mop_prf & DIO_FLAG_GROUNDED != 0,
+                mop: (g_mop_prf >> 3) & 0x07,
+                preference: g_mop_prf & 0x07,
+                dtsn,
+                dodag_id,
+            }
+        );
+    }
+}
+
+/// The contents of the DODAG Configuration option, which the root sets for
+/// the whole DODAG.
+#[derive(Copy, Clone)]
+pub struct DodagConfig {
+    pub min_hop_rank_increase: u16,
+    /// The Objective Code Point, which selects the objective function
+    pub ocp: u16,
+    /// The lifetime of DAO routes in units of `lifetime_unit` seconds, 0xff
+    /// for infinity
+    pub default_lifetime: u8,
+    pub lifetime_unit: u16,
+}
+
+impl Default for DodagConfig {
+    /// The defaults of RFC 6550, used until a DIO carries the option.
+    fn default() -> DodagConfig {
+        DodagConfig {
+            min_hop_rank_increase: 256,
+            ocp: 0,
+            default_lifetime: 0xff,
+            lifetime_unit: 0xffff,
+        }
+    }
+}
+
+impl DodagConfig {
+    /// The lifetime of DAO routes in seconds, `None` for infinity.
+    pub fn route_lifetime(&self) -> Option<u32> {
+        if self.default_lifetime == 0xff {
+            None
+        } else {
+            Some(self.default_lifetime as u32 * self.lifetime_unit as u32)
+        }
+    }
+}
+
+/// A decoded RPL control message option.
+#[derive(Copy, Clone)]
+pub enum RplOption {
+    DodagConfig(DodagConfig),
+    PrefixInfo(PrefixInfo),
+    /// An option that is not interpreted, with its type.
+    Other(u8),
+}
+
+impl RplOption {
+    /// Deserializes one option from the start of `buf`, returning the
+    /// length of the option in bytes. Unlike Neighbor Discovery options,
+    /// the length of RPL options is in bytes, and excludes the type and
+    /// length fields.
+    pub fn decode(buf: &[u8]) -> SResult<RplOption> {
+        let (off, opt_type) = dec_try!(buf, 0; decode_u8);
+        if opt_type == rpl_opt::PAD1 {
+            stream_done!(off, RplOption::Other(opt_type));
+        }
+        let (off, data_len) = dec_try!(buf, off; decode_u8);
+        let len = off + data_len as usize;
+        stream_len_cond!(buf, len);
+
+        let option = match opt_type {
+            rpl_opt::DODAG_CONFIG => {
+                stream_cond!(data_len == 14, ());
+                // Skip the flags, the trickle timer parameters and the
+                // maximum rank increase
+                let off = off + 6;
+                let (off, min_hop_rank_increase) = dec_try!(buf, off; decode_u16);
+                let (off, ocp) = dec_try!(buf, off; decode_u16);
+                // Skip the reserved byte
+                let off = off + 1;
+                let (off, default_lifetime) = dec_try!(buf, off; decode_u8);
+                let (_, lifetime_unit) = dec_try!(buf, off; decode_u16);
+                stream_cond!(min_hop_rank_increase != 0, ());
+                RplOption::DodagConfig(DodagConfig {
+                    min_hop_rank_increase,
+                    ocp,
+                    default_lifetime,
+                    lifetime_unit,
+                })
+            }
+            rpl_opt::PREFIX_INFO => {
+                // The same layout as the Neighbor Discovery option
+                stream_cond!(data_len == 30, ());
+                let (off, prefix_len) = dec_try!(buf, off; decode_u8);
+                let (off, flags) = dec_try!(buf, off; decode_u8);
+                let (off, valid_lifetime) = dec_try!(buf, off; decode_u32);
+                let (off, preferred_lifetime) = dec_try!(buf, off; decode_u32);
+                // Skip the reserved word
+                let off = off + 4;
+                let mut prefix = [0; 16];
+                dec_consume!(buf, off; decode_bytes, &mut prefix);
+                RplOption::PrefixInfo(PrefixInfo {
+                    prefix_len,
+                    flags,
+                    valid_lifetime,
+                    preferred_lifetime,
+                    prefix,
+                })
+            }
+            _ => RplOption::Other(opt_type),
+        };
+        stream_done!(len, option);
+    }
+}
+
+/// Iterates over the options of an RPL control message. The iteration ends
+/// at the first malformed option.
+pub struct RplOptionIter<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> RplOptionIter<'a> {
+    /// `buf` - The options of the message, following its base
+    pub fn new(buf: &'a [u8]) -> RplOptionIter<'a> {
+        RplOptionIter { buf: buf }
+    }
+}
+
+impl<'a> Iterator for RplOptionIter<'a> {
+    type Item = RplOption;
+
+    fn next(&mut self) -> Option<RplOption> {
+        match RplOption::decode(self.buf).done() {
+            Some((len, option)) => {
+                self.buf = &self.buf[len..];
+                Some(option)
+            }
+            None => None,
+        }
+    }
+}
+
+/// The base of a DAO Acknowledgement.
+#[derive(Copy, Clone)]
+pub struct DaoAck {
+    pub instance_id: u8,
+    pub sequence: u8,
+    pub status: u8,
+    pub dodag_id: Option<IPAddr>,
+}
+
+impl DaoAck {
+    pub fn decode(buf: &[u8]) -> SResult<DaoAck> {
+        let (off, instance_id) = dec_try!(buf, 0; decode_u8);
+        let (off, flags) = dec_try!(buf, off; decode_u8);
+        let (off, sequence) = dec_try!(buf, off; decode_u8);
+        let (off, status) = dec_try!(buf, off; decode_u8);
+        let (off, dodag_id) = if flags & DAO_ACK_FLAG_DODAG_ID != 0 {
+            let mut dodag_id = IPAddr::new();
+            let off = dec_consume!(buf, off; decode_bytes, &mut dodag_id.0);
+            (off, Some(dodag_id))
+        } else {
+            (off, None)
+        };
+        stream_done!(
+            off,
+            DaoAck {
+                instance_id,
+                sequence,
+                status,
+                dodag_id,
+            }
+        );
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.status < DAO_ACK_REJECT
+    }
+}
+
+/// Serializes a DODAG Information Solicitation without options. It is
+/// padded to the 4 bytes the ICMPv6 header holds.
+///
+/// # Return Value
+///
+/// This function returns the length of the message, wrapped in an SResult
+pub fn encode_dis(buf: &mut [u8]) -> SResult<usize> {
+    let mut off = enc_consume!(buf, 0; encode_bytes, &[0; 2]);
+    off = enc_consume!(buf, off; encode_u8, rpl_opt::PADN);
+    off = enc_consume!(buf, off; encode_u8, 0);
+    stream_done!(off, off);
+}
+
+/// Serializes a non-storing mode DAO, which advertises that `target` is
+/// reachable through `parent` to the root of the DODAG, and asks the root to
+/// acknowledge it.
+///
+/// # Arguments
+///
+/// `buf` - The buffer to serialize the message into
+/// `instance_id` - The RPL instance of the DODAG
+/// `sequence` - The DAO sequence number, echoed by the DAO-ACK
+/// `dodag_id` - The DODAGID, the address of the root
+/// `target` - The address advertised
+/// `parent` - The global address of the parent of the node
+/// `path_sequence` - Incremented when the parent changes
+/// `path_lifetime` - The lifetime of the route, in units of the lifetime
+/// unit of the DODAG, or 0 to remove the route
+///
+/// # Return Value
+///
+/// This function returns the length of the message, wrapped in an SResult
+pub fn encode_dao(
+    buf: &mut [u8],
+    instance_id: u8,
+    sequence: u8,
+    dodag_id: &IPAddr,
+    target: &IPAddr,
+    parent: &IPAddr,
+    path_sequence: u8,
+    path_lifetime: u8,
+) -> SResult<usize> {
+    let mut off = enc_consume!(buf, 0; encode_u8, instance_id);
+    off = enc_consume!(buf, off; encode_u8, DAO_FLAG_ACK | DAO_FLAG_DODAG_ID);
+    off = enc_consume!(buf, off; encode_u8, 0);
+    off = enc_consume!(buf, off; encode_u8, sequence);
+    off = enc_consume!(buf, off; encode_bytes, &dodag_id.0);
+
+    // RPL Target option, with the full address as prefix
+    off = enc_consume!(buf, off; encode_u8, rpl_opt::TARGET);
+    off = enc_consume!(buf, off; encode_u8, 18);
+    off = enc_consume!(buf, off; encode_u8, 0);
+    off = enc_consume!(buf, off; encode_u8, 128);
+    off = enc_consume!(buf, off; encode_bytes, &target.0);
+
+    // Transit Information option, which carries the parent address in
+    // non-storing mode
+    off = enc_consume!(buf, off; encode_u8, rpl_opt::TRANSIT_INFO);
+    off = enc_consume!(buf, off; encode_u8, 20);
+    off = enc_consume!(buf, off; encode_u8, 0);
+    off = enc_consume!(buf, off; encode_u8, 0);
+    off = enc_consume!(buf, off; encode_u8, path_sequence);
+    off = enc_consume!(buf, off; encode_u8, path_lifetime);
+    off = enc_consume!(buf, off; encode_bytes, &parent.0);
+    stream_done!(off, off);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DODAG_ID: IPAddr = IPAddr([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+    fn dio_base(g_mop_prf: u8) -> [u8; DIO_BASE_LEN] {
+        let mut buf = [0; DIO_BASE_LEN];
+        buf[..8].copy_from_slice(&[1, 2, 0x02, 0x00, g_mop_prf, 7, 0, 0]);
+        buf[8..].copy_from_slice(&DODAG_ID.0);
+        buf
+    }
+
+    const DODAG_CONFIG_OPT: [u8; 16] = [
+        rpl_opt::DODAG_CONFIG,
+        14,
+        0,
+        20,
+        3,
+        10,
+        0x07,
+        0x00,
+        0x00,
+        0x80,
+        0x00,
+        0x01,
+        0,
+        30,
+        0x00,
+        0x3c,
+    ];
+
+    fn prefix_info_opt() -> [u8; 32] {
+        let mut opt = [0; 32];
+        opt[..4].copy_from_slice(&[rpl_opt::PREFIX_INFO, 30, 64, 0x40]);
+        opt[4..12].copy_from_slice(&[0, 0, 0x0e, 0x10, 0, 0, 0x07, 0x08]);
+        opt[16..].copy_from_slice(&DODAG_ID.0);
+        opt
+    }
+
+    #[test]
+    fn dio_base_decode() {
+        let buf = dio_base(DIO_FLAG_GROUNDED | MOP_NON_STORING << 3 | 5);
+        let (off, base) = DioBase::decode(&buf).done().unwrap();
+        assert_eq!(off, DIO_BASE_LEN);
+        assert_eq!(base.instance_id, 1);
+        assert_eq!(base.version, 2);
+        assert_eq!(base.rank, 0x200);
+        assert!(base.grounded);
+        assert_eq!(base.mop, MOP_NON_STORING);
+        assert_eq!(base.preference, 5);
+        assert_eq!(base.dtsn, 7);
+        assert_eq!(base.dodag_id, DODAG_ID);
+
+        let (_, base) = DioBase::decode(&dio_base(2 << 3)).done().unwrap();
+        assert!(!base.grounded);
+        assert_eq!(base.mop, 2);
+        assert_eq!(base.preference, 0);
+
+        assert!(DioBase::decode(&buf[..DIO_BASE_LEN - 1]).is_needed());
+    }
+
+    #[test]
+    fn dodag_config_decode() {
+        let (off, option) = RplOption::decode(&DODAG_CONFIG_OPT).done().unwrap();
+        assert_eq!(off, DODAG_CONFIG_OPT.len());
+        let config = match option {
+            RplOption::DodagConfig(config) => config,
+            _ => panic!("not a DODAG Configuration option"),
+        };
+        assert_eq!(config.min_hop_rank_increase, 128);
+        assert_eq!(config.ocp, 1);
+        assert_eq!(config.default_lifetime, 30);
+        assert_eq!(config.lifetime_unit, 60);
+        assert_eq!(config.route_lifetime(), Some(1800));
+
+        // Wrong length
+        let mut opt = DODAG_CONFIG_OPT;
+        opt[1] = 13;
+        assert!(RplOption::decode(&opt).is_err());
+        opt[1] = 15;
+        assert!(RplOption::decode(&opt).is_needed());
+
+        // A rank increase of 0 would let ranks loop
+        let mut opt = DODAG_CONFIG_OPT;
+        opt[8] = 0;
+        opt[9] = 0;
+        assert!(RplOption::decode(&opt).is_err());
+
+        assert!(RplOption::decode(&DODAG_CONFIG_OPT[..15]).is_needed());
+    }
+
+    #[test]
+    fn dodag_config_default() {
+        let config = DodagConfig::default();
+        assert_eq!(config.min_hop_rank_increase, 256);
+        assert_eq!(config.ocp, 0);
+        assert_eq!(config.route_lifetime(), None);
+    }
+
+    #[test]
+    fn prefix_info_decode() {
+        let opt = prefix_info_opt();
+        let (off, option) = RplOption::decode(&opt).done().unwrap();
+        assert_eq!(off, opt.len());
+        let info = match option {
+            RplOption::PrefixInfo(info) => info,
+            _ => panic!("not a Prefix Information option"),
+        };
+        assert_eq!(info.prefix_len, 64);
+        assert!(info.is_autonomous());
+        assert_eq!(info.valid_lifetime, 3600);
+        assert_eq!(info.preferred_lifetime, 1800);
+        assert_eq!(info.prefix, DODAG_ID.0);
+
+        let mut opt = prefix_info_opt();
+        opt[1] = 29;
+        assert!(RplOption::decode(&opt).is_err());
+        assert!(RplOption::decode(&prefix_info_opt()[..31]).is_needed());
+    }
+
+    #[test]
+    fn option_iter() {
+        let mut buf = [0; 64];
+        buf[0] = rpl_opt::PAD1;
+        buf[1..5].copy_from_slice(&[rpl_opt::PADN, 2, 0, 0]);
+        buf[5..21].copy_from_slice(&DODAG_CONFIG_OPT);
+        buf[21..53].copy_from_slice(&prefix_info_opt());
+        buf[53..57].copy_from_slice(&[rpl_opt::TARGET, 2, 0, 0]);
+        // Truncated option, which ends the iteration
+        buf[57..60].copy_from_slice(&[rpl_opt::TARGET, 18, 0]);
+
+        let mut iter = RplOptionIter::new(&buf[..60]);
+        assert!(matches!(iter.next(), Some(RplOption::Other(rpl_opt::PAD1))));
+        assert!(matches!(iter.next(), Some(RplOption::Other(rpl_opt::PADN))));
+        assert!(matches!(iter.next(), Some(RplOption::DodagConfig(_))));
+        assert!(matches!(iter.next(), Some(RplOption::PrefixInfo(_))));
+        assert!(matches!(
+            iter.next(),
+            Some(RplOption::Other(rpl_opt::TARGET))
+        ));
+        assert!(iter.next().is_none());
+        assert!(iter.next().is_none());
+
+        // A malformed option hides the ones after it
+        let mut buf = [0; 20];
+        buf[..2].copy_from_slice(&[rpl_opt::DODAG_CONFIG, 2]);
+        buf[4..8].copy_from_slice(&[rpl_opt::TARGET, 2, 0, 0]);
+        assert!(RplOptionIter::new(&buf[..8]).next().is_none());
+
+        assert!(RplOptionIter::new(&[]).next().is_none());
+    }
+
+    #[test]
+    fn dao_ack_decode() {
+        let mut buf = [0; 20];
+        buf[..4].copy_from_slice(&[1, DAO_ACK_FLAG_DODAG_ID, 9, 0]);
+        buf[4..].copy_from_slice(&DODAG_ID.0);
+        let (off, ack) = DaoAck::decode(&buf).done().unwrap();
+        assert_eq!(off, 20);
+        assert_eq!(ack.instance_id, 1);
+        assert_eq!(ack.sequence, 9);
+        assert_eq!(ack.dodag_id, Some(DODAG_ID));
+        assert!(ack.is_accepted());
+        assert!(DaoAck::decode(&buf[..19]).is_needed());
+
+        buf[1] = 0;
+        buf[3] = DAO_ACK_REJECT - 1;
+        let (off, ack) = DaoAck::decode(&buf).done().unwrap();
+        assert_eq!(off, 4);
+        assert_eq!(ack.dodag_id, None);
+        assert!(ack.is_accepted());
+
+        buf[3] = DAO_ACK_REJECT;
+        let (_, ack) = DaoAck::decode(&buf).done().unwrap();
+        assert!(!ack.is_accepted());
+
+        assert!(DaoAck::decode(&buf[..3]).is_needed());
+    }
+
+    #[test]
+    fn dis_encode() {
+        let mut buf = [0xff; 8];
+        let (len, _) = encode_dis(&mut buf).done().unwrap();
+        assert_eq!(len, 4);
+        assert_eq!(&buf[..4], &[0, 0, rpl_opt::PADN, 0]);
+        // The padding is the only option
+        let mut iter = RplOptionIter::new(&buf[2..len]);
+        assert!(matches!(iter.next(), Some(RplOption::Other(rpl_opt::PADN))));
+        assert!(iter.next().is_none());
+
+        assert!(encode_dis(&mut buf[..3]).done().is_none());
+    }
+
+    #[test]
+    fn dao_encode() {
+        let target = IPAddr([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        let parent = IPAddr([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3]);
+        let mut buf = [0; 64];
+        let (len, _) = encode_dao(&mut buf, 1, 9, &DODAG_ID, &target, &parent, 4, 30)
+            .done()
+            .unwrap();
+        assert_eq!(len, 62);
+
+        assert_eq!(&buf[..4], &[1, DAO_FLAG_ACK | DAO_FLAG_DODAG_ID, 0, 9]);
+        assert_eq!(&buf[4..20], &DODAG_ID.0);
+        assert_eq!(&buf[20..24], &[rpl_opt::TARGET, 18, 0, 128]);
+        assert_eq!(&buf[24..40], &target.0);
+        assert_eq!(&buf[40..46], &[rpl_opt::TRANSIT_INFO, 20, 0, 0, 4, 30]);
+        assert_eq!(&buf[46..62], &parent.0);
+
+        // The options are well-formed
+        let mut iter = RplOptionIter::new(&buf[20..len]);
+        assert!(matches!(
+            iter.next(),
+            Some(RplOption::Other(rpl_opt::TARGET))
+        ));
+        assert!(matches!(
+            iter.next(),
+            Some(RplOption::Other(rpl_opt::TRANSIT_INFO))
+        ));
+        assert!(iter.next().is_none());
+
+        assert!(
+            encode_dao(&mut buf[..61], 1, 9, &DODAG_ID, &target, &parent, 4, 30)
+                .done()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,7 @@
+//! Modules for RPL (RFC 6550), which routes packets over several hops in a
+//! 6LoWPAN mesh. The node joins a DODAG in non-storing mode and resolves the
+//! next hop of the packets the IPv6 senders send.
+
+pub mod messages;
+pub mod node;
+pub mod objective;
@@ -0,0 +1,695 @@
+//! This file contains the [RplNode](struct.RplNode.html), which joins a
+//! DODAG of an RPL (RFC 6550) mesh in non-storing mode, so that packets can
+//! reach destinations several hops away:
+//!
+//! - After `start()` it sends DODAG Information Solicitations to the
+//!   all-RPL-nodes address until it hears a DODAG Information Object of a
+//!   non-storing DODAG that advertises a prefix and uses a supported
+//!   objective function.
+//! - It keeps the neighbors it hears DIOs from in a small table, and selects
+//!   its preferred parent among them with the objective function of the
+//!   DODAG. A new version of the DODAG, or a neighbor advertising the
+//!   infinite rank, makes it select again.
+//! - It forms a global address from the prefix of the DODAG, and advertises
+//!   it to the root in a DAO that names the parent, so that the root can
+//!   source-route packets down to the node. The DAO is sent again before
+//!   the route expires, when the parent changes, and when the parent asks
+//!   for it by incrementing its DTSN. A parent through which none of
+//!   several DAOs is acknowledged is dropped.
+//!
+//! As the stack does not forward packets, the node joins as a leaf: it
+//! does not send DIOs, so no other node selects it as parent.
+//!
+//! The node is the `NextHopResolver` of the IPv6 senders: packets to a
+//! link-local address are sent to the neighbor with that interface
+//! identifier, and all other unicast packets to the preferred parent, from
+//! where they travel up to the root.
+//!
+//! Without link-layer statistics, the ETX of the link to the parent, which
+//! MRHOF uses, is estimated from the number of transmissions a DAO needs to
+//! be acknowledged. Other neighbors keep an initial estimate.
+//!
+//! Usage
+//! -----
+//!
+//! The node is set up by `components::rpl::RplComponent`:
+//!
+//! ```rust
+//! let rpl_node = components::rpl::RplComponent::new(
+//!     mux_mac,
+//!     DEFAULT_CTX_PREFIX_LEN,
+//!     DEFAULT_CTX_PREFIX,
+//!     DST_MAC_ADDR,
+//!     src_mac_from_serial_num,
+//!     local_ip_ifaces,
+//!     mux_alarm,
+//! )
+//! .finalize(components::rpl_component_helper!(nrf52840::rtc::Rtc));
+//! udp_send_mux.set_next_hop_resolver(rpl_node);
+//! rpl_node.start();
+//! ```
+
+use crate::net::icmpv6::{ICMP6Header, ICMP6HeaderOptions, ICMP6Type};
+use crate::net::ieee802154::MacAddress;
+use crate::net::ipv6::ip_utils::{ip6_nh, IPAddr};
+use crate::net::ipv6::ipv6_recv::IP6RecvClient;
+use crate::net::ipv6::ipv6_send::{IP6SendClient, IP6Sender, NextHopResolver};
+use crate::net::ipv6::{IP6Header, TransportHeader};
+use crate::net::network_capabilities::NetworkCapability;
+use crate::net::rpl::messages::{self, rpl_code, DaoAck, DioBase, DodagConfig, RplOption};
+use crate::net::rpl::messages::{RplOptionIter, DIO_BASE_LEN, INFINITE_RANK, MOP_NON_STORING};
+use crate::net::rpl::objective::{self, ETX_DIVISOR};
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::hil::time::{self, ConvertTicks};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::utilities::leasable_buffer::LeasableMutableBuffer;
+use kernel::ErrorCode;
+
+/// The link-local all-RPL-nodes multicast address, ff02::1a.
+pub const ALL_RPL_NODES_ADDR: IPAddr =
+    IPAddr([0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x1a]);
+
+/// The number of neighbors the node keeps track of.
+pub const MAX_NEIGHBORS: usize = 4;
+
+/// Length of the buffer RPL messages are built in, which holds a DAO.
+pub const SEND_BUF_LEN: usize = 64;
+
+// Timers, in seconds.
+const DIS_INTERVAL: u32 = 60;
+const DAO_ACK_TIMEOUT: u32 = 5;
+/// DAOs for routes with an infinite lifetime are still refreshed this
+/// often, in case the root lost them.
+const MAX_DAO_INTERVAL: u32 = 3600;
+const MAX_DAO_ATTEMPTS: u8 = 3;
+
+/// The longest single alarm, in seconds. Longer timers are split so that the
+/// alarm does not overflow on chips with narrow counters.
+const MAX_ALARM_INTERVAL: u32 = 60;
+
+/// The ETX of a link before it is measured.
+const INITIAL_ETX: u16 = 2 * ETX_DIVISOR;
+
+/// The length of the prefix advertised in the DODAG.
+const PREFIX_LEN: u8 = 64;
+
+/// Receives the changes of the global address the node advertises in the
+/// DODAG.
+pub trait RplClient {
+    /// The root acknowledged the route to the global address of the node, or
+    /// the node left the DODAG (`None`).
+    fn global_address_changed(&self, addr: Option<IPAddr>);
+}
+
+/// A neighbor the node heard a DIO from.
+#[derive(Copy, Clone)]
+pub struct Neighbor {
+    /// The link-local address of the neighbor
+    pub addr: IPAddr,
+    pub rank: u16,
+    /// The estimated ETX of the link, in units of `1 / ETX_DIVISOR`
+    pub etx: u16,
+    dtsn: u8,
+}
+
+/// The DODAG the node is part of.
+#[derive(Copy, Clone)]
+struct Dodag {
+    instance_id: u8,
+    dodag_id: IPAddr,
+    version: u8,
+    config: DodagConfig,
+    prefix: [u8; 16],
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum RplState {
+    /// The node has not been started.
+    Idle,
+    /// Soliciting DIOs, outside of any DODAG.
+    Detached,
+    /// Sending DAOs until the root acknowledges one.
+    Advertising { attempts: u8 },
+    /// The root acknowledged the route, which is refreshed once the timer
+    /// expires.
+    Joined,
+}
+
+/// Returns whether the lollipop counter `a` (RFC 6550 7.2) is newer than
+/// `b`. This is a plain circular comparison, which is right once the
+/// counters left their initial linear region.
+fn is_newer(a: u8, b: u8) -> bool {
+    (a.wrapping_sub(b) as i8) > 0
+}
+
+/// Returns the MAC address a link-local address was formed from.
+fn mac_from_link_local(addr: &IPAddr) -> MacAddress {
+    if addr.0[8..14] == [0, 0, 0, 0xff, 0xfe, 0] {
+        MacAddress::Short((addr.0[14] as u16) << 8 | addr.0[15] as u16)
+    } else {
+        let mut long_addr = [0; 8];
+        long_addr.copy_from_slice(&addr.0[8..]);
+        long_addr[0] ^= 0b00000010;
+        MacAddress::Long(long_addr)
+    }
+}
+
+pub struct RplNode<'a, A: time::Alarm<'a>, S: IP6Sender<'a>> {
+    ip_send: &'a S,
+    alarm: &'a A,
+    net_cap: &'static NetworkCapability,
+    /// The link-local addresses of the node. The first one is used as the
+    /// source of DISes.
+    interface_list: &'static [IPAddr],
+    mac_addr: MacAddress,
+    /// Buffer for the messages sent.
+    send_buf: TakeCell<'static, [u8]>,
+    sending: Cell<bool>,
+    /// The message of the current state could not be sent because the
+    /// sender was busy, and is sent once it is done.
+    send_pending: Cell<bool>,
+    state: Cell<RplState>,
+    /// Seconds left until the timer of the current state expires.
+    timer: Cell<u32>,
+    dodag: OptionalCell<Dodag>,
+    neighbors: Cell<[Option<Neighbor>; MAX_NEIGHBORS]>,
+    /// The link-local address of the preferred parent
+    parent: OptionalCell<IPAddr>,
+    rank: Cell<u16>,
+    global_addr: OptionalCell<IPAddr>,
+    /// Whether the client was told about the global address.
+    global_addr_announced: Cell<bool>,
+    dao_sequence: Cell<u8>,
+    path_sequence: Cell<u8>,
+    client: OptionalCell<&'a dyn RplClient>,
+}
+
+impl<'a, A: time::Alarm<'a>, S: IP6Sender<'a>> RplNode<'a, A, S> {
+    pub fn new(
+        ip_send: &'a S,
+        alarm: &'a A,
+        net_cap: &'static NetworkCapability,
+        interface_list: &'static [IPAddr],
+        mac_addr: MacAddress,
+        send_buf: &'static mut [u8],
+    ) -> RplNode<'a, A, S> {
+        RplNode {
+            ip_send: ip_send,
+            alarm: alarm,
+            net_cap: net_cap,
+            interface_list: interface_list,
+            mac_addr: mac_addr,
+            send_buf: TakeCell::new(send_buf),
+            sending: Cell::new(false),
+            send_pending: Cell::new(false),
+            state: Cell::new(RplState::Idle),
+            timer: Cell::new(0),
+            dodag: OptionalCell::empty(),
+            neighbors: Cell::new([None; MAX_NEIGHBORS]),
+            parent: OptionalCell::empty(),
+            rank: Cell::new(INFINITE_RANK),
+            global_addr: OptionalCell::empty(),
+            global_addr_announced: Cell::new(false),
+            dao_sequence: Cell::new(0),
+            path_sequence: Cell::new(0),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn RplClient) {
+        self.client.set(client);
+    }
+
+    /// Start looking for a DODAG to join.
+    pub fn start(&self) {
+        if self.state.get() == RplState::Idle {
+            self.detach();
+        }
+    }
+
+    /// Returns the rank of the node, `INFINITE_RANK` outside of a DODAG.
+    pub fn rank(&self) -> u16 {
+        self.rank.get()
+    }
+
+    /// Returns the link-local address of the preferred parent.
+    pub fn parent(&self) -> Option<IPAddr> {
+        self.parent.extract()
+    }
+
+    /// Returns the global address formed from the prefix of the DODAG.
+    pub fn global_addr(&self) -> Option<IPAddr> {
+        self.global_addr.extract()
+    }
+
+    fn set_timer(&self, seconds: u32) {
+        self.timer.set(seconds);
+        self.arm_alarm();
+    }
+
+    fn arm_alarm(&self) {
+        let interval = cmp::min(self.timer.get(), MAX_ALARM_INTERVAL);
+        self.alarm
+            .set_alarm(self.alarm.now(), self.alarm.ticks_from_seconds(interval));
+    }
+
+    /// Leave the DODAG, if any, and solicit DIOs.
+    fn detach(&self) {
+        self.dodag.clear();
+        self.neighbors.set([None; MAX_NEIGHBORS]);
+        self.parent.clear();
+        self.rank.set(INFINITE_RANK);
+        self.global_addr.clear();
+        if self.global_addr_announced.take() {
+            self.client
+                .map(|client| client.global_address_changed(None));
+        }
+        self.state.set(RplState::Detached);
+        self.send_message();
+        self.set_timer(DIS_INTERVAL);
+    }
+
+    /// Advertise the route through the current parent with a new DAO.
+    fn start_advertising(&self) {
+        self.dao_sequence
+            .set(self.dao_sequence.get().wrapping_add(1));
+        self.state.set(RplState::Advertising { attempts: 1 });
+        self.send_message();
+        self.set_timer(DAO_ACK_TIMEOUT);
+    }
+
+    fn timer_expired(&self) {
+        match self.state.get() {
+            RplState::Idle => {}
+            RplState::Detached => {
+                self.send_message();
+                self.set_timer(DIS_INTERVAL);
+            }
+            RplState::Advertising { attempts } => {
+                if attempts >= MAX_DAO_ATTEMPTS {
+                    // The DAOs do not get through the parent, try another one.
+                    self.parent
+                        .take()
+                        .map(|parent| self.remove_neighbor(parent));
+                    self.select_parent(false);
+                } else {
+                    self.state.set(RplState::Advertising {
+                        attempts: attempts + 1,
+                    });
+                    self.send_message();
+                    self.set_timer(DAO_ACK_TIMEOUT);
+                }
+            }
+            RplState::Joined => self.start_advertising(),
+        }
+    }
+
+    /// Send the message of the current state, or send it once the sender is
+    /// free.
+    fn send_message(&self) {
+        let result = match self.state.get() {
+            RplState::Idle | RplState::Joined => Ok(()),
+            RplState::Detached => self.send_dis(),
+            RplState::Advertising { .. } => self.send_dao(),
+        };
+        if result == Err(ErrorCode::BUSY) {
+            self.send_pending.set(true);
+        }
+    }
+
+    fn send_dis(&self) -> Result<(), ErrorCode> {
+        let src_addr = self.interface_list.first().ok_or(ErrorCode::FAIL)?;
+        self.send_rpl(*src_addr, ALL_RPL_NODES_ADDR, rpl_code::DIS, |buf| {
+            messages::encode_dis(buf).done()
+        })
+    }
+
+    fn send_dao(&self) -> Result<(), ErrorCode> {
+        let (dodag, parent, global_addr) = match (
+            self.dodag.extract(),
+            self.parent.extract(),
+            self.global_addr.extract(),
+        ) {
+            (Some(dodag), Some(parent), Some(global_addr)) => (dodag, parent, global_addr),
+            _ => return Err(ErrorCode::FAIL),
+        };
+        // The root needs a global address of the parent, which is formed
+        // from the prefix of the DODAG like that of the node.
+        let mut parent_addr = parent;
+        parent_addr.set_prefix(&dodag.prefix, PREFIX_LEN);
+        self.send_rpl(global_addr, dodag.dodag_id, rpl_code::DAO, |buf| {
+            messages::encode_dao(
+                buf,
+                dodag.instance_id,
+                self.dao_sequence.get(),
+                &dodag.dodag_id,
+                &global_addr,
+                &parent_addr,
+                self.path_sequence.get(),
+                dodag.config.default_lifetime,
+            )
+            .done()
+        })
+    }
+
+    /// Send an RPL control message with code `code`, which is written by
+    /// `write_message` from the start of its base. The first word of the
+    /// message goes into the ICMPv6 header.
+    fn send_rpl<F>(
+        &self,
+        src_addr: IPAddr,
+        dst_addr: IPAddr,
+        code: u8,
+        write_message: F,
+    ) -> Result<(), ErrorCode>
+    where
+        F: FnOnce(&mut [u8]) -> Option<(usize, usize)>,
+    {
+        if self.sending.get() {
+            return Err(ErrorCode::BUSY);
+        }
+        let buf = self.send_buf.take().ok_or(ErrorCode::NOMEM)?;
+        let len = match write_message(buf) {
+            Some((len, _)) if len >= 4 => len,
+            _ => {
+                self.send_buf.replace(buf);
+                return Err(ErrorCode::SIZE);
+            }
+        };
+        let mut header = ICMP6Header::new(ICMP6Type::Type155);
+        header.set_code(code);
+        header.set_options(ICMP6HeaderOptions::Type155 {
+            base: u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]),
+        });
+        let mut payload = LeasableMutableBuffer::new(buf);
+        payload.slice(4..len);
+
+        // The packet is copied by the sender, so the buffer can be reused
+        // right away.
+        self.sending.set(true);
+        self.ip_send.set_addr(src_addr);
+        let result = self.ip_send.send_to(
+            dst_addr,
+            TransportHeader::ICMP(header),
+            &payload,
+            self.net_cap,
+        );
+        self.send_buf.replace(payload.take());
+        if result.is_err() {
+            self.sending.set(false);
+        }
+        result
+    }
+
+    fn remove_neighbor(&self, addr: IPAddr) {
+        let mut neighbors = self.neighbors.get();
+        for entry in neighbors.iter_mut() {
+            if entry.map_or(false, |neighbor| neighbor.addr == addr) {
+                *entry = None;
+            }
+        }
+        self.neighbors.set(neighbors);
+    }
+
+    /// Record the rank and DTSN a neighbor advertised. When the table is
+    /// full, the neighbor with the highest rank other than the parent makes
+    /// room for a neighbor with a lower rank.
+    ///
+    /// Returns whether the neighbor is the parent and asks for a new DAO.
+    fn update_neighbor(&self, addr: IPAddr, rank: u16, dtsn: u8) -> bool {
+        if rank == INFINITE_RANK {
+            self.remove_neighbor(addr);
+            return false;
+        }
+        let mut neighbors = self.neighbors.get();
+        let is_parent = self.parent.contains(&addr);
+        let mut dao_requested = false;
+        if let Some(neighbor) = neighbors.iter_mut().flatten().find(|n| n.addr == addr) {
+            dao_requested = is_parent && is_newer(dtsn, neighbor.dtsn);
+            neighbor.rank = rank;
+            neighbor.dtsn = dtsn;
+        } else {
+            let slot = match neighbors.iter().position(|entry| entry.is_none()) {
+                Some(free) => Some(free),
+                None => neighbors
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, entry)| entry.map(|neighbor| (i, neighbor)))
+                    .filter(|(_, neighbor)| !self.parent.contains(&neighbor.addr))
+                    .max_by_key(|(_, neighbor)| neighbor.rank)
+                    .filter(|(_, neighbor)| neighbor.rank > rank)
+                    .map(|(i, _)| i),
+            };
+            slot.map(|i| {
+                neighbors[i] = Some(Neighbor {
+                    addr,
+                    rank,
+                    etx: INITIAL_ETX,
+                    dtsn,
+                })
+            });
+        }
+        self.neighbors.set(neighbors);
+        dao_requested
+    }
+
+    /// Select the preferred parent with the objective function of the DODAG,
+    /// and advertise the route through it if it changed or `new_dao`.
+    fn select_parent(&self, new_dao: bool) {
+        let dodag = match self.dodag.extract() {
+            Some(dodag) => dodag,
+            None => return,
+        };
+        let of = match objective::from_ocp(dodag.config.ocp) {
+            Some(of) => of,
+            None => return self.detach(),
+        };
+        let min_hop_rank_increase = dodag.config.min_hop_rank_increase;
+        let neighbors = self.neighbors.get();
+        let rank_via = |neighbor: &Neighbor| {
+            of.rank_via(neighbor.rank, neighbor.etx, min_hop_rank_increase)
+                .map(|rank| (neighbor.addr, rank))
+        };
+
+        let best = neighbors
+            .iter()
+            .flatten()
+            .filter_map(rank_via)
+            .min_by_key(|(_, rank)| *rank);
+        let current = self.parent.extract().and_then(|parent| {
+            neighbors
+                .iter()
+                .flatten()
+                .find(|neighbor| neighbor.addr == parent)
+                .and_then(rank_via)
+        });
+        let selected = match (best, current) {
+            (Some((_, best_rank)), Some((_, current_rank)))
+                if !of.should_switch(best_rank, current_rank) =>
+            {
+                current
+            }
+            _ => best,
+        };
+
+        match selected {
+            None => self.detach(),
+            Some((parent, rank)) => {
+                self.rank.set(rank);
+                let parent_changed = !self.parent.contains(&parent);
+                if parent_changed {
+                    self.parent.set(parent);
+                    self.path_sequence
+                        .set(self.path_sequence.get().wrapping_add(1));
+                }
+                if parent_changed || new_dao || self.state.get() == RplState::Detached {
+                    self.start_advertising();
+                }
+            }
+        }
+    }
+
+    fn dio(&self, ip_header: &IP6Header, message: &[u8]) {
+        let src_addr = ip_header.get_src_addr();
+        if !src_addr.is_unicast_link_local() {
+            return;
+        }
+        let base = match DioBase::decode(message).done() {
+            Some((_, base)) => base,
+            None => return,
+        };
+        if base.mop != MOP_NON_STORING {
+            return;
+        }
+
+        let mut config = None;
+        let mut prefix = None;
+        for option in RplOptionIter::new(&message[DIO_BASE_LEN..]) {
+            match option {
+                RplOption::DodagConfig(dodag_config) => config = Some(dodag_config),
+                RplOption::PrefixInfo(info)
+                    if info.is_autonomous() && info.prefix_len == PREFIX_LEN =>
+                {
+                    prefix = Some(info.prefix)
+                }
+                _ => {}
+            }
+        }
+        let supported = |config: &DodagConfig| objective::from_ocp(config.ocp).is_some();
+
+        match self.dodag.extract() {
+            None => {
+                // Join a DODAG whose objective function is supported, and
+                // that gives a prefix for the global address.
+                let config = config.unwrap_or_default();
+                let prefix = match prefix {
+                    Some(prefix) if supported(&config) && base.rank != INFINITE_RANK => prefix,
+                    _ => return,
+                };
+                let mut global_addr = IPAddr::generate_from_mac(self.mac_addr);
+                global_addr.set_prefix(&prefix, PREFIX_LEN);
+                self.global_addr.set(global_addr);
+                self.dodag.set(Dodag {
+                    instance_id: base.instance_id,
+                    dodag_id: base.dodag_id,
+                    version: base.version,
+                    config,
+                    prefix,
+                });
+            }
+            Some(mut dodag)
+                if dodag.instance_id == base.instance_id && dodag.dodag_id == base.dodag_id =>
+            {
+                if is_newer(base.version, dodag.version) {
+                    // The root rebuilt the DODAG, the ranks of the old
+                    // version no longer count.
+                    self.neighbors.set([None; MAX_NEIGHBORS]);
+                    self.parent.clear();
+                    dodag.version = base.version;
+                } else if base.version != dodag.version {
+                    return;
+                }
+                config.filter(supported).map(|config| dodag.config = config);
+                self.dodag.set(dodag);
+            }
+            Some(_) => return,
+        }
+
+        let dao_requested = self.update_neighbor(src_addr, base.rank, base.dtsn);
+        self.select_parent(dao_requested);
+    }
+
+    fn dao_ack(&self, ip_header: &IP6Header, message: &[u8]) {
+        if !self.global_addr.contains(&ip_header.get_dst_addr()) {
+            return;
+        }
+        let ack = match DaoAck::decode(message).done() {
+            Some((_, ack)) => ack,
+            None => return,
+        };
+        let attempts = match self.state.get() {
+            RplState::Advertising { attempts } => attempts,
+            _ => return,
+        };
+        let dodag = match self.dodag.extract() {
+            Some(dodag) => dodag,
+            None => return,
+        };
+        if ack.instance_id != dodag.instance_id || ack.sequence != self.dao_sequence.get() {
+            return;
+        }
+        if !ack.is_accepted() {
+            // The root does not take the route, look for another DODAG.
+            self.detach();
+            return;
+        }
+
+        // Move the ETX estimate of the link to the parent towards the
+        // number of transmissions the DAO took.
+        let mut neighbors = self.neighbors.get();
+        for neighbor in neighbors.iter_mut().flatten() {
+            if self.parent.contains(&neighbor.addr) {
+                neighbor.etx =
+                    ((neighbor.etx as u32 * 9 + attempts as u32 * ETX_DIVISOR as u32) / 10) as u16;
+            }
+        }
+        self.neighbors.set(neighbors);
+
+        self.state.set(RplState::Joined);
+        // Refresh the route when 80% of its lifetime passed.
+        let refresh = dodag
+            .config
+            .route_lifetime()
+            .map_or(MAX_DAO_INTERVAL, |lifetime| {
+                cmp::min(lifetime / 5 * 4, MAX_DAO_INTERVAL)
+            });
+        self.set_timer(cmp::max(refresh, DAO_ACK_TIMEOUT));
+        if !self.global_addr_announced.replace(true) {
+            self.global_addr.map(|addr| {
+                self.client
+                    .map(|client| client.global_address_changed(Some(*addr)))
+            });
+        }
+        self.select_parent(false);
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, S: IP6Sender<'a>> IP6RecvClient for RplNode<'a, A, S> {
+    fn receive(&self, ip_header: IP6Header, payload: &[u8]) {
+        // Other protocols may share the IP receive path
+        if ip_header.get_next_header() != ip6_nh::ICMP || self.state.get() == RplState::Idle {
+            return;
+        }
+        let len = cmp::min(payload.len(), ip_header.get_payload_len() as usize);
+        let payload = &payload[..len];
+        let icmp_header = match ICMP6Header::decode(payload).done() {
+            Some((_, icmp_header)) => icmp_header,
+            None => return,
+        };
+        // The message starts after the ICMPv6 checksum
+        let message = &payload[4..];
+        match (icmp_header.get_options(), icmp_header.get_code()) {
+            (ICMP6HeaderOptions::Type155 { .. }, rpl_code::DIO) => self.dio(&ip_header, message),
+            (ICMP6HeaderOptions::Type155 { .. }, rpl_code::DAO_ACK) => {
+                self.dao_ack(&ip_header, message)
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, S: IP6Sender<'a>> IP6SendClient for RplNode<'a, A, S> {
+    fn send_done(&self, _result: Result<(), ErrorCode>) {
+        self.sending.set(false);
+        if self.send_pending.take() {
+            self.send_message();
+        }
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, S: IP6Sender<'a>> NextHopResolver for RplNode<'a, A, S> {
+    fn next_hop(&self, dst: IPAddr) -> Option<MacAddress> {
+        if dst.is_unicast_link_local() {
+            Some(mac_from_link_local(&dst))
+        } else {
+            self.parent
+                .extract()
+                .map(|parent| mac_from_link_local(&parent))
+        }
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, S: IP6Sender<'a>> time::AlarmClient for RplNode<'a, A, S> {
+    fn alarm(&self) {
+        let interval = cmp::min(self.timer.get(), MAX_ALARM_INTERVAL);
+        self.timer.set(self.timer.get() - interval);
+        if self.timer.get() > 0 {
+            self.arm_alarm();
+        } else {
+            self.timer_expired();
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! This file contains the objective functions a node uses to compute its
+//! rank and select its preferred parent: the Objective Function Zero (RFC
+//! 6552) and the Minimum Rank with Hysteresis Objective Function (RFC 6719)
+//! with the ETX metric. The root of a DODAG selects the objective function
+//! with the Objective Code Point of the DODAG Configuration option.
+//!
+//! Link qualities are expressed as ETX, the expected number of transmissions
+//! of a packet over the link, in units of `1 / ETX_DIVISOR`.
+
+use crate::net::rpl::messages::INFINITE_RANK;
+
+use core::cmp;
+
+/// Objective Code Points.
+pub mod ocp {
+    pub const OF0: u16 = 0;
+    pub const MRHOF: u16 = 1;
+}
+
+/// The ETX of a perfect link.
+pub const ETX_DIVISOR: u16 = 128;
+
+pub trait ObjectiveFunction {
+    /// Returns the rank of the node if it picks the neighbor with rank
+    /// `neighbor_rank` and link ETX `etx` as parent, or `None` if the
+    /// neighbor is not acceptable as a parent.
+    fn rank_via(&self, neighbor_rank: u16, etx: u16, min_hop_rank_increase: u16) -> Option<u16>;
+
+    /// Returns whether the node should switch from a parent through which it
+    /// has rank `current` to one through which it has rank `candidate`.
+    fn should_switch(&self, candidate: u16, current: u16) -> bool;
+}
+
+/// Returns the objective function of an Objective Code Point, or `None` if
+/// it is not supported.
+pub fn from_ocp(code_point: u16) -> Option<&'static dyn ObjectiveFunction> {
+    match code_point {
+        ocp::OF0 => Some(&Of0),
+        ocp::MRHOF => Some(&Mrhof),
+        _ => None,
+    }
+}
+
+/// Limits a rank to the infinite rank, which no parent can give.
+fn finite_rank(rank: u32) -> Option<u16> {
+    if rank < INFINITE_RANK as u32 {
+        Some(rank as u16)
+    } else {
+        None
+    }
+}
+
+/// Objective Function Zero, which adds the same rank increase for every hop
+/// regardless of the link quality.
+pub struct Of0;
+
+/// The default step of rank of OF0, with the default rank factor of 1 and
+/// stretch of rank of 0.
+const OF0_STEP_OF_RANK: u32 = 3;
+
+impl ObjectiveFunction for Of0 {
+    fn rank_via(&self, neighbor_rank: u16, _etx: u16, min_hop_rank_increase: u16) -> Option<u16> {
+        finite_rank(neighbor_rank as u32 + OF0_STEP_OF_RANK * min_hop_rank_increase as u32)
+    }
+
+    fn should_switch(&self, candidate: u16, current: u16) -> bool {
+        candidate < current
+    }
+}
+
+/// Minimum Rank with Hysteresis Objective Function, which uses the sum of
+/// the ETX of the links to the root as rank, and only switches parents for
+/// a significantly better path.
+pub struct Mrhof;
+
+/// Links with a higher ETX are not used.
+const MRHOF_MAX_LINK_METRIC: u16 = 4 * ETX_DIVISOR;
+/// Paths with a higher cost are not used.
+const MRHOF_MAX_PATH_COST: u32 = 256 * ETX_DIVISOR as u32;
+/// The improvement in path cost needed to switch parents.
+const MRHOF_PARENT_SWITCH_THRESHOLD: u16 = 3 * ETX_DIVISOR / 2;
+
+impl ObjectiveFunction for Mrhof {
+    fn rank_via(&self, neighbor_rank: u16, etx: u16, min_hop_rank_increase: u16) -> Option<u16> {
+        if etx > MRHOF_MAX_LINK_METRIC {
+            return None;
+        }
+        let path_cost = neighbor_rank as u32 + etx as u32;
+        if path_cost > MRHOF_MAX_PATH_COST {
+            return None;
+        }
+        // The rank must increase by at least one hop
+        finite_rank(cmp::max(
+            path_cost,
+            neighbor_rank as u32 + min_hop_rank_increase as u32,
+        ))
+    }
+
+    fn should_switch(&self, candidate: u16, current: u16) -> bool {
+        candidate.saturating_add(MRHOF_PARENT_SWITCH_THRESHOLD) < current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_points() {
+        assert!(from_ocp(ocp::OF0).is_some());
+        assert!(from_ocp(ocp::MRHOF).is_some());
+        assert!(from_ocp(2).is_none());
+        assert!(from_ocp(u16::MAX).is_none());
+    }
+
+    #[test]
+    fn of0_rank() {
+        // Three times the minimum increase per hop, whatever the link
+        assert_eq!(Of0.rank_via(256, ETX_DIVISOR, 256), Some(1024));
+        assert_eq!(Of0.rank_via(256, 4 * ETX_DIVISOR, 256), Some(1024));
+        assert_eq!(Of0.rank_via(256, u16::MAX, 128), Some(640));
+
+        // No parent can give the infinite rank
+        assert_eq!(
+            Of0.rank_via(INFINITE_RANK - 3 * 256, ETX_DIVISOR, 256),
+            None
+        );
+        assert_eq!(
+            Of0.rank_via(INFINITE_RANK - 3 * 256 - 1, ETX_DIVISOR, 256),
+            Some(INFINITE_RANK - 1)
+        );
+        assert_eq!(Of0.rank_via(INFINITE_RANK, ETX_DIVISOR, 256), None);
+        assert_eq!(Of0.rank_via(0x8000, ETX_DIVISOR, u16::MAX), None);
+    }
+
+    #[test]
+    fn of0_parent_switch() {
+        assert!(Of0.should_switch(512, 768));
+        assert!(Of0.should_switch(767, 768));
+        assert!(!Of0.should_switch(768, 768));
+        assert!(!Of0.should_switch(1024, 768));
+    }
+
+    #[test]
+    fn mrhof_rank() {
+        // The ETX is added to the rank of the neighbor
+        assert_eq!(Mrhof.rank_via(256, 3 * ETX_DIVISOR, 128), Some(640));
+        // But the rank increases by at least one hop
+        assert_eq!(Mrhof.rank_via(256, ETX_DIVISOR, 256), Some(512));
+        assert_eq!(Mrhof.rank_via(256, 0, 256), Some(512));
+
+        // Links that are too lossy are not used
+        assert_eq!(
+            Mrhof.rank_via(256, MRHOF_MAX_LINK_METRIC, 128),
+            Some(256 + MRHOF_MAX_LINK_METRIC)
+        );
+        assert_eq!(Mrhof.rank_via(256, MRHOF_MAX_LINK_METRIC + 1, 128), None);
+
+        // Neither are paths that are too long
+        let max_path_cost = MRHOF_MAX_PATH_COST as u16;
+        assert_eq!(
+            Mrhof.rank_via(max_path_cost - ETX_DIVISOR, ETX_DIVISOR, 1),
+            Some(max_path_cost)
+        );
+        assert_eq!(
+            Mrhof.rank_via(max_path_cost - ETX_DIVISOR + 1, ETX_DIVISOR, 1),
+            None
+        );
+
+        // The minimum increase can't reach the infinite rank either
+        assert_eq!(Mrhof.rank_via(0x7f00, ETX_DIVISOR, 0x80ff), None);
+        assert_eq!(
+            Mrhof.rank_via(0x7f00, ETX_DIVISOR, 0x80fe),
+            Some(INFINITE_RANK - 1)
+        );
+    }
+
+    #[test]
+    fn mrhof_parent_switch() {
+        let current = 1000;
+        // Only paths that are better by the threshold are worth switching to
+        assert!(Mrhof.should_switch(current - MRHOF_PARENT_SWITCH_THRESHOLD - 1, current));
+        assert!(!Mrhof.should_switch(current - MRHOF_PARENT_SWITCH_THRESHOLD, current));
+        assert!(!Mrhof.should_switch(current - 1, current));
+        assert!(!Mrhof.should_switch(current, current));
+        assert!(!Mrhof.should_switch(current + 1, current));
+
+        // Ranks close to infinity don't overflow
+        assert!(!Mrhof.should_switch(INFINITE_RANK - 1, INFINITE_RANK));
+        assert!(Mrhof.should_switch(0, INFINITE_RANK));
+    }
+}
@@ -228,7 +228,7 @@ use crate::ieee802154::device::{MacDevice, RxClient};
 use crate::ieee802154::framer::Frame;
 use crate::net::frag_utils::Bitmap;
 use crate::net::ieee802154::{Header, KeyId, MacAddress, PanID, SecurityLevel};
-use crate::net::ipv6::IP6Packet;
+use crate::net::ipv6::{IP6Packet, MAX_HDRS_LEN};
 use crate::net::sixlowpan::sixlowpan_compression;
 use crate::net::sixlowpan::sixlowpan_compression::{is_lowpan, ContextStore};
 use crate::net::util::{network_slice_to_u16, u16_to_network_slice};
@@ -576,7 +576,7 @@ impl<'a> TxState<'a> {
             // statically allocate room on the stack. However, we do not know
             // how many additional headers we have until runtime. This
             // functionality should be fixed in the future.
-            let mut headers = [0 as u8; 60];
+            let mut headers = [0 as u8; MAX_HDRS_LEN];
             ip6_packet.encode(&mut headers);
             let _ = frame.append_payload(&headers[dgram_offset..dgram_offset + headers_to_write]);
             payload_len -= headers_to_write;
@@ -0,0 +1,527 @@
+//! TCP userspace interface.
+//!
+//! Implements a userspace interface for opening TCP connections and
+//! exchanging stream data over them. Each process may use one connection at a
+//! time; connections are backed by a fixed pool of `TCPSocket`s shared by all
+//! processes.
+//!
+//! The stack does not buffer stream data in the kernel. Outgoing data is read
+//! from the process' write buffer whenever a segment is (re)transmitted, so
+//! the buffer must not be modified until the send completes. Incoming data is
+//! appended to the process' read buffer, whose free space is advertised to
+//! the peer as the receive window; the process hands the buffer back with
+//! the "receive consumed" command once it has processed the data.
+
+use crate::net::ipv6::ip_utils::IPAddr;
+use crate::net::network_capabilities::NetworkCapability;
+use crate::net::tcp::tcp_mux::TCPSocketManager;
+use crate::net::tcp::tcp_socket::{TCPClient, TCPSocket, TCPState};
+use crate::net::util::host_slice_to_u16;
+
+use core::cmp;
+use core::mem::size_of;
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::processbuffer::{ReadableProcessBuffer, WriteableProcessBuffer};
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::{ErrorCode, ProcessId};
+
+use crate::driver;
+pub const DRIVER_NUM: usize = driver::NUM::Tcp as usize;
+
+/// Size of an endpoint in the config buffer: a 16 byte IPv6 address followed
+/// by a port in host byte order.
+const ENDPOINT_LEN: usize = size_of::<IPAddr>() + size_of::<u16>();
+
+/// Ids for read-only allow buffers
+mod ro_allow {
+    pub const WRITE: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Ids for read-write allow buffers
+mod rw_allow {
+    pub const READ: usize = 0;
+    pub const CFG: usize = 1;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 2;
+}
+
+/// Ids for subscribe upcalls
+mod upcalls {
+    pub const RECEIVE: usize = 0;
+    pub const SEND_DONE: usize = 1;
+    pub const CONNECTION: usize = 2;
+    /// The number of upcalls the kernel stores for this grant
+    pub const COUNT: u8 = 3;
+}
+
+/// Events reported through the connection upcall.
+mod connection_event {
+    pub const CONNECTED: usize = 0;
+    pub const REMOTE_CLOSED: usize = 1;
+    pub const CLOSED: usize = 2;
+}
+
+#[derive(Default)]
+pub struct App {
+    /// Index of the socket this process uses in the socket pool
+    socket: Option<usize>,
+    /// Length of the outstanding send, 0 if none
+    tx_len: usize,
+    /// Bytes of the outstanding send acknowledged by the peer
+    tx_acked: usize,
+    /// Bytes received into the read buffer and not yet consumed
+    rx_len: usize,
+}
+
+pub struct TCPDriver<'a> {
+    tcp: &'a dyn TCPSocketManager<'a>,
+    sockets: &'a [TCPSocket<'a>],
+    apps: Grant<
+        App,
+        UpcallCount<{ upcalls::COUNT }>,
+        AllowRoCount<{ ro_allow::COUNT }>,
+        AllowRwCount<{ rw_allow::COUNT }>,
+    >,
+    /// List of IP Addresses of the interfaces on the device
+    interface_list: &'static [IPAddr],
+    net_cap: &'static NetworkCapability,
+}
+
+impl<'a> TCPDriver<'a> {
+    pub fn new(
+        tcp: &'a dyn TCPSocketManager<'a>,
+        sockets: &'a [TCPSocket<'a>],
+        grant: Grant<
+            App,
+            UpcallCount<{ upcalls::COUNT }>,
+            AllowRoCount<{ ro_allow::COUNT }>,
+            AllowRwCount<{ rw_allow::COUNT }>,
+        >,
+        interface_list: &'static [IPAddr],
+        net_cap: &'static NetworkCapability,
+    ) -> TCPDriver<'a> {
+        TCPDriver {
+            tcp: tcp,
+            sockets: sockets,
+            apps: grant,
+            interface_list: interface_list,
+            net_cap: net_cap,
+        }
+    }
+
+    /// Returns the process that owns socket `socket_id`, if any.
+    fn owner(&self, socket_id: usize) -> Option<ProcessId> {
+        self.apps.iter().find_map(|app| {
+            let processid = app.processid();
+            app.enter(|app, _| {
+                if app.socket == Some(socket_id) {
+                    Some(processid)
+                } else {
+                    None
+                }
+            })
+        })
+    }
+
+    /// Returns the socket of `processid`, allocating one from the pool if the
+    /// process does not have one yet. Fails with BUSY if the process' socket
+    /// is still in use and with NOMEM if the pool is exhausted.
+    fn get_or_allocate_socket(&self, processid: ProcessId) -> Result<&'a TCPSocket<'a>, ErrorCode> {
+        let current = self
+            .apps
+            .enter(processid, |app, _| app.socket)
+            .map_err(ErrorCode::from)?;
+        if let Some(index) = current {
+            let socket = &self.sockets[index];
+            return if socket.get_state() == TCPState::Closed {
+                Ok(socket)
+            } else {
+                Err(ErrorCode::BUSY)
+            };
+        }
+
+        let index = (0..self.sockets.len())
+            .find(|&index| self.owner(index).is_none())
+            .ok_or(ErrorCode::NOMEM)?;
+        let socket = &self.sockets[index];
+        // The previous owner may have exited without closing its connection
+        if socket.get_state() != TCPState::Closed {
+            self.tcp.abort(socket);
+        }
+        self.apps
+            .enter(processid, |app, _| {
+                app.socket = Some(index);
+                app.tx_len = 0;
+                app.tx_acked = 0;
+                app.rx_len = 0;
+            })
+            .map_err(ErrorCode::from)?;
+        Ok(socket)
+    }
+
+    /// Returns the socket of `processid`, or OFF if it has none.
+    fn get_socket(&self, processid: ProcessId) -> Result<&'a TCPSocket<'a>, ErrorCode> {
+        self.apps
+            .enter(processid, |app, _| app.socket)
+            .map_err(ErrorCode::from)?
+            .map(|index| &self.sockets[index])
+            .ok_or(ErrorCode::OFF)
+    }
+
+    fn release_socket(&self, processid: ProcessId) {
+        let _ = self.apps.enter(processid, |app, _| {
+            app.socket = None;
+            app.tx_len = 0;
+            app.tx_acked = 0;
+        });
+    }
+
+    /// Reads `count` endpoints from the config buffer of `processid`.
+    fn read_endpoints(
+        &self,
+        processid: ProcessId,
+        count: usize,
+    ) -> Result<[(IPAddr, u16); 2], ErrorCode> {
+        self.apps
+            .enter(processid, |_, kernel_data| {
+                kernel_data
+                    .get_readwrite_processbuffer(rw_allow::CFG)
+                    .and_then(|cfg| {
+                        cfg.enter(|cfg| {
+                            if cfg.len() != count * ENDPOINT_LEN {
+                                return Err(ErrorCode::INVAL);
+                            }
+                            let mut tmp_cfg_buffer = [0; 2 * ENDPOINT_LEN];
+                            cfg.copy_to_slice(&mut tmp_cfg_buffer[..cfg.len()]);
+                            let mut endpoints = [(IPAddr::new(), 0); 2];
+                            for (i, endpoint) in endpoints.iter_mut().take(count).enumerate() {
+                                let buf = &tmp_cfg_buffer[i * ENDPOINT_LEN..];
+                                endpoint.0 .0.copy_from_slice(&buf[..size_of::<IPAddr>()]);
+                                endpoint.1 = host_slice_to_u16(&buf[size_of::<IPAddr>()..]);
+                            }
+                            Ok(endpoints)
+                        })
+                    })
+                    .unwrap_or(Err(ErrorCode::INVAL))
+            })
+            .unwrap_or_else(|err| Err(err.into()))
+    }
+
+    fn is_local_addr(&self, addr: IPAddr) -> bool {
+        self.interface_list.iter().any(|iface| *iface == addr)
+    }
+
+    fn schedule_upcall(&self, socket_id: usize, upcall: usize, data: (usize, usize, usize)) {
+        self.owner(socket_id).map(|processid| {
+            let _ = self.apps.enter(processid, |_, kernel_data| {
+                kernel_data.schedule_upcall(upcall, data).ok();
+            });
+        });
+    }
+}
+
+impl<'a> SyscallDriver for TCPDriver<'a> {
+    /// TCP control
+    ///
+    /// ### `command_num`
+    ///
+    /// - `0`: Driver check.
+    /// - `1`: Get the interface list, as in the UDP driver.
+    ///        app_cfg (out): 16 * `n` bytes: the list of interface IPv6
+    ///        addresses, length limited by `app_cfg` length. Returns the
+    ///        total number of interfaces.
+    /// - `2`: Listen on the local endpoint in the config buffer. The
+    ///        connection upcall reports `CONNECTED` once a peer connects.
+    ///        Returns INVAL if the address is not a local interface or the
+    ///        port is 0, BUSY if the process' connection is still open and
+    ///        NOMEM if no socket is available.
+    /// - `3`: Connect from the local endpoint to the remote endpoint in the
+    ///        config buffer (two endpoints, local first). A local port of 0
+    ///        selects an ephemeral port. The connection upcall reports
+    ///        `CONNECTED` with the result of the handshake.
+    /// - `4`: Send the first `arg1` bytes of the write buffer. The send done
+    ///        upcall is scheduled once the peer acknowledged all of them;
+    ///        the write buffer must be left untouched until then. Returns
+    ///        BUSY if a send is outstanding.
+    /// - `5`: Mark all data in the read buffer as consumed, reopening the
+    ///        receive window.
+    /// - `6`: Close the connection once outstanding data is sent. The
+    ///        connection upcall reports `CLOSED` when done.
+    /// - `7`: Abort the connection and release the socket immediately.
+    /// - `8`: Get the state of the connection (see `TCPState`).
+    /// - `9`: Get the maximum number of payload bytes sent per segment.
+    fn command(
+        &self,
+        command_num: usize,
+        arg1: usize,
+        _: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            // Writes the requested number of network interface addresses
+            // `arg1`: number of interfaces requested that will fit into the buffer
+            1 => self
+                .apps
+                .enter(processid, |_, kernel_data| {
+                    kernel_data
+                        .get_readwrite_processbuffer(rw_allow::CFG)
+                        .and_then(|cfg| {
+                            cfg.mut_enter(|cfg| {
+                                if cfg.len() != arg1 * size_of::<IPAddr>() {
+                                    return CommandReturn::failure(ErrorCode::INVAL);
+                                }
+                                let n_ifaces_to_copy = cmp::min(arg1, self.interface_list.len());
+                                let iface_size = size_of::<IPAddr>();
+                                for i in 0..n_ifaces_to_copy {
+                                    cfg[i * iface_size..(i + 1) * iface_size]
+                                        .copy_from_slice(&self.interface_list[i].0);
+                                }
+                                CommandReturn::success_u32(self.interface_list.len() as u32)
+                            })
+                        })
+                        .unwrap_or(CommandReturn::failure(ErrorCode::INVAL))
+                })
+                .unwrap_or_else(|err| CommandReturn::failure(err.into())),
+
+            // Listen
+            2 => {
+                let result = self.read_endpoints(processid, 1).and_then(|endpoints| {
+                    let (local_addr, local_port) = endpoints[0];
+                    if !self.is_local_addr(local_addr) || local_port == 0 {
+                        return Err(ErrorCode::INVAL);
+                    }
+                    let socket = self.get_or_allocate_socket(processid)?;
+                    self.tcp.listen(socket, local_port, self.net_cap)
+                });
+                CommandReturn::from(result)
+            }
+
+            // Connect
+            3 => {
+                let result = self.read_endpoints(processid, 2).and_then(|endpoints| {
+                    let (local_addr, local_port) = endpoints[0];
+                    let (remote_addr, remote_port) = endpoints[1];
+                    if !self.is_local_addr(local_addr) {
+                        return Err(ErrorCode::INVAL);
+                    }
+                    let socket = self.get_or_allocate_socket(processid)?;
+                    self.tcp
+                        .connect(socket, local_port, remote_addr, remote_port, self.net_cap)
+                });
+                CommandReturn::from(result)
+            }
+
+            // Send
+            4 => {
+                let result = self
+                    .apps
+                    .enter(processid, |app, kernel_data| {
+                        if app.socket.is_none() {
+                            return Err(ErrorCode::OFF);
+                        }
+                        if app.tx_len != 0 {
+                            return Err(ErrorCode::BUSY);
+                        }
+                        let available = kernel_data
+                            .get_readonly_processbuffer(ro_allow::WRITE)
+                            .map_or(0, |write| write.len());
+                        if arg1 == 0 || arg1 > available {
+                            return Err(ErrorCode::SIZE);
+                        }
+                        app.tx_len = arg1;
+                        app.tx_acked = 0;
+                        Ok(())
+                    })
+                    .unwrap_or_else(|err| Err(err.into()))
+                    .and_then(|()| {
+                        let socket = self.get_socket(processid)?;
+                        self.tcp.send(socket, arg1).map_err(|err| {
+                            let _ = self.apps.enter(processid, |app, _| app.tx_len = 0);
+                            err
+                        })
+                    });
+                CommandReturn::from(result)
+            }
+
+            // Receive consumed
+            5 => {
+                let result = self
+                    .apps
+                    .enter(processid, |app, _| app.rx_len = 0)
+                    .map_err(ErrorCode::from)
+                    .and_then(|()| self.get_socket(processid))
+                    .map(|socket| self.tcp.window_update(socket));
+                CommandReturn::from(result)
+            }
+
+            // Close
+            6 => {
+                let result = self.get_socket(processid).and_then(|socket| {
+                    self.tcp.close(socket)?;
+                    // Closing a socket without a connection completes
+                    // immediately
+                    if socket.get_state() == TCPState::Closed {
+                        self.release_socket(processid);
+                    }
+                    Ok(())
+                });
+                CommandReturn::from(result)
+            }
+
+            // Abort
+            7 => {
+                let result = self.get_socket(processid).map(|socket| {
+                    self.tcp.abort(socket);
+                    self.release_socket(processid);
+                });
+                CommandReturn::from(result)
+            }
+
+            // Get state
+            8 => match self.get_socket(processid) {
+                Ok(socket) => CommandReturn::success_u32(socket.get_state() as u32),
+                Err(ErrorCode::OFF) => CommandReturn::success_u32(TCPState::Closed as u32),
+                Err(err) => CommandReturn::failure(err),
+            },
+
+            9 => CommandReturn::success_u32(self.tcp.max_segment_size() as u32),
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
+
+impl<'a> TCPClient for TCPDriver<'a> {
+    fn connected(&self, socket_id: usize, result: Result<(), ErrorCode>) {
+        self.schedule_upcall(
+            socket_id,
+            upcalls::CONNECTION,
+            (
+                connection_event::CONNECTED,
+                kernel::errorcode::into_statuscode(result),
+                0,
+            ),
+        );
+    }
+
+    fn read_send_data(&self, socket_id: usize, offset: usize, buf: &mut [u8]) -> usize {
+        self.owner(socket_id).map_or(0, |processid| {
+            self.apps
+                .enter(processid, |app, kernel_data| {
+                    let start = app.tx_acked + offset;
+                    if start >= app.tx_len {
+                        return 0;
+                    }
+                    let len = cmp::min(buf.len(), app.tx_len - start);
+                    kernel_data
+                        .get_readonly_processbuffer(ro_allow::WRITE)
+                        .and_then(|write| {
+                            write.enter(|data| {
+                                // The process may have swapped the buffer
+                                if data.len() < start + len {
+                                    return 0;
+                                }
+                                data[start..start + len].copy_to_slice(&mut buf[..len]);
+                                len
+                            })
+                        })
+                        .unwrap_or(0)
+                })
+                .unwrap_or(0)
+        })
+    }
+
+    fn send_done(&self, socket_id: usize, len: usize) {
+        self.owner(socket_id).map(|processid| {
+            let _ = self.apps.enter(processid, |app, kernel_data| {
+                if app.tx_len == 0 {
+                    return;
+                }
+                app.tx_acked = cmp::min(app.tx_acked + len, app.tx_len);
+                if app.tx_acked == app.tx_len {
+                    kernel_data
+                        .schedule_upcall(upcalls::SEND_DONE, (0, app.tx_len, 0))
+                        .ok();
+                    app.tx_len = 0;
+                    app.tx_acked = 0;
+                }
+            });
+        });
+    }
+
+    fn receive(&self, socket_id: usize, data: &[u8]) -> usize {
+        self.owner(socket_id).map_or(0, |processid| {
+            self.apps
+                .enter(processid, |app, kernel_data| {
+                    let copied = kernel_data
+                        .get_readwrite_processbuffer(rw_allow::READ)
+                        .and_then(|read| {
+                            read.mut_enter(|rbuf| {
+                                if app.rx_len >= rbuf.len() {
+                                    return 0;
+                                }
+                                let len = cmp::min(data.len(), rbuf.len() - app.rx_len);
+                                rbuf[app.rx_len..app.rx_len + len].copy_from_slice(&data[..len]);
+                                len
+                            })
+                        })
+                        .unwrap_or(0);
+                    if copied > 0 {
+                        app.rx_len += copied;
+                        kernel_data
+                            .schedule_upcall(upcalls::RECEIVE, (copied, app.rx_len, 0))
+                            .ok();
+                    }
+                    copied
+                })
+                .unwrap_or(0)
+        })
+    }
+
+    fn receive_window(&self, socket_id: usize) -> usize {
+        self.owner(socket_id).map_or(0, |processid| {
+            self.apps
+                .enter(processid, |app, kernel_data| {
+                    kernel_data
+                        .get_readwrite_processbuffer(rw_allow::READ)
+                        .map_or(0, |read| read.len().saturating_sub(app.rx_len))
+                })
+                .unwrap_or(0)
+        })
+    }
+
+    fn remote_closed(&self, socket_id: usize) {
+        self.schedule_upcall(
+            socket_id,
+            upcalls::CONNECTION,
+            (connection_event::REMOTE_CLOSED, 0, 0),
+        );
+    }
+
+    fn closed(&self, socket_id: usize, result: Result<(), ErrorCode>) {
+        self.owner(socket_id).map(|processid| {
+            let _ = self.apps.enter(processid, |_, kernel_data| {
+                kernel_data
+                    .schedule_upcall(
+                        upcalls::CONNECTION,
+                        (
+                            connection_event::CLOSED,
+                            kernel::errorcode::into_statuscode(result),
+                            0,
+                        ),
+                    )
+                    .ok();
+            });
+            self.release_socket(processid);
+        });
+    }
+}
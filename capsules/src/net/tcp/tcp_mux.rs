@@ -0,0 +1,374 @@
+//! This file contains the definition and implementation of the TCP layer that
+//! sits between the IPv6 layer and the individual TCP sockets. The
+//! [TCPSocketManager](trait.TCPSocketManager.html) trait is the interface
+//! used by kernel capsules (and the userspace TCP driver) to open, use and
+//! close connections on a [TCPSocket](../tcp_socket/struct.TCPSocket.html).
+//!
+//! [MuxTcp](struct.MuxTcp.html) implements this trait. It owns the list of
+//! sockets and is responsible for:
+//!
+//! - demultiplexing received segments to the socket with the matching
+//!   connection (or to a listening socket on the destination port), and
+//!   answering segments that match no socket with a reset,
+//! - transmitting segments: the IPv6 sender can only hold one packet at a
+//!   time, so whenever it is idle the mux asks each socket in turn whether
+//!   it has a segment to send,
+//! - running a single periodic timer, while any socket needs it, which
+//!   drives the retransmission and TIME-WAIT timers of all sockets.
+//!
+//! Several sockets may listen on the same port; each accepts one connection,
+//! so the number of listening sockets bounds the number of connections that
+//! can be accepted concurrently on that port.
+
+use crate::net::ipv6::ip_utils::{ip6_nh, IPAddr};
+use crate::net::ipv6::ipv6_recv::IP6RecvClient;
+use crate::net::ipv6::ipv6_send::{IP6SendClient, IP6Sender};
+use crate::net::ipv6::{IP6Header, TransportHeader};
+use crate::net::network_capabilities::NetworkCapability;
+use crate::net::tcp::tcp_socket::{TCPSocket, TCPState};
+use crate::net::tcp::{tcp_flags, TCPHeader};
+
+use core::cell::Cell;
+
+use kernel::collections::list::List;
+use kernel::debug;
+use kernel::hil::time::{self, ConvertTicks, Ticks};
+use kernel::utilities::cells::{MapCell, OptionalCell};
+use kernel::utilities::leasable_buffer::LeasableMutableBuffer;
+use kernel::ErrorCode;
+
+/// Period of the TCP timer. All socket timeouts are multiples of this.
+pub const TICK_MS: u32 = 250;
+
+/// First port of the dynamic range (RFC 6335) used for ephemeral ports.
+const EPHEMERAL_PORT_START: u16 = 49152;
+
+/// This trait provides the interface for opening, using and closing TCP
+/// connections. Each operation takes the socket it applies to; sockets must
+/// have been registered with the implementation beforehand.
+pub trait TCPSocketManager<'a> {
+    /// Puts `socket` into the LISTEN state on `local_port`. The socket's
+    /// client receives `connected` once a peer completes the handshake.
+    fn listen(
+        &self,
+        socket: &'a TCPSocket<'a>,
+        local_port: u16,
+        net_cap: &'static NetworkCapability,
+    ) -> Result<(), ErrorCode>;
+
+    /// Opens a connection from `local_port` to the given remote endpoint.
+    /// If `local_port` is 0, an ephemeral port is chosen. The socket's
+    /// client receives `connected` when the handshake completes or fails.
+    fn connect(
+        &self,
+        socket: &'a TCPSocket<'a>,
+        local_port: u16,
+        remote_addr: IPAddr,
+        remote_port: u16,
+        net_cap: &'static NetworkCapability,
+    ) -> Result<(), ErrorCode>;
+
+    /// Makes `len` more bytes of the client's outgoing stream available for
+    /// transmission. The data itself is requested from the client through
+    /// `TCPClient::read_send_data`.
+    fn send(&self, socket: &'a TCPSocket<'a>, len: usize) -> Result<(), ErrorCode>;
+
+    /// Tells the stack that the client freed space in its receive buffer, so
+    /// that a window update can be sent to the peer.
+    fn window_update(&self, socket: &'a TCPSocket<'a>);
+
+    /// Gracefully closes the outgoing direction of the connection once all
+    /// queued data has been sent.
+    fn close(&self, socket: &'a TCPSocket<'a>) -> Result<(), ErrorCode>;
+
+    /// Immediately closes the connection, sending a reset to the peer if a
+    /// connection exists. No callback is delivered.
+    fn abort(&self, socket: &'a TCPSocket<'a>);
+
+    /// Returns the largest payload that is carried in a single segment.
+    fn max_segment_size(&self) -> usize;
+}
+
+pub struct MuxTcp<'a, A: time::Alarm<'a>> {
+    sockets: List<'a, TCPSocket<'a>>,
+    ip_sender: &'a dyn IP6Sender<'a>,
+    alarm: &'a A,
+    // Payload buffer for the segment currently being built
+    tx_buffer: MapCell<LeasableMutableBuffer<'static, u8>>,
+    // The IPv6 sender holds a segment of ours
+    sending: Cell<bool>,
+    // Input or timer processing is underway; output is deferred until done
+    processing: Cell<bool>,
+    pending_rst: OptionalCell<(IPAddr, TCPHeader)>,
+    iss_counter: Cell<u32>,
+    next_ephemeral_port: Cell<u16>,
+    timer_running: Cell<bool>,
+    // Capability used for resets that do not belong to any socket
+    net_cap: &'static NetworkCapability,
+}
+
+impl<'a, A: time::Alarm<'a>> MuxTcp<'a, A> {
+    pub fn new(
+        ip_sender: &'a dyn IP6Sender<'a>,
+        alarm: &'a A,
+        tx_buffer: LeasableMutableBuffer<'static, u8>,
+        net_cap: &'static NetworkCapability,
+    ) -> MuxTcp<'a, A> {
+        MuxTcp {
+            sockets: List::new(),
+            ip_sender: ip_sender,
+            alarm: alarm,
+            tx_buffer: MapCell::new(tx_buffer),
+            sending: Cell::new(false),
+            processing: Cell::new(false),
+            pending_rst: OptionalCell::empty(),
+            iss_counter: Cell::new(0),
+            next_ephemeral_port: Cell::new(EPHEMERAL_PORT_START),
+            timer_running: Cell::new(false),
+            net_cap: net_cap,
+        }
+    }
+
+    pub fn add_socket(&self, socket: &'a TCPSocket<'a>) {
+        self.sockets.push_tail(socket);
+    }
+
+    /// Returns a new initial sequence number. As in RFC 6528, this combines
+    /// a clock with a counter so that consecutive connections do not reuse
+    /// sequence space.
+    fn next_iss(&self) -> u32 {
+        let counter = self.iss_counter.get().wrapping_add(1);
+        self.iss_counter.set(counter);
+        self.alarm
+            .now()
+            .into_u32()
+            .wrapping_add(counter.wrapping_mul(64000))
+    }
+
+    fn port_in_use(&self, local_port: u16) -> bool {
+        self.sockets.iter().any(|socket| {
+            socket.get_state() != TCPState::Closed && socket.get_local_port() == local_port
+        })
+    }
+
+    fn ephemeral_port(&self) -> Option<u16> {
+        for _ in EPHEMERAL_PORT_START..=u16::MAX {
+            let port = self.next_ephemeral_port.get();
+            self.next_ephemeral_port
+                .set(port.checked_add(1).unwrap_or(EPHEMERAL_PORT_START));
+            if !self.port_in_use(port) {
+                return Some(port);
+            }
+        }
+        None
+    }
+
+    /// Queues a reset in reply to `tcp_header`, following the rules of
+    /// RFC 793 section 3.4. Only one reset is held at a time; if one is
+    /// already pending, this one is dropped.
+    fn queue_reset(&self, dst: IPAddr, tcp_header: &TCPHeader, data_len: usize) {
+        if self.pending_rst.is_some() {
+            return;
+        }
+        let mut rst = TCPHeader::new();
+        rst.set_src_port(tcp_header.get_dst_port());
+        rst.set_dst_port(tcp_header.get_src_port());
+        if tcp_header.has_flags(tcp_flags::ACK) {
+            rst.set_seq_num(tcp_header.get_ack_num());
+            rst.set_flags(tcp_flags::RST);
+        } else {
+            let mut seg_len = data_len as u32;
+            if tcp_header.has_flags(tcp_flags::SYN) {
+                seg_len += 1;
+            }
+            if tcp_header.has_flags(tcp_flags::FIN) {
+                seg_len += 1;
+            }
+            rst.set_ack_num(tcp_header.get_seq_num().wrapping_add(seg_len));
+            rst.set_flags(tcp_flags::RST | tcp_flags::ACK);
+        }
+        self.pending_rst.set((dst, rst));
+    }
+
+    /// Hands the next segment to the IPv6 layer if it is idle. Resets are
+    /// sent first, then sockets are polled in list order.
+    fn do_output(&self) {
+        if self.sending.get() || self.processing.get() {
+            return;
+        }
+        self.tx_buffer.take().map(|mut buf| {
+            let segment = match self.pending_rst.take() {
+                Some((dst, rst)) => Some((dst, rst, 0, self.net_cap)),
+                None => self.sockets.iter().find_map(|socket| {
+                    socket
+                        .prepare_segment(&mut buf[..])
+                        .and_then(|(tcp_header, len)| {
+                            socket
+                                .get_net_cap()
+                                .map(|net_cap| (socket.get_remote_addr(), tcp_header, len, net_cap))
+                        })
+                }),
+            };
+            if let Some((dst, tcp_header, len, net_cap)) = segment {
+                buf.slice(0..len);
+                match self
+                    .ip_sender
+                    .send_to(dst, TransportHeader::TCP(tcp_header), &buf, net_cap)
+                {
+                    Ok(()) => self.sending.set(true),
+                    // The segment is lost; retransmission recovers it
+                    Err(e) => debug!("[TCP] IP send_to failed: {:?}", e),
+                }
+                buf.reset();
+            }
+            self.tx_buffer.replace(buf);
+        });
+        self.start_timer();
+    }
+
+    fn start_timer(&self) {
+        if !self.timer_running.get() && self.sockets.iter().any(|s| s.timer_active()) {
+            self.timer_running.set(true);
+            self.alarm
+                .set_alarm(self.alarm.now(), self.alarm.ticks_from_ms(TICK_MS));
+        }
+    }
+}
+
+impl<'a, A: time::Alarm<'a>> TCPSocketManager<'a> for MuxTcp<'a, A> {
+    fn listen(
+        &self,
+        socket: &'a TCPSocket<'a>,
+        local_port: u16,
+        net_cap: &'static NetworkCapability,
+    ) -> Result<(), ErrorCode> {
+        if local_port == 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        socket.open_passive(local_port, net_cap)
+    }
+
+    fn connect(
+        &self,
+        socket: &'a TCPSocket<'a>,
+        local_port: u16,
+        remote_addr: IPAddr,
+        remote_port: u16,
+        net_cap: &'static NetworkCapability,
+    ) -> Result<(), ErrorCode> {
+        if remote_port == 0 || remote_addr.is_unspecified() || remote_addr.is_multicast() {
+            return Err(ErrorCode::INVAL);
+        }
+        let local_port = if local_port == 0 {
+            self.ephemeral_port().ok_or(ErrorCode::NOMEM)?
+        } else if self
+            .sockets
+            .iter()
+            .any(|s| s.matches(local_port, remote_addr, remote_port))
+        {
+            return Err(ErrorCode::BUSY);
+        } else {
+            local_port
+        };
+        socket.open_active(
+            local_port,
+            remote_addr,
+            remote_port,
+            self.next_iss(),
+            net_cap,
+        )?;
+        self.do_output();
+        Ok(())
+    }
+
+    fn send(&self, socket: &'a TCPSocket<'a>, len: usize) -> Result<(), ErrorCode> {
+        socket.queue_send(len)?;
+        self.do_output();
+        Ok(())
+    }
+
+    fn window_update(&self, socket: &'a TCPSocket<'a>) {
+        socket.window_update();
+        self.do_output();
+    }
+
+    fn close(&self, socket: &'a TCPSocket<'a>) -> Result<(), ErrorCode> {
+        socket.close()?;
+        self.do_output();
+        Ok(())
+    }
+
+    fn abort(&self, socket: &'a TCPSocket<'a>) {
+        let remote_addr = socket.get_remote_addr();
+        if let Some(rst) = socket.abort() {
+            if self.pending_rst.is_none() {
+                self.pending_rst.set((remote_addr, rst));
+            }
+        }
+        self.do_output();
+    }
+
+    fn max_segment_size(&self) -> usize {
+        self.tx_buffer.map_or(0, |buf| buf.len())
+    }
+}
+
+impl<'a, A: time::Alarm<'a>> IP6RecvClient for MuxTcp<'a, A> {
+    fn receive(&self, ip_header: IP6Header, payload: &[u8]) {
+        if ip_header.get_next_header() != ip6_nh::TCP {
+            return;
+        }
+        let (offset, tcp_header) = match TCPHeader::decode(payload).done() {
+            Some(decoded) => decoded,
+            None => return,
+        };
+        let data = &payload[offset..];
+        let src_addr = ip_header.get_src_addr();
+        let local_port = tcp_header.get_dst_port();
+        let remote_port = tcp_header.get_src_port();
+
+        // Prefer an existing connection, then a listening socket
+        let socket = self
+            .sockets
+            .iter()
+            .find(|s| s.matches(local_port, src_addr, remote_port))
+            .or_else(|| {
+                self.sockets
+                    .iter()
+                    .find(|s| s.get_state() == TCPState::Listen && s.get_local_port() == local_port)
+            });
+
+        self.processing.set(true);
+        let reset = match socket {
+            Some(socket) => socket.segment_arrived(src_addr, &tcp_header, data, self.next_iss()),
+            None => !tcp_header.has_flags(tcp_flags::RST),
+        };
+        if reset {
+            self.queue_reset(src_addr, &tcp_header, data.len());
+        }
+        self.processing.set(false);
+        self.do_output();
+    }
+}
+
+impl<'a, A: time::Alarm<'a>> IP6SendClient for MuxTcp<'a, A> {
+    fn send_done(&self, result: Result<(), ErrorCode>) {
+        if result != Ok(()) {
+            debug!("[TCP] segment send failed: {:?}", result);
+        }
+        self.sending.set(false);
+        self.do_output();
+    }
+}
+
+impl<'a, A: time::Alarm<'a>> time::AlarmClient for MuxTcp<'a, A> {
+    fn alarm(&self) {
+        self.timer_running.set(false);
+        self.processing.set(true);
+        for socket in self.sockets.iter() {
+            socket.tick();
+        }
+        self.processing.set(false);
+        self.do_output();
+    }
+}
@@ -0,0 +1,1370 @@
+//! This file contains the TCP connection state machine. A
+//! [TCPSocket](struct.TCPSocket.html) holds the transmission control block
+//! (TCB) of a single connection as described in RFC 793, and implements the
+//! processing of incoming segments, the selection of outgoing segments, and
+//! the retransmission timer.
+//!
+//! Sockets do not send or receive anything on their own: they are owned by a
+//! [MuxTcp](../tcp_mux/struct.MuxTcp.html), which demultiplexes incoming
+//! segments to the matching socket, asks sockets for segments to transmit
+//! whenever the IP layer is idle, and drives the retransmission timers.
+//!
+//! Sockets also do not buffer any data. Outgoing data is pulled from the
+//! [TCPClient](trait.TCPClient.html) when a segment is built (and pulled
+//! again if that segment has to be retransmitted), and incoming data is
+//! pushed to the client as soon as it arrives in order. The receive window
+//! advertised to the peer is whatever space the client reports as
+//! available, so the client's own buffer provides flow control.
+//!
+//! Known limitations: out-of-order segments are dropped rather than queued,
+//! retransmission uses go-back-N from the first unacknowledged byte, and no
+//! TCP options (MSS, window scaling, SACK) are generated.
+
+use crate::net::ipv6::ip_utils::IPAddr;
+use crate::net::network_capabilities::NetworkCapability;
+use crate::net::tcp::tcp_flags;
+use crate::net::tcp::TCPHeader;
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::collections::list::{ListLink, ListNode};
+use kernel::utilities::cells::OptionalCell;
+use kernel::ErrorCode;
+
+/// Initial retransmission timeout, in timer ticks of the owning `MuxTcp`.
+pub const INITIAL_RTO_TICKS: u8 = 8;
+/// Upper bound for the retransmission timeout after exponential backoff.
+pub const MAX_RTO_TICKS: u8 = 240;
+/// Number of retransmissions of the same segment before the connection is
+/// aborted.
+pub const MAX_RETRIES: u8 = 6;
+/// Time spent in TIME-WAIT, in timer ticks. This is far shorter than the
+/// 2 * MSL recommended by RFC 793, as constrained nodes cannot afford to hold
+/// sockets for minutes.
+pub const TIME_WAIT_TICKS: u8 = 16;
+
+/// The connection states defined in RFC 793.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TCPState {
+    Closed = 0,
+    Listen = 1,
+    SynSent = 2,
+    SynReceived = 3,
+    Established = 4,
+    FinWait1 = 5,
+    FinWait2 = 6,
+    CloseWait = 7,
+    Closing = 8,
+    LastAck = 9,
+    TimeWait = 10,
+}
+
+/// The client of a `TCPSocket`. All callbacks carry the `socket_id` that
+/// was passed to `TCPSocket::new`, so that a single client can serve
+/// several sockets.
+pub trait TCPClient {
+    /// Called when a connection opened with `connect` or accepted on a
+    /// listening socket is established, or when an active open fails.
+    fn connected(&self, socket_id: usize, result: Result<(), ErrorCode>);
+
+    /// Copies outgoing stream data into `buf`, starting `offset` bytes after
+    /// the first unacknowledged byte of the stream, and returns the number
+    /// of bytes copied. The same bytes may be requested more than once if
+    /// they need to be retransmitted.
+    fn read_send_data(&self, socket_id: usize, offset: usize, buf: &mut [u8]) -> usize;
+
+    /// Called when the peer acknowledged `len` more bytes of the outgoing
+    /// stream. These bytes will not be requested again.
+    fn send_done(&self, socket_id: usize, len: usize);
+
+    /// Delivers in-order stream data. Returns the number of bytes the client
+    /// accepted; any remainder is left for the peer to retransmit.
+    fn receive(&self, socket_id: usize, data: &[u8]) -> usize;
+
+    /// Returns the number of bytes the client can currently accept. This is
+    /// advertised to the peer as the receive window.
+    fn receive_window(&self, socket_id: usize) -> usize;
+
+    /// Called when the peer closed its direction of the connection. No
+    /// further data will be received.
+    fn remote_closed(&self, socket_id: usize);
+
+    /// Called when the connection is fully closed, either gracefully
+    /// (`Ok(())`), because it was reset by the peer (`CANCEL`) or because the
+    /// peer stopped acknowledging segments (`NOACK`).
+    fn closed(&self, socket_id: usize, result: Result<(), ErrorCode>);
+}
+
+// Sequence number comparisons modulo 2^32
+fn seq_lt(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) < 0
+}
+
+fn seq_le(a: u32, b: u32) -> bool {
+    (a.wrapping_sub(b) as i32) <= 0
+}
+
+/// A single TCP connection endpoint.
+pub struct TCPSocket<'a> {
+    id: usize,
+    state: Cell<TCPState>,
+    // Whether the socket returns to LISTEN when the connection attempt fails
+    passive: Cell<bool>,
+
+    local_port: Cell<u16>,
+    remote_addr: Cell<IPAddr>,
+    remote_port: Cell<u16>,
+
+    // Send sequence variables
+    iss: Cell<u32>,
+    snd_una: Cell<u32>,
+    snd_nxt: Cell<u32>,
+    snd_max: Cell<u32>,
+    snd_wnd: Cell<u16>,
+    // Bytes queued by the client that have not been acknowledged yet
+    snd_buffered: Cell<usize>,
+    fin_queued: Cell<bool>,
+
+    // Receive sequence variables
+    rcv_nxt: Cell<u32>,
+    rcv_adv: Cell<u16>,
+    ack_pending: Cell<bool>,
+
+    // Retransmission (or TIME-WAIT) timer, in ticks. Zero means stopped.
+    timer: Cell<u8>,
+    rto: Cell<u8>,
+    retries: Cell<u8>,
+    probe: Cell<bool>,
+
+    net_cap: OptionalCell<&'static NetworkCapability>,
+    client: OptionalCell<&'a dyn TCPClient>,
+    next: ListLink<'a, TCPSocket<'a>>,
+}
+
+impl<'a> ListNode<'a, TCPSocket<'a>> for TCPSocket<'a> {
+    fn next(&'a self) -> &'a ListLink<'a, TCPSocket<'a>> {
+        &self.next
+    }
+}
+
+impl<'a> TCPSocket<'a> {
+    pub fn new(id: usize) -> TCPSocket<'a> {
+        TCPSocket {
+            id: id,
+            state: Cell::new(TCPState::Closed),
+            passive: Cell::new(false),
+            local_port: Cell::new(0),
+            remote_addr: Cell::new(IPAddr::new()),
+            remote_port: Cell::new(0),
+            iss: Cell::new(0),
+            snd_una: Cell::new(0),
+            snd_nxt: Cell::new(0),
+            snd_max: Cell::new(0),
+            snd_wnd: Cell::new(0),
+            snd_buffered: Cell::new(0),
+            fin_queued: Cell::new(false),
+            rcv_nxt: Cell::new(0),
+            rcv_adv: Cell::new(0),
+            ack_pending: Cell::new(false),
+            timer: Cell::new(0),
+            rto: Cell::new(INITIAL_RTO_TICKS),
+            retries: Cell::new(0),
+            probe: Cell::new(false),
+            net_cap: OptionalCell::empty(),
+            client: OptionalCell::empty(),
+            next: ListLink::empty(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn TCPClient) {
+        self.client.set(client);
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn get_state(&self) -> TCPState {
+        self.state.get()
+    }
+
+    pub fn get_local_port(&self) -> u16 {
+        self.local_port.get()
+    }
+
+    pub fn get_remote_addr(&self) -> IPAddr {
+        self.remote_addr.get()
+    }
+
+    pub fn get_remote_port(&self) -> u16 {
+        self.remote_port.get()
+    }
+
+    pub(crate) fn get_net_cap(&self) -> Option<&'static NetworkCapability> {
+        self.net_cap.extract()
+    }
+
+    /// Returns true if this socket is the endpoint of a connection (in any
+    /// state other than CLOSED or LISTEN) with the given remote endpoint.
+    pub(crate) fn matches(&self, local_port: u16, remote_addr: IPAddr, remote_port: u16) -> bool {
+        match self.state.get() {
+            TCPState::Closed | TCPState::Listen => false,
+            _ => {
+                self.local_port.get() == local_port
+                    && self.remote_port.get() == remote_port
+                    && self.remote_addr.get() == remote_addr
+            }
+        }
+    }
+
+    /// Returns true if the retransmission or TIME-WAIT timer is running.
+    pub(crate) fn timer_active(&self) -> bool {
+        self.timer.get() != 0
+    }
+
+    fn reset_tcb(&self) {
+        self.snd_buffered.set(0);
+        self.fin_queued.set(false);
+        self.ack_pending.set(false);
+        self.timer.set(0);
+        self.rto.set(INITIAL_RTO_TICKS);
+        self.retries.set(0);
+        self.probe.set(false);
+    }
+
+    fn enter_closed(&self) {
+        self.reset_tcb();
+        self.state.set(TCPState::Closed);
+    }
+
+    fn enter_listen(&self) {
+        self.reset_tcb();
+        self.remote_addr.set(IPAddr::new());
+        self.remote_port.set(0);
+        self.state.set(TCPState::Listen);
+    }
+
+    /// Tears the connection down after a failure, notifying the client if
+    /// it had been told about the connection.
+    fn fail(&self, err: ErrorCode) {
+        match self.state.get() {
+            TCPState::SynReceived if self.passive.get() => {
+                // The client never saw this connection
+                self.enter_listen();
+            }
+            TCPState::SynSent | TCPState::SynReceived => {
+                self.enter_closed();
+                self.client
+                    .map(|client| client.connected(self.id, Err(err)));
+            }
+            TCPState::Closed | TCPState::Listen => {}
+            _ => {
+                self.enter_closed();
+                self.client.map(|client| client.closed(self.id, Err(err)));
+            }
+        }
+    }
+
+    fn rcv_window(&self) -> u16 {
+        self.client.map_or(0, |client| {
+            cmp::min(client.receive_window(self.id), u16::MAX as usize) as u16
+        })
+    }
+
+    fn start_timer(&self) {
+        if self.timer.get() == 0 {
+            self.timer.set(self.rto.get());
+        }
+    }
+
+    pub(crate) fn open_passive(
+        &self,
+        local_port: u16,
+        net_cap: &'static NetworkCapability,
+    ) -> Result<(), ErrorCode> {
+        if self.state.get() != TCPState::Closed {
+            return Err(ErrorCode::BUSY);
+        }
+        self.local_port.set(local_port);
+        self.net_cap.set(net_cap);
+        self.passive.set(true);
+        self.enter_listen();
+        Ok(())
+    }
+
+    pub(crate) fn open_active(
+        &self,
+        local_port: u16,
+        remote_addr: IPAddr,
+        remote_port: u16,
+        iss: u32,
+        net_cap: &'static NetworkCapability,
+    ) -> Result<(), ErrorCode> {
+        if self.state.get() != TCPState::Closed {
+            return Err(ErrorCode::BUSY);
+        }
+        self.reset_tcb();
+        self.local_port.set(local_port);
+        self.remote_addr.set(remote_addr);
+        self.remote_port.set(remote_port);
+        self.net_cap.set(net_cap);
+        self.passive.set(false);
+        self.iss.set(iss);
+        self.snd_una.set(iss);
+        self.snd_nxt.set(iss);
+        self.snd_max.set(iss);
+        self.snd_wnd.set(0);
+        self.state.set(TCPState::SynSent);
+        Ok(())
+    }
+
+    /// Queues `len` more bytes of the client's outgoing stream.
+    pub(crate) fn queue_send(&self, len: usize) -> Result<(), ErrorCode> {
+        match self.state.get() {
+            TCPState::SynSent
+            | TCPState::SynReceived
+            | TCPState::Established
+            | TCPState::CloseWait => {
+                if self.fin_queued.get() {
+                    return Err(ErrorCode::ALREADY);
+                }
+                self.snd_buffered.set(self.snd_buffered.get() + len);
+                Ok(())
+            }
+            _ => Err(ErrorCode::OFF),
+        }
+    }
+
+    /// Starts a graceful close. A FIN is sent once all queued data has been
+    /// transmitted. Closing a socket that has no connection yet takes effect
+    /// immediately and generates no callback.
+    pub(crate) fn close(&self) -> Result<(), ErrorCode> {
+        match self.state.get() {
+            TCPState::Listen | TCPState::SynSent => {
+                self.enter_closed();
+                Ok(())
+            }
+            TCPState::SynReceived | TCPState::Established => {
+                self.fin_queued.set(true);
+                self.state.set(TCPState::FinWait1);
+                Ok(())
+            }
+            TCPState::CloseWait => {
+                self.fin_queued.set(true);
+                self.state.set(TCPState::LastAck);
+                Ok(())
+            }
+            _ => Err(ErrorCode::ALREADY),
+        }
+    }
+
+    /// Aborts the connection without notifying the client. Returns the reset
+    /// segment that must be sent to the peer, if any.
+    pub(crate) fn abort(&self) -> Option<TCPHeader> {
+        let rst = match self.state.get() {
+            TCPState::Closed | TCPState::Listen | TCPState::SynSent | TCPState::TimeWait => None,
+            _ => Some(self.make_header(self.snd_nxt.get(), tcp_flags::RST)),
+        };
+        self.enter_closed();
+        rst
+    }
+
+    /// Called by the client after it freed space in its receive buffer.
+    /// Schedules a window update if the window grew substantially since it
+    /// was last advertised.
+    pub(crate) fn window_update(&self) {
+        match self.state.get() {
+            TCPState::Established | TCPState::FinWait1 | TCPState::FinWait2 => {
+                if self.rcv_adv.get() < self.rcv_window() / 2 {
+                    self.ack_pending.set(true);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn make_header(&self, seq: u32, flags: u16) -> TCPHeader {
+        let mut tcp_header = TCPHeader::new();
+        tcp_header.set_src_port(self.local_port.get());
+        tcp_header.set_dst_port(self.remote_port.get());
+        tcp_header.set_seq_num(seq);
+        if flags & tcp_flags::ACK != 0 {
+            tcp_header.set_ack_num(self.rcv_nxt.get());
+            let window = self.rcv_window();
+            self.rcv_adv.set(window);
+            tcp_header.set_window(window);
+        }
+        tcp_header.set_flags(flags);
+        tcp_header
+    }
+
+    fn advance_snd_nxt(&self, len: usize) {
+        let snd_nxt = self.snd_nxt.get().wrapping_add(len as u32);
+        self.snd_nxt.set(snd_nxt);
+        if seq_lt(self.snd_max.get(), snd_nxt) {
+            self.snd_max.set(snd_nxt);
+        }
+    }
+
+    /// Builds the next segment this socket wants to transmit, if any. Payload
+    /// is written to the start of `buf`. Returns the TCP header (with `len`
+    /// not yet set) and the number of payload bytes.
+    pub(crate) fn prepare_segment(&self, buf: &mut [u8]) -> Option<(TCPHeader, usize)> {
+        let iss = self.iss.get();
+        match self.state.get() {
+            TCPState::Closed | TCPState::Listen => return None,
+            TCPState::SynSent | TCPState::SynReceived => {
+                let flags = if self.state.get() == TCPState::SynSent {
+                    tcp_flags::SYN
+                } else {
+                    tcp_flags::SYN | tcp_flags::ACK
+                };
+                if self.snd_nxt.get() == iss {
+                    self.advance_snd_nxt(1);
+                    self.start_timer();
+                    self.ack_pending.set(false);
+                    return Some((self.make_header(iss, flags), 0));
+                }
+                return None;
+            }
+            _ => {}
+        }
+
+        // Synchronized states: send data, a FIN, or a bare acknowledgment
+        let snd_una = self.snd_una.get();
+        let snd_nxt = self.snd_nxt.get();
+        let buffered = self.snd_buffered.get();
+        let in_flight = snd_nxt.wrapping_sub(snd_una) as usize;
+        let fin_in_flight = in_flight > buffered;
+        let sent = cmp::min(in_flight, buffered);
+        let unsent = buffered - sent;
+
+        let window = if self.snd_wnd.get() == 0 && self.probe.get() {
+            1
+        } else {
+            self.snd_wnd.get() as usize
+        };
+        let usable = window.saturating_sub(in_flight);
+        let mut len = cmp::min(cmp::min(unsent, usable), buf.len());
+        if len > 0 {
+            len = self.client.map_or(0, |client| {
+                cmp::min(client.read_send_data(self.id, sent, &mut buf[..len]), len)
+            });
+        }
+
+        let may_send_fin = match self.state.get() {
+            TCPState::FinWait1 | TCPState::Closing | TCPState::LastAck => true,
+            _ => false,
+        };
+        let fin = may_send_fin && self.fin_queued.get() && !fin_in_flight && sent + len == buffered;
+
+        if len > 0 || fin {
+            let mut flags = tcp_flags::ACK;
+            if len > 0 {
+                flags |= tcp_flags::PSH;
+                self.probe.set(false);
+            }
+            if fin {
+                flags |= tcp_flags::FIN;
+            }
+            let tcp_header = self.make_header(snd_nxt, flags);
+            self.advance_snd_nxt(len + fin as usize);
+            self.start_timer();
+            self.ack_pending.set(false);
+            Some((tcp_header, len))
+        } else {
+            if unsent > 0 && in_flight == 0 && usable == 0 {
+                // Zero window: the timer triggers a window probe
+                self.start_timer();
+            }
+            if self.ack_pending.get() {
+                self.ack_pending.set(false);
+                Some((self.make_header(snd_nxt, tcp_flags::ACK), 0))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Advances the timers of this socket by one tick. Returns true if the
+    /// socket has a segment to retransmit.
+    pub(crate) fn tick(&self) -> bool {
+        let ticks = self.timer.get();
+        if ticks == 0 {
+            return false;
+        }
+        self.timer.set(ticks - 1);
+        if ticks > 1 {
+            return false;
+        }
+
+        match self.state.get() {
+            TCPState::Closed | TCPState::Listen => false,
+            TCPState::TimeWait => {
+                self.enter_closed();
+                self.client.map(|client| client.closed(self.id, Ok(())));
+                false
+            }
+            state => {
+                let retries = self.retries.get() + 1;
+                if retries > MAX_RETRIES {
+                    self.fail(ErrorCode::NOACK);
+                    return false;
+                }
+                self.retries.set(retries);
+                self.rto
+                    .set(cmp::min(self.rto.get().saturating_mul(2), MAX_RTO_TICKS));
+                match state {
+                    TCPState::SynSent | TCPState::SynReceived => self.snd_nxt.set(self.iss.get()),
+                    _ => self.snd_nxt.set(self.snd_una.get()),
+                }
+                if self.snd_wnd.get() == 0 {
+                    self.probe.set(true);
+                }
+                true
+            }
+        }
+    }
+
+    /// Processes an incoming segment addressed to this socket. `iss` is a
+    /// fresh initial sequence number, used if the segment opens a connection
+    /// on a listening socket. Returns true if the segment must be answered
+    /// with a reset.
+    pub(crate) fn segment_arrived(
+        &self,
+        src_addr: IPAddr,
+        tcp_header: &TCPHeader,
+        data: &[u8],
+        iss: u32,
+    ) -> bool {
+        let is_rst = tcp_header.has_flags(tcp_flags::RST);
+        let is_syn = tcp_header.has_flags(tcp_flags::SYN);
+        let is_ack = tcp_header.has_flags(tcp_flags::ACK);
+        let is_fin = tcp_header.has_flags(tcp_flags::FIN);
+        let seq = tcp_header.get_seq_num();
+        let ack = tcp_header.get_ack_num();
+
+        match self.state.get() {
+            TCPState::Closed => return !is_rst,
+            TCPState::Listen => {
+                if is_rst {
+                    return false;
+                }
+                if is_ack {
+                    return true;
+                }
+                if is_syn {
+                    self.remote_addr.set(src_addr);
+                    self.remote_port.set(tcp_header.get_src_port());
+                    self.rcv_nxt.set(seq.wrapping_add(1));
+                    self.iss.set(iss);
+                    self.snd_una.set(iss);
+                    self.snd_nxt.set(iss);
+                    self.snd_max.set(iss);
+                    self.snd_wnd.set(tcp_header.get_window());
+                    self.state.set(TCPState::SynReceived);
+                }
+                return false;
+            }
+            TCPState::SynSent => {
+                let iss = self.iss.get();
+                if is_ack && (seq_le(ack, iss) || seq_lt(self.snd_max.get(), ack)) {
+                    return !is_rst;
+                }
+                if is_rst {
+                    if is_ack {
+                        // Connection refused
+                        self.fail(ErrorCode::FAIL);
+                    }
+                    return false;
+                }
+                if is_syn {
+                    self.rcv_nxt.set(seq.wrapping_add(1));
+                    self.ack_pending.set(true);
+                    if is_ack {
+                        self.snd_una.set(ack);
+                        self.snd_wnd.set(tcp_header.get_window());
+                        self.timer.set(0);
+                        self.retries.set(0);
+                        self.rto.set(INITIAL_RTO_TICKS);
+                        self.state.set(TCPState::Established);
+                        self.client.map(|client| client.connected(self.id, Ok(())));
+                    } else {
+                        // Simultaneous open: answer with a SYN-ACK
+                        self.snd_nxt.set(iss);
+                        self.state.set(TCPState::SynReceived);
+                    }
+                }
+                return false;
+            }
+            _ => {}
+        }
+
+        // Check that the segment overlaps the receive window
+        let rcv_nxt = self.rcv_nxt.get();
+        let window = self.rcv_window() as u32;
+        let seg_len = data.len() as u32 + is_syn as u32 + is_fin as u32;
+        let in_window = |s: u32| seq_le(rcv_nxt, s) && seq_lt(s, rcv_nxt.wrapping_add(window));
+        let acceptable = if seg_len == 0 {
+            if window == 0 {
+                seq == rcv_nxt
+            } else {
+                in_window(seq)
+            }
+        } else {
+            window != 0 && (in_window(seq) || in_window(seq.wrapping_add(seg_len - 1)))
+        };
+        if !acceptable {
+            if !is_rst {
+                if self.state.get() == TCPState::SynReceived {
+                    // Most likely a retransmitted SYN: repeat the SYN-ACK
+                    self.snd_nxt.set(self.iss.get());
+                }
+                self.ack_pending.set(true);
+            }
+            return false;
+        }
+
+        if is_rst {
+            self.fail(ErrorCode::CANCEL);
+            return false;
+        }
+
+        if is_syn {
+            // A SYN inside the window is an error; the connection is reset
+            self.fail(ErrorCode::CANCEL);
+            return true;
+        }
+
+        if !is_ack {
+            return false;
+        }
+
+        if self.state.get() == TCPState::SynReceived {
+            if seq_lt(self.snd_una.get(), ack) && seq_le(ack, self.snd_max.get()) {
+                self.snd_una.set(self.iss.get().wrapping_add(1));
+                if seq_lt(self.snd_nxt.get(), self.snd_una.get()) {
+                    self.snd_nxt.set(self.snd_una.get());
+                }
+                self.timer.set(0);
+                self.retries.set(0);
+                self.rto.set(INITIAL_RTO_TICKS);
+                self.state.set(TCPState::Established);
+                self.client.map(|client| client.connected(self.id, Ok(())));
+            } else {
+                return true;
+            }
+        }
+
+        if !self.process_ack(ack, tcp_header.get_window()) {
+            return false;
+        }
+        if self.state.get() == TCPState::Closed {
+            return false;
+        }
+
+        // Deliver in-order data, trimming bytes that were already received
+        let mut consumed_to = seq.wrapping_add(data.len() as u32);
+        let accepts_data = match self.state.get() {
+            TCPState::Established | TCPState::FinWait1 | TCPState::FinWait2 => true,
+            _ => false,
+        };
+        if !data.is_empty() {
+            if accepts_data && seq_le(seq, rcv_nxt) {
+                let skip = rcv_nxt.wrapping_sub(seq) as usize;
+                if skip < data.len() {
+                    let new_data = &data[skip..];
+                    let len = cmp::min(new_data.len(), window as usize);
+                    let accepted = self.client.map_or(0, |client| {
+                        cmp::min(client.receive(self.id, &new_data[..len]), len)
+                    });
+                    self.rcv_nxt.set(rcv_nxt.wrapping_add(accepted as u32));
+                }
+            }
+            self.ack_pending.set(true);
+        } else {
+            consumed_to = seq;
+        }
+
+        // A FIN is only processed once all data before it was accepted
+        if is_fin && consumed_to == self.rcv_nxt.get() {
+            self.rcv_nxt.set(self.rcv_nxt.get().wrapping_add(1));
+            self.ack_pending.set(true);
+            match self.state.get() {
+                TCPState::Established => {
+                    self.state.set(TCPState::CloseWait);
+                    self.client.map(|client| client.remote_closed(self.id));
+                }
+                TCPState::FinWait1 => {
+                    // Our FIN has not been acknowledged yet
+                    self.state.set(TCPState::Closing);
+                    self.client.map(|client| client.remote_closed(self.id));
+                }
+                TCPState::FinWait2 => {
+                    self.state.set(TCPState::TimeWait);
+                    self.timer.set(TIME_WAIT_TICKS);
+                    self.client.map(|client| client.remote_closed(self.id));
+                }
+                TCPState::TimeWait => {
+                    self.timer.set(TIME_WAIT_TICKS);
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Processes the acknowledgment field of an incoming segment in a
+    /// synchronized state. Returns false if the segment must be dropped.
+    fn process_ack(&self, ack: u32, window: u16) -> bool {
+        let snd_una = self.snd_una.get();
+        if seq_lt(self.snd_max.get(), ack) {
+            // Acknowledges something not yet sent
+            self.ack_pending.set(true);
+            return false;
+        }
+
+        let mut fin_acked = false;
+        if seq_lt(snd_una, ack) {
+            let acked = ack.wrapping_sub(snd_una) as usize;
+            let data_acked = cmp::min(acked, self.snd_buffered.get());
+            fin_acked = acked > data_acked;
+
+            self.snd_una.set(ack);
+            if seq_lt(self.snd_nxt.get(), ack) {
+                self.snd_nxt.set(ack);
+            }
+            self.snd_buffered.set(self.snd_buffered.get() - data_acked);
+            self.retries.set(0);
+            self.rto.set(INITIAL_RTO_TICKS);
+            if self.state.get() != TCPState::TimeWait {
+                self.timer.set(if ack == self.snd_max.get() {
+                    0
+                } else {
+                    self.rto.get()
+                });
+            }
+            if data_acked > 0 {
+                self.client
+                    .map(|client| client.send_done(self.id, data_acked));
+            }
+        }
+
+        self.snd_wnd.set(window);
+        if window == 0 {
+            // Do not give up on a peer that keeps answering window probes
+            self.retries.set(0);
+        } else {
+            self.probe.set(false);
+        }
+
+        if fin_acked {
+            match self.state.get() {
+                TCPState::FinWait1 => self.state.set(TCPState::FinWait2),
+                TCPState::Closing => {
+                    self.state.set(TCPState::TimeWait);
+                    self.timer.set(TIME_WAIT_TICKS);
+                }
+                TCPState::LastAck => {
+                    self.enter_closed();
+                    self.client.map(|client| client.closed(self.id, Ok(())));
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::network_capabilities::{AddrRange, PortRange};
+
+    const LOCAL_PORT: u16 = 1000;
+    const REMOTE_PORT: u16 = 2000;
+    const PEER: IPAddr = IPAddr([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+    const STREAM: &[u8; 16] = b"abcdefghijklmnop";
+
+    struct TestClient {
+        connected: Cell<Option<Result<(), ErrorCode>>>,
+        closed: Cell<Option<Result<(), ErrorCode>>>,
+        remote_closed: Cell<usize>,
+        send_acked: Cell<usize>,
+        received: Cell<[u8; 32]>,
+        received_len: Cell<usize>,
+        window: Cell<usize>,
+    }
+
+    impl TestClient {
+        fn new() -> TestClient {
+            TestClient {
+                connected: Cell::new(None),
+                closed: Cell::new(None),
+                remote_closed: Cell::new(0),
+                send_acked: Cell::new(0),
+                received: Cell::new([0; 32]),
+                received_len: Cell::new(0),
+                window: Cell::new(32),
+            }
+        }
+
+        fn received(&self) -> ([u8; 32], usize) {
+            (self.received.get(), self.received_len.get())
+        }
+    }
+
+    impl TCPClient for TestClient {
+        fn connected(&self, _socket_id: usize, result: Result<(), ErrorCode>) {
+            self.connected.set(Some(result));
+        }
+
+        fn read_send_data(&self, _socket_id: usize, offset: usize, buf: &mut [u8]) -> usize {
+            let data = &STREAM[self.send_acked.get() + offset..];
+            let len = cmp::min(data.len(), buf.len());
+            buf[..len].copy_from_slice(&data[..len]);
+            len
+        }
+
+        fn send_done(&self, _socket_id: usize, len: usize) {
+            self.send_acked.set(self.send_acked.get() + len);
+        }
+
+        fn receive(&self, _socket_id: usize, data: &[u8]) -> usize {
+            let mut received = self.received.get();
+            let start = self.received_len.get();
+            let len = cmp::min(data.len(), self.window.get());
+            received[start..start + len].copy_from_slice(&data[..len]);
+            self.received.set(received);
+            self.received_len.set(start + len);
+            self.window.set(self.window.get() - len);
+            len
+        }
+
+        fn receive_window(&self, _socket_id: usize) -> usize {
+            self.window.get()
+        }
+
+        fn remote_closed(&self, _socket_id: usize) {
+            self.remote_closed.set(self.remote_closed.get() + 1);
+        }
+
+        fn closed(&self, _socket_id: usize, result: Result<(), ErrorCode>) {
+            self.closed.set(Some(result));
+        }
+    }
+
+    /// A segment from the peer.
+    fn segment(seq: u32, ack: u32, flags: u16) -> TCPHeader {
+        let mut tcp_header = TCPHeader::new();
+        tcp_header.set_src_port(REMOTE_PORT);
+        tcp_header.set_dst_port(LOCAL_PORT);
+        tcp_header.set_seq_num(seq);
+        tcp_header.set_ack_num(ack);
+        tcp_header.set_window(64);
+        tcp_header.set_flags(flags);
+        tcp_header
+    }
+
+    static NET_CAP: NetworkCapability =
+        NetworkCapability::new_for_test(AddrRange::Any, PortRange::Any, PortRange::Any);
+
+    fn open_active(socket: &TCPSocket, iss: u32) {
+        assert_eq!(
+            socket.open_active(LOCAL_PORT, PEER, REMOTE_PORT, iss, &NET_CAP),
+            Ok(())
+        );
+    }
+
+    fn open_passive(socket: &TCPSocket) {
+        assert_eq!(socket.open_passive(LOCAL_PORT, &NET_CAP), Ok(()));
+    }
+
+    /// Actively opens a connection to a peer with initial sequence number
+    /// `peer_iss`, and returns the next sequence number of the socket.
+    fn establish(socket: &TCPSocket, iss: u32, peer_iss: u32) -> u32 {
+        open_active(socket, iss);
+        let mut buf = [0; 16];
+        socket.prepare_segment(&mut buf).unwrap();
+        let syn_ack = segment(
+            peer_iss,
+            iss.wrapping_add(1),
+            tcp_flags::SYN | tcp_flags::ACK,
+        );
+        assert!(!socket.segment_arrived(PEER, &syn_ack, &[], 0));
+        assert_eq!(socket.get_state(), TCPState::Established);
+        // The ACK of the handshake
+        socket.prepare_segment(&mut buf).unwrap();
+        iss.wrapping_add(1)
+    }
+
+    #[test]
+    fn sequence_comparisons() {
+        assert!(seq_lt(1, 2));
+        assert!(!seq_lt(2, 2));
+        assert!(seq_le(2, 2));
+        assert!(seq_lt(u32::MAX, 0));
+        assert!(seq_lt(u32::MAX - 10, 10));
+        assert!(!seq_lt(10, u32::MAX - 10));
+        assert!(seq_le(0x8000_0000, 0xffff_ffff));
+    }
+
+    #[test]
+    fn active_open() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        open_active(&socket, 100);
+        assert!(socket.matches(LOCAL_PORT, PEER, REMOTE_PORT));
+
+        let mut buf = [0; 16];
+        let (syn, len) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(syn.get_flags(), tcp_flags::SYN);
+        assert_eq!(syn.get_seq_num(), 100);
+        assert_eq!(syn.get_src_port(), LOCAL_PORT);
+        assert_eq!(syn.get_dst_port(), REMOTE_PORT);
+        assert_eq!(len, 0);
+        assert!(socket.timer_active());
+        assert!(socket.prepare_segment(&mut buf).is_none());
+
+        // An ACK of something else is answered with a reset
+        let bad_ack = segment(5000, 100, tcp_flags::SYN | tcp_flags::ACK);
+        assert!(socket.segment_arrived(PEER, &bad_ack, &[], 0));
+        let bad_ack = segment(5000, 102, tcp_flags::SYN | tcp_flags::ACK);
+        assert!(socket.segment_arrived(PEER, &bad_ack, &[], 0));
+        assert_eq!(socket.get_state(), TCPState::SynSent);
+
+        // A reset without an ACK is ignored
+        assert!(!socket.segment_arrived(PEER, &segment(5000, 0, tcp_flags::RST), &[], 0));
+        assert_eq!(socket.get_state(), TCPState::SynSent);
+
+        let syn_ack = segment(5000, 101, tcp_flags::SYN | tcp_flags::ACK);
+        assert!(!socket.segment_arrived(PEER, &syn_ack, &[], 0));
+        assert_eq!(socket.get_state(), TCPState::Established);
+        assert_eq!(client.connected.get(), Some(Ok(())));
+        assert!(!socket.timer_active());
+
+        let (ack, len) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(ack.get_flags(), tcp_flags::ACK);
+        assert_eq!(ack.get_seq_num(), 101);
+        assert_eq!(ack.get_ack_num(), 5001);
+        assert_eq!(ack.get_window(), 32);
+        assert_eq!(len, 0);
+        assert!(socket.prepare_segment(&mut buf).is_none());
+    }
+
+    #[test]
+    fn active_open_refused() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        open_active(&socket, 100);
+        socket.prepare_segment(&mut [0; 16]).unwrap();
+
+        let rst = segment(0, 101, tcp_flags::RST | tcp_flags::ACK);
+        assert!(!socket.segment_arrived(PEER, &rst, &[], 0));
+        assert_eq!(socket.get_state(), TCPState::Closed);
+        assert_eq!(client.connected.get(), Some(Err(ErrorCode::FAIL)));
+        assert_eq!(client.closed.get(), None);
+    }
+
+    #[test]
+    fn simultaneous_open() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        open_active(&socket, 100);
+        let mut buf = [0; 16];
+        socket.prepare_segment(&mut buf).unwrap();
+
+        assert!(!socket.segment_arrived(PEER, &segment(5000, 0, tcp_flags::SYN), &[], 0));
+        assert_eq!(socket.get_state(), TCPState::SynReceived);
+        let (syn_ack, _) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(syn_ack.get_flags(), tcp_flags::SYN | tcp_flags::ACK);
+        assert_eq!(syn_ack.get_seq_num(), 100);
+        assert_eq!(syn_ack.get_ack_num(), 5001);
+
+        assert!(!socket.segment_arrived(PEER, &segment(5001, 101, tcp_flags::ACK), &[], 0));
+        assert_eq!(socket.get_state(), TCPState::Established);
+        assert_eq!(client.connected.get(), Some(Ok(())));
+    }
+
+    #[test]
+    fn passive_open() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        open_passive(&socket);
+        assert_eq!(socket.get_state(), TCPState::Listen);
+        assert!(!socket.matches(LOCAL_PORT, PEER, REMOTE_PORT));
+
+        // Listening sockets reset ACKs and ignore resets
+        assert!(socket.segment_arrived(PEER, &segment(7000, 1, tcp_flags::ACK), &[], 300));
+        assert!(!socket.segment_arrived(PEER, &segment(7000, 0, tcp_flags::RST), &[], 300));
+        assert_eq!(socket.get_state(), TCPState::Listen);
+
+        assert!(!socket.segment_arrived(PEER, &segment(7000, 0, tcp_flags::SYN), &[], 300));
+        assert_eq!(socket.get_state(), TCPState::SynReceived);
+        assert!(socket.matches(LOCAL_PORT, PEER, REMOTE_PORT));
+        let mut buf = [0; 16];
+        let (syn_ack, _) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(syn_ack.get_flags(), tcp_flags::SYN | tcp_flags::ACK);
+        assert_eq!(syn_ack.get_seq_num(), 300);
+        assert_eq!(syn_ack.get_ack_num(), 7001);
+        assert!(socket.prepare_segment(&mut buf).is_none());
+
+        // A retransmitted SYN gets the SYN-ACK again
+        assert!(!socket.segment_arrived(PEER, &segment(7000, 0, tcp_flags::SYN), &[], 300));
+        assert_eq!(socket.get_state(), TCPState::SynReceived);
+        let (syn_ack, _) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(syn_ack.get_flags(), tcp_flags::SYN | tcp_flags::ACK);
+        assert_eq!(syn_ack.get_seq_num(), 300);
+
+        // An ACK of something else is answered with a reset
+        assert!(socket.segment_arrived(PEER, &segment(7001, 305, tcp_flags::ACK), &[], 0));
+        assert_eq!(socket.get_state(), TCPState::SynReceived);
+
+        assert!(!socket.segment_arrived(PEER, &segment(7001, 301, tcp_flags::ACK), &[], 0));
+        assert_eq!(socket.get_state(), TCPState::Established);
+        assert_eq!(client.connected.get(), Some(Ok(())));
+        assert!(!socket.timer_active());
+    }
+
+    #[test]
+    fn passive_open_reset() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        open_passive(&socket);
+        socket.segment_arrived(PEER, &segment(7000, 0, tcp_flags::SYN), &[], 300);
+        socket.prepare_segment(&mut [0; 16]).unwrap();
+
+        // A reset outside of the window is ignored
+        assert!(!socket.segment_arrived(PEER, &segment(9000, 0, tcp_flags::RST), &[], 0));
+        assert_eq!(socket.get_state(), TCPState::SynReceived);
+
+        // The client never heard of the connection, so it isn't told
+        assert!(!socket.segment_arrived(PEER, &segment(7001, 0, tcp_flags::RST), &[], 0));
+        assert_eq!(socket.get_state(), TCPState::Listen);
+        assert!(!socket.matches(LOCAL_PORT, PEER, REMOTE_PORT));
+        assert_eq!(client.connected.get(), None);
+        assert_eq!(client.closed.get(), None);
+    }
+
+    #[test]
+    fn receive_data() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        let snd_nxt = establish(&socket, 100, 5000);
+        let mut buf = [0; 16];
+
+        assert!(!socket.segment_arrived(
+            PEER,
+            &segment(5001, snd_nxt, tcp_flags::ACK),
+            b"hello",
+            0
+        ));
+        let (ack, _) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(ack.get_ack_num(), 5006);
+        assert_eq!(ack.get_window(), 27);
+
+        // Bytes that were already received are trimmed
+        assert!(!socket.segment_arrived(
+            PEER,
+            &segment(5004, snd_nxt, tcp_flags::ACK),
+            b"lo, w",
+            0
+        ));
+        // A segment after a gap is dropped
+        assert!(!socket.segment_arrived(PEER, &segment(5020, snd_nxt, tcp_flags::ACK), b"x", 0));
+        // And so is one outside of the window
+        assert!(!socket.segment_arrived(PEER, &segment(6000, snd_nxt, tcp_flags::ACK), b"x", 0));
+        let (received, len) = client.received();
+        assert_eq!(&received[..len], b"hello, w");
+        let (ack, _) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(ack.get_ack_num(), 5009);
+
+        // Only as much as the client accepts is acknowledged
+        client.window.set(2);
+        assert!(!socket.segment_arrived(PEER, &segment(5009, snd_nxt, tcp_flags::ACK), b"orld", 0));
+        let (received, len) = client.received();
+        assert_eq!(&received[..len], b"hello, wor");
+        let (ack, _) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(ack.get_ack_num(), 5011);
+        assert_eq!(ack.get_window(), 0);
+    }
+
+    #[test]
+    fn send_data() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        let snd_nxt = establish(&socket, 100, 5000);
+
+        socket.queue_send(10).unwrap();
+        let mut buf = [0; 16];
+        let (data, len) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(data.get_flags(), tcp_flags::ACK | tcp_flags::PSH);
+        assert_eq!(data.get_seq_num(), snd_nxt);
+        assert_eq!(&buf[..len], &STREAM[..10]);
+        assert!(socket.timer_active());
+        assert!(socket.prepare_segment(&mut buf).is_none());
+
+        // An ACK of data that was not sent is dropped
+        let ack = segment(5001, snd_nxt + 11, tcp_flags::ACK);
+        assert!(!socket.segment_arrived(PEER, &ack, &[], 0));
+        assert_eq!(client.send_acked.get(), 0);
+        let (ack, _) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(ack.get_flags(), tcp_flags::ACK);
+
+        let ack = segment(5001, snd_nxt + 4, tcp_flags::ACK);
+        assert!(!socket.segment_arrived(PEER, &ack, &[], 0));
+        assert_eq!(client.send_acked.get(), 4);
+        assert!(socket.timer_active());
+
+        let ack = segment(5001, snd_nxt + 10, tcp_flags::ACK);
+        assert!(!socket.segment_arrived(PEER, &ack, &[], 0));
+        assert_eq!(client.send_acked.get(), 10);
+        assert!(!socket.timer_active());
+    }
+
+    #[test]
+    fn retransmission() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        let snd_nxt = establish(&socket, 100, 5000);
+        socket.queue_send(4).unwrap();
+        let mut buf = [0; 16];
+        socket.prepare_segment(&mut buf).unwrap();
+
+        let mut retransmissions = 0;
+        for _ in 0..10_000 {
+            if socket.tick() {
+                let (data, len) = socket.prepare_segment(&mut buf).unwrap();
+                assert_eq!(data.get_seq_num(), snd_nxt);
+                assert_eq!(&buf[..len], &STREAM[..4]);
+                retransmissions += 1;
+            }
+            if socket.get_state() == TCPState::Closed {
+                break;
+            }
+        }
+        assert_eq!(retransmissions, MAX_RETRIES);
+        assert_eq!(socket.get_state(), TCPState::Closed);
+        assert_eq!(client.closed.get(), Some(Err(ErrorCode::NOACK)));
+    }
+
+    #[test]
+    fn passive_close() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        let snd_nxt = establish(&socket, 100, 5000);
+        let mut buf = [0; 16];
+
+        // A FIN after a gap is not processed
+        let fin = segment(5003, snd_nxt, tcp_flags::ACK | tcp_flags::FIN);
+        assert!(!socket.segment_arrived(PEER, &fin, &[], 0));
+        assert_eq!(socket.get_state(), TCPState::Established);
+
+        let fin = segment(5001, snd_nxt, tcp_flags::ACK | tcp_flags::FIN);
+        assert!(!socket.segment_arrived(PEER, &fin, b"bye", 0));
+        assert_eq!(socket.get_state(), TCPState::CloseWait);
+        assert_eq!(client.remote_closed.get(), 1);
+        let (received, len) = client.received();
+        assert_eq!(&received[..len], b"bye");
+        let (ack, _) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(ack.get_ack_num(), 5005);
+
+        // Data can still be sent, and the FIN follows it
+        socket.queue_send(2).unwrap();
+        socket.close().unwrap();
+        assert_eq!(socket.get_state(), TCPState::LastAck);
+        assert_eq!(socket.queue_send(1), Err(ErrorCode::OFF));
+        let (fin, len) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(
+            fin.get_flags(),
+            tcp_flags::ACK | tcp_flags::PSH | tcp_flags::FIN
+        );
+        assert_eq!(&buf[..len], &STREAM[..2]);
+
+        // The data alone does not close the connection
+        let ack = segment(5005, snd_nxt + 2, tcp_flags::ACK);
+        assert!(!socket.segment_arrived(PEER, &ack, &[], 0));
+        assert_eq!(socket.get_state(), TCPState::LastAck);
+
+        let ack = segment(5005, snd_nxt + 3, tcp_flags::ACK);
+        assert!(!socket.segment_arrived(PEER, &ack, &[], 0));
+        assert_eq!(socket.get_state(), TCPState::Closed);
+        assert_eq!(client.send_acked.get(), 2);
+        assert_eq!(client.closed.get(), Some(Ok(())));
+    }
+
+    #[test]
+    fn active_close() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        let snd_nxt = establish(&socket, 100, 5000);
+        let mut buf = [0; 16];
+
+        socket.close().unwrap();
+        assert_eq!(socket.get_state(), TCPState::FinWait1);
+        assert_eq!(socket.close(), Err(ErrorCode::ALREADY));
+        let (fin, len) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(fin.get_flags(), tcp_flags::ACK | tcp_flags::FIN);
+        assert_eq!(fin.get_seq_num(), snd_nxt);
+        assert_eq!(len, 0);
+
+        let ack = segment(5001, snd_nxt + 1, tcp_flags::ACK);
+        assert!(!socket.segment_arrived(PEER, &ack, &[], 0));
+        assert_eq!(socket.get_state(), TCPState::FinWait2);
+
+        // Data is still received until the peer closes
+        let fin = segment(5001, snd_nxt + 1, tcp_flags::ACK | tcp_flags::FIN);
+        assert!(!socket.segment_arrived(PEER, &fin, b"ok", 0));
+        assert_eq!(socket.get_state(), TCPState::TimeWait);
+        assert_eq!(client.remote_closed.get(), 1);
+        let (received, len) = client.received();
+        assert_eq!(&received[..len], b"ok");
+        let (ack, _) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(ack.get_ack_num(), 5004);
+
+        for _ in 1..TIME_WAIT_TICKS {
+            assert!(!socket.tick());
+            assert_eq!(socket.get_state(), TCPState::TimeWait);
+        }
+        assert_eq!(client.closed.get(), None);
+        assert!(!socket.tick());
+        assert_eq!(socket.get_state(), TCPState::Closed);
+        assert_eq!(client.closed.get(), Some(Ok(())));
+    }
+
+    #[test]
+    fn simultaneous_close() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        let snd_nxt = establish(&socket, 100, 5000);
+        let mut buf = [0; 16];
+
+        socket.close().unwrap();
+        socket.prepare_segment(&mut buf).unwrap();
+
+        // The FIN of the peer crosses ours
+        let fin = segment(5001, snd_nxt, tcp_flags::ACK | tcp_flags::FIN);
+        assert!(!socket.segment_arrived(PEER, &fin, &[], 0));
+        assert_eq!(socket.get_state(), TCPState::Closing);
+        assert_eq!(client.remote_closed.get(), 1);
+
+        let ack = segment(5002, snd_nxt + 1, tcp_flags::ACK);
+        assert!(!socket.segment_arrived(PEER, &ack, &[], 0));
+        assert_eq!(socket.get_state(), TCPState::TimeWait);
+        assert!(socket.timer_active());
+    }
+
+    #[test]
+    fn reset() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        let snd_nxt = establish(&socket, 100, 5000);
+
+        // Resets outside of the window are ignored
+        assert!(!socket.segment_arrived(PEER, &segment(4000, 0, tcp_flags::RST), &[], 0));
+        assert!(!socket.segment_arrived(PEER, &segment(5033, 0, tcp_flags::RST), &[], 0));
+        assert_eq!(socket.get_state(), TCPState::Established);
+
+        assert!(!socket.segment_arrived(PEER, &segment(5010, snd_nxt, tcp_flags::RST), &[], 0));
+        assert_eq!(socket.get_state(), TCPState::Closed);
+        assert_eq!(client.closed.get(), Some(Err(ErrorCode::CANCEL)));
+
+        // A closed socket answers everything but resets with a reset
+        assert!(socket.segment_arrived(PEER, &segment(5001, snd_nxt, tcp_flags::ACK), &[], 0));
+        assert!(!socket.segment_arrived(PEER, &segment(5001, 0, tcp_flags::RST), &[], 0));
+    }
+
+    #[test]
+    fn syn_in_window() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        establish(&socket, 100, 5000);
+
+        assert!(socket.segment_arrived(PEER, &segment(5001, 0, tcp_flags::SYN), &[], 0));
+        assert_eq!(socket.get_state(), TCPState::Closed);
+        assert_eq!(client.closed.get(), Some(Err(ErrorCode::CANCEL)));
+    }
+
+    #[test]
+    fn abort() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        let snd_nxt = establish(&socket, 100, 5000);
+
+        let rst = socket.abort().unwrap();
+        assert_eq!(rst.get_flags(), tcp_flags::RST);
+        assert_eq!(rst.get_seq_num(), snd_nxt);
+        assert_eq!(socket.get_state(), TCPState::Closed);
+        assert_eq!(client.closed.get(), None);
+        assert!(socket.abort().is_none());
+    }
+
+    #[test]
+    fn sequence_wraparound() {
+        let client = TestClient::new();
+        let socket = TCPSocket::new(0);
+        socket.set_client(&client);
+        // Both streams cross 2^32 in the middle of a segment
+        let snd_nxt = establish(&socket, u32::MAX - 5, u32::MAX - 3);
+        assert_eq!(snd_nxt, u32::MAX - 4);
+        let mut buf = [0; 16];
+
+        assert!(!socket.segment_arrived(
+            PEER,
+            &segment(u32::MAX - 2, snd_nxt, tcp_flags::ACK),
+            b"wrapped",
+            0
+        ));
+        let (ack, _) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(ack.get_ack_num(), 4);
+        // A retransmission across the wrap is trimmed
+        assert!(!socket.segment_arrived(
+            PEER,
+            &segment(u32::MAX, snd_nxt, tcp_flags::ACK),
+            b"apped!",
+            0
+        ));
+        let (received, len) = client.received();
+        assert_eq!(&received[..len], b"wrapped!");
+
+        socket.queue_send(8).unwrap();
+        let (data, len) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(data.get_seq_num(), u32::MAX - 4);
+        assert_eq!(data.get_ack_num(), 5);
+        assert_eq!(len, 8);
+
+        let ack = segment(5, 1, tcp_flags::ACK);
+        assert!(!socket.segment_arrived(PEER, &ack, &[], 0));
+        assert_eq!(client.send_acked.get(), 6);
+        let ack = segment(5, 3, tcp_flags::ACK);
+        assert!(!socket.segment_arrived(PEER, &ack, &[], 0));
+        assert_eq!(client.send_acked.get(), 8);
+        assert!(!socket.timer_active());
+
+        // Old ACKs from before the wrap change nothing
+        let ack = segment(5, u32::MAX - 2, tcp_flags::ACK);
+        assert!(!socket.segment_arrived(PEER, &ack, &[], 0));
+        assert_eq!(client.send_acked.get(), 8);
+
+        let fin = segment(5, 3, tcp_flags::ACK | tcp_flags::FIN);
+        assert!(!socket.segment_arrived(PEER, &fin, &[], 0));
+        assert_eq!(socket.get_state(), TCPState::CloseWait);
+        let (ack, _) = socket.prepare_segment(&mut buf).unwrap();
+        assert_eq!(ack.get_ack_num(), 6);
+    }
+}
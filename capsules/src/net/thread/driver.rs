@@ -0,0 +1,153 @@
+//! Thread userspace interface.
+//!
+//! Lets processes provision the network key and attach the node to a Thread
+//! network as a sleepy end device. Processes are told when the attach state
+//! changes; the node is shared by all processes, so any process can attach
+//! or detach it.
+
+use crate::net::thread::mle::{AttachState, Mle, MleClient};
+
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::processbuffer::ReadableProcessBuffer;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::{ErrorCode, ProcessId};
+
+use crate::driver;
+pub const DRIVER_NUM: usize = driver::NUM::Thread as usize;
+
+/// Length of the network key.
+const NETWORK_KEY_LEN: usize = 16;
+
+/// Ids for read-only allow buffers
+mod ro_allow {
+    pub const NETWORK_KEY: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+/// Ids for subscribe upcalls
+mod upcalls {
+    pub const STATE_CHANGED: usize = 0;
+    /// The number of upcalls the kernel stores for this grant
+    pub const COUNT: u8 = 1;
+}
+
+#[derive(Default)]
+pub struct App {}
+
+pub struct ThreadDriver<'a> {
+    mle: &'a dyn Mle<'a>,
+    apps: Grant<
+        App,
+        UpcallCount<{ upcalls::COUNT }>,
+        AllowRoCount<{ ro_allow::COUNT }>,
+        AllowRwCount<0>,
+    >,
+}
+
+impl<'a> ThreadDriver<'a> {
+    pub fn new(
+        mle: &'a dyn Mle<'a>,
+        grant: Grant<
+            App,
+            UpcallCount<{ upcalls::COUNT }>,
+            AllowRoCount<{ ro_allow::COUNT }>,
+            AllowRwCount<0>,
+        >,
+    ) -> ThreadDriver<'a> {
+        ThreadDriver {
+            mle: mle,
+            apps: grant,
+        }
+    }
+
+    /// Reads the network key from the allow buffer of `processid`.
+    fn read_network_key(&self, processid: ProcessId) -> Result<[u8; NETWORK_KEY_LEN], ErrorCode> {
+        self.apps
+            .enter(processid, |_, kernel_data| {
+                kernel_data
+                    .get_readonly_processbuffer(ro_allow::NETWORK_KEY)
+                    .and_then(|key| {
+                        key.enter(|key| {
+                            if key.len() != NETWORK_KEY_LEN {
+                                return Err(ErrorCode::INVAL);
+                            }
+                            let mut network_key = [0; NETWORK_KEY_LEN];
+                            key.copy_to_slice(&mut network_key);
+                            Ok(network_key)
+                        })
+                    })
+                    .unwrap_or(Err(ErrorCode::RESERVE))
+            })
+            .unwrap_or_else(|err| Err(err.into()))
+    }
+}
+
+impl<'a> SyscallDriver for ThreadDriver<'a> {
+    /// Thread control
+    ///
+    /// ### `command_num`
+    ///
+    /// - `0`: Driver check.
+    /// - `1`: Set the network key in the network key buffer, with the key
+    ///        sequence `arg1`. Returns INVAL if the buffer is not 16 bytes
+    ///        long and BUSY unless the node is detached.
+    /// - `2`: Attach to a parent. The state changed upcall reports the
+    ///        progress. Returns INVAL if no network key was set and ALREADY
+    ///        unless the node is detached.
+    /// - `3`: Detach from the parent, or stop attaching.
+    /// - `4`: Get the attach state: 0 detached, 1 attaching, 2 attached.
+    /// - `5`: Get the RLOC16 assigned by the parent. Returns FAIL unless the
+    ///        node is attached.
+    fn command(
+        &self,
+        command_num: usize,
+        arg1: usize,
+        _: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        match command_num {
+            0 => CommandReturn::success(),
+
+            // Set network key
+            1 => {
+                let result = self
+                    .read_network_key(processid)
+                    .and_then(|key| self.mle.set_network_key(key, arg1 as u32));
+                CommandReturn::from(result)
+            }
+
+            // Attach
+            2 => CommandReturn::from(self.mle.attach()),
+
+            // Detach
+            3 => CommandReturn::from(self.mle.detach()),
+
+            // Get attach state
+            4 => CommandReturn::success_u32(self.mle.attach_state() as u32),
+
+            // Get RLOC16
+            5 => match self.mle.rloc16() {
+                Some(rloc16) => CommandReturn::success_u32(rloc16 as u32),
+                None => CommandReturn::failure(ErrorCode::FAIL),
+            },
+
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
+
+impl<'a> MleClient for ThreadDriver<'a> {
+    fn attach_state_changed(&self, state: AttachState) {
+        let rloc16 = self.mle.rloc16().unwrap_or(0xfffe) as usize;
+        self.apps.each(|_, _, kernel_data| {
+            kernel_data
+                .schedule_upcall(upcalls::STATE_CHANGED, (state as usize, rloc16, 0))
+                .ok();
+        });
+    }
+}
@@ -0,0 +1,1495 @@
+//! Mesh Link Establishment (MLE) for attaching a Sleepy End Device (SED) to
+//! a Thread network, as outlined in Chapter 4 of the Thread 1.1.1
+//! Specification.
+//!
+//! MLE for network attaching comprises a four-step handshake that works
+//! as follows:
+//!
+//! 1. A child device multicasts a Parent Request MLE command.
+//! 2. Each potential parent device on the network unicasts a Parent
+//!    Response MLE command.
+//! 3. The child device selects a parent based on a hierarchy of
+//!    connectivity metrics and unicasts a Child ID Request MLE
+//!    command.
+//! 4. The selected parent unicasts a Child ID Response MLE command.
+//!
+//! The [MleNode](struct.MleNode.html) first sends the Parent Request to
+//! routers only, and then also to Router-Eligible End Devices (REEDs) if no
+//! router answered. Among the parents that answered, the one with the best
+//! link quality is chosen, then the one with the highest priority, then the
+//! one with the most neighbors of high link quality. Once the parent assigns
+//! the child its RLOC16, the node uses it as its short MAC address and sends
+//! all unicast packets to the parent. If the attach fails, it is retried
+//! with an exponential backoff.
+//!
+//! As a sleepy end device, the node does not receive frames sent to it
+//! while it is idle. Instead, it polls its parent for pending frames by
+//! sending MAC Data Request commands, quickly while it waits for an MLE
+//! response and every few seconds otherwise. Before the child timeout passes
+//! it sends a Child Update Request to keep the parent from removing it. If
+//! the parent does not answer or no longer knows the child, the node
+//! attaches again.
+//!
+//! MLE messages are secured with AES-CCM using the MLE key, which is derived
+//! together with the MAC key from the network key with HMAC-SHA256 (Section
+//! 7.1.4). The key sequence is carried in each message, and a node that
+//! receives a message with a newer key sequence from its parent switches to
+//! it.
+//!
+//! Once attached, the Data Requests and the data frames of the UDP stack are
+//! secured with the MAC key, identified by key ID mode 1 with the key index
+//! of the key sequence (Section 7.2.2.1). MLE messages are only secured by
+//! MLE, so their UDP sender sends them without link-layer security. The MAC
+//! key is added to the 802.15.4 key table in place of the keys of other key
+//! sequences, and the parent to the device table, so that the frames of the
+//! parent are unsecured and checked for replays.
+//!
+//! The keys derived for a key sequence are the same after a reboot, so a
+//! frame counter must never be used twice with them. MLE messages take their
+//! frame counters from the outgoing frame counter of the 802.15.4
+//! `SecurityTable`, which reserves them in nonvolatile storage. The node only
+//! attaches if the table is kept in storage, and does not send messages
+//! until it has been read.
+//!
+//! Limitations:
+//!
+//! - The radio is left on; turning it off between polls is left to the
+//!   power management of the board.
+//! - The network data sent by the parent is not interpreted.
+//! - The UDP stack must use the link-local address formed from the extended
+//!   MAC address as its first interface address, and send from the extended
+//!   MAC address.
+//!
+//! Usage
+//! -----
+//!
+//! The node is set up by `components::thread::ThreadComponent`, which also
+//! creates the userspace driver:
+//!
+//! ```rust
+//! let thread_driver = components::thread::ThreadComponent::new(
+//!     board_kernel,
+//!     capsules::net::thread::DRIVER_NUM,
+//!     mux_mac,
+//!     udp_send_mux,
+//!     udp_recv_mux,
+//!     udp_port_table,
+//!     mux_alarm,
+//!     aes_mux,
+//!     rng,
+//! )
+//! .finalize(components::thread_component_helper!(
+//!     nrf52840::rtc::Rtc,
+//!     nrf52840::aes::AesECB<'static>
+//! ));
+//! ```
+
+use crate::ieee802154::device::{MacDevice, TxClient};
+use crate::ieee802154::framer::KeyProcedure;
+use crate::ieee802154::security_table::{KeyDescriptor, KeyUsage, SecurityTable};
+use crate::net::icmpv6::icmpv6_responder::ALL_ROUTERS_ADDR;
+use crate::net::ieee802154::{FrameType, KeyId, KeyIdMode, MacAddress, SecurityLevel};
+use crate::net::ipv6::ip_utils::IPAddr;
+use crate::net::ipv6::ipv6_send::IP6Sender;
+use crate::net::network_capabilities::NetworkCapability;
+use crate::net::thread::tlv::{LinkMode, MulticastResponder, Tlv, TlvType};
+use crate::net::udp::udp_recv::UDPRecvClient;
+use crate::net::udp::udp_send::{MuxUdpSender, UDPSendClient, UDPSender};
+
+use core::cell::Cell;
+use core::cmp;
+
+use kernel::hil::digest::{self, DigestDataHash};
+use kernel::hil::rng::{self, Rng};
+use kernel::hil::symmetric_encryption::{CCMClient, AES128CCM, CCM_NONCE_LENGTH};
+use kernel::hil::time::{self, ConvertTicks};
+use kernel::utilities::cells::{OptionalCell, TakeCell};
+use kernel::utilities::leasable_buffer::LeasableMutableBuffer;
+use kernel::ErrorCode;
+
+/// The UDP port MLE messages are sent from and to.
+pub const MLE_PORT: u16 = 19788;
+
+/// The largest MLE command with its TLVs that is sent or received.
+pub const MAX_MESSAGE_LEN: usize = 200;
+
+/// Length of the buffer MLE messages are secured in: the authenticated
+/// header, the command with its TLVs and the MIC.
+pub const CRYPT_BUF_LEN: usize = AUTH_LEN + MAX_MESSAGE_LEN + MIC_LEN;
+
+/// Length of the buffer the UDP payload of MLE messages is built in.
+pub const SEND_BUF_LEN: usize = 1 + AUX_HEADER_LEN + MAX_MESSAGE_LEN + MIC_LEN;
+
+/// Length of the buffer the keys are derived in: an HMAC block followed by
+/// the inner hash.
+pub const HASH_BUF_LEN: usize = HMAC_BLOCK_LEN + 32;
+
+/// The version of the Thread protocol sent in the Version TLV.
+const THREAD_VERSION: u16 = 2;
+
+/// The child timeout requested from the parent, in seconds.
+const CHILD_TIMEOUT: u32 = 240;
+
+// Timers of the attach process and of the data polls, in milliseconds.
+const PARENT_REQUEST_ROUTER_TIMEOUT_MS: u32 = 750;
+const PARENT_REQUEST_REED_TIMEOUT_MS: u32 = 1250;
+const CHILD_ID_RESPONSE_TIMEOUT_MS: u32 = 1250;
+const CHILD_UPDATE_RESPONSE_TIMEOUT_MS: u32 = 2000;
+const CHILD_UPDATE_INTERVAL_MS: u32 = CHILD_TIMEOUT * 1000 / 2;
+const ATTACH_BACKOFF_MIN_MS: u32 = 5000;
+const ATTACH_BACKOFF_MAX_MS: u32 = 120000;
+const FAST_POLL_PERIOD_MS: u32 = 250;
+const POLL_PERIOD_MS: u32 = 5000;
+
+/// The longest single alarm, in milliseconds. Longer timers are split so
+/// that the alarm does not overflow on chips with narrow counters.
+const MAX_ALARM_INTERVAL_MS: u32 = 60000;
+
+/// How many times a Child ID Request or Child Update Request is sent before
+/// giving up.
+const MAX_REQUEST_ATTEMPTS: u8 = 3;
+
+/// MLE command types.
+mod command {
+    pub const PARENT_REQUEST: u8 = 9;
+    pub const PARENT_RESPONSE: u8 = 10;
+    pub const CHILD_ID_REQUEST: u8 = 11;
+    pub const CHILD_ID_RESPONSE: u8 = 12;
+    pub const CHILD_UPDATE_REQUEST: u8 = 13;
+    pub const CHILD_UPDATE_RESPONSE: u8 = 14;
+}
+
+/// The first byte of a secured MLE message. Messages with the unsecured
+/// suite (255) are only used for discovery and are dropped.
+const SECURITY_SUITE_154: u8 = 0;
+
+/// Security control of the auxiliary header of MLE messages: encrypted with
+/// a 32-bit MIC, and the key identified by a 4-byte source and an index.
+const SECURITY_CONTROL: u8 = SecurityLevel::EncMic32 as u8 | KeyIdMode::Source4Index as u8;
+
+/// Length of the auxiliary security header: security control, frame
+/// counter, key source and key index.
+const AUX_HEADER_LEN: usize = 10;
+
+const MIC_LEN: usize = 4;
+
+/// The security level of MAC frames secured with the MAC key.
+const MAC_SECURITY_LEVEL: SecurityLevel = SecurityLevel::EncMic32;
+
+/// Length of the data that is authenticated but not encrypted: the source
+/// and destination IPv6 addresses followed by the auxiliary header.
+const AUTH_LEN: usize = 16 + 16 + AUX_HEADER_LEN;
+
+const HMAC_BLOCK_LEN: usize = 64;
+const HMAC_IPAD: u8 = 0x36;
+const HMAC_OPAD: u8 = 0x5c;
+
+/// Appended to the key sequence when deriving the keys.
+const KEY_DERIVATION_STRING: &[u8; 6] = b"Thread";
+
+/// The identifier of the MAC Data Request command, which polls the parent
+/// for pending frames.
+const DATA_REQUEST_COMMAND: u8 = 0x04;
+
+/// The short address of a node without one.
+const NO_SHORT_ADDR: u16 = 0xfffe;
+
+/// Whether the node is attached to a parent, as seen by clients.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AttachState {
+    Detached = 0,
+    Attaching = 1,
+    Attached = 2,
+}
+
+/// Receives the changes of the attach state.
+pub trait MleClient {
+    fn attach_state_changed(&self, state: AttachState);
+}
+
+/// The interface to attach to a Thread network.
+pub trait Mle<'a> {
+    fn set_client(&self, client: &'a dyn MleClient);
+
+    /// Sets the network key and its key sequence, from which the MLE and MAC
+    /// keys are derived. Returns BUSY unless the node is detached and not
+    /// deriving keys.
+    fn set_network_key(&self, key: [u8; 16], key_sequence: u32) -> Result<(), ErrorCode>;
+
+    /// Starts attaching to a parent. Returns INVAL if no network key was
+    /// set, BUSY while the keys are derived, NOSUPPORT if frame counters are
+    /// not kept in storage and ALREADY unless the node is detached.
+    fn attach(&self) -> Result<(), ErrorCode>;
+
+    /// Stops the attach process or leaves the parent.
+    fn detach(&self) -> Result<(), ErrorCode>;
+
+    fn attach_state(&self) -> AttachState;
+
+    /// The RLOC16 assigned by the parent, if attached.
+    fn rloc16(&self) -> Option<u16>;
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum MleState {
+    Detached,
+    /// Waiting for Parent Responses, from routers only or also from REEDs.
+    ParentRequest {
+        reeds: bool,
+    },
+    /// Waiting for the Child ID Response of the chosen parent.
+    ChildIdRequest {
+        attempts: u8,
+    },
+    Attached,
+    /// Waiting for the answer of the parent to a keep-alive.
+    ChildUpdateRequest {
+        attempts: u8,
+    },
+    /// Waiting to attach again after an attach failed.
+    Backoff,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum CryptoState {
+    Idle,
+    /// Hashing the inner and outer parts of the HMAC for a key sequence.
+    DeriveInner {
+        key_sequence: u32,
+    },
+    DeriveOuter {
+        key_sequence: u32,
+    },
+    Encrypting {
+        dst_addr: IPAddr,
+        len: usize,
+    },
+    Decrypting,
+}
+
+#[derive(Copy, Clone)]
+struct Keys {
+    key_sequence: u32,
+    mle_key: [u8; 16],
+    mac_key: [u8; 16],
+}
+
+#[derive(Copy, Clone)]
+struct LeaderData {
+    partition_id: u32,
+    weighting: u8,
+    data_version: u8,
+    stable_data_version: u8,
+    leader_router_id: u8,
+}
+
+/// A parent that answered the Parent Request, or the one attached to.
+#[derive(Copy, Clone)]
+struct Parent {
+    ext_addr: [u8; 8],
+    rloc16: u16,
+    /// The challenge of the parent, answered in the Child ID Request.
+    challenge: [u8; 8],
+    link_quality: u8,
+    /// -1, 0 or 1.
+    priority: i8,
+    /// The number of neighbors the parent has with link quality 3.
+    link_quality_3: u8,
+    /// The last MLE frame counter received from the parent.
+    frame_counter: u32,
+    leader_data: LeaderData,
+}
+
+impl Parent {
+    fn is_better_than(&self, other: &Parent) -> bool {
+        (self.link_quality, self.priority, self.link_quality_3)
+            > (other.link_quality, other.priority, other.link_quality_3)
+    }
+}
+
+/// A received message that is being decrypted.
+#[derive(Copy, Clone)]
+struct RxMessage {
+    src_addr: IPAddr,
+    frame_counter: u32,
+    key_sequence: u32,
+    len: usize,
+}
+
+/// The TLVs of a received message that the node interprets.
+#[derive(Default)]
+struct MessageTlvs {
+    source_address: Option<u16>,
+    challenge: Option<[u8; 8]>,
+    response: Option<[u8; 8]>,
+    address16: Option<u16>,
+    leader_data: Option<LeaderData>,
+    link_margin: Option<u8>,
+    /// The parent priority and the number of neighbors with link quality 3.
+    connectivity: Option<(i8, u8)>,
+    status: Option<u8>,
+}
+
+impl MessageTlvs {
+    /// Decodes the TLVs following the command. Unknown and malformed TLVs
+    /// are skipped.
+    fn parse(tlvs: &[u8]) -> MessageTlvs {
+        let mut parsed = MessageTlvs::default();
+        let mut offset = 0;
+        // Each TLV starts with its type and length
+        while offset + 2 <= tlvs.len() {
+            let end = offset + 2 + tlvs[offset + 1] as usize;
+            if end > tlvs.len() {
+                break;
+            }
+            match Tlv::decode(&tlvs[offset..end]).done() {
+                Some((_, Tlv::SourceAddress(addr))) => parsed.source_address = Some(addr),
+                Some((_, Tlv::Challenge(challenge))) => parsed.challenge = Some(challenge),
+                Some((_, Tlv::Response(response))) => parsed.response = Some(response),
+                Some((_, Tlv::Address16(addr))) => parsed.address16 = Some(addr),
+                Some((
+                    _,
+                    Tlv::LeaderData {
+                        partition_id,
+                        weighting,
+                        data_version,
+                        stable_data_version,
+                        leader_router_id,
+                    },
+                )) => {
+                    parsed.leader_data = Some(LeaderData {
+                        partition_id: partition_id,
+                        weighting: weighting,
+                        data_version: data_version,
+                        stable_data_version: stable_data_version,
+                        leader_router_id: leader_router_id,
+                    })
+                }
+                Some((_, Tlv::LinkMargin(margin))) => parsed.link_margin = Some(margin),
+                Some((
+                    _,
+                    Tlv::Connectivity {
+                        parent_priority,
+                        link_quality_3,
+                        ..
+                    },
+                )) => {
+                    // The priority is a signed 2-bit value in the top bits
+                    let priority = match parent_priority >> 6 {
+                        0b01 => 1,
+                        0b11 => -1,
+                        _ => 0,
+                    };
+                    parsed.connectivity = Some((priority, link_quality_3));
+                }
+                Some((_, Tlv::Status(status))) => parsed.status = Some(status),
+                _ => {}
+            }
+            offset = end;
+        }
+        parsed
+    }
+}
+
+/// Converts the link margin in dB reported by a parent to a link quality
+/// (Section 4.4.1.2.3).
+fn link_quality(link_margin: u8) -> u8 {
+    if link_margin > 20 {
+        3
+    } else if link_margin > 10 {
+        2
+    } else if link_margin > 2 {
+        1
+    } else {
+        0
+    }
+}
+
+/// Returns the extended MAC address a link-local address was formed from.
+fn ext_addr_from_link_local(addr: &IPAddr) -> [u8; 8] {
+    let mut ext_addr = [0; 8];
+    ext_addr.copy_from_slice(&addr.0[8..16]);
+    ext_addr[0] ^= 0x02;
+    ext_addr
+}
+
+/// Whether `addr` is the RLOC16 of a child of the router with RLOC16
+/// `router`: the router ID in the top 6 bits must match and the child ID in
+/// the low 9 bits must not be 0.
+fn is_child_rloc16(addr: u16, router: u16) -> bool {
+    addr & 0xfc00 == router & 0xfc00 && addr & 0x01ff != 0
+}
+
+/// The key index of a key sequence: its low 7 bits plus one.
+fn key_index(key_sequence: u32) -> u8 {
+    (key_sequence & 0x7f) as u8 + 1
+}
+
+fn ccm_nonce(ext_addr: &[u8; 8], frame_counter: u32) -> [u8; CCM_NONCE_LENGTH] {
+    let mut nonce = [0; CCM_NONCE_LENGTH];
+    nonce[0..8].copy_from_slice(ext_addr);
+    nonce[8..12].copy_from_slice(&frame_counter.to_be_bytes());
+    nonce[12] = SecurityLevel::EncMic32 as u8;
+    nonce
+}
+
+pub struct MleNode<
+    'a,
+    A: time::Alarm<'a>,
+    C: AES128CCM<'a>,
+    D: DigestDataHash<'a, 32>,
+    T: IP6Sender<'a>,
+> {
+    alarm: &'a A,
+    ccm: &'a C,
+    digest: &'a D,
+    udp_mux: &'a MuxUdpSender<'a, T>,
+    udp_sender: &'a dyn UDPSender<'a>,
+    mac: &'a dyn MacDevice<'a>,
+    /// Provides the frame counters of the MLE messages sent, and holds the
+    /// MAC key and the parent for securing MAC frames.
+    security_table: &'a SecurityTable<'a>,
+    rng: &'a dyn Rng<'a>,
+    net_cap: &'static NetworkCapability,
+
+    state: Cell<MleState>,
+    /// Milliseconds left until the timer of the current state expires.
+    timer: Cell<u32>,
+    /// Milliseconds the alarm was last set for.
+    alarm_interval: Cell<u32>,
+    attach_backoff: Cell<u32>,
+    /// A message of the current state could not be sent because a buffer,
+    /// the crypto engine or a challenge was not available, and is sent once
+    /// it is.
+    send_pending: Cell<bool>,
+
+    network_key: OptionalCell<[u8; 16]>,
+    keys: OptionalCell<Keys>,
+    /// Keys of a newer key sequence, adopted once a message secured with
+    /// them is accepted.
+    rx_keys: OptionalCell<Keys>,
+    crypto: Cell<CryptoState>,
+    rx_message: OptionalCell<RxMessage>,
+
+    /// The challenge sent in the last request, which the response must
+    /// carry.
+    challenge: Cell<[u8; 8]>,
+    next_challenge: OptionalCell<[u8; 8]>,
+    parent: OptionalCell<Parent>,
+    rloc16: OptionalCell<u16>,
+    leader_data: OptionalCell<LeaderData>,
+
+    crypt_buf: TakeCell<'static, [u8]>,
+    send_buf: TakeCell<'static, [u8]>,
+    hash_buf: TakeCell<'static, [u8]>,
+    digest_buf: TakeCell<'static, [u8; 32]>,
+    poll_buf: TakeCell<'static, [u8]>,
+
+    client: OptionalCell<&'a dyn MleClient>,
+}
+
+impl<'a, A: time::Alarm<'a>, C: AES128CCM<'a>, D: DigestDataHash<'a, 32>, T: IP6Sender<'a>>
+    MleNode<'a, A, C, D, T>
+{
+    pub fn new(
+        alarm: &'a A,
+        ccm: &'a C,
+        digest: &'a D,
+        udp_mux: &'a MuxUdpSender<'a, T>,
+        udp_sender: &'a dyn UDPSender<'a>,
+        mac: &'a dyn MacDevice<'a>,
+        security_table: &'a SecurityTable<'a>,
+        rng: &'a dyn Rng<'a>,
+        net_cap: &'static NetworkCapability,
+        crypt_buf: &'static mut [u8],
+        send_buf: &'static mut [u8],
+        hash_buf: &'static mut [u8],
+        digest_buf: &'static mut [u8; 32],
+        poll_buf: &'static mut [u8],
+    ) -> MleNode<'a, A, C, D, T> {
+        MleNode {
+            alarm: alarm,
+            ccm: ccm,
+            digest: digest,
+            udp_mux: udp_mux,
+            udp_sender: udp_sender,
+            mac: mac,
+            security_table: security_table,
+            rng: rng,
+            net_cap: net_cap,
+            state: Cell::new(MleState::Detached),
+            timer: Cell::new(0),
+            alarm_interval: Cell::new(0),
+            attach_backoff: Cell::new(ATTACH_BACKOFF_MIN_MS),
+            send_pending: Cell::new(false),
+            network_key: OptionalCell::empty(),
+            keys: OptionalCell::empty(),
+            rx_keys: OptionalCell::empty(),
+            crypto: Cell::new(CryptoState::Idle),
+            rx_message: OptionalCell::empty(),
+            challenge: Cell::new([0; 8]),
+            next_challenge: OptionalCell::empty(),
+            parent: OptionalCell::empty(),
+            rloc16: OptionalCell::empty(),
+            leader_data: OptionalCell::empty(),
+            crypt_buf: TakeCell::new(crypt_buf),
+            send_buf: TakeCell::new(send_buf),
+            hash_buf: TakeCell::new(hash_buf),
+            digest_buf: TakeCell::new(digest_buf),
+            poll_buf: TakeCell::new(poll_buf),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    /// The MAC key derived from the network key and its key sequence, for
+    /// securing MAC frames.
+    pub fn mac_key(&self) -> Option<([u8; 16], u32)> {
+        self.keys.map(|keys| (keys.mac_key, keys.key_sequence))
+    }
+
+    fn ext_addr(&self) -> [u8; 8] {
+        self.mac.get_address_long()
+    }
+
+    fn link_local_addr(&self) -> IPAddr {
+        IPAddr::generate_from_mac(MacAddress::Long(self.ext_addr()))
+    }
+
+    fn set_state(&self, state: MleState) {
+        let old_state = self.attach_state();
+        self.state.set(state);
+        let new_state = self.attach_state();
+        if new_state != old_state {
+            self.client
+                .map(|client| client.attach_state_changed(new_state));
+        }
+    }
+
+    // Timers
+
+    fn set_timer(&self, ms: u32) {
+        self.timer.set(ms);
+        self.arm_alarm();
+    }
+
+    fn arm_alarm(&self) {
+        let mut interval = cmp::min(self.timer.get(), MAX_ALARM_INTERVAL_MS);
+        if let Some(poll_period) = self.poll_period() {
+            interval = cmp::min(interval, poll_period);
+        }
+        self.alarm_interval.set(interval);
+        self.alarm
+            .set_alarm(self.alarm.now(), self.alarm.ticks_from_ms(interval));
+    }
+
+    /// How often the parent is polled for pending frames in the current
+    /// state, if at all.
+    fn poll_period(&self) -> Option<u32> {
+        match self.state.get() {
+            MleState::ChildIdRequest { .. } | MleState::ChildUpdateRequest { .. } => {
+                Some(FAST_POLL_PERIOD_MS)
+            }
+            MleState::Attached => Some(POLL_PERIOD_MS),
+            _ => None,
+        }
+    }
+
+    fn timer_expired(&self) {
+        match self.state.get() {
+            MleState::Detached => {}
+            MleState::ParentRequest { reeds } => {
+                if self.parent.is_some() {
+                    self.start_child_id_request();
+                } else if !reeds {
+                    self.start_parent_request(true);
+                } else {
+                    self.start_backoff();
+                }
+            }
+            MleState::ChildIdRequest { attempts } => {
+                if attempts < MAX_REQUEST_ATTEMPTS {
+                    self.state.set(MleState::ChildIdRequest {
+                        attempts: attempts + 1,
+                    });
+                    self.send_state_message();
+                    self.set_timer(CHILD_ID_RESPONSE_TIMEOUT_MS);
+                } else {
+                    self.parent.clear();
+                    self.start_backoff();
+                }
+            }
+            MleState::Attached => {
+                self.state.set(MleState::ChildUpdateRequest { attempts: 1 });
+                self.send_state_message();
+                self.set_timer(CHILD_UPDATE_RESPONSE_TIMEOUT_MS);
+            }
+            MleState::ChildUpdateRequest { attempts } => {
+                if attempts < MAX_REQUEST_ATTEMPTS {
+                    self.state.set(MleState::ChildUpdateRequest {
+                        attempts: attempts + 1,
+                    });
+                    self.send_state_message();
+                    self.set_timer(CHILD_UPDATE_RESPONSE_TIMEOUT_MS);
+                } else {
+                    self.reattach();
+                }
+            }
+            MleState::Backoff => self.start_parent_request(false),
+        }
+    }
+
+    // Attach process
+
+    fn start_parent_request(&self, reeds: bool) {
+        if !reeds {
+            self.parent.clear();
+        }
+        self.set_state(MleState::ParentRequest { reeds: reeds });
+        self.send_state_message();
+        self.set_timer(if reeds {
+            PARENT_REQUEST_REED_TIMEOUT_MS
+        } else {
+            PARENT_REQUEST_ROUTER_TIMEOUT_MS
+        });
+    }
+
+    fn start_child_id_request(&self) {
+        let parent = match self.parent.extract() {
+            Some(parent) => parent,
+            None => return self.start_backoff(),
+        };
+        // The secured frames of the parent are only accepted once it is in
+        // the device table
+        if self
+            .security_table
+            .add_device(parent.rloc16, parent.ext_addr)
+            .is_err()
+        {
+            self.parent.clear();
+            return self.start_backoff();
+        }
+        self.udp_mux.set_gateway(MacAddress::Long(parent.ext_addr));
+        self.state.set(MleState::ChildIdRequest { attempts: 1 });
+        self.send_state_message();
+        self.set_timer(CHILD_ID_RESPONSE_TIMEOUT_MS);
+    }
+
+    fn start_attached(&self) {
+        self.set_state(MleState::Attached);
+        self.update_link_security();
+        self.set_timer(CHILD_UPDATE_INTERVAL_MS);
+    }
+
+    fn start_backoff(&self) {
+        self.set_state(MleState::Backoff);
+        let backoff = self.attach_backoff.get();
+        self.attach_backoff
+            .set(cmp::min(backoff * 2, ATTACH_BACKOFF_MAX_MS));
+        self.set_timer(backoff);
+    }
+
+    /// Drops the parent and the RLOC16 it assigned.
+    fn leave_parent(&self) {
+        self.parent.clear();
+        self.udp_mux.set_link_security(None);
+        if self.rloc16.take().is_some() {
+            self.mac.set_address(NO_SHORT_ADDR);
+            self.mac.config_commit();
+        }
+    }
+
+    // MAC security
+
+    /// Makes sure the MAC key of the current key sequence is in the key
+    /// table, and returns the security MAC frames are sent with. Returns
+    /// `None` if the key cannot be added, for example until the table is
+    /// read from storage.
+    fn mac_security(&self) -> Option<(SecurityLevel, KeyId)> {
+        let keys = self.keys.extract()?;
+        let key_id = KeyId::Index(key_index(keys.key_sequence));
+        let table = self.security_table;
+        if table.lookup_key(FrameType::Data, MAC_SECURITY_LEVEL, key_id) != Some(keys.mac_key) {
+            // Drop the keys of other key sequences, which use key ID mode 1
+            // as well
+            let mut index = 0;
+            while let Some(key) = table.get_key(index) {
+                if key.level == MAC_SECURITY_LEVEL && matches!(key.key_id, KeyId::Index(_)) {
+                    let _ = table.remove_key(index);
+                } else {
+                    index += 1;
+                }
+            }
+            table
+                .add_key(KeyDescriptor {
+                    level: MAC_SECURITY_LEVEL,
+                    key_id: key_id,
+                    key: keys.mac_key,
+                    usage: KeyUsage::default(),
+                })
+                .ok()?;
+        }
+        Some((MAC_SECURITY_LEVEL, key_id))
+    }
+
+    /// Secures the data frames of the UDP stack with the current MAC key
+    /// while attached.
+    fn update_link_security(&self) {
+        if self.attach_state() == AttachState::Attached {
+            self.udp_mux.set_link_security(self.mac_security());
+        }
+    }
+
+    /// Attaches again after the parent was lost.
+    fn reattach(&self) {
+        self.leave_parent();
+        self.attach_backoff.set(ATTACH_BACKOFF_MIN_MS);
+        self.start_parent_request(false);
+    }
+
+    // Sending
+
+    /// Sends the request of the current state. If a resource is missing,
+    /// the request is sent once it becomes available.
+    fn send_state_message(&self) {
+        let result = match self.state.get() {
+            MleState::ParentRequest { reeds } => self.send_parent_request(reeds),
+            MleState::ChildIdRequest { .. } => self.send_child_id_request(),
+            MleState::ChildUpdateRequest { .. } => self.send_child_update_request(),
+            _ => Ok(()),
+        };
+        self.send_pending.set(result == Err(ErrorCode::BUSY));
+    }
+
+    fn retry_send(&self) {
+        if self.send_pending.get() {
+            self.send_state_message();
+        }
+    }
+
+    /// Returns a fresh challenge, and requests randomness for the next one.
+    fn take_challenge(&self) -> Result<[u8; 8], ErrorCode> {
+        let challenge = self.next_challenge.take();
+        let _ = self.rng.get();
+        challenge.ok_or(ErrorCode::BUSY).map(|challenge| {
+            self.challenge.set(challenge);
+            challenge
+        })
+    }
+
+    fn can_send(&self) -> Result<(), ErrorCode> {
+        if self.keys.is_none() {
+            Err(ErrorCode::INVAL)
+        } else if self.crypto.get() != CryptoState::Idle
+            || self.crypt_buf.is_none()
+            || self.send_buf.is_none()
+        {
+            Err(ErrorCode::BUSY)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn send_parent_request(&self, reeds: bool) -> Result<(), ErrorCode> {
+        self.can_send()?;
+        let challenge = self.take_challenge()?;
+        let scan_mask = if reeds {
+            MulticastResponder::Router as u8 | MulticastResponder::EndDevice as u8
+        } else {
+            MulticastResponder::Router as u8
+        };
+        self.send_message(
+            ALL_ROUTERS_ADDR,
+            command::PARENT_REQUEST,
+            &[
+                Tlv::Mode(LinkMode::SecureDataRequests as u8),
+                Tlv::Challenge(challenge),
+                Tlv::ScanMask(scan_mask),
+                Tlv::Version(THREAD_VERSION),
+            ],
+        )
+    }
+
+    fn send_child_id_request(&self) -> Result<(), ErrorCode> {
+        self.can_send()?;
+        let parent = self.parent.extract().ok_or(ErrorCode::FAIL)?;
+        // MLE messages and MAC frames share the outgoing frame counter
+        let frame_counter = self.security_table.frame_counter();
+        self.send_message(
+            IPAddr::generate_from_mac(MacAddress::Long(parent.ext_addr)),
+            command::CHILD_ID_REQUEST,
+            &[
+                Tlv::Response(parent.challenge),
+                Tlv::LinkLayerFrameCounter(frame_counter),
+                Tlv::MleFrameCounter(frame_counter),
+                Tlv::Mode(LinkMode::SecureDataRequests as u8),
+                Tlv::Timeout(CHILD_TIMEOUT),
+                Tlv::Version(THREAD_VERSION),
+                Tlv::TlvRequest(&[TlvType::Address16 as u8, TlvType::NetworkData as u8]),
+            ],
+        )
+    }
+
+    fn send_child_update_request(&self) -> Result<(), ErrorCode> {
+        self.can_send()?;
+        let parent = self.parent.extract().ok_or(ErrorCode::FAIL)?;
+        let leader_data = self.leader_data.extract().ok_or(ErrorCode::FAIL)?;
+        let challenge = self.take_challenge()?;
+        self.send_message(
+            IPAddr::generate_from_mac(MacAddress::Long(parent.ext_addr)),
+            command::CHILD_UPDATE_REQUEST,
+            &[
+                Tlv::Mode(LinkMode::SecureDataRequests as u8),
+                Tlv::Challenge(challenge),
+                Tlv::Timeout(CHILD_TIMEOUT),
+                Tlv::LeaderData {
+                    partition_id: leader_data.partition_id,
+                    weighting: leader_data.weighting,
+                    data_version: leader_data.data_version,
+                    stable_data_version: leader_data.stable_data_version,
+                    leader_router_id: leader_data.leader_router_id,
+                },
+            ],
+        )
+    }
+
+    /// Encodes the command and its TLVs after the authenticated header and
+    /// starts encrypting them. The message is sent once it is encrypted.
+    fn send_message(&self, dst_addr: IPAddr, command: u8, tlvs: &[Tlv]) -> Result<(), ErrorCode> {
+        self.can_send()?;
+        let keys = self.keys.extract().ok_or(ErrorCode::INVAL)?;
+        // No frame counter is available until the security table is read
+        // from storage, or while its next block of counters is reserved
+        let frame_counter = self
+            .security_table
+            .next_frame_counter()
+            .ok_or(ErrorCode::BUSY)?;
+        let buf = self.crypt_buf.take().ok_or(ErrorCode::BUSY)?;
+
+        buf[0..16].copy_from_slice(&self.link_local_addr().0);
+        buf[16..32].copy_from_slice(&dst_addr.0);
+        self.encode_aux_header(&mut buf[32..AUTH_LEN], frame_counter, keys.key_sequence);
+
+        let tlvs_end = buf.len() - MIC_LEN;
+        buf[AUTH_LEN] = command;
+        let mut offset = AUTH_LEN + 1;
+        for tlv in tlvs {
+            match tlv.encode(&mut buf[offset..tlvs_end]).done() {
+                Some((new_offset, _)) => offset += new_offset,
+                None => {
+                    self.crypt_buf.replace(buf);
+                    return Err(ErrorCode::SIZE);
+                }
+            }
+        }
+        let len = offset - AUTH_LEN;
+
+        let nonce = ccm_nonce(&self.ext_addr(), frame_counter);
+        let _ = self.ccm.set_key(&keys.mle_key);
+        let _ = self.ccm.set_nonce(&nonce);
+        match self.ccm.crypt(buf, 0, AUTH_LEN, len, MIC_LEN, true, true) {
+            Ok(()) => {
+                self.crypto.set(CryptoState::Encrypting {
+                    dst_addr: dst_addr,
+                    len: len,
+                });
+                Ok(())
+            }
+            Err((ecode, buf)) => {
+                self.crypt_buf.replace(buf);
+                Err(ecode)
+            }
+        }
+    }
+
+    fn encode_aux_header(&self, buf: &mut [u8], frame_counter: u32, key_sequence: u32) {
+        buf[0] = SECURITY_CONTROL;
+        buf[1..5].copy_from_slice(&frame_counter.to_le_bytes());
+        // The key source is the key sequence
+        buf[5..9].copy_from_slice(&key_sequence.to_be_bytes());
+        buf[9] = key_index(key_sequence);
+    }
+
+    /// Sends an encrypted message, which is in `buf` after the source and
+    /// destination addresses.
+    fn send_encrypted(&self, buf: &[u8], dst_addr: IPAddr, len: usize) {
+        let secured_len = AUX_HEADER_LEN + len + MIC_LEN;
+        self.send_buf.take().map(|send_buf| {
+            send_buf[0] = SECURITY_SUITE_154;
+            send_buf[1..1 + secured_len].copy_from_slice(&buf[32..32 + secured_len]);
+            let mut payload = LeasableMutableBuffer::new(send_buf);
+            payload.slice(..1 + secured_len);
+            if let Err(payload) = self
+                .udp_sender
+                .send_to(dst_addr, MLE_PORT, payload, self.net_cap)
+            {
+                self.send_buf.replace(payload.take());
+            }
+        });
+    }
+
+    /// Polls the parent for pending frames with a MAC Data Request.
+    fn send_data_request(&self) {
+        let parent = match self.parent.extract() {
+            Some(parent) => parent,
+            None => return,
+        };
+        // The node announces in its mode that its Data Requests are secured
+        let security = match self.mac_security() {
+            Some(security) => security,
+            None => return,
+        };
+        self.poll_buf.take().map(|buf| {
+            let pan = self.mac.get_pan();
+            match self.mac.prepare_command_frame(
+                buf,
+                pan,
+                MacAddress::Long(parent.ext_addr),
+                pan,
+                MacAddress::Long(self.ext_addr()),
+                Some(security),
+            ) {
+                Ok(mut frame) => {
+                    if frame.append_payload(&[DATA_REQUEST_COMMAND]).is_err() {
+                        self.poll_buf.replace(frame.into_buf());
+                        return;
+                    }
+                    if let Err((_, buf)) = self.mac.transmit(frame) {
+                        self.poll_buf.replace(buf);
+                    }
+                }
+                Err(buf) => {
+                    self.poll_buf.replace(buf);
+                }
+            }
+        });
+    }
+
+    // Receiving
+
+    /// Decrypts the received message in `crypt_buf` with the keys for its
+    /// key sequence.
+    fn start_decrypt(&self, keys: &Keys) {
+        let rx = match self.rx_message.extract() {
+            Some(rx) => rx,
+            None => return,
+        };
+        let buf = match self.crypt_buf.take() {
+            Some(buf) => buf,
+            None => return,
+        };
+        let nonce = ccm_nonce(&ext_addr_from_link_local(&rx.src_addr), rx.frame_counter);
+        let _ = self.ccm.set_key(&keys.mle_key);
+        let _ = self.ccm.set_nonce(&nonce);
+        match self
+            .ccm
+            .crypt(buf, 0, AUTH_LEN, rx.len, MIC_LEN, true, false)
+        {
+            Ok(()) => self.crypto.set(CryptoState::Decrypting),
+            Err((_, buf)) => {
+                self.crypt_buf.replace(buf);
+                self.rx_message.clear();
+                self.rx_keys.clear();
+            }
+        }
+    }
+
+    /// Handles a decrypted message, returning whether it was accepted.
+    fn handle_message(&self, message: &[u8], rx: &RxMessage) -> bool {
+        if message.is_empty() {
+            return false;
+        }
+        let tlvs = MessageTlvs::parse(&message[1..]);
+        match message[0] {
+            command::PARENT_RESPONSE => self.handle_parent_response(rx, &tlvs),
+            command::CHILD_ID_RESPONSE => self.handle_child_id_response(rx, &tlvs),
+            command::CHILD_UPDATE_RESPONSE => self.handle_child_update_response(rx, &tlvs),
+            _ => false,
+        }
+    }
+
+    fn handle_parent_response(&self, rx: &RxMessage, tlvs: &MessageTlvs) -> bool {
+        match self.state.get() {
+            MleState::ParentRequest { .. } => {}
+            _ => return false,
+        }
+        if tlvs.response != Some(self.challenge.get()) {
+            return false;
+        }
+        let candidate = match (
+            tlvs.source_address,
+            tlvs.challenge,
+            tlvs.link_margin,
+            tlvs.connectivity,
+            tlvs.leader_data,
+        ) {
+            (
+                Some(rloc16),
+                Some(challenge),
+                Some(link_margin),
+                Some((priority, link_quality_3)),
+                Some(leader_data),
+            ) => Parent {
+                ext_addr: ext_addr_from_link_local(&rx.src_addr),
+                rloc16: rloc16,
+                challenge: challenge,
+                link_quality: link_quality(link_margin),
+                priority: priority,
+                link_quality_3: link_quality_3,
+                frame_counter: rx.frame_counter,
+                leader_data: leader_data,
+            },
+            _ => return false,
+        };
+        let better = self
+            .parent
+            .map_or(true, |parent| candidate.is_better_than(parent));
+        if better {
+            self.parent.set(candidate);
+        }
+        true
+    }
+
+    /// Checks that a message comes from the parent and is not replayed, and
+    /// records its frame counter.
+    fn check_parent_message(&self, rx: &RxMessage) -> Option<Parent> {
+        let mut parent = self.parent.extract()?;
+        if ext_addr_from_link_local(&rx.src_addr) != parent.ext_addr
+            || rx.frame_counter <= parent.frame_counter
+        {
+            return None;
+        }
+        parent.frame_counter = rx.frame_counter;
+        self.parent.set(parent);
+        Some(parent)
+    }
+
+    fn handle_child_id_response(&self, rx: &RxMessage, tlvs: &MessageTlvs) -> bool {
+        match self.state.get() {
+            MleState::ChildIdRequest { .. } => {}
+            _ => return false,
+        }
+        let parent = match self.check_parent_message(rx) {
+            Some(parent) => parent,
+            None => return false,
+        };
+        if tlvs.source_address != Some(parent.rloc16) {
+            return false;
+        }
+        let rloc16 = match tlvs.address16 {
+            Some(rloc16) if is_child_rloc16(rloc16, parent.rloc16) => rloc16,
+            _ => return false,
+        };
+
+        self.rloc16.set(rloc16);
+        self.leader_data
+            .set(tlvs.leader_data.unwrap_or(parent.leader_data));
+        self.mac.set_address(rloc16);
+        self.mac.config_commit();
+        self.attach_backoff.set(ATTACH_BACKOFF_MIN_MS);
+        self.start_attached();
+        true
+    }
+
+    fn handle_child_update_response(&self, rx: &RxMessage, tlvs: &MessageTlvs) -> bool {
+        match self.state.get() {
+            MleState::ChildUpdateRequest { .. } => {}
+            _ => return false,
+        }
+        if self.check_parent_message(rx).is_none() {
+            return false;
+        }
+        if tlvs.status.is_some() {
+            // The parent no longer has the node as its child
+            self.reattach();
+            return true;
+        }
+        if tlvs.response != Some(self.challenge.get()) {
+            return false;
+        }
+        tlvs.leader_data
+            .map(|leader_data| self.leader_data.set(leader_data));
+        self.start_attached();
+        true
+    }
+
+    // Key derivation
+
+    /// Starts deriving the MLE and MAC keys for a key sequence, hashing the
+    /// inner part of the HMAC first.
+    fn start_key_derivation(&self, key_sequence: u32) -> Result<(), ErrorCode> {
+        let buf = self.hash_buf.take().ok_or(ErrorCode::BUSY)?;
+        if !self.fill_hmac_block(buf, HMAC_IPAD) {
+            self.hash_buf.replace(buf);
+            return Err(ErrorCode::INVAL);
+        }
+        let seq_end = HMAC_BLOCK_LEN + 4;
+        buf[HMAC_BLOCK_LEN..seq_end].copy_from_slice(&key_sequence.to_be_bytes());
+        buf[seq_end..seq_end + KEY_DERIVATION_STRING.len()].copy_from_slice(KEY_DERIVATION_STRING);
+        let mut data = LeasableMutableBuffer::new(buf);
+        data.slice(..seq_end + KEY_DERIVATION_STRING.len());
+        match self.digest.add_mut_data(data) {
+            Ok(()) => {
+                self.crypto.set(CryptoState::DeriveInner {
+                    key_sequence: key_sequence,
+                });
+                Ok(())
+            }
+            Err((ecode, data)) => {
+                self.hash_buf.replace(data.take());
+                Err(ecode)
+            }
+        }
+    }
+
+    /// Writes the network key padded to a block and XORed with `pad` to the
+    /// start of `buf`.
+    fn fill_hmac_block(&self, buf: &mut [u8], pad: u8) -> bool {
+        self.network_key
+            .map(|key| {
+                for (i, b) in buf[..HMAC_BLOCK_LEN].iter_mut().enumerate() {
+                    *b = key.get(i).map_or(0, |k| *k) ^ pad;
+                }
+            })
+            .is_some()
+    }
+
+    fn keys_derived(&self, keys: Keys) {
+        if self.rx_message.is_some() {
+            // The keys of a newer key sequence are only used for the
+            // received message until it is accepted
+            self.rx_keys.set(keys);
+            self.start_decrypt(&keys);
+        } else {
+            self.keys.set(keys);
+        }
+        if self.crypto.get() != CryptoState::Decrypting {
+            self.crypto_idle();
+        }
+    }
+
+    fn derivation_failed(&self) {
+        self.rx_message.clear();
+        self.crypto_idle();
+    }
+
+    fn crypto_idle(&self) {
+        self.crypto.set(CryptoState::Idle);
+        self.retry_send();
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, C: AES128CCM<'a>, D: DigestDataHash<'a, 32>, T: IP6Sender<'a>> Mle<'a>
+    for MleNode<'a, A, C, D, T>
+{
+    fn set_client(&self, client: &'a dyn MleClient) {
+        self.client.set(client);
+    }
+
+    fn set_network_key(&self, key: [u8; 16], key_sequence: u32) -> Result<(), ErrorCode> {
+        if self.state.get() != MleState::Detached || self.crypto.get() != CryptoState::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        self.network_key.set(key);
+        self.keys.clear();
+        self.rx_keys.clear();
+        // Ask for the first challenge early, so that attaching does not have
+        // to wait for it
+        if self.next_challenge.is_none() {
+            let _ = self.rng.get();
+        }
+        self.start_key_derivation(key_sequence)
+    }
+
+    fn attach(&self) -> Result<(), ErrorCode> {
+        if self.state.get() != MleState::Detached {
+            return Err(ErrorCode::ALREADY);
+        }
+        if !self.security_table.is_persistent() {
+            return Err(ErrorCode::NOSUPPORT);
+        }
+        if self.keys.is_none() {
+            return match self.crypto.get() {
+                CryptoState::DeriveInner { .. } | CryptoState::DeriveOuter { .. } => {
+                    Err(ErrorCode::BUSY)
+                }
+                _ => Err(ErrorCode::INVAL),
+            };
+        }
+        self.attach_backoff.set(ATTACH_BACKOFF_MIN_MS);
+        self.start_parent_request(false);
+        Ok(())
+    }
+
+    fn detach(&self) -> Result<(), ErrorCode> {
+        if self.state.get() == MleState::Detached {
+            return Err(ErrorCode::ALREADY);
+        }
+        let _ = self.alarm.disarm();
+        self.send_pending.set(false);
+        self.leave_parent();
+        self.leader_data.clear();
+        self.set_state(MleState::Detached);
+        Ok(())
+    }
+
+    fn attach_state(&self) -> AttachState {
+        match self.state.get() {
+            MleState::Detached => AttachState::Detached,
+            MleState::Attached | MleState::ChildUpdateRequest { .. } => AttachState::Attached,
+            _ => AttachState::Attaching,
+        }
+    }
+
+    fn rloc16(&self) -> Option<u16> {
+        self.rloc16.extract()
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, C: AES128CCM<'a>, D: DigestDataHash<'a, 32>, T: IP6Sender<'a>>
+    time::AlarmClient for MleNode<'a, A, C, D, T>
+{
+    fn alarm(&self) {
+        self.timer
+            .set(self.timer.get().saturating_sub(self.alarm_interval.get()));
+        if self.poll_period().is_some() {
+            self.send_data_request();
+        }
+        if self.timer.get() > 0 {
+            self.arm_alarm();
+        } else {
+            self.timer_expired();
+        }
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, C: AES128CCM<'a>, D: DigestDataHash<'a, 32>, T: IP6Sender<'a>>
+    UDPRecvClient for MleNode<'a, A, C, D, T>
+{
+    fn receive(
+        &self,
+        src_addr: IPAddr,
+        dst_addr: IPAddr,
+        src_port: u16,
+        _dst_port: u16,
+        payload: &[u8],
+    ) {
+        // MLE messages only come from neighbors, and are only of interest
+        // while attaching or attached
+        if src_port != MLE_PORT
+            || !src_addr.is_unicast_link_local()
+            || self.state.get() == MleState::Detached
+        {
+            return;
+        }
+        let secured = &payload[cmp::min(1, payload.len())..];
+        if payload.first() != Some(&SECURITY_SUITE_154)
+            || secured.len() < AUX_HEADER_LEN + 1 + MIC_LEN
+            || secured[0] != SECURITY_CONTROL
+        {
+            return;
+        }
+        let len = secured.len() - AUX_HEADER_LEN - MIC_LEN;
+        if len > MAX_MESSAGE_LEN || self.crypto.get() != CryptoState::Idle {
+            return;
+        }
+        let keys = match self.keys.extract() {
+            Some(keys) => keys,
+            None => return,
+        };
+        let mut frame_counter = [0; 4];
+        frame_counter.copy_from_slice(&secured[1..5]);
+        let mut key_sequence = [0; 4];
+        key_sequence.copy_from_slice(&secured[5..9]);
+        let key_sequence = u32::from_be_bytes(key_sequence);
+        if key_sequence < keys.key_sequence {
+            return;
+        }
+
+        match self.crypt_buf.take() {
+            Some(buf) => {
+                buf[0..16].copy_from_slice(&src_addr.0);
+                buf[16..32].copy_from_slice(&dst_addr.0);
+                buf[32..32 + secured.len()].copy_from_slice(secured);
+                self.crypt_buf.replace(buf);
+            }
+            None => return,
+        }
+        self.rx_message.set(RxMessage {
+            src_addr: src_addr,
+            frame_counter: u32::from_le_bytes(frame_counter),
+            key_sequence: key_sequence,
+            len: len,
+        });
+        if key_sequence == keys.key_sequence {
+            self.start_decrypt(&keys);
+        } else if self.start_key_derivation(key_sequence).is_err() {
+            self.rx_message.clear();
+        }
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, C: AES128CCM<'a>, D: DigestDataHash<'a, 32>, T: IP6Sender<'a>>
+    CCMClient for MleNode<'a, A, C, D, T>
+{
+    fn crypt_done(&self, buf: &'static mut [u8], res: Result<(), ErrorCode>, tag_is_valid: bool) {
+        match self.crypto.get() {
+            CryptoState::Encrypting { dst_addr, len } => {
+                if res.is_ok() {
+                    self.send_encrypted(buf, dst_addr, len);
+                }
+            }
+            CryptoState::Decrypting => {
+                let rx_keys = self.rx_keys.take();
+                self.rx_message.take().map(|rx| {
+                    if res.is_ok()
+                        && tag_is_valid
+                        && self.handle_message(&buf[AUTH_LEN..AUTH_LEN + rx.len], &rx)
+                    {
+                        rx_keys.map(|keys| {
+                            if keys.key_sequence == rx.key_sequence {
+                                self.keys.set(keys);
+                                self.update_link_security();
+                            }
+                        });
+                    }
+                });
+            }
+            _ => {}
+        }
+        self.crypt_buf.replace(buf);
+        self.crypto_idle();
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, C: AES128CCM<'a>, D: DigestDataHash<'a, 32>, T: IP6Sender<'a>>
+    digest::ClientData<32> for MleNode<'a, A, C, D, T>
+{
+    fn add_data_done(
+        &self,
+        _result: Result<(), ErrorCode>,
+        _data: kernel::utilities::leasable_buffer::LeasableBuffer<'static, u8>,
+    ) {
+    }
+
+    fn add_mut_data_done(
+        &self,
+        result: Result<(), ErrorCode>,
+        data: LeasableMutableBuffer<'static, u8>,
+    ) {
+        self.hash_buf.replace(data.take());
+        if result.is_err() {
+            self.derivation_failed();
+            return;
+        }
+        match self.digest_buf.take() {
+            Some(digest) => {
+                if let Err((_, digest)) = self.digest.run(digest) {
+                    self.digest_buf.replace(digest);
+                    self.derivation_failed();
+                }
+            }
+            None => self.derivation_failed(),
+        }
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, C: AES128CCM<'a>, D: DigestDataHash<'a, 32>, T: IP6Sender<'a>>
+    digest::ClientHash<32> for MleNode<'a, A, C, D, T>
+{
+    fn hash_done(&self, result: Result<(), ErrorCode>, digest: &'static mut [u8; 32]) {
+        if result.is_err() {
+            self.digest_buf.replace(digest);
+            self.derivation_failed();
+            return;
+        }
+        match self.crypto.get() {
+            CryptoState::DeriveInner { key_sequence } => {
+                // The outer part of the HMAC hashes the key block and the
+                // inner hash
+                let result = self.hash_buf.take().map_or(Err(ErrorCode::BUSY), |buf| {
+                    self.fill_hmac_block(buf, HMAC_OPAD);
+                    buf[HMAC_BLOCK_LEN..HASH_BUF_LEN].copy_from_slice(&digest[..]);
+                    let mut data = LeasableMutableBuffer::new(buf);
+                    data.slice(..HASH_BUF_LEN);
+                    self.digest.add_mut_data(data).map_err(|(ecode, data)| {
+                        self.hash_buf.replace(data.take());
+                        ecode
+                    })
+                });
+                self.digest_buf.replace(digest);
+                match result {
+                    Ok(()) => self.crypto.set(CryptoState::DeriveOuter {
+                        key_sequence: key_sequence,
+                    }),
+                    Err(_) => self.derivation_failed(),
+                }
+            }
+            CryptoState::DeriveOuter { key_sequence } => {
+                let mut keys = Keys {
+                    key_sequence: key_sequence,
+                    mle_key: [0; 16],
+                    mac_key: [0; 16],
+                };
+                keys.mle_key.copy_from_slice(&digest[0..16]);
+                keys.mac_key.copy_from_slice(&digest[16..32]);
+                // Do not leave key material behind in the buffers
+                digest.iter_mut().for_each(|b| *b = 0);
+                self.digest_buf.replace(digest);
+                self.hash_buf.map(|buf| buf.iter_mut().for_each(|b| *b = 0));
+                self.keys_derived(keys);
+            }
+            _ => {
+                self.digest_buf.replace(digest);
+            }
+        }
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, C: AES128CCM<'a>, D: DigestDataHash<'a, 32>, T: IP6Sender<'a>>
+    digest::ClientVerify<32> for MleNode<'a, A, C, D, T>
+{
+    fn verification_done(&self, _result: Result<bool, ErrorCode>, _compare: &'static mut [u8; 32]) {
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, C: AES128CCM<'a>, D: DigestDataHash<'a, 32>, T: IP6Sender<'a>>
+    UDPSendClient for MleNode<'a, A, C, D, T>
+{
+    fn send_done(&self, _result: Result<(), ErrorCode>, dgram: LeasableMutableBuffer<'static, u8>) {
+        self.send_buf.replace(dgram.take());
+        self.retry_send();
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, C: AES128CCM<'a>, D: DigestDataHash<'a, 32>, T: IP6Sender<'a>> TxClient
+    for MleNode<'a, A, C, D, T>
+{
+    fn send_done(&self, spi_buf: &'static mut [u8], _acked: bool, _result: Result<(), ErrorCode>) {
+        self.poll_buf.replace(spi_buf);
+    }
+}
+
+impl<'a, A: time::Alarm<'a>, C: AES128CCM<'a>, D: DigestDataHash<'a, 32>, T: IP6Sender<'a>>
+    rng::Client for MleNode<'a, A, C, D, T>
+{
+    fn randomness_available(
+        &self,
+        randomness: &mut dyn Iterator<Item = u32>,
+        error: Result<(), ErrorCode>,
+    ) -> rng::Continue {
+        if error.is_err() {
+            return rng::Continue::Done;
+        }
+        match (randomness.next(), randomness.next()) {
+            (Some(high), Some(low)) => {
+                let mut challenge = [0; 8];
+                challenge[0..4].copy_from_slice(&high.to_be_bytes());
+                challenge[4..8].copy_from_slice(&low.to_be_bytes());
+                self.next_challenge.set(challenge);
+                self.retry_send();
+                rng::Continue::Done
+            }
+            _ => rng::Continue::More,
+        }
+    }
+}
@@ -1 +1,6 @@
+pub mod driver;
+pub mod mle;
 pub mod tlv;
+
+pub use self::driver::ThreadDriver;
+pub use self::driver::DRIVER_NUM;
@@ -15,8 +15,9 @@
 //! the userspace driver must queue app packets on its own, as it can only pass a single
 //! packet to the MuxUdpSender queue at a time.
 
+use crate::net::ieee802154::{KeyId, MacAddress, SecurityLevel};
 use crate::net::ipv6::ip_utils::IPAddr;
-use crate::net::ipv6::ipv6_send::{IP6SendClient, IP6Sender};
+use crate::net::ipv6::ipv6_send::{IP6SendClient, IP6Sender, NextHopResolver};
 use crate::net::ipv6::TransportHeader;
 use crate::net::network_capabilities::{NetworkCapability, UdpVisibilityCapability};
 use crate::net::udp::udp_port_table::UdpPortBindingTx;
@@ -34,6 +35,7 @@ use kernel::ErrorCode;
 pub struct MuxUdpSender<'a, T: IP6Sender<'a>> {
     sender_list: List<'a, UDPSendStruct<'a, T>>,
     ip_sender: &'a dyn IP6Sender<'a>,
+    link_security: Cell<Option<(SecurityLevel, KeyId)>>,
 }
 
 impl<'a, T: IP6Sender<'a>> MuxUdpSender<'a, T> {
@@ -42,9 +44,38 @@ impl<'a, T: IP6Sender<'a>> MuxUdpSender<'a, T> {
         MuxUdpSender {
             sender_list: List::new(),
             ip_sender: ip6_sender,
+            link_security: Cell::new(None),
         }
     }
 
+    /// Sets the link-layer address that unicast packets of all senders are
+    /// sent to, such as the parent of a Thread end device.
+    pub fn set_gateway(&self, gateway: MacAddress) {
+        self.ip_sender.set_gateway(gateway);
+    }
+
+    /// Sets the resolver that picks the link-layer address unicast packets of
+    /// all senders are sent to, such as a routing protocol.
+    pub fn set_next_hop_resolver(&self, resolver: &'a dyn NextHopResolver) {
+        self.ip_sender.set_next_hop_resolver(resolver);
+    }
+
+    /// Sets the link-layer security of the packets of all senders that have
+    /// not disabled it, such as the MAC key of a Thread network.
+    pub fn set_link_security(&self, security: Option<(SecurityLevel, KeyId)>) {
+        self.link_security.set(security);
+    }
+
+    /// Applies the link-layer security of `sender` to the next packet.
+    fn set_sender_security(&self, sender: &UDPSendStruct<'a, T>) {
+        let security = if sender.link_security.get() {
+            self.link_security.get()
+        } else {
+            None
+        };
+        self.ip_sender.set_link_security(security);
+    }
+
     fn send_to(
         &self,
         dest: IPAddr,
@@ -61,6 +92,7 @@ impl<'a, T: IP6Sender<'a>> MuxUdpSender<'a, T> {
         if list_empty {
             ret = match caller.tx_buffer.take() {
                 Some(buf) => {
+                    self.set_sender_security(caller);
                     let ret = self
                         .ip_sender
                         .send_to(dest, transport_header, &buf, net_cap);
@@ -112,6 +144,7 @@ impl<'a, T: IP6Sender<'a>> IP6SendClient for MuxUdpSender<'a, T> {
                     Some(buf) => match next_sender.next_th.take() {
                         Some(th) => match next_sender.net_cap.take() {
                             Some(net_cap) => {
+                                self.set_sender_security(next_sender);
                                 let ret = self.ip_sender.send_to(
                                     next_sender.next_dest.get(),
                                     th,
@@ -249,6 +282,8 @@ pub struct UDPSendStruct<'a, T: IP6Sender<'a>> {
     binding: MapCell<UdpPortBindingTx>,
     udp_vis: &'static UdpVisibilityCapability,
     net_cap: OptionalCell<&'static NetworkCapability>,
+    /// Whether the packets use the link-layer security of the mux.
+    link_security: Cell<bool>,
 }
 
 impl<'a, T: IP6Sender<'a>> ListNode<'a, UDPSendStruct<'a, T>> for UDPSendStruct<'a, T> {
@@ -358,6 +393,13 @@ impl<'a, T: IP6Sender<'a>> UDPSendStruct<'a, T> {
             binding: MapCell::empty(),
             udp_vis: udp_vis,
             net_cap: OptionalCell::empty(),
+            link_security: Cell::new(true),
         }
     }
+
+    /// Sends the packets of this sender without link-layer security, for
+    /// protocols that secure their messages themselves.
+    pub fn disable_link_security(&self) {
+        self.link_security.set(false);
+    }
 }
@@ -17,6 +17,11 @@
 //!  - 'panic' causes the kernel to run the panic handler
 //!  - 'process n' prints the memory map of process with name n
 //!  - 'kernel' prints the kernel memory map
+//!  - 'faults' prints the recorded process faults, if the board records them
+//!  - 'limits' prints the state of the syscall limits of each process, if the
+//!    board enforces them
+//!  - 'grants n' prints the grants of the process with name n and the memory
+//!    they use
 //!
 //! ### `list` Command Fields:
 //!
@@ -26,12 +31,58 @@
 //! - `Quanta`: How many times this process has exceeded its allotted time
 //!   quanta.
 //! - `Syscalls`: The number of system calls the process has made to the kernel.
+//! - `Upcalls`: The number of upcalls from capsules the process has executed.
+//! - `CPU(ms)`: The CPU time the process has used since it was last started.
+//!   This is only measured if the chip provides a scheduler timer.
 //! - `Restarts`: How many times this process has crashed and been restarted by
 //!   the kernel.
 //! - `Grants`: The number of grants that have been initialized for the process
 //!   out of the total number of grants defined by the kernel.
 //! - `State`: The state the process is in.
 //!
+//! ### `faults` Command Fields:
+//!
+//! The `faults` command lists the faults recorded by a
+//! `kernel::process::FaultHistory`, oldest first, including the faults from
+//! before the last reset. The board must pass the fault history to
+//! `ProcessConsole::set_fault_history()`.
+//!
+//! - `Name`: The process name, possibly truncated.
+//! - `Restarts`: How many times the process had been restarted before the
+//!   fault.
+//! - `PC`: The program counter of the process when it faulted.
+//! - `Address`: The address whose access caused the fault, if known.
+//! - `Code`: The completion code the process last exited with, if any.
+//! - `Action`: What the fault policy did with the process.
+//!
+//! ### `limits` Command Fields:
+//!
+//! The `limits` command lists the syscall limits from the TBF headers of the
+//! processes that have called a limited driver, as enforced by a
+//! `kernel::platform::TbfHeaderFilterRateLimit`. The board must pass the
+//! filter to `ProcessConsole::set_syscall_limits()`.
+//!
+//! - `Name`: The process name.
+//! - `Driver`: The driver number the limit applies to.
+//! - `Cmd`: The command number the limit applies to, or `*` for all commands.
+//! - `Rate`: The maximum number of calls in each period, if rate limited.
+//! - `Quota`: The maximum number of calls in total, if limited.
+//! - `Calls`: The number of calls counted since the application was loaded,
+//!   including calls made before the process restarted.
+//! - `Rejected`: The number of calls the limit rejected.
+//!
+//! ### `grants` Command Fields:
+//!
+//! The `grants` command lists the grants the process has allocated, and the
+//! total number of bytes of its grant region they use.
+//!
+//! - `Grant`: The grant number.
+//! - `Driver`: The driver number of the capsule the grant belongs to.
+//! - `Bytes`: The memory the grant uses, including padding for alignment.
+//! - `State`: `released` if the capsule released the grant, but its memory
+//!   has not been returned to the process yet because other grants were
+//!   allocated after it.
+//!
 //! Setup
 //! -----
 //!
@@ -85,9 +136,9 @@
 //! Initialization complete. Entering main loop
 //! Hello World!
 //! list
-//! PID    Name    Quanta  Syscalls  Restarts Grants  State
-//! 00     blink        0       113         0  1/12   Yielded
-//! 01     c_hello      0         8         0  3/12   Yielded
+//! PID    Name    Quanta  Syscalls  Upcalls  CPU(ms)  Restarts Grants  State
+//! 00     blink        0       113       56       12         0  1/12   Yielded
+//! 01     c_hello      0         8        1        0         0  3/12   Yielded
 //! ```
 //!
 //! To get a general view of the system, use the status command:
@@ -97,6 +148,9 @@
 //! Total processes: 2
 //! Active processes: 2
 //! Timeslice expirations: 0
+//! CPU time: 12 ms
+//! Syscalls: Yield 57, Subscribe 4, Command 50, ReadWriteAllow 6, ...
+//! Sleep states: 0 entered 41, 3 ms; 1 entered 12, 980 ms
 //! ```
 //!
 //! and you can control processes with the `start` and `stop` commands:
@@ -113,14 +167,17 @@ use core::fmt::write;
 use core::str;
 use kernel::capabilities::ProcessManagementCapability;
 use kernel::hil::time::ConvertTicks;
-use kernel::utilities::cells::TakeCell;
+use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::ProcessId;
 
 use kernel::debug;
 use kernel::hil::time::{Alarm, AlarmClient};
 use kernel::hil::uart;
 use kernel::introspection::KernelInfo;
-use kernel::process::{ProcessPrinter, ProcessPrinterContext};
+use kernel::platform::power::MAX_SLEEP_STATES;
+use kernel::platform::rate_limit::SyscallLimitReport;
+use kernel::process::{FaultAction, ProcessFaultHistory, ProcessPrinter, ProcessPrinterContext};
+use kernel::syscall::SyscallClass;
 use kernel::utilities::binary_write::BinaryWrite;
 use kernel::ErrorCode;
 use kernel::Kernel;
@@ -140,7 +197,7 @@ pub static mut COMMAND_BUF: [u8; 32] = [0; 32];
 /// List of valid commands for printing help. Consolidated as these are
 /// displayed in a few different cases.
 const VALID_COMMANDS_STR: &[u8] =
-    b"help status list stop start fault boot terminate process kernel panic\r\n";
+    b"help status list stop start fault boot terminate process kernel faults limits grants panic\r\n";
 
 /// States used for state machine to allow printing large strings asynchronously
 /// across multiple calls. This reduces the size of the buffer needed to print
@@ -162,6 +219,19 @@ enum WriterState {
         index: isize,
         total: isize,
     },
+    Faults {
+        index: isize,
+        total: isize,
+    },
+    Limits {
+        index: isize,
+        total: isize,
+    },
+    Grants {
+        process_id: ProcessId,
+        index: isize,
+        total: isize,
+    },
 }
 
 impl Default for WriterState {
@@ -219,6 +289,12 @@ pub struct ProcessConsole<'a, A: Alarm<'a>, C: ProcessManagementCapability> {
     /// Memory addresses of where the kernel is placed in memory on chip.
     kernel_addresses: KernelAddresses,
 
+    /// Recorded process faults, if the board records them.
+    fault_history: OptionalCell<&'a dyn ProcessFaultHistory>,
+
+    /// Syscall limits of processes, if the board enforces them.
+    syscall_limits: OptionalCell<&'a dyn SyscallLimitReport>,
+
     /// This capsule needs to use potentially dangerous APIs related to
     /// processes, and requires a capability to access those APIs.
     capability: C,
@@ -292,10 +368,24 @@ impl<'a, A: Alarm<'a>, C: ProcessManagementCapability> ProcessConsole<'a, A, C>
             execute: Cell::new(false),
             kernel: kernel,
             kernel_addresses: kernel_addresses,
+            fault_history: OptionalCell::empty(),
+            syscall_limits: OptionalCell::empty(),
             capability: capability,
         }
     }
 
+    /// Enable the `faults` command, which prints the faults recorded in
+    /// `fault_history`.
+    pub fn set_fault_history(&self, fault_history: &'a dyn ProcessFaultHistory) {
+        self.fault_history.set(fault_history);
+    }
+
+    /// Enable the `limits` command, which prints the state of the syscall
+    /// limits in `syscall_limits`.
+    pub fn set_syscall_limits(&self, syscall_limits: &'a dyn SyscallLimitReport) {
+        self.syscall_limits.set(syscall_limits);
+    }
+
     /// Start the process console listening for user commands.
     pub fn start(&self) -> Result<(), ErrorCode> {
         if self.running.get() == false {
@@ -366,6 +456,47 @@ impl<'a, A: Alarm<'a>, C: ProcessManagementCapability> ProcessConsole<'a, A, C>
                     }
                 }
             }
+            WriterState::Faults { index, total } => {
+                if index + 1 == total {
+                    WriterState::Empty
+                } else {
+                    WriterState::Faults {
+                        index: index + 1,
+                        total,
+                    }
+                }
+            }
+            WriterState::Limits { index, total } => {
+                if index + 1 == total {
+                    WriterState::Empty
+                } else {
+                    WriterState::Limits {
+                        index: index + 1,
+                        total,
+                    }
+                }
+            }
+            WriterState::Grants {
+                process_id,
+                index,
+                total,
+            } => {
+                // Skip the grants the process has not allocated, as there is
+                // nothing to print for them.
+                let info: KernelInfo = KernelInfo::new(self.kernel);
+                let next = (index + 1..total).find(|&grant_num| {
+                    info.app_grant_usage(process_id, grant_num as usize, &self.capability)
+                        .is_some()
+                });
+                match next {
+                    Some(grant_num) => WriterState::Grants {
+                        process_id,
+                        index: grant_num,
+                        total,
+                    },
+                    None => WriterState::Empty,
+                }
+            }
             WriterState::Empty => WriterState::Empty,
         }
     }
@@ -516,11 +647,13 @@ impl<'a, A: Alarm<'a>, C: ProcessManagementCapability> ProcessConsole<'a, A, C>
                             let _ = write(
                                 &mut console_writer,
                                 format_args!(
-                                    " {:<7?}{:<20}{:6}{:10}{:10}  {:2}/{:2}   {:?}\r\n",
+                                    " {:<7?}{:<20}{:6}{:10}{:9}{:9}{:10}  {:2}/{:2}   {:?}\r\n",
                                     process_id,
                                     pname,
                                     process.debug_timeslice_expiration_count(),
                                     process.debug_syscall_count(),
+                                    process.debug_upcall_count(),
+                                    process.debug_cpu_time_us() / 1000,
                                     process.get_restart_count(),
                                     grants_used,
                                     grants_total,
@@ -532,6 +665,109 @@ impl<'a, A: Alarm<'a>, C: ProcessManagementCapability> ProcessConsole<'a, A, C>
                         }
                     });
             }
+            WriterState::Faults { index, total: _ } => {
+                self.fault_history
+                    .map(|fault_history| fault_history.get_record(index as usize))
+                    .flatten()
+                    .map(|record| {
+                        let mut console_writer = ConsoleWriter::new();
+                        let _ = write(
+                            &mut console_writer,
+                            format_args!(" {:<17}{:8}  ", record.name(), record.restart_count),
+                        );
+                        for value in [record.pc, record.fault_address] {
+                            let _ = match value {
+                                Some(value) => {
+                                    write(&mut console_writer, format_args!("{:#010X}  ", value))
+                                }
+                                None => write(&mut console_writer, format_args!("{:10}  ", "-")),
+                            };
+                        }
+                        let _ = match record.completion_code {
+                            Some(code) => write(&mut console_writer, format_args!("{:10}", code)),
+                            None => write(&mut console_writer, format_args!("{:>10}", "-")),
+                        };
+                        let action = match record.action {
+                            FaultAction::Panic => "Panic",
+                            FaultAction::Restart => "Restart",
+                            FaultAction::Stop => "Stop",
+                        };
+                        let _ = write(&mut console_writer, format_args!("  {}\r\n", action));
+
+                        let _ = self.write_bytes(&(console_writer.buf)[..console_writer.size]);
+                    });
+            }
+            WriterState::Limits { index, total: _ } => {
+                self.syscall_limits
+                    .map(|syscall_limits| syscall_limits.get_limit(index as usize))
+                    .flatten()
+                    .map(|status| {
+                        let limit = status.limit;
+                        let mut console_writer = ConsoleWriter::new();
+                        let _ = write(
+                            &mut console_writer,
+                            format_args!(
+                                " {:<17}{:#07x}  ",
+                                status.process_name,
+                                limit.driver_number()
+                            ),
+                        );
+                        let _ = if limit.command_number() == u16::MAX {
+                            write(&mut console_writer, format_args!("{:>5}", "*"))
+                        } else {
+                            write(
+                                &mut console_writer,
+                                format_args!("{:5}", limit.command_number()),
+                            )
+                        };
+                        let _ = if limit.max_calls() != 0 && limit.period_ms() != 0 {
+                            write(
+                                &mut console_writer,
+                                format_args!("  {:>5}/{:<7}", limit.max_calls(), limit.period_ms()),
+                            )
+                        } else {
+                            write(&mut console_writer, format_args!("  {:>5} {:7}", "-", ""))
+                        };
+                        let _ = if limit.quota() != 0 {
+                            write(&mut console_writer, format_args!("{:10}", limit.quota()))
+                        } else {
+                            write(&mut console_writer, format_args!("{:>10}", "-"))
+                        };
+                        let _ = write(
+                            &mut console_writer,
+                            format_args!("{:10}{:10}\r\n", status.total_calls, status.rejected),
+                        );
+
+                        let _ = self.write_bytes(&(console_writer.buf)[..console_writer.size]);
+                    });
+            }
+            WriterState::Grants {
+                process_id,
+                index,
+                total: _,
+            } => {
+                let info: KernelInfo = KernelInfo::new(self.kernel);
+                info.app_grant_usage(process_id, index as usize, &self.capability)
+                    .map(|usage| {
+                        let mut console_writer = ConsoleWriter::new();
+                        let _ = write(
+                            &mut console_writer,
+                            format_args!(
+                                " {:5}  {:#07x}  {:8}  {}\r\n",
+                                index,
+                                usage.driver_num,
+                                usage.size,
+                                if usage.released {
+                                    "released"
+                                } else {
+                                    "allocated"
+                                }
+                            ),
+                        );
+
+                        let _ = self.write_bytes(&(console_writer.buf)[..console_writer.size]);
+                    });
+            }
             WriterState::Empty => {
                 self.prompt();
             }
@@ -605,6 +841,95 @@ impl<'a, A: Alarm<'a>, C: ProcessManagementCapability> ProcessConsole<'a, A, C>
                                         }
                                     });
                             });
+                        } else if clean_str.starts_with("faults") {
+                            let count = self
+                                .fault_history
+                                .map_or(0, |fault_history| fault_history.record_count());
+                            if self.fault_history.is_none() {
+                                let _ = self.write_bytes(b"Fault history is not enabled.\r\n");
+                            } else if count == 0 {
+                                let _ = self.write_bytes(b"No faults recorded.\r\n");
+                            } else {
+                                let _ =
+                                    self.write_bytes(b" Name             Restarts  PC          ");
+                                let _ = self.write_bytes(b"Address           Code  Action\r\n");
+
+                                // Start the state machine to print each separately.
+                                self.write_state(WriterState::Faults {
+                                    index: -1,
+                                    total: count as isize,
+                                });
+                            }
+                        } else if clean_str.starts_with("limits") {
+                            let count = self
+                                .syscall_limits
+                                .map_or(0, |syscall_limits| syscall_limits.limit_count());
+                            if self.syscall_limits.is_none() {
+                                let _ = self.write_bytes(b"Syscall limits are not enabled.\r\n");
+                            } else if count == 0 {
+                                let _ = self.write_bytes(b"No syscall limits in use.\r\n");
+                            } else {
+                                let _ = self
+                                    .write_bytes(b" Name             Driver     Cmd  Rate(/ms)");
+                                let _ = self.write_bytes(b"         Quota     Calls  Rejected\r\n");
+
+                                // Start the state machine to print each separately.
+                                self.write_state(WriterState::Limits {
+                                    index: -1,
+                                    total: count as isize,
+                                });
+                            }
+                        } else if clean_str.starts_with("grants") {
+                            let argument = clean_str.split_whitespace().nth(1);
+                            argument.map(|name| {
+                                // If two processes have the same name, only
+                                // print the first one we find.
+                                let mut process_id = None;
+                                self.kernel
+                                    .process_each_capability(&self.capability, |proc| {
+                                        if process_id.is_none() && proc.get_process_name() == name {
+                                            process_id = Some(proc.processid());
+                                        }
+                                    });
+                                process_id.map(|process_id| {
+                                    let info: KernelInfo = KernelInfo::new(self.kernel);
+                                    let (grants_used, grants_total) =
+                                        info.number_app_grant_uses(process_id, &self.capability);
+                                    let bytes: usize = (0..grants_total)
+                                        .filter_map(|grant_num| {
+                                            info.app_grant_usage(
+                                                process_id,
+                                                grant_num,
+                                                &self.capability,
+                                            )
+                                        })
+                                        .map(|usage| usage.size)
+                                        .sum();
+                                    let mut console_writer = ConsoleWriter::new();
+                                    let _ = write(
+                                        &mut console_writer,
+                                        format_args!(
+                                            "Process {}: {}/{} grants, {} bytes\r\n",
+                                            name, grants_used, grants_total, bytes
+                                        ),
+                                    );
+                                    let _ = self
+                                        .write_bytes(&(console_writer.buf)[..console_writer.size]);
+
+                                    if bytes > 0 {
+                                        let _ = self
+                                            .write_bytes(b" Grant  Driver      Bytes  State\r\n");
+
+                                        // Start the state machine to print each
+                                        // grant separately.
+                                        self.write_state(WriterState::Grants {
+                                            process_id,
+                                            index: -1,
+                                            total: grants_total as isize,
+                                        });
+                                    }
+                                });
+                            });
                         } else if clean_str.starts_with("fault") {
                             let argument = clean_str.split_whitespace().nth(1);
                             argument.map(|name| {
@@ -670,7 +995,9 @@ impl<'a, A: Alarm<'a>, C: ProcessManagementCapability> ProcessConsole<'a, A, C>
                             });
                         } else if clean_str.starts_with("list") {
                             let _ = self.write_bytes(b" PID    Name                Quanta  ");
-                            let _ = self.write_bytes(b"Syscalls  Restarts  Grants  State\r\n");
+                            let _ = self.write_bytes(
+                                b"Syscalls  Upcalls  CPU(ms)  Restarts  Grants  State\r\n",
+                            );
 
                             // Count the number of current processes.
                             let mut count = 0;
@@ -714,6 +1041,66 @@ impl<'a, A: Alarm<'a>, C: ProcessManagementCapability> ProcessConsole<'a, A, C>
                                 ),
                             );
                             let _ = self.write_bytes(&(console_writer.buf)[..console_writer.size]);
+                            console_writer.clear();
+                            let _ = write(
+                                &mut console_writer,
+                                format_args!(
+                                    "CPU time: {} ms\r\n",
+                                    info.cpu_time_us(&self.capability) / 1000
+                                ),
+                            );
+                            let _ = self.write_bytes(&(console_writer.buf)[..console_writer.size]);
+                            console_writer.clear();
+                            let _ = write(&mut console_writer, format_args!("Syscalls:"));
+                            for (i, class) in [
+                                SyscallClass::Yield,
+                                SyscallClass::Subscribe,
+                                SyscallClass::Command,
+                                SyscallClass::ReadWriteAllow,
+                                SyscallClass::ReadOnlyAllow,
+                                SyscallClass::UserspaceReadableAllow,
+                                SyscallClass::Memop,
+                                SyscallClass::Exit,
+                            ]
+                            .iter()
+                            .enumerate()
+                            {
+                                let _ = write(
+                                    &mut console_writer,
+                                    format_args!(
+                                        "{} {:?} {}",
+                                        if i == 0 { "" } else { "," },
+                                        class,
+                                        info.number_syscalls_of_class(*class, &self.capability)
+                                    ),
+                                );
+                            }
+                            let _ = write(&mut console_writer, format_args!("\r\n"));
+                            let _ = self.write_bytes(&(console_writer.buf)[..console_writer.size]);
+                            console_writer.clear();
+                            let _ = write(&mut console_writer, format_args!("Sleep states:"));
+                            let power = self.kernel.power_manager();
+                            let mut first = true;
+                            for state in 0..MAX_SLEEP_STATES {
+                                match power.stats(state) {
+                                    Some(stats) if stats.entries > 0 => {
+                                        let _ = write(
+                                            &mut console_writer,
+                                            format_args!(
+                                                "{} {} entered {}, {} ms",
+                                                if first { "" } else { ";" },
+                                                state,
+                                                stats.entries,
+                                                stats.time_us / 1000
+                                            ),
+                                        );
+                                        first = false;
+                                    }
+                                    _ => {}
+                                }
+                            }
+                            let _ = write(&mut console_writer, format_args!("\r\n"));
+                            let _ = self.write_bytes(&(console_writer.buf)[..console_writer.size]);
                         } else if clean_str.starts_with("process") {
                             let argument = clean_str.split_whitespace().nth(1);
                             argument.map(|name| {
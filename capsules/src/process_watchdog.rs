@@ -0,0 +1,324 @@
+//! Software watchdog for monitoring the liveness of individual processes.
+//!
+//! A process registers a heartbeat timeout with this capsule and must then
+//! send a heartbeat at least once per timeout period. If a process misses its
+//! deadline, the capsule either faults the process, which applies the
+//! board's `ProcessFaultPolicy` to it, or resets the whole board.
+//!
+//! Resetting the board relies on a hardware watchdog: `ProcessWatchdog`
+//! implements the kernel's `WatchDog` trait by wrapping the board's hardware
+//! watchdog, and stops tickling it once a process has expired. Boards that
+//! use `ExpiryAction::ResetBoard` must therefore pass a real hardware
+//! watchdog and use the capsule as their `KernelResources::WatchDog`.
+//!
+//! All deadlines are tracked with a single virtual alarm, which is set to
+//! fire at the earliest deadline of any monitored process.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! # use kernel::static_init;
+//!
+//! let watchdog_alarm = static_init!(
+//!     capsules::virtual_alarm::VirtualMuxAlarm<'static, sam4l::ast::Ast>,
+//!     capsules::virtual_alarm::VirtualMuxAlarm::new(mux_alarm)
+//! );
+//! watchdog_alarm.setup();
+//! let process_watchdog = static_init!(
+//!     capsules::process_watchdog::ProcessWatchdog<
+//!         'static,
+//!         capsules::virtual_alarm::VirtualMuxAlarm<'static, sam4l::ast::Ast>,
+//!         sam4l::wdt::Wdt,
+//!         ProcessMgmtCap,
+//!     >,
+//!     capsules::process_watchdog::ProcessWatchdog::new(
+//!         watchdog_alarm,
+//!         &peripherals.wdt,
+//!         board_kernel,
+//!         ProcessMgmtCap,
+//!         capsules::process_watchdog::ExpiryAction::FaultProcess,
+//!         board_kernel.create_grant(capsules::process_watchdog::DRIVER_NUM, &grant_cap)
+//!     )
+//! );
+//! watchdog_alarm.set_alarm_client(process_watchdog);
+//! ```
+//!
+//! Syscall Interface
+//! -----------------
+//!
+//! - Command 0: Check that the driver exists.
+//! - Command 1: Start monitoring the calling process. `data1` is the
+//!   heartbeat timeout in milliseconds. Starting again while already
+//!   monitored changes the timeout and counts as a heartbeat.
+//! - Command 2: Heartbeat. Restarts the timeout period.
+//! - Command 3: Stop monitoring the calling process.
+
+use core::cell::Cell;
+
+use kernel::capabilities::ProcessManagementCapability;
+use kernel::debug;
+use kernel::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
+use kernel::hil::time::{self, Alarm, ConvertTicks, Ticks};
+use kernel::platform::watchdog::WatchDog;
+use kernel::syscall::{CommandReturn, SyscallDriver};
+use kernel::{ErrorCode, Kernel, ProcessId};
+
+/// Syscall driver number.
+use crate::driver;
+pub const DRIVER_NUM: usize = driver::NUM::ProcessWatchdog as usize;
+
+/// What to do when a process misses its heartbeat deadline.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExpiryAction {
+    /// Fault the process, which applies its `ProcessFaultPolicy`.
+    FaultProcess,
+    /// Stop tickling the hardware watchdog so that it resets the board.
+    ResetBoard,
+}
+
+/// Per-process watchdog state. Times are the lower 32 bits of the alarm
+/// counter, as in the alarm driver.
+#[derive(Default)]
+pub struct App {
+    timeout: Option<u32>,
+    last_heartbeat: u32,
+}
+
+pub struct ProcessWatchdog<'a, A: Alarm<'a>, W: WatchDog, C: ProcessManagementCapability> {
+    alarm: &'a A,
+    hardware_watchdog: &'a W,
+    kernel: &'static Kernel,
+    capability: C,
+    action: ExpiryAction,
+    apps: Grant<App, UpcallCount<0>, AllowRoCount<0>, AllowRwCount<0>>,
+    expired: Cell<bool>,
+}
+
+impl<'a, A: Alarm<'a>, W: WatchDog, C: ProcessManagementCapability> ProcessWatchdog<'a, A, W, C> {
+    pub fn new(
+        alarm: &'a A,
+        hardware_watchdog: &'a W,
+        kernel: &'static Kernel,
+        capability: C,
+        action: ExpiryAction,
+        grant: Grant<App, UpcallCount<0>, AllowRoCount<0>, AllowRwCount<0>>,
+    ) -> ProcessWatchdog<'a, A, W, C> {
+        ProcessWatchdog {
+            alarm,
+            hardware_watchdog,
+            kernel,
+            capability,
+            action,
+            apps: grant,
+            expired: Cell::new(false),
+        }
+    }
+
+    /// Whether a monitored process has missed its deadline and the board is
+    /// waiting for the hardware watchdog to reset it.
+    pub fn expired(&self) -> bool {
+        self.expired.get()
+    }
+
+    /// Lower 32 bits of the current alarm counter.
+    fn now(&self) -> u32 {
+        self.alarm.now().into_u32()
+    }
+
+    /// Ticks elapsed between `reference` and `now`, both lower 32 bits of the
+    /// alarm counter. Converting the difference back through `into_u32()`
+    /// keeps this correct for counters wider than 32 bits.
+    fn elapsed(reference: u32, now: u32) -> A::Ticks {
+        A::Ticks::from(
+            A::Ticks::from(now)
+                .wrapping_sub(A::Ticks::from(reference))
+                .into_u32(),
+        )
+    }
+
+    /// Convert a timeout to ticks, rejecting timeouts that cannot be tracked
+    /// without the counter wrapping.
+    fn timeout_ticks(&self, timeout_ms: u32) -> Result<u32, ErrorCode> {
+        if timeout_ms == 0 {
+            return Err(ErrorCode::INVAL);
+        }
+        let ticks = self.alarm.ticks_from_ms(timeout_ms);
+        if ticks > A::Ticks::half_max_value() || ticks > A::Ticks::from(u32::MAX >> 1) {
+            return Err(ErrorCode::SIZE);
+        }
+        Ok(ticks.into_u32())
+    }
+
+    /// Set the alarm to the earliest heartbeat deadline, or disarm it if no
+    /// process is monitored.
+    fn reset_active_alarm(&self) {
+        let now_ticks = self.alarm.now();
+        let now = now_ticks.into_u32();
+        let mut earliest: Option<A::Ticks> = None;
+        for app in self.apps.iter() {
+            app.enter(|app, _| {
+                if let Some(timeout) = app.timeout {
+                    let elapsed = Self::elapsed(app.last_heartbeat, now);
+                    let timeout = A::Ticks::from(timeout);
+                    let remaining = if elapsed >= timeout {
+                        A::Ticks::from(0)
+                    } else {
+                        timeout.wrapping_sub(elapsed)
+                    };
+                    earliest = Some(earliest.map_or(remaining, |e| e.min(remaining)));
+                }
+            });
+        }
+
+        match earliest {
+            Some(dt) => self.alarm.set_alarm(now_ticks, dt),
+            None => {
+                let _ = self.alarm.disarm();
+            }
+        }
+    }
+
+    /// Find one process whose deadline has passed and stop monitoring it.
+    fn take_expired(&self, now: u32) -> Option<ProcessId> {
+        let mut expired = None;
+        for app in self.apps.iter() {
+            if expired.is_some() {
+                break;
+            }
+            let processid = app.processid();
+            app.enter(|app, _| {
+                if let Some(timeout) = app.timeout {
+                    if Self::elapsed(app.last_heartbeat, now) >= A::Ticks::from(timeout) {
+                        app.timeout = None;
+                        expired = Some(processid);
+                    }
+                }
+            });
+        }
+        expired
+    }
+
+    fn expire(&self, processid: ProcessId) {
+        self.kernel.process_map_or_external(
+            (),
+            processid,
+            |process| {
+                debug!(
+                    "process_watchdog: {} missed its heartbeat",
+                    process.get_process_name()
+                );
+                match self.action {
+                    ExpiryAction::FaultProcess => process.set_fault_state(),
+                    ExpiryAction::ResetBoard => self.expired.set(true),
+                }
+            },
+            &self.capability,
+        );
+    }
+}
+
+impl<'a, A: Alarm<'a>, W: WatchDog, C: ProcessManagementCapability> time::AlarmClient
+    for ProcessWatchdog<'a, A, W, C>
+{
+    fn alarm(&self) {
+        let now = self.now();
+        // Faulting a process clears its grant, so it cannot happen while the
+        // grant is entered. Handle expired processes one at a time instead.
+        while let Some(processid) = self.take_expired(now) {
+            self.expire(processid);
+        }
+        self.reset_active_alarm();
+    }
+}
+
+impl<'a, A: Alarm<'a>, W: WatchDog, C: ProcessManagementCapability> WatchDog
+    for ProcessWatchdog<'a, A, W, C>
+{
+    fn setup(&self) {
+        self.hardware_watchdog.setup();
+    }
+
+    fn tickle(&self) {
+        if !self.expired.get() {
+            self.hardware_watchdog.tickle();
+        }
+    }
+
+    fn suspend(&self) {
+        // Keep the hardware watchdog running while asleep so that it still
+        // resets the board.
+        if !self.expired.get() {
+            self.hardware_watchdog.suspend();
+        }
+    }
+
+    fn resume(&self) {
+        if !self.expired.get() {
+            self.hardware_watchdog.resume();
+        }
+    }
+}
+
+impl<'a, A: Alarm<'a>, W: WatchDog, C: ProcessManagementCapability> SyscallDriver
+    for ProcessWatchdog<'a, A, W, C>
+{
+    /// Control the watchdog for the calling process.
+    ///
+    /// ### `command_num`
+    ///
+    /// - `0`: Driver check.
+    /// - `1`: Start monitoring with a heartbeat timeout of `data1`
+    ///   milliseconds.
+    /// - `2`: Heartbeat.
+    /// - `3`: Stop monitoring.
+    fn command(
+        &self,
+        command_num: usize,
+        data1: usize,
+        _data2: usize,
+        processid: ProcessId,
+    ) -> CommandReturn {
+        let now = self.now();
+        let res = match command_num {
+            0 => return CommandReturn::success(),
+            1 => self.timeout_ticks(data1 as u32).and_then(|timeout| {
+                self.apps
+                    .enter(processid, |app, _| {
+                        app.timeout = Some(timeout);
+                        app.last_heartbeat = now;
+                    })
+                    .map_err(ErrorCode::from)
+            }),
+            2 => self
+                .apps
+                .enter(processid, |app, _| match app.timeout {
+                    Some(_) => {
+                        app.last_heartbeat = now;
+                        Ok(())
+                    }
+                    None => Err(ErrorCode::OFF),
+                })
+                .unwrap_or_else(|err| Err(err.into())),
+            3 => self
+                .apps
+                .enter(processid, |app, _| match app.timeout.take() {
+                    Some(_) => Ok(()),
+                    None => Err(ErrorCode::ALREADY),
+                })
+                .unwrap_or_else(|err| Err(err.into())),
+            _ => return CommandReturn::failure(ErrorCode::NOSUPPORT),
+        };
+
+        // A heartbeat only moves a deadline later, so the alarm can stay
+        // armed for the old deadline and be recomputed when it fires.
+        if res.is_ok() && command_num != 2 {
+            self.reset_active_alarm();
+        }
+        res.into()
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), kernel::process::Error> {
+        self.apps.enter(processid, |_, _| {})
+    }
+}
@@ -10,10 +10,8 @@
 //! # use capsules::virtual_alarm::VirtualMuxAlarm;
 //! # use kernel::hil::spi::SpiMasterDevice;
 //!
-//! let spi_mux = components::spi::SpiMuxComponent::new(
-//!         &base_peripherals.spim0,
-//!         dynamic_deferred_caller
-//!     ).finalize(components::spi_mux_component_helper!(nrf52833::spi::SPIM));
+//! let spi_mux = components::spi::SpiMuxComponent::new(&base_peripherals.spim0)
+//!     .finalize(components::spi_mux_component_helper!(nrf52833::spi::SPIM));
 //! base_peripherals.spim0.configure(
 //!     nrf52833::pinmux::Pinmux::new(SPI_MOSI_PIN as u32), // SD MOSI
 //!     nrf52833::pinmux::Pinmux::new(SPI_MISO_PIN as u32), // SD MISO
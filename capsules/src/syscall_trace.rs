@@ -0,0 +1,149 @@
+//! Periodically sends the kernel's syscall trace to a host.
+//!
+//! The kernel records system calls in a `kernel::syscall_trace::SyscallTrace`
+//! when it is built with the `trace_syscalls_buffer` feature. This capsule
+//! drains the recorded system calls at a fixed interval and writes them, in
+//! the binary frame format described in `kernel::syscall_trace`, either to the
+//! debug writer or to a UART such as a SEGGER RTT channel. The frames are
+//! decoded on the host with `tools/syscall-trace`.
+//!
+//! Frames written to the debug writer are interleaved with the text output
+//! of `debug!()`, and the decoder skips over it. A dedicated UART or RTT
+//! channel avoids filling the debug buffer.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! # use kernel::static_init;
+//!
+//! let trace_alarm = static_init!(
+//!     capsules::virtual_alarm::VirtualMuxAlarm<'static, nrf52840::rtc::Rtc>,
+//!     capsules::virtual_alarm::VirtualMuxAlarm::new(mux_alarm)
+//! );
+//! trace_alarm.setup();
+//! let syscall_trace = static_init!(
+//!     kernel::syscall_trace::SyscallTrace,
+//!     kernel::syscall_trace::SyscallTrace::new(
+//!         static_init!(
+//!             [kernel::syscall_trace::SyscallRecord; 64],
+//!             [kernel::syscall_trace::SyscallRecord::EMPTY; 64]
+//!         )
+//!     )
+//! );
+//! syscall_trace.set_clock(trace_alarm);
+//! board_kernel.set_syscall_trace(syscall_trace);
+//! let trace_writer = static_init!(
+//!     capsules::syscall_trace::SyscallTraceWriter<
+//!         'static,
+//!         capsules::virtual_alarm::VirtualMuxAlarm<'static, nrf52840::rtc::Rtc>,
+//!     >,
+//!     capsules::syscall_trace::SyscallTraceWriter::new(
+//!         trace_alarm,
+//!         syscall_trace,
+//!         capsules::syscall_trace::TraceOutput::Uart(rtt),
+//!         &mut capsules::syscall_trace::BUF,
+//!         100,
+//!     )
+//! );
+//! trace_alarm.set_alarm_client(trace_writer);
+//! rtt.set_transmit_client(trace_writer);
+//! trace_writer.start();
+//! ```
+
+use kernel::debug;
+use kernel::hil::time::{self, Alarm, ConvertTicks};
+use kernel::hil::uart;
+use kernel::syscall_trace::{SyscallTrace, HEADER_LEN, RECORD_LEN};
+use kernel::utilities::cells::TakeCell;
+use kernel::ErrorCode;
+
+/// Default buffer for a frame of 16 records.
+pub static mut BUF: [u8; HEADER_LEN + 16 * RECORD_LEN] = [0; HEADER_LEN + 16 * RECORD_LEN];
+
+/// Where the trace is written.
+#[derive(Copy, Clone)]
+pub enum TraceOutput<'a> {
+    /// The kernel debug writer, shared with `debug!()`.
+    DebugWriter,
+    /// A UART used only for the trace.
+    Uart(&'a dyn uart::Transmit<'a>),
+}
+
+pub struct SyscallTraceWriter<'a, A: Alarm<'a>> {
+    alarm: &'a A,
+    trace: &'a SyscallTrace,
+    output: TraceOutput<'a>,
+    buffer: TakeCell<'static, [u8]>,
+    interval_ms: u32,
+}
+
+impl<'a, A: Alarm<'a>> SyscallTraceWriter<'a, A> {
+    pub fn new(
+        alarm: &'a A,
+        trace: &'a SyscallTrace,
+        output: TraceOutput<'a>,
+        buffer: &'static mut [u8],
+        interval_ms: u32,
+    ) -> SyscallTraceWriter<'a, A> {
+        SyscallTraceWriter {
+            alarm,
+            trace,
+            output,
+            buffer: TakeCell::new(buffer),
+            interval_ms,
+        }
+    }
+
+    /// Start draining the trace.
+    pub fn start(&self) {
+        self.alarm
+            .set_alarm(self.alarm.now(), self.alarm.ticks_from_ms(self.interval_ms));
+    }
+
+    /// Write one frame if there are records and the buffer is not in use by
+    /// the UART.
+    fn write_frame(&self) {
+        self.buffer.take().map(|buffer| {
+            let len = self.trace.drain(buffer);
+            match self.output {
+                TraceOutput::DebugWriter => {
+                    if len > 0 {
+                        debug::debug_write_bytes(&buffer[..len]);
+                    }
+                    self.buffer.replace(buffer);
+                }
+                TraceOutput::Uart(uart) => {
+                    if len == 0 {
+                        self.buffer.replace(buffer);
+                    } else if let Err((_err, buffer)) = uart.transmit_buffer(buffer, len) {
+                        self.buffer.replace(buffer);
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl<'a, A: Alarm<'a>> time::AlarmClient for SyscallTraceWriter<'a, A> {
+    fn alarm(&self) {
+        self.write_frame();
+        self.start();
+    }
+}
+
+impl<'a, A: Alarm<'a>> uart::TransmitClient for SyscallTraceWriter<'a, A> {
+    fn transmitted_buffer(
+        &self,
+        buffer: &'static mut [u8],
+        _tx_len: usize,
+        _rcode: Result<(), ErrorCode>,
+    ) {
+        self.buffer.replace(buffer);
+        // Keep sending while records arrive faster than one frame per
+        // interval.
+        if self.trace.has_records() {
+            self.write_frame();
+        }
+    }
+}
@@ -185,4 +185,12 @@ impl<'a, S: KVSystem<'static, K = T>, T: KeyType + core::fmt::Debug> kv_system::
             }
         }
     }
+    fn get_next_key_complete(
+        &self,
+        result: Result<(), ErrorCode>,
+        _next_cursor: usize,
+        key: &'static mut T,
+    ) {
+        debug!("Found key: {:?} ({:?})", key, result);
+    }
 }
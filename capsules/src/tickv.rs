@@ -49,6 +49,7 @@ enum Operation {
     AppendKey,
     InvalidateKey,
     GarbageCollect,
+    GetNextKey,
 }
 
 pub struct TickFSFlastCtrl<'a, F: Flash + 'static> {
@@ -78,17 +79,15 @@ impl<'a, F: Flash> tickv::flash_controller::FlashController<64> for TickFSFlastC
         _offset: usize,
         _buf: &mut [u8; 64],
     ) -> Result<(), tickv::error_codes::ErrorCode> {
-        if self
-            .flash
-            .read_page(
-                self.region_offset + region_number,
-                self.flash_read_buffer.take().unwrap(),
-            )
-            .is_err()
-        {
-            Err(tickv::error_codes::ErrorCode::ReadFail)
-        } else {
-            Err(tickv::error_codes::ErrorCode::ReadNotReady(region_number))
+        match self.flash.read_page(
+            self.region_offset + region_number,
+            self.flash_read_buffer.take().unwrap(),
+        ) {
+            Ok(()) => Err(tickv::error_codes::ErrorCode::ReadNotReady(region_number)),
+            Err((_, buf)) => {
+                self.flash_read_buffer.replace(buf);
+                Err(tickv::error_codes::ErrorCode::ReadFail)
+            }
         }
     }
 
@@ -99,19 +98,34 @@ impl<'a, F: Flash> tickv::flash_controller::FlashController<64> for TickFSFlastC
             data_buf.as_mut()[i + (address % 64)] = *d;
         }
 
-        if self
+        match self
             .flash
             .write_page(self.region_offset + (address / 64), data_buf)
-            .is_err()
         {
-            return Err(tickv::error_codes::ErrorCode::WriteFail);
+            Ok(()) => Err(tickv::error_codes::ErrorCode::WriteNotReady(address)),
+            Err((_, buf)) => {
+                self.flash_read_buffer.replace(buf);
+                Err(tickv::error_codes::ErrorCode::WriteFail)
+            }
         }
-
-        Err(tickv::error_codes::ErrorCode::WriteNotReady(address))
     }
 
     fn erase_region(&self, region_number: usize) -> Result<(), tickv::error_codes::ErrorCode> {
-        let _ = self.flash.erase_page(self.region_offset + region_number);
+        if self
+            .flash
+            .erase_page(self.region_offset + region_number)
+            .is_err()
+        {
+            return Err(tickv::error_codes::ErrorCode::EraseFail);
+        }
+
+        // The page buffer is used for the next write, which might be to the
+        // erased region without reading it first.
+        self.flash_read_buffer.map(|buf| {
+            for d in buf.as_mut().iter_mut() {
+                *d = 0xFF;
+            }
+        });
 
         Err(tickv::error_codes::ErrorCode::EraseNotReady(region_number))
     }
@@ -130,6 +144,7 @@ pub struct TicKVStore<'a, F: Flash + 'static, H: Hasher<'a, 8>> {
     ret_buffer: TakeCell<'static, [u8]>,
     unhashed_key_buf: TakeCell<'static, [u8]>,
     key_buf: TakeCell<'static, [u8; 8]>,
+    cursor: Cell<usize>,
 
     client: OptionalCell<&'a dyn kv_system::Client<TicKVKeyType>>,
 }
@@ -159,6 +174,7 @@ impl<'a, F: Flash, H: Hasher<'a, 8>> TicKVStore<'a, F, H> {
             ret_buffer: TakeCell::empty(),
             unhashed_key_buf: TakeCell::empty(),
             key_buf: TakeCell::empty(),
+            cursor: Cell::new(0),
             client: OptionalCell::empty(),
         }
     }
@@ -216,9 +232,93 @@ impl<'a, F: Flash, H: Hasher<'a, 8>> TicKVStore<'a, F, H> {
                 }
                 _ => {}
             },
+            Operation::GetNextKey => {
+                match self.get_next_key(self.cursor.get(), self.key_buffer.take().unwrap()) {
+                    Err((key, error)) => {
+                        self.client.map(move |cb| {
+                            cb.get_next_key_complete(error, 0, key);
+                        });
+                    }
+                    _ => {}
+                }
+            }
         }
         self.next_operation.set(Operation::None);
     }
+
+    /// Continue an ongoing garbage collection once a flash operation
+    /// completed.
+    fn continue_garbage_collect(&self) {
+        let (ret, buf_buffer) = self.tickv.continue_operation();
+
+        buf_buffer.map(|buf| {
+            self.ret_buffer.replace(buf);
+        });
+
+        self.garbage_collect_result(ret);
+    }
+
+    /// Retire the region of a write or erase that failed, and finish or
+    /// continue the current operation.
+    fn flash_failed(&self) {
+        let (ret, buf_buffer) = self.tickv.continue_failed_operation();
+
+        buf_buffer.map(|buf| {
+            self.ret_buffer.replace(buf);
+        });
+
+        match self.operation.get() {
+            Operation::Init => self.complete_init(),
+            Operation::AppendKey => {
+                self.operation.set(Operation::None);
+                self.client.map(|cb| {
+                    cb.append_key_complete(
+                        Err(ErrorCode::FAIL),
+                        self.key_buffer.take().unwrap(),
+                        self.tickv.get_stored_value_buffer().unwrap(),
+                    );
+                });
+            }
+            Operation::InvalidateKey => {
+                self.operation.set(Operation::None);
+                self.client.map(|cb| {
+                    cb.invalidate_key_complete(
+                        Err(ErrorCode::FAIL),
+                        self.key_buffer.take().unwrap(),
+                    );
+                });
+            }
+            Operation::GarbageCollect => self.garbage_collect_result(ret),
+            // Retiring a region after a failed operation
+            Operation::None => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn garbage_collect_result(
+        &self,
+        ret: Result<tickv::success_codes::SuccessCode, tickv::error_codes::ErrorCode>,
+    ) {
+        match ret {
+            Ok(tickv::success_codes::SuccessCode::Complete)
+            | Ok(tickv::success_codes::SuccessCode::Written) => {
+                self.operation.set(Operation::None);
+                self.client.map(|cb| {
+                    cb.garbage_collect_complete(Ok(()));
+                });
+            }
+            Ok(_)
+            | Err(tickv::error_codes::ErrorCode::ReadNotReady(_))
+            | Err(tickv::error_codes::ErrorCode::WriteNotReady(_))
+            | Err(tickv::error_codes::ErrorCode::EraseNotReady(_)) => {}
+            Err(_) => {
+                self.operation.set(Operation::None);
+                self.client.map(|cb| {
+                    cb.garbage_collect_complete(Err(ErrorCode::FAIL));
+                });
+            }
+        }
+    }
 }
 
 impl<'a, F: Flash, H: Hasher<'a, 8>> hasher::Client<8> for TicKVStore<'a, F, H> {
@@ -289,7 +389,19 @@ impl<'a, F: Flash, H: Hasher<'a, 8>> flash::Client<F> for TicKVStore<'a, F, H> {
                 | Ok(tickv::success_codes::SuccessCode::Written) => {
                     self.operation.set(Operation::None);
                 }
-                _ => {}
+                Ok(_)
+                | Err(tickv::error_codes::ErrorCode::ReadNotReady(_))
+                | Err(tickv::error_codes::ErrorCode::WriteNotReady(_)) => {}
+                Err(_) => {
+                    self.operation.set(Operation::None);
+                    self.client.map(|cb| {
+                        cb.append_key_complete(
+                            Err(ErrorCode::FAIL),
+                            self.key_buffer.take().unwrap(),
+                            self.tickv.get_stored_value_buffer().unwrap(),
+                        );
+                    });
+                }
             },
             Operation::InvalidateKey => match ret {
                 Ok(tickv::success_codes::SuccessCode::Complete)
@@ -298,27 +410,53 @@ impl<'a, F: Flash, H: Hasher<'a, 8>> flash::Client<F> for TicKVStore<'a, F, H> {
                 }
                 _ => {}
             },
-            Operation::GarbageCollect => match ret {
-                Ok(tickv::success_codes::SuccessCode::Complete)
-                | Ok(tickv::success_codes::SuccessCode::Written) => {
+            Operation::GarbageCollect => self.garbage_collect_result(ret),
+            Operation::GetNextKey => match ret {
+                Ok(_) => {
+                    self.operation.set(Operation::None);
+                    let key = self.key_buffer.take().unwrap();
+                    match self.tickv.get_stored_key_entry() {
+                        Some(entry) => {
+                            *key = entry.hashed_key.to_le_bytes();
+                            self.client.map(|cb| {
+                                cb.get_next_key_complete(Ok(()), entry.next_cursor, key);
+                            });
+                        }
+                        None => {
+                            self.client.map(|cb| {
+                                cb.get_next_key_complete(Err(ErrorCode::FAIL), 0, key);
+                            });
+                        }
+                    }
+                }
+                Err(tickv::error_codes::ErrorCode::ReadNotReady(_)) => {}
+                Err(e) => {
                     self.operation.set(Operation::None);
+                    let error = match e {
+                        tickv::error_codes::ErrorCode::KeyNotFound => ErrorCode::NOSUPPORT,
+                        _ => ErrorCode::FAIL,
+                    };
                     self.client.map(|cb| {
-                        cb.garbage_collect_complete(Ok(()));
+                        cb.get_next_key_complete(Err(error), 0, self.key_buffer.take().unwrap());
                     });
                 }
-                _ => {}
             },
             _ => unreachable!(),
         }
     }
 
-    fn write_complete(&self, pagebuffer: &'static mut F::Page, _error: flash::Error) {
+    fn write_complete(&self, pagebuffer: &'static mut F::Page, error: flash::Error) {
         self.tickv
             .tickv
             .controller
             .flash_read_buffer
             .replace(pagebuffer);
 
+        if error != flash::Error::CommandComplete {
+            self.flash_failed();
+            return;
+        }
+
         match self.operation.get() {
             Operation::Init => {
                 self.complete_init();
@@ -339,11 +477,27 @@ impl<'a, F: Flash, H: Hasher<'a, 8>> flash::Client<F> for TicKVStore<'a, F, H> {
                     cb.invalidate_key_complete(Ok(()), self.key_buffer.take().unwrap());
                 });
             }
+            Operation::GarbageCollect => {
+                // A region header was written, continue with the next region
+                self.continue_garbage_collect();
+            }
+            // Retiring a region after a failed operation
+            Operation::None => {}
             _ => unreachable!(),
         }
     }
 
-    fn erase_complete(&self, _error: flash::Error) {
+    fn erase_complete(&self, error: flash::Error) {
+        if error != flash::Error::CommandComplete {
+            self.flash_failed();
+            return;
+        }
+
+        if self.operation.get() == Operation::GarbageCollect {
+            self.continue_garbage_collect();
+            return;
+        }
+
         let (ret, buf_buffer) = self.tickv.continue_operation();
 
         buf_buffer.map(|buf| {
@@ -358,16 +512,6 @@ impl<'a, F: Flash, H: Hasher<'a, 8>> flash::Client<F> for TicKVStore<'a, F, H> {
                 }
                 _ => {}
             },
-            Operation::GarbageCollect => match ret {
-                Ok(tickv::success_codes::SuccessCode::Complete)
-                | Ok(tickv::success_codes::SuccessCode::Written) => {
-                    self.operation.set(Operation::None);
-                    self.client.map(|cb| {
-                        cb.garbage_collect_complete(Ok(()));
-                    });
-                }
-                _ => {}
-            },
             _ => unreachable!(),
         }
     }
@@ -539,11 +683,18 @@ impl<'a, F: Flash, H: Hasher<'a, 8>> KVSystem<'a> for TicKVStore<'a, F, H> {
                 self.operation.set(Operation::GarbageCollect);
 
                 match self.tickv.garbage_collect() {
-                    Ok(freed) => Ok(freed),
+                    Ok(freed) => {
+                        self.operation.set(Operation::None);
+                        Ok(freed)
+                    }
                     Err(e) => match e {
                         tickv::error_codes::ErrorCode::ReadNotReady(_)
-                        | tickv::error_codes::ErrorCode::WriteNotReady(_) => Ok(0),
-                        _ => Err(Err(ErrorCode::FAIL)),
+                        | tickv::error_codes::ErrorCode::WriteNotReady(_)
+                        | tickv::error_codes::ErrorCode::EraseNotReady(_) => Ok(0),
+                        _ => {
+                            self.operation.set(Operation::None);
+                            Err(Err(ErrorCode::FAIL))
+                        }
                     },
                 }
             }
@@ -559,4 +710,46 @@ impl<'a, F: Flash, H: Hasher<'a, 8>> KVSystem<'a> for TicKVStore<'a, F, H> {
             }
         }
     }
+
+    fn get_next_key(
+        &self,
+        cursor: usize,
+        key: &'static mut Self::K,
+    ) -> Result<(), (&'static mut Self::K, Result<(), ErrorCode>)> {
+        match self.operation.get() {
+            Operation::None => {
+                self.operation.set(Operation::GetNextKey);
+
+                match self.tickv.get_next_key(cursor) {
+                    Err(tickv::error_codes::ErrorCode::ReadNotReady(_)) => {
+                        self.key_buffer.replace(key);
+                        Ok(())
+                    }
+                    // Flash reads always complete asynchronously, so the
+                    // operation can only complete without a read if there
+                    // are no regions left to search.
+                    Ok(_) | Err(tickv::error_codes::ErrorCode::KeyNotFound) => {
+                        self.operation.set(Operation::None);
+                        Err((key, Err(ErrorCode::NOSUPPORT)))
+                    }
+                    Err(_) => {
+                        self.operation.set(Operation::None);
+                        Err((key, Err(ErrorCode::FAIL)))
+                    }
+                }
+            }
+            Operation::Init => {
+                // The init process is still occurring.
+                // We can save this request and start it after init
+                self.next_operation.set(Operation::GetNextKey);
+                self.key_buffer.replace(key);
+                self.cursor.set(cursor);
+                Ok(())
+            }
+            _ => {
+                // An operation is already in process.
+                Err((key, Err(ErrorCode::BUSY)))
+            }
+        }
+    }
 }
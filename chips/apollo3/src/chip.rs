@@ -4,14 +4,29 @@ use core::fmt::Write;
 use cortexm4::{self, CortexM4, CortexMVariant};
 use kernel::platform::chip::Chip;
 use kernel::platform::chip::InterruptService;
+use kernel::platform::power::SleepState;
 
-pub struct Apollo3<I: InterruptService<()> + 'static> {
+use crate::pwrctrl;
+
+/// Sleep states, entered with `WFI` with and without `SLEEPDEEP` set.
+static SLEEP_STATES: [SleepState; 2] = [
+    SleepState {
+        name: "sleep",
+        wake_latency_us: 1,
+    },
+    SleepState {
+        name: "deep sleep",
+        wake_latency_us: 25,
+    },
+];
+
+pub struct Apollo3<I: InterruptService + 'static> {
     mpu: cortexm4::mpu::MPU,
     userspace_kernel_boundary: cortexm4::syscall::SysCall,
     interrupt_service: &'static I,
 }
 
-impl<I: InterruptService<()> + 'static> Apollo3<I> {
+impl<I: InterruptService + 'static> Apollo3<I> {
     pub unsafe fn new(interrupt_service: &'static I) -> Self {
         Self {
             mpu: cortexm4::mpu::MPU::new(),
@@ -57,7 +72,7 @@ impl Apollo3DefaultPeripherals {
     }
 }
 
-impl kernel::platform::chip::InterruptService<()> for Apollo3DefaultPeripherals {
+impl kernel::platform::chip::InterruptService for Apollo3DefaultPeripherals {
     unsafe fn service_interrupt(&self, interrupt: u32) -> bool {
         use crate::nvic;
         match interrupt {
@@ -76,12 +91,9 @@ impl kernel::platform::chip::InterruptService<()> for Apollo3DefaultPeripherals
         }
         true
     }
-    unsafe fn service_deferred_call(&self, _: ()) -> bool {
-        false
-    }
 }
 
-impl<I: InterruptService<()> + 'static> Chip for Apollo3<I> {
+impl<I: InterruptService + 'static> Chip for Apollo3<I> {
     type MPU = cortexm4::mpu::MPU;
     type UserspaceKernelBoundary = cortexm4::syscall::SysCall;
 
@@ -116,8 +128,28 @@ impl<I: InterruptService<()> + 'static> Chip for Apollo3<I> {
     }
 
     fn sleep(&self) {
+        self.sleep_in(0);
+    }
+
+    fn sleep_states(&self) -> &'static [SleepState] {
+        &SLEEP_STATES
+    }
+
+    fn deepest_sleep_state(&self) -> usize {
+        if pwrctrl::deep_sleep_ready() {
+            1
+        } else {
+            0
+        }
+    }
+
+    fn sleep_in(&self, state: usize) {
         unsafe {
-            cortexm4::scb::unset_sleepdeep();
+            if state > 0 {
+                cortexm4::scb::set_sleepdeep();
+            } else {
+                cortexm4::scb::unset_sleepdeep();
+            }
             cortexm4::support::wfi();
         }
     }
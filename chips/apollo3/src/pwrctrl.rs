@@ -1,7 +1,9 @@
 //! Power Control driver.
 
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable};
-use kernel::utilities::registers::{register_bitfields, register_structs, ReadOnly, ReadWrite};
+use kernel::utilities::registers::{
+    register_bitfields, register_structs, FieldValue, ReadOnly, ReadWrite,
+};
 use kernel::utilities::StaticRef;
 
 const PWRCTRL_BASE: StaticRef<PwrCtrlRegisters> =
@@ -97,3 +99,24 @@ impl PwrCtrl {
         while !regs.devpwrstatus.is_set(DEVPWRSTATUS::BLEL) {}
     }
 }
+
+/// Whether the core may enter deep sleep. Deep sleep stops the high
+/// frequency clock, so it is only allowed when no peripheral that runs from
+/// that clock is powered.
+pub fn deep_sleep_ready() -> bool {
+    let hfrc_devices: FieldValue<u32, DEVPWREN::Register> = DEVPWREN::PWRIOS::SET
+        + DEVPWREN::PWRIOM0::SET
+        + DEVPWREN::PWRIOM1::SET
+        + DEVPWREN::PWRIOM2::SET
+        + DEVPWREN::PWRIOM3::SET
+        + DEVPWREN::PWRIOM4::SET
+        + DEVPWREN::PWRIOM5::SET
+        + DEVPWREN::PWRUART0::SET
+        + DEVPWREN::PWRUART1::SET
+        + DEVPWREN::PWRSCARD::SET
+        + DEVPWREN::PWRMSPI::SET
+        + DEVPWREN::PWRPDM::SET
+        + DEVPWREN::PWRBLEL::SET;
+
+    PWRCTRL_BASE.devpwren.get() & hfrc_devices.mask() == 0
+}
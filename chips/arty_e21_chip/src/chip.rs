@@ -13,7 +13,7 @@ extern "C" {
     fn _start_trap();
 }
 
-pub struct ArtyExx<'a, I: InterruptService<()> + 'a> {
+pub struct ArtyExx<'a, I: InterruptService + 'a> {
     pmp: PMP<2>,
     userspace_kernel_boundary: rv32i::syscall::SysCall,
     clic: rv32i::clic::Clic,
@@ -37,7 +37,7 @@ impl<'a> ArtyExxDefaultPeripherals<'a> {
     }
 }
 
-impl<'a> InterruptService<()> for ArtyExxDefaultPeripherals<'a> {
+impl<'a> InterruptService for ArtyExxDefaultPeripherals<'a> {
     unsafe fn service_interrupt(&self, interrupt: u32) -> bool {
         match interrupt {
             interrupts::MTIP => self.machinetimer.handle_interrupt(),
@@ -65,13 +65,9 @@ impl<'a> InterruptService<()> for ArtyExxDefaultPeripherals<'a> {
         }
         true
     }
-
-    unsafe fn service_deferred_call(&self, _: ()) -> bool {
-        false
-    }
 }
 
-impl<'a, I: InterruptService<()> + 'a> ArtyExx<'a, I> {
+impl<'a, I: InterruptService + 'a> ArtyExx<'a, I> {
     pub unsafe fn new(
         machinetimer: &'a sifive::clint::Clint<'a>,
         interrupt_service: &'a I,
@@ -143,7 +139,7 @@ impl<'a, I: InterruptService<()> + 'a> ArtyExx<'a, I> {
     }
 }
 
-impl<'a, I: InterruptService<()> + 'a> kernel::platform::chip::Chip for ArtyExx<'a, I> {
+impl<'a, I: InterruptService + 'a> kernel::platform::chip::Chip for ArtyExx<'a, I> {
     type MPU = PMP<2>;
     type UserspaceKernelBoundary = rv32i::syscall::SysCall;
 
@@ -13,7 +13,7 @@ impl<'a> E310G003DefaultPeripherals<'a> {
         }
     }
 }
-impl<'a> kernel::platform::chip::InterruptService<()> for E310G003DefaultPeripherals<'a> {
+impl<'a> kernel::platform::chip::InterruptService for E310G003DefaultPeripherals<'a> {
     unsafe fn service_interrupt(&self, interrupt: u32) -> bool {
         match interrupt {
             interrupts::UART0 => self.e310x.uart0.handle_interrupt(),
@@ -28,8 +28,4 @@ impl<'a> kernel::platform::chip::InterruptService<()> for E310G003DefaultPeriphe
         }
         true
     }
-
-    unsafe fn service_deferred_call(&self, task: ()) -> bool {
-        self.e310x.service_deferred_call(task)
-    }
 }
@@ -14,7 +14,7 @@ use crate::plic::PLIC;
 use kernel::platform::chip::InterruptService;
 use sifive::plic::Plic;
 
-pub struct E310x<'a, I: InterruptService<()> + 'a> {
+pub struct E310x<'a, I: InterruptService + 'a> {
     userspace_kernel_boundary: rv32i::syscall::SysCall,
     pmp: PMP<4>,
     plic: &'a Plic,
@@ -50,17 +50,13 @@ impl<'a> E310xDefaultPeripherals<'a> {
     }
 }
 
-impl<'a> InterruptService<()> for E310xDefaultPeripherals<'a> {
+impl<'a> InterruptService for E310xDefaultPeripherals<'a> {
     unsafe fn service_interrupt(&self, _interrupt: u32) -> bool {
         false
     }
-
-    unsafe fn service_deferred_call(&self, _: ()) -> bool {
-        false
-    }
 }
 
-impl<'a, I: InterruptService<()> + 'a> E310x<'a, I> {
+impl<'a, I: InterruptService + 'a> E310x<'a, I> {
     pub unsafe fn new(plic_interrupt_service: &'a I, timer: &'a sifive::clint::Clint<'a>) -> Self {
         Self {
             userspace_kernel_boundary: rv32i::syscall::SysCall::new(),
@@ -104,7 +100,7 @@ impl<'a, I: InterruptService<()> + 'a> E310x<'a, I> {
     }
 }
 
-impl<'a, I: InterruptService<()> + 'a> kernel::platform::chip::Chip for E310x<'a, I> {
+impl<'a, I: InterruptService + 'a> kernel::platform::chip::Chip for E310x<'a, I> {
     type MPU = PMP<4>;
     type UserspaceKernelBoundary = rv32i::syscall::SysCall;
 
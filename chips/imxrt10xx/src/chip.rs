@@ -7,13 +7,13 @@ use kernel::platform::chip::{Chip, InterruptService};
 
 use crate::nvic;
 
-pub struct Imxrt10xx<I: InterruptService<()> + 'static> {
+pub struct Imxrt10xx<I: InterruptService + 'static> {
     mpu: cortexm7::mpu::MPU,
     userspace_kernel_boundary: cortexm7::syscall::SysCall,
     interrupt_service: &'static I,
 }
 
-impl<I: InterruptService<()> + 'static> Imxrt10xx<I> {
+impl<I: InterruptService + 'static> Imxrt10xx<I> {
     pub unsafe fn new(interrupt_service: &'static I) -> Self {
         Imxrt10xx {
             mpu: cortexm7::mpu::MPU::new(),
@@ -57,7 +57,7 @@ impl Imxrt10xxDefaultPeripherals {
     }
 }
 
-impl InterruptService<()> for Imxrt10xxDefaultPeripherals {
+impl InterruptService for Imxrt10xxDefaultPeripherals {
     unsafe fn service_interrupt(&self, interrupt: u32) -> bool {
         match interrupt {
             nvic::LPUART1 => self.lpuart1.handle_interrupt(),
@@ -96,13 +96,9 @@ impl InterruptService<()> for Imxrt10xxDefaultPeripherals {
         }
         true
     }
-
-    unsafe fn service_deferred_call(&self, _: ()) -> bool {
-        false
-    }
 }
 
-impl<I: InterruptService<()> + 'static> Chip for Imxrt10xx<I> {
+impl<I: InterruptService + 'static> Chip for Imxrt10xx<I> {
     type MPU = cortexm7::mpu::MPU;
     type UserspaceKernelBoundary = cortexm7::syscall::SysCall;
 
@@ -4,9 +4,7 @@
 //! [`litex/soc/cores/uart.py`](https://github.com/enjoy-digital/litex/blob/master/litex/soc/cores/uart.py).
 
 use core::cell::Cell;
-use kernel::dynamic_deferred_call::{
-    DeferredCallHandle, DynamicDeferredCall, DynamicDeferredCallClient,
-};
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
 use kernel::hil::uart;
 use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::utilities::StaticRef;
@@ -99,15 +97,13 @@ pub struct LiteXUart<'a, R: LiteXSoCRegisterConfiguration> {
     rx_progress: Cell<usize>,
     rx_aborted: Cell<bool>,
     rx_deferred_call: Cell<bool>,
-    deferred_caller: &'static DynamicDeferredCall,
-    deferred_handle: OptionalCell<DeferredCallHandle>,
+    deferred_call: DeferredCall,
 }
 
 impl<'a, R: LiteXSoCRegisterConfiguration> LiteXUart<'a, R> {
     pub fn new(
         uart_base: StaticRef<LiteXUartRegisters<R>>,
         phy_args: Option<(StaticRef<LiteXUartPhyRegisters<R>>, u32)>,
-        deferred_caller: &'static DynamicDeferredCall,
     ) -> LiteXUart<'a, R> {
         LiteXUart {
             uart_regs: uart_base,
@@ -124,14 +120,12 @@ impl<'a, R: LiteXSoCRegisterConfiguration> LiteXUart<'a, R> {
             rx_progress: Cell::new(0),
             rx_aborted: Cell::new(false),
             rx_deferred_call: Cell::new(false),
-            deferred_caller,
-            deferred_handle: OptionalCell::empty(),
+            deferred_call: DeferredCall::new(),
         }
     }
 
-    pub fn initialize(&self, deferred_call_handle: DeferredCallHandle) {
+    pub fn initialize(&self) {
         self.uart_regs.ev().disable_all();
-        self.deferred_handle.set(deferred_call_handle);
     }
 
     pub fn transmit_sync(&self, bytes: &[u8]) {
@@ -335,9 +329,6 @@ impl<'a, R: LiteXSoCRegisterConfiguration> uart::Transmit<'a> for LiteXUart<'a,
         tx_buffer: &'static mut [u8],
         tx_len: usize,
     ) -> Result<(), (ErrorCode, &'static mut [u8])> {
-        // Make sure the UART is initialized
-        assert!(self.deferred_handle.is_some());
-
         if tx_buffer.len() < tx_len {
             return Err((ErrorCode::SIZE, tx_buffer));
         }
@@ -397,8 +388,7 @@ impl<'a, R: LiteXSoCRegisterConfiguration> uart::Transmit<'a> for LiteXUart<'a,
             assert!(progress == tx_len);
 
             self.tx_deferred_call.set(true);
-            self.deferred_handle
-                .map(|handle| self.deferred_caller.set(*handle));
+            self.deferred_call.set();
         }
 
         // If fifo_full == true, we will get an interrupt
@@ -407,9 +397,6 @@ impl<'a, R: LiteXSoCRegisterConfiguration> uart::Transmit<'a> for LiteXUart<'a,
     }
 
     fn transmit_word(&self, _word: u32) -> Result<(), ErrorCode> {
-        // Make sure the UART is initialized
-        assert!(self.deferred_handle.is_some());
-
         Err(ErrorCode::FAIL)
     }
 
@@ -420,16 +407,12 @@ impl<'a, R: LiteXSoCRegisterConfiguration> uart::Transmit<'a> for LiteXUart<'a,
         // transmission, however that will be routed to
         // `deferred_tx_abort` if `tx_aborted` is set
 
-        // Make sure the UART is initialized
-        assert!(self.deferred_handle.is_some());
-
         self.uart_regs.ev().disable_event(EVENT_MANAGER_INDEX_TX);
 
         if self.tx_buffer.is_some() {
             self.tx_aborted.set(true);
             self.tx_deferred_call.set(true);
-            self.deferred_handle
-                .map(|handle| self.deferred_caller.set(*handle));
+            self.deferred_call.set();
 
             Err(ErrorCode::BUSY)
         } else {
@@ -448,9 +431,6 @@ impl<'a, R: LiteXSoCRegisterConfiguration> uart::Receive<'a> for LiteXUart<'a, R
         rx_buffer: &'static mut [u8],
         rx_len: usize,
     ) -> Result<(), (ErrorCode, &'static mut [u8])> {
-        // Make sure the UART is initialized
-        assert!(self.deferred_handle.is_some());
-
         if rx_len > rx_buffer.len() {
             return Err((ErrorCode::SIZE, rx_buffer));
         }
@@ -484,8 +464,7 @@ impl<'a, R: LiteXSoCRegisterConfiguration> uart::Receive<'a> for LiteXUart<'a, R
             // instead! Otherwise we risk double-delivery of the
             // interrupt _and_ the deferred call
             self.rx_deferred_call.set(true);
-            self.deferred_handle
-                .map(|handle| self.deferred_caller.set(*handle));
+            self.deferred_call.set();
         } else {
             // We do _not_ clear any pending data in the FIFO by
             // acknowledging previous events
@@ -496,15 +475,10 @@ impl<'a, R: LiteXSoCRegisterConfiguration> uart::Receive<'a> for LiteXUart<'a, R
     }
 
     fn receive_word(&self) -> Result<(), ErrorCode> {
-        // Make sure the UART is initialized
-        assert!(self.deferred_handle.is_some());
         Err(ErrorCode::FAIL)
     }
 
     fn receive_abort(&self) -> Result<(), ErrorCode> {
-        // Make sure the UART is initialized
-        assert!(self.deferred_handle.is_some());
-
         // Disable RX events
         self.uart_regs.ev().disable_event(EVENT_MANAGER_INDEX_RX);
 
@@ -513,8 +487,7 @@ impl<'a, R: LiteXSoCRegisterConfiguration> uart::Receive<'a> for LiteXUart<'a, R
             // call
             self.rx_aborted.set(true);
             self.rx_deferred_call.set(true);
-            self.deferred_handle
-                .map(|handle| self.deferred_caller.set(*handle));
+            self.deferred_call.set();
 
             Err(ErrorCode::BUSY)
         } else {
@@ -523,8 +496,8 @@ impl<'a, R: LiteXSoCRegisterConfiguration> uart::Receive<'a> for LiteXUart<'a, R
     }
 }
 
-impl<'a, R: LiteXSoCRegisterConfiguration> DynamicDeferredCallClient for LiteXUart<'a, R> {
-    fn call(&self, _handle: DeferredCallHandle) {
+impl<'a, R: LiteXSoCRegisterConfiguration> DeferredCallClient for LiteXUart<'a, R> {
+    fn handle_deferred_call(&self) {
         // Are we currently in a TX or RX transaction?
         if self.tx_deferred_call.get() {
             self.tx_deferred_call.set(false);
@@ -558,4 +531,8 @@ impl<'a, R: LiteXSoCRegisterConfiguration> DynamicDeferredCallClient for LiteXUa
             }
         }
     }
+
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+    }
 }
@@ -37,6 +37,7 @@ use core::cell::Cell;
 use core::convert::TryFrom;
 use kernel::hil::ble_advertising;
 use kernel::hil::ble_advertising::RadioChannel;
+use kernel::platform::power::PowerConstraint;
 use kernel::utilities::cells::OptionalCell;
 use kernel::utilities::cells::TakeCell;
 use kernel::utilities::registers::interfaces::{Readable, Writeable};
@@ -536,6 +537,7 @@ pub struct Radio<'a> {
     rx_client: OptionalCell<&'a dyn ble_advertising::RxClient>,
     tx_client: OptionalCell<&'a dyn ble_advertising::TxClient>,
     buffer: TakeCell<'static, [u8]>,
+    power_constraint: PowerConstraint<'static>,
 }
 
 impl<'a> Radio<'a> {
@@ -546,9 +548,16 @@ impl<'a> Radio<'a> {
             rx_client: OptionalCell::empty(),
             tx_client: OptionalCell::empty(),
             buffer: TakeCell::empty(),
+            power_constraint: PowerConstraint::new(),
         }
     }
 
+    /// The constraint that keeps the chip out of deep sleep while the radio
+    /// is powered.
+    pub fn power_constraint(&self) -> &PowerConstraint<'static> {
+        &self.power_constraint
+    }
+
     pub fn is_enabled(&self) -> bool {
         self.registers.mode.matches_all(Mode::MODE::BLE_1MBIT)
     }
@@ -576,6 +585,8 @@ impl<'a> Radio<'a> {
     }
 
     fn radio_on(&self) {
+        // The radio needs the HFXO, so keep the chip from stopping it.
+        crate::clock::require_hfxo(&self.power_constraint);
         // reset and enable power
         self.registers.power.write(Task::ENABLE::CLEAR);
         self.registers.power.write(Task::ENABLE::SET);
@@ -583,6 +594,7 @@ impl<'a> Radio<'a> {
 
     fn radio_off(&self) {
         self.registers.power.write(Task::ENABLE::CLEAR);
+        self.power_constraint.release();
     }
 
     fn set_tx_power(&self) {
@@ -1,17 +1,38 @@
-use crate::deferred_call_tasks::DeferredCallTask;
 use core::fmt::Write;
 use cortexm4::{self, nvic, CortexM4, CortexMVariant};
-use kernel::deferred_call;
+use kernel::deferred_call::DeferredCallClient;
 use kernel::hil::time::Alarm;
 use kernel::platform::chip::InterruptService;
+use kernel::platform::power::{PowerManager, SleepState};
 
-pub struct NRF52<'a, I: InterruptService<DeferredCallTask> + 'a> {
+use crate::clock;
+use crate::power::{self, SubPowerMode};
+
+/// Sleep states, entered with `WFI` in each of the System ON sub power modes.
+/// In the deepest state the HFXO is also stopped while the CPU sleeps, and
+/// restarted before the kernel runs again.
+static SLEEP_STATES: [SleepState; 3] = [
+    SleepState {
+        name: "constant latency",
+        wake_latency_us: 1,
+    },
+    SleepState {
+        name: "low power",
+        wake_latency_us: 3,
+    },
+    SleepState {
+        name: "HFXO off",
+        wake_latency_us: clock::HFXO_STARTUP_US,
+    },
+];
+
+pub struct NRF52<'a, I: InterruptService + 'a> {
     mpu: cortexm4::mpu::MPU,
     userspace_kernel_boundary: cortexm4::syscall::SysCall,
     interrupt_service: &'a I,
 }
 
-impl<'a, I: InterruptService<DeferredCallTask> + 'a> NRF52<'a, I> {
+impl<'a, I: InterruptService + 'a> NRF52<'a, I> {
     pub unsafe fn new(interrupt_service: &'a I) -> Self {
         Self {
             mpu: cortexm4::mpu::MPU::new(),
@@ -76,14 +97,25 @@ impl<'a> Nrf52DefaultPeripherals<'a> {
         }
     }
     // Necessary for setting up circular dependencies
-    pub fn init(&'a self) {
+    pub fn init(&'static self) {
         self.ieee802154_radio.set_timer_ref(&self.timer0);
         self.timer0.set_alarm_client(&self.ieee802154_radio);
+        self.nvmc.register();
+    }
+
+    /// Register the power constraints of the drivers that keep the chip out
+    /// of its deeper sleep states while they are active. Without them, the
+    /// HFXO may be stopped while a radio is using it.
+    pub fn add_power_constraints(&'static self, power_manager: &PowerManager) {
+        power_manager.add_constraint(self.ieee802154_radio.power_constraint());
+        power_manager.add_constraint(self.ble_radio.power_constraint());
+        power_manager.add_constraint(self.uarte0.power_constraint());
+        power_manager.add_constraint(self.spim0.power_constraint());
+        power_manager.add_constraint(self.spim1.power_constraint());
+        power_manager.add_constraint(self.spim2.power_constraint());
     }
 }
-impl<'a> kernel::platform::chip::InterruptService<DeferredCallTask>
-    for Nrf52DefaultPeripherals<'a>
-{
+impl<'a> kernel::platform::chip::InterruptService for Nrf52DefaultPeripherals<'a> {
     unsafe fn service_interrupt(&self, interrupt: u32) -> bool {
         match interrupt {
             crate::peripheral_interrupts::COMP => self.acomp.handle_interrupt(),
@@ -143,15 +175,9 @@ impl<'a> kernel::platform::chip::InterruptService<DeferredCallTask>
         }
         true
     }
-    unsafe fn service_deferred_call(&self, task: DeferredCallTask) -> bool {
-        match task {
-            DeferredCallTask::Nvmc => self.nvmc.handle_interrupt(),
-        }
-        true
-    }
 }
 
-impl<'a, I: InterruptService<DeferredCallTask> + 'a> kernel::platform::chip::Chip for NRF52<'a, I> {
+impl<'a, I: InterruptService + 'a> kernel::platform::chip::Chip for NRF52<'a, I> {
     type MPU = cortexm4::mpu::MPU;
     type UserspaceKernelBoundary = cortexm4::syscall::SysCall;
 
@@ -166,11 +192,7 @@ impl<'a, I: InterruptService<DeferredCallTask> + 'a> kernel::platform::chip::Chi
     fn service_pending_interrupts(&self) {
         unsafe {
             loop {
-                if let Some(task) = deferred_call::DeferredCall::next_pending() {
-                    if !self.interrupt_service.service_deferred_call(task) {
-                        panic!("unhandled deferred call task");
-                    }
-                } else if let Some(interrupt) = nvic::next_pending() {
+                if let Some(interrupt) = nvic::next_pending() {
                     if !self.interrupt_service.service_interrupt(interrupt) {
                         panic!("unhandled interrupt {}", interrupt);
                     }
@@ -185,7 +207,7 @@ impl<'a, I: InterruptService<DeferredCallTask> + 'a> kernel::platform::chip::Chi
     }
 
     fn has_pending_interrupts(&self) -> bool {
-        unsafe { nvic::has_pending() || deferred_call::has_tasks() }
+        unsafe { nvic::has_pending() }
     }
 
     fn sleep(&self) {
@@ -194,6 +216,25 @@ impl<'a, I: InterruptService<DeferredCallTask> + 'a> kernel::platform::chip::Chi
         }
     }
 
+    fn sleep_states(&self) -> &'static [SleepState] {
+        &SLEEP_STATES
+    }
+
+    fn sleep_in(&self, state: usize) {
+        power::set_sub_power_mode(if state > 0 {
+            SubPowerMode::LowPower
+        } else {
+            SubPowerMode::ConstantLatency
+        });
+        if state > 1 && clock::hfxo_running() {
+            clock::hfxo_stop();
+            self.sleep();
+            clock::hfxo_start_and_wait();
+        } else {
+            self.sleep();
+        }
+    }
+
     unsafe fn atomic<F, R>(&self, f: F) -> R
     where
         F: FnOnce() -> R,
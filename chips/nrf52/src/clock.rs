@@ -16,6 +16,7 @@
 //! * 32.768 kHz synthesized from HFCLK (LFSYNT)
 //!
 
+use kernel::platform::power::PowerConstraint;
 use kernel::utilities::cells::OptionalCell;
 use kernel::utilities::registers::interfaces::{Readable, Writeable};
 use kernel::utilities::registers::{
@@ -278,3 +279,31 @@ impl Clock {
             .write(LfClkSrc::SRC.val(clock_source as u32));
     }
 }
+
+/// Time from starting the HFXO until it is running, in microseconds.
+pub const HFXO_STARTUP_US: u32 = 400;
+
+/// Check if the high frequency clock is running from the HFXO.
+pub(crate) fn hfxo_running() -> bool {
+    CLOCK_BASE
+        .hfclkstat
+        .matches_all(HfClkStat::SRC::XTAL + HfClkStat::STATE::RUNNING)
+}
+
+/// Stop the HFXO. Peripherals that need the high frequency clock then run
+/// from the HFINT.
+pub(crate) fn hfxo_stop() {
+    CLOCK_BASE.tasks_hfclkstop.write(Control::ENABLE::SET);
+}
+
+/// Keep the chip from stopping the HFXO while it sleeps, by ruling out the
+/// sleep states that wake up no faster than the HFXO starts.
+pub(crate) fn require_hfxo(constraint: &PowerConstraint) {
+    constraint.set_max_wake_latency_us(HFXO_STARTUP_US - 1);
+}
+
+/// Start the HFXO and wait until it is running.
+pub(crate) fn hfxo_start_and_wait() {
+    CLOCK_BASE.tasks_hfclkstart.write(Control::ENABLE::SET);
+    while !hfxo_running() {}
+}
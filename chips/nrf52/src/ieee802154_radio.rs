@@ -5,6 +5,7 @@ use core::convert::TryFrom;
 use kernel;
 use kernel::hil::radio::{self, PowerClient};
 use kernel::hil::time::{Alarm, AlarmClient};
+use kernel::platform::power::PowerConstraint;
 use kernel::utilities::cells::{OptionalCell, TakeCell};
 use kernel::utilities::registers::interfaces::{Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, ReadOnly, ReadWrite, WriteOnly};
@@ -674,6 +675,7 @@ pub struct Radio<'p> {
     channel: Cell<RadioChannel>,
     transmitting: Cell<bool>,
     timer0: OptionalCell<&'p crate::timer::TimerAlarm<'p>>,
+    power_constraint: PowerConstraint<'static>,
 }
 
 impl<'a> AlarmClient for Radio<'a> {
@@ -700,9 +702,16 @@ impl<'p> Radio<'p> {
             channel: Cell::new(RadioChannel::DataChannel26),
             transmitting: Cell::new(false),
             timer0: OptionalCell::empty(),
+            power_constraint: PowerConstraint::new(),
         }
     }
 
+    /// The constraint that keeps the chip out of deep sleep while the radio
+    /// is powered.
+    pub fn power_constraint(&self) -> &PowerConstraint<'static> {
+        &self.power_constraint
+    }
+
     pub fn set_timer_ref(&self, timer: &'p crate::timer::TimerAlarm<'p>) {
         self.timer0.set(timer);
     }
@@ -743,6 +752,8 @@ impl<'p> Radio<'p> {
     }
 
     fn radio_on(&self) {
+        // The radio needs the HFXO, so keep the chip from stopping it.
+        crate::clock::require_hfxo(&self.power_constraint);
         // reset and enable power
         self.registers.power.write(Task::ENABLE::CLEAR);
         self.registers.power.write(Task::ENABLE::SET);
@@ -750,6 +761,7 @@ impl<'p> Radio<'p> {
 
     fn radio_off(&self) {
         self.registers.power.write(Task::ENABLE::CLEAR);
+        self.power_constraint.release();
     }
 
     fn set_tx_power(&self) {
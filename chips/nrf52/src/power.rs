@@ -365,3 +365,20 @@ impl<'a> Power<'a> {
         self.registers.gpregret.write(Byte::VALUE.val(val as u32));
     }
 }
+
+/// Sub power modes of System ON, which trade wake-up latency for power.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SubPowerMode {
+    /// Keep resources running for the shortest wake-up latency.
+    ConstantLatency,
+    /// Let the power management unit switch resources off while sleeping.
+    LowPower,
+}
+
+/// Select the sub power mode used while the CPU sleeps.
+pub fn set_sub_power_mode(mode: SubPowerMode) {
+    match mode {
+        SubPowerMode::ConstantLatency => POWER_BASE.task_constlat.write(Task::ENABLE::SET),
+        SubPowerMode::LowPower => POWER_BASE.task_lowpwr.write(Task::ENABLE::SET),
+    }
+}
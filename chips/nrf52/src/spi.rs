@@ -32,6 +32,7 @@
 use core::cell::Cell;
 use core::{cmp, ptr};
 use kernel::hil;
+use kernel::platform::power::PowerConstraint;
 use kernel::utilities::cells::{OptionalCell, TakeCell, VolatileCell};
 use kernel::utilities::registers::interfaces::{ReadWriteable, Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, ReadWrite, WriteOnly};
@@ -242,6 +243,7 @@ pub struct SPIM {
     tx_buf: TakeCell<'static, [u8]>,
     rx_buf: TakeCell<'static, [u8]>,
     transfer_len: Cell<usize>,
+    power_constraint: PowerConstraint<'static>,
 }
 
 impl SPIM {
@@ -255,9 +257,16 @@ impl SPIM {
             tx_buf: TakeCell::empty(),
             rx_buf: TakeCell::empty(),
             transfer_len: Cell::new(0),
+            power_constraint: PowerConstraint::new(),
         }
     }
 
+    /// The constraint that keeps the chip out of deep sleep during a
+    /// transfer.
+    pub fn power_constraint(&self) -> &PowerConstraint<'static> {
+        &self.power_constraint
+    }
+
     #[inline(never)]
     pub fn handle_interrupt(&self) {
         if self.registers.events_end.is_set(EVENT::EVENT) {
@@ -272,6 +281,7 @@ impl SPIM {
             self.registers.events_end.write(EVENT::EVENT::CLEAR);
 
             self.busy.set(false);
+            self.power_constraint.release();
 
             self.client.map(|client| match self.tx_buf.take() {
                 None => (),
@@ -391,6 +401,9 @@ impl hil::spi::SpiMaster for SPIM {
 
         // Start the transfer
         self.busy.set(true);
+        // Waiting for the HFXO to start when waking up would delay handling
+        // the end of the transfer.
+        crate::clock::require_hfxo(&self.power_constraint);
         self.registers.tasks_start.write(TASK::TASK::SET);
         Ok(())
     }
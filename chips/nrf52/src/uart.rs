@@ -10,6 +10,7 @@ use core;
 use core::cell::Cell;
 use core::cmp::min;
 use kernel::hil::uart;
+use kernel::platform::power::PowerConstraint;
 use kernel::utilities::cells::OptionalCell;
 use kernel::utilities::registers::interfaces::{Readable, Writeable};
 use kernel::utilities::registers::{register_bitfields, ReadOnly, ReadWrite, WriteOnly};
@@ -170,6 +171,7 @@ pub struct Uarte<'a> {
     rx_remaining_bytes: Cell<usize>,
     rx_abort_in_progress: Cell<bool>,
     offset: Cell<usize>,
+    power_constraint: PowerConstraint<'static>,
 }
 
 #[derive(Copy, Clone)]
@@ -192,6 +194,23 @@ impl<'a> Uarte<'a> {
             rx_remaining_bytes: Cell::new(0),
             rx_abort_in_progress: Cell::new(false),
             offset: Cell::new(0),
+            power_constraint: PowerConstraint::new(),
+        }
+    }
+
+    /// The constraint that keeps the chip out of deep sleep while a transmit
+    /// or receive is in progress.
+    pub fn power_constraint(&self) -> &PowerConstraint<'static> {
+        &self.power_constraint
+    }
+
+    // The baud rate is derived from the HFXO, so keep the chip from stopping
+    // it while the UART is in use.
+    fn update_power_constraint(&self) {
+        if self.tx_buffer.is_some() || self.rx_buffer.is_some() {
+            crate::clock::require_hfxo(&self.power_constraint);
+        } else {
+            self.power_constraint.release();
         }
     }
 
@@ -379,6 +398,8 @@ impl<'a> Uarte<'a> {
                 }
             }
         }
+
+        self.update_power_constraint();
     }
 
     /// Transmit one byte at the time and the client is responsible for polling
@@ -433,6 +454,7 @@ impl<'a> Uarte<'a> {
         self.registers.task_starttx.write(Task::ENABLE::SET);
 
         self.enable_tx_interrupts();
+        self.update_power_constraint();
     }
 }
 
@@ -515,6 +537,7 @@ impl<'a> uart::Receive<'a> for Uarte<'a> {
         self.registers.task_startrx.write(Task::ENABLE::SET);
 
         self.enable_rx_interrupts();
+        self.update_power_constraint();
         Ok(())
     }
 
@@ -19,7 +19,7 @@ use crate::interrupts;
 
 type QemuRv32VirtPMP = PMP<8>;
 
-pub struct QemuRv32VirtChip<'a, I: InterruptService<()> + 'a> {
+pub struct QemuRv32VirtChip<'a, I: InterruptService + 'a> {
     userspace_kernel_boundary: rv32i::syscall::SysCall,
     pmp: QemuRv32VirtPMP,
     plic: &'a Plic,
@@ -39,7 +39,7 @@ impl<'a> QemuRv32VirtDefaultPeripherals<'a> {
     }
 }
 
-impl<'a> InterruptService<()> for QemuRv32VirtDefaultPeripherals<'a> {
+impl<'a> InterruptService for QemuRv32VirtDefaultPeripherals<'a> {
     unsafe fn service_interrupt(&self, interrupt: u32) -> bool {
         match interrupt {
             interrupts::UART0 => {
@@ -49,13 +49,9 @@ impl<'a> InterruptService<()> for QemuRv32VirtDefaultPeripherals<'a> {
         }
         true
     }
-
-    unsafe fn service_deferred_call(&self, _: ()) -> bool {
-        false
-    }
 }
 
-impl<'a, I: InterruptService<()> + 'a> QemuRv32VirtChip<'a, I> {
+impl<'a, I: InterruptService + 'a> QemuRv32VirtChip<'a, I> {
     pub unsafe fn new(plic_interrupt_service: &'a I, timer: &'a sifive::clint::Clint<'a>) -> Self {
         Self {
             userspace_kernel_boundary: rv32i::syscall::SysCall::new(),
@@ -84,7 +80,7 @@ impl<'a, I: InterruptService<()> + 'a> QemuRv32VirtChip<'a, I> {
     }
 }
 
-impl<'a, I: InterruptService<()> + 'a> Chip for QemuRv32VirtChip<'a, I> {
+impl<'a, I: InterruptService + 'a> Chip for QemuRv32VirtChip<'a, I> {
     type MPU = QemuRv32VirtPMP;
     type UserspaceKernelBoundary = rv32i::syscall::SysCall;
 
@@ -1,13 +1,12 @@
 //! Chip trait setup.
 
 use core::fmt::Write;
-use kernel::deferred_call;
+use kernel::deferred_call::DeferredCallClient;
 use kernel::platform::chip::Chip;
 use kernel::platform::chip::InterruptService;
 
 use crate::adc;
 use crate::clocks::Clocks;
-use crate::deferred_call_tasks::DeferredCallTask;
 use crate::gpio::{RPPins, SIO};
 use crate::i2c;
 use crate::interrupts;
@@ -26,7 +25,7 @@ pub enum Processor {
     Processor1 = 1,
 }
 
-pub struct Rp2040<'a, I: InterruptService<DeferredCallTask> + 'a> {
+pub struct Rp2040<'a, I: InterruptService + 'a> {
     mpu: cortexm0p::mpu::MPU,
     userspace_kernel_boundary: cortexm0p::syscall::SysCall,
     interrupt_service: &'a I,
@@ -35,7 +34,7 @@ pub struct Rp2040<'a, I: InterruptService<DeferredCallTask> + 'a> {
     processor1_interrupt_mask: (u128, u128),
 }
 
-impl<'a, I: InterruptService<DeferredCallTask>> Rp2040<'a, I> {
+impl<'a, I: InterruptService> Rp2040<'a, I> {
     pub unsafe fn new(interrupt_service: &'a I, sio: &'a SIO) -> Self {
         Self {
             mpu: cortexm0p::mpu::MPU::new(),
@@ -48,7 +47,7 @@ impl<'a, I: InterruptService<DeferredCallTask>> Rp2040<'a, I> {
     }
 }
 
-impl<'a, I: InterruptService<DeferredCallTask>> Chip for Rp2040<'a, I> {
+impl<'a, I: InterruptService> Chip for Rp2040<'a, I> {
     type MPU = cortexm0p::mpu::MPU;
     type UserspaceKernelBoundary = cortexm0p::syscall::SysCall;
 
@@ -59,11 +58,7 @@ impl<'a, I: InterruptService<DeferredCallTask>> Chip for Rp2040<'a, I> {
                 Processor::Processor1 => self.processor1_interrupt_mask,
             };
             loop {
-                if let Some(task) = deferred_call::DeferredCall::next_pending() {
-                    if !self.interrupt_service.service_deferred_call(task) {
-                        panic!("unhandled deferred call");
-                    }
-                } else if let Some(interrupt) = cortexm0p::nvic::next_pending_with_mask(mask) {
+                if let Some(interrupt) = cortexm0p::nvic::next_pending_with_mask(mask) {
                     // ignore SIO_IRQ_PROC1 as it is intended for processor 1
                     // not able to unset its pending status
                     // probably only processor 1 can unset the pending by reading the fifo
@@ -88,7 +83,7 @@ impl<'a, I: InterruptService<DeferredCallTask>> Chip for Rp2040<'a, I> {
             Processor::Processor0 => self.processor0_interrupt_mask,
             Processor::Processor1 => self.processor1_interrupt_mask,
         };
-        unsafe { cortexm0p::nvic::has_pending_with_mask(mask) || deferred_call::has_tasks() }
+        unsafe { cortexm0p::nvic::has_pending_with_mask(mask) }
     }
 
     fn mpu(&self) -> &Self::MPU {
@@ -152,14 +147,16 @@ impl<'a> Rp2040DefaultPeripherals<'a> {
         }
     }
 
-    pub fn resolve_dependencies(&'a self) {
+    pub fn resolve_dependencies(&'static self) {
         self.spi0.set_clocks(&self.clocks);
         self.uart0.set_clocks(&self.clocks);
+        self.uart0.register();
+        self.uart1.register();
         self.i2c0.resolve_dependencies(&self.clocks, &self.resets);
     }
 }
 
-impl InterruptService<DeferredCallTask> for Rp2040DefaultPeripherals<'_> {
+impl InterruptService for Rp2040DefaultPeripherals<'_> {
     unsafe fn service_interrupt(&self, interrupt: u32) -> bool {
         match interrupt {
             interrupts::TIMER_IRQ_0 => {
@@ -197,12 +194,4 @@ impl InterruptService<DeferredCallTask> for Rp2040DefaultPeripherals<'_> {
             _ => false,
         }
     }
-
-    unsafe fn service_deferred_call(&self, task: DeferredCallTask) -> bool {
-        match task {
-            DeferredCallTask::Uart0 => self.uart0.handle_deferred_call(),
-            DeferredCallTask::Uart1 => self.uart1.handle_deferred_call(),
-        }
-        true
-    }
 }
@@ -3,7 +3,6 @@
 pub mod adc;
 pub mod chip;
 pub mod clocks;
-pub mod deferred_call_tasks;
 pub mod gpio;
 pub mod i2c;
 pub mod interrupts;
@@ -48,10 +48,9 @@
 //
 // - Support continuous-mode CRC
 
-use crate::deferred_call_tasks::Task;
 use crate::pm::{disable_clock, enable_clock, Clock, HSBClock, PBBClock};
 use core::cell::Cell;
-use kernel::deferred_call::DeferredCall;
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
 use kernel::hil::crc::{Client, Crc, CrcAlgorithm, CrcOutput};
 use kernel::utilities::cells::OptionalCell;
 use kernel::utilities::leasable_buffer::LeasableMutableBuffer;
@@ -66,8 +65,6 @@ use kernel::ErrorCode;
 pub const BASE_ADDRESS: StaticRef<CrccuRegisters> =
     unsafe { StaticRef::new(0x400A4000 as *const CrccuRegisters) };
 
-static DEFERRED_CALL: DeferredCall<Task> = unsafe { DeferredCall::new(Task::CRCCU) };
-
 #[repr(C)]
 pub struct CrccuRegisters {
     // From page 1005 of SAM4L manual
@@ -265,6 +262,8 @@ pub struct Crccu<'a> {
     // Must be aligned to a 512-byte boundary, which is guaranteed by
     // the struct definition.
     descriptor: Descriptor,
+
+    deferred_call: DeferredCall,
 }
 
 impl Crccu<'_> {
@@ -277,6 +276,7 @@ impl Crccu<'_> {
             current_full_buffer: Cell::new((0 as *mut u8, 0)),
             compute_requested: Cell::new(false),
             descriptor: Descriptor::new(),
+            deferred_call: DeferredCall::new(),
         }
     }
 
@@ -364,26 +364,6 @@ impl Crccu<'_> {
             }
         }
     }
-
-    pub fn handle_deferred_call(&self) {
-        // A deferred call is currently only issued on a call to
-        // compute, in which case we need to provide the CRC to the
-        // client
-        let result = post_process(
-            self.registers.sr.read(Status::CRC),
-            self.algorithm.unwrap_or_panic(), // Unwrap fail = crccu deferred call: no algorithm
-        );
-
-        // Reset the internal CRC state such that the next call to
-        // input will start a new CRC
-        self.registers.cr.write(Control::RESET::SET);
-        self.descriptor.ctrl.set(TCR::default().0);
-        self.compute_requested.set(false);
-
-        self.client.map(|client| {
-            client.crc_done(Ok(result));
-        });
-    }
 }
 
 // Implement the generic CRC interface with the CRCCU
@@ -533,7 +513,7 @@ impl<'a> Crc<'a> for Crccu<'a> {
 
         // Request a deferred call such that we can provide the result
         // back to the client
-        DEFERRED_CALL.set();
+        self.deferred_call.set();
 
         Ok(())
     }
@@ -542,3 +522,29 @@ impl<'a> Crc<'a> for Crccu<'a> {
         Crccu::disable(self);
     }
 }
+
+impl DeferredCallClient for Crccu<'_> {
+    fn handle_deferred_call(&self) {
+        // A deferred call is currently only issued on a call to
+        // compute, in which case we need to provide the CRC to the
+        // client
+        let result = post_process(
+            self.registers.sr.read(Status::CRC),
+            self.algorithm.unwrap_or_panic(), // Unwrap fail = crccu deferred call: no algorithm
+        );
+
+        // Reset the internal CRC state such that the next call to
+        // input will start a new CRC
+        self.registers.cr.write(Control::RESET::SET);
+        self.descriptor.ctrl.set(TCR::default().0);
+        self.compute_requested.set(false);
+
+        self.client.map(|client| {
+            client.crc_done(Ok(result));
+        });
+    }
+
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+    }
+}
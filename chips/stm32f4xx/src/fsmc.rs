@@ -1,6 +1,6 @@
 use crate::rcc;
 use core::cell::Cell;
-use kernel::deferred_call::DeferredCall;
+use kernel::deferred_call::{DeferredCall, DeferredCallClient};
 use kernel::hil::bus8080::{Bus8080, BusWidth, Client};
 use kernel::platform::chip::ClockInterface;
 use kernel::utilities::cells::{OptionalCell, TakeCell};
@@ -9,8 +9,6 @@ use kernel::utilities::registers::{register_bitfields, ReadWrite};
 use kernel::utilities::StaticRef;
 use kernel::ErrorCode;
 
-use crate::deferred_calls::DeferredCallTask;
-
 /// FSMC peripheral interface
 #[repr(C)]
 struct FsmcBankRegisters {
@@ -128,11 +126,6 @@ register_bitfields![u32,
     ]
 ];
 
-/// This mechanism allows us to schedule "interrupts" even if the hardware
-/// does not support them.
-static DEFERRED_CALL: DeferredCall<DeferredCallTask> =
-    unsafe { DeferredCall::new(DeferredCallTask::Fsmc) };
-
 const FSMC_BASE: StaticRef<FsmcBankRegisters> =
     unsafe { StaticRef::new(0xA000_0000 as *const FsmcBankRegisters) };
 
@@ -170,6 +163,8 @@ pub struct Fsmc<'a> {
     buffer: TakeCell<'static, [u8]>,
     bus_width: Cell<usize>,
     len: Cell<usize>,
+
+    deferred_call: DeferredCall,
 }
 
 impl<'a> Fsmc<'a> {
@@ -186,6 +181,7 @@ impl<'a> Fsmc<'a> {
             buffer: TakeCell::empty(),
             bus_width: Cell::new(1),
             len: Cell::new(0),
+            deferred_call: DeferredCall::new(),
         }
     }
 
@@ -312,7 +308,7 @@ impl Bus8080<'static> for Fsmc<'_> {
         match addr_width {
             BusWidth::Bits8 => {
                 self.write_reg(FsmcBanks::Bank1, addr as u16);
-                DEFERRED_CALL.set();
+                self.deferred_call.set();
                 Ok(())
             }
             _ => Err(ErrorCode::NOSUPPORT),
@@ -343,7 +339,7 @@ impl Bus8080<'static> for Fsmc<'_> {
             self.buffer.replace(buffer);
             self.bus_width.set(bytes);
             self.len.set(len);
-            DEFERRED_CALL.set();
+            self.deferred_call.set();
             Ok(())
         } else {
             Err((ErrorCode::NOMEM, buffer))
@@ -374,7 +370,7 @@ impl Bus8080<'static> for Fsmc<'_> {
             self.buffer.replace(buffer);
             self.bus_width.set(bytes);
             self.len.set(len);
-            DEFERRED_CALL.set();
+            self.deferred_call.set();
             Ok(())
         } else {
             Err((ErrorCode::NOMEM, buffer))
@@ -385,3 +381,13 @@ impl Bus8080<'static> for Fsmc<'_> {
         self.client.replace(client);
     }
 }
+
+impl DeferredCallClient for Fsmc<'_> {
+    fn handle_deferred_call(&self) {
+        self.handle_interrupt();
+    }
+
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+    }
+}
@@ -12,7 +12,6 @@ pub mod nvic;
 // Peripherals
 pub mod adc;
 pub mod dbg;
-pub mod deferred_calls;
 pub mod dma;
 pub mod exti;
 pub mod fsmc;
@@ -23,7 +23,7 @@ pub const IRQ_UART: u32 = 1;
 /// This is a fake value used to indicate a timer1 interrupt
 pub const IRQ_TIMER1: u32 = 0xFFFF_FFFF;
 
-pub struct SweRVolf<'a, I: InterruptService<()> + 'a> {
+pub struct SweRVolf<'a, I: InterruptService + 'a> {
     userspace_kernel_boundary: SysCall,
     pic: &'a Pic,
     scheduler_timer: swerv::eh1_timer::Timer<'static>,
@@ -45,7 +45,7 @@ impl<'a> SweRVolfDefaultPeripherals<'a> {
     }
 }
 
-impl<'a> InterruptService<()> for SweRVolfDefaultPeripherals<'a> {
+impl<'a> InterruptService for SweRVolfDefaultPeripherals<'a> {
     unsafe fn service_interrupt(&self, interrupt: u32) -> bool {
         match interrupt {
             IRQ_UART => {
@@ -60,13 +60,9 @@ impl<'a> InterruptService<()> for SweRVolfDefaultPeripherals<'a> {
         }
         true
     }
-
-    unsafe fn service_deferred_call(&self, _: ()) -> bool {
-        false
-    }
 }
 
-impl<'a, I: InterruptService<()> + 'a> SweRVolf<'a, I> {
+impl<'a, I: InterruptService + 'a> SweRVolf<'a, I> {
     pub unsafe fn new(
         pic_interrupt_service: &'a I,
         mtimer: &'static crate::syscon::SysCon,
@@ -101,7 +97,7 @@ impl<'a, I: InterruptService<()> + 'a> SweRVolf<'a, I> {
     }
 }
 
-impl<'a, I: InterruptService<()> + 'a> kernel::platform::chip::Chip for SweRVolf<'a, I> {
+impl<'a, I: InterruptService + 'a> kernel::platform::chip::Chip for SweRVolf<'a, I> {
     type MPU = ();
     type UserspaceKernelBoundary = SysCall;
 
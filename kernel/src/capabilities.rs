@@ -51,6 +51,10 @@
 /// otherwise managing processes.
 pub unsafe trait ProcessManagementCapability {}
 
+/// The `ProcessApprovalCapability` allows the holder to approve or reject the
+/// credentials of a process, and thereby decide whether it may run.
+pub unsafe trait ProcessApprovalCapability {}
+
 /// The `MainLoopCapability` capability allows the holder to start executing as
 /// well as manage the main scheduler loop in Tock. This is needed in a board's
 /// main.rs file to start the kernel. It also allows an external implementation
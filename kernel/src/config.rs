@@ -38,6 +38,12 @@ pub(crate) struct Config {
     /// system call and upcall, with details including the application ID, and
     /// system call or upcall parameters.
     pub(crate) trace_syscalls: bool,
+    /// Whether the kernel should record syscalls in a `SyscallTrace`.
+    ///
+    /// If enabled, and the board registered a trace buffer with
+    /// `Kernel::set_syscall_trace()`, the kernel will record each system call
+    /// in a compact binary form that can be read out while processes run.
+    pub(crate) trace_syscalls_buffer: bool,
 
     /// Whether the kernel should show debugging output when loading processes.
     ///
@@ -67,6 +73,14 @@ pub(crate) struct Config {
     // is identified, using configuration constants is the most effective
     // option.
     pub(crate) debug_panics: bool,
+
+    /// Whether the kernel should check that every deferred call that was
+    /// created was also registered before it enters the main loop, and panic
+    /// otherwise.
+    ///
+    /// This is enabled in debug builds, so that a missing registration is
+    /// found during development without panicking deployed kernels.
+    pub(crate) verify_deferred_calls: bool,
 }
 
 /// A unique instance of `Config` where compile-time configuration options are
@@ -76,6 +90,8 @@ pub(crate) struct Config {
 /// Cargo features.
 pub(crate) const CONFIG: Config = Config {
     trace_syscalls: cfg!(feature = "trace_syscalls"),
+    trace_syscalls_buffer: cfg!(feature = "trace_syscalls_buffer"),
     debug_load_processes: cfg!(feature = "debug_load_processes"),
     debug_panics: !cfg!(feature = "no_debug_panics"),
+    verify_deferred_calls: cfg!(debug_assertions),
 };
@@ -555,6 +555,14 @@ pub fn debug_println(args: Arguments) {
     writer.publish_bytes();
 }
 
+/// Write raw bytes, such as binary trace data, to the debug output.
+pub fn debug_write_bytes(bytes: &[u8]) {
+    let writer = unsafe { get_debug_writer() };
+
+    writer.write(bytes);
+    writer.publish_bytes();
+}
+
 fn write_header(writer: &mut DebugWriterWrapper, (file, line): &(&'static str, u32)) -> Result {
     writer.increment_count();
     let count = writer.get_count();
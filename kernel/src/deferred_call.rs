@@ -1,84 +1,187 @@
-//! Deferred call mechanism.
+//! Hardware-independent kernel interface for deferred calls.
 //!
-//! This is a tool to allow chip peripherals to schedule "interrupts"
-//! in the chip scheduler if the hardware doesn't support interrupts where
-//! they are needed.
-
-use core::cell::UnsafeCell;
-use core::convert::Into;
-use core::convert::TryFrom;
-use core::convert::TryInto;
-use core::intrinsics;
-use core::marker::Copy;
-use core::marker::Sync;
-
-/// AtomicUsize with no CAS operations that works on targets that have "no atomic
-/// support" according to their specification. This makes it work on thumbv6
-/// platforms.
-///
-/// Borrowed from https://github.com/japaric/heapless/blob/master/src/ring_buffer/mod.rs
-/// See: https://github.com/japaric/heapless/commit/37c8b5b63780ed8811173dc1ec8859cd99efa9ad
-struct AtomicUsize {
-    v: UnsafeCell<usize>,
-}
+//! This allows any struct in the kernel which implements [`DeferredCallClient`]
+//! to set and receive deferred calls. Chip drivers use them to schedule
+//! "interrupts" where the hardware does not generate one, and capsules use
+//! them to implement long-running in-kernel algorithms or software devices
+//! that are supposed to work like hardware devices. Deferred calls let the
+//! chip handle more important interrupts first, and let a kernel component
+//! return the function call stack up to the scheduler before it is called
+//! again.
+//!
+//! Each [`DeferredCall`] is registered once with the client it calls, usually
+//! by the component or the chip's peripherals struct that creates the client.
+//! Registered deferred calls are kept in a list, so there is no table to size
+//! and no per-chip enum of tasks. The kernel services pending deferred calls
+//! from its main loop, after hardware interrupts.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust
+//! use kernel::deferred_call::{DeferredCall, DeferredCallClient};
+//! use kernel::static_init;
+//!
+//! struct SomeCapsule {
+//!     deferred_call: DeferredCall,
+//! }
+//!
+//! impl SomeCapsule {
+//!     pub fn new() -> Self {
+//!         SomeCapsule {
+//!             deferred_call: DeferredCall::new(),
+//!         }
+//!     }
+//!
+//!     fn start_operation(&self) {
+//!         // Finish the operation from the kernel loop.
+//!         self.deferred_call.set();
+//!     }
+//! }
+//!
+//! impl DeferredCallClient for SomeCapsule {
+//!     fn handle_deferred_call(&self) {
+//!         // Handle the deferred call, e.g. call back the client.
+//!     }
+//!
+//!     fn register(&'static self) {
+//!         self.deferred_call.register(self);
+//!     }
+//! }
+//!
+//! // Register the capsule once it is in its final location.
+//! let some_capsule = unsafe { static_init!(SomeCapsule, SomeCapsule::new()) };
+//! some_capsule.register();
+//! ```
+//!
+//! Every deferred call that was created should also be registered. Debug
+//! builds of the kernel check this with [`DeferredCall::verify_setup()`]
+//! when they enter the main loop.
 
-impl AtomicUsize {
-    pub(crate) const fn new(v: usize) -> AtomicUsize {
-        AtomicUsize {
-            v: UnsafeCell::new(v),
-        }
-    }
+use core::cell::Cell;
 
-    pub(crate) fn load_relaxed(&self) -> usize {
-        unsafe { intrinsics::atomic_load_relaxed(self.v.get()) }
-    }
+use crate::collections::list::{List, ListLink, ListNode};
+use crate::utilities::cells::OptionalCell;
 
-    pub(crate) fn store_relaxed(&self, val: usize) {
-        unsafe { intrinsics::atomic_store_relaxed(self.v.get(), val) }
-    }
+/// This trait should be implemented by structs that receive deferred calls.
+pub trait DeferredCallClient {
+    /// Called by the kernel loop after the deferred call was set.
+    fn handle_deferred_call(&self);
 
-    pub(crate) fn fetch_or_relaxed(&self, val: usize) {
-        unsafe { intrinsics::atomic_store_relaxed(self.v.get(), self.load_relaxed() | val) }
-    }
+    /// Register the client with its deferred call. This should be implemented
+    /// as `self.deferred_call.register(self);`.
+    fn register(&'static self);
 }
 
-unsafe impl Sync for AtomicUsize {}
+// The globals below are only accessed by the functions in this file through
+// shared references. Tock is single threaded, so each is only ever accessed
+// from the kernel thread.
+
+/// All registered deferred calls.
+static mut DEFERRED_CALLS: List<'static, DeferredCall> = List::new();
+
+/// Number of deferred calls that have been created.
+static mut CREATED: Cell<usize> = Cell::new(0);
 
-static DEFERRED_CALL: AtomicUsize = AtomicUsize::new(0);
+/// Number of deferred calls that have been registered.
+static mut REGISTERED: Cell<usize> = Cell::new(0);
 
-/// Are there any pending `DeferredCall`s?
-pub fn has_tasks() -> bool {
-    DEFERRED_CALL.load_relaxed() != 0
+/// Number of registered deferred calls that are pending.
+static mut PENDING: Cell<usize> = Cell::new(0);
+
+/// A way to generate an asynchronous call to a client without a hardware
+/// interrupt.
+pub struct DeferredCall {
+    pending: Cell<bool>,
+    client: OptionalCell<&'static dyn DeferredCallClient>,
+    next: ListLink<'static, DeferredCall>,
 }
 
-/// Represents a way to generate an asynchronous call without a hardware
-/// interrupt. Supports up to 32 possible deferrable tasks.
-pub struct DeferredCall<T>(T);
+impl ListNode<'static, DeferredCall> for DeferredCall {
+    fn next(&'static self) -> &'static ListLink<'static, DeferredCall> {
+        &self.next
+    }
+}
 
-impl<T: Into<usize> + TryFrom<usize> + Copy> DeferredCall<T> {
-    /// Creates a new DeferredCall
-    ///
-    /// Only create one per task, preferably in the module that it will be used
-    /// in.
-    pub const unsafe fn new(task: T) -> Self {
-        DeferredCall(task)
+impl DeferredCall {
+    /// Create a new deferred call. It must be registered with
+    /// [`DeferredCall::register()`] before it is used.
+    pub fn new() -> Self {
+        // SAFETY: the kernel is single threaded and the counter is only
+        // accessed through shared references.
+        let created = unsafe { &CREATED };
+        created.set(created.get() + 1);
+        DeferredCall {
+            pending: Cell::new(false),
+            client: OptionalCell::empty(),
+            next: ListLink::empty(),
+        }
     }
 
-    /// Set the `DeferredCall` as pending
+    /// Register the client that is called when this deferred call is
+    /// serviced. Registering a deferred call again replaces its client.
+    pub fn register(&'static self, client: &'static dyn DeferredCallClient) {
+        if self.client.replace(client).is_none() {
+            let registered = unsafe { &REGISTERED };
+            registered.set(registered.get() + 1);
+            unsafe { DEFERRED_CALLS.push_head(self) };
+        }
+    }
+
+    /// Schedule a call to the client of this deferred call. Setting a
+    /// deferred call that is already pending has no effect. Deferred calls
+    /// that have not been registered are never called.
     pub fn set(&self) {
-        DEFERRED_CALL.fetch_or_relaxed(1 << self.0.into() as usize);
+        if !self.pending.get() && self.client.is_some() {
+            self.pending.set(true);
+            let pending = unsafe { &PENDING };
+            pending.set(pending.get() + 1);
+        }
     }
 
-    /// Gets and clears the next pending `DeferredCall`
-    pub fn next_pending() -> Option<T> {
-        let val = DEFERRED_CALL.load_relaxed();
-        if val == 0 {
-            None
-        } else {
-            let bit = val.trailing_zeros() as usize;
-            let new_val = val & !(1 << bit);
-            DEFERRED_CALL.store_relaxed(new_val);
-            bit.try_into().ok()
+    /// Whether the deferred call has been set and not yet serviced.
+    pub fn is_pending(&self) -> bool {
+        self.pending.get()
+    }
+
+    /// Whether any deferred calls are waiting to be serviced.
+    pub fn has_tasks() -> bool {
+        unsafe { PENDING.get() != 0 }
+    }
+
+    /// Service pending deferred calls while `f` returns `true`, in a single
+    /// pass over the registered deferred calls. Each deferred call is serviced
+    /// at most once per pass, so a client that sets its deferred call again
+    /// from `handle_deferred_call()` is only called again in the next pass,
+    /// after the other deferred calls and processes had their turn.
+    pub fn service_pending_while<F: Fn() -> bool>(f: F) {
+        for call in unsafe { DEFERRED_CALLS.iter() } {
+            if !Self::has_tasks() || !f() {
+                break;
+            }
+            if call.pending.get() {
+                call.pending.set(false);
+                let pending = unsafe { &PENDING };
+                pending.set(pending.get() - 1);
+                call.client.map(|client| client.handle_deferred_call());
+            }
+        }
+    }
+
+    /// Check that every deferred call that was created was also registered,
+    /// and panic otherwise. A deferred call that was never registered is
+    /// usually a component or chip that forgot to register a driver, whose
+    /// operations would then never complete.
+    ///
+    /// Debug builds of the kernel call this when they enter the main loop.
+    pub fn verify_setup() {
+        let created = unsafe { CREATED.get() };
+        let registered = unsafe { REGISTERED.get() };
+        if created != registered {
+            panic!(
+                "{} deferred calls were created but only {} were registered",
+                created, registered
+            );
         }
     }
 }
@@ -1720,6 +1720,30 @@ impl<T: Default, Upcalls: UpcallSize, AllowROs: AllowRoSize, AllowRWs: AllowRwSi
         Ok(pg.enter_with_allocator(fun))
     }
 
+    /// Release the grant of a specific process.
+    ///
+    /// A capsule calls this once it no longer needs to keep state for the
+    /// process, so that the memory can be used by the process again. Pending
+    /// upcalls of this grant are removed from the process. The `T` stored in
+    /// the grant is not dropped. If the grant is entered again later it is
+    /// allocated and initialized anew.
+    ///
+    /// This fails if the process is invalid or inactive, or if the grant is
+    /// currently entered.
+    pub fn release(&self, processid: ProcessId) -> Result<(), Error> {
+        self.kernel
+            .process_map_or(Err(Error::NoSuchApp), processid, |process| {
+                process.free_grant(self.grant_num)?;
+                for subscribe_num in 0..Upcalls::COUNT {
+                    process.remove_pending_upcalls(UpcallId {
+                        driver_num: self.driver_num,
+                        subscribe_num: subscribe_num as usize,
+                    });
+                }
+                Ok(())
+            })
+    }
+
     /// Run a function on the grant for each active process if the grant has
     /// been allocated for that process.
     ///
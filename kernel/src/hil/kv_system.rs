@@ -92,6 +92,21 @@ pub trait StoreClient<K: KeyType> {
     /// `result`: Nothing on success, 'ErrorCode' on error
     /// `key`: The key buffer
     fn delete_complete(&self, result: Result<(), ErrorCode>, key: &'static mut [u8]);
+
+    /// This callback is called when the next_key operation completes
+    ///
+    /// `result`: Nothing on success, 'ErrorCode' on error
+    /// `key`: The hashed key that was found
+    /// `value_length`: The length of the value stored with `key`
+    /// `next_cursor`: The cursor to pass to `next_key()` to continue after
+    ///                `key`
+    fn next_key_complete(
+        &self,
+        result: Result<(), ErrorCode>,
+        key: &K,
+        value_length: usize,
+        next_cursor: usize,
+    );
 }
 
 /// Implement this trait and use `set_client()` in order to receive callbacks.
@@ -142,6 +157,20 @@ pub trait Client<K: KeyType> {
     ///
     /// `result`: Nothing on success, 'ErrorCode' on error
     fn garbage_collect_complete(&self, result: Result<(), ErrorCode>);
+
+    /// This callback is called when the get_next_key operation completes
+    ///
+    /// `result`: Nothing on success, 'ErrorCode' on error
+    /// `next_cursor`: The cursor to pass to `get_next_key()` to continue
+    ///                after the key that was found
+    /// `key`: The key buffer, which contains the hashed key that was found on
+    ///        success
+    fn get_next_key_complete(
+        &self,
+        result: Result<(), ErrorCode>,
+        next_cursor: usize,
+        key: &'static mut K,
+    );
 }
 
 pub trait KVSystem<'a> {
@@ -255,4 +284,27 @@ pub trait KVSystem<'a> {
     ///    `INVAL`: An invalid parameter was passed
     ///    `NODEVICE`: No KV store was setup
     fn garbage_collect(&self) -> Result<usize, Result<(), ErrorCode>>;
+
+    /// Finds the next valid key stored after `cursor`.
+    ///
+    /// Keys are not returned in any particular order. Adding or removing keys
+    /// while iterating might cause keys to be skipped or returned twice.
+    ///
+    /// `cursor`: `0` to start with the first key, otherwise the cursor
+    ///           returned by the previous `get_next_key_complete()` callback.
+    /// `key`: A buffer to store the hashed key that is found.
+    ///
+    /// On success nothing will be returned.
+    /// On error the key and a `Result<(), ErrorCode>` will be returned.
+    ///
+    /// The possible `Result<(), ErrorCode>`s are:
+    ///    `BUSY`: An operation is already in progress
+    ///    `INVAL`: An invalid parameter was passed
+    ///    `NODEVICE`: No KV store was setup
+    ///    `ENOSUPPORT`: There are no more keys.
+    fn get_next_key(
+        &self,
+        cursor: usize,
+        key: &'static mut Self::K,
+    ) -> Result<(), (&'static mut Self::K, Result<(), ErrorCode>)>;
 }
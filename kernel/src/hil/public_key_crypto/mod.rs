@@ -2,3 +2,4 @@
 
 pub mod keys;
 pub mod rsa_math;
+pub mod signature;
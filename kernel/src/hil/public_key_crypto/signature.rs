@@ -0,0 +1,45 @@
+//! Interface for verifying signatures.
+
+use crate::ErrorCode;
+
+/// This trait provides callbacks for when the verification has completed.
+///
+/// 'HL' is the length of the hash and 'SL' the length of the signature.
+pub trait ClientVerify<const HL: usize, const SL: usize> {
+    /// Called when the verification is complete. `hash` and `signature` are
+    /// the buffers passed to `verify()`. On `Ok` the `bool` indicates whether
+    /// `signature` is a valid signature of `hash`. Valid `ErrorCode` values
+    /// are:
+    ///  - OFF: the underlying engine is powered down and cannot be used.
+    ///  - NODEVICE: no public key has been set.
+    ///  - FAIL: an internal failure.
+    fn verification_done(
+        &self,
+        result: Result<bool, ErrorCode>,
+        hash: &'static mut [u8; HL],
+        signature: &'static mut [u8; SL],
+    );
+}
+
+/// Verifies a signature of a hash with a public key. The public key is
+/// configured by the implementation, for example through the
+/// `public_key_crypto::keys` traits.
+///
+/// 'HL' is the length of the hash and 'SL' the length of the signature.
+pub trait SignatureVerify<'a, const HL: usize, const SL: usize> {
+    /// Set the client instance which will receive the `verification_done()`
+    /// callback.
+    fn set_verify_client(&'a self, client: &'a dyn ClientVerify<HL, SL>);
+
+    /// Verify that `signature` is a valid signature of `hash`. The result is
+    /// returned in a `verification_done` callback. On error the return value
+    /// contains the buffers passed in. Valid `ErrorCode` values are:
+    ///  - OFF: the underlying engine is powered down and cannot be used.
+    ///  - BUSY: a verification is already in progress.
+    ///  - NODEVICE: no public key has been set.
+    fn verify(
+        &'a self,
+        hash: &'static mut [u8; HL],
+        signature: &'static mut [u8; SL],
+    ) -> Result<(), (ErrorCode, &'static mut [u8; HL], &'static mut [u8; SL])>;
+}
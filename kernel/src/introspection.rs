@@ -16,6 +16,7 @@ use crate::capabilities::ProcessManagementCapability;
 use crate::kernel::Kernel;
 use crate::process;
 use crate::process::ProcessId;
+use crate::syscall::SyscallClass;
 use crate::utilities::cells::NumericCellExt;
 
 /// This struct provides the inspection functions.
@@ -91,6 +92,49 @@ impl KernelInfo {
             .process_map_or(0, app, |process| process.debug_syscall_count())
     }
 
+    /// Returns the number of syscalls of the given class the app has called.
+    pub fn number_app_syscalls_of_class(
+        &self,
+        app: ProcessId,
+        class: SyscallClass,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> usize {
+        self.kernel
+            .process_map_or(0, app, |process| process.debug_syscall_class_count(class))
+    }
+
+    /// Returns the number of upcalls from capsules the app has executed.
+    pub fn number_app_upcalls(
+        &self,
+        app: ProcessId,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> usize {
+        self.kernel
+            .process_map_or(0, app, |process| process.debug_upcall_count())
+    }
+
+    /// Returns the CPU time in microseconds the app has used since it was
+    /// last started. Time is only measured if the chip provides a scheduler
+    /// timer.
+    pub fn app_cpu_time_us(
+        &self,
+        app: ProcessId,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> u64 {
+        self.kernel
+            .process_map_or(0, app, |process| process.debug_cpu_time_us())
+    }
+
+    /// Returns the CPU budget in microseconds of the app, if it has one.
+    pub fn app_cpu_budget_us(
+        &self,
+        app: ProcessId,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> Option<u64> {
+        self.kernel
+            .process_map_or(None, app, |process| process.get_cpu_budget())
+    }
+
     /// Returns the number of dropped upcalls the app has experience.
     /// Upcalls can be dropped if the queue for the app is full when a capsule
     /// tries to schedule a upcall.
@@ -123,6 +167,51 @@ impl KernelInfo {
             .process_map_or(0, app, |process| process.debug_timeslice_expiration_count())
     }
 
+    /// Returns the most stack, in bytes, the app has used since it was last
+    /// started, if the app told the kernel where its stack starts.
+    pub fn app_stack_high_water(
+        &self,
+        app: ProcessId,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> Option<usize> {
+        self.kernel.process_map_or(None, app, |process| {
+            let addresses = process.get_addresses();
+            match (addresses.sram_stack_top, addresses.sram_stack_high_water) {
+                (Some(top), Some(lowest)) => Some(top.saturating_sub(lowest)),
+                _ => None,
+            }
+        })
+    }
+
+    /// Returns the largest heap, in bytes, the app has had since it was last
+    /// started, if the app told the kernel where its heap starts.
+    pub fn app_heap_high_water(
+        &self,
+        app: ProcessId,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> Option<usize> {
+        self.kernel.process_map_or(None, app, |process| {
+            let addresses = process.get_addresses();
+            addresses
+                .sram_heap_start
+                .map(|start| addresses.sram_app_brk_high_water.saturating_sub(start))
+        })
+    }
+
+    /// Returns the most process-accessible memory, in bytes, the app has had
+    /// since it was last started. Together with the kernel's grant region
+    /// this is what the app's `minimum_ram_size` needs to cover.
+    pub fn app_memory_high_water(
+        &self,
+        app: ProcessId,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> usize {
+        self.kernel.process_map_or(0, app, |process| {
+            let addresses = process.get_addresses();
+            addresses.sram_app_brk_high_water - addresses.sram_start
+        })
+    }
+
     /// Returns a tuple of the (the number of grants in the grant region this
     /// app has allocated, total number of grants that exist in the system).
     pub fn number_app_grant_uses(
@@ -143,6 +232,42 @@ impl KernelInfo {
         (used, number_of_grants)
     }
 
+    /// Returns the memory used by the grant `grant_num` of the app, or `None`
+    /// if the app has not allocated that grant.
+    pub fn app_grant_usage(
+        &self,
+        app: ProcessId,
+        grant_num: usize,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> Option<process::GrantUsage> {
+        self.kernel
+            .process_map_or(None, app, |process| process.get_grant_usage(grant_num))
+    }
+
+    /// Returns the total CPU time in microseconds all processes have used
+    /// since they were last started.
+    pub fn cpu_time_us(&self, _capability: &dyn ProcessManagementCapability) -> u64 {
+        let time: Cell<u64> = Cell::new(0);
+        self.kernel.process_each(|proc| {
+            time.set(time.get() + proc.debug_cpu_time_us());
+        });
+        time.get()
+    }
+
+    /// Returns the total number of syscalls of the given class all processes
+    /// have called since they were last started.
+    pub fn number_syscalls_of_class(
+        &self,
+        class: SyscallClass,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> usize {
+        let count: Cell<usize> = Cell::new(0);
+        self.kernel.process_each(|proc| {
+            count.add(proc.debug_syscall_class_count(class));
+        });
+        count.get()
+    }
+
     /// Returns the total number of times all processes have exceeded
     /// their timeslices.
     pub fn timeslice_expirations(&self, _capability: &dyn ProcessManagementCapability) -> usize {
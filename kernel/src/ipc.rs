@@ -1,14 +1,22 @@
 //! Inter-process communication mechanism for Tock.
 //!
-//! This is a special syscall driver that allows userspace applications to
-//! share memory.
+//! This module provides two special syscall drivers:
+//!
+//! - [`IPC`] allows userspace applications to share memory. Services are
+//!   discovered by their package name, and any process may notify any other.
+//! - [`MessageIPC`] copies bounded messages between processes, so that the
+//!   processes do not need access to each other's memory. A client must
+//!   connect to a service before sending it requests, and a service only
+//!   accepts clients listed in the IPC allow list of its TBF header. Services
+//!   can only send replies to clients connected to them.
 
 use crate::capabilities::MemoryAllocationCapability;
 use crate::grant::{AllowRoCount, AllowRwCount, Grant, UpcallCount};
 use crate::kernel::Kernel;
+use crate::platform::mpu;
 use crate::process;
-use crate::process::ProcessId;
-use crate::processbuffer::ReadableProcessBuffer;
+use crate::process::{Process, ProcessId, ShortID};
+use crate::processbuffer::{ReadableProcessBuffer, ReadableProcessSlice, WriteableProcessBuffer};
 use crate::syscall_driver::{CommandReturn, SyscallDriver};
 use crate::ErrorCode;
 
@@ -82,7 +90,12 @@ impl<const NUM_PROCS: u8> IPC<NUM_PROCS> {
                         self.data
                             .kernel
                             .process_map_or(None, schedule_on, |process| {
-                                process.add_mpu_region(slice.ptr(), slice.len(), slice.len())
+                                process.add_mpu_region(
+                                    slice.ptr(),
+                                    slice.len(),
+                                    slice.len(),
+                                    mpu::Permissions::ReadWriteOnly,
+                                )
                             });
                         (slice.len(), slice.ptr() as usize)
                     }
@@ -238,3 +251,347 @@ impl<const NUM_PROCS: u8> SyscallDriver for IPC<NUM_PROCS> {
         self.data.enter(processid, |_, _| {})
     }
 }
+
+/// Syscall number of the message-passing IPC driver.
+pub const MESSAGE_DRIVER_NUM: usize = 0x10002;
+
+/// The largest message, in bytes, that message-passing IPC copies between
+/// processes.
+pub const MAX_MESSAGE_SIZE: usize = 32;
+
+/// The number of messages that can wait in the mailbox of a process.
+pub const MAILBOX_DEPTH: usize = 4;
+
+/// The number of services a process can be connected to at the same time.
+pub const MAX_CONNECTIONS: usize = 4;
+
+/// Ids for message-passing IPC upcalls
+mod message_upcall {
+    /// A message was added to the mailbox of the process.
+    pub(super) const MESSAGE: usize = 0;
+    /// The number of upcalls the kernel stores for this grant.
+    pub(super) const COUNT: u8 = 1;
+}
+
+/// Ids for message-passing IPC read-only allow buffers
+mod message_ro_allow {
+    /// Package name of the service to connect to.
+    pub(super) const SEARCH: usize = 0;
+    /// Message to send.
+    pub(super) const MESSAGE: usize = 1;
+    /// The number of allow buffers the kernel stores for this grant.
+    pub(super) const COUNT: u8 = 2;
+}
+
+/// Ids for message-passing IPC read-write allow buffers
+mod message_rw_allow {
+    /// Buffer that received messages are copied into.
+    pub(super) const RECEIVE: usize = 0;
+    /// The number of allow buffers the kernel stores for this grant.
+    pub(super) const COUNT: u8 = 1;
+}
+
+/// Whether a message is a request from a client or a reply from a service.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MessageKind {
+    Request = 0,
+    Reply = 1,
+}
+
+/// A message waiting in a mailbox.
+#[derive(Copy, Clone)]
+struct Message {
+    /// Index of the process that sent the message.
+    sender: usize,
+    kind: MessageKind,
+    length: usize,
+    data: [u8; MAX_MESSAGE_SIZE],
+}
+
+/// State that is stored in each process's grant region to support
+/// message-passing IPC.
+#[derive(Default)]
+struct Mailbox {
+    /// Messages waiting to be received, oldest first.
+    messages: [Option<Message>; MAILBOX_DEPTH],
+    /// The services this process is connected to.
+    connections: [Option<ProcessId>; MAX_CONNECTIONS],
+}
+
+impl Mailbox {
+    fn is_connected(&self, service: ProcessId) -> bool {
+        self.connections.iter().any(|c| *c == Some(service))
+    }
+
+    fn pending(&self) -> usize {
+        self.messages.iter().filter(|m| m.is_some()).count()
+    }
+}
+
+/// The message-passing IPC mechanism struct.
+pub struct MessageIPC {
+    /// The grant regions for each process that hold the mailboxes.
+    data: Grant<
+        Mailbox,
+        UpcallCount<{ message_upcall::COUNT }>,
+        AllowRoCount<{ message_ro_allow::COUNT }>,
+        AllowRwCount<{ message_rw_allow::COUNT }>,
+    >,
+}
+
+impl MessageIPC {
+    pub fn new(
+        kernel: &'static Kernel,
+        driver_num: usize,
+        capability: &dyn MemoryAllocationCapability,
+    ) -> Self {
+        Self {
+            data: kernel.create_grant(driver_num, capability),
+        }
+    }
+
+    /// Find the process with the given index.
+    fn find_process(&self, index: usize) -> Option<ProcessId> {
+        self.data
+            .kernel
+            .process_until(|p| match p.processid().index() {
+                Some(i) if i == index => Some(p.processid()),
+                _ => None,
+            })
+    }
+
+    /// Find the process with the package name in `name`.
+    fn find_service(&self, name: &ReadableProcessSlice) -> Option<ProcessId> {
+        self.data.kernel.process_until(|p| {
+            let s = p.get_process_name().as_bytes();
+            if s.len() == name.len() && s.iter().zip(name.iter()).all(|(c1, c2)| *c1 == c2.get()) {
+                Some(p.processid())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Whether the IPC allow list of `service` admits a client with the
+    /// `ShortID` `client`. Processes without an allow list are not services.
+    /// A client id of 0 is an empty entry and admits no client, so clients
+    /// with a `LocallyUnique` `ShortID` can never connect.
+    fn client_allowed(client: ShortID, service: &dyn Process) -> bool {
+        let client_id = match client {
+            ShortID::Fixed(short_id) => short_id.get(),
+            ShortID::LocallyUnique => return false,
+        };
+        service
+            .get_ipc_allow_list()
+            .map_or(false, |(count, client_ids)| {
+                client_ids
+                    .iter()
+                    .take(count)
+                    .any(|&id| id != 0 && id == client_id)
+            })
+    }
+
+    /// Connect the process `appid` to the service named in its `SEARCH`
+    /// buffer, and return the descriptor of the service.
+    fn connect(&self, appid: ProcessId) -> Result<u32, ErrorCode> {
+        let client_id = self
+            .data
+            .kernel
+            .process_map_or(ShortID::LocallyUnique, appid, |p| p.short_app_id());
+
+        self.data
+            .enter(appid, |mailbox, kernel_data| {
+                let service = kernel_data
+                    .get_readonly_processbuffer(message_ro_allow::SEARCH)
+                    .and_then(|search| search.enter(|name| self.find_service(name)))
+                    .map_err(|_| ErrorCode::INVAL)?
+                    .ok_or(ErrorCode::NODEVICE)?;
+
+                // Clients cannot tell services they may not connect to from
+                // services that do not exist.
+                let allowed = self
+                    .data
+                    .kernel
+                    .process_map_or(false, service, |p| Self::client_allowed(client_id, p));
+                if service == appid || !allowed {
+                    return Err(ErrorCode::NODEVICE);
+                }
+
+                if !mailbox.is_connected(service) {
+                    // Reuse the connection of a service that no longer runs
+                    // if there is no free slot.
+                    let slot = mailbox
+                        .connections
+                        .iter_mut()
+                        .find(|c| match c {
+                            Some(id) => self.data.kernel.process_map_or(true, *id, |_| false),
+                            None => true,
+                        })
+                        .ok_or(ErrorCode::NOMEM)?;
+                    *slot = Some(service);
+                }
+
+                service.index().map(|i| i as u32).ok_or(ErrorCode::NODEVICE)
+            })
+            .unwrap_or(Err(ErrorCode::NOMEM))
+    }
+
+    /// Copy the message in the `MESSAGE` buffer of `from` into the mailbox of
+    /// `to`.
+    ///
+    /// Requests can only be sent to services `from` is connected to, and
+    /// replies only to clients connected to `from`.
+    fn send(&self, from: ProcessId, to: ProcessId, kind: MessageKind) -> Result<(), ErrorCode> {
+        // Entering the grant of the same process twice is not possible.
+        if from == to {
+            return Err(ErrorCode::INVAL);
+        }
+        let sender = from.index().ok_or(ErrorCode::INVAL)?;
+
+        self.data
+            .enter(from, |from_mailbox, from_data| {
+                if kind == MessageKind::Request && !from_mailbox.is_connected(to) {
+                    return Err(ErrorCode::INVAL);
+                }
+
+                let mut message = Message {
+                    sender,
+                    kind,
+                    length: 0,
+                    data: [0; MAX_MESSAGE_SIZE],
+                };
+                from_data
+                    .get_readonly_processbuffer(message_ro_allow::MESSAGE)
+                    .and_then(|buffer| {
+                        buffer.enter(|payload| {
+                            let dest = message
+                                .data
+                                .get_mut(0..payload.len())
+                                .ok_or(ErrorCode::SIZE)?;
+                            payload.copy_to_slice(dest);
+                            message.length = payload.len();
+                            Ok(())
+                        })
+                    })
+                    .unwrap_or(Err(ErrorCode::INVAL))?;
+
+                self.data
+                    .enter(to, |to_mailbox, to_data| {
+                        if kind == MessageKind::Reply && !to_mailbox.is_connected(from) {
+                            return Err(ErrorCode::INVAL);
+                        }
+                        let slot = to_mailbox
+                            .messages
+                            .iter_mut()
+                            .find(|m| m.is_none())
+                            .ok_or(ErrorCode::BUSY)?;
+                        *slot = Some(message);
+                        let _ = to_data.schedule_upcall(
+                            message_upcall::MESSAGE,
+                            (sender, kind as usize, to_mailbox.pending()),
+                        );
+                        Ok(())
+                    })
+                    .unwrap_or(Err(ErrorCode::INVAL))
+            })
+            .unwrap_or(Err(ErrorCode::NOMEM))
+    }
+
+    /// Copy the oldest message in the mailbox of `appid` into its `RECEIVE`
+    /// buffer and remove it from the mailbox.
+    fn receive(&self, appid: ProcessId) -> Result<(usize, usize, MessageKind), ErrorCode> {
+        self.data
+            .enter(appid, |mailbox, kernel_data| {
+                let message = mailbox.messages[0].ok_or(ErrorCode::FAIL)?;
+
+                // If the buffer is too small the message stays in the mailbox,
+                // so that it can be received with a larger buffer.
+                kernel_data
+                    .get_readwrite_processbuffer(message_rw_allow::RECEIVE)
+                    .and_then(|buffer| {
+                        buffer.mut_enter(|dest| {
+                            dest.get(0..message.length)
+                                .ok_or(ErrorCode::SIZE)
+                                .map(|dest| dest.copy_from_slice(&message.data[..message.length]))
+                        })
+                    })
+                    .unwrap_or(Err(ErrorCode::INVAL))?;
+
+                mailbox.messages.rotate_left(1);
+                mailbox.messages[MAILBOX_DEPTH - 1] = None;
+                Ok((message.sender, message.length, message.kind))
+            })
+            .unwrap_or(Err(ErrorCode::NOMEM))
+    }
+}
+
+impl SyscallDriver for MessageIPC {
+    /// Connects to services and sends and receives messages.
+    ///
+    /// ### `command_num`
+    ///
+    /// - `0`: Driver check, always returns Ok(())
+    /// - `1`: Connect to the service with the package name passed to
+    ///        `allow_readonly` 0. Returns the service descriptor if the service
+    ///        exists and its IPC allow list admits this process, otherwise
+    ///        returns `NODEVICE`.
+    /// - `2`: Send the message passed to `allow_readonly` 1 as a request to the
+    ///        connected service with descriptor `target_id`.
+    /// - `3`: Send the message passed to `allow_readonly` 1 as a reply to the
+    ///        client with descriptor `target_id`. The client must be connected
+    ///        to this process.
+    /// - `4`: Copy the oldest message in the mailbox into the buffer passed to
+    ///        `allow_readwrite` 0. Returns the descriptor of the sender, the
+    ///        length of the message and its kind (0 for a request, 1 for a
+    ///        reply).
+    /// - `5`: Disconnect from the service with descriptor `target_id`.
+    fn command(
+        &self,
+        command_number: usize,
+        target_id: usize,
+        _: usize,
+        appid: ProcessId,
+    ) -> CommandReturn {
+        match command_number {
+            0 => CommandReturn::success(),
+            1 => match self.connect(appid) {
+                Ok(descriptor) => CommandReturn::success_u32(descriptor),
+                Err(e) => CommandReturn::failure(e),
+            },
+            2 | 3 => {
+                let kind = if command_number == 2 {
+                    MessageKind::Request
+                } else {
+                    MessageKind::Reply
+                };
+                self.find_process(target_id)
+                    .map_or(Err(ErrorCode::INVAL), |target| {
+                        self.send(appid, target, kind)
+                    })
+                    .into()
+            }
+            4 => match self.receive(appid) {
+                Ok((sender, length, kind)) => {
+                    CommandReturn::success_u32_u32_u32(sender as u32, length as u32, kind as u32)
+                }
+                Err(e) => CommandReturn::failure(e),
+            },
+            5 => self
+                .data
+                .enter(appid, |mailbox, _| {
+                    for connection in mailbox.connections.iter_mut() {
+                        if connection.map_or(false, |id| id.index() == Some(target_id)) {
+                            *connection = None;
+                        }
+                    }
+                    CommandReturn::success()
+                })
+                .unwrap_or(CommandReturn::failure(ErrorCode::NOMEM)),
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, processid: ProcessId) -> Result<(), crate::process::Error> {
+        self.data.enter(processid, |_, _| {})
+    }
+}
@@ -11,7 +11,7 @@ use core::ptr::NonNull;
 use crate::capabilities;
 use crate::config;
 use crate::debug;
-use crate::dynamic_deferred_call::DynamicDeferredCall;
+use crate::deferred_call::DeferredCall;
 use crate::errorcode::ErrorCode;
 use crate::grant::{AllowRoSize, AllowRwSize, Grant, UpcallSize};
 use crate::ipc;
@@ -21,6 +21,7 @@ use crate::platform::mpu::MPU;
 use crate::platform::platform::ContextSwitchCallback;
 use crate::platform::platform::KernelResources;
 use crate::platform::platform::{ProcessFault, SyscallDriverLookup, SyscallFilter};
+use crate::platform::power::PowerManager;
 use crate::platform::scheduler_timer::SchedulerTimer;
 use crate::platform::watchdog::WatchDog;
 use crate::process::ProcessId;
@@ -29,14 +30,22 @@ use crate::scheduler::{Scheduler, SchedulingDecision};
 use crate::syscall::{ContextSwitchReason, SyscallReturn};
 use crate::syscall::{Syscall, YieldCall};
 use crate::syscall_driver::CommandReturn;
+use crate::syscall_trace::SyscallTrace;
 use crate::upcall::{Upcall, UpcallId};
-use crate::utilities::cells::NumericCellExt;
+use crate::utilities::cells::{NumericCellExt, OptionalCell};
 
 /// Threshold in microseconds to consider a process's timeslice to be exhausted.
 /// That is, Tock will skip re-scheduling a process if its remaining timeslice
 /// is less than this threshold.
 pub(crate) const MIN_QUANTA_THRESHOLD_US: u32 = 500;
 
+/// Interval in microseconds the scheduler timer is started with to measure the
+/// CPU time of a process that runs without a timeslice. When it expires the
+/// interval is added to the time measured so far and the timer is restarted.
+/// The process is charged for the time it actually used, including the
+/// current partial interval, every time it returns to the kernel.
+const CPU_TIME_WINDOW_US: u32 = 100_000;
+
 /// Main object for the kernel. Each board will need to create one.
 pub struct Kernel {
     /// How many "to-do" items exist at any given time. These include
@@ -60,6 +69,16 @@ pub struct Kernel {
     /// created and the data structures for grants have already been
     /// established.
     grants_finalized: Cell<bool>,
+
+    /// Selects the sleep state to enter when there is no work to do.
+    power: PowerManager,
+
+    /// Where system calls are recorded if the kernel is built with the
+    /// `trace_syscalls_buffer` feature.
+    syscall_trace: OptionalCell<&'static SyscallTrace>,
+
+    /// Told when a process terminates.
+    process_termination_client: OptionalCell<&'static dyn process::ProcessTerminationClient>,
 }
 
 /// Enum used to inform scheduler why a process stopped executing (aka why
@@ -124,6 +143,49 @@ impl Kernel {
             process_identifier_max: Cell::new(0),
             grant_counter: Cell::new(0),
             grants_finalized: Cell::new(false),
+            power: PowerManager::new(),
+            syscall_trace: OptionalCell::empty(),
+            process_termination_client: OptionalCell::empty(),
+        }
+    }
+
+    /// The power manager, which drivers register their sleep constraints with.
+    pub fn power_manager(&self) -> &PowerManager {
+        &self.power
+    }
+
+    /// Record the system calls of all processes in `trace`. System calls are
+    /// only recorded if the kernel is built with the `trace_syscalls_buffer`
+    /// feature.
+    pub fn set_syscall_trace(&self, trace: &'static SyscallTrace) {
+        self.syscall_trace.set(trace);
+    }
+
+    /// Tell `client` whenever a process terminates, so that it can release the
+    /// resources it holds for the process.
+    pub fn set_process_termination_client(
+        &self,
+        client: &'static dyn process::ProcessTerminationClient,
+    ) {
+        self.process_termination_client.set(client);
+    }
+
+    /// Called by processes when they terminate.
+    pub(crate) fn process_terminated(&self, processid: ProcessId) {
+        self.process_termination_client
+            .map(|client| client.process_terminated(processid));
+    }
+
+    /// Add a system call and its return value to the syscall trace.
+    fn trace_syscall(
+        &self,
+        process: &dyn process::Process,
+        syscall: &Syscall,
+        rval: Option<&SyscallReturn>,
+    ) {
+        if config::CONFIG.trace_syscalls_buffer {
+            self.syscall_trace
+                .map(|trace| trace.record(process.processid(), syscall, rval));
         }
     }
 
@@ -483,12 +545,10 @@ impl Kernel {
                                     // starts, the interrupt will not be
                                     // serviced and the chip will never wake
                                     // from sleep.
-                                    if !chip.has_pending_interrupts()
-                                        && !DynamicDeferredCall::global_instance_calls_pending()
-                                            .unwrap_or(false)
+                                    if !chip.has_pending_interrupts() && !DeferredCall::has_tasks()
                                     {
                                         resources.watchdog().suspend();
-                                        chip.sleep();
+                                        self.power.sleep(chip);
                                         resources.watchdog().resume();
                                     }
                                 });
@@ -512,6 +572,10 @@ impl Kernel {
         capability: &dyn capabilities::MainLoopCapability,
     ) -> ! {
         resources.watchdog().setup();
+        // Before we begin, check that every deferred call has a client.
+        if config::CONFIG.verify_deferred_calls {
+            DeferredCall::verify_setup();
+        }
         loop {
             self.kernel_loop_operation(resources, chip, ipc, false, capability);
         }
@@ -556,21 +620,19 @@ impl Kernel {
         ipc: Option<&crate::ipc::IPC<NUM_PROCS>>,
         timeslice_us: Option<u32>,
     ) -> (StoppedExecutingReason, Option<u32>) {
-        // We must use a dummy scheduler timer if the process should be executed
-        // without any timeslice restrictions. Note, a chip may not provide a
-        // real scheduler timer implementation even if a timeslice is requested.
-        let scheduler_timer: &dyn SchedulerTimer = if timeslice_us.is_none() {
-            &() // dummy timer, no preemption
-        } else {
-            resources.scheduler_timer()
-        };
+        // The scheduler timer is started even if the process should be
+        // executed without any timeslice restrictions, as it also measures the
+        // CPU time the process uses. In that case it is started with
+        // `CPU_TIME_WINDOW_US` and restarted whenever it expires, rather than
+        // preempting the process. Note, a chip may not provide a real scheduler
+        // timer implementation, in which case no CPU time is measured.
+        let scheduler_timer: &dyn SchedulerTimer = resources.scheduler_timer();
 
         // Clear the scheduler timer and then start the counter. This starts the
         // process's timeslice. Since the kernel is still executing at this
         // point, the scheduler timer need not have an interrupt enabled after
         // `start()`.
-        scheduler_timer.reset();
-        timeslice_us.map(|timeslice| scheduler_timer.start(timeslice));
+        let mut cpu_time = CpuTimeMeter::start(scheduler_timer, timeslice_us);
 
         // Need to track why the process is no longer executing so that we can
         // inform the scheduler.
@@ -583,8 +645,8 @@ impl Kernel {
         // no longer wants to execute this process or if it exceeds its
         // timeslice.
         loop {
-            let stop_running = match scheduler_timer.get_remaining_us() {
-                Some(us) => us <= MIN_QUANTA_THRESHOLD_US,
+            let stop_running = match cpu_time.remaining_us() {
+                Some(us) => timeslice_us.is_some() && us <= MIN_QUANTA_THRESHOLD_US,
                 None => true,
             };
             if stop_running {
@@ -625,11 +687,23 @@ impl Kernel {
                         .context_switch_hook(process);
                     process.setup_mpu();
                     chip.mpu().enable_app_mpu();
-                    scheduler_timer.arm();
+                    if timeslice_us.is_some() {
+                        scheduler_timer.arm();
+                    }
                     let context_switch_reason = process.switch_to();
                     scheduler_timer.disarm();
                     chip.mpu().disable_app_mpu();
 
+                    // Charge the process for the time it ran before handling
+                    // why it returned, as a fault or a syscall may restart or
+                    // terminate it. If this exceeded its CPU budget the
+                    // process is no longer running and there is nothing left
+                    // to handle.
+                    cpu_time.charge(process);
+                    if process.get_state() != process::State::Running {
+                        continue;
+                    }
+
                     // Now the process has returned back to the kernel. Check
                     // why and handle the process as appropriate.
                     match context_switch_reason {
@@ -649,7 +723,7 @@ impl Kernel {
                             self.handle_syscall(resources, process, syscall);
                         }
                         Some(ContextSwitchReason::Interrupted) => {
-                            if scheduler_timer.get_remaining_us().is_none() {
+                            if cpu_time.remaining_us().is_none() {
                                 // This interrupt was a timeslice expiration.
                                 process.debug_timeslice_expired();
                                 return_reason = StoppedExecutingReason::TimesliceExpired;
@@ -719,6 +793,11 @@ impl Kernel {
                     // We should never be scheduling a process in fault.
                     panic!("Attempted to schedule a faulty process");
                 }
+                process::State::CredentialsUnchecked | process::State::CredentialsFailed => {
+                    // A process whose credentials are not approved has no
+                    // work to do and must not run.
+                    break;
+                }
                 process::State::StoppedRunning => {
                     return_reason = StoppedExecutingReason::Stopped;
                     break;
@@ -730,19 +809,19 @@ impl Kernel {
             }
         }
 
-        // Check how much time the process used while it was executing, and
-        // return the value so we can provide it to the scheduler.
-        let time_executed_us = timeslice_us.map_or(None, |timeslice| {
-            // Note, we cannot call `.get_remaining_us()` again if it has previously
-            // returned `None`, so we _must_ check the return reason first.
+        // Charge the process for the time the kernel spent on its behalf since
+        // it last returned to the kernel. This may stop the process if it
+        // exceeded its CPU budget.
+        cpu_time.charge(process);
+
+        // Only schedulers that use a timeslice are told how much of it the
+        // process used.
+        let time_executed_us = timeslice_us.map(|timeslice_us| {
             if return_reason == StoppedExecutingReason::TimesliceExpired {
                 // used the whole timeslice
-                Some(timeslice)
+                timeslice_us
             } else {
-                match scheduler_timer.get_remaining_us() {
-                    Some(remaining) => Some(timeslice - remaining),
-                    None => Some(timeslice), // used whole timeslice
-                }
+                cpu_time.used_us()
             }
         });
 
@@ -797,7 +876,9 @@ impl Kernel {
                 // Check all other syscalls for filtering.
                 if let Err(response) = resources.syscall_filter().filter_syscall(process, &syscall)
                 {
-                    process.set_syscall_return_value(SyscallReturn::Failure(response));
+                    let rval = SyscallReturn::Failure(response);
+                    self.trace_syscall(process, &syscall, Some(&rval));
+                    process.set_syscall_return_value(rval);
 
                     if config::CONFIG.trace_syscalls {
                         debug!(
@@ -826,9 +907,11 @@ impl Kernel {
                         rval
                     );
                 }
+                self.trace_syscall(process, &syscall, Some(&rval));
                 process.set_syscall_return_value(rval);
             }
             Syscall::Yield { which, address } => {
+                self.trace_syscall(process, &syscall, None);
                 if config::CONFIG.trace_syscalls {
                     debug!("[{:?}] yield. which: {}", process.processid(), which);
                 }
@@ -994,6 +1077,7 @@ impl Kernel {
                     );
                 }
 
+                self.trace_syscall(process, &syscall, Some(&rval));
                 process.set_syscall_return_value(rval);
             }
             Syscall::Command {
@@ -1022,6 +1106,7 @@ impl Kernel {
                         res,
                     );
                 }
+                self.trace_syscall(process, &syscall, Some(&res));
                 process.set_syscall_return_value(res);
             }
             Syscall::ReadWriteAllow {
@@ -1115,6 +1200,7 @@ impl Kernel {
                         res
                     );
                 }
+                self.trace_syscall(process, &syscall, Some(&res));
                 process.set_syscall_return_value(res);
             }
             Syscall::UserspaceReadableAllow {
@@ -1193,6 +1279,7 @@ impl Kernel {
                         res
                     );
                 }
+                self.trace_syscall(process, &syscall, Some(&res));
                 process.set_syscall_return_value(res);
             }
             Syscall::ReadOnlyAllow {
@@ -1287,6 +1374,7 @@ impl Kernel {
                     );
                 }
 
+                self.trace_syscall(process, &syscall, Some(&res));
                 process.set_syscall_return_value(res);
             }
             Syscall::Exit {
@@ -1294,13 +1382,96 @@ impl Kernel {
                 completion_code,
             } => match which {
                 // The process called the `exit-terminate` system call.
-                0 => process.terminate(Some(completion_code as u32)),
+                0 => {
+                    self.trace_syscall(process, &syscall, None);
+                    process.terminate(Some(completion_code as u32))
+                }
                 // The process called the `exit-restart` system call.
-                1 => process.try_restart(Some(completion_code as u32)),
+                1 => {
+                    self.trace_syscall(process, &syscall, None);
+                    process.try_restart(Some(completion_code as u32))
+                }
                 // The process called an invalid variant of the Exit
                 // system call class.
-                _ => process.set_syscall_return_value(SyscallReturn::Failure(ErrorCode::NOSUPPORT)),
+                _ => {
+                    let rval = SyscallReturn::Failure(ErrorCode::NOSUPPORT);
+                    self.trace_syscall(process, &syscall, Some(&rval));
+                    process.set_syscall_return_value(rval)
+                }
             },
         }
     }
 }
+
+/// Measures the CPU time a process uses while `do_process()` runs it with the
+/// scheduler timer, and charges the process for it.
+///
+/// Without a timeslice the timer is started with `CPU_TIME_WINDOW_US` and
+/// restarted whenever it expires. With a timeslice the timer is started once,
+/// and is not read again after it expired, as `SchedulerTimer` requires.
+struct CpuTimeMeter<'a> {
+    scheduler_timer: &'a dyn SchedulerTimer,
+    /// Whether the scheduler timer measures a timeslice.
+    timeslice: bool,
+    /// Length of the interval the scheduler timer is started with.
+    window_us: u32,
+    /// CPU time used in measurement windows that already expired.
+    expired_windows_us: u32,
+    /// Whether the timeslice expired.
+    expired: bool,
+    /// CPU time the process has already been charged for.
+    charged_us: u32,
+}
+
+impl<'a> CpuTimeMeter<'a> {
+    fn start(scheduler_timer: &'a dyn SchedulerTimer, timeslice_us: Option<u32>) -> Self {
+        let window_us = timeslice_us.unwrap_or(CPU_TIME_WINDOW_US);
+        scheduler_timer.reset();
+        scheduler_timer.start(window_us);
+        CpuTimeMeter {
+            scheduler_timer,
+            timeslice: timeslice_us.is_some(),
+            window_us,
+            expired_windows_us: 0,
+            expired: false,
+            charged_us: 0,
+        }
+    }
+
+    /// Returns the time left in the timeslice, or `None` if it expired.
+    /// Without a timeslice an expired measurement window is added to the
+    /// measured time and the next one is started, so this never returns
+    /// `None`.
+    fn remaining_us(&mut self) -> Option<u32> {
+        if self.expired {
+            return None;
+        }
+        match self.scheduler_timer.get_remaining_us() {
+            Some(us) => Some(us),
+            None if !self.timeslice => {
+                self.expired_windows_us = self.expired_windows_us.saturating_add(self.window_us);
+                self.scheduler_timer.start(self.window_us);
+                Some(self.window_us)
+            }
+            None => {
+                self.expired = true;
+                None
+            }
+        }
+    }
+
+    /// Returns the CPU time used since the timer was started.
+    fn used_us(&mut self) -> u32 {
+        let remaining_us = self.remaining_us().unwrap_or(0);
+        self.expired_windows_us
+            .saturating_add(self.window_us.saturating_sub(remaining_us))
+    }
+
+    /// Charges the process for the CPU time it used since it was last
+    /// charged.
+    fn charge(&mut self, process: &dyn process::Process) {
+        let used_us = self.used_us();
+        process.charge_cpu_time(used_us.saturating_sub(self.charged_us));
+        self.charged_us = used_us;
+    }
+}
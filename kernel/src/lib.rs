@@ -98,7 +98,6 @@ pub mod collections;
 pub mod component;
 pub mod debug;
 pub mod deferred_call;
-pub mod dynamic_deferred_call;
 pub mod errorcode;
 pub mod grant;
 pub mod hil;
@@ -108,14 +107,20 @@ pub mod platform;
 pub mod process;
 pub mod processbuffer;
 pub mod scheduler;
+pub mod shared_memory;
 pub mod storage_permissions;
 pub mod syscall;
+pub mod syscall_trace;
 pub mod upcall;
 pub mod utilities;
 
 mod config;
 mod kernel;
 mod memop;
+mod process_checker;
+mod process_core_dump;
+mod process_fault_history;
+mod process_loader;
 mod process_policies;
 mod process_printer;
 mod process_standard;
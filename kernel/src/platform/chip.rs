@@ -1,6 +1,7 @@
 //! Interfaces for implementing microcontrollers in Tock.
 
 use crate::platform::mpu;
+use crate::platform::power::SleepState;
 use crate::syscall;
 use core::fmt::Write;
 
@@ -46,6 +47,26 @@ pub trait Chip {
     /// chip and resumes the scheduler.
     fn sleep(&self);
 
+    /// The low power states this chip supports, ordered from the lightest to
+    /// the deepest. The kernel's power manager picks one of these each time
+    /// the chip goes to sleep. Chips that return no states are always put to
+    /// sleep with `sleep()`.
+    fn sleep_states(&self) -> &'static [SleepState] {
+        &[]
+    }
+
+    /// The deepest of `sleep_states()` the chip can enter right now, for
+    /// example given which peripherals are running.
+    fn deepest_sleep_state(&self) -> usize {
+        self.sleep_states().len().saturating_sub(1)
+    }
+
+    /// Enter sleep state `state`, an index into `sleep_states()`. Like
+    /// `sleep()`, the next interrupt must wake the chip.
+    fn sleep_in(&self, _state: usize) {
+        self.sleep();
+    }
+
     /// Run a function in an atomic state, which means that interrupts are
     /// disabled so that an interrupt will not fire during the passed in
     /// function's execution.
@@ -66,7 +87,7 @@ pub trait Chip {
     unsafe fn print_state(&self, writer: &mut dyn Write);
 }
 
-/// Interface for handling interrupts and deferred calls on a hardware chip.
+/// Interface for handling interrupts on a hardware chip.
 ///
 /// Each board must construct an implementation of this trait to handle specific
 /// interrupts. When an interrupt (identified by number) has triggered and
@@ -102,13 +123,10 @@ pub trait Chip {
 /// where the kernel instructs the `nrf52` crate to handle interrupts, and if
 /// there is an interrupt ready then that interrupt is passed through the
 /// InterruptService objects until something can service it.
-pub trait InterruptService<T> {
+pub trait InterruptService {
     /// Service an interrupt, if supported by this chip. If this interrupt
     /// number is not supported, return false.
     unsafe fn service_interrupt(&self, interrupt: u32) -> bool;
-
-    /// Service a deferred call. If this task is not supported, return false.
-    unsafe fn service_deferred_call(&self, task: T) -> bool;
 }
 
 /// Generic operations that clock-like things are expected to support.
@@ -4,6 +4,8 @@
 
 pub mod chip;
 pub mod mpu;
+pub mod power;
+pub mod rate_limit;
 pub mod scheduler_timer;
 pub mod watchdog;
 
@@ -15,3 +17,4 @@ pub use self::platform::ProcessFault;
 pub use self::platform::SyscallDriverLookup;
 pub use self::platform::SyscallFilter;
 pub use self::platform::TbfHeaderFilterDefaultAllow;
+pub use self::rate_limit::TbfHeaderFilterRateLimit;
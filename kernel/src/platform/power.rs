@@ -0,0 +1,203 @@
+//! Power management and sleep-state selection.
+//!
+//! Chips describe the low power states they support with
+//! `Chip::sleep_states()`, ordered from the lightest to the deepest, and
+//! report the deepest state the hardware can currently enter with
+//! `Chip::deepest_sleep_state()` (for example, based on which peripheral
+//! clocks are running).
+//!
+//! Drivers that need the chip to wake up quickly, or that must keep it out of
+//! its deeper states while a peripheral is active, register a
+//! `PowerConstraint` with the kernel's `PowerManager` and update it as their
+//! activity changes:
+//!
+//! ```rust,ignore
+//! board_kernel.power_manager().add_constraint(&driver.power_constraint);
+//!
+//! // In the driver.
+//! self.power_constraint.require_active();
+//! // ... operation completes ...
+//! self.power_constraint.release();
+//! ```
+//!
+//! When the kernel has nothing to do it enters the deepest state that the
+//! chip allows and whose wake-up latency satisfies every constraint. The
+//! lightest state is always allowed. The power manager counts how often each
+//! state is entered and, if the board provides a clock with
+//! `PowerManager::set_clock()`, how long the chip spent in it.
+
+use core::cell::Cell;
+
+use crate::collections::list::{List, ListLink, ListNode};
+use crate::hil::time::{ConvertTicks, Ticks, Time};
+use crate::platform::chip::Chip;
+use crate::utilities::cells::OptionalCell;
+
+/// Maximum number of sleep states the power manager keeps statistics for.
+pub const MAX_SLEEP_STATES: usize = 4;
+
+/// Description of one low power state of a chip.
+#[derive(Copy, Clone, Debug)]
+pub struct SleepState {
+    /// Human-readable name of the state.
+    pub name: &'static str,
+    /// Time from a wake-up event until the chip is executing code again.
+    pub wake_latency_us: u32,
+}
+
+/// A limit a driver places on how deeply the chip may sleep.
+pub struct PowerConstraint<'a> {
+    max_wake_latency_us: Cell<u32>,
+    next: ListLink<'a, PowerConstraint<'a>>,
+}
+
+impl<'a> ListNode<'a, PowerConstraint<'a>> for PowerConstraint<'a> {
+    fn next(&'a self) -> &'a ListLink<'a, PowerConstraint<'a>> {
+        &self.next
+    }
+}
+
+impl<'a> PowerConstraint<'a> {
+    /// Create a constraint that does not limit sleep.
+    pub const fn new() -> PowerConstraint<'a> {
+        PowerConstraint {
+            max_wake_latency_us: Cell::new(u32::MAX),
+            next: ListLink::empty(),
+        }
+    }
+
+    /// The driver is active, so only the lightest sleep state may be used.
+    pub fn require_active(&self) {
+        self.max_wake_latency_us.set(0);
+    }
+
+    /// Only allow sleep states that wake up within `latency_us`.
+    pub fn set_max_wake_latency_us(&self, latency_us: u32) {
+        self.max_wake_latency_us.set(latency_us);
+    }
+
+    /// Remove the limit on sleep states.
+    pub fn release(&self) {
+        self.max_wake_latency_us.set(u32::MAX);
+    }
+
+    /// The current limit on wake-up latency, if any.
+    pub fn max_wake_latency_us(&self) -> Option<u32> {
+        match self.max_wake_latency_us.get() {
+            u32::MAX => None,
+            latency => Some(latency),
+        }
+    }
+}
+
+/// How often, and for how long, the chip has been in a sleep state.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SleepStats {
+    /// Number of times the state was entered.
+    pub entries: u32,
+    /// Total time spent in the state. Only counted if the board set a clock
+    /// with `PowerManager::set_clock()`.
+    pub time_us: u64,
+}
+
+/// A clock used to measure how long the chip sleeps. It must keep running in
+/// every sleep state, so it is usually a low-frequency RTC.
+///
+/// This is implemented for every `hil::time::Time`.
+pub trait SleepClock {
+    /// The current time, as the lower 32 bits of the clock's ticks.
+    fn now(&self) -> u32;
+
+    /// Microseconds elapsed since `start`, a value returned by `now()`.
+    fn us_since(&self, start: u32) -> u32;
+}
+
+impl<T: Time> SleepClock for T {
+    fn now(&self) -> u32 {
+        Time::now(self).into_u32()
+    }
+
+    fn us_since(&self, start: u32) -> u32 {
+        let now = T::Ticks::from(Time::now(self).into_u32());
+        // Go through `into_u32()` so that the subtraction wraps at 32 bits
+        // for clocks with wider counters.
+        let elapsed = T::Ticks::from(now.wrapping_sub(T::Ticks::from(start)).into_u32());
+        self.ticks_to_us(elapsed)
+    }
+}
+
+/// Selects the sleep state the kernel enters when idle and keeps statistics
+/// on the time spent in each state.
+pub struct PowerManager {
+    constraints: List<'static, PowerConstraint<'static>>,
+    clock: OptionalCell<&'static dyn SleepClock>,
+    stats: [Cell<SleepStats>; MAX_SLEEP_STATES],
+}
+
+impl PowerManager {
+    pub(crate) fn new() -> PowerManager {
+        PowerManager {
+            constraints: List::new(),
+            clock: OptionalCell::empty(),
+            stats: Default::default(),
+        }
+    }
+
+    /// Register a driver's constraint on sleep states.
+    pub fn add_constraint(&self, constraint: &'static PowerConstraint<'static>) {
+        self.constraints.push_head(constraint);
+    }
+
+    /// Set the clock used to measure the time spent in each sleep state.
+    pub fn set_clock(&self, clock: &'static dyn SleepClock) {
+        self.clock.set(clock);
+    }
+
+    /// The strictest wake-up latency limit of all registered constraints.
+    pub fn max_wake_latency_us(&self) -> Option<u32> {
+        self.constraints
+            .iter()
+            .filter_map(|constraint| constraint.max_wake_latency_us())
+            .min()
+    }
+
+    /// The sleep state the kernel would enter now: the deepest state the chip
+    /// allows whose wake-up latency meets every constraint.
+    pub fn select_state<C: Chip>(&self, chip: &C) -> usize {
+        let states = chip.sleep_states();
+        if states.is_empty() {
+            return 0;
+        }
+        let deepest = core::cmp::min(chip.deepest_sleep_state(), states.len() - 1);
+        let max_latency = self.max_wake_latency_us().unwrap_or(u32::MAX);
+
+        (1..=deepest)
+            .rev()
+            .find(|&state| states[state].wake_latency_us <= max_latency)
+            .unwrap_or(0)
+    }
+
+    /// Statistics for sleep state `state`. For chips that do not describe
+    /// their sleep states, all sleep is counted as state 0.
+    pub fn stats(&self, state: usize) -> Option<SleepStats> {
+        self.stats.get(state).map(|stats| stats.get())
+    }
+
+    /// Put the chip into the selected sleep state until the next interrupt.
+    pub(crate) fn sleep<C: Chip>(&self, chip: &C) {
+        let state = self.select_state(chip);
+        let start = self.clock.map(|clock| clock.now());
+
+        chip.sleep_in(state);
+
+        if let Some(stats) = self.stats.get(state) {
+            let mut s = stats.get();
+            s.entries = s.entries.wrapping_add(1);
+            if let Some(start) = start {
+                self.clock
+                    .map(|clock| s.time_us += clock.us_since(start) as u64);
+            }
+            stats.set(s);
+        }
+    }
+}
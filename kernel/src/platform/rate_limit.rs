@@ -0,0 +1,300 @@
+//! System call filter that enforces the syscall limits in the TBF header.
+//!
+//! [`TbfHeaderFilterRateLimit`] first applies the same driver permissions as
+//! [`TbfHeaderFilterDefaultAllow`](crate::platform::TbfHeaderFilterDefaultAllow).
+//! It then enforces the `Syscall Limits` TLV of each process, which limits how
+//! often a process may call the commands of a driver (for example how many
+//! packets it may transmit over the radio each second) and how many calls it
+//! may make in total:
+//!
+//! - A command that would exceed a quota fails with `ErrorCode::NOMEM`.
+//! - A command that would exceed a rate limit fails with `ErrorCode::BUSY`,
+//!   and succeeds again once the current period has passed.
+//!
+//! A command is only counted if it passes every limit that applies to it.
+//! Subscribe and allow calls are never limited. The counters of a process are
+//! kept when it restarts, so a process cannot reset its quota by faulting or
+//! exiting. They are only reset when a different application, with another
+//! name or `ShortID`, runs in the same process slot.
+//!
+//! The first time a limit rejects a command of a process the filter prints a
+//! message with `debug!()`. The state of every limit can be inspected through
+//! the [`SyscallLimitReport`] trait, for example by the process console.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let syscall_filter = static_init!(
+//!     kernel::platform::TbfHeaderFilterRateLimit<
+//!         'static,
+//!         VirtualMuxAlarm<'static, nrf52840::rtc::Rtc>,
+//!         NUM_PROCS,
+//!     >,
+//!     kernel::platform::TbfHeaderFilterRateLimit::new(filter_alarm)
+//! );
+//! process_console.set_syscall_limits(syscall_filter);
+//! ```
+//!
+//! The board then returns `syscall_filter` from
+//! `KernelResources::syscall_filter()`.
+
+use crate::debug;
+use crate::errorcode::ErrorCode;
+use crate::hil::time::{ConvertTicks, Ticks, Time};
+use crate::platform::{SyscallFilter, TbfHeaderFilterDefaultAllow};
+use crate::process::{Process, ProcessId, ShortID};
+use crate::syscall::Syscall;
+use crate::utilities::cells::MapCell;
+use tock_tbf::types::TbfHeaderDriverLimit;
+
+/// Maximum number of limits enforced for each process.
+pub const MAX_SYSCALL_LIMITS: usize = 8;
+
+/// The state of one limit of a process.
+#[derive(Copy, Clone, Debug)]
+pub struct SyscallLimitStatus {
+    /// The process the limit belongs to.
+    pub processid: ProcessId,
+    /// The name of the process.
+    pub process_name: &'static str,
+    /// The limit from the TBF header of the process.
+    pub limit: TbfHeaderDriverLimit,
+    /// Commands counted in the current period.
+    pub window_calls: u16,
+    /// Commands counted since the application was loaded.
+    pub total_calls: u32,
+    /// Commands rejected by this limit since the application was loaded.
+    pub rejected: u32,
+}
+
+/// Read access to the state of the syscall limits of all processes.
+pub trait SyscallLimitReport {
+    /// Returns the number of limits that are being enforced.
+    fn limit_count(&self) -> usize;
+
+    /// Returns the limit at `index`, ordered by process.
+    fn get_limit(&self, index: usize) -> Option<SyscallLimitStatus>;
+}
+
+/// The counters of one limit of a process.
+#[derive(Copy, Clone)]
+struct LimitCounters<T: Ticks> {
+    limit: TbfHeaderDriverLimit,
+    /// Start of the current period.
+    window_start: T,
+    window_calls: u16,
+    total_calls: u32,
+    rejected: u32,
+}
+
+/// The limits of one process.
+#[derive(Copy, Clone)]
+struct ProcessLimits<T: Ticks> {
+    processid: Option<ProcessId>,
+    process_name: &'static str,
+    short_id: ShortID,
+    limits: [Option<LimitCounters<T>>; MAX_SYSCALL_LIMITS],
+}
+
+impl<T: Ticks> ProcessLimits<T> {
+    const EMPTY: ProcessLimits<T> = ProcessLimits {
+        processid: None,
+        process_name: "",
+        short_id: ShortID::LocallyUnique,
+        limits: [None; MAX_SYSCALL_LIMITS],
+    };
+}
+
+/// A system call filter that enforces the TBF header permissions and syscall
+/// limits. See the module documentation.
+pub struct TbfHeaderFilterRateLimit<'a, T: Time, const NUM_PROCS: usize> {
+    time: &'a T,
+    permissions: TbfHeaderFilterDefaultAllow,
+    /// The limits of each process, indexed by the index of the process in the
+    /// kernel's process array.
+    processes: MapCell<[ProcessLimits<T::Ticks>; NUM_PROCS]>,
+}
+
+impl<'a, T: Time, const NUM_PROCS: usize> TbfHeaderFilterRateLimit<'a, T, NUM_PROCS> {
+    pub fn new(time: &'a T) -> TbfHeaderFilterRateLimit<'a, T, NUM_PROCS> {
+        TbfHeaderFilterRateLimit {
+            time,
+            permissions: TbfHeaderFilterDefaultAllow {},
+            processes: MapCell::new([ProcessLimits::EMPTY; NUM_PROCS]),
+        }
+    }
+
+    /// Load the limits of `process` from its TBF header.
+    fn load_limits(process: &dyn Process) -> ProcessLimits<T::Ticks> {
+        let mut entry = ProcessLimits {
+            processid: Some(process.processid()),
+            process_name: process.get_process_name(),
+            short_id: process.short_app_id(),
+            limits: [None; MAX_SYSCALL_LIMITS],
+        };
+        for (counters, limit) in entry
+            .limits
+            .iter_mut()
+            .zip(process.get_syscall_limits().iter())
+        {
+            *counters = Some(LimitCounters {
+                limit: *limit,
+                window_start: T::Ticks::from(0),
+                window_calls: 0,
+                total_calls: 0,
+                rejected: 0,
+            });
+        }
+        entry
+    }
+
+    /// Check and count a command against the limits of `entry`.
+    fn check_limits(
+        &self,
+        entry: &mut ProcessLimits<T::Ticks>,
+        driver_number: usize,
+        command_number: usize,
+    ) -> Result<(), ErrorCode> {
+        let now = self.time.now();
+        let mut result = Ok(());
+
+        for counters in entry.limits.iter_mut().flatten() {
+            if !counters.limit.applies_to(driver_number, command_number) {
+                continue;
+            }
+            let limit = counters.limit;
+
+            // Start a new period if the current one has passed.
+            let rate_limited = limit.max_calls() != 0 && limit.period_ms() != 0;
+            if rate_limited {
+                let elapsed = now.wrapping_sub(counters.window_start);
+                if elapsed >= self.time.ticks_from_ms(limit.period_ms()) {
+                    counters.window_start = now;
+                    counters.window_calls = 0;
+                }
+            }
+
+            let error = if limit.quota() != 0 && counters.total_calls >= limit.quota() {
+                Some(ErrorCode::NOMEM)
+            } else if rate_limited && counters.window_calls >= limit.max_calls() {
+                Some(ErrorCode::BUSY)
+            } else {
+                None
+            };
+
+            if let Some(error) = error {
+                if counters.rejected == 0 {
+                    debug!(
+                        "{} exceeded the {} of driver {:#x} command {}",
+                        entry.process_name,
+                        if error == ErrorCode::NOMEM {
+                            "quota"
+                        } else {
+                            "rate limit"
+                        },
+                        driver_number,
+                        command_number,
+                    );
+                }
+                counters.rejected = counters.rejected.saturating_add(1);
+                // Report a quota before a rate limit, since waiting does not
+                // help.
+                if result != Err(ErrorCode::NOMEM) {
+                    result = Err(error);
+                }
+            }
+        }
+
+        if result.is_ok() {
+            for counters in entry.limits.iter_mut().flatten() {
+                if counters.limit.applies_to(driver_number, command_number) {
+                    counters.window_calls = counters.window_calls.saturating_add(1);
+                    counters.total_calls = counters.total_calls.saturating_add(1);
+                }
+            }
+        }
+        result
+    }
+}
+
+impl<'a, T: Time, const NUM_PROCS: usize> SyscallFilter
+    for TbfHeaderFilterRateLimit<'a, T, NUM_PROCS>
+{
+    fn filter_syscall(&self, process: &dyn Process, syscall: &Syscall) -> Result<(), ErrorCode> {
+        self.permissions.filter_syscall(process, syscall)?;
+
+        let (driver_number, subdriver_number) = match *syscall {
+            Syscall::Command {
+                driver_number,
+                subdriver_number,
+                ..
+            } => (driver_number, subdriver_number),
+            _ => return Ok(()),
+        };
+        if process.get_syscall_limits().is_empty() {
+            return Ok(());
+        }
+
+        let processid = process.processid();
+        let index = match processid.index() {
+            Some(index) if index < NUM_PROCS => index,
+            _ => return Ok(()),
+        };
+
+        self.processes.map_or(Ok(()), |processes| {
+            let entry = &mut processes[index];
+            if entry.processid != Some(processid) {
+                if entry.processid.is_some()
+                    && entry.process_name == process.get_process_name()
+                    && entry.short_id == process.short_app_id()
+                {
+                    // The process restarted. Keep its counters so that it
+                    // does not get a new quota.
+                    entry.processid = Some(processid);
+                } else {
+                    // A different application now uses this process slot.
+                    *entry = Self::load_limits(process);
+                }
+            }
+            self.check_limits(entry, driver_number, subdriver_number)
+        })
+    }
+}
+
+impl<'a, T: Time, const NUM_PROCS: usize> SyscallLimitReport
+    for TbfHeaderFilterRateLimit<'a, T, NUM_PROCS>
+{
+    fn limit_count(&self) -> usize {
+        self.processes.map_or(0, |processes| {
+            processes
+                .iter()
+                .filter(|entry| entry.processid.is_some())
+                .map(|entry| entry.limits.iter().flatten().count())
+                .sum()
+        })
+    }
+
+    fn get_limit(&self, index: usize) -> Option<SyscallLimitStatus> {
+        self.processes.map_or(None, |processes| {
+            processes
+                .iter()
+                .filter_map(|entry| entry.processid.map(|processid| (processid, entry)))
+                .flat_map(|(processid, entry)| {
+                    entry
+                        .limits
+                        .iter()
+                        .flatten()
+                        .map(move |counters| SyscallLimitStatus {
+                            processid,
+                            process_name: entry.process_name,
+                            limit: counters.limit,
+                            window_calls: counters.window_calls,
+                            total_calls: counters.total_calls,
+                            rejected: counters.rejected,
+                        })
+                })
+                .nth(index)
+        })
+    }
+}
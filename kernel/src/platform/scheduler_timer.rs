@@ -124,7 +124,8 @@ pub trait SchedulerTimer {
 /// A dummy `SchedulerTimer` implementation in which the timer never expires.
 ///
 /// Using this implementation is functional, but will mean the scheduler cannot
-/// interrupt non-yielding processes.
+/// interrupt non-yielding processes, and that the kernel cannot measure the CPU
+/// time processes use.
 impl SchedulerTimer for () {
     fn reset(&self) {}
 
@@ -135,7 +136,7 @@ impl SchedulerTimer for () {
     fn arm(&self) {}
 
     fn get_remaining_us(&self) -> Option<u32> {
-        Some(10000) // chose arbitrary large value
+        Some(u32::MAX) // never expires and no time passes
     }
 }
 
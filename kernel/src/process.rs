@@ -13,18 +13,29 @@ use crate::kernel::Kernel;
 use crate::platform::mpu::{self};
 use crate::processbuffer::{ReadOnlyProcessBuffer, ReadWriteProcessBuffer};
 use crate::storage_permissions;
-use crate::syscall::{self, Syscall, SyscallReturn};
+use crate::syscall::{self, Syscall, SyscallClass, SyscallReturn};
 use crate::upcall::UpcallId;
-use tock_tbf::types::CommandPermissions;
+use tock_tbf::types::{CommandPermissions, TbfFooterV2Credentials, TbfHeaderDriverLimit};
 
 // Export all process related types via `kernel::process::`.
+pub use crate::process_checker::{
+    AppCredentialsChecker, CheckResult, Compress, CredentialsCheckingClient, ProcessCheckerMachine,
+    ShortID,
+};
+pub use crate::process_core_dump::{CoreDump, CoreDumpClient, ProcessCoreDump};
+pub use crate::process_fault_history::{
+    FaultHistory, ProcessFaultHistory, ProcessFaultRecord, FAULT_RECORD_LEN, FAULT_RECORD_NAME_LEN,
+};
+pub use crate::process_loader::{DynamicProcessLoader, DynamicProcessLoading};
 pub use crate::process_policies::{
     PanicFaultPolicy, ProcessFaultPolicy, RestartFaultPolicy, StopFaultPolicy,
     StopWithDebugFaultPolicy, ThresholdRestartFaultPolicy, ThresholdRestartThenPanicFaultPolicy,
 };
 pub use crate::process_printer::{ProcessPrinter, ProcessPrinterContext, ProcessPrinterText};
 pub use crate::process_standard::ProcessStandard;
-pub use crate::process_utilities::{load_processes, load_processes_advanced, ProcessLoadError};
+pub use crate::process_utilities::{
+    load_and_check_processes, load_processes, load_processes_advanced, ProcessLoadError,
+};
 
 /// Userspace process identifier.
 ///
@@ -193,6 +204,24 @@ impl ProcessId {
         self.kernel
             .process_map_or(None, *self, |process| process.get_storage_permissions())
     }
+
+    /// Get the `ShortID` of the application the process runs. Returns
+    /// `ShortID::LocallyUnique` if the process no longer exists.
+    pub fn short_app_id(&self) -> ShortID {
+        self.kernel
+            .process_map_or(ShortID::LocallyUnique, *self, |process| {
+                process.short_app_id()
+            })
+    }
+}
+
+/// Kernel components that hold resources on behalf of processes implement
+/// this trait to release them when a process terminates.
+pub trait ProcessTerminationClient {
+    /// Called when the process `processid` has terminated, because it exited,
+    /// faulted or is about to be restarted. A restarted process gets a new
+    /// `ProcessId`.
+    fn process_terminated(&self, processid: ProcessId);
 }
 
 /// This trait represents a generic process that the Tock scheduler can
@@ -268,6 +297,37 @@ pub trait Process {
     /// Get the name of the process. Used for IPC.
     fn get_process_name(&self) -> &'static str;
 
+    /// Returns the region of flash covered by the process's credentials: its
+    /// TBF header and application binary.
+    fn get_credentials_region(&self) -> &'static [u8];
+
+    /// Returns the footers of the process's TBF object, which hold its
+    /// credentials. Empty if the process has no footers.
+    fn get_footers(&self) -> &'static [u8];
+
+    /// Returns the credentials that were accepted when this process was
+    /// checked, or `None` if the process was approved without credentials.
+    fn get_credentials(&self) -> Option<TbfFooterV2Credentials>;
+
+    /// Returns the short identifier of the application this process runs,
+    /// derived from its credentials when it was checked.
+    fn short_app_id(&self) -> ShortID;
+
+    /// Approve the process's credentials, recording the accepted
+    /// `credentials` and the resulting `short_app_id`, and make the process
+    /// runnable. Fails with `INVAL` if the process is not in the
+    /// `CredentialsUnchecked` state.
+    fn mark_credentials_pass(
+        &self,
+        credentials: Option<TbfFooterV2Credentials>,
+        short_app_id: ShortID,
+        capability: &dyn capabilities::ProcessApprovalCapability,
+    ) -> Result<(), ErrorCode>;
+
+    /// Reject the process's credentials. The process is put into the
+    /// `CredentialsFailed` state and never runs.
+    fn mark_credentials_fail(&self, capability: &dyn capabilities::ProcessApprovalCapability);
+
     /// Get the completion code if the process has previously terminated.
     ///
     /// If the process has never terminated then there has been no opportunity
@@ -431,6 +491,24 @@ pub trait Process {
     /// Returns `None` if the process has no storage permissions.
     fn get_storage_permissions(&self) -> Option<storage_permissions::StoragePermissions>;
 
+    /// Get the timing constraints of the process as the period and the
+    /// relative deadline of its jobs, in microseconds.
+    ///
+    /// Returns `None` if the process does not specify timing constraints.
+    fn get_timing_constraints(&self) -> Option<(u32, u32)>;
+
+    /// Get the `ShortID`s of the clients that may connect to this process over
+    /// message-passing IPC, as the number of valid ids and the ids. An id of 0
+    /// admits any client.
+    ///
+    /// Returns `None` if the process does not offer a message-passing service.
+    fn get_ipc_allow_list(&self) -> Option<(usize, [u32; 8])>;
+
+    /// Get the rate limits and quotas on the commands this process may call.
+    ///
+    /// Returns an empty slice if the process does not specify syscall limits.
+    fn get_syscall_limits(&self) -> &[TbfHeaderDriverLimit];
+
     // mpu
 
     /// Configure the MPU to use the process's allocated regions.
@@ -440,8 +518,8 @@ pub trait Process {
     fn setup_mpu(&self);
 
     /// Allocate a new MPU region for the process that is at least
-    /// `min_region_size` bytes and lies within the specified stretch of
-    /// unallocated memory.
+    /// `min_region_size` bytes, lies within the specified stretch of
+    /// unallocated memory and grants the process `permissions` to it.
     ///
     /// It is not valid to call this function when the process is inactive (i.e.
     /// the process will not run again).
@@ -450,6 +528,7 @@ pub trait Process {
         unallocated_memory_start: *const u8,
         unallocated_memory_size: usize,
         min_region_size: usize,
+        permissions: mpu::Permissions,
     ) -> Option<mpu::Region>;
 
     /// Removes an MPU region from the process that has been previouly added with
@@ -553,6 +632,24 @@ pub trait Process {
     /// if there is a grant associated with that driver_num.
     fn lookup_grant_from_driver_num(&self, driver_num: usize) -> Result<usize, Error>;
 
+    /// Release the grant based on `grant_num` for this process.
+    ///
+    /// After a grant is released it is no longer allocated, and a later
+    /// access allocates it again. The memory of the grant is returned to the
+    /// process if it is at the bottom of the grant region; otherwise it is
+    /// kept and reused when the same grant is allocated again.
+    ///
+    /// This returns `Ok(())` if the grant is not allocated. It returns an
+    /// `Err` if the process is inactive, if `grant_num` is invalid, or if the
+    /// grant is currently entered.
+    fn free_grant(&self, grant_num: usize) -> Result<(), Error>;
+
+    /// Get how much memory the grant based on `grant_num` uses, or `None` if
+    /// the grant is not allocated or `grant_num` is invalid.
+    ///
+    /// Useful for debugging/inspecting the system.
+    fn get_grant_usage(&self, grant_num: usize) -> Option<GrantUsage>;
+
     // subscribe
 
     /// Verify that an Upcall function pointer is within process-accessible
@@ -614,6 +711,15 @@ pub trait Process {
     /// context, and the state of the memory protection unit (MPU).
     fn print_full_process(&self, writer: &mut dyn Write);
 
+    /// Print out the state of the memory protection unit (MPU) for the
+    /// process.
+    fn print_mpu_config(&self, writer: &mut dyn Write);
+
+    /// Returns what the architecture reports about the last fault of the
+    /// process, such as the program counter and the faulting address. Only
+    /// meaningful while the kernel is handling a fault of the process.
+    fn get_fault_context(&self) -> syscall::FaultContext;
+
     // debug
 
     /// Returns how many syscalls this app has called.
@@ -635,6 +741,33 @@ pub trait Process {
     /// Return the last syscall the process called. Returns `None` if the
     /// process has not called any syscalls or the information is unknown.
     fn debug_syscall_last(&self) -> Option<Syscall>;
+
+    /// Returns how many syscalls of the given class this app has called.
+    fn debug_syscall_class_count(&self, class: SyscallClass) -> usize;
+
+    /// Returns how many upcalls from capsules this process has executed.
+    fn debug_upcall_count(&self) -> usize;
+
+    /// Returns the CPU time, in microseconds, this process has used since it
+    /// was last started. Time is measured with the scheduler timer, so this
+    /// is 0 if the chip does not provide one.
+    fn debug_cpu_time_us(&self) -> u64;
+
+    // cpu budget
+
+    /// Add CPU time the process has used. If this exceeds the CPU budget of
+    /// the process, the process is put into the fault state, and the
+    /// `ProcessFaultPolicy` decides what happens to it.
+    fn charge_cpu_time(&self, time_us: u32);
+
+    /// Set how much CPU time, in microseconds, the process may use each time
+    /// it is started. `None` removes the budget. Boards can set budgets after
+    /// loading processes, for example with
+    /// `Kernel::process_each_capability()`.
+    fn set_cpu_budget(&self, budget_us: Option<u64>);
+
+    /// Returns the CPU budget of the process, if it has one.
+    fn get_cpu_budget(&self) -> Option<u64>;
 }
 
 /// Opaque identifier for custom grants allocated dynamically from a process's
@@ -655,6 +788,19 @@ pub struct ProcessCustomGrantIdentifer {
     pub(crate) offset: usize,
 }
 
+/// The memory used by a grant of a process.
+#[derive(Copy, Clone, Debug)]
+pub struct GrantUsage {
+    /// The driver number the grant belongs to.
+    pub driver_num: usize,
+    /// The number of bytes of the grant region used by the grant, including
+    /// padding for alignment.
+    pub size: usize,
+    /// Whether the grant was released, but its memory has not been returned
+    /// to the process yet.
+    pub released: bool,
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Error {
     /// The process has been removed and no longer exists. For example, the
@@ -742,6 +888,13 @@ pub enum State {
     /// processes yet. It can also happen if an process is terminated and all of
     /// its state is reset as if it has not been executed yet.
     Unstarted,
+
+    /// The process has been loaded but its credentials have not been checked
+    /// yet. It cannot run until they are approved.
+    CredentialsUnchecked,
+
+    /// The process's credentials were rejected, so it will never run.
+    CredentialsFailed,
 }
 
 /// A wrapper around `Cell<State>` is used by `Process` to prevent bugs arising
@@ -891,6 +1044,13 @@ pub struct ProcessAddresses {
     /// have reached a lower address, this is only the lowest address seen when
     /// the process calls a syscall.
     pub sram_stack_bottom: Option<usize>,
+    /// The lowest address the process's stack has reached since the process
+    /// started, if known. Unlike `sram_stack_bottom` this includes stack
+    /// usage between syscalls, as found by painting process memory. It
+    /// assumes the stack grows down towards `sram_start`.
+    pub sram_stack_high_water: Option<usize>,
+    /// The highest application break the process has had since it started.
+    pub sram_app_brk_high_water: usize,
 }
 
 /// Collection of process state related to the size in memory of various process
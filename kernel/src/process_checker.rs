@@ -0,0 +1,365 @@
+//! Checking the credentials of processes before they run.
+//!
+//! A TBF object can carry credentials, such as a hash or a signature of its
+//! header and binary, in footers that follow the application binary. When
+//! processes are loaded with `load_and_check_processes()` or a
+//! `DynamicProcessLoader`, they are created in the `CredentialsUnchecked`
+//! state and do not run until a [`ProcessCheckerMachine`] has checked their
+//! credentials against the board's [`AppCredentialsChecker`] policy:
+//!
+//! - The machine walks the footers of each process in order and passes every
+//!   credential to the policy.
+//! - The policy answers `Accept`, `Reject` or `Pass`. The first `Accept` or
+//!   `Reject` decides the process; `Pass`, or a format the policy does not
+//!   support (`NOSUPPORT` or `INVAL`), moves on to the next footer.
+//! - If the policy is `BUSY`, the same footer is checked again from a
+//!   deferred call. Any other error rejects the process, since its
+//!   credentials could not be checked.
+//! - If no footer decides the process, it runs only if the policy does not
+//!   require credentials.
+//!
+//! Accepted processes are given a [`ShortID`], computed from the accepted
+//! credentials by a [`Compress`] policy. Two running processes can never share
+//! the same fixed `ShortID`; a process whose identifier is already in use is
+//! rejected.
+
+use core::cell::Cell;
+use core::num::NonZeroU32;
+
+use crate::capabilities;
+use crate::config;
+use crate::debug;
+use crate::deferred_call::{DeferredCall, DeferredCallClient};
+use crate::errorcode::ErrorCode;
+use crate::kernel::Kernel;
+use crate::process::{Process, State};
+use crate::utilities::cells::OptionalCell;
+use tock_tbf::types::{TbfFooterV2Credentials, TbfFooterV2CredentialsType};
+
+/// A compressed, 32 bit identifier of the application a process runs.
+///
+/// Capsules can use `ShortID`s to recognize an application across reboots
+/// and restarts, for example to decide which application may access some
+/// resource.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShortID {
+    /// The application has no global identity. Its identifier is only unique
+    /// among the processes currently on the board.
+    LocallyUnique,
+    /// The application has the given global identifier.
+    Fixed(NonZeroU32),
+}
+
+/// The decision of an `AppCredentialsChecker` on a single credential.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckResult {
+    /// The credential is valid and the process may run.
+    Accept,
+    /// The credential does not decide whether the process may run; the next
+    /// credential should be checked.
+    Pass,
+    /// The credential is invalid (for example, the hash does not match) and
+    /// the process must not run.
+    Reject,
+}
+
+/// Client of an `AppCredentialsChecker`.
+pub trait CredentialsCheckingClient<'a> {
+    /// Called when checking `credentials` against `binary` completes.
+    /// Returns the credentials and binary passed to `check_credentials`.
+    fn check_done(
+        &self,
+        result: Result<CheckResult, ErrorCode>,
+        credentials: TbfFooterV2Credentials,
+        binary: &'a [u8],
+    );
+}
+
+/// The policy that decides whether a process may run based on its
+/// credentials.
+pub trait AppCredentialsChecker<'a> {
+    fn set_client(&self, client: &'a dyn CredentialsCheckingClient<'a>);
+
+    /// Whether processes without any accepted credentials must be rejected.
+    /// Boards that only run trusted applications should return `true`.
+    fn require_credentials(&self) -> bool;
+
+    /// Starts checking `credentials` against `binary`, the TBF header and
+    /// application binary they cover. The result is delivered through
+    /// `CredentialsCheckingClient::check_done`.
+    ///
+    /// Returns `NOSUPPORT` (or `INVAL`) if the policy does not handle this
+    /// credentials format, or `BUSY` if a check is already in progress,
+    /// together with the arguments. A busy check is retried later; any other
+    /// error rejects the process.
+    fn check_credentials(
+        &self,
+        credentials: TbfFooterV2Credentials,
+        binary: &'a [u8],
+    ) -> Result<(), (ErrorCode, TbfFooterV2Credentials, &'a [u8])>;
+}
+
+/// Computes the `ShortID` of an application from the credentials that were
+/// accepted for it.
+pub trait Compress {
+    fn to_short_id(&self, credentials: &TbfFooterV2Credentials) -> ShortID;
+}
+
+/// Capability used by the `ProcessCheckerMachine` to approve and reject
+/// processes.
+struct ApprovalCapability;
+unsafe impl capabilities::ProcessApprovalCapability for ApprovalCapability {}
+
+/// Checks the credentials of every process in the `CredentialsUnchecked`
+/// state, one credential at a time, and approves or rejects the processes.
+pub struct ProcessCheckerMachine {
+    kernel: &'static Kernel,
+    policy: OptionalCell<&'static dyn AppCredentialsChecker<'static>>,
+    compressor: OptionalCell<&'static dyn Compress>,
+    /// Index of the process being checked.
+    process: Cell<usize>,
+    /// Offset of the footer to check within the process's footers.
+    footer: Cell<usize>,
+    /// Length of the footer being checked.
+    footer_len: Cell<usize>,
+    /// A credential is being checked by the policy.
+    checking: Cell<bool>,
+    /// Processes were loaded while a credential was being checked, so all
+    /// processes are walked again once the current walk ends.
+    rescan: Cell<bool>,
+    /// Retries the current footer when the policy was busy.
+    deferred_call: DeferredCall,
+}
+
+impl ProcessCheckerMachine {
+    pub fn new(kernel: &'static Kernel) -> ProcessCheckerMachine {
+        ProcessCheckerMachine {
+            kernel,
+            policy: OptionalCell::empty(),
+            compressor: OptionalCell::empty(),
+            process: Cell::new(0),
+            footer: Cell::new(0),
+            footer_len: Cell::new(0),
+            checking: Cell::new(false),
+            rescan: Cell::new(false),
+            deferred_call: DeferredCall::new(),
+        }
+    }
+
+    /// Sets the policy that checks credentials and the policy that computes
+    /// `ShortID`s from accepted credentials. Without a policy, every process
+    /// is approved with a `LocallyUnique` identifier.
+    pub fn set_policy(
+        &'static self,
+        policy: &'static dyn AppCredentialsChecker<'static>,
+        compressor: &'static dyn Compress,
+    ) {
+        self.register();
+        policy.set_client(self);
+        self.policy.replace(policy);
+        self.compressor.replace(compressor);
+    }
+
+    /// Starts checking the credentials of all unchecked processes. If a check
+    /// is in progress, the processes are walked again once it completes.
+    pub(crate) fn start(&'static self) {
+        self.register();
+        if self.checking.get() || self.deferred_call.is_pending() {
+            self.rescan.set(true);
+            return;
+        }
+        self.process.set(0);
+        self.footer.set(0);
+        self.next();
+    }
+
+    fn current_process(&self) -> Option<&'static dyn Process> {
+        self.kernel.get_process_iter().nth(self.process.get())
+    }
+
+    fn next_process(&self) {
+        self.process.set(self.process.get() + 1);
+        self.footer.set(0);
+    }
+
+    fn next_footer(&self) {
+        self.footer.set(self.footer.get() + self.footer_len.get());
+    }
+
+    /// Checks credentials until a check is started that completes
+    /// asynchronously, or until all processes are decided.
+    fn next(&self) {
+        loop {
+            let process = match self.current_process() {
+                Some(process) => process,
+                None if self.rescan.take() => {
+                    self.process.set(0);
+                    self.footer.set(0);
+                    continue;
+                }
+                None => return,
+            };
+            if process.get_state() != State::CredentialsUnchecked {
+                self.next_process();
+                continue;
+            }
+
+            let policy = match self.policy.extract() {
+                Some(policy) => policy,
+                None => {
+                    self.approve(process, None);
+                    self.next_process();
+                    continue;
+                }
+            };
+
+            // A footer that does not parse ends the list of credentials.
+            let footers = process.get_footers();
+            let credentials = footers
+                .get(self.footer.get()..)
+                .and_then(|footer| tock_tbf::parse::parse_tbf_footer(footer).ok());
+            let (credentials, footer_len) = match credentials {
+                Some(footer) => footer,
+                None => {
+                    if policy.require_credentials() {
+                        self.reject(process, "no accepted credentials");
+                    } else {
+                        self.approve(process, None);
+                    }
+                    self.next_process();
+                    continue;
+                }
+            };
+            self.footer_len.set(footer_len as usize);
+            if credentials.format() == TbfFooterV2CredentialsType::Reserved {
+                self.next_footer();
+                continue;
+            }
+
+            self.checking.set(true);
+            match policy.check_credentials(credentials, process.get_credentials_region()) {
+                Ok(()) => return,
+                Err((ErrorCode::BUSY, _, _)) => {
+                    // Check the same footer again once the policy is done.
+                    self.checking.set(false);
+                    self.deferred_call.set();
+                    return;
+                }
+                Err((ErrorCode::NOSUPPORT, _, _)) | Err((ErrorCode::INVAL, _, _)) => {
+                    // The policy does not handle this credential; try the next.
+                    self.checking.set(false);
+                    self.next_footer();
+                }
+                Err((error, _, _)) => {
+                    self.checking.set(false);
+                    self.check_failed(process, error);
+                    self.next_process();
+                }
+            }
+        }
+    }
+
+    /// Rejects a process whose credentials could not be checked.
+    fn check_failed(&self, process: &dyn Process, error: ErrorCode) {
+        debug!(
+            "Process {} credentials check failed: {:?}",
+            process.get_process_name(),
+            error
+        );
+        process.mark_credentials_fail(&ApprovalCapability);
+    }
+
+    fn approve(&self, process: &dyn Process, credentials: Option<TbfFooterV2Credentials>) {
+        let short_app_id = match (credentials, self.compressor.extract()) {
+            (Some(credentials), Some(compressor)) => compressor.to_short_id(&credentials),
+            _ => ShortID::LocallyUnique,
+        };
+
+        // Fixed identifiers must be unique among the processes that may run.
+        if let ShortID::Fixed(_) = short_app_id {
+            let duplicate = self.kernel.get_process_iter().any(|other| {
+                other.processid() != process.processid()
+                    && other.get_state() != State::CredentialsUnchecked
+                    && other.get_state() != State::CredentialsFailed
+                    && other.short_app_id() == short_app_id
+            });
+            if duplicate {
+                self.reject(process, "application identifier already in use");
+                return;
+            }
+        }
+
+        if config::CONFIG.debug_load_processes {
+            debug!(
+                "Process {} credentials approved, {:?}",
+                process.get_process_name(),
+                short_app_id
+            );
+        }
+        if process
+            .mark_credentials_pass(credentials, short_app_id, &ApprovalCapability)
+            .is_err()
+        {
+            debug!(
+                "Process {} could not be started after approval",
+                process.get_process_name()
+            );
+        }
+    }
+
+    fn reject(&self, process: &dyn Process, reason: &str) {
+        debug!(
+            "Process {} not started: {}",
+            process.get_process_name(),
+            reason
+        );
+        process.mark_credentials_fail(&ApprovalCapability);
+    }
+}
+
+impl CredentialsCheckingClient<'static> for ProcessCheckerMachine {
+    fn check_done(
+        &self,
+        result: Result<CheckResult, ErrorCode>,
+        credentials: TbfFooterV2Credentials,
+        _binary: &'static [u8],
+    ) {
+        self.checking.set(false);
+        if let Some(process) = self.current_process() {
+            match result {
+                Ok(CheckResult::Accept) => {
+                    self.approve(process, Some(credentials));
+                    self.next_process();
+                }
+                Ok(CheckResult::Reject) => {
+                    self.reject(process, "credentials rejected");
+                    self.next_process();
+                }
+                // Move on to the next credential of this process.
+                Ok(CheckResult::Pass) => self.next_footer(),
+                // Check the same credential again from the kernel loop.
+                Err(ErrorCode::BUSY) => {
+                    self.deferred_call.set();
+                    return;
+                }
+                Err(error) => {
+                    self.check_failed(process, error);
+                    self.next_process();
+                }
+            }
+        }
+        self.next();
+    }
+}
+
+impl DeferredCallClient for ProcessCheckerMachine {
+    fn handle_deferred_call(&self) {
+        if !self.checking.get() {
+            self.next();
+        }
+    }
+
+    fn register(&'static self) {
+        self.deferred_call.register(self);
+    }
+}
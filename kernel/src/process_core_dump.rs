@@ -0,0 +1,366 @@
+//! Core dumps of faulted processes.
+//!
+//! [`CoreDump`] is a `ProcessFaultPolicy` that wraps the board's fault
+//! policy. Every time a process faults it asks the wrapped policy what to do,
+//! and takes a snapshot of the process before the kernel acts on the
+//! decision, so the state of the process is not lost when it is restarted.
+//! The snapshot holds the memory the process has access to, the registers
+//! the kernel stored when the process stopped running, the MPU configuration
+//! and the TBF header of the process.
+//!
+//! The snapshot is kept in a buffer until it has been exported through the
+//! [`ProcessCoreDump`] trait, usually by `capsules::core_dump::CoreDumpWriter`
+//! to a console UART or to nonvolatile storage. `tools/core-dump` turns it
+//! into an ELF core file that gdb loads alongside the ELF of the app. Faults
+//! that happen while a snapshot is waiting to be exported are not dumped, so
+//! the first fault of a crash loop is the one that is kept. If the fault
+//! policy panics the kernel, the snapshot is taken but never exported.
+//!
+//! Format
+//! ------
+//!
+//! A dump is a header followed by a number of sections. Each section is
+//! padded to a multiple of 4 bytes. All fields are little endian.
+//!
+//! ```text
+//! Header:
+//!   0   magic, the bytes "TKCD"
+//!   4   u16 format version, 1
+//!   6   u16 number of sections
+//!   8   u32 length of the dump, including the header
+//!   12  u32 flags, bit 0 is set if the process memory did not fit in the
+//!           buffer and was truncated
+//!
+//! Section header:
+//!   0   tag, four ASCII bytes
+//!   4   u32 address of the data in the address space of the process, or 0
+//!   8   u32 length of the data, without padding
+//!
+//! Sections, in this order:
+//!   "PROC"  information about the process, see below
+//!   "REGS"  the stored state of the process, as written by
+//!           `UserspaceKernelBoundary::store_context()`. The first three words
+//!           are a version, a length and a tag naming the architecture
+//!           ("ctxm" for Cortex-M, "rv5i" for RISC-V). Omitted if the
+//!           architecture could not store the state.
+//!   "MPU "  the MPU configuration of the process, as text
+//!   "TBFH"  the TBF header of the process, at the start of its flash region
+//!   "RAM "  the memory the process has access to, from the start of its
+//!           RAM region up to the application break
+//!
+//! PROC:
+//!   0   process name, truncated to 16 bytes and padded with zeros
+//!   16  u32 restart count
+//!   20  u32 program counter when the process faulted
+//!   24  u32 address whose access caused the fault
+//!   28  u8  fault action, 0 panic, 1 restart, 2 stop
+//!   29  u8  flags, bit 0 is set if the program counter is valid and bit 1
+//!           if the fault address is valid
+//!   30  u16 reserved
+//!   32  u32 start of the flash region of the process
+//!   36  u32 end of the flash region
+//!   40  u32 start of the RAM region of the process
+//!   44  u32 application break
+//!   48  u32 start of the grant region
+//!   52  u32 end of the RAM region
+//!   56  u32 top of the stack, or 0 if unknown
+//!   60  u32 start of the heap, or 0 if unknown
+//! ```
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let core_dump = static_init!(
+//!     kernel::process::CoreDump,
+//!     kernel::process::CoreDump::new(&FAULT_RESPONSE, &mut CORE_DUMP_BUF)
+//! );
+//! core_dump.set_client(core_dump_writer);
+//!
+//! kernel::process::load_processes(
+//!     board_kernel,
+//!     chip,
+//!     app_flash,
+//!     &mut APP_MEMORY,
+//!     &mut PROCESSES,
+//!     core_dump,
+//!     &process_management_capability,
+//! );
+//! ```
+
+use core::cell::Cell;
+use core::fmt::Write;
+
+use crate::process::{FaultAction, Process};
+use crate::process_policies::ProcessFaultPolicy;
+use crate::utilities::cells::{OptionalCell, TakeCell};
+
+/// The bytes that start every dump.
+const CORE_DUMP_MAGIC: [u8; 4] = *b"TKCD";
+
+/// Version of the dump format.
+const FORMAT_VERSION: u16 = 1;
+
+/// Length of the dump header in bytes.
+const HEADER_LEN: usize = 16;
+
+/// Length of a section header in bytes.
+const SECTION_HEADER_LEN: usize = 12;
+
+/// Length of the PROC section in bytes.
+const PROC_LEN: usize = 64;
+
+/// Maximum number of bytes of the process name stored in a dump.
+const NAME_LEN: usize = 16;
+
+// Flags in the header.
+const FLAG_TRUNCATED: u32 = 0x01;
+
+// Flags in the PROC section.
+const FLAG_PC: u8 = 0x01;
+const FLAG_FAULT_ADDRESS: u8 = 0x02;
+
+/// Client notified when a core dump has been taken.
+pub trait CoreDumpClient {
+    /// Called after a process faulted and a dump of `length` bytes is ready
+    /// to be exported.
+    fn core_dump_ready(&self, length: usize);
+}
+
+/// Read access to the core dump waiting to be exported.
+pub trait ProcessCoreDump {
+    /// Returns the length of the dump, or 0 if there is no dump.
+    fn dump_len(&self) -> usize;
+
+    /// Copies the dump, starting at `offset`, into `buf`. Returns the number
+    /// of bytes copied.
+    fn read_dump(&self, offset: usize, buf: &mut [u8]) -> usize;
+
+    /// Discards the dump once it has been exported, so that the next fault
+    /// can be dumped.
+    fn clear_dump(&self);
+}
+
+/// Writes the sections of a dump into a buffer.
+struct DumpBuilder<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+    sections: u16,
+    flags: u32,
+}
+
+impl<'b> DumpBuilder<'b> {
+    fn new(buf: &'b mut [u8]) -> DumpBuilder<'b> {
+        DumpBuilder {
+            buf,
+            len: HEADER_LEN,
+            sections: 0,
+            flags: 0,
+        }
+    }
+
+    /// Adds a section if there is room for its header. `fill` writes the
+    /// data of the section into the slice it is passed, and returns its
+    /// length, or `None` to leave the section out.
+    fn section<F: FnOnce(&mut [u8]) -> Option<usize>>(
+        &mut self,
+        tag: &[u8; 4],
+        address: usize,
+        fill: F,
+    ) {
+        let data_start = self.len + SECTION_HEADER_LEN;
+        if data_start > self.buf.len() {
+            return;
+        }
+        let length = match fill(&mut self.buf[data_start..]) {
+            Some(length) => length,
+            None => return,
+        };
+
+        let header = &mut self.buf[self.len..data_start];
+        header[0..4].copy_from_slice(tag);
+        header[4..8].copy_from_slice(&(address as u32).to_le_bytes());
+        header[8..12].copy_from_slice(&(length as u32).to_le_bytes());
+
+        // Pad the section with zeros to a multiple of 4 bytes.
+        let end = core::cmp::min(data_start + ((length + 3) & !3), self.buf.len());
+        self.buf[data_start + length..end].fill(0);
+        self.len = end;
+        self.sections += 1;
+    }
+
+    /// Writes the header and returns the length of the dump.
+    fn finish(self) -> usize {
+        self.buf[0..4].copy_from_slice(&CORE_DUMP_MAGIC);
+        self.buf[4..6].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+        self.buf[6..8].copy_from_slice(&self.sections.to_le_bytes());
+        self.buf[8..12].copy_from_slice(&(self.len as u32).to_le_bytes());
+        self.buf[12..16].copy_from_slice(&self.flags.to_le_bytes());
+        self.len
+    }
+}
+
+/// Writes text into a slice, dropping whatever does not fit.
+struct SliceWriter<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl<'b> Write for SliceWriter<'b> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let count = core::cmp::min(s.len(), self.buf.len() - self.len);
+        self.buf[self.len..self.len + count].copy_from_slice(&s.as_bytes()[..count]);
+        self.len += count;
+        Ok(())
+    }
+}
+
+/// Takes core dumps of faulted processes. See the module documentation.
+pub struct CoreDump<'a> {
+    policy: &'a dyn ProcessFaultPolicy,
+    buffer: TakeCell<'static, [u8]>,
+    /// Length of the dump in `buffer`, or 0 if there is none.
+    length: Cell<usize>,
+    client: OptionalCell<&'a dyn CoreDumpClient>,
+}
+
+impl<'a> CoreDump<'a> {
+    /// `buffer` holds the dump, and limits how much of the memory of a
+    /// process is dumped.
+    pub fn new(policy: &'a dyn ProcessFaultPolicy, buffer: &'static mut [u8]) -> CoreDump<'a> {
+        CoreDump {
+            policy,
+            buffer: TakeCell::new(buffer),
+            length: Cell::new(0),
+            client: OptionalCell::empty(),
+        }
+    }
+
+    pub fn set_client(&self, client: &'a dyn CoreDumpClient) {
+        self.client.set(client);
+    }
+
+    /// Takes a snapshot of `process` into `buf` and returns its length.
+    fn snapshot(process: &dyn Process, action: FaultAction, buf: &mut [u8]) -> usize {
+        if buf.len() < HEADER_LEN {
+            return 0;
+        }
+        let addresses = process.get_addresses();
+        let mut dump = DumpBuilder::new(buf);
+
+        dump.section(b"PROC", 0, |data| {
+            if data.len() < PROC_LEN {
+                return None;
+            }
+            let data = &mut data[..PROC_LEN];
+            data.fill(0);
+            let name = process.get_process_name().as_bytes();
+            let name_len = core::cmp::min(name.len(), NAME_LEN);
+            data[..name_len].copy_from_slice(&name[..name_len]);
+
+            let context = process.get_fault_context();
+            let flags = context.pc.map_or(0, |_| FLAG_PC)
+                | context.fault_address.map_or(0, |_| FLAG_FAULT_ADDRESS);
+            let words = [
+                (16, process.get_restart_count()),
+                (20, context.pc.unwrap_or(0)),
+                (24, context.fault_address.unwrap_or(0)),
+                (32, addresses.flash_start),
+                (36, addresses.flash_end),
+                (40, addresses.sram_start),
+                (44, addresses.sram_app_brk),
+                (48, addresses.sram_grant_start),
+                (52, addresses.sram_end),
+                (56, addresses.sram_stack_top.unwrap_or(0)),
+                (60, addresses.sram_heap_start.unwrap_or(0)),
+            ];
+            for (offset, value) in words {
+                data[offset..offset + 4].copy_from_slice(&(value as u32).to_le_bytes());
+            }
+            data[28] = match action {
+                FaultAction::Panic => 0,
+                FaultAction::Restart => 1,
+                FaultAction::Stop => 2,
+            };
+            data[29] = flags;
+            Some(PROC_LEN)
+        });
+
+        dump.section(b"REGS", 0, |data| process.get_stored_state(data).ok());
+
+        dump.section(b"MPU ", 0, |data| {
+            let mut writer = SliceWriter { buf: data, len: 0 };
+            process.print_mpu_config(&mut writer);
+            Some(writer.len)
+        });
+
+        let header_len = addresses.flash_non_protected_start - addresses.flash_start;
+        dump.section(b"TBFH", addresses.flash_start, |data| {
+            let length = core::cmp::min(header_len, data.len());
+            // SAFETY: the flash region of the process is mapped and is never
+            // written while the process exists.
+            let header =
+                unsafe { core::slice::from_raw_parts(addresses.flash_start as *const u8, length) };
+            data[..length].copy_from_slice(header);
+            Some(length)
+        });
+
+        let ram_len = addresses.sram_app_brk - addresses.sram_start;
+        let mut truncated = false;
+        dump.section(b"RAM ", addresses.sram_start, |data| {
+            let length = core::cmp::min(ram_len, data.len());
+            truncated = length < ram_len;
+            // SAFETY: the memory below the application break belongs to the
+            // process, and the kernel does not hold any references into it
+            // while it handles the fault. The process does not run while the
+            // memory is copied.
+            let ram =
+                unsafe { core::slice::from_raw_parts(addresses.sram_start as *const u8, length) };
+            data[..length].copy_from_slice(ram);
+            Some(length)
+        });
+        if truncated {
+            dump.flags |= FLAG_TRUNCATED;
+        }
+
+        dump.finish()
+    }
+}
+
+impl<'a> ProcessFaultPolicy for CoreDump<'a> {
+    fn action(&self, process: &dyn Process) -> FaultAction {
+        let action = self.policy.action(process);
+        if self.length.get() == 0 {
+            let length = self
+                .buffer
+                .map_or(0, |buffer| Self::snapshot(process, action, buffer));
+            self.length.set(length);
+            if length > 0 {
+                self.client.map(|client| client.core_dump_ready(length));
+            }
+        }
+        action
+    }
+}
+
+impl<'a> ProcessCoreDump for CoreDump<'a> {
+    fn dump_len(&self) -> usize {
+        self.length.get()
+    }
+
+    fn read_dump(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let length = self.length.get();
+        if offset >= length {
+            return 0;
+        }
+        self.buffer.map_or(0, |dump| {
+            let count = core::cmp::min(buf.len(), length - offset);
+            buf[..count].copy_from_slice(&dump[offset..offset + count]);
+            count
+        })
+    }
+
+    fn clear_dump(&self) {
+        self.length.set(0);
+    }
+}
@@ -0,0 +1,424 @@
+//! Persistent history of process faults.
+//!
+//! [`FaultHistory`] is a `ProcessFaultPolicy` that wraps the board's fault
+//! policy. Every time a process faults it asks the wrapped policy what to do,
+//! and records the fault and the decision in a [`ProcessFaultRecord`]. The
+//! records are appended to a persistent log (for example a
+//! `capsules::log::Log` volume), so that crash loops can be diagnosed after
+//! the board resets.
+//!
+//! The most recent records, including the ones read back from the log at
+//! boot, are also kept in RAM and can be inspected through the
+//! [`ProcessFaultHistory`] trait, for example by the process console.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! let fault_history = static_init!(
+//!     kernel::process::FaultHistory<'static, capsules::log::Log<'static, F>>,
+//!     kernel::process::FaultHistory::new(
+//!         &FAULT_RESPONSE,
+//!         log,
+//!         &mut FAULT_HISTORY_RECORDS,
+//!         &mut FAULT_HISTORY_BUF,
+//!     )
+//! );
+//! log.set_read_client(fault_history);
+//! log.set_append_client(fault_history);
+//! fault_history.load();
+//!
+//! kernel::process::load_processes(
+//!     board_kernel,
+//!     chip,
+//!     app_flash,
+//!     &mut APP_MEMORY,
+//!     &mut PROCESSES,
+//!     fault_history,
+//!     &process_management_capability,
+//! );
+//! ```
+
+use core::cell::Cell;
+
+use crate::hil::log::{LogRead, LogReadClient, LogWrite, LogWriteClient};
+use crate::process::{FaultAction, Process};
+use crate::process_policies::ProcessFaultPolicy;
+use crate::utilities::cells::TakeCell;
+use crate::ErrorCode;
+
+/// Length of an encoded `ProcessFaultRecord` in the log.
+pub const FAULT_RECORD_LEN: usize = 36;
+
+/// Maximum number of bytes of the process name stored in a record.
+pub const FAULT_RECORD_NAME_LEN: usize = 16;
+
+/// Version of the encoding of `ProcessFaultRecord`.
+const RECORD_VERSION: u8 = 1;
+
+// Flags for which optional fields of a record are present.
+const FLAG_PC: u8 = 0x01;
+const FLAG_FAULT_ADDRESS: u8 = 0x02;
+const FLAG_COMPLETION_CODE: u8 = 0x04;
+
+/// A single fault of a process.
+#[derive(Clone, Copy)]
+pub struct ProcessFaultRecord {
+    /// The process name, truncated to `FAULT_RECORD_NAME_LEN` bytes and
+    /// padded with zeros.
+    pub name: [u8; FAULT_RECORD_NAME_LEN],
+    /// How many times the process had been restarted before this fault.
+    pub restart_count: u32,
+    /// The program counter of the process when it faulted.
+    pub pc: Option<u32>,
+    /// The address whose access caused the fault, if the architecture
+    /// reports it.
+    pub fault_address: Option<u32>,
+    /// The completion code of the last time the process exited, if it
+    /// exited before this fault rather than faulting.
+    pub completion_code: Option<u32>,
+    /// What the fault policy decided to do with the process.
+    pub action: FaultAction,
+}
+
+impl Default for ProcessFaultRecord {
+    fn default() -> Self {
+        ProcessFaultRecord {
+            name: [0; FAULT_RECORD_NAME_LEN],
+            restart_count: 0,
+            pc: None,
+            fault_address: None,
+            completion_code: None,
+            action: FaultAction::Stop,
+        }
+    }
+}
+
+impl ProcessFaultRecord {
+    fn new(process: &dyn Process, action: FaultAction) -> ProcessFaultRecord {
+        let mut name = [0; FAULT_RECORD_NAME_LEN];
+        let process_name = process.get_process_name().as_bytes();
+        let name_len = core::cmp::min(process_name.len(), FAULT_RECORD_NAME_LEN);
+        name[..name_len].copy_from_slice(&process_name[..name_len]);
+
+        let context = process.get_fault_context();
+        ProcessFaultRecord {
+            name,
+            restart_count: process.get_restart_count() as u32,
+            pc: context.pc.map(|pc| pc as u32),
+            fault_address: context.fault_address.map(|address| address as u32),
+            completion_code: process.get_completion_code().flatten(),
+            action,
+        }
+    }
+
+    /// The process name, without padding.
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(FAULT_RECORD_NAME_LEN);
+        // A name truncated in the middle of a character loses that character.
+        match core::str::from_utf8(&self.name[..len]) {
+            Ok(name) => name,
+            Err(e) => core::str::from_utf8(&self.name[..e.valid_up_to()]).unwrap_or(""),
+        }
+    }
+
+    /// Encodes the record into the first `FAULT_RECORD_LEN` bytes of `buf`.
+    fn encode(&self, buf: &mut [u8]) {
+        let flags = self.pc.map_or(0, |_| FLAG_PC)
+            | self.fault_address.map_or(0, |_| FLAG_FAULT_ADDRESS)
+            | self.completion_code.map_or(0, |_| FLAG_COMPLETION_CODE);
+        buf[0] = RECORD_VERSION;
+        buf[1] = flags;
+        buf[2] = match self.action {
+            FaultAction::Panic => 0,
+            FaultAction::Restart => 1,
+            FaultAction::Stop => 2,
+        };
+        buf[3] = 0;
+        buf[4..20].copy_from_slice(&self.name);
+        buf[20..24].copy_from_slice(&self.restart_count.to_le_bytes());
+        buf[24..28].copy_from_slice(&self.pc.unwrap_or(0).to_le_bytes());
+        buf[28..32].copy_from_slice(&self.fault_address.unwrap_or(0).to_le_bytes());
+        buf[32..36].copy_from_slice(&self.completion_code.unwrap_or(0).to_le_bytes());
+    }
+
+    /// Decodes a record encoded with `encode()`.
+    fn decode(buf: &[u8]) -> Option<ProcessFaultRecord> {
+        if buf.len() != FAULT_RECORD_LEN || buf[0] != RECORD_VERSION {
+            return None;
+        }
+        let flags = buf[1];
+        let action = match buf[2] {
+            0 => FaultAction::Panic,
+            1 => FaultAction::Restart,
+            2 => FaultAction::Stop,
+            _ => return None,
+        };
+        let word = |offset: usize| {
+            u32::from_le_bytes([
+                buf[offset],
+                buf[offset + 1],
+                buf[offset + 2],
+                buf[offset + 3],
+            ])
+        };
+        let field = |flag: u8, offset: usize| {
+            if flags & flag != 0 {
+                Some(word(offset))
+            } else {
+                None
+            }
+        };
+
+        let mut name = [0; FAULT_RECORD_NAME_LEN];
+        name.copy_from_slice(&buf[4..20]);
+        Some(ProcessFaultRecord {
+            name,
+            restart_count: word(20),
+            pc: field(FLAG_PC, 24),
+            fault_address: field(FLAG_FAULT_ADDRESS, 28),
+            completion_code: field(FLAG_COMPLETION_CODE, 32),
+            action,
+        })
+    }
+}
+
+/// Read access to the recorded process faults.
+pub trait ProcessFaultHistory {
+    /// Returns the number of records available.
+    fn record_count(&self) -> usize;
+
+    /// Returns the record at `index`, where index 0 is the oldest record.
+    fn get_record(&self, index: usize) -> Option<ProcessFaultRecord>;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    Reading,
+    Appending,
+    Syncing,
+}
+
+/// Records process faults in a log. See the module documentation.
+pub struct FaultHistory<'a, L: LogRead<'a> + LogWrite<'a>> {
+    policy: &'a dyn ProcessFaultPolicy,
+    log: &'a L,
+    buffer: TakeCell<'static, [u8]>,
+    /// The most recent records, in a circular buffer.
+    records: TakeCell<'static, [ProcessFaultRecord]>,
+    /// Index of the oldest record in `records`.
+    start: Cell<usize>,
+    /// Number of records in `records`.
+    count: Cell<usize>,
+    /// Number of the newest records that are not yet in the log.
+    unwritten: Cell<usize>,
+    state: Cell<State>,
+}
+
+impl<'a, L: LogRead<'a> + LogWrite<'a>> FaultHistory<'a, L> {
+    /// `records` holds the most recent records in RAM. `buffer` must be at
+    /// least `FAULT_RECORD_LEN` bytes long.
+    pub fn new(
+        policy: &'a dyn ProcessFaultPolicy,
+        log: &'a L,
+        records: &'static mut [ProcessFaultRecord],
+        buffer: &'static mut [u8],
+    ) -> FaultHistory<'a, L> {
+        FaultHistory {
+            policy,
+            log,
+            buffer: TakeCell::new(buffer),
+            records: TakeCell::new(records),
+            start: Cell::new(0),
+            count: Cell::new(0),
+            unwritten: Cell::new(0),
+            state: Cell::new(State::Idle),
+        }
+    }
+
+    /// Reads the records stored in the log by earlier boots. Faults that
+    /// occur while the log is read are written to the log afterwards.
+    pub fn load(&self) -> Result<(), ErrorCode> {
+        if self.state.get() != State::Idle {
+            return Err(ErrorCode::BUSY);
+        }
+        self.read_next()
+    }
+
+    fn read_next(&self) -> Result<(), ErrorCode> {
+        let buffer = self.buffer.take().ok_or(ErrorCode::BUSY)?;
+        let len = buffer.len();
+        match self.log.read(buffer, len) {
+            Ok(()) => {
+                self.state.set(State::Reading);
+                Ok(())
+            }
+            Err((e, buffer)) => {
+                self.buffer.replace(buffer);
+                self.state.set(State::Idle);
+                Err(e)
+            }
+        }
+    }
+
+    /// Inserts `record` before the records that are not yet in the log,
+    /// dropping the oldest record if the history is full.
+    fn insert(&self, record: ProcessFaultRecord) {
+        self.records.map(|records| {
+            let capacity = records.len();
+            if capacity == 0 {
+                return;
+            }
+            let mut position = self.count.get() - self.unwritten.get();
+            if self.count.get() == capacity {
+                if position == 0 {
+                    // The new record would be the oldest one.
+                    return;
+                }
+                self.start.set((self.start.get() + 1) % capacity);
+                self.count.set(capacity - 1);
+                position -= 1;
+            }
+
+            // Shift the unwritten records to make room.
+            let start = self.start.get();
+            let mut index = self.count.get();
+            while index > position {
+                records[(start + index) % capacity] = records[(start + index - 1) % capacity];
+                index -= 1;
+            }
+            records[(start + position) % capacity] = record;
+            self.count.set(self.count.get() + 1);
+        });
+    }
+
+    /// Adds `record` as the newest record and writes it to the log.
+    fn push(&self, record: ProcessFaultRecord) {
+        self.records.map(|records| {
+            let capacity = records.len();
+            if capacity == 0 {
+                return;
+            }
+            if self.count.get() == capacity {
+                self.start.set((self.start.get() + 1) % capacity);
+                self.count.set(capacity - 1);
+            }
+            records[(self.start.get() + self.count.get()) % capacity] = record;
+            self.count.set(self.count.get() + 1);
+            self.unwritten
+                .set(core::cmp::min(self.unwritten.get() + 1, self.count.get()));
+        });
+        self.write_next();
+    }
+
+    /// Appends the oldest unwritten record to the log, or syncs the log once
+    /// all records are appended.
+    fn write_next(&self) {
+        if self.state.get() != State::Idle || self.unwritten.get() == 0 {
+            return;
+        }
+        let record = match self.get_record(self.count.get() - self.unwritten.get()) {
+            Some(record) => record,
+            None => return,
+        };
+        self.buffer.take().map(|buffer| {
+            if buffer.len() < FAULT_RECORD_LEN {
+                self.buffer.replace(buffer);
+                return;
+            }
+            record.encode(buffer);
+            match self.log.append(buffer, FAULT_RECORD_LEN) {
+                Ok(()) => self.state.set(State::Appending),
+                Err((_, buffer)) => {
+                    // Try again on the next fault.
+                    self.buffer.replace(buffer);
+                }
+            }
+        });
+    }
+}
+
+impl<'a, L: LogRead<'a> + LogWrite<'a>> ProcessFaultPolicy for FaultHistory<'a, L> {
+    fn action(&self, process: &dyn Process) -> FaultAction {
+        let action = self.policy.action(process);
+        // A panic stops the kernel before the record could reach the log.
+        self.push(ProcessFaultRecord::new(process, action));
+        action
+    }
+}
+
+impl<'a, L: LogRead<'a> + LogWrite<'a>> ProcessFaultHistory for FaultHistory<'a, L> {
+    fn record_count(&self) -> usize {
+        self.count.get()
+    }
+
+    fn get_record(&self, index: usize) -> Option<ProcessFaultRecord> {
+        if index >= self.count.get() {
+            return None;
+        }
+        self.records
+            .map(|records| records[(self.start.get() + index) % records.len()])
+    }
+}
+
+impl<'a, L: LogRead<'a> + LogWrite<'a>> LogReadClient for FaultHistory<'a, L> {
+    fn read_done(&self, buffer: &'static mut [u8], length: usize, error: Result<(), ErrorCode>) {
+        self.buffer.replace(buffer);
+        match error {
+            Ok(()) => {
+                // Skip entries that are not fault records.
+                let record = self
+                    .buffer
+                    .map(|buffer| ProcessFaultRecord::decode(&buffer[..length]))
+                    .flatten();
+                record.map(|record| self.insert(record));
+                if self.read_next().is_err() {
+                    self.write_next();
+                }
+            }
+            Err(_) => {
+                // The end of the log.
+                self.state.set(State::Idle);
+                self.write_next();
+            }
+        }
+    }
+
+    fn seek_done(&self, _error: Result<(), ErrorCode>) {}
+}
+
+impl<'a, L: LogRead<'a> + LogWrite<'a>> LogWriteClient for FaultHistory<'a, L> {
+    fn append_done(
+        &self,
+        buffer: &'static mut [u8],
+        _length: usize,
+        _records_lost: bool,
+        _error: Result<(), ErrorCode>,
+    ) {
+        self.buffer.replace(buffer);
+        // A record that could not be appended is not retried, so that a full
+        // log does not block newer records.
+        self.unwritten.set(self.unwritten.get().saturating_sub(1));
+        self.state.set(State::Idle);
+
+        if self.unwritten.get() == 0 {
+            if self.log.sync().is_ok() {
+                self.state.set(State::Syncing);
+            }
+        } else {
+            self.write_next();
+        }
+    }
+
+    fn sync_done(&self, _error: Result<(), ErrorCode>) {
+        self.state.set(State::Idle);
+        self.write_next();
+    }
+
+    fn erase_done(&self, _error: Result<(), ErrorCode>) {}
+}
@@ -0,0 +1,250 @@
+//! Loading new processes while the kernel is running.
+//!
+//! Boards that support dynamic app loading load their processes at boot with
+//! a [`DynamicProcessLoader`] instead of `load_processes()`. The loader keeps
+//! the app flash after the last app and the app memory that was not assigned
+//! to a process, and creates new processes from them at runtime.
+//!
+//! Loading an app takes three steps, driven by a capsule through the
+//! [`DynamicProcessLoading`] trait:
+//!
+//! 1. The capsule reserves flash for the new TBF object. The object is placed
+//!    directly after the last app, so it extends the app linked list and is
+//!    found again by the boot loader after a reboot.
+//! 2. The capsule writes the TBF object to the reserved flash, for example
+//!    through `hil::nonvolatile_storage`.
+//! 3. The capsule asks the loader to load the reserved app. The loader parses
+//!    the TBF header and creates the process in an empty slot of the processes
+//!    array.
+//!
+//! Like the processes loaded at boot, new processes are created in the
+//! `CredentialsUnchecked` state. The board's [`ProcessCheckerMachine`] checks
+//! their credentials, and they only start once it approves them.
+//!
+//! The reserved flash must be placed where the MPU can protect it. Apps that
+//! need their flash aligned to their size (for example, on Cortex-M MPUs)
+//! must be sized so that the end of the previous app is suitably aligned,
+//! otherwise loading fails.
+
+use core::cell::Cell;
+use core::convert::TryInto;
+
+use crate::capabilities::ProcessManagementCapability;
+use crate::config;
+use crate::debug;
+use crate::errorcode::ErrorCode;
+use crate::kernel::Kernel;
+use crate::platform::chip::Chip;
+use crate::process::{Process, ProcessId};
+use crate::process_checker::ProcessCheckerMachine;
+use crate::process_policies::ProcessFaultPolicy;
+use crate::process_standard::ProcessStandard;
+use crate::process_utilities::{load_processes_from_flash, ProcessLoadError};
+use crate::utilities::cells::{OptionalCell, TakeCell};
+
+/// Interface for capsules that receive new apps at runtime.
+pub trait DynamicProcessLoading {
+    /// Reserves `size` bytes of app flash for a new TBF object. Returns the
+    /// address of the reserved flash, where the object must be written.
+    ///
+    /// Returns `BUSY` if flash is already reserved, `NOMEM` if there is not
+    /// enough free app flash and `INVAL` if `size` is too small to hold a
+    /// TBF header.
+    fn reserve_app_flash(&self, size: usize) -> Result<usize, ErrorCode>;
+
+    /// Creates a process from the TBF object written to the reserved flash.
+    /// The process starts once its credentials are approved. The reservation
+    /// is released either way.
+    ///
+    /// Returns `RESERVE` if no flash is reserved, `INVAL` if the TBF object
+    /// is not a valid app or does not fill the reserved flash, and `NOMEM` if
+    /// there is no free slot or not enough memory for the process.
+    fn load_app(&self) -> Result<ProcessId, ErrorCode>;
+
+    /// Releases the reserved flash without loading an app from it.
+    fn cancel_reservation(&self);
+}
+
+/// Loads processes at boot and keeps the remaining app flash and memory to
+/// load new processes at runtime.
+pub struct DynamicProcessLoader<C: 'static + Chip> {
+    kernel: &'static Kernel,
+    chip: &'static C,
+    app_flash: &'static [u8],
+    fault_policy: &'static dyn ProcessFaultPolicy,
+    checker: &'static ProcessCheckerMachine,
+    procs: TakeCell<'static, [Option<&'static dyn Process>]>,
+    /// App memory not yet assigned to a process.
+    app_memory: TakeCell<'static, [u8]>,
+    /// Offset into `app_flash` of the end of the last app.
+    flash_end: Cell<usize>,
+    /// Length of the reserved flash, which starts at `flash_end`.
+    reserved: OptionalCell<usize>,
+}
+
+impl<C: 'static + Chip> DynamicProcessLoader<C> {
+    pub fn new(
+        kernel: &'static Kernel,
+        chip: &'static C,
+        app_flash: &'static [u8],
+        app_memory: &'static mut [u8],
+        procs: &'static mut [Option<&'static dyn Process>],
+        fault_policy: &'static dyn ProcessFaultPolicy,
+        checker: &'static ProcessCheckerMachine,
+        _capability: &dyn ProcessManagementCapability,
+    ) -> DynamicProcessLoader<C> {
+        DynamicProcessLoader {
+            kernel,
+            chip,
+            app_flash,
+            fault_policy,
+            checker,
+            procs: TakeCell::new(procs),
+            app_memory: TakeCell::new(app_memory),
+            flash_end: Cell::new(app_flash.len()),
+            reserved: OptionalCell::empty(),
+        }
+    }
+
+    /// Loads the processes in app flash, like `load_and_check_processes()`.
+    ///
+    /// If loading fails, no apps can be loaded at runtime, as the end of the
+    /// app linked list is unknown.
+    pub fn load_processes(&self) -> Result<(), ProcessLoadError> {
+        let procs = self.procs.take().ok_or(ProcessLoadError::InternalError)?;
+        let app_memory = self
+            .app_memory
+            .take()
+            .ok_or(ProcessLoadError::InternalError)?;
+        let memory_len = app_memory.len();
+
+        let result = load_processes_from_flash(
+            self.kernel,
+            self.chip,
+            self.app_flash,
+            &mut app_memory[..],
+            procs,
+            self.fault_policy,
+            true,
+            true,
+        )
+        .map(|(remaining_flash, remaining_memory)| {
+            (
+                self.app_flash.len() - remaining_flash.len(),
+                memory_len - remaining_memory.len(),
+            )
+        });
+        self.procs.replace(procs);
+        // Check the processes that were loaded, even if loading stopped early.
+        self.checker.start();
+
+        let (flash_used, memory_used) = result?;
+        self.flash_end.set(flash_used);
+        self.app_memory.replace(&mut app_memory[memory_used..]);
+        Ok(())
+    }
+}
+
+impl<C: 'static + Chip> DynamicProcessLoading for DynamicProcessLoader<C> {
+    fn reserve_app_flash(&self, size: usize) -> Result<usize, ErrorCode> {
+        if self.reserved.is_some() {
+            return Err(ErrorCode::BUSY);
+        }
+        // The TBF header base is 16 bytes long.
+        if size < 16 {
+            return Err(ErrorCode::INVAL);
+        }
+        let start = self.flash_end.get();
+        if size > self.app_flash.len() - start {
+            return Err(ErrorCode::NOMEM);
+        }
+
+        self.reserved.set(size);
+        Ok(self.app_flash.as_ptr() as usize + start)
+    }
+
+    fn load_app(&self) -> Result<ProcessId, ErrorCode> {
+        let size = self.reserved.take().ok_or(ErrorCode::RESERVE)?;
+        let start = self.flash_end.get();
+        let entry_flash = self
+            .app_flash
+            .get(start..start + size)
+            .ok_or(ErrorCode::FAIL)?;
+
+        let (version, header_length, entry_length) = entry_flash
+            .get(0..8)
+            .and_then(|header| header.try_into().ok())
+            .and_then(|header| tock_tbf::parse::parse_tbf_header_lengths(header).ok())
+            .ok_or(ErrorCode::INVAL)?;
+        if entry_length as usize != size {
+            return Err(ErrorCode::INVAL);
+        }
+
+        let result = self.procs.map_or(Err(ErrorCode::FAIL), |procs| {
+            let index = procs
+                .iter()
+                .position(|slot| slot.is_none())
+                .ok_or(ErrorCode::NOMEM)?;
+            let app_memory = self.app_memory.take().ok_or(ErrorCode::NOMEM)?;
+            let memory_len = app_memory.len();
+
+            let result = unsafe {
+                ProcessStandard::create(
+                    self.kernel,
+                    self.chip,
+                    entry_flash,
+                    header_length as usize,
+                    version,
+                    &mut app_memory[..],
+                    self.fault_policy,
+                    true,
+                    true,
+                    index,
+                )
+            }
+            .map(|(process, remaining_memory)| (process, memory_len - remaining_memory.len()));
+
+            match result {
+                Ok((Some(process), memory_used)) => {
+                    self.app_memory.replace(&mut app_memory[memory_used..]);
+                    self.flash_end.set(start + size);
+                    procs[index] = Some(process);
+                    if config::CONFIG.debug_load_processes {
+                        debug!(
+                            "Loaded process[{}] from flash={:#010X}-{:#010X} = {:?}",
+                            index,
+                            entry_flash.as_ptr() as usize,
+                            entry_flash.as_ptr() as usize + entry_flash.len() - 1,
+                            process.get_process_name()
+                        );
+                    }
+                    Ok(process.processid())
+                }
+                Ok((None, _)) => {
+                    // A padding app or a disabled app.
+                    self.app_memory.replace(app_memory);
+                    Err(ErrorCode::INVAL)
+                }
+                Err(e) => {
+                    self.app_memory.replace(app_memory);
+                    if config::CONFIG.debug_load_processes {
+                        debug!("Loading process failed: {:?}", e);
+                    }
+                    match e {
+                        ProcessLoadError::NotEnoughMemory => Err(ErrorCode::NOMEM),
+                        _ => Err(ErrorCode::INVAL),
+                    }
+                }
+            }
+        });
+        // The process only starts once its credentials are approved.
+        if result.is_ok() {
+            self.checker.start();
+        }
+        result
+    }
+
+    fn cancel_reservation(&self) {
+        self.reserved.clear();
+    }
+}
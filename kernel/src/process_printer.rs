@@ -147,6 +147,30 @@ impl ProcessPrinter for ProcessPrinterText {
             None => bww.write_str(" Completion Code: None\r\n"),
         };
 
+        // Peak memory usage, in bytes, since the process last started.
+        let stack_high_water = match (addresses.sram_stack_top, addresses.sram_stack_high_water) {
+            (Some(top), Some(lowest)) => Some(top.saturating_sub(lowest)),
+            _ => None,
+        };
+        let heap_high_water = addresses
+            .sram_heap_start
+            .map(|start| addresses.sram_app_brk_high_water.saturating_sub(start));
+        let app_memory_high_water = addresses.sram_app_brk_high_water - addresses.sram_start;
+
+        let _ = bww.write_str(" High Water Marks:");
+        let _ = match stack_high_water {
+            Some(bytes) => bww.write_fmt(format_args!("   Stack {}", bytes)),
+            None => bww.write_str("   Stack ?"),
+        };
+        let _ = match heap_high_water {
+            Some(bytes) => bww.write_fmt(format_args!("   Heap {}", bytes)),
+            None => bww.write_str("   Heap ?"),
+        };
+        let _ = bww.write_fmt(format_args!(
+            "   App Memory {} (bytes)\r\n",
+            app_memory_high_water
+        ));
+
         let _ = bww.write_fmt(format_args!(
             "\
                  \r\n\
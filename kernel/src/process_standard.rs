@@ -9,6 +9,7 @@ use core::fmt::Write;
 use core::ptr::NonNull;
 use core::{mem, ptr, slice, str};
 
+use crate::capabilities;
 use crate::collections::queue::Queue;
 use crate::collections::ring_buffer::RingBuffer;
 use crate::config;
@@ -19,15 +20,17 @@ use crate::platform::chip::Chip;
 use crate::platform::mpu::{self, MPU};
 use crate::process::{Error, FunctionCall, FunctionCallSource, Process, State, Task};
 use crate::process::{FaultAction, ProcessCustomGrantIdentifer, ProcessId, ProcessStateCell};
-use crate::process::{ProcessAddresses, ProcessSizes};
+use crate::process::{GrantUsage, ProcessAddresses, ProcessSizes, ShortID};
 use crate::process_policies::ProcessFaultPolicy;
 use crate::process_utilities::ProcessLoadError;
 use crate::processbuffer::{ReadOnlyProcessBuffer, ReadWriteProcessBuffer};
 use crate::storage_permissions;
-use crate::syscall::{self, Syscall, SyscallReturn, UserspaceKernelBoundary};
+use crate::syscall::{
+    self, FaultContext, Syscall, SyscallClass, SyscallReturn, UserspaceKernelBoundary,
+};
 use crate::upcall::UpcallId;
 use crate::utilities::cells::{MapCell, NumericCellExt, OptionalCell};
-use tock_tbf::types::CommandPermissions;
+use tock_tbf::types::{CommandPermissions, TbfFooterV2Credentials, TbfHeaderDriverLimit};
 
 /// State for helping with debugging apps.
 ///
@@ -55,6 +58,9 @@ struct ProcessStandardDebug {
     /// How low have we ever seen the stack pointer.
     app_stack_min_pointer: Option<*const u8>,
 
+    /// The highest the application break has been since the process started.
+    app_break_max: *const u8,
+
     /// How many syscalls have occurred since the process started.
     syscall_count: usize,
 
@@ -68,6 +74,17 @@ struct ProcessStandardDebug {
     /// How many times this process has been paused because it exceeded its
     /// timeslice.
     timeslice_expiration_count: usize,
+
+    /// How many syscalls of each `SyscallClass` have occurred since the
+    /// process started, indexed by the class identifier.
+    syscall_class_counts: [usize; 8],
+
+    /// How many upcalls from capsules the process has executed.
+    upcall_count: usize,
+
+    /// How much CPU time the process has used since it started, in
+    /// microseconds.
+    cpu_time_us: u64,
 }
 
 /// Entry that is stored in the grant pointer table at the top of process
@@ -88,7 +105,35 @@ struct GrantPointerEntry {
 
     /// The start of the memory location where the grant has been allocated, or
     /// null if the grant has not been allocated.
+    ///
+    /// The lowest bit is set while the grant is entered. Bit 1 is set if the
+    /// grant was released but its memory could not be reclaimed yet, because
+    /// it is not at the bottom of the grant region. The memory is reused if
+    /// the grant is allocated again.
     grant_ptr: *mut u8,
+
+    /// The number of bytes of the grant region used by the grant, including
+    /// padding for alignment, or 0 if the grant has not been allocated.
+    size: usize,
+}
+
+impl GrantPointerEntry {
+    /// Whether the grant is allocated and has not been released.
+    fn is_allocated(&self) -> bool {
+        !self.grant_ptr.is_null() && (self.grant_ptr as usize) & 0x2 == 0
+    }
+
+    /// Whether the grant was released but still holds its memory.
+    fn is_released(&self) -> bool {
+        (self.grant_ptr as usize) & 0x2 == 0x2
+    }
+
+    /// Mark the grant as not allocated.
+    fn clear(&mut self) {
+        self.driver_num = 0;
+        self.grant_ptr = ptr::null_mut();
+        self.size = 0;
+    }
 }
 
 /// A type for userspace processes in Tock.
@@ -212,9 +257,21 @@ pub struct ProcessStandard<'a, C: 'static + Chip> {
     /// be stored as `Some(completion code)`.
     completion_code: OptionalCell<Option<u32>>,
 
+    /// How much CPU time, in microseconds, the process may use each time it
+    /// is started, if it is limited.
+    cpu_budget_us: Cell<Option<u64>>,
+
     /// Name of the app.
     process_name: &'static str,
 
+    /// The credentials that were accepted when the process was checked, if
+    /// any.
+    credentials: OptionalCell<TbfFooterV2Credentials>,
+
+    /// Short identifier of the application, assigned when the process's
+    /// credentials are approved.
+    short_app_id: Cell<ShortID>,
+
     /// Values kept so that we can print useful debug messages when apps fault.
     debug: MapCell<ProcessStandardDebug>,
 }
@@ -348,6 +405,11 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
     }
 
     fn try_restart(&self, completion_code: Option<u32>) {
+        // A process whose credentials have not been approved must never run.
+        if !self.credentials_approved() {
+            return;
+        }
+
         // Terminate the process, freeing its state and removing any
         // pending tasks from the scheduler's queue.
         self.terminate(completion_code);
@@ -361,6 +423,12 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
     }
 
     fn terminate(&self, completion_code: Option<u32>) {
+        // A process that was never approved has no state to clear, and must
+        // keep its credentials state so that it cannot be restarted.
+        if !self.credentials_approved() {
+            return;
+        }
+
         // Remove the tasks that were scheduled for the app from the
         // amount of work queue.
         let tasks_len = self.tasks.map_or(0, |tasks| tasks.len());
@@ -383,6 +451,9 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
 
         // Mark the app as stopped so the scheduler won't try to run it.
         self.state.update(State::Terminated);
+
+        // Release the resources the kernel holds for the process.
+        self.kernel.process_terminated(self.processid());
     }
 
     fn get_restart_count(&self) -> usize {
@@ -397,6 +468,13 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
         self.tasks.map_or(None, |tasks| {
             tasks.dequeue().map(|cb| {
                 self.kernel.decrement_work();
+                if let Task::FunctionCall(FunctionCall {
+                    source: FunctionCallSource::Driver(_),
+                    ..
+                }) = cb
+                {
+                    self.debug.map(|debug| debug.upcall_count += 1);
+                }
                 cb
             })
         })
@@ -410,6 +488,18 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
         self.header.get_command_permissions(driver_num, offset)
     }
 
+    fn get_timing_constraints(&self) -> Option<(u32, u32)> {
+        self.header.get_timing_constraints()
+    }
+
+    fn get_ipc_allow_list(&self) -> Option<(usize, [u32; 8])> {
+        self.header.get_ipc_allow_list()
+    }
+
+    fn get_syscall_limits(&self) -> &[TbfHeaderDriverLimit] {
+        self.header.get_syscall_limits()
+    }
+
     fn get_storage_permissions(&self) -> Option<storage_permissions::StoragePermissions> {
         let (read_count, read_storage_ids) = self
             .header
@@ -471,13 +561,14 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
         unallocated_memory_start: *const u8,
         unallocated_memory_size: usize,
         min_region_size: usize,
+        permissions: mpu::Permissions,
     ) -> Option<mpu::Region> {
         self.mpu_config.and_then(|mut config| {
             let new_region = self.chip.mpu().allocate_region(
                 unallocated_memory_start,
                 unallocated_memory_size,
                 min_region_size,
-                mpu::Permissions::ReadWriteOnly,
+                permissions,
                 &mut config,
             );
 
@@ -552,6 +643,16 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
                     let old_break = self.app_break.get();
                     self.app_break.set(new_break);
                     self.chip.mpu().configure_mpu(&config, &self.processid());
+                    if new_break > old_break {
+                        // Memory given to the process is painted so that
+                        // stack usage within it can be measured later.
+                        Self::paint_memory(old_break, new_break);
+                        self.debug.map(|debug| {
+                            if new_break > debug.app_break_max {
+                                debug.app_break_max = new_break;
+                            }
+                        });
+                    }
                     Ok(old_break)
                 }
             })
@@ -718,7 +819,7 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
             // panic.
             grant_pointers
                 .get(grant_num)
-                .map_or(None, |grant_entry| Some(!grant_entry.grant_ptr.is_null()))
+                .map_or(None, |grant_entry| Some(grant_entry.is_allocated()))
         })
     }
 
@@ -752,9 +853,9 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
         let exists = self.grant_pointers.map_or(false, |grant_pointers| {
             // Check our list of grant pointers if the driver number is used.
             grant_pointers.iter().any(|grant_entry| {
-                // Check if the grant is both allocated (its grant pointer is
-                // non null) and the driver number matches.
-                (!grant_entry.grant_ptr.is_null()) && grant_entry.driver_num == driver_num
+                // Check if the grant is both allocated and the driver number
+                // matches.
+                grant_entry.is_allocated() && grant_entry.driver_num == driver_num
             })
         });
         // If we find a match, then the driver_num must already be used and the
@@ -763,8 +864,28 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
             return false;
         }
 
+        // If the grant was released but still holds its memory, reuse it. A
+        // grant always has the same size and alignment, so the memory fits.
+        let reused = self.grant_pointers.map_or(false, |grant_pointers| {
+            grant_pointers
+                .get_mut(grant_num)
+                .map_or(false, |grant_entry| {
+                    if grant_entry.is_released() && grant_entry.size >= size {
+                        grant_entry.driver_num = driver_num;
+                        grant_entry.grant_ptr = (grant_entry.grant_ptr as usize & !0x2) as *mut u8;
+                        true
+                    } else {
+                        false
+                    }
+                })
+        });
+        if reused {
+            return true;
+        }
+
         // Use the shared grant allocator function to actually allocate memory.
         // Returns `None` if the allocation cannot be created.
+        let kernel_memory_break = self.kernel_memory_break.get();
         if let Some(grant_ptr) = self.allocate_in_grant_region_internal(size, align) {
             // Update the grant pointer to the address of the new allocation.
             self.grant_pointers.map_or(false, |grant_pointers| {
@@ -773,9 +894,12 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
                 grant_pointers
                     .get_mut(grant_num)
                     .map_or(false, |grant_entry| {
-                        // Actually set the driver num and grant pointer.
+                        // Actually set the driver num and grant pointer, and
+                        // record how much memory the allocation used.
                         grant_entry.driver_num = driver_num;
                         grant_entry.grant_ptr = grant_ptr.as_ptr() as *mut u8;
+                        grant_entry.size =
+                            kernel_memory_break as usize - grant_ptr.as_ptr() as usize;
 
                         // If all of this worked, return true.
                         true
@@ -829,6 +953,12 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
                         // Get a copy of the actual grant pointer.
                         let grant_ptr = grant_entry.grant_ptr;
 
+                        // A grant that was never allocated, or that has been
+                        // released, has no valid memory to enter.
+                        if !grant_entry.is_allocated() {
+                            return Err(Error::AddressOutOfBounds);
+                        }
+
                         // Check if the grant pointer is marked that the grant
                         // has already been entered. If so, return an error.
                         if (grant_ptr as usize) & 0x1 == 0x1 {
@@ -899,12 +1029,11 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
         }
 
         self.grant_pointers.map(|grant_pointers| {
-            // Filter our list of grant pointers into just the non null ones,
-            // and count those. A grant is allocated if its grant pointer is non
-            // null.
+            // Filter our list of grant pointers into just the allocated ones,
+            // and count those.
             grant_pointers
                 .iter()
-                .filter(|grant_entry| !grant_entry.grant_ptr.is_null())
+                .filter(|grant_entry| grant_entry.is_allocated())
                 .count()
         })
     }
@@ -912,12 +1041,10 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
     fn lookup_grant_from_driver_num(&self, driver_num: usize) -> Result<usize, Error> {
         self.grant_pointers
             .map_or(Err(Error::KernelError), |grant_pointers| {
-                // Filter our list of grant pointers into just the non null
-                // ones, and count those. A grant is allocated if its grant
-                // pointer is non null.
+                // Find the allocated grant with this driver number.
                 match grant_pointers.iter().position(|grant_entry| {
                     // Only consider allocated grants.
-                    (!grant_entry.grant_ptr.is_null()) && grant_entry.driver_num == driver_num
+                    grant_entry.is_allocated() && grant_entry.driver_num == driver_num
                 }) {
                     Some(idx) => Ok(idx),
                     None => Err(Error::OutOfMemory),
@@ -925,6 +1052,60 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
             })
     }
 
+    fn get_grant_usage(&self, grant_num: usize) -> Option<GrantUsage> {
+        self.grant_pointers.map_or(None, |grant_pointers| {
+            grant_pointers.get(grant_num).and_then(|grant_entry| {
+                if grant_entry.grant_ptr.is_null() {
+                    None
+                } else {
+                    Some(GrantUsage {
+                        driver_num: grant_entry.driver_num,
+                        size: grant_entry.size,
+                        released: grant_entry.is_released(),
+                    })
+                }
+            })
+        })
+    }
+
+    fn free_grant(&self, grant_num: usize) -> Result<(), Error> {
+        // Do not modify an inactive process.
+        if !self.is_active() {
+            return Err(Error::InactiveApp);
+        }
+
+        self.grant_pointers
+            .map_or(Err(Error::KernelError), |grant_pointers| {
+                let grant_entry = grant_pointers
+                    .get_mut(grant_num)
+                    .ok_or(Error::AddressOutOfBounds)?;
+                if !grant_entry.is_allocated() {
+                    // Nothing to release.
+                    return Ok(());
+                }
+                if (grant_entry.grant_ptr as usize) & 0x1 == 0x1 {
+                    // The grant is entered, so the capsule still has
+                    // references into its memory.
+                    return Err(Error::AlreadyInUse);
+                }
+                grant_entry.grant_ptr = (grant_entry.grant_ptr as usize | 0x2) as *mut u8;
+
+                // Return the memory of released grants at the bottom of the
+                // grant region to the free memory of the process. This may
+                // uncover grants that were released earlier.
+                while let Some(released) = grant_pointers.iter_mut().find(|grant_entry| {
+                    grant_entry.is_released()
+                        && (grant_entry.grant_ptr as usize & !0x3)
+                            == self.kernel_memory_break.get() as usize
+                }) {
+                    self.kernel_memory_break
+                        .set(self.kernel_memory_break.get().wrapping_add(released.size));
+                    released.clear();
+                }
+                Ok(())
+            })
+    }
+
     fn is_valid_upcall_function_pointer(&self, upcall_fn: NonNull<()>) -> bool {
         let ptr = upcall_fn.as_ptr() as *const u8;
         let size = mem::size_of::<*const u8>();
@@ -937,6 +1118,64 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
         self.process_name
     }
 
+    fn get_credentials_region(&self) -> &'static [u8] {
+        let binary_end = self.header.get_binary_end() as usize;
+        self.flash.get(0..binary_end).unwrap_or(self.flash)
+    }
+
+    fn get_footers(&self) -> &'static [u8] {
+        let binary_end = self.header.get_binary_end() as usize;
+        self.flash.get(binary_end..).unwrap_or(&[])
+    }
+
+    fn get_credentials(&self) -> Option<TbfFooterV2Credentials> {
+        self.credentials.extract()
+    }
+
+    fn short_app_id(&self) -> ShortID {
+        self.short_app_id.get()
+    }
+
+    fn mark_credentials_pass(
+        &self,
+        credentials: Option<TbfFooterV2Credentials>,
+        short_app_id: ShortID,
+        _capability: &dyn capabilities::ProcessApprovalCapability,
+    ) -> Result<(), ErrorCode> {
+        if self.state.get() != State::CredentialsUnchecked {
+            return Err(ErrorCode::INVAL);
+        }
+        credentials.map(|credentials| self.credentials.set(credentials));
+        self.short_app_id.set(short_app_id);
+
+        // Queue the initial function, which the process was loaded without.
+        let flash_protected_size = self.header.get_protected_size() as usize;
+        let init_fn = self.flash_start() as usize + self.header.get_init_function_offset() as usize;
+        let enqueued = self.tasks.map_or(false, |tasks| {
+            tasks.enqueue(Task::FunctionCall(FunctionCall {
+                source: FunctionCallSource::Kernel,
+                pc: init_fn,
+                argument0: self.flash_start() as usize + flash_protected_size,
+                argument1: self.mem_start() as usize,
+                argument2: self.memory_len,
+                argument3: self.app_break.get() as usize,
+            }))
+        });
+        if !enqueued {
+            return Err(ErrorCode::FAIL);
+        }
+
+        self.state.update(State::Unstarted);
+        self.kernel.increment_work();
+        Ok(())
+    }
+
+    fn mark_credentials_fail(&self, _capability: &dyn capabilities::ProcessApprovalCapability) {
+        if self.state.get() == State::CredentialsUnchecked {
+            self.state.update(State::CredentialsFailed);
+        }
+    }
+
     fn get_completion_code(&self) -> Option<Option<u32>> {
         self.completion_code.extract()
     }
@@ -1084,6 +1323,7 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
     fn debug_syscall_called(&self, last_syscall: Syscall) {
         self.debug.map(|debug| {
             debug.syscall_count += 1;
+            debug.syscall_class_counts[last_syscall.class() as usize] += 1;
             debug.last_syscall = Some(last_syscall);
         });
     }
@@ -1092,6 +1332,48 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
         self.debug.map_or(None, |debug| debug.last_syscall)
     }
 
+    fn debug_syscall_class_count(&self, class: SyscallClass) -> usize {
+        self.debug
+            .map_or(0, |debug| debug.syscall_class_counts[class as usize])
+    }
+
+    fn debug_upcall_count(&self) -> usize {
+        self.debug.map_or(0, |debug| debug.upcall_count)
+    }
+
+    fn debug_cpu_time_us(&self) -> u64 {
+        self.debug.map_or(0, |debug| debug.cpu_time_us)
+    }
+
+    fn charge_cpu_time(&self, time_us: u32) {
+        let cpu_time_us = self.debug.map_or(0, |debug| {
+            debug.cpu_time_us += time_us as u64;
+            debug.cpu_time_us
+        });
+        let over_budget = self
+            .cpu_budget_us
+            .get()
+            .map_or(false, |budget_us| cpu_time_us > budget_us);
+        if over_budget && self.is_active() {
+            if config::CONFIG.debug_panics {
+                debug!(
+                    "[{:?}] exceeded its CPU budget ({} us)",
+                    self.processid(),
+                    cpu_time_us
+                );
+            }
+            self.set_fault_state();
+        }
+    }
+
+    fn set_cpu_budget(&self, budget_us: Option<u64>) {
+        self.cpu_budget_us.set(budget_us);
+    }
+
+    fn get_cpu_budget(&self) -> Option<u64> {
+        self.cpu_budget_us.get()
+    }
+
     fn get_addresses(&self) -> ProcessAddresses {
         ProcessAddresses {
             flash_start: self.flash_start() as usize,
@@ -1110,6 +1392,11 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
             sram_stack_bottom: self.debug.map_or(None, |debug| {
                 debug.app_stack_min_pointer.map(|p| p as usize)
             }),
+            sram_stack_high_water: self.stack_high_water().map(|p| p as usize),
+            sram_app_brk_high_water: self
+                .debug
+                .map_or(self.app_break.get(), |debug| debug.app_break_max)
+                as usize,
         }
     }
 
@@ -1122,6 +1409,22 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
         }
     }
 
+    fn get_fault_context(&self) -> FaultContext {
+        self.stored_state
+            .map(|stored_state| {
+                // We guarantee the memory bounds pointers provided to the UKB
+                // are correct.
+                unsafe {
+                    self.chip.userspace_kernel_boundary().fault_context(
+                        self.mem_start(),
+                        self.app_break.get(),
+                        stored_state,
+                    )
+                }
+            })
+            .unwrap_or_default()
+    }
+
     fn print_full_process(&self, writer: &mut dyn Write) {
         if !config::CONFIG.debug_panics {
             return;
@@ -1162,7 +1465,7 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
                     // Implement `grant_pointers[grant_num]` without a chance of
                     // a panic.
                     grant_pointers.get(index).map(|grant_entry| {
-                        if grant_entry.grant_ptr.is_null() {
+                        if !grant_entry.is_allocated() {
                             let _ =
                                 writer.write_fmt(format_args!("  Grant {:>2} : --        ", index));
                         } else {
@@ -1178,9 +1481,7 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
         });
 
         // Display the current state of the MPU for this process.
-        self.mpu_config.map(|config| {
-            let _ = writer.write_fmt(format_args!("{}", config));
-        });
+        self.print_mpu_config(writer);
 
         // Print a helpful message on how to re-compile a process to view the
         // listing file. If a process is PIC, then we also need to print the
@@ -1214,6 +1515,12 @@ impl<C: Chip> Process for ProcessStandard<'_, C> {
         });
     }
 
+    fn print_mpu_config(&self, writer: &mut dyn Write) {
+        self.mpu_config.map(|config| {
+            let _ = writer.write_fmt(format_args!("{}", config));
+        });
+    }
+
     fn get_stored_state(&self, out: &mut [u8]) -> Result<usize, ErrorCode> {
         self.stored_state
             .map(|stored_state| {
@@ -1233,6 +1540,10 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
     // Memory offset to make room for this process's metadata.
     const PROCESS_STRUCT_OFFSET: usize = mem::size_of::<ProcessStandard<C>>();
 
+    // Value written to process memory when it is given to the process, so
+    // that the deepest point the stack has reached can be found later.
+    const MEMORY_PAINT: u8 = 0xA5;
+
     pub(crate) unsafe fn create<'a>(
         kernel: &'static Kernel,
         chip: &'static C,
@@ -1242,6 +1553,7 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
         remaining_memory: &'a mut [u8],
         fault_policy: &'static dyn ProcessFaultPolicy,
         require_kernel_version: bool,
+        check_credentials: bool,
         index: usize,
     ) -> Result<(Option<&'static dyn Process>, &'a mut [u8]), ProcessLoadError> {
         // Get a slice for just the app header.
@@ -1541,8 +1853,7 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
             grant_ptrs_num,
         );
         for grant_entry in grant_pointers.iter_mut() {
-            grant_entry.driver_num = 0;
-            grant_entry.grant_ptr = ptr::null_mut();
+            grant_entry.clear();
         }
 
         // Now that we know we have the space we can setup the memory for the
@@ -1601,6 +1912,7 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
         process.fault_policy = fault_policy;
         process.restart_count = Cell::new(0);
         process.completion_code = OptionalCell::empty();
+        process.cpu_budget_us = Cell::new(None);
 
         process.mpu_config = MapCell::new(mpu_config);
         process.mpu_regions = [
@@ -1613,6 +1925,8 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
         ];
         process.tasks = MapCell::new(tasks);
         process.process_name = process_name.unwrap_or("");
+        process.credentials = OptionalCell::empty();
+        process.short_app_id = Cell::new(ShortID::LocallyUnique);
 
         process.debug = MapCell::new(ProcessStandardDebug {
             fixed_address_flash: fixed_address_flash,
@@ -1620,25 +1934,35 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
             app_heap_start_pointer: None,
             app_stack_start_pointer: None,
             app_stack_min_pointer: None,
+            app_break_max: initial_app_brk,
             syscall_count: 0,
             last_syscall: None,
             dropped_upcall_count: 0,
             timeslice_expiration_count: 0,
+            syscall_class_counts: [0; 8],
+            upcall_count: 0,
+            cpu_time_us: 0,
         });
 
         let flash_protected_size = process.header.get_protected_size() as usize;
         let flash_app_start_addr = app_flash.as_ptr() as usize + flash_protected_size;
 
-        process.tasks.map(|tasks| {
-            tasks.enqueue(Task::FunctionCall(FunctionCall {
-                source: FunctionCallSource::Kernel,
-                pc: init_fn,
-                argument0: flash_app_start_addr,
-                argument1: process.memory_start as usize,
-                argument2: process.memory_len,
-                argument3: process.app_break.get() as usize,
-            }));
-        });
+        // A process whose credentials must be checked does not get its
+        // initial function until they are approved.
+        if check_credentials {
+            process.state.update(State::CredentialsUnchecked);
+        } else {
+            process.tasks.map(|tasks| {
+                tasks.enqueue(Task::FunctionCall(FunctionCall {
+                    source: FunctionCallSource::Kernel,
+                    pc: init_fn,
+                    argument0: flash_app_start_addr,
+                    argument1: process.memory_start as usize,
+                    argument2: process.memory_len,
+                    argument3: process.app_break.get() as usize,
+                }));
+            });
+        }
 
         // Handle any architecture-specific requirements for a new process.
         //
@@ -1669,7 +1993,9 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
             }
         };
 
-        kernel.increment_work();
+        if !check_credentials {
+            kernel.increment_work();
+        }
 
         // Return the process object and a remaining memory for processes slice.
         Ok((Some(process), unused_memory))
@@ -1696,6 +2022,9 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
             debug.last_syscall = None;
             debug.dropped_upcall_count = 0;
             debug.timeslice_expiration_count = 0;
+            debug.syscall_class_counts = [0; 8];
+            debug.upcall_count = 0;
+            debug.cpu_time_us = 0;
         });
 
         // FLASH
@@ -1785,11 +2114,21 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
         // process's memory region.
         self.allow_high_water_mark.set(app_mpu_mem_start);
 
-        // Drop the old config and use the clean one
+        // Drop the old config and use the clean one, along with the regions
+        // that were added to it.
         self.mpu_config.replace(mpu_config);
+        for region in self.mpu_regions.iter() {
+            region.set(None);
+        }
 
         // Handle any architecture-specific requirements for a process when it
         // first starts (as it would when it is new).
+        // Reset the memory high-water marks for the new execution.
+        self.debug.map(|debug| {
+            debug.app_stack_min_pointer = debug.app_stack_start_pointer;
+            debug.app_break_max = app_brk;
+        });
+
         let ukb_init_process = self.stored_state.map_or(Err(()), |stored_state| unsafe {
             self.chip.userspace_kernel_boundary().initialize_process(
                 app_mpu_mem_start,
@@ -1866,8 +2205,7 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
     unsafe fn grant_ptrs_reset(&self) {
         self.grant_pointers.map(|grant_pointers| {
             for grant_entry in grant_pointers.iter_mut() {
-                grant_entry.driver_num = 0;
-                grant_entry.grant_ptr = ptr::null_mut();
+                grant_entry.clear();
             }
         });
     }
@@ -1887,12 +2225,12 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
             // allocation or that it meets alignment requirements.
             let new_break_unaligned = self.kernel_memory_break.get().wrapping_sub(size);
 
-            // Our minimum alignment requirement is two bytes, so that the
-            // lowest bit of the address will always be zero and we can use it
-            // as a flag. It doesn't hurt to increase the alignment (except for
-            // potentially a wasted byte) so we make sure `align` is at least
-            // two.
-            let align = cmp::max(align, 2);
+            // Our minimum alignment requirement is four bytes, so that the
+            // lowest two bits of the address will always be zero and we can
+            // use them as flags. It doesn't hurt to increase the alignment
+            // (except for potentially a few wasted bytes) so we make sure
+            // `align` is at least four.
+            let align = cmp::max(align, 4);
 
             // The alignment must be a power of two, 2^a. The expression
             // `!(align - 1)` then returns a mask with leading ones, followed by
@@ -1972,7 +2310,63 @@ impl<C: 'static + Chip> ProcessStandard<'_, C> {
     /// explicitly exits.
     fn is_active(&self) -> bool {
         let current_state = self.state.get();
-        current_state != State::Terminated && current_state != State::Faulted
+        current_state != State::Terminated
+            && current_state != State::Faulted
+            && current_state != State::CredentialsUnchecked
+            && current_state != State::CredentialsFailed
+    }
+
+    /// Whether the process was allowed to run, either because its credentials
+    /// were approved or because it was loaded without checking them.
+    fn credentials_approved(&self) -> bool {
+        let current_state = self.state.get();
+        current_state != State::CredentialsUnchecked && current_state != State::CredentialsFailed
+    }
+
+    /// Fill process memory from `start` up to `end` with `MEMORY_PAINT`.
+    fn paint_memory(start: *const u8, end: *const u8) {
+        let len = (end as usize).saturating_sub(start as usize);
+        // The range is process memory that was just made accessible to the
+        // process (or the process is being initialized), so nothing in the
+        // kernel holds a reference to it.
+        unsafe {
+            ptr::write_bytes(start as *mut u8, Self::MEMORY_PAINT, len);
+        }
+    }
+
+    /// The lowest address the process's stack has reached, if the process
+    /// told the kernel where its stack starts.
+    ///
+    /// This assumes the stack grows down towards the start of process memory,
+    /// as it does for processes built with libtock-c and libtock-rs. Process
+    /// memory is painted when `brk` gives it to the process, so the first
+    /// address that no longer holds the paint is the deepest point the stack
+    /// has reached. The initial memory the process starts with is never
+    /// painted, since the kernel places the first context switch frame there,
+    /// and is skipped. The lowest stack pointer seen at a context switch is
+    /// used if it is lower.
+    fn stack_high_water(&self) -> Option<*const u8> {
+        let (stack_top, stack_min) = self.debug.map_or((None, None), |debug| {
+            (debug.app_stack_start_pointer, debug.app_stack_min_pointer)
+        });
+        let stack_top = cmp::min(stack_top?, self.app_break.get());
+        let scan_start = self.mem_start().wrapping_add(
+            self.chip
+                .userspace_kernel_boundary()
+                .initial_process_app_brk_size(),
+        );
+
+        let len = (stack_top as usize).saturating_sub(scan_start as usize);
+        // The range is within process-accessible memory, which the kernel can
+        // read. The process is not running while the kernel is.
+        let stack = unsafe { slice::from_raw_parts(scan_start, len) };
+        let painted = stack
+            .iter()
+            .position(|byte| *byte != Self::MEMORY_PAINT)
+            .unwrap_or(len);
+        let painted_high_water = scan_start.wrapping_add(painted);
+
+        Some(stack_min.map_or(painted_high_water, |min| cmp::min(min, painted_high_water)))
     }
 
     /// The start address of allocated RAM for this process.
@@ -9,6 +9,7 @@ use crate::debug;
 use crate::kernel::Kernel;
 use crate::platform::chip::Chip;
 use crate::process::Process;
+use crate::process_checker::ProcessCheckerMachine;
 use crate::process_policies::ProcessFaultPolicy;
 use crate::process_standard::ProcessStandard;
 
@@ -162,6 +163,36 @@ pub fn load_processes_advanced<C: Chip>(
     require_kernel_version: bool,
     _capability: &dyn ProcessManagementCapability,
 ) -> Result<(), ProcessLoadError> {
+    load_processes_from_flash(
+        kernel,
+        chip,
+        app_flash,
+        app_memory,
+        procs,
+        fault_policy,
+        require_kernel_version,
+        false,
+    )
+    .map(|_| ())
+}
+
+/// Discovers processes in flash and creates them. If `check_credentials` is
+/// set, processes are created in the `CredentialsUnchecked` state and do not
+/// run until their credentials have been approved.
+///
+/// On success, returns the flash after the last app and the memory that was
+/// not assigned to any process.
+#[inline(always)]
+pub(crate) fn load_processes_from_flash<'a, C: Chip>(
+    kernel: &'static Kernel,
+    chip: &'static C,
+    app_flash: &'static [u8],
+    app_memory: &'a mut [u8],
+    procs: &mut [Option<&'static dyn Process>],
+    fault_policy: &'static dyn ProcessFaultPolicy,
+    require_kernel_version: bool,
+    check_credentials: bool,
+) -> Result<(&'static [u8], &'a mut [u8]), ProcessLoadError> {
     if config::CONFIG.debug_load_processes {
         debug!(
             "Loading processes from flash={:#010X}-{:#010X} into sram={:#010X}-{:#010X}",
@@ -186,7 +217,7 @@ pub fn load_processes_advanced<C: Chip>(
                 // Not enough flash to test for another app. This just means
                 // we are at the end of flash, and there are no more apps to
                 // load.
-                return Ok(());
+                return Ok((remaining_flash, remaining_memory));
             }
         };
 
@@ -209,7 +240,7 @@ pub fn load_processes_advanced<C: Chip>(
                 // header we started to parse is intentionally invalid to signal
                 // the end of apps. This is ok and just means we have finished
                 // loading apps.
-                return Ok(());
+                return Ok((remaining_flash, remaining_memory));
             }
         };
 
@@ -248,6 +279,7 @@ pub fn load_processes_advanced<C: Chip>(
                     remaining_memory,
                     fault_policy,
                     require_kernel_version,
+                    check_credentials,
                     index,
                 )?
             };
@@ -280,7 +312,7 @@ pub fn load_processes_advanced<C: Chip>(
         };
     }
 
-    Ok(())
+    Ok((remaining_flash, remaining_memory))
 }
 
 /// This is a wrapper function for `load_processes_advanced` that uses
@@ -309,3 +341,36 @@ pub fn load_processes<C: Chip>(
         capability,
     )
 }
+
+/// Loads processes like `load_processes`, but does not run them until their
+/// credentials have been checked by `checker`. Processes whose credentials
+/// are rejected by the checker's policy never run.
+///
+/// Checking the credentials is asynchronous: processes are started as they
+/// are approved, once the kernel loop is running.
+#[inline(always)]
+pub fn load_and_check_processes<C: Chip>(
+    kernel: &'static Kernel,
+    chip: &'static C,
+    app_flash: &'static [u8],
+    app_memory: &mut [u8], // not static, so that process.rs cannot hold on to slice w/o unsafe
+    procs: &'static mut [Option<&'static dyn Process>],
+    fault_policy: &'static dyn ProcessFaultPolicy,
+    checker: &'static ProcessCheckerMachine,
+    _capability: &dyn ProcessManagementCapability,
+) -> Result<(), ProcessLoadError> {
+    let result = load_processes_from_flash(
+        kernel,
+        chip,
+        app_flash,
+        app_memory,
+        procs,
+        fault_policy,
+        true,
+        true,
+    )
+    .map(|_| ());
+    // Check the processes that were loaded, even if loading stopped early.
+    checker.start();
+    result
+}
@@ -1,11 +1,13 @@
 //! Interface for Tock kernel schedulers.
 
 pub mod cooperative;
+pub mod edf;
 pub mod mlfq;
 pub mod priority;
+pub mod rate_monotonic;
 pub mod round_robin;
 
-use crate::dynamic_deferred_call::DynamicDeferredCall;
+use crate::deferred_call::DeferredCall;
 use crate::kernel::StoppedExecutingReason;
 use crate::platform::chip::Chip;
 use crate::process::ProcessId;
@@ -32,7 +34,7 @@ pub trait Scheduler<C: Chip> {
     fn result(&self, result: StoppedExecutingReason, execution_time_us: Option<u32>);
 
     /// Tell the scheduler to execute kernel work such as interrupt bottom
-    /// halves and dynamic deferred calls. Most schedulers will use this default
+    /// halves and deferred calls. Most schedulers will use this default
     /// implementation, but schedulers which at times wish to defer interrupt
     /// handling will reimplement it.
     ///
@@ -47,7 +49,7 @@ pub trait Scheduler<C: Chip> {
     /// as this function is called in the core kernel loop.
     unsafe fn execute_kernel_work(&self, chip: &C) {
         chip.service_pending_interrupts();
-        DynamicDeferredCall::call_global_instance_while(|| !chip.has_pending_interrupts());
+        DeferredCall::service_pending_while(|| !chip.has_pending_interrupts());
     }
 
     /// Ask the scheduler whether to take a break from executing userspace
@@ -55,8 +57,7 @@ pub trait Scheduler<C: Chip> {
     /// implementation, which always prioritizes kernel work, but schedulers
     /// that wish to defer interrupt handling may reimplement it.
     unsafe fn do_kernel_work_now(&self, chip: &C) -> bool {
-        chip.has_pending_interrupts()
-            || DynamicDeferredCall::global_instance_calls_pending().unwrap_or(false)
+        chip.has_pending_interrupts() || DeferredCall::has_tasks()
     }
 
     /// Ask the scheduler whether to continue trying to execute a process.
@@ -76,8 +77,7 @@ pub trait Scheduler<C: Chip> {
     ///
     /// `id` is the identifier of the currently active process.
     unsafe fn continue_process(&self, _id: ProcessId, chip: &C) -> bool {
-        !(chip.has_pending_interrupts()
-            || DynamicDeferredCall::global_instance_calls_pending().unwrap_or(false))
+        !(chip.has_pending_interrupts() || DeferredCall::has_tasks())
     }
 }
 
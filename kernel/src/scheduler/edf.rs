@@ -0,0 +1,211 @@
+//! Earliest Deadline First Scheduler for Tock
+//!
+//! This scheduler runs the ready process whose current job has the earliest
+//! absolute deadline. Processes declare the period and relative deadline of
+//! their jobs in the timing constraints TBF header. A job is released when a
+//! process becomes ready after it ran out of work, and its absolute deadline
+//! is the release time plus the relative deadline. The job completes when the
+//! process yields with no work left.
+//!
+//! Processes without timing constraints have no deadline. They only run when
+//! no process with a deadline is ready, in the order of the `PROCESSES` array.
+//! Kernel tasks (bottom half interrupt handling / deferred call handling)
+//! always take priority over userspace processes.
+//!
+//! Like the fixed priority scheduler, processes run without timeslices, and
+//! the running process is preempted as soon as a process with an earlier
+//! deadline becomes ready. Jobs that complete after their deadline are counted
+//! as deadline misses.
+//!
+//! The scheduler measures time with an alarm, which it reads at least every
+//! time it schedules a process. The alarm must not wrap around while the
+//! kernel sleeps.
+
+use core::cell::Cell;
+
+use crate::deferred_call::DeferredCall;
+use crate::hil::time::{self, ConvertTicks, Ticks};
+use crate::kernel::{Kernel, StoppedExecutingReason};
+use crate::platform::chip::Chip;
+use crate::process::{Process, ProcessId};
+use crate::scheduler::{Scheduler, SchedulingDecision};
+use crate::utilities::cells::OptionalCell;
+
+/// Per-process state of the current job.
+#[derive(Default)]
+pub struct EDFProcessState {
+    /// Whether the process has a job that has not completed.
+    job_active: Cell<bool>,
+    /// Absolute deadline of the current job, in ticks since the scheduler
+    /// started.
+    deadline: Cell<u64>,
+    /// Number of jobs that completed after their deadline.
+    deadline_misses: Cell<usize>,
+}
+
+impl EDFProcessState {
+    pub const fn new() -> EDFProcessState {
+        EDFProcessState {
+            job_active: Cell::new(false),
+            deadline: Cell::new(0),
+            deadline_misses: Cell::new(0),
+        }
+    }
+}
+
+/// Earliest deadline first scheduler.
+pub struct EDFSched<'a, A: 'static + time::Alarm<'static>> {
+    kernel: &'static Kernel,
+    alarm: &'static A,
+    /// Job state of each process, indexed like the `PROCESSES` array.
+    states: &'a [EDFProcessState],
+    running: OptionalCell<ProcessId>,
+    /// Time since the scheduler started, which does not wrap around.
+    time: Cell<u64>,
+    last_now: Cell<A::Ticks>,
+}
+
+impl<'a, A: 'static + time::Alarm<'static>> EDFSched<'a, A> {
+    pub fn new(
+        kernel: &'static Kernel,
+        alarm: &'static A,
+        states: &'a [EDFProcessState],
+    ) -> EDFSched<'a, A> {
+        EDFSched {
+            kernel,
+            alarm,
+            states,
+            running: OptionalCell::empty(),
+            time: Cell::new(0),
+            last_now: Cell::new(alarm.now()),
+        }
+    }
+
+    /// Returns how many jobs of the process completed after their deadline.
+    pub fn deadline_misses(&self, process_id: ProcessId) -> usize {
+        self.states
+            .get(process_id.index)
+            .map_or(0, |state| state.deadline_misses.get())
+    }
+
+    /// Advances and returns the scheduler's time.
+    fn now(&self) -> u64 {
+        let now = self.alarm.now();
+        let elapsed = now.wrapping_sub(self.last_now.get());
+        self.last_now.set(now);
+        self.time.set(self.time.get() + elapsed.into_u32() as u64);
+        self.time.get()
+    }
+
+    /// Returns the absolute deadline of the current job of a ready process,
+    /// or `None` if the process has no deadline. Releases a new job if the
+    /// process has no active job and `release` is set.
+    fn deadline(&self, process: &dyn Process, now: u64, release: bool) -> Option<u64> {
+        let (_, relative_deadline_us) = process.get_timing_constraints()?;
+        let state = self.states.get(process.processid().index)?;
+        if state.job_active.get() {
+            Some(state.deadline.get())
+        } else {
+            let deadline = now + self.alarm.ticks_from_us(relative_deadline_us).into_u32() as u64;
+            if release {
+                state.job_active.set(true);
+                state.deadline.set(deadline);
+            }
+            Some(deadline)
+        }
+    }
+
+    /// Returns the ready process with the earliest deadline, preferring
+    /// processes with a deadline and, among equal deadlines, the one earliest
+    /// in the `PROCESSES` array.
+    fn earliest(&self, now: u64, release: bool) -> Option<(ProcessId, Option<u64>)> {
+        let mut earliest: Option<(ProcessId, Option<u64>)> = None;
+        for process in self.kernel.get_process_iter().filter(|proc| proc.ready()) {
+            let deadline = self.deadline(process, now, release);
+            let earlier = match (earliest, deadline) {
+                (None, _) => true,
+                (Some((_, None)), Some(_)) => true,
+                (Some((_, Some(current))), Some(deadline)) => deadline < current,
+                _ => false,
+            };
+            if earlier {
+                earliest = Some((process.processid(), deadline));
+            }
+        }
+        earliest
+    }
+}
+
+impl<'a, A: 'static + time::Alarm<'static>, C: Chip> Scheduler<C> for EDFSched<'a, A> {
+    fn next(&self, kernel: &Kernel) -> SchedulingDecision {
+        if kernel.processes_blocked() {
+            // No processes ready
+            SchedulingDecision::TrySleep
+        } else {
+            // Release the jobs of all processes that became ready, so that
+            // their deadlines count from when they became ready rather than
+            // from when they are first scheduled.
+            let now = self.now();
+            match self.earliest(now, true) {
+                Some((next, _)) => {
+                    self.running.set(next);
+                    SchedulingDecision::RunProcess((next, None))
+                }
+                None => SchedulingDecision::TrySleep,
+            }
+        }
+    }
+
+    unsafe fn continue_process(&self, id: ProcessId, chip: &C) -> bool {
+        // In addition to checking for interrupts, also checks if a process
+        // with an earlier deadline has become ready, for example because the
+        // running process sent it an IPC message.
+        if chip.has_pending_interrupts() || DeferredCall::has_tasks() {
+            return false;
+        }
+
+        // A process that ran out of work stops by itself, which completes its
+        // job.
+        let running = match self
+            .kernel
+            .get_process_iter()
+            .find(|proc| proc.processid() == id && proc.ready())
+        {
+            Some(running) => running,
+            None => return true,
+        };
+
+        let now = self.now();
+        let running_deadline = self.deadline(running, now, false);
+        match self.earliest(now, false) {
+            Some((earliest, deadline)) if earliest != id => match (deadline, running_deadline) {
+                (Some(_), None) => false,
+                (Some(deadline), Some(running)) => deadline >= running,
+                _ => true,
+            },
+            _ => true,
+        }
+    }
+
+    fn result(&self, result: StoppedExecutingReason, _: Option<u32>) {
+        let now = self.now();
+        self.running.take().map(|running| {
+            self.states.get(running.index).map(|state| {
+                match result {
+                    StoppedExecutingReason::NoWorkLeft => {
+                        // The job is complete.
+                        if state.job_active.get() && now > state.deadline.get() {
+                            state.deadline_misses.set(state.deadline_misses.get() + 1);
+                        }
+                        state.job_active.set(false);
+                    }
+                    StoppedExecutingReason::StoppedFaulted | StoppedExecutingReason::Stopped => {
+                        state.job_active.set(false);
+                    }
+                    StoppedExecutingReason::TimesliceExpired
+                    | StoppedExecutingReason::KernelPreemption => {}
+                }
+            });
+        });
+    }
+}
@@ -0,0 +1,79 @@
+//! Rate Monotonic Scheduler for Tock
+//!
+//! This scheduler assigns fixed priorities to processes based on the period
+//! in their timing constraints TBF header: the shorter the period, the higher
+//! the priority. It runs the highest priority process available at any point
+//! in time. Processes without timing constraints have the lowest priority.
+//! Ties are broken by the order of the processes in the `PROCESSES` array.
+//! Kernel tasks (bottom half interrupt handling / deferred call handling)
+//! always take priority over userspace processes.
+//!
+//! As with the fixed priority scheduler, there is no need to enforce
+//! timeslices. The running process is preempted as soon as a higher priority
+//! process becomes ready.
+
+use crate::deferred_call::DeferredCall;
+use crate::kernel::{Kernel, StoppedExecutingReason};
+use crate::platform::chip::Chip;
+use crate::process::{Process, ProcessId};
+use crate::scheduler::{Scheduler, SchedulingDecision};
+
+/// Rate monotonic scheduler based on the periods of processes.
+pub struct RateMonotonicSched {
+    kernel: &'static Kernel,
+}
+
+impl RateMonotonicSched {
+    pub const fn new(kernel: &'static Kernel) -> Self {
+        Self { kernel }
+    }
+
+    /// Returns the priority of a process, where lower values are higher
+    /// priorities.
+    fn priority(process: &dyn Process) -> (u32, usize) {
+        let period = process
+            .get_timing_constraints()
+            .map_or(u32::MAX, |(period, _)| period);
+        (period, process.processid().index)
+    }
+
+    /// Returns the highest priority process that is ready to run.
+    fn highest_priority_ready(&self) -> Option<&dyn Process> {
+        self.kernel
+            .get_process_iter()
+            .filter(|proc| proc.ready())
+            .min_by_key(|&proc| Self::priority(proc))
+    }
+}
+
+impl<C: Chip> Scheduler<C> for RateMonotonicSched {
+    fn next(&self, kernel: &Kernel) -> SchedulingDecision {
+        if kernel.processes_blocked() {
+            // No processes ready
+            SchedulingDecision::TrySleep
+        } else {
+            match self.highest_priority_ready() {
+                Some(proc) => SchedulingDecision::RunProcess((proc.processid(), None)),
+                None => SchedulingDecision::TrySleep,
+            }
+        }
+    }
+
+    unsafe fn continue_process(&self, id: ProcessId, chip: &C) -> bool {
+        // In addition to checking for interrupts, also checks if any higher
+        // priority processes have become ready, for example because this
+        // process sent a higher priority process an IPC message.
+        !(chip.has_pending_interrupts()
+            || DeferredCall::has_tasks()
+            || self.highest_priority_ready().map_or(false, |ready_proc| {
+                self.kernel
+                    .get_process_iter()
+                    .find(|proc| proc.processid() == id)
+                    .map_or(false, |running| {
+                        Self::priority(ready_proc) < Self::priority(running)
+                    })
+            }))
+    }
+
+    fn result(&self, _: StoppedExecutingReason, _: Option<u32>) {}
+}
@@ -0,0 +1,488 @@
+//! Kernel-managed memory regions shared between processes.
+//!
+//! The board gives the kernel a pool of memory that is not part of any
+//! process, and the pool is divided into equally sized blocks. A process
+//! creates a shared region by allocating a block, which is mapped read-write
+//! into its MPU configuration. The owner can then grant other processes
+//! read-only or read-write access to the region, and those processes map it
+//! into their own MPU configuration. This lets processes exchange large
+//! amounts of data without the kernel copying it.
+//!
+//! Regions are revoked from every process when the owner destroys them or
+//! when the owner exits, faults or is restarted. Processes that end are
+//! removed from the regions shared with them. The kernel tells the driver
+//! when a process terminates, so the board must register the driver with
+//! `Kernel::set_process_termination_client()`. A block is only reused after
+//! it has been removed from the MPU configuration of every process.
+//!
+//! Most MPUs require regions to be aligned to their size, so the pool should
+//! be aligned to the block size and the block size should be a power of two.
+//! Regions that the MPU cannot map exactly within their block are rejected.
+//!
+//! Usage
+//! -----
+//!
+//! ```rust,ignore
+//! #[repr(align(1024))]
+//! struct SharedMemoryPool([u8; 0x1000]);
+//! static mut SHARED_MEMORY_POOL: SharedMemoryPool = SharedMemoryPool([0; 0x1000]);
+//!
+//! let shared_memory = static_init!(
+//!     kernel::shared_memory::SharedMemory<4>,
+//!     kernel::shared_memory::SharedMemory::new(board_kernel, &mut SHARED_MEMORY_POOL.0)
+//! );
+//! board_kernel.set_process_termination_client(shared_memory);
+//! ```
+
+use core::cell::Cell;
+
+use crate::kernel::Kernel;
+use crate::platform::mpu;
+use crate::process::{self, Process, ProcessId, ProcessTerminationClient, State};
+use crate::syscall_driver::{CommandReturn, SyscallDriver};
+use crate::utilities::cells::TakeCell;
+use crate::ErrorCode;
+
+/// Syscall number
+pub const DRIVER_NUM: usize = 0x10003;
+
+/// The number of processes, including the owner, that can have access to a
+/// shared region.
+pub const MAX_SHARERS: usize = 4;
+
+/// Access to a shared region that was granted to a process.
+#[derive(Copy, Clone)]
+struct Share {
+    process: ProcessId,
+    writable: bool,
+    /// The MPU region of the process, if it has mapped the shared region.
+    region: Option<mpu::Region>,
+}
+
+/// A block of the pool. The first share is the owner of the block.
+struct Block {
+    length: Cell<usize>,
+    shares: [Cell<Option<Share>>; MAX_SHARERS],
+}
+
+impl Block {
+    const NO_SHARE: Cell<Option<Share>> = Cell::new(None);
+    const EMPTY: Block = Block {
+        length: Cell::new(0),
+        shares: [Self::NO_SHARE; MAX_SHARERS],
+    };
+
+    fn owner(&self) -> Option<ProcessId> {
+        self.shares[0].get().map(|share| share.process)
+    }
+
+    fn find_share(&self, processid: ProcessId) -> Option<&Cell<Option<Share>>> {
+        self.shares
+            .iter()
+            .find(|share| share.get().map_or(false, |s| s.process == processid))
+    }
+}
+
+/// The shared memory driver, which manages a pool divided into `NUM_BLOCKS`
+/// blocks.
+pub struct SharedMemory<const NUM_BLOCKS: usize> {
+    kernel: &'static Kernel,
+    pool: TakeCell<'static, [u8]>,
+    pool_start: *const u8,
+    block_size: usize,
+    blocks: [Block; NUM_BLOCKS],
+}
+
+impl<const NUM_BLOCKS: usize> SharedMemory<NUM_BLOCKS> {
+    pub fn new(kernel: &'static Kernel, pool: &'static mut [u8]) -> Self {
+        Self {
+            kernel,
+            pool_start: pool.as_ptr(),
+            block_size: pool.len() / NUM_BLOCKS,
+            pool: TakeCell::new(pool),
+            blocks: [Block::EMPTY; NUM_BLOCKS],
+        }
+    }
+
+    fn block_start(&self, index: usize) -> *const u8 {
+        self.pool_start.wrapping_add(index * self.block_size)
+    }
+
+    /// Whether the process can still run. Processes that exited or faulted
+    /// get a new `ProcessId` when they are restarted.
+    fn is_alive(&self, processid: ProcessId) -> bool {
+        self.kernel.process_map_or(false, processid, |process| {
+            !matches!(process.get_state(), State::Faulted | State::Terminated)
+        })
+    }
+
+    /// Find the process with the given index.
+    fn find_process(&self, index: usize) -> Option<ProcessId> {
+        self.kernel.process_until(|p| match p.processid().index() {
+            Some(i) if i == index => Some(p.processid()),
+            _ => None,
+        })
+    }
+
+    /// Map block `index` into the MPU configuration of `process`. The region
+    /// must lie within the block so that the process cannot access any other
+    /// memory.
+    fn map(
+        &self,
+        index: usize,
+        process: &dyn Process,
+        writable: bool,
+    ) -> Result<mpu::Region, ErrorCode> {
+        let permissions = if writable {
+            mpu::Permissions::ReadWriteOnly
+        } else {
+            mpu::Permissions::ReadOnly
+        };
+        let start = self.block_start(index);
+        let length = self.blocks[index].length.get();
+        let region = process
+            .add_mpu_region(start, self.block_size, length, permissions)
+            .ok_or(ErrorCode::NOMEM)?;
+
+        let region_start = region.start_address() as usize;
+        let region_end = region_start + region.size();
+        if region_start < start as usize
+            || region_end > start as usize + self.block_size
+            || region.size() < length
+        {
+            let _ = process.remove_mpu_region(region);
+            return Err(ErrorCode::NOMEM);
+        }
+        Ok(region)
+    }
+
+    /// Remove a share from the MPU configuration of its process, if it is
+    /// mapped and the process can still run.
+    fn unmap(&self, share: Share) {
+        if let Some(region) = share.region {
+            if self.is_alive(share.process) {
+                self.kernel.process_map_or((), share.process, |process| {
+                    let _ = process.remove_mpu_region(region);
+                });
+            }
+        }
+    }
+
+    /// Revoke block `index` from every process and free it.
+    fn release(&self, index: usize) {
+        for share in self.blocks[index].shares.iter() {
+            if let Some(s) = share.take() {
+                self.unmap(s);
+            }
+        }
+        self.blocks[index].length.set(0);
+    }
+
+    /// The block `id` if it is owned by `processid`.
+    fn owned_block(&self, id: usize, processid: ProcessId) -> Result<&Block, ErrorCode> {
+        self.blocks
+            .get(id)
+            .filter(|block| block.owner() == Some(processid))
+            .ok_or(ErrorCode::INVAL)
+    }
+
+    /// Allocate a block of at least `length` bytes owned by `owner`, without
+    /// mapping it. Returns the index of the block.
+    fn allocate(&self, owner: ProcessId, length: usize) -> Result<usize, ErrorCode> {
+        if length == 0 || length > self.block_size {
+            return Err(ErrorCode::SIZE);
+        }
+        let index = self
+            .blocks
+            .iter()
+            .position(|block| block.owner().is_none())
+            .ok_or(ErrorCode::NOMEM)?;
+
+        // Do not leak data from the previous user of the block.
+        let start = index * self.block_size;
+        self.pool
+            .map(|pool| pool[start..start + self.block_size].fill(0));
+
+        self.blocks[index].length.set(length);
+        self.blocks[index].shares[0].set(Some(Share {
+            process: owner,
+            writable: true,
+            region: None,
+        }));
+        Ok(index)
+    }
+
+    /// Allocate a block of at least `length` bytes and map it into the
+    /// process. Returns the id and address of the region.
+    fn create(&self, processid: ProcessId, length: usize) -> Result<(usize, usize), ErrorCode> {
+        let index = self.allocate(processid, length)?;
+        let region = self
+            .kernel
+            .process_map_or(Err(ErrorCode::INVAL), processid, |process| {
+                self.map(index, process, true)
+            });
+        match region {
+            Ok(region) => {
+                self.blocks[index].shares[0].set(Some(Share {
+                    process: processid,
+                    writable: true,
+                    region: Some(region),
+                }));
+                Ok((index, region.start_address() as usize))
+            }
+            Err(e) => {
+                self.release(index);
+                Err(e)
+            }
+        }
+    }
+
+    /// Grant the process with index `target` access to the region `id`.
+    fn share(
+        &self,
+        processid: ProcessId,
+        id: usize,
+        target: usize,
+        writable: bool,
+    ) -> Result<(), ErrorCode> {
+        let block = self.owned_block(id, processid)?;
+        let target = self
+            .find_process(target)
+            .filter(|&target| target != processid && self.is_alive(target))
+            .ok_or(ErrorCode::INVAL)?;
+        self.add_share(block, target, writable)
+    }
+
+    /// Record that `block` is shared with `target`, which has not mapped it
+    /// yet.
+    fn add_share(&self, block: &Block, target: ProcessId, writable: bool) -> Result<(), ErrorCode> {
+        if block.find_share(target).is_some() {
+            return Err(ErrorCode::ALREADY);
+        }
+        let slot = block
+            .shares
+            .iter()
+            .find(|share| share.get().is_none())
+            .ok_or(ErrorCode::NOMEM)?;
+        slot.set(Some(Share {
+            process: target,
+            writable,
+            region: None,
+        }));
+        Ok(())
+    }
+
+    /// Map the region `id` into a process it was shared with. Returns the
+    /// address and length of the region.
+    fn map_shared(&self, processid: ProcessId, id: usize) -> Result<(usize, usize), ErrorCode> {
+        let block = self.blocks.get(id).ok_or(ErrorCode::INVAL)?;
+        let slot = block.find_share(processid).ok_or(ErrorCode::INVAL)?;
+        let mut share = slot.get().ok_or(ErrorCode::INVAL)?;
+        let region = match share.region {
+            Some(region) => region,
+            None => {
+                let region =
+                    self.kernel
+                        .process_map_or(Err(ErrorCode::INVAL), processid, |process| {
+                            self.map(id, process, share.writable)
+                        })?;
+                share.region = Some(region);
+                slot.set(Some(share));
+                region
+            }
+        };
+        Ok((region.start_address() as usize, block.length.get()))
+    }
+
+    /// Remove the region `id` from a process it was shared with. The process
+    /// can map it again later.
+    fn unmap_shared(&self, processid: ProcessId, id: usize) -> Result<(), ErrorCode> {
+        let block = self.blocks.get(id).ok_or(ErrorCode::INVAL)?;
+        if block.owner() == Some(processid) {
+            return Err(ErrorCode::INVAL);
+        }
+        let slot = block.find_share(processid).ok_or(ErrorCode::INVAL)?;
+        let mut share = slot.get().ok_or(ErrorCode::INVAL)?;
+        if share.region.is_none() {
+            return Err(ErrorCode::ALREADY);
+        }
+        self.unmap(share);
+        share.region = None;
+        slot.set(Some(share));
+        Ok(())
+    }
+}
+
+impl<const NUM_BLOCKS: usize> SyscallDriver for SharedMemory<NUM_BLOCKS> {
+    /// Creates, shares and maps shared memory regions.
+    ///
+    /// Processes are identified by descriptors, which are the same as those
+    /// used by IPC.
+    ///
+    /// ### `command_num`
+    ///
+    /// - `0`: Driver check, always returns Ok(())
+    /// - `1`: Create a region of `data1` bytes and map it read-write into this
+    ///        process. Returns the id and the address of the region.
+    /// - `2`: Grant the process with descriptor `data2` read-only access to
+    ///        the region with id `data1`. Only the owner can share a region.
+    /// - `3`: Grant the process with descriptor `data2` read-write access to
+    ///        the region with id `data1`.
+    /// - `4`: Map the region with id `data1`, which was shared with this
+    ///        process. Returns the address and the length of the region.
+    /// - `5`: Unmap the region with id `data1`, which was shared with this
+    ///        process.
+    /// - `6`: Destroy the region with id `data1` and revoke it from every
+    ///        process. Only the owner can destroy a region.
+    fn command(
+        &self,
+        command_number: usize,
+        data1: usize,
+        data2: usize,
+        appid: ProcessId,
+    ) -> CommandReturn {
+        match command_number {
+            0 => CommandReturn::success(),
+            1 => match self.create(appid, data1) {
+                Ok((id, address)) => CommandReturn::success_u32_u32(id as u32, address as u32),
+                Err(e) => CommandReturn::failure(e),
+            },
+            2 | 3 => self.share(appid, data1, data2, command_number == 3).into(),
+            4 => match self.map_shared(appid, data1) {
+                Ok((address, length)) => {
+                    CommandReturn::success_u32_u32(address as u32, length as u32)
+                }
+                Err(e) => CommandReturn::failure(e),
+            },
+            5 => self.unmap_shared(appid, data1).into(),
+            6 => self
+                .owned_block(data1, appid)
+                .map(|_| self.release(data1))
+                .into(),
+            _ => CommandReturn::failure(ErrorCode::NOSUPPORT),
+        }
+    }
+
+    fn allocate_grant(&self, _processid: ProcessId) -> Result<(), process::Error> {
+        Ok(())
+    }
+}
+
+impl<const NUM_BLOCKS: usize> ProcessTerminationClient for SharedMemory<NUM_BLOCKS> {
+    /// Free the blocks owned by the process, and remove it from the blocks
+    /// shared with it. The process will not run again with this `ProcessId`,
+    /// so its own MPU configuration is left as it is.
+    fn process_terminated(&self, processid: ProcessId) {
+        for (index, block) in self.blocks.iter().enumerate() {
+            if block.owner() == Some(processid) {
+                self.release(index);
+            } else if let Some(share) = block.find_share(processid) {
+                share.set(None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate std;
+    use std::boxed::Box;
+
+    /// A pool of four 16 byte blocks, shared between processes that are
+    /// not running.
+    fn shared_memory() -> &'static SharedMemory<4> {
+        let kernel = Box::leak(Box::new(Kernel::new(&[])));
+        let pool = Box::leak(Box::new([0xAA; 64]));
+        Box::leak(Box::new(SharedMemory::new(kernel, pool)))
+    }
+
+    fn processid(shared_memory: &SharedMemory<4>, identifier: usize) -> ProcessId {
+        ProcessId::new(shared_memory.kernel, identifier, identifier)
+    }
+
+    #[test]
+    fn test_create() {
+        let shared_memory = shared_memory();
+        let owner = processid(shared_memory, 0);
+
+        assert_eq!(shared_memory.allocate(owner, 0), Err(ErrorCode::SIZE));
+        assert_eq!(shared_memory.allocate(owner, 17), Err(ErrorCode::SIZE));
+        for index in 0..4 {
+            assert_eq!(shared_memory.allocate(owner, 16), Ok(index));
+        }
+        assert_eq!(shared_memory.allocate(owner, 8), Err(ErrorCode::NOMEM));
+
+        // Only the owner can use the region as its owner.
+        assert!(shared_memory.owned_block(1, owner).is_ok());
+        assert!(shared_memory
+            .owned_block(1, processid(shared_memory, 1))
+            .is_err());
+        assert!(shared_memory.owned_block(4, owner).is_err());
+
+        // Blocks are cleared when they are allocated.
+        shared_memory
+            .pool
+            .map(|pool| assert!(pool.iter().all(|&b| b == 0)));
+    }
+
+    #[test]
+    fn test_share() {
+        let shared_memory = shared_memory();
+        let owner = processid(shared_memory, 0);
+        let index = shared_memory.allocate(owner, 8).unwrap();
+        let block = &shared_memory.blocks[index];
+
+        for identifier in 1..MAX_SHARERS {
+            let target = processid(shared_memory, identifier);
+            assert_eq!(shared_memory.add_share(block, target, false), Ok(()));
+            assert!(block.find_share(target).is_some());
+        }
+        assert_eq!(
+            shared_memory.add_share(block, processid(shared_memory, 1), true),
+            Err(ErrorCode::ALREADY)
+        );
+        assert_eq!(
+            shared_memory.add_share(block, processid(shared_memory, MAX_SHARERS), true),
+            Err(ErrorCode::NOMEM)
+        );
+    }
+
+    #[test]
+    fn test_release() {
+        let shared_memory = shared_memory();
+        let owner = processid(shared_memory, 0);
+        let target = processid(shared_memory, 1);
+        let index = shared_memory.allocate(owner, 8).unwrap();
+        let block = &shared_memory.blocks[index];
+        shared_memory.add_share(block, target, true).unwrap();
+
+        shared_memory.release(index);
+        assert_eq!(block.owner(), None);
+        assert!(block.find_share(target).is_none());
+        assert_eq!(shared_memory.allocate(target, 16), Ok(index));
+    }
+
+    #[test]
+    fn test_process_terminated() {
+        let shared_memory = shared_memory();
+        let owner = processid(shared_memory, 0);
+        let target = processid(shared_memory, 1);
+        let first = shared_memory.allocate(owner, 8).unwrap();
+        let second = shared_memory.allocate(target, 8).unwrap();
+        shared_memory
+            .add_share(&shared_memory.blocks[first], target, false)
+            .unwrap();
+        shared_memory
+            .add_share(&shared_memory.blocks[second], owner, false)
+            .unwrap();
+
+        // The regions of a terminated process are freed, and it loses access
+        // to the regions of other processes.
+        shared_memory.process_terminated(owner);
+        assert_eq!(shared_memory.blocks[first].owner(), None);
+        assert!(shared_memory.blocks[first].find_share(target).is_none());
+        assert_eq!(shared_memory.blocks[second].owner(), Some(target));
+        assert!(shared_memory.blocks[second].find_share(owner).is_none());
+    }
+}
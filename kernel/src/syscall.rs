@@ -207,6 +207,20 @@ impl Syscall {
             Err(_) => None,
         }
     }
+
+    /// Returns the class of this system call.
+    pub fn class(&self) -> SyscallClass {
+        match self {
+            Syscall::Yield { .. } => SyscallClass::Yield,
+            Syscall::Subscribe { .. } => SyscallClass::Subscribe,
+            Syscall::Command { .. } => SyscallClass::Command,
+            Syscall::ReadWriteAllow { .. } => SyscallClass::ReadWriteAllow,
+            Syscall::UserspaceReadableAllow { .. } => SyscallClass::UserspaceReadableAllow,
+            Syscall::ReadOnlyAllow { .. } => SyscallClass::ReadOnlyAllow,
+            Syscall::Memop { .. } => SyscallClass::Memop,
+            Syscall::Exit { .. } => SyscallClass::Exit,
+        }
+    }
 }
 
 // ---------- SYSCALL RETURN VALUE ENCODING ----------
@@ -470,6 +484,16 @@ pub enum ContextSwitchReason {
     Interrupted,
 }
 
+/// Information the architecture reports about why a process faulted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FaultContext {
+    /// The program counter of the process when it faulted.
+    pub pc: Option<usize>,
+    /// The address whose access caused the fault, if the fault was a memory
+    /// access fault and the hardware reports the address.
+    pub fault_address: Option<usize>,
+}
+
 /// The `UserspaceKernelBoundary` trait is implemented by the
 /// architectural component of the chip implementation of Tock. This
 /// trait allows the kernel to switch to and from processes
@@ -652,4 +676,27 @@ pub trait UserspaceKernelBoundary {
     /// Store architecture specific (e.g. CPU registers or status flags) data
     /// for a process. On success returns the number of elements written to out.
     fn store_context(&self, state: &Self::StoredState, out: &mut [u8]) -> Result<usize, ErrorCode>;
+
+    /// Return what the architecture knows about the last fault of a process
+    /// identified by the stored state for that process. Only meaningful
+    /// immediately after a context switch returned
+    /// `ContextSwitchReason::Fault`.
+    ///
+    /// The default implementation reports nothing.
+    ///
+    /// ### Safety
+    ///
+    /// This function guarantees that it will only read process memory
+    /// starting at `accessible_memory_start` and before `app_brk`. The caller
+    /// is responsible for guaranteeing that those pointers are valid for the
+    /// process.
+    #[allow(unused_variables)]
+    unsafe fn fault_context(
+        &self,
+        accessible_memory_start: *const u8,
+        app_brk: *const u8,
+        state: &Self::StoredState,
+    ) -> FaultContext {
+        FaultContext::default()
+    }
 }
@@ -0,0 +1,255 @@
+//! Binary trace of the system calls made by processes.
+//!
+//! When the kernel is built with the `trace_syscalls_buffer` feature and the
+//! board registers a `SyscallTrace` with `Kernel::set_syscall_trace()`, the
+//! kernel records every system call in a fixed size ring buffer. Each record
+//! holds the time of the call, the calling process, the system call class,
+//! the driver number and the variant of the value returned to the process.
+//! If the buffer is full the oldest record is overwritten.
+//!
+//! Unlike the `trace_syscalls` feature, which prints each system call as
+//! text, recording a system call is cheap enough to leave enabled while
+//! processes run normally. Records are read out with `SyscallTrace::drain()`
+//! in a compact binary form, usually by
+//! `capsules::syscall_trace::SyscallTraceWriter` over the debug writer or
+//! SEGGER RTT, and decoded on the host with `tools/syscall-trace`.
+//!
+//! Format
+//! ------
+//!
+//! `drain()` writes one frame, made of a 12 byte header and a number of 16
+//! byte records. All fields are little endian.
+//!
+//! ```text
+//! Header:
+//!   0   magic, the bytes "SYST"
+//!   4   u32 frequency of the timestamps in Hz, or 0 if there is no clock
+//!   8   u16 number of records in the frame
+//!   10  u16 number of records that were overwritten since the last frame
+//!
+//! Record:
+//!   0   u32 timestamp, the lower 32 bits of the clock's ticks
+//!   4   u16 the identifier of the process, as returned by `ProcessId::id()`
+//!   6   u8  system call class, as in `SyscallClass`
+//!   7   u8  return variant, as in `SyscallReturnVariant`, or 0xff for yield
+//!           and exit which do not return a value
+//!   8   u32 driver number, or the operand of memop, or the yield or exit
+//!           identifier
+//!   12  u16 subdriver number
+//!   14  u16 error code if the system call failed, otherwise 0
+//! ```
+
+use core::cell::Cell;
+
+use crate::collections::queue::Queue;
+use crate::collections::ring_buffer::RingBuffer;
+use crate::hil::time::{Frequency, Ticks, Time};
+use crate::process::ProcessId;
+use crate::syscall::{Syscall, SyscallReturn, SyscallReturnVariant};
+use crate::utilities::cells::{MapCell, OptionalCell};
+
+/// The bytes that start every frame.
+pub const FRAME_MAGIC: [u8; 4] = *b"SYST";
+
+/// Length of a frame header in bytes.
+pub const HEADER_LEN: usize = 12;
+
+/// Length of an encoded record in bytes.
+pub const RECORD_LEN: usize = 16;
+
+/// Return variant of system calls that do not return to the process.
+pub const NO_RETURN: u8 = 0xff;
+
+/// A clock used to timestamp system calls.
+///
+/// This is implemented for every `hil::time::Time`.
+pub trait TraceClock {
+    /// The current time, as the lower 32 bits of the clock's ticks.
+    fn timestamp(&self) -> u32;
+
+    /// The frequency of the clock in Hz.
+    fn frequency(&self) -> u32;
+}
+
+impl<T: Time> TraceClock for T {
+    fn timestamp(&self) -> u32 {
+        Time::now(self).into_u32()
+    }
+
+    fn frequency(&self) -> u32 {
+        T::Frequency::frequency()
+    }
+}
+
+/// One recorded system call.
+#[derive(Copy, Clone)]
+pub struct SyscallRecord {
+    timestamp: u32,
+    process: u16,
+    class: u8,
+    return_variant: u8,
+    number: u32,
+    subdriver: u16,
+    error: u16,
+}
+
+impl SyscallRecord {
+    /// A record used to initialize trace buffers.
+    pub const EMPTY: SyscallRecord = SyscallRecord {
+        timestamp: 0,
+        process: 0,
+        class: 0,
+        return_variant: NO_RETURN,
+        number: 0,
+        subdriver: 0,
+        error: 0,
+    };
+
+    fn new(
+        timestamp: u32,
+        processid: ProcessId,
+        syscall: &Syscall,
+        rval: Option<&SyscallReturn>,
+    ) -> SyscallRecord {
+        let (number, subdriver) = match *syscall {
+            Syscall::Yield { which, .. } => (which, 0),
+            Syscall::Subscribe {
+                driver_number,
+                subdriver_number,
+                ..
+            }
+            | Syscall::Command {
+                driver_number,
+                subdriver_number,
+                ..
+            }
+            | Syscall::ReadWriteAllow {
+                driver_number,
+                subdriver_number,
+                ..
+            }
+            | Syscall::UserspaceReadableAllow {
+                driver_number,
+                subdriver_number,
+                ..
+            }
+            | Syscall::ReadOnlyAllow {
+                driver_number,
+                subdriver_number,
+                ..
+            } => (driver_number, subdriver_number),
+            Syscall::Memop { operand, .. } => (operand, 0),
+            Syscall::Exit { which, .. } => (which, 0),
+        };
+
+        let (return_variant, error) = rval.map_or((NO_RETURN, 0), |rval| {
+            let (mut a0, mut a1, mut a2, mut a3) = (0, 0, 0, 0);
+            rval.encode_syscall_return(&mut a0, &mut a1, &mut a2, &mut a3);
+            let error = if a0 < SyscallReturnVariant::Success as u32 {
+                a1 as u16
+            } else {
+                0
+            };
+            (a0 as u8, error)
+        });
+
+        SyscallRecord {
+            timestamp,
+            process: processid.id() as u16,
+            class: syscall.class() as u8,
+            return_variant,
+            number: number as u32,
+            subdriver: subdriver as u16,
+            error,
+        }
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(&self.timestamp.to_le_bytes());
+        buf[4..6].copy_from_slice(&self.process.to_le_bytes());
+        buf[6] = self.class;
+        buf[7] = self.return_variant;
+        buf[8..12].copy_from_slice(&self.number.to_le_bytes());
+        buf[12..14].copy_from_slice(&self.subdriver.to_le_bytes());
+        buf[14..16].copy_from_slice(&self.error.to_le_bytes());
+    }
+}
+
+/// Ring buffer of recorded system calls.
+pub struct SyscallTrace {
+    records: MapCell<RingBuffer<'static, SyscallRecord>>,
+    clock: OptionalCell<&'static dyn TraceClock>,
+    /// Records overwritten since the last frame.
+    overwritten: Cell<u16>,
+}
+
+impl SyscallTrace {
+    pub fn new(buffer: &'static mut [SyscallRecord]) -> SyscallTrace {
+        SyscallTrace {
+            records: MapCell::new(RingBuffer::new(buffer)),
+            clock: OptionalCell::empty(),
+            overwritten: Cell::new(0),
+        }
+    }
+
+    /// Set the clock used to timestamp system calls. Without a clock all
+    /// timestamps are 0.
+    pub fn set_clock(&self, clock: &'static dyn TraceClock) {
+        self.clock.set(clock);
+    }
+
+    /// Record a system call and the value returned to the process.
+    pub(crate) fn record(
+        &self,
+        processid: ProcessId,
+        syscall: &Syscall,
+        rval: Option<&SyscallReturn>,
+    ) {
+        let timestamp = self.clock.map_or(0, |clock| clock.timestamp());
+        let record = SyscallRecord::new(timestamp, processid, syscall, rval);
+        self.records.map(|records| {
+            if records.push(record).is_some() {
+                self.overwritten
+                    .set(self.overwritten.get().saturating_add(1));
+            }
+        });
+    }
+
+    /// Whether there are records that have not been drained.
+    pub fn has_records(&self) -> bool {
+        self.records.map_or(false, |records| records.has_elements())
+    }
+
+    /// Move as many records as fit into `buf` as one frame, and return the
+    /// length of the frame. Returns 0 if there are no records or `buf` cannot
+    /// hold a single record.
+    pub fn drain(&self, buf: &mut [u8]) -> usize {
+        let capacity = buf.len().saturating_sub(HEADER_LEN) / RECORD_LEN;
+        if capacity == 0 || !self.has_records() {
+            return 0;
+        }
+
+        let mut count = 0;
+        self.records.map(|records| {
+            for chunk in buf[HEADER_LEN..]
+                .chunks_exact_mut(RECORD_LEN)
+                .take(capacity.min(u16::MAX as usize))
+            {
+                match records.dequeue() {
+                    Some(record) => {
+                        record.encode(chunk);
+                        count += 1;
+                    }
+                    None => break,
+                }
+            }
+        });
+
+        let frequency = self.clock.map_or(0, |clock| clock.frequency());
+        buf[0..4].copy_from_slice(&FRAME_MAGIC);
+        buf[4..8].copy_from_slice(&frequency.to_le_bytes());
+        buf[8..10].copy_from_slice(&(count as u16).to_le_bytes());
+        buf[10..12].copy_from_slice(&self.overwritten.take().to_le_bytes());
+        HEADER_LEN + count * RECORD_LEN
+    }
+}
@@ -126,7 +126,7 @@
 use crate::error_codes::ErrorCode;
 use crate::flash_controller::FlashController;
 use crate::success_codes::SuccessCode;
-use crate::tickv::{State, TicKV};
+use crate::tickv::{KeyEntry, State, TicKV};
 use core::cell::Cell;
 
 /// The return type from the continue operation
@@ -144,6 +144,8 @@ pub struct AsyncTicKV<'a, C: FlashController<S>, const S: usize> {
     key: Cell<Option<u64>>,
     value: Cell<Option<&'static mut [u8]>>,
     buf: Cell<Option<&'static mut [u8]>>,
+    cursor: Cell<usize>,
+    key_entry: Cell<Option<KeyEntry>>,
 }
 
 impl<'a, C: FlashController<S>, const S: usize> AsyncTicKV<'a, C, S> {
@@ -159,6 +161,8 @@ impl<'a, C: FlashController<S>, const S: usize> AsyncTicKV<'a, C, S> {
             key: Cell::new(None),
             value: Cell::new(None),
             buf: Cell::new(None),
+            cursor: Cell::new(0),
+            key_entry: Cell::new(None),
         }
     }
 
@@ -263,6 +267,27 @@ impl<'a, C: FlashController<S>, const S: usize> AsyncTicKV<'a, C, S> {
         self.tickv.garbage_collect()
     }
 
+    /// Find the next valid key, starting at `cursor`.
+    ///
+    /// `cursor`: `0` to start at the beginning of the flash, otherwise the
+    ///           `next_cursor` of the previously returned `KeyEntry`.
+    ///
+    /// On success the `KeyEntry` of the next valid key will be returned.
+    /// On error a `ErrorCode` will be returned. `KeyNotFound` indicates that
+    /// there are no more keys.
+    ///
+    /// If the operation completes asynchronously the `KeyEntry` can be
+    /// retrieved with `get_stored_key_entry()`.
+    pub fn get_next_key(&self, cursor: usize) -> Result<KeyEntry, ErrorCode> {
+        match self.tickv.get_next_key(cursor) {
+            Ok(entry) => Ok(entry),
+            Err(e) => {
+                self.cursor.set(cursor);
+                Err(e)
+            }
+        }
+    }
+
     /// Copy data from `read_buffer` argument to the internal read_buffer.
     /// This should be used to copy the data that the implementation wanted
     /// to read when calling `read_region` after the async operation has
@@ -285,6 +310,12 @@ impl<'a, C: FlashController<S>, const S: usize> AsyncTicKV<'a, C, S> {
         self.buf.take()
     }
 
+    /// Get the `KeyEntry` found by a previous `get_next_key()` command
+    /// that completed asynchronously.
+    pub fn get_stored_key_entry(&self) -> Option<KeyEntry> {
+        self.key_entry.take()
+    }
+
     /// Continue the last operation after the async operation has completed.
     /// This should be called from a read/erase complete callback.
     /// NOTE: If called from a read callback, `set_read_buffer` should be
@@ -320,6 +351,13 @@ impl<'a, C: FlashController<S>, const S: usize> AsyncTicKV<'a, C, S> {
                 Ok(_) => Ok(SuccessCode::Complete),
                 Err(e) => Err(e),
             },
+            State::IterateKeys(_) => match self.tickv.get_next_key(self.cursor.get()) {
+                Ok(entry) => {
+                    self.key_entry.set(Some(entry));
+                    Ok(SuccessCode::Complete)
+                }
+                Err(e) => Err(e),
+            },
             _ => unreachable!(),
         };
 
@@ -331,7 +369,11 @@ impl<'a, C: FlashController<S>, const S: usize> AsyncTicKV<'a, C, S> {
             Err(e) => match e {
                 ErrorCode::ReadNotReady(_) | ErrorCode::EraseNotReady(_) => (ret, None),
                 ErrorCode::WriteNotReady(_) => {
-                    self.tickv.state.set(State::None);
+                    // Garbage collection continues with the next region once
+                    // the region header has been written
+                    if !matches!(self.tickv.state.get(), State::GarbageCollect(_)) {
+                        self.tickv.state.set(State::None);
+                    }
                     (ret, None)
                 }
                 _ => {
@@ -341,6 +383,24 @@ impl<'a, C: FlashController<S>, const S: usize> AsyncTicKV<'a, C, S> {
             },
         }
     }
+
+    /// Continue the last operation after an async write or erase failed.
+    /// This should be called instead of `continue_operation()` from a
+    /// write/erase complete callback that reports an error.
+    ///
+    /// The region that couldn't be written or erased is retired, so that it
+    /// won't be used again. A garbage collection continues with the next
+    /// region, any other operation fails with `WriteFail` or `EraseFail`.
+    ///
+    /// Returns the same values as `continue_operation()`.
+    pub fn continue_failed_operation(&self) -> ContinueReturn {
+        match self.tickv.flash_operation_failed() {
+            Ok(()) => self.continue_operation(),
+            // Garbage collection continues once the region is retired
+            Err(ErrorCode::WriteNotReady(reg)) => (Err(ErrorCode::WriteNotReady(reg)), None),
+            Err(e) => (Err(e), self.buf.take()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -377,10 +437,10 @@ mod tests {
             assert_eq!(buf[HASH_OFFSET + 7], 0x44);
 
             // Check the check hash
-            assert_eq!(buf[HASH_OFFSET + 8], 0xbb);
-            assert_eq!(buf[HASH_OFFSET + 9], 0x32);
-            assert_eq!(buf[HASH_OFFSET + 10], 0x74);
-            assert_eq!(buf[HASH_OFFSET + 11], 0x1d);
+            assert_eq!(buf[HASH_OFFSET + 8], 0x3e);
+            assert_eq!(buf[HASH_OFFSET + 9], 0xa7);
+            assert_eq!(buf[HASH_OFFSET + 10], 0x40);
+            assert_eq!(buf[HASH_OFFSET + 11], 0x13);
         }
 
         fn check_region_one(buf: &[u8]) {
@@ -407,10 +467,10 @@ mod tests {
             assert_eq!(buf[42], 0x23);
 
             // Check the check hash
-            assert_eq!(buf[43], 0xfd);
-            assert_eq!(buf[44], 0x24);
-            assert_eq!(buf[45], 0xf0);
-            assert_eq!(buf[46], 0x07);
+            assert_eq!(buf[43], 0x54);
+            assert_eq!(buf[44], 0x72);
+            assert_eq!(buf[45], 0xf4);
+            assert_eq!(buf[46], 0x31);
         }
 
         fn check_region_two(buf: &[u8]) {
@@ -437,10 +497,10 @@ mod tests {
             assert_eq!(buf[42], 0x23);
 
             // Check the check hash
-            assert_eq!(buf[43], 0x1b);
-            assert_eq!(buf[44], 0x53);
-            assert_eq!(buf[45], 0xf9);
-            assert_eq!(buf[46], 0x54);
+            assert_eq!(buf[43], 0xb2);
+            assert_eq!(buf[44], 0x05);
+            assert_eq!(buf[45], 0xfd);
+            assert_eq!(buf[46], 0x62);
         }
 
         fn get_hashed_key(unhashed_key: &[u8]) -> u64 {
@@ -875,5 +935,62 @@ mod tests {
                     .unwrap();
             }
         }
+
+        #[test]
+        fn test_iterate_keys() {
+            let mut read_buf: [u8; 1024] = [0; 1024];
+            let mut hash_function = DefaultHasher::new();
+            MAIN_KEY.hash(&mut hash_function);
+
+            let tickv =
+                AsyncTicKV::<FlashCtrl, 1024>::new(FlashCtrl::new(), &mut read_buf, 0x10000);
+
+            let mut ret = tickv.initialise(hash_function.finish());
+            while ret.is_err() {
+                // There is no actual delay in the test, just continue now
+                let (r, _buf) = tickv.continue_operation();
+                ret = r;
+            }
+
+            static mut VALUE: [u8; 32] = [0x23; 32];
+
+            println!("Add key ONE");
+            let ret = unsafe { tickv.append_key(get_hashed_key(b"ONE"), &mut VALUE) };
+            match ret {
+                Err((_buf, ErrorCode::ReadNotReady(reg))) => {
+                    // There is no actual delay in the test, just continue now
+                    tickv.set_read_buffer(&tickv.tickv.controller.buf.borrow()[reg]);
+                    tickv.continue_operation().0.unwrap();
+                }
+                Ok(_) => {}
+                _ => unreachable!(),
+            }
+
+            println!("Iterate over key ONE");
+            let mut ret = tickv.get_next_key(0);
+            while let Err(ErrorCode::ReadNotReady(reg)) = ret {
+                // There is no actual delay in the test, just continue now
+                tickv.set_read_buffer(&tickv.tickv.controller.buf.borrow()[reg]);
+                ret = match tickv.continue_operation().0 {
+                    Ok(_) => Ok(tickv.get_stored_key_entry().unwrap()),
+                    Err(e) => Err(e),
+                };
+            }
+            let entry = ret.unwrap();
+            assert_eq!(entry.hashed_key, get_hashed_key(b"ONE"));
+            assert_eq!(entry.value_length, 32);
+
+            println!("Iterate past the last key");
+            let mut ret = tickv.get_next_key(entry.next_cursor);
+            while let Err(ErrorCode::ReadNotReady(reg)) = ret {
+                // There is no actual delay in the test, just continue now
+                tickv.set_read_buffer(&tickv.tickv.controller.buf.borrow()[reg]);
+                ret = match tickv.continue_operation().0 {
+                    Ok(_) => Ok(tickv.get_stored_key_entry().unwrap()),
+                    Err(e) => Err(e),
+                };
+            }
+            assert_eq!(ret, Err(ErrorCode::KeyNotFound));
+        }
     }
 }
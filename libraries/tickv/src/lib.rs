@@ -221,9 +221,12 @@ pub use crate::async_ops::AsyncTicKV;
 pub use crate::error_codes::ErrorCode;
 #[doc(inline)]
 pub use crate::flash_controller::FlashController;
+pub use crate::tickv::KeyEntry;
 #[doc(inline)]
 pub use crate::tickv::TicKV;
+pub use crate::tickv::WearStats;
 pub use crate::tickv::MAIN_KEY;
+pub use crate::tickv::WEAR_LEVELING_THRESHOLD;
 
 // This is used to run the tests on a host
 #[cfg(test)]
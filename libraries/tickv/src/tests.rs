@@ -25,10 +25,10 @@ fn check_region_main(buf: &[u8]) {
     assert_eq!(buf[HASH_OFFSET + 7], 0x44);
 
     // Check the check hash
-    assert_eq!(buf[HASH_OFFSET + 8], 0xbb);
-    assert_eq!(buf[HASH_OFFSET + 9], 0x32);
-    assert_eq!(buf[HASH_OFFSET + 10], 0x74);
-    assert_eq!(buf[HASH_OFFSET + 11], 0x1d);
+    assert_eq!(buf[HASH_OFFSET + 8], 0x3e);
+    assert_eq!(buf[HASH_OFFSET + 9], 0xa7);
+    assert_eq!(buf[HASH_OFFSET + 10], 0x40);
+    assert_eq!(buf[HASH_OFFSET + 11], 0x13);
 }
 
 fn check_region_one(buf: &[u8]) {
@@ -55,10 +55,10 @@ fn check_region_one(buf: &[u8]) {
     assert_eq!(buf[42], 0x23);
 
     // Check the check hash
-    assert_eq!(buf[43], 0xfd);
-    assert_eq!(buf[44], 0x24);
-    assert_eq!(buf[45], 0xf0);
-    assert_eq!(buf[46], 0x07);
+    assert_eq!(buf[43], 0x54);
+    assert_eq!(buf[44], 0x72);
+    assert_eq!(buf[45], 0xf4);
+    assert_eq!(buf[46], 0x31);
 }
 
 fn check_region_two(buf: &[u8]) {
@@ -85,10 +85,10 @@ fn check_region_two(buf: &[u8]) {
     assert_eq!(buf[42], 0x23);
 
     // Check the check hash
-    assert_eq!(buf[43], 0x1b);
-    assert_eq!(buf[44], 0x53);
-    assert_eq!(buf[45], 0xf9);
-    assert_eq!(buf[46], 0x54);
+    assert_eq!(buf[43], 0xb2);
+    assert_eq!(buf[44], 0x05);
+    assert_eq!(buf[45], 0xfd);
+    assert_eq!(buf[46], 0x62);
 }
 
 fn get_hashed_key(unhashed_key: &[u8]) -> u64 {
@@ -414,6 +414,51 @@ mod store_flast_ctrl {
         println!("Add Key ONE");
         tickv.append_key(get_hashed_key(b"ONE"), &value).unwrap();
     }
+
+    #[test]
+    fn test_iterate_keys() {
+        let mut read_buf: [u8; 1024] = [0; 1024];
+        let mut hash_function = DefaultHasher::new();
+        MAIN_KEY.hash(&mut hash_function);
+        let hash = hash_function.finish();
+
+        let tickv = TicKV::<FlashCtrl, 1024>::new(FlashCtrl::new(), &mut read_buf, 0x10000);
+        tickv.initialise(hash).unwrap();
+
+        let value: [u8; 32] = [0x23; 32];
+
+        println!("Iterate over empty flash");
+        assert_eq!(tickv.get_next_key(0), Err(ErrorCode::KeyNotFound));
+
+        println!("Add Key ONE");
+        tickv.append_key(get_hashed_key(b"ONE"), &value).unwrap();
+
+        println!("Add Key TWO");
+        tickv.append_key(get_hashed_key(b"TWO"), &value).unwrap();
+
+        println!("Iterate over keys ONE and TWO");
+        let mut keys = vec![];
+        let mut cursor = 0;
+        while let Ok(entry) = tickv.get_next_key(cursor) {
+            assert_eq!(entry.value_length, value.len());
+            keys.push(entry.hashed_key);
+            cursor = entry.next_cursor;
+        }
+        assert_eq!(keys.len(), 2);
+        assert!(keys.contains(&get_hashed_key(b"ONE")));
+        assert!(keys.contains(&get_hashed_key(b"TWO")));
+
+        println!("Delete Key ONE");
+        tickv.invalidate_key(get_hashed_key(b"ONE")).unwrap();
+
+        println!("Iterate over key TWO");
+        let entry = tickv.get_next_key(0).unwrap();
+        assert_eq!(entry.hashed_key, get_hashed_key(b"TWO"));
+        assert_eq!(
+            tickv.get_next_key(entry.next_cursor),
+            Err(ErrorCode::KeyNotFound)
+        );
+    }
 }
 
 mod no_check_store_flast_ctrl {
@@ -558,3 +603,379 @@ mod no_check_store_flast_ctrl {
         );
     }
 }
+
+/// Tests using a flash controller that can store data and inject failures
+mod wear_flash_ctrl {
+    use super::*;
+    use crate::async_ops::AsyncTicKV;
+    use crate::success_codes::SuccessCode;
+    use crate::tickv::{REGION_FLAGS_GOOD, REGION_FLAGS_OFFSET, VERSION_WITHOUT_REGION_HEADER};
+    use std::boxed::Box;
+
+    // An example FlashCtrl implementation
+    struct FlashCtrl {
+        buf: RefCell<[[u8; 256]; 4]>,
+        erase_fail: Cell<bool>,
+        write_fail: Cell<bool>,
+        async_erase_fail: Cell<bool>,
+        async_write_fail: Cell<bool>,
+        erases: Cell<usize>,
+    }
+
+    impl FlashCtrl {
+        fn new() -> Self {
+            Self {
+                buf: RefCell::new([[0xFF; 256]; 4]),
+                erase_fail: Cell::new(false),
+                write_fail: Cell::new(false),
+                async_erase_fail: Cell::new(false),
+                async_write_fail: Cell::new(false),
+                erases: Cell::new(0),
+            }
+        }
+
+        /// Returns the region storing the object with key `hash`
+        fn key_region(&self, hash: u64) -> Option<usize> {
+            self.buf
+                .borrow()
+                .iter()
+                .position(|region| region.windows(8).any(|window| window == hash.to_be_bytes()))
+        }
+
+        fn erase_count(&self, region: usize) -> u32 {
+            let buf = self.buf.borrow();
+            !((buf[region][0] as u32) << 16 | (buf[region][1] as u32) << 8 | buf[region][2] as u32)
+                & 0xFF_FFFF
+        }
+    }
+
+    impl FlashController<256> for FlashCtrl {
+        fn read_region(
+            &self,
+            region_number: usize,
+            offset: usize,
+            buf: &mut [u8; 256],
+        ) -> Result<(), ErrorCode> {
+            println!("Read from region: {}", region_number);
+
+            for (i, b) in buf.iter_mut().enumerate() {
+                *b = self.buf.borrow()[region_number][offset + i]
+            }
+
+            Ok(())
+        }
+
+        fn write(&self, address: usize, buf: &[u8]) -> Result<(), ErrorCode> {
+            println!(
+                "Write to address: {:#x}, region: {}",
+                address,
+                address / 256
+            );
+
+            // Only fail a single write
+            if self.write_fail.take() {
+                return Err(ErrorCode::WriteFail);
+            }
+
+            // Start a single write that fails once it completes
+            if self.async_write_fail.take() {
+                return Err(ErrorCode::WriteNotReady(address));
+            }
+
+            for (i, d) in buf.iter().enumerate() {
+                self.buf.borrow_mut()[address / 256][(address % 256) + i] = *d;
+            }
+
+            Ok(())
+        }
+
+        fn erase_region(&self, region_number: usize) -> Result<(), ErrorCode> {
+            println!("Erase region: {}", region_number);
+            self.erases.set(self.erases.get() + 1);
+
+            if self.erase_fail.get() {
+                return Err(ErrorCode::EraseFail);
+            }
+
+            // Start a single erase that fails once it completes
+            if self.async_erase_fail.take() {
+                return Err(ErrorCode::EraseNotReady(region_number));
+            }
+
+            for d in self.buf.borrow_mut()[region_number].iter_mut() {
+                *d = 0xFF;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_erase_count() {
+        let mut read_buf: [u8; 256] = [0; 256];
+        let mut hash_function = DefaultHasher::new();
+        MAIN_KEY.hash(&mut hash_function);
+        let hash = hash_function.finish();
+
+        let tickv = TicKV::<FlashCtrl, 256>::new(FlashCtrl::new(), &mut read_buf, 0x400);
+        tickv.initialise(hash).unwrap();
+
+        let value: [u8; 32] = [0x23; 32];
+        let mut buf: [u8; 32] = [0; 32];
+
+        println!("No wear stats before garbage collection");
+        assert_eq!(tickv.wear_stats(), None);
+
+        println!("Add and delete Key TWO");
+        tickv.append_key(get_hashed_key(b"TWO"), &value).unwrap();
+        tickv.invalidate_key(get_hashed_key(b"TWO")).unwrap();
+        let region = tickv.controller.key_region(get_hashed_key(b"TWO")).unwrap();
+
+        println!("Garbage collect flash with deleted key");
+        assert_eq!(tickv.garbage_collect(), Ok(256));
+        assert_eq!(tickv.controller.erase_count(region), 1);
+
+        let stats = tickv.wear_stats().unwrap();
+        assert_eq!(stats.min_erase_count, 0);
+        assert_eq!(stats.max_erase_count, 1);
+        assert_eq!(stats.retired_regions, 0);
+
+        println!("Add Key TWO to the erased region");
+        tickv.append_key(get_hashed_key(b"TWO"), &value).unwrap();
+        tickv.get_key(get_hashed_key(b"TWO"), &mut buf).unwrap();
+        assert_eq!(buf, value);
+        assert_eq!(tickv.controller.erase_count(region), 1);
+    }
+
+    #[test]
+    fn test_erase_failure() {
+        let mut read_buf: [u8; 256] = [0; 256];
+        let mut hash_function = DefaultHasher::new();
+        MAIN_KEY.hash(&mut hash_function);
+        let hash = hash_function.finish();
+
+        let tickv = TicKV::<FlashCtrl, 256>::new(FlashCtrl::new(), &mut read_buf, 0x400);
+        tickv.initialise(hash).unwrap();
+
+        let value: [u8; 32] = [0x23; 32];
+
+        println!("Add and delete Key TWO");
+        tickv.append_key(get_hashed_key(b"TWO"), &value).unwrap();
+        tickv.invalidate_key(get_hashed_key(b"TWO")).unwrap();
+        let region = tickv.controller.key_region(get_hashed_key(b"TWO")).unwrap();
+
+        println!("Garbage collect with a failing erase");
+        tickv.controller.erase_fail.set(true);
+        tickv.controller.erases.set(0);
+        assert_eq!(tickv.garbage_collect(), Ok(0));
+        assert_eq!(tickv.controller.erases.get(), 1);
+        assert_eq!(
+            tickv.controller.buf.borrow()[region][REGION_FLAGS_OFFSET] & REGION_FLAGS_GOOD,
+            0
+        );
+        assert_eq!(tickv.wear_stats().unwrap().retired_regions, 1);
+
+        println!("Garbage collect skips the retired region");
+        assert_eq!(tickv.garbage_collect(), Ok(0));
+        assert_eq!(tickv.controller.erases.get(), 1);
+        assert_eq!(tickv.wear_stats().unwrap().retired_regions, 1);
+    }
+
+    #[test]
+    fn test_write_failure() {
+        let mut read_buf: [u8; 256] = [0; 256];
+        let mut hash_function = DefaultHasher::new();
+        MAIN_KEY.hash(&mut hash_function);
+        let hash = hash_function.finish();
+
+        let tickv = TicKV::<FlashCtrl, 256>::new(FlashCtrl::new(), &mut read_buf, 0x400);
+        tickv.initialise(hash).unwrap();
+
+        let value: [u8; 32] = [0x23; 32];
+        let mut buf: [u8; 32] = [0; 32];
+
+        println!("Add Key ZERO with a failing write");
+        tickv.controller.write_fail.set(true);
+        assert_eq!(
+            tickv.append_key(get_hashed_key(b"ZERO"), &value),
+            Err(ErrorCode::WriteFail)
+        );
+        assert_eq!(
+            tickv.controller.buf.borrow()[0][REGION_FLAGS_OFFSET] & REGION_FLAGS_GOOD,
+            0
+        );
+
+        println!("Add Key ZERO to the next region");
+        tickv.append_key(get_hashed_key(b"ZERO"), &value).unwrap();
+        assert_eq!(
+            tickv.controller.key_region(get_hashed_key(b"ZERO")),
+            Some(1)
+        );
+        tickv.get_key(get_hashed_key(b"ZERO"), &mut buf).unwrap();
+        assert_eq!(buf, value);
+
+        assert_eq!(tickv.garbage_collect(), Ok(0));
+        assert_eq!(tickv.wear_stats().unwrap().retired_regions, 1);
+    }
+
+    #[test]
+    fn test_async_erase_failure() {
+        let mut read_buf: [u8; 256] = [0; 256];
+        let mut hash_function = DefaultHasher::new();
+        MAIN_KEY.hash(&mut hash_function);
+        let hash = hash_function.finish();
+
+        let tickv = AsyncTicKV::<FlashCtrl, 256>::new(FlashCtrl::new(), &mut read_buf, 0x400);
+        tickv.initialise(hash).unwrap();
+
+        let value: &'static mut [u8; 32] = Box::leak(Box::new([0x23; 32]));
+
+        println!("Add and delete Key TWO");
+        tickv.append_key(get_hashed_key(b"TWO"), value).unwrap();
+        tickv.invalidate_key(get_hashed_key(b"TWO")).unwrap();
+        let region = tickv
+            .tickv
+            .controller
+            .key_region(get_hashed_key(b"TWO"))
+            .unwrap();
+
+        println!("Garbage collect with an erase that fails asynchronously");
+        tickv.tickv.controller.async_erase_fail.set(true);
+        tickv.tickv.controller.erases.set(0);
+        assert_eq!(
+            tickv.garbage_collect(),
+            Err(ErrorCode::EraseNotReady(region))
+        );
+        assert_eq!(
+            tickv.continue_failed_operation().0,
+            Ok(SuccessCode::Complete)
+        );
+        assert_eq!(
+            tickv.tickv.controller.buf.borrow()[region][REGION_FLAGS_OFFSET] & REGION_FLAGS_GOOD,
+            0
+        );
+        assert_eq!(tickv.tickv.wear_stats().unwrap().retired_regions, 1);
+
+        println!("Garbage collect skips the retired region");
+        assert_eq!(tickv.garbage_collect(), Ok(0));
+        assert_eq!(tickv.tickv.controller.erases.get(), 1);
+        assert_eq!(tickv.tickv.wear_stats().unwrap().retired_regions, 1);
+    }
+
+    #[test]
+    fn test_async_write_failure() {
+        let mut read_buf: [u8; 256] = [0; 256];
+        let mut hash_function = DefaultHasher::new();
+        MAIN_KEY.hash(&mut hash_function);
+        let hash = hash_function.finish();
+
+        let tickv = AsyncTicKV::<FlashCtrl, 256>::new(FlashCtrl::new(), &mut read_buf, 0x400);
+        tickv.initialise(hash).unwrap();
+
+        let value: &'static mut [u8; 32] = Box::leak(Box::new([0x23; 32]));
+        let buf: &'static mut [u8; 32] = Box::leak(Box::new([0; 32]));
+
+        println!("Add Key ZERO with a write that fails asynchronously");
+        tickv.tickv.controller.async_write_fail.set(true);
+        assert_eq!(
+            tickv.append_key(get_hashed_key(b"ZERO"), value).unwrap(),
+            SuccessCode::Queued
+        );
+        assert_eq!(
+            tickv.continue_failed_operation().0,
+            Err(ErrorCode::WriteFail)
+        );
+        assert_eq!(
+            tickv.tickv.controller.buf.borrow()[0][REGION_FLAGS_OFFSET] & REGION_FLAGS_GOOD,
+            0
+        );
+
+        println!("Add Key ZERO to the next region");
+        let value: &'static mut [u8; 32] = Box::leak(Box::new([0x23; 32]));
+        tickv.append_key(get_hashed_key(b"ZERO"), value).unwrap();
+        assert_eq!(
+            tickv.tickv.controller.key_region(get_hashed_key(b"ZERO")),
+            Some(1)
+        );
+        tickv.get_key(get_hashed_key(b"ZERO"), buf).unwrap();
+
+        assert_eq!(tickv.garbage_collect(), Ok(0));
+        assert_eq!(tickv.tickv.wear_stats().unwrap().retired_regions, 1);
+    }
+
+    #[test]
+    fn test_worn_region() {
+        let mut read_buf: [u8; 256] = [0; 256];
+        let mut hash_function = DefaultHasher::new();
+        MAIN_KEY.hash(&mut hash_function);
+        let hash = hash_function.finish();
+
+        let tickv = TicKV::<FlashCtrl, 256>::new(FlashCtrl::new(), &mut read_buf, 0x400);
+        tickv.initialise(hash).unwrap();
+
+        let value: [u8; 32] = [0x23; 32];
+        let mut buf: [u8; 32] = [0; 32];
+
+        println!("Mark region 0 as heavily erased");
+        {
+            let erase_count = !20u32;
+            let mut flash = tickv.controller.buf.borrow_mut();
+            flash[0][0] = (erase_count >> 16) as u8;
+            flash[0][1] = (erase_count >> 8) as u8;
+            flash[0][2] = erase_count as u8;
+            flash[0][REGION_FLAGS_OFFSET] = REGION_FLAGS_GOOD;
+        }
+
+        println!("Add Key ZERO before the wear is known");
+        tickv.append_key(get_hashed_key(b"ZERO"), &value).unwrap();
+        assert_eq!(
+            tickv.controller.key_region(get_hashed_key(b"ZERO")),
+            Some(0)
+        );
+
+        println!("Garbage collect to collect the wear stats");
+        assert_eq!(tickv.garbage_collect(), Ok(0));
+        let stats = tickv.wear_stats().unwrap();
+        assert_eq!(stats.min_erase_count, 0);
+        assert_eq!(stats.max_erase_count, 20);
+
+        println!("Add Key D, skipping the worn region");
+        tickv.append_key(get_hashed_key(b"D"), &value).unwrap();
+        assert_eq!(tickv.controller.key_region(get_hashed_key(b"D")), Some(1));
+
+        println!("Get keys ZERO and D");
+        tickv.get_key(get_hashed_key(b"ZERO"), &mut buf).unwrap();
+        assert_eq!(buf, value);
+        tickv.get_key(get_hashed_key(b"D"), &mut buf).unwrap();
+        assert_eq!(buf, value);
+    }
+    #[test]
+    fn test_version_without_region_header() {
+        let mut read_buf: [u8; 256] = [0; 256];
+        let mut hash_function = DefaultHasher::new();
+        MAIN_KEY.hash(&mut hash_function);
+        let hash = hash_function.finish();
+
+        let flash_ctrl = FlashCtrl::new();
+
+        println!("Store the main key as version 1 did, without a region header");
+        let region = (hash as usize & 0xFFFF) % 4;
+        {
+            let mut flash = flash_ctrl.buf.borrow_mut();
+            flash[region][VERSION_OFFSET] = VERSION_WITHOUT_REGION_HEADER;
+            flash[region][LEN_OFFSET] = 0x80;
+            flash[region][LEN_OFFSET + 1] = 15;
+            flash[region][HASH_OFFSET..HASH_OFFSET + 8].copy_from_slice(&hash.to_be_bytes());
+            flash[region][HASH_OFFSET + 8..HASH_OFFSET + 12]
+                .copy_from_slice(&[0xbb, 0x32, 0x74, 0x1d]);
+        }
+        let old_flash = *flash_ctrl.buf.borrow();
+
+        let tickv = TicKV::<FlashCtrl, 256>::new(flash_ctrl, &mut read_buf, 0x400);
+
+        println!("Initialise refuses to erase the old data");
+        assert_eq!(tickv.initialise(hash), Err(ErrorCode::UnsupportedVersion));
+        assert_eq!(tickv.controller.erases.get(), 0);
+        assert_eq!(*tickv.controller.buf.borrow(), old_flash);
+    }
+}
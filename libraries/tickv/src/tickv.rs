@@ -7,7 +7,11 @@ use crate::success_codes::SuccessCode;
 use core::cell::Cell;
 
 /// The current version of TicKV
-pub const VERSION: u8 = 1;
+pub const VERSION: u8 = 2;
+
+/// The last version of TicKV that didn't store a region header at the start of
+/// every region. `initialise()` refuses to use flash formatted by it.
+pub const VERSION_WITHOUT_REGION_HEADER: u8 = 1;
 
 #[derive(Clone, Copy, PartialEq)]
 pub(crate) enum InitState {
@@ -31,6 +35,8 @@ pub(crate) enum KeyState {
 pub(crate) enum RubbishState {
     ReadRegion(usize),
     EraseRegion(usize),
+    /// Trying to write the region header of a region
+    WriteRegionHeader(usize),
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -49,6 +55,8 @@ pub(crate) enum State {
     InvalidateKey(KeyState),
     /// Running garbage collection
     GarbageCollect(RubbishState),
+    /// Iterating over the stored keys
+    IterateKeys(KeyState),
 }
 
 /// The struct storing all of the TicKV information.
@@ -58,6 +66,102 @@ pub struct TicKV<'a, C: FlashController<S>, const S: usize> {
     flash_size: usize,
     pub(crate) read_buffer: Cell<Option<&'a mut [u8; S]>>,
     pub(crate) state: Cell<State>,
+    /// The hashed main key, which isn't returned when iterating over keys
+    main_key: Cell<u64>,
+    /// The wear statistics from the last complete garbage collection
+    wear_stats: Cell<Option<WearStats>>,
+    /// The wear statistics of the garbage collection in progress
+    gc_wear_stats: Cell<WearStats>,
+    /// The region header to write after erasing a region
+    pending_region_header: Cell<RegionHeader>,
+    /// Set if worn regions should be used when appending a key
+    ignore_wear: Cell<bool>,
+    /// The region of an asynchronous write that retires the region if it
+    /// fails
+    pending_write: Cell<Option<usize>>,
+}
+
+/// Statistics about the wear of the regions, collected during garbage
+/// collection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WearStats {
+    /// The lowest erase count of any region that hasn't been retired
+    pub min_erase_count: u32,
+    /// The highest erase count of any region that hasn't been retired
+    pub max_erase_count: u32,
+    /// The number of regions that have been retired
+    pub retired_regions: usize,
+}
+
+impl WearStats {
+    const fn new() -> Self {
+        Self {
+            min_erase_count: u32::MAX,
+            max_erase_count: 0,
+            retired_regions: 0,
+        }
+    }
+}
+
+/// A valid key found when iterating over the stored keys.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyEntry {
+    /// The hashed key
+    pub hashed_key: u64,
+    /// The length of the value stored with the key
+    pub value_length: usize,
+    /// The cursor to pass to `get_next_key()` to continue after this key
+    pub next_cursor: usize,
+}
+
+/// This is the header stored at the start of every region.
+///
+/// An erased region header (all `0xFF`) is valid and describes a region that
+/// has never been erased by garbage collection.
+#[derive(Clone, Copy, PartialEq)]
+struct RegionHeader {
+    // In reality this is a u24, stored inverted.
+    erase_count: u32,
+    flags: u8,
+}
+
+/// Cleared once a region has been retired. Retired regions are never written
+/// to or erased again.
+pub(crate) const REGION_FLAGS_GOOD: u8 = 0x80;
+/// Cleared once keys may have been stored past a region without using it, so
+/// that searches continue past the region even if it is empty.
+pub(crate) const REGION_FLAGS_TERMINAL: u8 = 0x40;
+
+const REGION_ERASE_COUNT_MAX: u32 = 0xFF_FFFF;
+
+impl RegionHeader {
+    /// Read the header from the start of some loaded region data
+    fn new_from_region(region_data: &[u8]) -> Self {
+        Self {
+            erase_count: !(((region_data[REGION_ERASE_COUNT_OFFSET] as u32) << 16)
+                | ((region_data[REGION_ERASE_COUNT_OFFSET + 1] as u32) << 8)
+                | region_data[REGION_ERASE_COUNT_OFFSET + 2] as u32)
+                & REGION_ERASE_COUNT_MAX,
+            flags: region_data[REGION_FLAGS_OFFSET],
+        }
+    }
+
+    /// Copy the header to the start of `region_data`
+    fn copy_to_region(&self, region_data: &mut [u8]) {
+        let erase_count = !self.erase_count & REGION_ERASE_COUNT_MAX;
+        region_data[REGION_ERASE_COUNT_OFFSET] = (erase_count >> 16) as u8;
+        region_data[REGION_ERASE_COUNT_OFFSET + 1] = (erase_count >> 8) as u8;
+        region_data[REGION_ERASE_COUNT_OFFSET + 2] = erase_count as u8;
+        region_data[REGION_FLAGS_OFFSET] = self.flags;
+    }
+
+    fn retired(&self) -> bool {
+        self.flags & REGION_FLAGS_GOOD != REGION_FLAGS_GOOD
+    }
+
+    fn terminal(&self) -> bool {
+        self.flags & REGION_FLAGS_TERMINAL == REGION_FLAGS_TERMINAL
+    }
 }
 
 /// This is the current object header used for TicKV objects
@@ -84,6 +188,15 @@ impl ObjectHeader {
     }
 }
 
+// A list of offsets into the RegionHeader
+pub(crate) const REGION_ERASE_COUNT_OFFSET: usize = 0;
+pub(crate) const REGION_FLAGS_OFFSET: usize = 3;
+pub(crate) const REGION_HEADER_LENGTH: usize = REGION_FLAGS_OFFSET + 1;
+
+/// How many more times than the least worn region a region can be erased
+/// before new keys avoid it.
+pub const WEAR_LEVELING_THRESHOLD: u32 = 16;
+
 // A list of offsets into the ObjectHeader
 pub(crate) const VERSION_OFFSET: usize = 0;
 pub(crate) const LEN_OFFSET: usize = 1;
@@ -109,6 +222,15 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
             flash_size,
             read_buffer: Cell::new(Some(read_buffer)),
             state: Cell::new(State::None),
+            main_key: Cell::new(0),
+            wear_stats: Cell::new(None),
+            gc_wear_stats: Cell::new(WearStats::new()),
+            pending_region_header: Cell::new(RegionHeader {
+                erase_count: 0,
+                flags: 0xFF,
+            }),
+            ignore_wear: Cell::new(false),
+            pending_write: Cell::new(None),
         }
     }
 
@@ -120,9 +242,13 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
     /// If the specified region has not already been setup for TicKV
     /// the entire region will be erased.
     ///
+    /// If the region was setup by a version of TicKV without region headers
+    /// `ErrorCode::UnsupportedVersion` is returned and nothing is erased.
+    ///
     /// On success nothing will be returned.
     /// On error a `ErrorCode` will be returned.
     pub fn initialise(&self, hashed_main_key: u64) -> Result<SuccessCode, ErrorCode> {
+        self.main_key.set(hashed_main_key);
         let mut buf: [u8; 0] = [0; 0];
 
         let key_ret = match self.state.get() {
@@ -143,6 +269,11 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
                             .set(State::Init(InitState::GetKeyReadRegion(reg)));
                         Err(ErrorCode::ReadNotReady(reg))
                     }
+                    ErrorCode::UnsupportedVersion if self.read_without_region_header() => {
+                        // Don't erase data stored by an older version of TicKV
+                        self.state.set(State::None);
+                        Err(e)
+                    }
                     _ => {
                         match self.state.get() {
                             State::None
@@ -241,6 +372,141 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
         None
     }
 
+    /// Check if some loaded region data starts with the main key, as stored
+    /// by a version of TicKV without region headers.
+    fn main_key_without_region_header(&self, region_data: &[u8]) -> bool {
+        let hash = self.main_key.get().to_ne_bytes();
+
+        region_data.get(VERSION_OFFSET) == Some(&VERSION_WITHOUT_REGION_HEADER)
+            && (0..8).all(|i| region_data.get(HASH_OFFSET + i) == hash.get(7 - i))
+    }
+
+    /// Check if the region data last read starts with the main key, as stored
+    /// by a version of TicKV without region headers.
+    fn read_without_region_header(&self) -> bool {
+        let region_data = self.read_buffer.take().unwrap();
+        let ret = self.main_key_without_region_header(region_data);
+        self.read_buffer.replace(Some(region_data));
+        ret
+    }
+
+    /// Check if a region has been erased a lot more often than the least
+    /// worn region.
+    fn region_worn(&self, region_header: &RegionHeader) -> bool {
+        self.wear_stats.get().map_or(false, |stats| {
+            region_header.erase_count
+                > stats
+                    .min_erase_count
+                    .saturating_add(WEAR_LEVELING_THRESHOLD)
+        })
+    }
+
+    /// Mark a region as retired, so that it won't be written to or erased
+    /// again. `region_data` must start with the region header of the region.
+    fn retire_region(&self, region: usize, region_data: &mut [u8]) -> Result<(), ErrorCode> {
+        region_data[REGION_FLAGS_OFFSET] &= !(REGION_FLAGS_GOOD | REGION_FLAGS_TERMINAL);
+        // There is nothing left to do if retiring the region fails
+        self.pending_write.set(None);
+
+        self.controller.write(
+            S * region + REGION_FLAGS_OFFSET,
+            &region_data[REGION_FLAGS_OFFSET..REGION_HEADER_LENGTH],
+        )
+    }
+
+    /// Write the region header of a region that has just been erased.
+    fn write_region_header(&self, region: usize) -> Result<(), ErrorCode> {
+        let region_header = self.pending_region_header.get();
+
+        let mut stats = self.gc_wear_stats.get();
+        stats.min_erase_count = stats.min_erase_count.min(region_header.erase_count);
+        stats.max_erase_count = stats.max_erase_count.max(region_header.erase_count);
+
+        let mut buf = [0xFF; REGION_HEADER_LENGTH];
+        region_header.copy_to_region(&mut buf);
+
+        match self.controller.write(S * region, &buf) {
+            Ok(()) => {}
+            Err(ErrorCode::WriteNotReady(reg)) => {
+                self.state
+                    .set(State::GarbageCollect(RubbishState::WriteRegionHeader(
+                        region,
+                    )));
+                self.pending_write.set(Some(region));
+                self.gc_wear_stats.set(stats);
+                return Err(ErrorCode::WriteNotReady(reg));
+            }
+            Err(_) => {
+                // The region can't be written, so don't use it again.
+                // There isn't anything else to do if this fails as well.
+                let _ = self.retire_region(region, &mut buf);
+                stats.retired_regions += 1;
+            }
+        }
+
+        self.gc_wear_stats.set(stats);
+        Ok(())
+    }
+
+    /// Handle a write or erase that failed after the `FlashController`
+    /// returned `WriteNotReady` or `EraseNotReady`.
+    ///
+    /// The region that couldn't be written or erased is retired, as it would
+    /// have been if the `FlashController` had returned the error directly.
+    ///
+    /// Returns `Ok(())` if a garbage collection should continue with the
+    /// next region, or `WriteNotReady` if it continues once the region has
+    /// been retired. Any other operation has failed and its error is
+    /// returned.
+    pub(crate) fn flash_operation_failed(&self) -> Result<(), ErrorCode> {
+        let pending_write = self.pending_write.take();
+        let (region, error) = match self.state.get() {
+            State::GarbageCollect(RubbishState::EraseRegion(region)) => {
+                (Some(region), ErrorCode::EraseFail)
+            }
+            State::GarbageCollect(RubbishState::WriteRegionHeader(region)) => (
+                pending_write.filter(|pending| *pending == region),
+                ErrorCode::WriteFail,
+            ),
+            State::Init(InitState::EraseRegion(_)) => (None, ErrorCode::EraseFail),
+            _ => (pending_write, ErrorCode::WriteFail),
+        };
+
+        // The read buffer still contains the data of the region
+        let ret = match region {
+            Some(region) => {
+                let region_data = self.read_buffer.take().unwrap();
+                let ret = self.retire_region(region, region_data);
+                self.read_buffer.replace(Some(region_data));
+                ret
+            }
+            None => Ok(()),
+        };
+
+        match self.state.get() {
+            State::GarbageCollect(RubbishState::EraseRegion(reg))
+            | State::GarbageCollect(RubbishState::WriteRegionHeader(reg)) => {
+                if region.is_some() {
+                    let mut stats = self.gc_wear_stats.get();
+                    stats.retired_regions += 1;
+                    self.gc_wear_stats.set(stats);
+                }
+
+                // Move on to the next region
+                self.state
+                    .set(State::GarbageCollect(RubbishState::WriteRegionHeader(reg)));
+                match ret {
+                    Err(ErrorCode::WriteNotReady(reg)) => Err(ErrorCode::WriteNotReady(reg)),
+                    _ => Ok(()),
+                }
+            }
+            _ => {
+                self.state.set(State::None);
+                Err(error)
+            }
+        }
+    }
+
     /// Find a key in some loaded region data.
     ///
     /// On success return the offset in the region_data where the key is and the
@@ -257,13 +523,15 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
         // Split the hash
         let hash = hash.to_ne_bytes();
 
-        let mut offset: usize = 0;
+        let region_header = RegionHeader::new_from_region(region_data);
+        let mut offset: usize = REGION_HEADER_LENGTH;
         let mut empty: bool = true;
 
         loop {
             if offset + HEADER_LENGTH >= S {
-                // We have reached the end of the region
-                return Err((false, ErrorCode::KeyNotFound));
+                // We have reached the end of the region, as the region is
+                // full the key might have been added to another region.
+                return Err((true, ErrorCode::KeyNotFound));
             }
 
             // Check to see if we have data
@@ -355,8 +623,9 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
                 // If we get here we have found out value (assuming no collisions)
                 return Ok((offset, total_length));
             } else {
-                // We hit the end.
-                return Err((!empty, ErrorCode::KeyNotFound));
+                // We hit the end. Keep looking if the region isn't empty or
+                // keys might have been added past it.
+                return Err((!empty || !region_header.terminal(), ErrorCode::KeyNotFound));
             }
         }
     }
@@ -369,7 +638,22 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
     ///
     /// On success nothing will be returned.
     /// On error a `ErrorCode` will be returned.
+    ///
+    /// Keys are not added to retired regions. Keys are only added to worn
+    /// regions if there is no space anywhere else.
     pub fn append_key(&self, hash: u64, value: &[u8]) -> Result<SuccessCode, ErrorCode> {
+        let ret = self.append_object(hash, value);
+
+        if let Err(ErrorCode::ReadNotReady(_)) = ret {
+            // The operation will be continued
+        } else {
+            self.ignore_wear.set(false);
+        }
+
+        ret
+    }
+
+    fn append_object(&self, hash: u64, value: &[u8]) -> Result<SuccessCode, ErrorCode> {
         let region = self.get_region(hash);
         let mut check_sum = crc32::Crc32::new();
 
@@ -431,10 +715,20 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
                 return Err(ErrorCode::KeyAlreadyExists);
             }
 
-            let mut offset: usize = 0;
+            // Retired regions are never used. Worn regions are skipped unless
+            // there is no space anywhere else, but only if searches don't
+            // stop at them.
+            let region_header = RegionHeader::new_from_region(region_data);
+            let region_empty = region_data[REGION_HEADER_LENGTH + VERSION_OFFSET] == 0xFF;
+            let skip_region = region_header.retired()
+                || (!self.ignore_wear.get()
+                    && self.region_worn(&region_header)
+                    && (!region_empty || !region_header.terminal()));
+
+            let mut offset: usize = REGION_HEADER_LENGTH;
 
             loop {
-                if offset + package_length >= S {
+                if skip_region || offset + package_length >= S {
                     // We have reached the end of the region
                     // We will need to try the next region
 
@@ -446,9 +740,18 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
                             region_offset = o;
                         }
                         None => {
-                            return Err(ErrorCode::FlashFull);
+                            if self.ignore_wear.get() {
+                                return Err(ErrorCode::FlashFull);
+                            }
+
+                            // Try again, this time using worn regions
+                            self.ignore_wear.set(true);
+                            region_offset = 0;
                         }
                     }
+
+                    // The next region hasn't been read yet
+                    self.state.set(State::None);
                     break;
                 }
 
@@ -623,10 +926,20 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
                         .get(offset..(offset + package_length + CHECK_SUM_LEN))
                         .ok_or(ErrorCode::ObjectTooLarge)?,
                 ) {
-                    self.read_buffer.replace(Some(region_data));
                     match e {
-                        ErrorCode::WriteNotReady(_) => return Ok(SuccessCode::Queued),
-                        _ => return Err(e),
+                        ErrorCode::WriteNotReady(_) => {
+                            self.pending_write.set(Some(new_region as usize));
+                            self.read_buffer.replace(Some(region_data));
+                            return Ok(SuccessCode::Queued);
+                        }
+                        _ => {
+                            // Don't try to use this region again. There
+                            // isn't anything else to do if this fails as
+                            // well.
+                            let _ = self.retire_region(new_region as usize, region_data);
+                            self.read_buffer.replace(Some(region_data));
+                            return Err(e);
+                        }
                     }
                 }
 
@@ -690,6 +1003,16 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
                 };
             }
 
+            if hash == self.main_key.get()
+                && new_region as usize == region
+                && self.main_key_without_region_header(region_data)
+            {
+                // The flash was formatted by an older version of TicKV, which
+                // stored objects where the region header is now.
+                self.read_buffer.replace(Some(region_data));
+                return Err(ErrorCode::UnsupportedVersion);
+            }
+
             match self.find_key_offset(hash, region_data) {
                 Ok((offset, total_length)) => {
                     // Add the header data to the check hash
@@ -769,6 +1092,9 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
                                 return Err(e);
                             }
                         }
+
+                        // The next region hasn't been read yet
+                        self.state.set(State::None);
                     } else {
                         return Err(e);
                     }
@@ -835,7 +1161,10 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
                     ) {
                         self.read_buffer.replace(Some(region_data));
                         match e {
-                            ErrorCode::WriteNotReady(_) => return Ok(SuccessCode::Queued),
+                            ErrorCode::WriteNotReady(_) => {
+                                self.pending_write.set(None);
+                                return Ok(SuccessCode::Queued);
+                            }
                             _ => return Err(e),
                         }
                     }
@@ -855,6 +1184,9 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
                                 return Err(e);
                             }
                         }
+
+                        // The next region hasn't been read yet
+                        self.state.set(State::None);
                     } else {
                         return Err(e);
                     }
@@ -880,13 +1212,26 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
             };
         }
 
+        let region_header = RegionHeader::new_from_region(region_data);
+        let mut stats = self.gc_wear_stats.get();
+
+        if region_header.retired() {
+            // Retired regions are never erased
+            stats.retired_regions += 1;
+            self.gc_wear_stats.set(stats);
+            self.read_buffer.replace(Some(region_data));
+            return Ok(0);
+        }
+
         let mut entry_found = false;
-        let mut offset: usize = 0;
+        let mut erase = false;
+        let mut offset: usize = REGION_HEADER_LENGTH;
 
         loop {
             if offset >= S {
                 // We have reached the end of the region without finding a
                 // valid object. All entries must be marked for deletion then.
+                erase = true;
                 break;
             }
 
@@ -934,51 +1279,102 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
 
                 // We have found a valid entry!
                 // Don't perform an erase!
-                self.read_buffer.replace(Some(region_data));
-                return Ok(0);
+                break;
             } else {
                 // We hit the end of valid data.
                 // The possible outcomes:
                 //    * The region is empty, we don't need to do anything
                 //    * The region has entries, all of which are marked for
                 //      deletion
-                if !entry_found {
-                    // We didn't find anything, don't bother erasing an empty region.
-                    self.read_buffer.replace(Some(region_data));
-                    return Ok(0);
-                }
+                // If we didn't find anything, don't bother erasing an empty
+                // region.
+                erase = entry_found;
                 break;
             }
         }
 
-        self.read_buffer.replace(Some(region_data));
+        if !erase {
+            // The region won't be erased
+            stats.min_erase_count = stats.min_erase_count.min(region_header.erase_count);
+            stats.max_erase_count = stats.max_erase_count.max(region_header.erase_count);
+            self.gc_wear_stats.set(stats);
+            self.read_buffer.replace(Some(region_data));
+            return Ok(0);
+        }
 
         // If we got down here, the region is ready to be erased.
 
-        if let Err(e) = self.controller.erase_region(region) {
-            if let ErrorCode::EraseNotReady(reg) = e {
+        // The erase count is carried over to the new region header
+        let mut new_header = region_header;
+        new_header.erase_count = (region_header.erase_count + 1).min(REGION_ERASE_COUNT_MAX);
+        if self.region_worn(&new_header) {
+            // New keys will skip this region, so searches need to continue
+            // past it
+            new_header.flags &= !REGION_FLAGS_TERMINAL;
+        }
+        self.pending_region_header.set(new_header);
+
+        match self.controller.erase_region(region) {
+            Ok(()) => {
+                self.read_buffer.replace(Some(region_data));
+            }
+            Err(ErrorCode::EraseNotReady(reg)) => {
+                self.read_buffer.replace(Some(region_data));
                 self.state
                     .set(State::GarbageCollect(RubbishState::EraseRegion(reg)));
+                return Err(ErrorCode::EraseNotReady(reg));
+            }
+            Err(_) => {
+                // The region can't be erased, so don't use it again
+                let ret = self.retire_region(region, region_data);
+                self.read_buffer.replace(Some(region_data));
+
+                stats.retired_regions += 1;
+                self.gc_wear_stats.set(stats);
+
+                if let Err(ErrorCode::WriteNotReady(reg)) = ret {
+                    self.state
+                        .set(State::GarbageCollect(RubbishState::WriteRegionHeader(
+                            region,
+                        )));
+                    return Err(ErrorCode::WriteNotReady(reg));
+                }
+                return Ok(0);
             }
-            return Err(e);
         }
 
+        self.write_region_header(region)?;
+
         Ok(S)
     }
 
     /// Perform a garbage collection on TicKV
     ///
+    /// Erased regions keep their erase count. Regions that fail to erase are
+    /// retired. Once complete, the wear statistics returned by
+    /// `wear_stats()` are updated.
+    ///
     /// On success the number of bytes freed will be returned.
     /// On error a `ErrorCode` will be returned.
     pub fn garbage_collect(&self) -> Result<usize, ErrorCode> {
         let num_region = self.flash_size / S;
         let mut flash_freed = 0;
         let start = match self.state.get() {
-            State::None => 0,
+            State::None => {
+                self.gc_wear_stats.set(WearStats::new());
+                0
+            }
             State::GarbageCollect(state) => match state {
                 RubbishState::ReadRegion(reg) => reg,
-                // We already erased region reg, so move to the next one
-                RubbishState::EraseRegion(reg) => reg + 1,
+                // We already erased region reg, so write the region header
+                // and move to the next one
+                RubbishState::EraseRegion(reg) => {
+                    self.write_region_header(reg)?;
+                    reg + 1
+                }
+                // We already wrote the region header of region reg, so move
+                // to the next one
+                RubbishState::WriteRegionHeader(reg) => reg + 1,
             },
             _ => unreachable!(),
         };
@@ -990,6 +1386,135 @@ impl<'a, C: FlashController<S>, const S: usize> TicKV<'a, C, S> {
             }
         }
 
+        let mut stats = self.gc_wear_stats.get();
+        if stats.min_erase_count > stats.max_erase_count {
+            // All regions have been retired
+            stats.min_erase_count = 0;
+        }
+        self.wear_stats.set(Some(stats));
+
         Ok(flash_freed)
     }
+
+    /// Get the wear statistics of the regions.
+    ///
+    /// The statistics are collected by `garbage_collect()`, this returns
+    /// `None` until a garbage collection has completed. Wear leveling only
+    /// starts once the statistics are available.
+    pub fn wear_stats(&self) -> Option<WearStats> {
+        self.wear_stats.get()
+    }
+
+    /// Find the next valid key, starting at `cursor`.
+    ///
+    /// `cursor`: `0` to start at the beginning of the flash, otherwise the
+    ///           `next_cursor` of the previously returned `KeyEntry`.
+    ///
+    /// Keys are returned in the order they are stored in flash, which is not
+    /// the order they were added in. Keys that have been invalidated and the
+    /// main key are skipped. Adding or removing keys while iterating might
+    /// cause keys to be skipped or returned twice.
+    ///
+    /// On success the `KeyEntry` of the next valid key will be returned.
+    /// On error a `ErrorCode` will be returned. `KeyNotFound` indicates that
+    /// there are no more keys.
+    pub fn get_next_key(&self, cursor: usize) -> Result<KeyEntry, ErrorCode> {
+        let num_region = self.flash_size / S;
+        let mut region = match self.state.get() {
+            State::None => cursor / S,
+            State::IterateKeys(key_state) => match key_state {
+                KeyState::ReadRegion(reg) => reg,
+            },
+            _ => unreachable!(),
+        };
+        // Only the region the cursor points into is searched from an offset,
+        // the following regions are searched from the start.
+        let mut offset = if region == cursor / S {
+            (cursor % S).max(REGION_HEADER_LENGTH)
+        } else {
+            REGION_HEADER_LENGTH
+        };
+
+        while region < num_region {
+            // Get the data from that region
+            let mut region_data = self.read_buffer.take().unwrap();
+            if self.state.get() != State::IterateKeys(KeyState::ReadRegion(region)) {
+                match self.controller.read_region(region, 0, &mut region_data) {
+                    Ok(()) => {}
+                    Err(e) => {
+                        self.read_buffer.replace(Some(region_data));
+                        if let ErrorCode::ReadNotReady(reg) = e {
+                            self.state
+                                .set(State::IterateKeys(KeyState::ReadRegion(reg)));
+                        }
+                        return Err(e);
+                    }
+                };
+            }
+
+            loop {
+                if offset + HEADER_LENGTH >= S {
+                    // We have reached the end of the region
+                    break;
+                }
+
+                let version = *region_data
+                    .get(offset + VERSION_OFFSET)
+                    .ok_or(ErrorCode::CorruptData)?;
+
+                if version == 0xFF {
+                    // We hit the end of valid data
+                    break;
+                }
+
+                // We found a version, check that we support it
+                if version != VERSION {
+                    self.read_buffer.replace(Some(region_data));
+                    return Err(ErrorCode::UnsupportedVersion);
+                }
+
+                // Find this entries length
+                let len_flags = *region_data
+                    .get(offset + LEN_OFFSET)
+                    .ok_or(ErrorCode::CorruptData)?;
+                let total_length = ((len_flags as u16) & !0xF0) << 8
+                    | *region_data
+                        .get(offset + LEN_OFFSET + 1)
+                        .ok_or(ErrorCode::CorruptData)? as u16;
+
+                if (total_length as usize) < HEADER_LENGTH + CHECK_SUM_LEN {
+                    // We found something invalid here, skip the rest of the
+                    // region
+                    break;
+                }
+
+                let mut hash_bytes = [0; 8];
+                hash_bytes.copy_from_slice(
+                    region_data
+                        .get((offset + HASH_OFFSET)..(offset + HEADER_LENGTH))
+                        .ok_or(ErrorCode::CorruptData)?,
+                );
+                let hashed_key = u64::from_be_bytes(hash_bytes);
+
+                offset += total_length as usize;
+
+                // Check to see if the entry has been deleted
+                if len_flags & 0x80 == 0x80 && hashed_key != self.main_key.get() {
+                    self.read_buffer.replace(Some(region_data));
+                    return Ok(KeyEntry {
+                        hashed_key,
+                        value_length: total_length as usize - HEADER_LENGTH - CHECK_SUM_LEN,
+                        next_cursor: region * S + offset,
+                    });
+                }
+            }
+
+            self.read_buffer.replace(Some(region_data));
+
+            region += 1;
+            offset = REGION_HEADER_LENGTH;
+        }
+
+        Err(ErrorCode::KeyNotFound)
+    }
 }
@@ -2,8 +2,10 @@
 
 // Parsing the headers does not require any unsafe operations.
 #![forbid(unsafe_code)]
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 
 pub mod parse;
+#[cfg(feature = "std")]
+pub mod serialize;
 #[allow(dead_code)] // Some fields not read on device, but read when creating headers
 pub mod types;
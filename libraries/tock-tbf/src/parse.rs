@@ -68,6 +68,29 @@ pub fn parse_tbf_header_lengths(
     }
 }
 
+/// Compute the checksum of a v2 TBF header.
+///
+/// The checksum is the XOR of each 4 byte word in the header, skipping the
+/// checksum field itself. `header` must contain only the TBF header.
+pub fn compute_tbf_header_checksum(header: &[u8]) -> Result<u32, types::TbfParseError> {
+    let mut checksum: u32 = 0;
+
+    // Get an iterator across 4 byte fields in the header.
+    let header_iter = header.chunks_exact(4);
+
+    // Iterate all chunks and XOR the chunks to compute the checksum.
+    for (i, chunk) in header_iter.enumerate() {
+        let word = u32::from_le_bytes(chunk.try_into()?);
+        if i == 3 {
+            // Skip the checksum field.
+        } else {
+            checksum ^= word;
+        }
+    }
+
+    Ok(checksum)
+}
+
 /// Parse a TBF header stored in flash.
 ///
 /// The `header` must be a slice that only contains the TBF header. The caller
@@ -83,22 +106,7 @@ pub fn parse_tbf_header(
             // first bit of the header already in `parse_tbf_header_lengths()`.
             let tbf_header_base: types::TbfHeaderV2Base = header.try_into()?;
 
-            // Calculate checksum. The checksum is the XOR of each 4 byte word
-            // in the header.
-            let mut checksum: u32 = 0;
-
-            // Get an iterator across 4 byte fields in the header.
-            let header_iter = header.chunks_exact(4);
-
-            // Iterate all chunks and XOR the chunks to compute the checksum.
-            for (i, chunk) in header_iter.enumerate() {
-                let word = u32::from_le_bytes(chunk.try_into()?);
-                if i == 3 {
-                    // Skip the checksum field.
-                } else {
-                    checksum ^= word;
-                }
-            }
+            let checksum = compute_tbf_header_checksum(header)?;
 
             // Verify the header matches.
             if checksum != tbf_header_base.checksum {
@@ -126,6 +134,7 @@ pub fn parse_tbf_header(
                 // Places to save fields that we parse out of the header
                 // options.
                 let mut main_pointer: Option<types::TbfHeaderV2Main> = None;
+                let mut program_pointer: Option<types::TbfHeaderV2Program> = None;
                 let mut wfr_pointer: [Option<types::TbfHeaderV2WriteableFlashRegion>; 4] =
                     Default::default();
                 let mut app_name_str = "";
@@ -133,6 +142,9 @@ pub fn parse_tbf_header(
                 let mut permissions_pointer: Option<types::TbfHeaderV2Permissions<8>> = None;
                 let mut persistent_acls_pointer: Option<types::TbfHeaderV2PersistentAcl<8>> = None;
                 let mut kernel_version: Option<types::TbfHeaderV2KernelVersion> = None;
+                let mut timing_constraints: Option<types::TbfHeaderV2TimingConstraints> = None;
+                let mut ipc_allow_list: Option<types::TbfHeaderV2IpcAllowList<8>> = None;
+                let mut syscall_limits: Option<types::TbfHeaderV2SyscallLimits<8>> = None;
 
                 // Iterate the remainder of the header looking for TLV entries.
                 while remaining.len() > 0 {
@@ -168,6 +180,22 @@ pub fn parse_tbf_header(
                             }
                         }
 
+                        types::TbfHeaderTypes::TbfHeaderProgram => {
+                            let entry_len = mem::size_of::<types::TbfHeaderV2Program>();
+                            if tlv_header.length as usize == entry_len {
+                                program_pointer = Some(
+                                    remaining
+                                        .get(0..entry_len)
+                                        .ok_or(types::TbfParseError::NotEnoughFlash)?
+                                        .try_into()?,
+                                );
+                            } else {
+                                return Err(types::TbfParseError::BadTlvEntry(
+                                    tlv_header.tipe as usize,
+                                ));
+                            }
+                        }
+
                         types::TbfHeaderTypes::TbfHeaderWriteableFlashRegions => {
                             // Length must be a multiple of the size of a region definition.
                             if tlv_header.length as usize
@@ -259,6 +287,40 @@ pub fn parse_tbf_header(
                             }
                         }
 
+                        types::TbfHeaderTypes::TbfHeaderTimingConstraints => {
+                            let entry_len = mem::size_of::<types::TbfHeaderV2TimingConstraints>();
+                            if tlv_header.length as usize == entry_len {
+                                timing_constraints = Some(
+                                    remaining
+                                        .get(0..entry_len)
+                                        .ok_or(types::TbfParseError::NotEnoughFlash)?
+                                        .try_into()?,
+                                );
+                            } else {
+                                return Err(types::TbfParseError::BadTlvEntry(
+                                    tlv_header.tipe as usize,
+                                ));
+                            }
+                        }
+
+                        types::TbfHeaderTypes::TbfHeaderIpcAllowList => {
+                            ipc_allow_list = Some(
+                                remaining
+                                    .get(0..tlv_header.length as usize)
+                                    .ok_or(types::TbfParseError::NotEnoughFlash)?
+                                    .try_into()?,
+                            );
+                        }
+
+                        types::TbfHeaderTypes::TbfHeaderSyscallLimits => {
+                            syscall_limits = Some(
+                                remaining
+                                    .get(0..tlv_header.length as usize)
+                                    .ok_or(types::TbfParseError::NotEnoughFlash)?
+                                    .try_into()?,
+                            );
+                        }
+
                         _ => {}
                     }
 
@@ -270,20 +332,68 @@ pub fn parse_tbf_header(
                         .ok_or(types::TbfParseError::NotEnoughFlash)?;
                 }
 
-                let tbf_header = types::TbfHeaderV2 {
+                let tbf_header_v2 = types::TbfHeaderV2 {
                     base: tbf_header_base,
                     main: main_pointer,
+                    program: program_pointer,
                     package_name: Some(app_name_str),
                     writeable_regions: Some(wfr_pointer),
                     fixed_addresses: fixed_address_pointer,
                     permissions: permissions_pointer,
                     persistent_acls: persistent_acls_pointer,
                     kernel_version: kernel_version,
+                    timing_constraints: timing_constraints,
+                    ipc_allow_list: ipc_allow_list,
+                    syscall_limits: syscall_limits,
                 };
 
-                Ok(types::TbfHeader::TbfHeaderV2(tbf_header))
+                let tbf_header = types::TbfHeader::TbfHeaderV2(tbf_header_v2);
+
+                // The footers start at the end of the binary, so the binary
+                // must end within the TBF object and after the header.
+                let binary_end = tbf_header.get_binary_end();
+                if binary_end > tbf_header_base.total_size
+                    || binary_end < tbf_header_base.header_size as u32
+                {
+                    return Err(types::TbfParseError::BadTlvEntry(
+                        types::TbfHeaderTypes::TbfHeaderProgram as usize,
+                    ));
+                }
+
+                Ok(tbf_header)
             }
         }
         _ => Err(types::TbfParseError::UnsupportedVersion(version)),
     }
 }
+
+/// Parse the first footer in `footers`, the region of a TBF object between
+/// the end of the application binary and the end of the object.
+///
+/// ## Return
+///
+/// If the footer parses, returns its credentials and the number of bytes it
+/// occupies (including its TLV header and padding), so that the caller can
+/// advance to the next footer. Returns `NotEnoughFlash` if `footers` does not
+/// hold a complete footer and `BadTlvEntry` if the footer is not a
+/// credentials footer or its credentials are malformed.
+pub fn parse_tbf_footer(
+    footers: &'static [u8],
+) -> Result<(types::TbfFooterV2Credentials, u32), types::TbfParseError> {
+    let tlv_header: types::TbfHeaderTlv = footers
+        .get(0..4)
+        .ok_or(types::TbfParseError::NotEnoughFlash)?
+        .try_into()?;
+
+    match tlv_header.tipe {
+        types::TbfHeaderTypes::TbfFooterCredentials => {
+            let credentials = footers
+                .get(4..4 + tlv_header.length as usize)
+                .ok_or(types::TbfParseError::NotEnoughFlash)?
+                .try_into()?;
+            let footer_len = align4!(4 + tlv_header.length as u32);
+            Ok((credentials, footer_len))
+        }
+        _ => Err(types::TbfParseError::BadTlvEntry(tlv_header.tipe as usize)),
+    }
+}
@@ -0,0 +1,937 @@
+//! Tock Binary Format serialization code.
+//!
+//! This is only available with the `std` feature, and is meant for host tools
+//! that create or modify TBF objects. The kernel only ever parses headers.
+//!
+//! A header is held as a [`TbfHeaderV2Builder`], an ordered list of TLV
+//! entries along with the fields of the base header. The builder computes the
+//! header size and checksum when it is serialized, so tools only need to edit
+//! the entries they care about.
+
+use std::convert::TryInto;
+use std::mem::size_of;
+use std::str;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::parse;
+use crate::types::*;
+
+/// Size of the fields in the base header that all v2 headers start with.
+pub const TBF_HEADER_V2_BASE_SIZE: usize = 16;
+
+/// Flag in the base header that marks the process as enabled.
+pub const TBF_FLAG_ENABLED: u32 = 0x00000001;
+
+/// Flag in the base header that marks the process as sticky, i.e. it should
+/// not be removed by tools that manage apps on a board.
+pub const TBF_FLAG_STICKY: u32 = 0x00000002;
+
+/// Maximum number of driver permissions, persistent ACL ids, IPC client ids and
+/// syscall limits the kernel parses from a header.
+pub const NUM_HEADER_ENTRIES: usize = 8;
+
+/// Types that can be written as the value of a TBF TLV entry.
+pub trait TbfSerialize {
+    /// Append the little-endian encoding of this entry to `buf`. This does not
+    /// include the TLV header or padding.
+    fn serialize_value(&self, buf: &mut Vec<u8>);
+}
+
+impl TbfHeaderV2Main {
+    pub fn new(init_fn_offset: u32, protected_size: u32, minimum_ram_size: u32) -> Self {
+        TbfHeaderV2Main {
+            init_fn_offset,
+            protected_size,
+            minimum_ram_size,
+        }
+    }
+
+    pub fn init_fn_offset(&self) -> u32 {
+        self.init_fn_offset
+    }
+
+    pub fn protected_size(&self) -> u32 {
+        self.protected_size
+    }
+
+    pub fn minimum_ram_size(&self) -> u32 {
+        self.minimum_ram_size
+    }
+}
+
+impl TbfSerialize for TbfHeaderV2Main {
+    fn serialize_value(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.init_fn_offset.to_le_bytes());
+        buf.extend_from_slice(&self.protected_size.to_le_bytes());
+        buf.extend_from_slice(&self.minimum_ram_size.to_le_bytes());
+    }
+}
+
+impl TbfHeaderV2Program {
+    pub fn new(
+        init_fn_offset: u32,
+        protected_trailer_size: u32,
+        minimum_ram_size: u32,
+        binary_end_offset: u32,
+        version: u32,
+    ) -> Self {
+        TbfHeaderV2Program {
+            init_fn_offset,
+            protected_trailer_size,
+            minimum_ram_size,
+            binary_end_offset,
+            version,
+        }
+    }
+
+    pub fn init_fn_offset(&self) -> u32 {
+        self.init_fn_offset
+    }
+
+    pub fn protected_trailer_size(&self) -> u32 {
+        self.protected_trailer_size
+    }
+
+    pub fn minimum_ram_size(&self) -> u32 {
+        self.minimum_ram_size
+    }
+
+    pub fn binary_end_offset(&self) -> u32 {
+        self.binary_end_offset
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+impl TbfSerialize for TbfHeaderV2Program {
+    fn serialize_value(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.init_fn_offset.to_le_bytes());
+        buf.extend_from_slice(&self.protected_trailer_size.to_le_bytes());
+        buf.extend_from_slice(&self.minimum_ram_size.to_le_bytes());
+        buf.extend_from_slice(&self.binary_end_offset.to_le_bytes());
+        buf.extend_from_slice(&self.version.to_le_bytes());
+    }
+}
+
+impl TbfHeaderV2WriteableFlashRegion {
+    pub fn new(offset: u32, size: u32) -> Self {
+        TbfHeaderV2WriteableFlashRegion {
+            writeable_flash_region_offset: offset,
+            writeable_flash_region_size: size,
+        }
+    }
+
+    pub fn offset(&self) -> u32 {
+        self.writeable_flash_region_offset
+    }
+
+    pub fn size(&self) -> u32 {
+        self.writeable_flash_region_size
+    }
+}
+
+impl TbfSerialize for TbfHeaderV2WriteableFlashRegion {
+    fn serialize_value(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.writeable_flash_region_offset.to_le_bytes());
+        buf.extend_from_slice(&self.writeable_flash_region_size.to_le_bytes());
+    }
+}
+
+impl TbfHeaderV2FixedAddresses {
+    /// Create a fixed addresses entry. Use `0xFFFFFFFF` for an address the
+    /// process does not depend on.
+    pub fn new(start_process_ram: u32, start_process_flash: u32) -> Self {
+        TbfHeaderV2FixedAddresses {
+            start_process_ram,
+            start_process_flash,
+        }
+    }
+
+    pub fn start_process_ram(&self) -> u32 {
+        self.start_process_ram
+    }
+
+    pub fn start_process_flash(&self) -> u32 {
+        self.start_process_flash
+    }
+}
+
+impl TbfSerialize for TbfHeaderV2FixedAddresses {
+    fn serialize_value(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.start_process_ram.to_le_bytes());
+        buf.extend_from_slice(&self.start_process_flash.to_le_bytes());
+    }
+}
+
+impl TbfHeaderDriverPermission {
+    pub fn new(driver_number: u32, offset: u32, allowed_commands: u64) -> Self {
+        TbfHeaderDriverPermission {
+            driver_number,
+            offset,
+            allowed_commands,
+        }
+    }
+
+    pub fn driver_number(&self) -> u32 {
+        self.driver_number
+    }
+
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    pub fn allowed_commands(&self) -> u64 {
+        self.allowed_commands
+    }
+}
+
+impl TbfSerialize for TbfHeaderDriverPermission {
+    fn serialize_value(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.driver_number.to_le_bytes());
+        buf.extend_from_slice(&self.offset.to_le_bytes());
+        buf.extend_from_slice(&self.allowed_commands.to_le_bytes());
+    }
+}
+
+impl<const L: usize> TbfHeaderV2Permissions<L> {
+    /// Create a permissions entry. Returns `None` if there are more than `L`
+    /// permissions.
+    pub fn new(perms: &[TbfHeaderDriverPermission]) -> Option<Self> {
+        if perms.len() > L {
+            return None;
+        }
+        let mut all_perms = [TbfHeaderDriverPermission::default(); L];
+        all_perms[..perms.len()].copy_from_slice(perms);
+        Some(TbfHeaderV2Permissions {
+            length: perms.len() as u16,
+            perms: all_perms,
+        })
+    }
+
+    pub fn perms(&self) -> &[TbfHeaderDriverPermission] {
+        &self.perms[..self.length as usize]
+    }
+}
+
+impl<const L: usize> TbfSerialize for TbfHeaderV2Permissions<L> {
+    fn serialize_value(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.length.to_le_bytes());
+        for perm in self.perms() {
+            perm.serialize_value(buf);
+        }
+    }
+}
+
+impl<const L: usize> TbfHeaderV2PersistentAcl<L> {
+    /// Create a persistent ACL entry. Returns `None` if there are more than
+    /// `L` read or access ids.
+    pub fn new(write_id: u32, read_ids: &[u32], access_ids: &[u32]) -> Option<Self> {
+        if read_ids.len() > L || access_ids.len() > L {
+            return None;
+        }
+        let mut acl = TbfHeaderV2PersistentAcl {
+            write_id,
+            read_length: read_ids.len() as u16,
+            read_ids: [0; L],
+            access_length: access_ids.len() as u16,
+            access_ids: [0; L],
+        };
+        acl.read_ids[..read_ids.len()].copy_from_slice(read_ids);
+        acl.access_ids[..access_ids.len()].copy_from_slice(access_ids);
+        Some(acl)
+    }
+
+    pub fn write_id(&self) -> u32 {
+        self.write_id
+    }
+
+    pub fn read_ids(&self) -> &[u32] {
+        &self.read_ids[..self.read_length as usize]
+    }
+
+    pub fn access_ids(&self) -> &[u32] {
+        &self.access_ids[..self.access_length as usize]
+    }
+}
+
+impl<const L: usize> TbfSerialize for TbfHeaderV2PersistentAcl<L> {
+    fn serialize_value(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.write_id.to_le_bytes());
+        buf.extend_from_slice(&self.read_length.to_le_bytes());
+        for id in self.read_ids() {
+            buf.extend_from_slice(&id.to_le_bytes());
+        }
+        buf.extend_from_slice(&self.access_length.to_le_bytes());
+        for id in self.access_ids() {
+            buf.extend_from_slice(&id.to_le_bytes());
+        }
+    }
+}
+
+impl TbfHeaderV2KernelVersion {
+    pub fn new(major: u16, minor: u16) -> Self {
+        TbfHeaderV2KernelVersion { major, minor }
+    }
+
+    pub fn major(&self) -> u16 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u16 {
+        self.minor
+    }
+}
+
+impl TbfSerialize for TbfHeaderV2KernelVersion {
+    fn serialize_value(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.major.to_le_bytes());
+        buf.extend_from_slice(&self.minor.to_le_bytes());
+    }
+}
+
+impl TbfHeaderV2TimingConstraints {
+    /// Create a timing constraints entry. A `deadline_us` of 0 means the
+    /// deadline is the end of the period.
+    pub fn new(period_us: u32, deadline_us: u32) -> Self {
+        TbfHeaderV2TimingConstraints {
+            period_us,
+            deadline_us,
+        }
+    }
+
+    pub fn period_us(&self) -> u32 {
+        self.period_us
+    }
+
+    pub fn deadline_us(&self) -> u32 {
+        self.deadline_us
+    }
+}
+
+impl TbfSerialize for TbfHeaderV2TimingConstraints {
+    fn serialize_value(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.period_us.to_le_bytes());
+        buf.extend_from_slice(&self.deadline_us.to_le_bytes());
+    }
+}
+
+impl<const L: usize> TbfHeaderV2IpcAllowList<L> {
+    /// Create an IPC allow list entry. Returns `None` if there are more than
+    /// `L` client ids.
+    pub fn new(client_ids: &[u32]) -> Option<Self> {
+        if client_ids.len() > L {
+            return None;
+        }
+        let mut allow_list = TbfHeaderV2IpcAllowList {
+            length: client_ids.len() as u16,
+            client_ids: [0; L],
+        };
+        allow_list.client_ids[..client_ids.len()].copy_from_slice(client_ids);
+        Some(allow_list)
+    }
+
+    pub fn client_ids(&self) -> &[u32] {
+        &self.client_ids[..self.length as usize]
+    }
+}
+
+impl<const L: usize> TbfSerialize for TbfHeaderV2IpcAllowList<L> {
+    fn serialize_value(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.length.to_le_bytes());
+        for id in self.client_ids() {
+            buf.extend_from_slice(&id.to_le_bytes());
+        }
+    }
+}
+
+impl TbfHeaderDriverLimit {
+    pub fn new(
+        driver_number: u32,
+        command_number: u16,
+        max_calls: u16,
+        period_ms: u32,
+        quota: u32,
+    ) -> Self {
+        TbfHeaderDriverLimit {
+            driver_number,
+            command_number,
+            max_calls,
+            period_ms,
+            quota,
+        }
+    }
+}
+
+impl TbfSerialize for TbfHeaderDriverLimit {
+    fn serialize_value(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.driver_number.to_le_bytes());
+        buf.extend_from_slice(&self.command_number.to_le_bytes());
+        buf.extend_from_slice(&self.max_calls.to_le_bytes());
+        buf.extend_from_slice(&self.period_ms.to_le_bytes());
+        buf.extend_from_slice(&self.quota.to_le_bytes());
+    }
+}
+
+impl<const L: usize> TbfHeaderV2SyscallLimits<L> {
+    /// Create a syscall limits entry. Returns `None` if there are more than
+    /// `L` limits.
+    pub fn new(limits: &[TbfHeaderDriverLimit]) -> Option<Self> {
+        if limits.len() > L {
+            return None;
+        }
+        let mut all_limits = [TbfHeaderDriverLimit::default(); L];
+        all_limits[..limits.len()].copy_from_slice(limits);
+        Some(TbfHeaderV2SyscallLimits {
+            length: limits.len() as u16,
+            limits: all_limits,
+        })
+    }
+
+    pub fn limits(&self) -> &[TbfHeaderDriverLimit] {
+        &self.limits[..self.length as usize]
+    }
+}
+
+impl<const L: usize> TbfSerialize for TbfHeaderV2SyscallLimits<L> {
+    fn serialize_value(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.length.to_le_bytes());
+        for limit in self.limits() {
+            limit.serialize_value(buf);
+        }
+    }
+}
+
+/// One TLV entry of a v2 TBF header.
+#[derive(Clone, Debug)]
+pub enum TbfHeaderV2Entry {
+    Main(TbfHeaderV2Main),
+    Program(TbfHeaderV2Program),
+    WriteableFlashRegions(Vec<TbfHeaderV2WriteableFlashRegion>),
+    PackageName(String),
+    FixedAddresses(TbfHeaderV2FixedAddresses),
+    Permissions(TbfHeaderV2Permissions<NUM_HEADER_ENTRIES>),
+    PersistentAcl(TbfHeaderV2PersistentAcl<NUM_HEADER_ENTRIES>),
+    KernelVersion(TbfHeaderV2KernelVersion),
+    TimingConstraints(TbfHeaderV2TimingConstraints),
+    IpcAllowList(TbfHeaderV2IpcAllowList<NUM_HEADER_ENTRIES>),
+    SyscallLimits(TbfHeaderV2SyscallLimits<NUM_HEADER_ENTRIES>),
+    /// An entry this library does not know about. These are kept as-is so
+    /// that modifying a header does not drop them.
+    Unknown(u16, Vec<u8>),
+}
+
+impl TbfHeaderV2Entry {
+    /// The "tipe" field of this entry's TLV header.
+    pub fn tipe(&self) -> u16 {
+        match self {
+            TbfHeaderV2Entry::Main(_) => TbfHeaderTypes::TbfHeaderMain as u16,
+            TbfHeaderV2Entry::Program(_) => TbfHeaderTypes::TbfHeaderProgram as u16,
+            TbfHeaderV2Entry::WriteableFlashRegions(_) => {
+                TbfHeaderTypes::TbfHeaderWriteableFlashRegions as u16
+            }
+            TbfHeaderV2Entry::PackageName(_) => TbfHeaderTypes::TbfHeaderPackageName as u16,
+            TbfHeaderV2Entry::FixedAddresses(_) => TbfHeaderTypes::TbfHeaderFixedAddresses as u16,
+            TbfHeaderV2Entry::Permissions(_) => TbfHeaderTypes::TbfHeaderPermissions as u16,
+            TbfHeaderV2Entry::PersistentAcl(_) => TbfHeaderTypes::TbfHeaderPersistentAcl as u16,
+            TbfHeaderV2Entry::KernelVersion(_) => TbfHeaderTypes::TbfHeaderKernelVersion as u16,
+            TbfHeaderV2Entry::TimingConstraints(_) => {
+                TbfHeaderTypes::TbfHeaderTimingConstraints as u16
+            }
+            TbfHeaderV2Entry::IpcAllowList(_) => TbfHeaderTypes::TbfHeaderIpcAllowList as u16,
+            TbfHeaderV2Entry::SyscallLimits(_) => TbfHeaderTypes::TbfHeaderSyscallLimits as u16,
+            TbfHeaderV2Entry::Unknown(tipe, _) => *tipe,
+        }
+    }
+
+    /// Parse the value of a TLV entry of type `tipe`. `value` must hold
+    /// exactly the number of bytes given by the entry's TLV length.
+    pub fn parse(tipe: u16, value: &[u8]) -> Result<TbfHeaderV2Entry, TbfParseError> {
+        let bad_entry = TbfParseError::BadTlvEntry(tipe as usize);
+        let fixed_size = |size: usize| {
+            if value.len() == size {
+                Ok(value)
+            } else {
+                Err(TbfParseError::BadTlvEntry(tipe as usize))
+            }
+        };
+
+        match tipe.try_into()? {
+            TbfHeaderTypes::TbfHeaderMain => Ok(TbfHeaderV2Entry::Main(
+                fixed_size(size_of::<TbfHeaderV2Main>())?.try_into()?,
+            )),
+            TbfHeaderTypes::TbfHeaderProgram => Ok(TbfHeaderV2Entry::Program(
+                fixed_size(size_of::<TbfHeaderV2Program>())?.try_into()?,
+            )),
+            TbfHeaderTypes::TbfHeaderWriteableFlashRegions => {
+                let wfr_len = size_of::<TbfHeaderV2WriteableFlashRegion>();
+                if value.len() % wfr_len != 0 {
+                    return Err(bad_entry);
+                }
+                let regions = value
+                    .chunks_exact(wfr_len)
+                    .map(|chunk| chunk.try_into())
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(TbfHeaderV2Entry::WriteableFlashRegions(regions))
+            }
+            TbfHeaderTypes::TbfHeaderPackageName => str::from_utf8(value)
+                .map(|name| TbfHeaderV2Entry::PackageName(String::from(name)))
+                .or(Err(TbfParseError::BadProcessName)),
+            TbfHeaderTypes::TbfHeaderFixedAddresses => Ok(TbfHeaderV2Entry::FixedAddresses(
+                fixed_size(size_of::<TbfHeaderV2FixedAddresses>())?.try_into()?,
+            )),
+            TbfHeaderTypes::TbfHeaderPermissions => {
+                Ok(TbfHeaderV2Entry::Permissions(value.try_into()?))
+            }
+            TbfHeaderTypes::TbfHeaderPersistentAcl => {
+                Ok(TbfHeaderV2Entry::PersistentAcl(value.try_into()?))
+            }
+            TbfHeaderTypes::TbfHeaderKernelVersion => Ok(TbfHeaderV2Entry::KernelVersion(
+                fixed_size(size_of::<TbfHeaderV2KernelVersion>())?.try_into()?,
+            )),
+            TbfHeaderTypes::TbfHeaderTimingConstraints => Ok(TbfHeaderV2Entry::TimingConstraints(
+                fixed_size(size_of::<TbfHeaderV2TimingConstraints>())?.try_into()?,
+            )),
+            TbfHeaderTypes::TbfHeaderIpcAllowList => {
+                Ok(TbfHeaderV2Entry::IpcAllowList(value.try_into()?))
+            }
+            TbfHeaderTypes::TbfHeaderSyscallLimits => {
+                Ok(TbfHeaderV2Entry::SyscallLimits(value.try_into()?))
+            }
+            // Footers cannot appear in the header.
+            TbfHeaderTypes::TbfFooterCredentials => Err(bad_entry),
+            TbfHeaderTypes::Unknown => Ok(TbfHeaderV2Entry::Unknown(tipe, value.to_vec())),
+        }
+    }
+
+    /// Append the value of this entry to `buf`, without the TLV header or
+    /// padding.
+    pub fn serialize_value(&self, buf: &mut Vec<u8>) {
+        match self {
+            TbfHeaderV2Entry::Main(main) => main.serialize_value(buf),
+            TbfHeaderV2Entry::Program(program) => program.serialize_value(buf),
+            TbfHeaderV2Entry::WriteableFlashRegions(regions) => {
+                for region in regions {
+                    region.serialize_value(buf);
+                }
+            }
+            TbfHeaderV2Entry::PackageName(name) => buf.extend_from_slice(name.as_bytes()),
+            TbfHeaderV2Entry::FixedAddresses(fixed) => fixed.serialize_value(buf),
+            TbfHeaderV2Entry::Permissions(permissions) => permissions.serialize_value(buf),
+            TbfHeaderV2Entry::PersistentAcl(acl) => acl.serialize_value(buf),
+            TbfHeaderV2Entry::KernelVersion(version) => version.serialize_value(buf),
+            TbfHeaderV2Entry::TimingConstraints(timing) => timing.serialize_value(buf),
+            TbfHeaderV2Entry::IpcAllowList(allow_list) => allow_list.serialize_value(buf),
+            TbfHeaderV2Entry::SyscallLimits(limits) => limits.serialize_value(buf),
+            TbfHeaderV2Entry::Unknown(_, value) => buf.extend_from_slice(value),
+        }
+    }
+
+    /// Append this entry to `buf` as a complete TLV entry, padded to a
+    /// multiple of 4 bytes.
+    pub fn serialize(&self, buf: &mut Vec<u8>) {
+        let mut value = Vec::new();
+        self.serialize_value(&mut value);
+
+        buf.extend_from_slice(&self.tipe().to_le_bytes());
+        buf.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        buf.extend_from_slice(&value);
+        buf.resize(buf.len() + (4 - value.len() % 4) % 4, 0);
+    }
+}
+
+/// An editable v2 TBF header.
+#[derive(Clone, Debug)]
+pub struct TbfHeaderV2Builder {
+    /// Size of the entire TBF object, including the header.
+    pub total_size: u32,
+    /// Flags of the process, see `TBF_FLAG_ENABLED` and `TBF_FLAG_STICKY`.
+    pub flags: u32,
+    /// The TLV entries of the header, in the order they are serialized.
+    pub entries: Vec<TbfHeaderV2Entry>,
+}
+
+impl TbfHeaderV2Builder {
+    pub fn new(total_size: u32, flags: u32) -> TbfHeaderV2Builder {
+        TbfHeaderV2Builder {
+            total_size,
+            flags,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Parse a v2 TBF header. `header` must start at the beginning of the TBF
+    /// object, and may extend beyond the end of the header. If
+    /// `verify_checksum` is false a header with a wrong checksum is still
+    /// parsed, which is useful to repair it.
+    pub fn parse(
+        header: &[u8],
+        verify_checksum: bool,
+    ) -> Result<TbfHeaderV2Builder, TbfParseError> {
+        let base: TbfHeaderV2Base = header
+            .get(0..TBF_HEADER_V2_BASE_SIZE)
+            .ok_or(TbfParseError::NotEnoughFlash)?
+            .try_into()?;
+        if base.version != 2 {
+            return Err(TbfParseError::UnsupportedVersion(base.version));
+        }
+
+        let header = header
+            .get(0..base.header_size as usize)
+            .ok_or(TbfParseError::NotEnoughFlash)?;
+        let checksum = parse::compute_tbf_header_checksum(header)?;
+        if verify_checksum && checksum != base.checksum {
+            return Err(TbfParseError::ChecksumMismatch(base.checksum, checksum));
+        }
+
+        let mut builder = TbfHeaderV2Builder::new(base.total_size, base.flags);
+        let mut remaining = header
+            .get(TBF_HEADER_V2_BASE_SIZE..)
+            .ok_or(TbfParseError::NotEnoughFlash)?;
+        while !remaining.is_empty() {
+            // Unknown entries must keep their type, so read the TLV header
+            // directly rather than as a `TbfHeaderTlv`.
+            let tipe = u16::from_le_bytes(
+                remaining
+                    .get(0..2)
+                    .ok_or(TbfParseError::NotEnoughFlash)?
+                    .try_into()?,
+            );
+            let length = u16::from_le_bytes(
+                remaining
+                    .get(2..4)
+                    .ok_or(TbfParseError::NotEnoughFlash)?
+                    .try_into()?,
+            ) as usize;
+            let value = remaining
+                .get(4..4 + length)
+                .ok_or(TbfParseError::NotEnoughFlash)?;
+            builder.entries.push(TbfHeaderV2Entry::parse(tipe, value)?);
+
+            // All TLV blocks are padded to 4 bytes.
+            let skip_len = 4 + length + (4 - length % 4) % 4;
+            remaining = remaining
+                .get(skip_len..)
+                .ok_or(TbfParseError::NotEnoughFlash)?;
+        }
+
+        Ok(builder)
+    }
+
+    /// The size of the serialized header, in bytes.
+    pub fn header_size(&self) -> usize {
+        self.serialize().len()
+    }
+
+    /// Serialize the header, filling in the header size and checksum.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&2u16.to_le_bytes());
+        // Header size and checksum are filled in below.
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&self.total_size.to_le_bytes());
+        header.extend_from_slice(&self.flags.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        for entry in &self.entries {
+            entry.serialize(&mut header);
+        }
+
+        let header_size = header.len() as u16;
+        header[2..4].copy_from_slice(&header_size.to_le_bytes());
+        // The header is a multiple of 4 bytes, so this cannot fail.
+        let checksum = parse::compute_tbf_header_checksum(&header).unwrap_or(0);
+        header[12..16].copy_from_slice(&checksum.to_le_bytes());
+        header
+    }
+
+    /// Replace the first entry with the same type as `entry`, or add `entry`
+    /// at the end of the header if there is none.
+    pub fn set_entry(&mut self, entry: TbfHeaderV2Entry) {
+        match self.entries.iter_mut().find(|e| e.tipe() == entry.tipe()) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// Remove all entries of type `tipe`.
+    pub fn remove_entries(&mut self, tipe: TbfHeaderTypes) {
+        self.entries.retain(|e| e.tipe() != tipe as u16);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::boxed::Box;
+
+    /// Serialize `entries` into a header and parse it as the kernel does.
+    fn roundtrip(entries: Vec<TbfHeaderV2Entry>) -> (Vec<u8>, TbfHeader) {
+        let mut builder = TbfHeaderV2Builder::new(0x1000, TBF_FLAG_ENABLED);
+        builder.entries = entries;
+        let bytes = builder.serialize();
+        assert_eq!(bytes.len(), builder.header_size());
+        assert_eq!(bytes.len() % 4, 0);
+        assert_eq!(
+            u16::from_le_bytes([bytes[2], bytes[3]]) as usize,
+            bytes.len()
+        );
+
+        // The builder must parse its own output back into the same header.
+        let reparsed = TbfHeaderV2Builder::parse(&bytes, true).unwrap();
+        assert_eq!(reparsed.total_size, 0x1000);
+        assert_eq!(reparsed.flags, TBF_FLAG_ENABLED);
+        assert_eq!(reparsed.serialize(), bytes);
+
+        let header: &'static [u8] = Box::leak(bytes.clone().into_boxed_slice());
+        (bytes, parse::parse_tbf_header(header, 2).unwrap())
+    }
+
+    fn main() -> TbfHeaderV2Entry {
+        TbfHeaderV2Entry::Main(TbfHeaderV2Main::new(0x40, 0x10, 4096))
+    }
+
+    #[test]
+    fn main_roundtrip() {
+        let (bytes, header) = roundtrip(vec![main()]);
+        let header_size = bytes.len() as u32;
+        assert!(header.is_app());
+        assert!(header.enabled());
+        assert_eq!(header.get_init_function_offset(), 0x40 + header_size);
+        assert_eq!(header.get_protected_size(), 0x10 + header_size);
+        assert_eq!(header.get_minimum_app_ram_size(), 4096);
+        assert_eq!(header.get_binary_end(), 0x1000);
+        assert_eq!(header.get_binary_version(), 0);
+    }
+
+    #[test]
+    fn program_roundtrip() {
+        let program = TbfHeaderV2Program::new(0x20, 0x8, 2048, 0x800, 7);
+        let (bytes, header) = roundtrip(vec![TbfHeaderV2Entry::Program(program)]);
+        let header_size = bytes.len() as u32;
+        assert_eq!(header.get_init_function_offset(), 0x20 + header_size);
+        assert_eq!(header.get_protected_size(), 0x8 + header_size);
+        assert_eq!(header.get_minimum_app_ram_size(), 2048);
+        assert_eq!(header.get_binary_end(), 0x800);
+        assert_eq!(header.get_binary_version(), 7);
+    }
+
+    #[test]
+    fn writeable_flash_regions_roundtrip() {
+        let regions = vec![
+            TbfHeaderV2WriteableFlashRegion::new(0x200, 0x100),
+            TbfHeaderV2WriteableFlashRegion::new(0x400, 0x200),
+        ];
+        let (_, header) = roundtrip(vec![
+            main(),
+            TbfHeaderV2Entry::WriteableFlashRegions(regions),
+        ]);
+        assert_eq!(header.number_writeable_flash_regions(), 2);
+        assert_eq!(header.get_writeable_flash_region(0), (0x200, 0x100));
+        assert_eq!(header.get_writeable_flash_region(1), (0x400, 0x200));
+        assert_eq!(header.get_writeable_flash_region(2), (0, 0));
+    }
+
+    #[test]
+    fn package_name_roundtrip() {
+        // Names that need every amount of padding, followed by another
+        // entry to check that the padding is skipped correctly.
+        for name in ["", "a", "ab", "abc", "blink"].iter() {
+            let (_, header) = roundtrip(vec![
+                main(),
+                TbfHeaderV2Entry::PackageName(String::from(*name)),
+                TbfHeaderV2Entry::KernelVersion(TbfHeaderV2KernelVersion::new(2, 1)),
+            ]);
+            assert_eq!(header.get_package_name(), Some(*name));
+            assert_eq!(header.get_kernel_version(), Some((2, 1)));
+        }
+    }
+
+    #[test]
+    fn fixed_addresses_roundtrip() {
+        let fixed = TbfHeaderV2FixedAddresses::new(0x20004000, 0x40000);
+        let (_, header) = roundtrip(vec![main(), TbfHeaderV2Entry::FixedAddresses(fixed)]);
+        assert_eq!(header.get_fixed_address_ram(), Some(0x20004000));
+        assert_eq!(header.get_fixed_address_flash(), Some(0x40000));
+
+        let fixed = TbfHeaderV2FixedAddresses::new(0xFFFFFFFF, 0x40000);
+        let (_, header) = roundtrip(vec![main(), TbfHeaderV2Entry::FixedAddresses(fixed)]);
+        assert_eq!(header.get_fixed_address_ram(), None);
+        assert_eq!(header.get_fixed_address_flash(), Some(0x40000));
+    }
+
+    #[test]
+    fn permissions_roundtrip() {
+        let permissions = TbfHeaderV2Permissions::new(&[
+            TbfHeaderDriverPermission::new(1, 0, 0b101),
+            TbfHeaderDriverPermission::new(2, 1, u64::MAX),
+        ])
+        .unwrap();
+        assert_eq!(permissions.perms().len(), 2);
+        let (_, header) = roundtrip(vec![main(), TbfHeaderV2Entry::Permissions(permissions)]);
+        assert!(matches!(
+            header.get_command_permissions(1, 0),
+            CommandPermissions::Mask(0b101)
+        ));
+        assert!(matches!(
+            header.get_command_permissions(2, 1),
+            CommandPermissions::Mask(u64::MAX)
+        ));
+        assert!(matches!(
+            header.get_command_permissions(2, 0),
+            CommandPermissions::Mask(0)
+        ));
+        assert!(matches!(
+            header.get_command_permissions(3, 0),
+            CommandPermissions::NoPermsThisDriver
+        ));
+
+        let (_, header) = roundtrip(vec![main()]);
+        assert!(matches!(
+            header.get_command_permissions(1, 0),
+            CommandPermissions::NoPermsAtAll
+        ));
+
+        let too_many = [TbfHeaderDriverPermission::default(); NUM_HEADER_ENTRIES + 1];
+        assert!(TbfHeaderV2Permissions::<NUM_HEADER_ENTRIES>::new(&too_many).is_none());
+    }
+
+    #[test]
+    fn persistent_acl_roundtrip() {
+        let acl = TbfHeaderV2PersistentAcl::new(7, &[1, 2], &[3]).unwrap();
+        let (_, header) = roundtrip(vec![main(), TbfHeaderV2Entry::PersistentAcl(acl)]);
+        assert_eq!(header.get_persistent_acl_write_id(), Some(7));
+        let (len, read_ids) = header.get_persistent_acl_read_ids().unwrap();
+        assert_eq!(&read_ids[..len], &[1, 2]);
+        let (len, access_ids) = header.get_persistent_acl_access_ids().unwrap();
+        assert_eq!(&access_ids[..len], &[3]);
+
+        let acl = TbfHeaderV2PersistentAcl::new(7, &[], &[]).unwrap();
+        let (_, header) = roundtrip(vec![main(), TbfHeaderV2Entry::PersistentAcl(acl)]);
+        assert_eq!(header.get_persistent_acl_read_ids().unwrap().0, 0);
+        assert_eq!(header.get_persistent_acl_access_ids().unwrap().0, 0);
+    }
+
+    #[test]
+    fn kernel_version_roundtrip() {
+        let version = TbfHeaderV2KernelVersion::new(2, 1);
+        let (_, header) = roundtrip(vec![main(), TbfHeaderV2Entry::KernelVersion(version)]);
+        assert_eq!(header.get_kernel_version(), Some((2, 1)));
+
+        let (_, header) = roundtrip(vec![main()]);
+        assert_eq!(header.get_kernel_version(), None);
+    }
+
+    #[test]
+    fn timing_constraints_roundtrip() {
+        let timing = TbfHeaderV2TimingConstraints::new(1000, 500);
+        let (_, header) = roundtrip(vec![main(), TbfHeaderV2Entry::TimingConstraints(timing)]);
+        assert_eq!(header.get_timing_constraints(), Some((1000, 500)));
+
+        // The deadline defaults to the end of the period.
+        let timing = TbfHeaderV2TimingConstraints::new(1000, 0);
+        let (_, header) = roundtrip(vec![main(), TbfHeaderV2Entry::TimingConstraints(timing)]);
+        assert_eq!(header.get_timing_constraints(), Some((1000, 1000)));
+    }
+
+    #[test]
+    fn ipc_allow_list_roundtrip() {
+        let allow_list = TbfHeaderV2IpcAllowList::new(&[5, 6]).unwrap();
+        let (_, header) = roundtrip(vec![main(), TbfHeaderV2Entry::IpcAllowList(allow_list)]);
+        let (len, client_ids) = header.get_ipc_allow_list().unwrap();
+        assert_eq!(&client_ids[..len], &[5, 6]);
+    }
+
+    #[test]
+    fn syscall_limits_roundtrip() {
+        let limits = TbfHeaderV2SyscallLimits::new(&[
+            TbfHeaderDriverLimit::new(0x1, 2, 10, 1000, 0),
+            TbfHeaderDriverLimit::new(0x30001, u16::MAX, 0, 0, 500),
+        ])
+        .unwrap();
+        let (_, header) = roundtrip(vec![main(), TbfHeaderV2Entry::SyscallLimits(limits)]);
+        let limits = header.get_syscall_limits();
+        assert_eq!(limits.len(), 2);
+        assert_eq!(limits[0].driver_number(), 0x1);
+        assert_eq!(limits[0].command_number(), 2);
+        assert_eq!(limits[0].max_calls(), 10);
+        assert_eq!(limits[0].period_ms(), 1000);
+        assert_eq!(limits[0].quota(), 0);
+        assert!(limits[1].applies_to(0x30001, 7));
+        assert_eq!(limits[1].quota(), 500);
+    }
+
+    #[test]
+    fn unknown_roundtrip() {
+        let (bytes, header) =
+            roundtrip(vec![TbfHeaderV2Entry::Unknown(0x55, vec![1, 2, 3]), main()]);
+        // The kernel skips the entry, and the builder keeps it.
+        assert_eq!(header.get_minimum_app_ram_size(), 4096);
+        let builder = TbfHeaderV2Builder::parse(&bytes, true).unwrap();
+        assert!(matches!(
+            &builder.entries[0],
+            TbfHeaderV2Entry::Unknown(0x55, value) if value == &[1, 2, 3]
+        ));
+    }
+
+    #[test]
+    fn footer_credentials_rejected() {
+        let tipe = TbfHeaderTypes::TbfFooterCredentials as u16;
+        assert!(matches!(
+            TbfHeaderV2Entry::parse(tipe, &[0; 4]),
+            Err(TbfParseError::BadTlvEntry(128))
+        ));
+    }
+
+    #[test]
+    fn padding_roundtrip() {
+        let (bytes, header) = roundtrip(vec![]);
+        assert_eq!(bytes.len(), TBF_HEADER_V2_BASE_SIZE);
+        assert!(!header.is_app());
+        assert!(!header.enabled());
+    }
+
+    #[test]
+    fn checksum() {
+        let mut builder = TbfHeaderV2Builder::new(0x1000, TBF_FLAG_ENABLED | TBF_FLAG_STICKY);
+        builder.entries.push(main());
+        builder
+            .entries
+            .push(TbfHeaderV2Entry::PackageName(String::from("blink")));
+        let bytes = builder.serialize();
+
+        let checksum = bytes
+            .chunks_exact(4)
+            .enumerate()
+            .filter(|(i, _)| *i != 3)
+            .fold(0, |acc, (_, word)| {
+                acc ^ u32::from_le_bytes(word.try_into().unwrap())
+            });
+        assert_eq!(&bytes[12..16], &checksum.to_le_bytes());
+        assert_eq!(
+            parse::compute_tbf_header_checksum(&bytes).unwrap(),
+            checksum
+        );
+
+        // Any change to the header, including the flags, breaks the checksum.
+        let mut corrupted = bytes.clone();
+        corrupted[8] ^= 0x1;
+        let corrupted: &'static [u8] = Box::leak(corrupted.into_boxed_slice());
+        assert!(matches!(
+            parse::parse_tbf_header(corrupted, 2),
+            Err(TbfParseError::ChecksumMismatch(stored, computed))
+                if stored == checksum && computed == checksum ^ 0x1
+        ));
+        assert!(matches!(
+            TbfHeaderV2Builder::parse(corrupted, true),
+            Err(TbfParseError::ChecksumMismatch(_, _))
+        ));
+
+        // Reserializing a header parsed without verification repairs it.
+        let mut repaired = TbfHeaderV2Builder::parse(corrupted, false).unwrap();
+        assert_eq!(repaired.flags, TBF_FLAG_STICKY);
+        repaired.flags |= TBF_FLAG_ENABLED;
+        assert_eq!(repaired.serialize(), bytes);
+    }
+}
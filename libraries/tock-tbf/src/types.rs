@@ -4,7 +4,9 @@ use core::convert::TryInto;
 use core::fmt;
 use core::mem::size_of;
 
-const NUM_PERSISTENT_ACLS: usize = 8;
+pub(crate) const NUM_PERSISTENT_ACLS: usize = 8;
+pub(crate) const NUM_IPC_ALLOWED_CLIENTS: usize = 8;
+pub(crate) const NUM_SYSCALL_LIMITS: usize = 8;
 
 /// Error when parsing just the beginning of the TBF header. This is only used
 /// when establishing the linked list structure of apps installed in flash.
@@ -124,6 +126,14 @@ pub enum TbfHeaderTypes {
     TbfHeaderPermissions = 6,
     TbfHeaderPersistentAcl = 7,
     TbfHeaderKernelVersion = 8,
+    TbfHeaderProgram = 9,
+    TbfHeaderTimingConstraints = 10,
+    TbfHeaderIpcAllowList = 11,
+    TbfHeaderSyscallLimits = 12,
+
+    /// Credentials (hashes or signatures) stored in the footer of a TBF object,
+    /// after the end of the application binary.
+    TbfFooterCredentials = 128,
 
     /// Some field in the header that we do not understand. Since the TLV format
     /// specifies the length of each section, if we get a field we do not
@@ -144,9 +154,25 @@ pub struct TbfHeaderTlv {
 /// only padding.
 #[derive(Clone, Copy, Debug)]
 pub struct TbfHeaderV2Main {
-    init_fn_offset: u32,
-    protected_size: u32,
-    minimum_ram_size: u32,
+    pub(crate) init_fn_offset: u32,
+    pub(crate) protected_size: u32,
+    pub(crate) minimum_ram_size: u32,
+}
+
+/// The v2 program section for apps.
+///
+/// This is a superset of the main section that also records where the
+/// application binary ends. Anything after `binary_end_offset` and before the
+/// end of the TBF object are footers, which hold credentials for the header
+/// and binary. If an app has a program section, it is used instead of the
+/// main section.
+#[derive(Clone, Copy, Debug)]
+pub struct TbfHeaderV2Program {
+    pub(crate) init_fn_offset: u32,
+    pub(crate) protected_trailer_size: u32,
+    pub(crate) minimum_ram_size: u32,
+    pub(crate) binary_end_offset: u32,
+    pub(crate) version: u32,
 }
 
 /// Writeable flash regions only need an offset and size.
@@ -155,8 +181,8 @@ pub struct TbfHeaderV2Main {
 /// struct.
 #[derive(Clone, Copy, Debug, Default)]
 pub struct TbfHeaderV2WriteableFlashRegion {
-    writeable_flash_region_offset: u32,
-    writeable_flash_region_size: u32,
+    pub(crate) writeable_flash_region_offset: u32,
+    pub(crate) writeable_flash_region_size: u32,
 }
 
 /// Optional fixed addresses for flash and RAM for this process.
@@ -176,41 +202,179 @@ pub struct TbfHeaderV2FixedAddresses {
     /// The absolute address of the start of RAM that the process expects. For
     /// example, if the process was linked with a RAM region starting at
     /// address `0x00023000`, then this would be set to `0x00023000`.
-    start_process_ram: u32,
+    pub(crate) start_process_ram: u32,
     /// The absolute address of the start of the process binary. This does _not_
     /// include the TBF header. This is the address the process used for the
     /// start of flash with the linker.
-    start_process_flash: u32,
+    pub(crate) start_process_flash: u32,
 }
 
+/// The allowed commands for one driver number. `allowed_commands` is a
+/// bitmask of the command numbers `offset * 64` to `offset * 64 + 63`.
 #[derive(Clone, Copy, Debug, Default)]
-struct TbfHeaderDriverPermission {
-    driver_number: u32,
-    offset: u32,
-    allowed_commands: u64,
+pub struct TbfHeaderDriverPermission {
+    pub(crate) driver_number: u32,
+    pub(crate) offset: u32,
+    pub(crate) allowed_commands: u64,
 }
 
 /// A list of permissions for this app
 #[derive(Clone, Copy, Debug)]
 pub struct TbfHeaderV2Permissions<const L: usize> {
-    length: u16,
-    perms: [TbfHeaderDriverPermission; L],
+    pub(crate) length: u16,
+    pub(crate) perms: [TbfHeaderDriverPermission; L],
 }
 
 /// A list of persistent access permissions
 #[derive(Clone, Copy, Debug)]
 pub struct TbfHeaderV2PersistentAcl<const L: usize> {
-    write_id: u32,
-    read_length: u16,
-    read_ids: [u32; L],
-    access_length: u16,
-    access_ids: [u32; L],
+    pub(crate) write_id: u32,
+    pub(crate) read_length: u16,
+    pub(crate) read_ids: [u32; L],
+    pub(crate) access_length: u16,
+    pub(crate) access_ids: [u32; L],
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct TbfHeaderV2KernelVersion {
-    major: u16,
-    minor: u16,
+    pub(crate) major: u16,
+    pub(crate) minor: u16,
+}
+
+/// Timing constraints for processes that run periodic real-time jobs.
+///
+/// Deadline-based schedulers use these to order processes. A
+/// `deadline_us` of 0 means that the deadline of each job is the end of its
+/// period.
+#[derive(Clone, Copy, Debug)]
+pub struct TbfHeaderV2TimingConstraints {
+    pub(crate) period_us: u32,
+    pub(crate) deadline_us: u32,
+}
+
+/// The clients that may connect to this process when it acts as a
+/// message-passing IPC service.
+///
+/// Clients are identified by their `ShortID`. A client id of 0 is an empty
+/// entry and admits no client, so clients without a fixed `ShortID` can never
+/// connect.
+#[derive(Clone, Copy, Debug)]
+pub struct TbfHeaderV2IpcAllowList<const L: usize> {
+    pub(crate) length: u16,
+    pub(crate) client_ids: [u32; L],
+}
+
+/// Limits on the commands a process may call on one driver.
+///
+/// At most `max_calls` commands may be called in every `period_ms`
+/// milliseconds, and at most `quota` commands in total until the board
+/// reboots, even if the process restarts. A `max_calls` or `period_ms` of 0
+/// disables the rate limit, and a `quota` of 0 disables the quota. A
+/// `command_number` of `u16::MAX` applies the limits to every command of the
+/// driver.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TbfHeaderDriverLimit {
+    pub(crate) driver_number: u32,
+    pub(crate) command_number: u16,
+    pub(crate) max_calls: u16,
+    pub(crate) period_ms: u32,
+    pub(crate) quota: u32,
+}
+
+impl TbfHeaderDriverLimit {
+    pub fn driver_number(&self) -> u32 {
+        self.driver_number
+    }
+
+    pub fn command_number(&self) -> u16 {
+        self.command_number
+    }
+
+    pub fn max_calls(&self) -> u16 {
+        self.max_calls
+    }
+
+    pub fn period_ms(&self) -> u32 {
+        self.period_ms
+    }
+
+    pub fn quota(&self) -> u32 {
+        self.quota
+    }
+
+    /// Whether the limits apply to command `command_number` of driver
+    /// `driver_number`.
+    pub fn applies_to(&self, driver_number: usize, command_number: usize) -> bool {
+        self.driver_number as usize == driver_number
+            && (self.command_number == u16::MAX || self.command_number as usize == command_number)
+    }
+}
+
+/// A list of syscall limits for this app.
+#[derive(Clone, Copy, Debug)]
+pub struct TbfHeaderV2SyscallLimits<const L: usize> {
+    pub(crate) length: u16,
+    pub(crate) limits: [TbfHeaderDriverLimit; L],
+}
+
+/// The format of the credentials stored in a `TbfFooterCredentials` footer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TbfFooterV2CredentialsType {
+    /// Reserved space that does not hold credentials. This allows credentials
+    /// to be added to a TBF object after it was built without changing its
+    /// size.
+    Reserved = 0,
+    /// A 3072 bit RSA public key followed by a PKCS#1 v1.5 signature of the
+    /// SHA-256 hash of the header and binary.
+    Rsa3072Key = 1,
+    /// A 4096 bit RSA public key followed by a PKCS#1 v1.5 signature of the
+    /// SHA-256 hash of the header and binary.
+    Rsa4096Key = 2,
+    /// A SHA-256 hash of the header and binary.
+    SHA256 = 3,
+    /// A SHA-384 hash of the header and binary.
+    SHA384 = 4,
+    /// A SHA-512 hash of the header and binary.
+    SHA512 = 5,
+    /// An ECDSA NIST P-256 signature (`r` followed by `s`) of the SHA-256 hash
+    /// of the header and binary.
+    EcdsaNistP256 = 6,
+}
+
+impl TbfFooterV2CredentialsType {
+    /// The number of bytes of credential data this format requires, or `None`
+    /// if the data can be of any length.
+    pub fn data_length(&self) -> Option<usize> {
+        match self {
+            TbfFooterV2CredentialsType::Reserved => None,
+            TbfFooterV2CredentialsType::Rsa3072Key => Some(384 * 2),
+            TbfFooterV2CredentialsType::Rsa4096Key => Some(512 * 2),
+            TbfFooterV2CredentialsType::SHA256 => Some(32),
+            TbfFooterV2CredentialsType::SHA384 => Some(48),
+            TbfFooterV2CredentialsType::SHA512 => Some(64),
+            TbfFooterV2CredentialsType::EcdsaNistP256 => Some(64),
+        }
+    }
+}
+
+/// Credentials stored in a TBF footer.
+///
+/// The credentials cover the TBF header and the application binary, i.e. all
+/// bytes of the TBF object before the first footer.
+#[derive(Clone, Copy, Debug)]
+pub struct TbfFooterV2Credentials {
+    format: TbfFooterV2CredentialsType,
+    data: &'static [u8],
+}
+
+impl TbfFooterV2Credentials {
+    pub fn format(&self) -> TbfFooterV2CredentialsType {
+        self.format
+    }
+
+    pub fn data(&self) -> &'static [u8] {
+        self.data
+    }
 }
 
 // Conversion functions from slices to the various TBF fields.
@@ -261,6 +425,11 @@ impl core::convert::TryFrom<u16> for TbfHeaderTypes {
             6 => Ok(TbfHeaderTypes::TbfHeaderPermissions),
             7 => Ok(TbfHeaderTypes::TbfHeaderPersistentAcl),
             8 => Ok(TbfHeaderTypes::TbfHeaderKernelVersion),
+            9 => Ok(TbfHeaderTypes::TbfHeaderProgram),
+            10 => Ok(TbfHeaderTypes::TbfHeaderTimingConstraints),
+            11 => Ok(TbfHeaderTypes::TbfHeaderIpcAllowList),
+            12 => Ok(TbfHeaderTypes::TbfHeaderSyscallLimits),
+            128 => Ok(TbfHeaderTypes::TbfFooterCredentials),
             _ => Ok(TbfHeaderTypes::Unknown),
         }
     }
@@ -310,6 +479,40 @@ impl core::convert::TryFrom<&[u8]> for TbfHeaderV2Main {
     }
 }
 
+impl core::convert::TryFrom<&[u8]> for TbfHeaderV2Program {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2Program, Self::Error> {
+        Ok(TbfHeaderV2Program {
+            init_fn_offset: u32::from_le_bytes(
+                b.get(0..4)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+            protected_trailer_size: u32::from_le_bytes(
+                b.get(4..8)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+            minimum_ram_size: u32::from_le_bytes(
+                b.get(8..12)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+            binary_end_offset: u32::from_le_bytes(
+                b.get(12..16)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+            version: u32::from_le_bytes(
+                b.get(16..20)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+        })
+    }
+}
+
 impl core::convert::TryFrom<&[u8]> for TbfHeaderV2WriteableFlashRegion {
     type Error = TbfParseError;
 
@@ -497,6 +700,163 @@ impl core::convert::TryFrom<&[u8]> for TbfHeaderV2KernelVersion {
     }
 }
 
+impl core::convert::TryFrom<&[u8]> for TbfHeaderV2TimingConstraints {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2TimingConstraints, Self::Error> {
+        Ok(TbfHeaderV2TimingConstraints {
+            period_us: u32::from_le_bytes(
+                b.get(0..4)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+            deadline_us: u32::from_le_bytes(
+                b.get(4..8)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+        })
+    }
+}
+
+impl<const L: usize> core::convert::TryFrom<&[u8]> for TbfHeaderV2IpcAllowList<L> {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2IpcAllowList<L>, Self::Error> {
+        let length = u16::from_le_bytes(
+            b.get(0..2)
+                .ok_or(TbfParseError::NotEnoughFlash)?
+                .try_into()?,
+        );
+
+        let mut client_ids: [u32; L] = [0; L];
+        for i in 0..length as usize {
+            let start = 2 + (i * size_of::<u32>());
+            let end = start + size_of::<u32>();
+            if let Some(client_id) = client_ids.get_mut(i) {
+                *client_id = u32::from_le_bytes(
+                    b.get(start..end)
+                        .ok_or(TbfParseError::NotEnoughFlash)?
+                        .try_into()?,
+                );
+            } else {
+                return Err(TbfParseError::BadTlvEntry(
+                    TbfHeaderTypes::TbfHeaderIpcAllowList as usize,
+                ));
+            }
+        }
+
+        Ok(TbfHeaderV2IpcAllowList { length, client_ids })
+    }
+}
+
+impl core::convert::TryFrom<&[u8]> for TbfHeaderDriverLimit {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderDriverLimit, Self::Error> {
+        Ok(TbfHeaderDriverLimit {
+            driver_number: u32::from_le_bytes(
+                b.get(0..4)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+            command_number: u16::from_le_bytes(
+                b.get(4..6)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+            max_calls: u16::from_le_bytes(
+                b.get(6..8)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+            period_ms: u32::from_le_bytes(
+                b.get(8..12)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+            quota: u32::from_le_bytes(
+                b.get(12..16)
+                    .ok_or(TbfParseError::InternalError)?
+                    .try_into()?,
+            ),
+        })
+    }
+}
+
+impl<const L: usize> core::convert::TryFrom<&[u8]> for TbfHeaderV2SyscallLimits<L> {
+    type Error = TbfParseError;
+
+    fn try_from(b: &[u8]) -> Result<TbfHeaderV2SyscallLimits<L>, Self::Error> {
+        let number_limits = u16::from_le_bytes(
+            b.get(0..2)
+                .ok_or(TbfParseError::NotEnoughFlash)?
+                .try_into()?,
+        );
+
+        let mut limits = [TbfHeaderDriverLimit::default(); L];
+        for i in 0..number_limits as usize {
+            let start = 2 + (i * size_of::<TbfHeaderDriverLimit>());
+            let end = start + size_of::<TbfHeaderDriverLimit>();
+            if let Some(limit) = limits.get_mut(i) {
+                *limit = b
+                    .get(start..end)
+                    .ok_or(TbfParseError::NotEnoughFlash)?
+                    .try_into()?;
+            } else {
+                return Err(TbfParseError::BadTlvEntry(
+                    TbfHeaderTypes::TbfHeaderSyscallLimits as usize,
+                ));
+            }
+        }
+
+        Ok(TbfHeaderV2SyscallLimits {
+            length: number_limits,
+            limits,
+        })
+    }
+}
+
+impl core::convert::TryFrom<u32> for TbfFooterV2CredentialsType {
+    type Error = TbfParseError;
+
+    fn try_from(format: u32) -> Result<TbfFooterV2CredentialsType, Self::Error> {
+        match format {
+            0 => Ok(TbfFooterV2CredentialsType::Reserved),
+            1 => Ok(TbfFooterV2CredentialsType::Rsa3072Key),
+            2 => Ok(TbfFooterV2CredentialsType::Rsa4096Key),
+            3 => Ok(TbfFooterV2CredentialsType::SHA256),
+            4 => Ok(TbfFooterV2CredentialsType::SHA384),
+            5 => Ok(TbfFooterV2CredentialsType::SHA512),
+            6 => Ok(TbfFooterV2CredentialsType::EcdsaNistP256),
+            _ => Err(TbfParseError::BadTlvEntry(
+                TbfHeaderTypes::TbfFooterCredentials as usize,
+            )),
+        }
+    }
+}
+
+impl core::convert::TryFrom<&'static [u8]> for TbfFooterV2Credentials {
+    type Error = TbfParseError;
+
+    fn try_from(b: &'static [u8]) -> Result<TbfFooterV2Credentials, Self::Error> {
+        let format: TbfFooterV2CredentialsType = u32::from_le_bytes(
+            b.get(0..4)
+                .ok_or(TbfParseError::NotEnoughFlash)?
+                .try_into()?,
+        )
+        .try_into()?;
+        let data = b.get(4..).ok_or(TbfParseError::NotEnoughFlash)?;
+        let data = match format.data_length() {
+            Some(length) => data.get(0..length).ok_or(TbfParseError::BadTlvEntry(
+                TbfHeaderTypes::TbfFooterCredentials as usize,
+            ))?,
+            None => data,
+        };
+        Ok(TbfFooterV2Credentials { format, data })
+    }
+}
+
 /// The command permissions specified by the TBF header.
 ///
 /// Use the `get_command_permissions()` function to retrieve these.
@@ -520,12 +880,16 @@ pub enum CommandPermissions {
 pub struct TbfHeaderV2 {
     pub(crate) base: TbfHeaderV2Base,
     pub(crate) main: Option<TbfHeaderV2Main>,
+    pub(crate) program: Option<TbfHeaderV2Program>,
     pub(crate) package_name: Option<&'static str>,
     pub(crate) writeable_regions: Option<[Option<TbfHeaderV2WriteableFlashRegion>; 4]>,
     pub(crate) fixed_addresses: Option<TbfHeaderV2FixedAddresses>,
     pub(crate) permissions: Option<TbfHeaderV2Permissions<8>>,
     pub(crate) persistent_acls: Option<TbfHeaderV2PersistentAcl<NUM_PERSISTENT_ACLS>>,
     pub(crate) kernel_version: Option<TbfHeaderV2KernelVersion>,
+    pub(crate) timing_constraints: Option<TbfHeaderV2TimingConstraints>,
+    pub(crate) ipc_allow_list: Option<TbfHeaderV2IpcAllowList<NUM_IPC_ALLOWED_CLIENTS>>,
+    pub(crate) syscall_limits: Option<TbfHeaderV2SyscallLimits<NUM_SYSCALL_LIMITS>>,
 }
 
 /// Type that represents the fields of the Tock Binary Format header.
@@ -566,7 +930,10 @@ impl TbfHeader {
     /// needed for this app.
     pub fn get_minimum_app_ram_size(&self) -> u32 {
         match *self {
-            TbfHeader::TbfHeaderV2(hd) => hd.main.map_or(0, |m| m.minimum_ram_size),
+            TbfHeader::TbfHeaderV2(hd) => match hd.program {
+                Some(p) => p.minimum_ram_size,
+                None => hd.main.map_or(0, |m| m.minimum_ram_size),
+            },
             _ => 0,
         }
     }
@@ -576,7 +943,11 @@ impl TbfHeader {
     pub fn get_protected_size(&self) -> u32 {
         match *self {
             TbfHeader::TbfHeaderV2(hd) => {
-                hd.main.map_or(0, |m| m.protected_size) + (hd.base.header_size as u32)
+                let trailer_size = match hd.program {
+                    Some(p) => p.protected_trailer_size,
+                    None => hd.main.map_or(0, |m| m.protected_size),
+                };
+                trailer_size + (hd.base.header_size as u32)
             }
             _ => 0,
         }
@@ -587,12 +958,37 @@ impl TbfHeader {
     pub fn get_init_function_offset(&self) -> u32 {
         match *self {
             TbfHeader::TbfHeaderV2(hd) => {
-                hd.main.map_or(0, |m| m.init_fn_offset) + (hd.base.header_size as u32)
+                let init_fn_offset = match hd.program {
+                    Some(p) => p.init_fn_offset,
+                    None => hd.main.map_or(0, |m| m.init_fn_offset),
+                };
+                init_fn_offset + (hd.base.header_size as u32)
             }
             _ => 0,
         }
     }
 
+    /// Get the offset from the beginning of the app's flash region where the
+    /// app binary ends and the footers begin. Apps without a program header
+    /// have no footers, so this is the total size of the TBF object.
+    pub fn get_binary_end(&self) -> u32 {
+        match *self {
+            TbfHeader::TbfHeaderV2(hd) => hd
+                .program
+                .map_or(hd.base.total_size, |p| p.binary_end_offset),
+            TbfHeader::Padding(base) => base.total_size,
+        }
+    }
+
+    /// Get the version of the app binary, as recorded in the program header.
+    /// Returns 0 if the app has no program header.
+    pub fn get_binary_version(&self) -> u32 {
+        match *self {
+            TbfHeader::TbfHeaderV2(hd) => hd.program.map_or(0, |p| p.version),
+            _ => 0,
+        }
+    }
+
     /// Get the name of the app.
     pub fn get_package_name(&self) -> Option<&'static str> {
         match *self {
@@ -745,4 +1141,53 @@ impl TbfHeader {
             _ => None,
         }
     }
+
+    /// Get the period and the relative deadline of the jobs of this process,
+    /// in microseconds. If the header leaves the deadline unset, the deadline
+    /// is the period. Returns `None` if the timing constraints header is not
+    /// included or the period is 0.
+    pub fn get_timing_constraints(&self) -> Option<(u32, u32)> {
+        match self {
+            TbfHeader::TbfHeaderV2(hd) => match hd.timing_constraints {
+                Some(timing) if timing.period_us > 0 => {
+                    let deadline_us = if timing.deadline_us == 0 {
+                        timing.period_us
+                    } else {
+                        timing.deadline_us
+                    };
+                    Some((timing.period_us, deadline_us))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Get the number of valid client ids and the ids of the clients that may
+    /// connect to this process over message-passing IPC.
+    /// Returns `None` if the IPC allow list header is not included.
+    pub fn get_ipc_allow_list(&self) -> Option<(usize, [u32; NUM_IPC_ALLOWED_CLIENTS])> {
+        match self {
+            TbfHeader::TbfHeaderV2(hd) => match hd.ipc_allow_list {
+                Some(allow_list) => Some((allow_list.length.into(), allow_list.client_ids)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Get the limits on the commands this process may call.
+    /// Returns an empty slice if the syscall limits header is not included.
+    pub fn get_syscall_limits(&self) -> &[TbfHeaderDriverLimit] {
+        match self {
+            TbfHeader::TbfHeaderV2(hd) => match &hd.syscall_limits {
+                Some(limits) => limits
+                    .limits
+                    .get(..limits.length as usize)
+                    .unwrap_or(&limits.limits),
+                _ => &[],
+            },
+            _ => &[],
+        }
+    }
 }
@@ -0,0 +1,500 @@
+//! Convert a core dump of a Tock process into an ELF core file.
+//!
+//! The input is a capture of the channel the kernel wrote the dump to, or an
+//! image of the flash region it was stored in. Dumps start with the bytes
+//! "TKCD", and any other data around them, such as console output, is
+//! skipped. The dump format is described in
+//! `kernel/src/process_core_dump.rs`.
+//!
+//! The core file holds the registers of the process in an `NT_PRSTATUS` note
+//! and the dumped memory as loadable segments, so that gdb can open it
+//! together with the ELF of the app.
+
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+const USAGE: &str = "Usage: core-dump [options] [capture]
+
+Convert a core dump of a Tock process into an ELF core file for gdb, and
+print a summary of the dump. The capture is read from standard input if no
+file is given.
+
+Options:
+  -o, --output <file>                 Write the core file to <file>. Defaults
+                                      to \"core\".
+  --index <n>                         Convert the <n>th dump in the capture,
+                                      counting from 0. Defaults to the last
+                                      dump.
+  --info                              Only print the summary.";
+
+const DUMP_MAGIC: &[u8] = b"TKCD";
+const FORMAT_VERSION: u16 = 1;
+const HEADER_LEN: usize = 16;
+const SECTION_HEADER_LEN: usize = 12;
+const PROC_LEN: usize = 64;
+
+const FLAG_TRUNCATED: u32 = 0x01;
+const FLAG_PC: u8 = 0x01;
+const FLAG_FAULT_ADDRESS: u8 = 0x02;
+
+// ELF constants.
+const ET_CORE: u16 = 4;
+const EM_ARM: u16 = 40;
+const EM_RISCV: u16 = 243;
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+const NT_PRSTATUS: u32 = 1;
+const NT_PRPSINFO: u32 = 3;
+const ELF_HEADER_LEN: usize = 52;
+const PROGRAM_HEADER_LEN: usize = 32;
+
+/// Signal reported in the core file, SIGSEGV.
+const SIGNAL: u16 = 11;
+
+fn u16_at(bytes: &[u8], i: usize) -> u16 {
+    u16::from_le_bytes([bytes[i], bytes[i + 1]])
+}
+
+fn u32_at(bytes: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]])
+}
+
+/// A section of a dump.
+struct Section<'a> {
+    tag: [u8; 4],
+    address: u32,
+    data: &'a [u8],
+}
+
+/// The PROC section of a dump.
+struct ProcessInfo {
+    name: String,
+    restart_count: u32,
+    pc: Option<u32>,
+    fault_address: Option<u32>,
+    action: u8,
+    flash_start: u32,
+    flash_end: u32,
+    sram_start: u32,
+    sram_app_brk: u32,
+    sram_grant_start: u32,
+    sram_end: u32,
+    stack_top: u32,
+    heap_start: u32,
+}
+
+impl ProcessInfo {
+    fn decode(data: &[u8]) -> Option<ProcessInfo> {
+        if data.len() < PROC_LEN {
+            return None;
+        }
+        let name_len = data[..16].iter().position(|&c| c == 0).unwrap_or(16);
+        let flags = data[29];
+        let field = |flag: u8, offset: usize| {
+            if flags & flag != 0 {
+                Some(u32_at(data, offset))
+            } else {
+                None
+            }
+        };
+        Some(ProcessInfo {
+            name: String::from_utf8_lossy(&data[..name_len]).into_owned(),
+            restart_count: u32_at(data, 16),
+            pc: field(FLAG_PC, 20),
+            fault_address: field(FLAG_FAULT_ADDRESS, 24),
+            action: data[28],
+            flash_start: u32_at(data, 32),
+            flash_end: u32_at(data, 36),
+            sram_start: u32_at(data, 40),
+            sram_app_brk: u32_at(data, 44),
+            sram_grant_start: u32_at(data, 48),
+            sram_end: u32_at(data, 52),
+            stack_top: u32_at(data, 56),
+            heap_start: u32_at(data, 60),
+        })
+    }
+}
+
+/// A decoded dump.
+struct Dump<'a> {
+    flags: u32,
+    sections: Vec<Section<'a>>,
+}
+
+impl<'a> Dump<'a> {
+    fn decode(bytes: &'a [u8]) -> Result<Dump<'a>, String> {
+        let version = u16_at(bytes, 4);
+        if version != FORMAT_VERSION {
+            return Err(format!("unsupported dump version {}", version));
+        }
+        let count = u16_at(bytes, 6) as usize;
+        let mut sections = Vec::new();
+        let mut offset = HEADER_LEN;
+        for _ in 0..count {
+            let header = bytes
+                .get(offset..offset + SECTION_HEADER_LEN)
+                .ok_or("truncated section header")?;
+            let length = u32_at(header, 8) as usize;
+            let start = offset + SECTION_HEADER_LEN;
+            let data = bytes
+                .get(start..start + length)
+                .ok_or("truncated section")?;
+            sections.push(Section {
+                tag: [header[0], header[1], header[2], header[3]],
+                address: u32_at(header, 4),
+                data,
+            });
+            offset = start + ((length + 3) & !3);
+        }
+        Ok(Dump {
+            flags: u32_at(bytes, 12),
+            sections,
+        })
+    }
+
+    fn section(&self, tag: &[u8; 4]) -> Option<&Section<'a>> {
+        self.sections.iter().find(|section| &section.tag == tag)
+    }
+
+    /// Reads a word of dumped memory.
+    fn read_u32(&self, address: u32) -> Option<u32> {
+        self.sections
+            .iter()
+            .filter(|section| section.address != 0)
+            .find_map(|section| {
+                let offset = address.checked_sub(section.address)? as usize;
+                section
+                    .data
+                    .get(offset..offset + 4)
+                    .map(|word| u32_at(word, 0))
+            })
+    }
+}
+
+/// Finds the dumps in `capture`.
+fn find_dumps(capture: &[u8]) -> Vec<&[u8]> {
+    let mut dumps = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = capture[offset..]
+        .windows(DUMP_MAGIC.len())
+        .position(|window| window == DUMP_MAGIC)
+        .map(|position| offset + position)
+    {
+        let length = match capture.get(start..start + HEADER_LEN) {
+            Some(header) => u32_at(header, 8) as usize,
+            None => break,
+        };
+        match capture.get(start..start + length) {
+            Some(dump) if length >= HEADER_LEN => {
+                dumps.push(dump);
+                offset = start + length;
+            }
+            _ => {
+                eprintln!("warning: truncated dump at offset {:#x}", start);
+                offset = start + DUMP_MAGIC.len();
+            }
+        }
+    }
+    dumps
+}
+
+/// The registers of the process, in the order of the `NT_PRSTATUS` note of
+/// the architecture.
+struct Registers {
+    machine: u16,
+    words: Vec<u32>,
+}
+
+/// Reconstructs the registers from the REGS section of `dump`.
+fn registers(dump: &Dump, info: &ProcessInfo) -> Result<Registers, String> {
+    let regs = dump.section(b"REGS").ok_or("the dump has no registers")?;
+    let words: Vec<u32> = regs.data.chunks_exact(4).map(|w| u32_at(w, 0)).collect();
+    if words.len() < 3 {
+        return Err(String::from("truncated registers"));
+    }
+
+    match &words[2].to_le_bytes() {
+        b"ctxm" => {
+            // yield_pc, psr, psp, then r4-r11. The hardware saved r0-r3, r12,
+            // lr, pc and xpsr on the process stack at psp.
+            if words.len() < 14 {
+                return Err(String::from("truncated Cortex-M registers"));
+            }
+            let (yield_pc, psr, psp) = (words[3], words[4], words[5]);
+            let frame: Option<Vec<u32>> = (0..8).map(|i| dump.read_u32(psp + 4 * i)).collect();
+            let mut r = vec![0; 18];
+            r[4..12].copy_from_slice(&words[6..14]);
+            match frame {
+                Some(frame) => {
+                    r[0..4].copy_from_slice(&frame[0..4]);
+                    r[12] = frame[4];
+                    r[14] = frame[5];
+                    r[15] = frame[6];
+                    r[16] = frame[7];
+                    // Bit 9 of the saved xpsr marks a padding word added to
+                    // align the stack.
+                    r[13] = psp + 0x20 + if frame[7] & (1 << 9) != 0 { 4 } else { 0 };
+                }
+                None => {
+                    eprintln!("warning: the exception frame is not in the dump");
+                    r[13] = psp;
+                    r[15] = yield_pc;
+                    r[16] = psr;
+                }
+            }
+            if let Some(pc) = info.pc {
+                r[15] = pc;
+            }
+            Ok(Registers {
+                machine: EM_ARM,
+                words: r,
+            })
+        }
+        b"rv5i" => {
+            // pc, mcause, mtval, then x1-x31.
+            if words.len() < 37 {
+                return Err(String::from("truncated RISC-V registers"));
+            }
+            let mut r = vec![info.pc.unwrap_or(words[3])];
+            r.extend_from_slice(&words[6..37]);
+            Ok(Registers {
+                machine: EM_RISCV,
+                words: r,
+            })
+        }
+        tag => Err(format!(
+            "unknown architecture {}",
+            String::from_utf8_lossy(tag)
+        )),
+    }
+}
+
+fn push_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn pad(out: &mut Vec<u8>) {
+    while out.len() % 4 != 0 {
+        out.push(0);
+    }
+}
+
+fn push_note(out: &mut Vec<u8>, note_type: u32, desc: &[u8]) {
+    push_u32(out, 5);
+    push_u32(out, desc.len() as u32);
+    push_u32(out, note_type);
+    out.extend_from_slice(b"CORE\0");
+    pad(out);
+    out.extend_from_slice(desc);
+    pad(out);
+}
+
+/// The `NT_PRSTATUS` note: signal information, the process identifiers,
+/// times, and then the registers.
+fn prstatus(registers: &Registers) -> Vec<u8> {
+    let mut desc = vec![0; 72];
+    desc[0..4].copy_from_slice(&(SIGNAL as u32).to_le_bytes());
+    desc[12..14].copy_from_slice(&SIGNAL.to_le_bytes());
+    desc[24..28].copy_from_slice(&1u32.to_le_bytes());
+    for word in registers.words.iter() {
+        push_u32(&mut desc, *word);
+    }
+    // pr_fpvalid
+    push_u32(&mut desc, 0);
+    desc
+}
+
+/// The `NT_PRPSINFO` note, which names the process.
+fn prpsinfo(registers: &Registers, name: &str) -> Vec<u8> {
+    // ARM uses 16 bit user and group identifiers, RISC-V 32 bit ones.
+    let ids_len = if registers.machine == EM_ARM { 4 } else { 8 };
+    let mut desc = vec![0; 8 + ids_len];
+    desc[1] = b'R';
+    push_u32(&mut desc, 1);
+    desc.extend_from_slice(&[0; 12]);
+    let mut fname = [0; 16];
+    let len = name.len().min(15);
+    fname[..len].copy_from_slice(&name.as_bytes()[..len]);
+    desc.extend_from_slice(&fname);
+    let mut psargs = [0; 80];
+    psargs[..len].copy_from_slice(&name.as_bytes()[..len]);
+    desc.extend_from_slice(&psargs);
+    desc
+}
+
+/// Builds an ELF core file from the registers and the dumped memory.
+fn elf_core(dump: &Dump, info: &ProcessInfo, registers: &Registers) -> Vec<u8> {
+    let mut notes = Vec::new();
+    push_note(&mut notes, NT_PRSTATUS, &prstatus(registers));
+    push_note(&mut notes, NT_PRPSINFO, &prpsinfo(registers, &info.name));
+
+    let segments: Vec<(&Section, u32)> = [(b"TBFH", PF_R), (b"RAM ", PF_R | PF_W)]
+        .iter()
+        .filter_map(|(tag, flags)| dump.section(tag).map(|section| (section, *flags)))
+        .filter(|(section, _)| !section.data.is_empty())
+        .collect();
+
+    let phnum = 1 + segments.len();
+    let mut offset = ELF_HEADER_LEN + phnum * PROGRAM_HEADER_LEN;
+    let mut out = Vec::new();
+
+    // ELF header.
+    out.extend_from_slice(&[0x7f, b'E', b'L', b'F', 1, 1, 1, 0]);
+    out.extend_from_slice(&[0; 8]);
+    push_u16(&mut out, ET_CORE);
+    push_u16(&mut out, registers.machine);
+    push_u32(&mut out, 1);
+    push_u32(&mut out, 0);
+    push_u32(&mut out, ELF_HEADER_LEN as u32);
+    push_u32(&mut out, 0);
+    push_u32(&mut out, 0);
+    push_u16(&mut out, ELF_HEADER_LEN as u16);
+    push_u16(&mut out, PROGRAM_HEADER_LEN as u16);
+    push_u16(&mut out, phnum as u16);
+    push_u16(&mut out, 0);
+    push_u16(&mut out, 0);
+    push_u16(&mut out, 0);
+
+    // Program headers.
+    let mut program_header = |p_type: u32, vaddr: u32, size: usize, flags: u32, align: u32| {
+        push_u32(&mut out, p_type);
+        push_u32(&mut out, offset as u32);
+        push_u32(&mut out, vaddr);
+        push_u32(&mut out, vaddr);
+        push_u32(&mut out, size as u32);
+        push_u32(&mut out, if p_type == PT_LOAD { size as u32 } else { 0 });
+        push_u32(&mut out, flags);
+        push_u32(&mut out, align);
+        offset += size;
+    };
+    program_header(PT_NOTE, 0, notes.len(), 0, 4);
+    for (section, flags) in segments.iter() {
+        program_header(PT_LOAD, section.address, section.data.len(), *flags, 1);
+    }
+
+    out.extend_from_slice(&notes);
+    for (section, _) in segments.iter() {
+        out.extend_from_slice(section.data);
+    }
+    out
+}
+
+fn action_name(action: u8) -> &'static str {
+    match action {
+        0 => "panic",
+        1 => "restart",
+        2 => "stop",
+        _ => "unknown",
+    }
+}
+
+fn print_summary(dump: &Dump, info: &ProcessInfo) {
+    println!("Process {}", info.name);
+    println!("  restarts:      {}", info.restart_count);
+    println!("  fault action:  {}", action_name(info.action));
+    if let Some(pc) = info.pc {
+        println!("  pc:            {:#010x}", pc);
+    }
+    if let Some(address) = info.fault_address {
+        println!("  fault address: {:#010x}", address);
+    }
+    println!(
+        "  flash:         {:#010x}-{:#010x}",
+        info.flash_start, info.flash_end
+    );
+    println!(
+        "  ram:           {:#010x}-{:#010x}, app break {:#010x}, grants from {:#010x}",
+        info.sram_start, info.sram_end, info.sram_app_brk, info.sram_grant_start
+    );
+    if info.stack_top != 0 {
+        println!("  stack top:     {:#010x}", info.stack_top);
+    }
+    if info.heap_start != 0 {
+        println!("  heap start:    {:#010x}", info.heap_start);
+    }
+    if dump.flags & FLAG_TRUNCATED != 0 {
+        println!("  memory:        truncated to fit the dump buffer");
+    }
+    if let Some(mpu) = dump.section(b"MPU ") {
+        println!("{}", String::from_utf8_lossy(mpu.data).replace('\r', ""));
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let mut output = String::from("core");
+    let mut index = None;
+    let mut info_only = false;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "-o" | "--output" => output = value()?.clone(),
+            "--index" => {
+                let value = value()?;
+                index = Some(
+                    value
+                        .parse::<usize>()
+                        .map_err(|_| format!("invalid number: {}", value))?,
+                )
+            }
+            "--info" => info_only = true,
+            "-h" | "--help" => return Err(String::from(USAGE)),
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+            _ => return Err(format!("unexpected argument: {}\n\n{}", arg, USAGE)),
+        }
+    }
+
+    let capture = match path {
+        Some(path) => fs::read(path).map_err(|e| format!("{}: {}", path, e))?,
+        None => {
+            let mut capture = Vec::new();
+            io::stdin()
+                .read_to_end(&mut capture)
+                .map_err(|e| format!("stdin: {}", e))?;
+            capture
+        }
+    };
+
+    let dumps = find_dumps(&capture);
+    if dumps.is_empty() {
+        return Err(String::from("no core dump found"));
+    }
+    let index = index.unwrap_or(dumps.len() - 1);
+    let bytes = dumps.get(index).ok_or(format!(
+        "dump {} not found, the capture has {} dumps",
+        index,
+        dumps.len()
+    ))?;
+
+    let dump = Dump::decode(bytes)?;
+    let info = dump
+        .section(b"PROC")
+        .and_then(|section| ProcessInfo::decode(section.data))
+        .ok_or("the dump has no process information")?;
+    print_summary(&dump, &info);
+    if info_only {
+        return Ok(());
+    }
+
+    let registers = registers(&dump, &info)?;
+    fs::write(&output, elf_core(&dump, &info, &registers))
+        .map_err(|e| format!("{}: {}", output, e))?;
+    eprintln!("wrote {}", output);
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(message) = run(&args) {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    }
+}
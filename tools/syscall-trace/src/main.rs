@@ -0,0 +1,286 @@
+//! Decode the binary syscall trace written by the kernel.
+//!
+//! The input is a capture of the debug output or of a dedicated trace
+//! channel. Frames start with the bytes "SYST", and any other data between
+//! frames, such as the text output of `debug!()`, is skipped. The frame
+//! format is described in `kernel/src/syscall_trace.rs`.
+
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+const USAGE: &str = "Usage: syscall-trace [options] [capture]
+
+Print the system calls recorded in a capture of the kernel's syscall trace.
+The capture is read from standard input if no file is given.
+
+Options:
+  --process <id>                      Only print system calls of the process
+                                      with identifier <id>.
+  --driver <number>                   Only print system calls to the driver
+                                      <number>.
+  --failures                          Only print system calls that failed.
+
+Numbers may be decimal or hexadecimal with a 0x prefix.";
+
+const FRAME_MAGIC: &[u8] = b"SYST";
+const HEADER_LEN: usize = 12;
+const RECORD_LEN: usize = 16;
+const NO_RETURN: u8 = 0xff;
+
+/// Lowest return variant that indicates success, as in TRD104.
+const SUCCESS: u8 = 128;
+
+/// A decoded system call record.
+struct Record {
+    timestamp: u32,
+    process: u16,
+    class: u8,
+    return_variant: u8,
+    number: u32,
+    subdriver: u16,
+    error: u16,
+}
+
+impl Record {
+    fn decode(bytes: &[u8]) -> Record {
+        let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        let u32_at =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        Record {
+            timestamp: u32_at(0),
+            process: u16_at(4),
+            class: bytes[6],
+            return_variant: bytes[7],
+            number: u32_at(8),
+            subdriver: u16_at(12),
+            error: u16_at(14),
+        }
+    }
+
+    fn failed(&self) -> bool {
+        self.return_variant < SUCCESS
+    }
+
+    /// Whether the record names a driver, rather than a memop operand or a
+    /// yield or exit identifier.
+    fn has_driver(&self) -> bool {
+        matches!(self.class, 1..=4 | 7)
+    }
+}
+
+/// Which records to print.
+#[derive(Default)]
+struct Filter {
+    process: Option<u16>,
+    driver: Option<u32>,
+    failures: bool,
+}
+
+impl Filter {
+    fn matches(&self, record: &Record) -> bool {
+        self.process.map_or(true, |p| p == record.process)
+            && self
+                .driver
+                .map_or(true, |d| record.has_driver() && d == record.number)
+            && (!self.failures || record.failed())
+    }
+}
+
+/// Converts the 32 bit timestamps of the records into seconds since the
+/// first record, assuming that consecutive records are less than one wrap of
+/// the counter apart.
+#[derive(Default)]
+struct Clock {
+    last: Option<u32>,
+    ticks: u64,
+}
+
+impl Clock {
+    fn seconds(&mut self, timestamp: u32, frequency: u32) -> Option<f64> {
+        if let Some(last) = self.last {
+            self.ticks += timestamp.wrapping_sub(last) as u64;
+        }
+        self.last = Some(timestamp);
+        if frequency == 0 {
+            None
+        } else {
+            Some(self.ticks as f64 / frequency as f64)
+        }
+    }
+}
+
+fn parse_number(s: &str) -> Result<u64, String> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| format!("invalid number: {}", s))
+}
+
+fn class_name(class: u8) -> &'static str {
+    match class {
+        0 => "yield",
+        1 => "subscribe",
+        2 => "command",
+        3 => "allow-rw",
+        4 => "allow-ro",
+        5 => "memop",
+        6 => "exit",
+        7 => "allow-userspace-r",
+        _ => "unknown",
+    }
+}
+
+fn return_name(variant: u8) -> &'static str {
+    match variant {
+        0 => "Failure",
+        1 => "FailureU32",
+        2 => "FailureU32U32",
+        3 => "FailureU64",
+        128 => "Success",
+        129 => "SuccessU32",
+        130 => "SuccessU32U32",
+        131 => "SuccessU64",
+        132 => "SuccessU32U32U32",
+        133 => "SuccessU32U64",
+        _ => "Unknown",
+    }
+}
+
+fn error_name(error: u16) -> &'static str {
+    match error {
+        1 => "FAIL",
+        2 => "BUSY",
+        3 => "ALREADY",
+        4 => "OFF",
+        5 => "RESERVE",
+        6 => "INVAL",
+        7 => "SIZE",
+        8 => "CANCEL",
+        9 => "NOMEM",
+        10 => "NOSUPPORT",
+        11 => "NODEVICE",
+        12 => "UNINSTALLED",
+        13 => "NOACK",
+        _ => "UNKNOWN",
+    }
+}
+
+fn print_record(record: &Record, seconds: Option<f64>) {
+    let time = seconds.map_or(String::from("-"), |s| format!("{:.6}", s));
+    let call = match record.class {
+        0 | 6 => format!("{}({})", class_name(record.class), record.number),
+        5 => format!("memop({})", record.number),
+        class => format!(
+            "{}({:#07x}, {})",
+            class_name(class),
+            record.number,
+            record.subdriver
+        ),
+    };
+    let result = if record.return_variant == NO_RETURN {
+        String::new()
+    } else if record.failed() {
+        format!(
+            " -> {}({})",
+            return_name(record.return_variant),
+            error_name(record.error)
+        )
+    } else {
+        format!(" -> {}", return_name(record.return_variant))
+    };
+    println!(
+        "{:>12}  process {:<5} {}{}",
+        time, record.process, call, result
+    );
+}
+
+/// Print the records of every frame in `capture`.
+fn decode(capture: &[u8], filter: &Filter) {
+    let mut clock = Clock::default();
+    let mut records = 0;
+    let mut overwritten = 0;
+    let mut offset = 0;
+
+    while let Some(start) = capture[offset..]
+        .windows(FRAME_MAGIC.len())
+        .position(|window| window == FRAME_MAGIC)
+        .map(|position| offset + position)
+    {
+        let header = match capture.get(start..start + HEADER_LEN) {
+            Some(header) => header,
+            None => break,
+        };
+        let frequency = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let count = u16::from_le_bytes([header[8], header[9]]) as usize;
+        let dropped = u16::from_le_bytes([header[10], header[11]]) as usize;
+
+        let body_start = start + HEADER_LEN;
+        let body = match capture.get(body_start..body_start + count * RECORD_LEN) {
+            Some(body) => body,
+            None => {
+                eprintln!("warning: truncated frame at offset {:#x}", start);
+                break;
+            }
+        };
+
+        if dropped > 0 {
+            println!("*** {} records overwritten in the kernel ***", dropped);
+            overwritten += dropped;
+        }
+        for bytes in body.chunks_exact(RECORD_LEN) {
+            let record = Record::decode(bytes);
+            let seconds = clock.seconds(record.timestamp, frequency);
+            if filter.matches(&record) {
+                print_record(&record, seconds);
+            }
+        }
+        records += count;
+        offset = body_start + body.len();
+    }
+
+    eprintln!("{} records, {} overwritten", records, overwritten);
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let mut filter = Filter::default();
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or(format!("missing value for {}", arg))
+                .and_then(|value| parse_number(value))
+        };
+        match arg.as_str() {
+            "--process" => filter.process = Some(value()? as u16),
+            "--driver" => filter.driver = Some(value()? as u32),
+            "--failures" => filter.failures = true,
+            "-h" | "--help" => return Err(String::from(USAGE)),
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+            _ => return Err(format!("unexpected argument: {}\n\n{}", arg, USAGE)),
+        }
+    }
+
+    let capture = match path {
+        Some(path) => fs::read(path).map_err(|e| format!("{}: {}", path, e))?,
+        None => {
+            let mut capture = Vec::new();
+            io::stdin()
+                .read_to_end(&mut capture)
+                .map_err(|e| format!("stdin: {}", e))?;
+            capture
+        }
+    };
+    decode(&capture, &filter);
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(message) = run(&args) {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    }
+}
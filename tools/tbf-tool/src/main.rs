@@ -0,0 +1,787 @@
+//! Inspect, create and modify Tock Binary Format (TBF) objects.
+//!
+//! This uses the header types from the `tock-tbf` crate, so that the headers
+//! it writes are the headers the kernel parses.
+
+use std::convert::TryInto;
+use std::fs;
+use std::process;
+
+use tock_tbf::parse::compute_tbf_header_checksum;
+use tock_tbf::serialize::*;
+use tock_tbf::types::*;
+
+const USAGE: &str = "Usage: tbf-tool <command> [options]
+
+Commands:
+  inspect <tbf>                       Print the header and footers of each TBF
+                                      object in <tbf>.
+  create <binary> -o <tbf> [header options]
+                                      Wrap an application binary in a TBF
+                                      header.
+  modify <tbf> [-o <out>] [header options]
+                                      Change the header of a TBF object.
+  checksum <tbf> [-o <out>]           Recompute the header checksum of each TBF
+                                      object in <tbf>.
+
+Files are modified in place unless -o is given.
+
+Options for create:
+  --protected-size <bytes>            Space between the header and the binary.
+  --init-fn-offset <bytes>            Offset of the entry point in the binary.
+
+Header options:
+  --name <name>                       Set the package name.
+  --minimum-ram-size <bytes>          Set the minimum RAM size.
+  --enable, --disable                 Enable or disable the process.
+  --sticky, --no-sticky               Set or clear the sticky flag.
+  --writeable-flash-region <offset>:<size>
+                                      Add a writeable flash region.
+  --fixed-addresses <ram>:<flash>     Set the fixed RAM and flash addresses.
+  --permission <driver>:<offset>:<mask>
+                                      Allow the commands in <mask> for the
+                                      command numbers starting at <offset> * 64
+                                      of <driver>.
+  --clear-permissions                 Remove all permissions.
+  --write-id <id>                     Set the persistent storage write id.
+  --read-id <id>                      Add a persistent storage read id.
+  --access-id <id>                    Add a persistent storage access id.
+  --clear-persistent-acl              Remove the persistent storage ACL.
+  --kernel-version <major>.<minor>    Set the required kernel version.
+  --timing-constraints <period_us>:<deadline_us>
+                                      Set the period and deadline of the jobs.
+  --ipc-client <id>                   Allow the client with ShortID <id> to
+                                      connect over message-passing IPC. An id
+                                      of 0 allows any client.
+  --clear-ipc-clients                 Remove the IPC allow list.
+  --syscall-limit <driver>:<command>:<max_calls>:<period_ms>:<quota>
+                                      Limit the process to <max_calls> calls
+                                      of <command> of <driver> in each
+                                      <period_ms> and <quota> calls in total.
+                                      A <command> of 0xffff applies to every
+                                      command, and 0 disables a limit.
+  --clear-syscall-limits              Remove all syscall limits.
+
+Numbers may be decimal or hexadecimal with a 0x prefix.";
+
+/// A change to a header requested on the command line.
+enum Edit {
+    Name(String),
+    MinimumRamSize(u32),
+    Enabled(bool),
+    Sticky(bool),
+    WriteableFlashRegion(u32, u32),
+    FixedAddresses(u32, u32),
+    Permission(u32, u32, u64),
+    ClearPermissions,
+    WriteId(u32),
+    ReadId(u32),
+    AccessId(u32),
+    ClearPersistentAcl,
+    KernelVersion(u16, u16),
+    TimingConstraints(u32, u32),
+    IpcClient(u32),
+    ClearIpcClients,
+    SyscallLimit(u32, u16, u16, u32, u32),
+    ClearSyscallLimits,
+}
+
+/// The options of a command.
+#[derive(Default)]
+struct Options {
+    output: Option<String>,
+    protected_size: Option<u32>,
+    init_fn_offset: Option<u32>,
+    edits: Vec<Edit>,
+}
+
+/// Add the usage string to an error about the command line arguments.
+fn usage_error(message: String) -> String {
+    format!("{}\n\n{}", message, USAGE)
+}
+
+fn parse_u64(s: &str) -> Result<u64, String> {
+    let result = match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    result.map_err(|_| format!("Invalid number: {}", s))
+}
+
+fn parse_u32(s: &str) -> Result<u32, String> {
+    parse_u64(s)?
+        .try_into()
+        .map_err(|_| format!("Number too large: {}", s))
+}
+
+fn parse_u16(s: &str) -> Result<u16, String> {
+    parse_u64(s)?
+        .try_into()
+        .map_err(|_| format!("Number too large: {}", s))
+}
+
+/// Split `value` at each `separator` into exactly `n` parts.
+fn split<'a>(value: &'a str, separator: char, n: usize) -> Result<Vec<&'a str>, String> {
+    let parts: Vec<&str> = value.split(separator).collect();
+    if parts.len() == n {
+        Ok(parts)
+    } else {
+        Err(format!(
+            "Expected {} values separated by '{}'",
+            n, separator
+        ))
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        // Options without a value.
+        let edit = match arg.as_str() {
+            "--enable" => Some(Edit::Enabled(true)),
+            "--disable" => Some(Edit::Enabled(false)),
+            "--sticky" => Some(Edit::Sticky(true)),
+            "--no-sticky" => Some(Edit::Sticky(false)),
+            "--clear-permissions" => Some(Edit::ClearPermissions),
+            "--clear-persistent-acl" => Some(Edit::ClearPersistentAcl),
+            "--clear-ipc-clients" => Some(Edit::ClearIpcClients),
+            "--clear-syscall-limits" => Some(Edit::ClearSyscallLimits),
+            _ => None,
+        };
+        if let Some(edit) = edit {
+            options.edits.push(edit);
+            continue;
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {}", arg))?;
+        match arg.as_str() {
+            "-o" => options.output = Some(value.clone()),
+            "--protected-size" => options.protected_size = Some(parse_u32(value)?),
+            "--init-fn-offset" => options.init_fn_offset = Some(parse_u32(value)?),
+            "--name" => options.edits.push(Edit::Name(value.clone())),
+            "--minimum-ram-size" => options.edits.push(Edit::MinimumRamSize(parse_u32(value)?)),
+            "--writeable-flash-region" => {
+                let parts = split(value, ':', 2)?;
+                options.edits.push(Edit::WriteableFlashRegion(
+                    parse_u32(parts[0])?,
+                    parse_u32(parts[1])?,
+                ));
+            }
+            "--fixed-addresses" => {
+                let parts = split(value, ':', 2)?;
+                options.edits.push(Edit::FixedAddresses(
+                    parse_u32(parts[0])?,
+                    parse_u32(parts[1])?,
+                ));
+            }
+            "--permission" => {
+                let parts = split(value, ':', 3)?;
+                options.edits.push(Edit::Permission(
+                    parse_u32(parts[0])?,
+                    parse_u32(parts[1])?,
+                    parse_u64(parts[2])?,
+                ));
+            }
+            "--write-id" => options.edits.push(Edit::WriteId(parse_u32(value)?)),
+            "--read-id" => options.edits.push(Edit::ReadId(parse_u32(value)?)),
+            "--access-id" => options.edits.push(Edit::AccessId(parse_u32(value)?)),
+            "--ipc-client" => options.edits.push(Edit::IpcClient(parse_u32(value)?)),
+            "--kernel-version" => {
+                let parts = split(value, '.', 2)?;
+                options.edits.push(Edit::KernelVersion(
+                    parse_u16(parts[0])?,
+                    parse_u16(parts[1])?,
+                ));
+            }
+            "--timing-constraints" => {
+                let parts = split(value, ':', 2)?;
+                options.edits.push(Edit::TimingConstraints(
+                    parse_u32(parts[0])?,
+                    parse_u32(parts[1])?,
+                ));
+            }
+            "--syscall-limit" => {
+                let parts = split(value, ':', 5)?;
+                options.edits.push(Edit::SyscallLimit(
+                    parse_u32(parts[0])?,
+                    parse_u16(parts[1])?,
+                    parse_u16(parts[2])?,
+                    parse_u32(parts[3])?,
+                    parse_u32(parts[4])?,
+                ));
+            }
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+/// Get the persistent ACL of `header`, or an empty ACL if it has none.
+fn persistent_acl(header: &TbfHeaderV2Builder) -> (u32, Vec<u32>, Vec<u32>) {
+    header
+        .entries
+        .iter()
+        .find_map(|entry| match entry {
+            TbfHeaderV2Entry::PersistentAcl(acl) => Some((
+                acl.write_id(),
+                acl.read_ids().to_vec(),
+                acl.access_ids().to_vec(),
+            )),
+            _ => None,
+        })
+        .unwrap_or((0, Vec::new(), Vec::new()))
+}
+
+fn set_persistent_acl(
+    header: &mut TbfHeaderV2Builder,
+    (write_id, read_ids, access_ids): (u32, Vec<u32>, Vec<u32>),
+) -> Result<(), String> {
+    let acl = TbfHeaderV2PersistentAcl::new(write_id, &read_ids, &access_ids).ok_or(format!(
+        "At most {} read and access ids are supported",
+        NUM_HEADER_ENTRIES
+    ))?;
+    header.set_entry(TbfHeaderV2Entry::PersistentAcl(acl));
+    Ok(())
+}
+
+fn apply_edit(header: &mut TbfHeaderV2Builder, edit: &Edit) -> Result<(), String> {
+    match *edit {
+        Edit::Name(ref name) => {
+            header.set_entry(TbfHeaderV2Entry::PackageName(name.clone()));
+        }
+        Edit::MinimumRamSize(size) => {
+            let mut found = false;
+            for entry in header.entries.iter_mut() {
+                match entry {
+                    TbfHeaderV2Entry::Main(main) => {
+                        *main = TbfHeaderV2Main::new(
+                            main.init_fn_offset(),
+                            main.protected_size(),
+                            size,
+                        );
+                        found = true;
+                    }
+                    TbfHeaderV2Entry::Program(program) => {
+                        *program = TbfHeaderV2Program::new(
+                            program.init_fn_offset(),
+                            program.protected_trailer_size(),
+                            size,
+                            program.binary_end_offset(),
+                            program.version(),
+                        );
+                        found = true;
+                    }
+                    _ => {}
+                }
+            }
+            if !found {
+                return Err("Header has no main or program entry".to_string());
+            }
+        }
+        Edit::Enabled(enabled) => {
+            if enabled {
+                header.flags |= TBF_FLAG_ENABLED;
+            } else {
+                header.flags &= !TBF_FLAG_ENABLED;
+            }
+        }
+        Edit::Sticky(sticky) => {
+            if sticky {
+                header.flags |= TBF_FLAG_STICKY;
+            } else {
+                header.flags &= !TBF_FLAG_STICKY;
+            }
+        }
+        Edit::WriteableFlashRegion(offset, size) => {
+            let region = TbfHeaderV2WriteableFlashRegion::new(offset, size);
+            let existing = header.entries.iter_mut().find_map(|entry| match entry {
+                TbfHeaderV2Entry::WriteableFlashRegions(regions) => Some(regions),
+                _ => None,
+            });
+            match existing {
+                Some(regions) => regions.push(region),
+                None => header
+                    .entries
+                    .push(TbfHeaderV2Entry::WriteableFlashRegions(vec![region])),
+            }
+        }
+        Edit::FixedAddresses(ram, flash) => {
+            header.set_entry(TbfHeaderV2Entry::FixedAddresses(
+                TbfHeaderV2FixedAddresses::new(ram, flash),
+            ));
+        }
+        Edit::Permission(driver_number, offset, allowed_commands) => {
+            let mut perms = header
+                .entries
+                .iter()
+                .find_map(|entry| match entry {
+                    TbfHeaderV2Entry::Permissions(permissions) => {
+                        Some(permissions.perms().to_vec())
+                    }
+                    _ => None,
+                })
+                .unwrap_or_default();
+            let perm = TbfHeaderDriverPermission::new(driver_number, offset, allowed_commands);
+            match perms
+                .iter_mut()
+                .find(|p| p.driver_number() == driver_number && p.offset() == offset)
+            {
+                Some(existing) => *existing = perm,
+                None => perms.push(perm),
+            }
+            let permissions = TbfHeaderV2Permissions::new(&perms).ok_or(format!(
+                "At most {} permissions are supported",
+                NUM_HEADER_ENTRIES
+            ))?;
+            header.set_entry(TbfHeaderV2Entry::Permissions(permissions));
+        }
+        Edit::ClearPermissions => header.remove_entries(TbfHeaderTypes::TbfHeaderPermissions),
+        Edit::WriteId(id) => {
+            let (_, read_ids, access_ids) = persistent_acl(header);
+            set_persistent_acl(header, (id, read_ids, access_ids))?;
+        }
+        Edit::ReadId(id) => {
+            let (write_id, mut read_ids, access_ids) = persistent_acl(header);
+            read_ids.push(id);
+            set_persistent_acl(header, (write_id, read_ids, access_ids))?;
+        }
+        Edit::AccessId(id) => {
+            let (write_id, read_ids, mut access_ids) = persistent_acl(header);
+            access_ids.push(id);
+            set_persistent_acl(header, (write_id, read_ids, access_ids))?;
+        }
+        Edit::ClearPersistentAcl => header.remove_entries(TbfHeaderTypes::TbfHeaderPersistentAcl),
+        Edit::KernelVersion(major, minor) => {
+            header.set_entry(TbfHeaderV2Entry::KernelVersion(
+                TbfHeaderV2KernelVersion::new(major, minor),
+            ));
+        }
+        Edit::TimingConstraints(period_us, deadline_us) => {
+            header.set_entry(TbfHeaderV2Entry::TimingConstraints(
+                TbfHeaderV2TimingConstraints::new(period_us, deadline_us),
+            ));
+        }
+        Edit::IpcClient(id) => {
+            let mut client_ids = header
+                .entries
+                .iter()
+                .find_map(|entry| match entry {
+                    TbfHeaderV2Entry::IpcAllowList(allow_list) => {
+                        Some(allow_list.client_ids().to_vec())
+                    }
+                    _ => None,
+                })
+                .unwrap_or_default();
+            client_ids.push(id);
+            let allow_list = TbfHeaderV2IpcAllowList::new(&client_ids).ok_or(format!(
+                "At most {} IPC clients are supported",
+                NUM_HEADER_ENTRIES
+            ))?;
+            header.set_entry(TbfHeaderV2Entry::IpcAllowList(allow_list));
+        }
+        Edit::ClearIpcClients => header.remove_entries(TbfHeaderTypes::TbfHeaderIpcAllowList),
+        Edit::SyscallLimit(driver_number, command_number, max_calls, period_ms, quota) => {
+            let mut limits = header
+                .entries
+                .iter()
+                .find_map(|entry| match entry {
+                    TbfHeaderV2Entry::SyscallLimits(limits) => Some(limits.limits().to_vec()),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            let limit = TbfHeaderDriverLimit::new(
+                driver_number,
+                command_number,
+                max_calls,
+                period_ms,
+                quota,
+            );
+            match limits.iter_mut().find(|l| {
+                l.driver_number() == driver_number && l.command_number() == command_number
+            }) {
+                Some(existing) => *existing = limit,
+                None => limits.push(limit),
+            }
+            let limits = TbfHeaderV2SyscallLimits::new(&limits).ok_or(format!(
+                "At most {} syscall limits are supported",
+                NUM_HEADER_ENTRIES
+            ))?;
+            header.set_entry(TbfHeaderV2Entry::SyscallLimits(limits));
+        }
+        Edit::ClearSyscallLimits => header.remove_entries(TbfHeaderTypes::TbfHeaderSyscallLimits),
+    }
+    Ok(())
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("Unable to read {}: {}", path, e))
+}
+
+fn write_file(path: &str, contents: &[u8]) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("Unable to write {}: {}", path, e))
+}
+
+/// Find the offset and total size of each TBF object in `tbf`. Like the
+/// kernel, this stops at the first object that does not have a v2 header.
+fn tbf_objects(tbf: &[u8]) -> Vec<(usize, usize)> {
+    let mut objects = Vec::new();
+    let mut offset = 0;
+    while let Some(base) = tbf.get(offset..offset + TBF_HEADER_V2_BASE_SIZE) {
+        let version = u16::from_le_bytes([base[0], base[1]]);
+        let total_size = u32::from_le_bytes([base[4], base[5], base[6], base[7]]) as usize;
+        if version != 2 || total_size < TBF_HEADER_V2_BASE_SIZE {
+            break;
+        }
+        objects.push((offset, total_size));
+        offset += total_size;
+    }
+    objects
+}
+
+fn header_size(object: &[u8]) -> usize {
+    u16::from_le_bytes([object[2], object[3]]) as usize
+}
+
+/// The protected region and initial function offsets, relative to the end of
+/// the header, and the offset of the end of the binary from the start of the
+/// object.
+fn binary_layout(header: &TbfHeaderV2Builder) -> Option<(u32, u32, u32)> {
+    header
+        .entries
+        .iter()
+        .find_map(|entry| match entry {
+            TbfHeaderV2Entry::Program(program) => Some((
+                program.protected_trailer_size(),
+                program.init_fn_offset(),
+                program.binary_end_offset(),
+            )),
+            _ => None,
+        })
+        .or_else(|| {
+            header.entries.iter().find_map(|entry| match entry {
+                TbfHeaderV2Entry::Main(main) => Some((
+                    main.protected_size(),
+                    main.init_fn_offset(),
+                    header.total_size,
+                )),
+                _ => None,
+            })
+        })
+}
+
+fn print_entry(entry: &TbfHeaderV2Entry) {
+    match entry {
+        TbfHeaderV2Entry::Main(main) => {
+            println!("  main:");
+            println!("    init_fn_offset:   {:#x}", main.init_fn_offset());
+            println!("    protected_size:   {:#x}", main.protected_size());
+            println!("    minimum_ram_size: {:#x}", main.minimum_ram_size());
+        }
+        TbfHeaderV2Entry::Program(program) => {
+            println!("  program:");
+            println!(
+                "    init_fn_offset:         {:#x}",
+                program.init_fn_offset()
+            );
+            println!(
+                "    protected_trailer_size: {:#x}",
+                program.protected_trailer_size()
+            );
+            println!(
+                "    minimum_ram_size:       {:#x}",
+                program.minimum_ram_size()
+            );
+            println!(
+                "    binary_end_offset:      {:#x}",
+                program.binary_end_offset()
+            );
+            println!("    version:                {}", program.version());
+        }
+        TbfHeaderV2Entry::WriteableFlashRegions(regions) => {
+            println!("  writeable flash regions:");
+            for region in regions {
+                println!(
+                    "    offset: {:#x} size: {:#x}",
+                    region.offset(),
+                    region.size()
+                );
+            }
+        }
+        TbfHeaderV2Entry::PackageName(name) => println!("  package name: {}", name),
+        TbfHeaderV2Entry::FixedAddresses(fixed) => {
+            println!("  fixed addresses:");
+            println!(
+                "    start_process_ram:   {:#010x}",
+                fixed.start_process_ram()
+            );
+            println!(
+                "    start_process_flash: {:#010x}",
+                fixed.start_process_flash()
+            );
+        }
+        TbfHeaderV2Entry::Permissions(permissions) => {
+            println!("  permissions:");
+            for perm in permissions.perms() {
+                println!(
+                    "    driver: {:#x} offset: {} allowed_commands: {:#018x}",
+                    perm.driver_number(),
+                    perm.offset(),
+                    perm.allowed_commands()
+                );
+            }
+        }
+        TbfHeaderV2Entry::PersistentAcl(acl) => {
+            println!("  persistent ACL:");
+            println!("    write_id:   {:#x}", acl.write_id());
+            println!("    read_ids:   {:x?}", acl.read_ids());
+            println!("    access_ids: {:x?}", acl.access_ids());
+        }
+        TbfHeaderV2Entry::KernelVersion(version) => {
+            println!("  kernel version: {}.{}", version.major(), version.minor());
+        }
+        TbfHeaderV2Entry::TimingConstraints(timing) => {
+            println!("  timing constraints:");
+            println!("    period_us:   {}", timing.period_us());
+            println!("    deadline_us: {}", timing.deadline_us());
+        }
+        TbfHeaderV2Entry::IpcAllowList(allow_list) => {
+            println!("  IPC clients: {:x?}", allow_list.client_ids());
+        }
+        TbfHeaderV2Entry::SyscallLimits(limits) => {
+            println!("  syscall limits:");
+            for limit in limits.limits() {
+                println!(
+                    "    driver: {:#x} command: {:#x} max_calls: {} period_ms: {} quota: {}",
+                    limit.driver_number(),
+                    limit.command_number(),
+                    limit.max_calls(),
+                    limit.period_ms(),
+                    limit.quota()
+                );
+            }
+        }
+        TbfHeaderV2Entry::Unknown(tipe, value) => {
+            println!("  unknown entry {}: {:02x?}", tipe, value);
+        }
+    }
+}
+
+/// Print the footers in `footers`, the bytes between the end of the binary
+/// and the end of the object.
+fn print_footers(mut footers: &[u8]) {
+    while footers.len() >= 4 {
+        let tipe = u16::from_le_bytes([footers[0], footers[1]]);
+        let length = u16::from_le_bytes([footers[2], footers[3]]) as usize;
+        if tipe == TbfHeaderTypes::TbfFooterCredentials as u16 && length >= 4 {
+            let format = footers
+                .get(4..8)
+                .map(|f| u32::from_le_bytes([f[0], f[1], f[2], f[3]]))
+                .unwrap_or(u32::MAX);
+            let format: Result<TbfFooterV2CredentialsType, _> = format.try_into();
+            match format {
+                Ok(format) => println!("  credentials: {:?} ({} bytes)", format, length - 4),
+                Err(_) => println!("  credentials: unknown format ({} bytes)", length - 4),
+            }
+        } else {
+            println!("  unknown footer {} ({} bytes)", tipe, length);
+        }
+        let footer_len = 4 + length + (4 - length % 4) % 4;
+        footers = footers.get(footer_len..).unwrap_or(&[]);
+    }
+}
+
+fn inspect(path: &str) -> Result<(), String> {
+    let tbf = read_file(path)?;
+    let objects = tbf_objects(&tbf);
+    if objects.is_empty() {
+        return Err(format!("{} does not start with a TBF header", path));
+    }
+
+    for (offset, total_size) in objects {
+        let object = &tbf[offset..tbf.len().min(offset + total_size)];
+        let header = TbfHeaderV2Builder::parse(object, false)
+            .map_err(|e| format!("Unable to parse header at {:#x}: {:?}", offset, e))?;
+        let header_bytes = &object[..header_size(object)];
+        let checksum = u32::from_le_bytes(header_bytes[12..16].try_into().unwrap());
+        let computed = compute_tbf_header_checksum(header_bytes).unwrap_or(0);
+
+        println!("TBF object at {:#x}:", offset);
+        println!("  header_size: {:#x}", header_bytes.len());
+        println!("  total_size:  {:#x}", header.total_size);
+        println!(
+            "  flags:       {:#x}{}{}",
+            header.flags,
+            if header.flags & TBF_FLAG_ENABLED != 0 {
+                " enabled"
+            } else {
+                " disabled"
+            },
+            if header.flags & TBF_FLAG_STICKY != 0 {
+                " sticky"
+            } else {
+                ""
+            }
+        );
+        if checksum == computed {
+            println!("  checksum:    {:#010x} (valid)", checksum);
+        } else {
+            println!(
+                "  checksum:    {:#010x} (invalid, expected {:#010x})",
+                checksum, computed
+            );
+        }
+        if header.entries.is_empty() {
+            println!("  padding");
+        }
+        for entry in &header.entries {
+            print_entry(entry);
+        }
+        if let Some((_, _, binary_end)) = binary_layout(&header) {
+            if let Some(footers) = object.get(binary_end as usize..) {
+                print_footers(footers);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn create(binary_path: &str, options: Options) -> Result<(), String> {
+    let output = options
+        .output
+        .as_ref()
+        .ok_or("create requires an output file (-o)")?;
+    let binary = read_file(binary_path)?;
+    let protected_size = options.protected_size.unwrap_or(0);
+    let init_fn_offset = options.init_fn_offset.unwrap_or(0);
+
+    let mut header = TbfHeaderV2Builder::new(0, TBF_FLAG_ENABLED);
+    header
+        .entries
+        .push(TbfHeaderV2Entry::Main(TbfHeaderV2Main::new(
+            protected_size + init_fn_offset,
+            protected_size,
+            0,
+        )));
+    for edit in &options.edits {
+        apply_edit(&mut header, edit)?;
+    }
+
+    // The TBF object is padded to a multiple of 4 bytes.
+    let size = header.header_size() + protected_size as usize + binary.len();
+    header.total_size = (size + (4 - size % 4) % 4) as u32;
+
+    let mut tbf = header.serialize();
+    tbf.resize(tbf.len() + protected_size as usize, 0);
+    tbf.extend_from_slice(&binary);
+    tbf.resize(header.total_size as usize, 0);
+    write_file(output, &tbf)
+}
+
+fn modify(path: &str, options: Options) -> Result<(), String> {
+    if options.protected_size.is_some() || options.init_fn_offset.is_some() {
+        return Err("--protected-size and --init-fn-offset are only valid with create".to_string());
+    }
+    let mut tbf = read_file(path)?;
+    let mut header = TbfHeaderV2Builder::parse(&tbf, true)
+        .map_err(|e| format!("Invalid TBF header: {:?}", e))?;
+    let old_header_size = header_size(&tbf);
+    let (protected_size, init_fn_offset, binary_end) =
+        binary_layout(&header).ok_or("Header has no main or program entry")?;
+    let binary_start = old_header_size + protected_size as usize;
+    if binary_start > tbf.len() {
+        return Err(format!("{} is truncated", path));
+    }
+
+    for edit in &options.edits {
+        apply_edit(&mut header, edit)?;
+    }
+
+    // The application binary must not move, since it may be compiled for a
+    // fixed address. The protected region absorbs any change in the size of
+    // the header, and the initial function offset is adjusted to match.
+    let new_header_size = header.header_size();
+    if new_header_size > binary_start {
+        return Err(format!(
+            "The new header ({} bytes) does not fit before the binary at offset {:#x}. \
+             Rebuild the TBF with a larger protected region.",
+            new_header_size, binary_start
+        ));
+    }
+    let new_protected_size = (binary_start - new_header_size) as u32;
+    let new_init_fn_offset = init_fn_offset + old_header_size as u32 - new_header_size as u32;
+    for entry in header.entries.iter_mut() {
+        match entry {
+            TbfHeaderV2Entry::Main(main) => {
+                *main = TbfHeaderV2Main::new(
+                    new_init_fn_offset,
+                    new_protected_size,
+                    main.minimum_ram_size(),
+                );
+            }
+            TbfHeaderV2Entry::Program(program) => {
+                *program = TbfHeaderV2Program::new(
+                    new_init_fn_offset,
+                    new_protected_size,
+                    program.minimum_ram_size(),
+                    program.binary_end_offset(),
+                    program.version(),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    let new_header = header.serialize();
+    tbf[..new_header.len()].copy_from_slice(&new_header);
+    for byte in &mut tbf[new_header.len()..binary_start] {
+        *byte = 0;
+    }
+    if (binary_end as usize) < tbf.len().min(header.total_size as usize) {
+        eprintln!("warning: credentials in the TBF footers no longer match the header");
+    }
+
+    write_file(options.output.as_deref().unwrap_or(path), &tbf)
+}
+
+fn checksum(path: &str, options: Options) -> Result<(), String> {
+    if !options.edits.is_empty() {
+        return Err("checksum only accepts -o".to_string());
+    }
+    let mut tbf = read_file(path)?;
+    for (offset, _) in tbf_objects(&tbf) {
+        let end = offset + header_size(&tbf[offset..]);
+        let header = tbf
+            .get_mut(offset..end)
+            .ok_or(format!("Header at {:#x} is truncated", offset))?;
+        let checksum = compute_tbf_header_checksum(header)
+            .map_err(|e| format!("Invalid header at {:#x}: {:?}", offset, e))?;
+        header[12..16].copy_from_slice(&checksum.to_le_bytes());
+    }
+    write_file(options.output.as_deref().unwrap_or(path), &tbf)
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let (command, path) = match args {
+        [command, path, ..] => (command.as_str(), path.as_str()),
+        _ => return Err(usage_error("Missing command".to_string())),
+    };
+    let options = parse_options(&args[2..]).map_err(usage_error)?;
+    match command {
+        "inspect" => inspect(path),
+        "create" => create(path, options),
+        "modify" => modify(path, options),
+        "checksum" => checksum(path, options),
+        _ => Err(usage_error(format!("Unknown command: {}", command))),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(message) = run(&args) {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    }
+}